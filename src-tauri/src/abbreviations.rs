@@ -0,0 +1,212 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+
+// A shorthand expanded in captured notes, e.g. "mtg" -> "meeting"
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct Abbreviation {
+    pub abbreviation: String,
+    pub expansion: String,
+}
+
+// Trim entries and reject empty, whitespace-containing or duplicate abbreviations
+pub fn validate_abbreviations(abbreviations: Vec<Abbreviation>) -> Result<Vec<Abbreviation>, AppError> {
+    let mut cleaned: Vec<Abbreviation> = Vec::new();
+
+    for mut entry in abbreviations {
+        entry.abbreviation = entry.abbreviation.trim().to_string();
+        if entry.abbreviation.is_empty() {
+            return Err(AppError::ValidationError("Abbreviations can't be empty".into()));
+        }
+        if entry.abbreviation.chars().any(char::is_whitespace) {
+            return Err(AppError::ValidationError(format!(
+                "Abbreviation '{}' can't contain spaces",
+                entry.abbreviation
+            )));
+        }
+        if cleaned.iter().any(|existing| existing.abbreviation.to_lowercase() == entry.abbreviation.to_lowercase()) {
+            return Err(AppError::ValidationError(format!("Duplicate abbreviation '{}'", entry.abbreviation)));
+        }
+        cleaned.push(entry);
+    }
+
+    Ok(cleaned)
+}
+
+// Expand whole-word abbreviations, leaving code fences and URLs alone.
+// `\mtg` keeps the literal "mtg". Longer abbreviations win over shorter ones
+// starting at the same place, so "b/c" is matched before "b".
+pub fn expand_abbreviations(text: &str, abbreviations: &[Abbreviation]) -> String {
+    if abbreviations.is_empty() {
+        return text.to_string();
+    }
+
+    // Longest first, then alphabetical, so overlapping keys always resolve the same way
+    let mut keys: Vec<(Vec<char>, &str)> = abbreviations
+        .iter()
+        .filter(|entry| !entry.abbreviation.is_empty())
+        .map(|entry| (entry.abbreviation.to_lowercase().chars().collect(), entry.expansion.as_str()))
+        .collect();
+    keys.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+
+    let mut expanded = String::with_capacity(text.len());
+    let mut in_fence = false;
+    for line in text.split_inclusive('\n') {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            expanded.push_str(line);
+        } else if in_fence {
+            expanded.push_str(line);
+        } else {
+            expand_line(line, &keys, &mut expanded);
+        }
+    }
+    expanded
+}
+
+fn expand_line(line: &str, keys: &[(Vec<char>, &str)], out: &mut String) {
+    let mut token_start = None;
+    for (index, c) in line.char_indices() {
+        if c.is_whitespace() {
+            if let Some(start) = token_start.take() {
+                expand_token(&line[start..index], keys, out);
+            }
+            out.push(c);
+        } else if token_start.is_none() {
+            token_start = Some(index);
+        }
+    }
+    if let Some(start) = token_start {
+        expand_token(&line[start..], keys, out);
+    }
+}
+
+fn is_url(token: &str) -> bool {
+    token.contains("://") || token.to_lowercase().starts_with("www.")
+}
+
+// Expand the abbreviations inside one whitespace-delimited token
+fn expand_token(token: &str, keys: &[(Vec<char>, &str)], out: &mut String) {
+    if is_url(token) {
+        out.push_str(token);
+        return;
+    }
+
+    let chars: Vec<char> = token.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let at_boundary = i == 0 || !chars[i - 1].is_alphanumeric();
+        if at_boundary {
+            let escaped = chars[i] == '\\';
+            let start = if escaped { i + 1 } else { i };
+            if let Some((len, expansion)) = match_at(&chars, start, keys) {
+                if escaped {
+                    out.extend(&chars[start..start + len]);
+                } else {
+                    out.push_str(&match_case(chars[start], expansion));
+                }
+                i = start + len;
+                continue;
+            }
+        }
+        out.push(chars[i]);
+        i += 1;
+    }
+}
+
+// The first (longest) key matching case-insensitively at `start` and ending on a word boundary
+fn match_at<'a>(chars: &[char], start: usize, keys: &[(Vec<char>, &'a str)]) -> Option<(usize, &'a str)> {
+    keys.iter().find_map(|(key, expansion)| {
+        let end = start + key.len();
+        if end > chars.len() {
+            return None;
+        }
+        let matches = chars[start..end]
+            .iter()
+            .zip(key)
+            .all(|(c, k)| c.to_lowercase().eq(k.to_lowercase()));
+        let at_boundary = end == chars.len() || !chars[end].is_alphanumeric();
+        (matches && at_boundary).then_some((key.len(), *expansion))
+    })
+}
+
+// "Mtg" becomes "Meeting": an uppercase first letter carries over to the expansion
+fn match_case(first: char, expansion: &str) -> String {
+    if !first.is_uppercase() {
+        return expansion.to_string();
+    }
+    let mut chars = expansion.chars();
+    match chars.next() {
+        Some(c) => c.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn abbreviations(pairs: &[(&str, &str)]) -> Vec<Abbreviation> {
+        pairs
+            .iter()
+            .map(|(abbreviation, expansion)| Abbreviation { abbreviation: abbreviation.to_string(), expansion: expansion.to_string() })
+            .collect()
+    }
+
+    fn expand(text: &str) -> String {
+        expand_abbreviations(text, &abbreviations(&[("mtg", "meeting"), ("b", "be"), ("b/c", "because"), ("w/", "with")]))
+    }
+
+    #[test]
+    fn whole_words_are_expanded() {
+        assert_eq!(expand("mtg at 3"), "meeting at 3");
+        assert_eq!(expand("mtg, then lunch. mtg"), "meeting, then lunch. meeting");
+        assert_eq!(expand("(mtg)"), "(meeting)");
+        // Not inside other words
+        assert_eq!(expand("mtgs and amtg"), "mtgs and amtg");
+        assert_eq!(expand("line one\nmtg\r\n  mtg"), "line one\nmeeting\r\n  meeting");
+    }
+
+    #[test]
+    fn case_of_the_first_letter_carries_over() {
+        assert_eq!(expand("Mtg moved"), "Meeting moved");
+        assert_eq!(expand("MTG moved"), "Meeting moved");
+        assert_eq!(expand("mTG moved"), "meeting moved");
+    }
+
+    #[test]
+    fn longer_abbreviations_win() {
+        assert_eq!(expand("late b/c traffic"), "late because traffic");
+        assert_eq!(expand("will b there"), "will be there");
+        assert_eq!(expand("lunch w/ Sam"), "lunch with Sam");
+    }
+
+    #[test]
+    fn escapes_fences_and_urls_are_left_alone() {
+        assert_eq!(expand("\\mtg is the tag"), "mtg is the tag");
+        assert_eq!(expand("C:\\path"), "C:\\path");
+        assert_eq!(expand("see https://example.com/mtg and www.mtg.com"), "see https://example.com/mtg and www.mtg.com");
+        assert_eq!(expand("mtg\n```\nmtg\n```\nmtg"), "meeting\n```\nmtg\n```\nmeeting");
+        // An unclosed fence protects the rest of the note
+        assert_eq!(expand("```\nmtg"), "```\nmtg");
+    }
+
+    #[test]
+    fn nothing_changes_without_abbreviations() {
+        let text = "mtg w/ b/c";
+        assert_eq!(expand_abbreviations(text, &[]), text);
+        assert_eq!(expand_abbreviations(text, &abbreviations(&[("", "nothing")])), text);
+    }
+
+    #[test]
+    fn validation_trims_and_rejects_bad_entries() {
+        let cleaned = validate_abbreviations(abbreviations(&[(" mtg ", "meeting"), ("w/", "with")])).unwrap();
+        assert_eq!(cleaned[0].abbreviation, "mtg");
+
+        let error = |pairs: &[(&str, &str)]| validate_abbreviations(abbreviations(pairs)).unwrap_err().to_string();
+        assert!(error(&[("  ", "x")]).contains("can't be empty"));
+        assert!(error(&[("a b", "x")]).contains("can't contain spaces"));
+        assert!(error(&[("mtg", "meeting"), ("MTG", "meetings")]).contains("Duplicate abbreviation 'MTG'"));
+    }
+}
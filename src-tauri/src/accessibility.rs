@@ -0,0 +1,162 @@
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::config::AppState;
+use crate::events;
+use crate::notion::AppendResult;
+use crate::page_display;
+use crate::platform::{self, AccessibilityPrefs};
+
+// The OS has no portable change notification, so settings are re-read on an interval
+const ACCESSIBILITY_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+// Native title of the note window in screen reader mode, read out when it gains focus
+pub const ACCESSIBLE_NOTE_WINDOW_TITLE: &str = "Quick note. Type your note, press Enter to send or Escape to close.";
+
+lazy_static::lazy_static! {
+    // Last prefs sent to the frontend, to only emit on change
+    static ref LAST_PREFS: Mutex<Option<AccessibilityPrefs>> = Mutex::new(None);
+}
+
+fn screen_reader_mode(app: &AppHandle) -> bool {
+    app.state::<AppState>().config.lock().unwrap().screen_reader_mode
+}
+
+pub fn current_prefs(app: &AppHandle) -> AccessibilityPrefs {
+    platform::resolve_accessibility_prefs(platform::detect_accessibility(), screen_reader_mode(app))
+}
+
+// Re-read the prefs and emit "accessibility-changed" if they differ from last time
+pub fn refresh_prefs(app: &AppHandle) {
+    let prefs = current_prefs(app);
+    if remember_prefs(&LAST_PREFS, prefs) {
+        events::emit_app_event(app, events::AppEvent::AccessibilityChanged(prefs));
+    }
+}
+
+// Store the prefs as the last ones sent; true when they differ from those
+fn remember_prefs(last: &Mutex<Option<AccessibilityPrefs>>, prefs: AccessibilityPrefs) -> bool {
+    last.lock().unwrap().replace(prefs) != Some(prefs)
+}
+
+// Background task: watch the OS settings for changes
+pub fn start_accessibility_watch(app: &AppHandle) {
+    let app_handle = app.clone();
+    let registry = app.state::<AppState>().tasks.clone();
+
+    registry.spawn("accessibility-watch", move |mut token| async move {
+        loop {
+            refresh_prefs(&app_handle);
+
+            tokio::select! {
+                _ = token.cancelled() => break,
+                _ = tokio::time::sleep(ACCESSIBILITY_POLL_INTERVAL) => {}
+            }
+        }
+    });
+}
+
+// In screen reader mode, capture results go out as OS notifications, which
+// screen readers announce; toasts inside the note window are visual only
+pub fn announce_capture_result(app: &AppHandle, result: &Result<AppendResult, String>) {
+    if !screen_reader_mode(app) {
+        return;
+    }
+
+    let sandbox = app.state::<AppState>().config.lock().unwrap().sandbox_mode;
+    let (title, body) = capture_announcement(result, sandbox, |page_id| page_display::display(app, page_id).label());
+    crate::notify(app, &title, &body)
+}
+
+// Title and body of the notification announcing a capture result
+fn capture_announcement(
+    result: &Result<AppendResult, String>,
+    sandbox: bool,
+    page_label: impl FnOnce(&str) -> String,
+) -> (String, String) {
+    let suffix = if sandbox { " (sandbox)" } else { "" };
+    match result {
+        Ok(result) => {
+            let body = if result.queued {
+                "Your note was saved and will be sent to Notion.".to_string()
+            } else {
+                format!("Your note was sent to {}.", page_label(&result.page_id))
+            };
+            (format!("Note sent{}", suffix), body)
+        }
+        Err(e) => (format!("Note not sent{}", suffix), e.clone()),
+    }
+}
+
+#[tauri::command]
+pub fn get_accessibility_prefs(app: AppHandle) -> AccessibilityPrefs {
+    current_prefs(&app)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::{resolve_accessibility_prefs, DetectedAccessibility};
+
+    fn detected(reduced_motion: Option<bool>, high_contrast: Option<bool>) -> DetectedAccessibility {
+        DetectedAccessibility { reduced_motion, high_contrast }
+    }
+
+    #[test]
+    fn os_settings_win_over_the_screen_reader_default() {
+        let prefs = resolve_accessibility_prefs(detected(Some(true), Some(true)), false);
+        assert_eq!(prefs, AccessibilityPrefs { reduced_motion: true, high_contrast: true, screen_reader_mode: false });
+
+        let prefs = resolve_accessibility_prefs(detected(Some(false), Some(false)), true);
+        assert_eq!(prefs, AccessibilityPrefs { reduced_motion: false, high_contrast: false, screen_reader_mode: true });
+    }
+
+    #[test]
+    fn unknown_settings_are_off_except_motion_for_screen_reader_users() {
+        let prefs = resolve_accessibility_prefs(detected(None, None), false);
+        assert_eq!(prefs, AccessibilityPrefs::default());
+
+        let prefs = resolve_accessibility_prefs(detected(None, None), true);
+        assert_eq!(prefs, AccessibilityPrefs { reduced_motion: true, high_contrast: false, screen_reader_mode: true });
+    }
+
+    #[test]
+    fn prefs_are_sent_again_only_when_they_change() {
+        let last = Mutex::new(None);
+        let prefs = AccessibilityPrefs::default();
+        assert!(remember_prefs(&last, prefs));
+        assert!(!remember_prefs(&last, prefs));
+        let reduced = AccessibilityPrefs { reduced_motion: true, ..prefs };
+        assert!(remember_prefs(&last, reduced));
+        assert!(!remember_prefs(&last, reduced));
+        assert!(remember_prefs(&last, prefs));
+    }
+
+    fn delivered(queued: bool) -> AppendResult {
+        AppendResult {
+            capture_id: "capture-1".to_string(),
+            block_ids: Vec::new(),
+            page_id: "page-1".to_string(),
+            appended_at: 0,
+            queued,
+        }
+    }
+
+    #[test]
+    fn capture_results_are_announced_with_the_page_name() {
+        let label = |page_id: &str| format!("Inbox ({})", page_id);
+        assert_eq!(
+            capture_announcement(&Ok(delivered(false)), false, label),
+            ("Note sent".to_string(), "Your note was sent to Inbox (page-1).".to_string())
+        );
+        assert_eq!(
+            capture_announcement(&Ok(delivered(true)), false, |_| unreachable!()),
+            ("Note sent".to_string(), "Your note was saved and will be sent to Notion.".to_string())
+        );
+        assert_eq!(
+            capture_announcement(&Err("Notion can't be reached".to_string()), true, label),
+            ("Note not sent (sandbox)".to_string(), "Notion can't be reached".to_string())
+        );
+    }
+}
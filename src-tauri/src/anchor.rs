@@ -0,0 +1,114 @@
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Manager, State};
+
+use crate::blocks;
+use crate::capture::resolve_capture_target;
+use crate::config::{AppConfig, AppState, TargetKind};
+use crate::day_headings;
+use crate::events::{self, AppEvent};
+use crate::notion::{AppendFailure, AppendResponse, NotionApiClient};
+
+// Optional insertion point on the selected page: notes go right after a
+// chosen top-level block (say an "Inbox" heading near the top) instead of at
+// the end of the page, so the newest note is the one just below it. Day
+// headings, when on, decide the place themselves. When Notion no longer
+// accepts the anchor, the note is appended at the end as before and the
+// frontend is told so it can ask for a new one.
+
+// Previews in the anchor picker are cut to this many characters
+const PREVIEW_CHARS: usize = 80;
+
+// A top-level block of the selected page, as offered in the anchor picker
+#[derive(Serialize, Debug, Clone)]
+pub struct AnchorCandidate {
+    pub id: String,
+    pub block_type: String,
+    // The block's text, or a child page's title; empty for dividers and the like
+    pub preview: String,
+}
+
+fn preview(block: &Value) -> String {
+    let text = match block["type"].as_str() {
+        Some("child_page") => block["child_page"]["title"].as_str().unwrap_or_default().to_string(),
+        Some("child_database") => block["child_database"]["title"].as_str().unwrap_or_default().to_string(),
+        _ => blocks::block_text(block),
+    };
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= PREVIEW_CHARS {
+        return text;
+    }
+    let cut: String = text.chars().take(PREVIEW_CHARS).collect();
+    format!("{}…", cut.trim_end())
+}
+
+// The anchor for appends to `page_id`; only the selected page has one
+pub fn anchor_for(config: &AppConfig, page_id: &str) -> Option<String> {
+    config.anchor_block_id.clone().filter(|_| page_id == config.selected_page_id)
+}
+
+// As `anchor_for`, reading the config from the app state
+pub fn configured(app: &AppHandle, page_id: &str) -> Option<String> {
+    anchor_for(&app.state::<AppState>().config.lock().unwrap(), page_id)
+}
+
+// Notion answers an `after` it can't find among the page's children with a 400 or 404
+fn is_missing_anchor(failure: &AppendFailure) -> bool {
+    matches!(failure, AppendFailure::Failed(error) if error.starts_with("API error: 400") || error.starts_with("API error: 404"))
+}
+
+// Append a note after the anchor, or wherever day_headings::append puts it
+// when there's no anchor or a day heading. A refused anchor falls back to the
+// end of the page.
+pub async fn append(
+    app: &AppHandle,
+    client: &NotionApiClient,
+    page_id: &str,
+    page_title: &str,
+    heading: Option<&str>,
+    children: &[Value],
+    anchor: Option<&str>,
+) -> Result<AppendResponse, AppendFailure> {
+    let anchor = match anchor {
+        Some(anchor) if heading.is_none() => anchor,
+        _ => return day_headings::append(client, page_id, page_title, heading, children).await,
+    };
+    match client.append_children_after(page_id, page_title, children, Some(anchor)).await {
+        Err(failure) if is_missing_anchor(&failure) => {
+            eprintln!("Anchor block {} was refused, appending at the end of the page: {}", anchor, String::from(failure));
+            events::emit_app_event(app, AppEvent::AnchorMissing(anchor.to_string()));
+            client.append_children(page_id, page_title, children).await
+        }
+        result => result,
+    }
+}
+
+// The selected page's top-level blocks from the top (the first 100), to pick an anchor from
+#[tauri::command]
+pub async fn list_anchor_blocks(state: State<'_, AppState>) -> Result<Vec<AnchorCandidate>, String> {
+    let target = {
+        let config = state.config.lock().unwrap();
+        if config.selected_target_kind == TargetKind::Database {
+            return Err("Notes saved as database rows have no page to anchor to".into());
+        }
+        resolve_capture_target(&config, None)?
+    };
+    if target.daily.is_some() {
+        return Err("Daily pages change every day, so they have no anchor".into());
+    }
+    if target.page_id.is_empty() {
+        return Err("Choose a page first".into());
+    }
+    let client = NotionApiClient::new(target.api_token)?;
+    let children = client.first_children(&target.page_id).await?;
+    Ok(children
+        .iter()
+        .filter_map(|block| {
+            Some(AnchorCandidate {
+                id: block["id"].as_str()?.to_string(),
+                block_type: block["type"].as_str().unwrap_or_default().to_string(),
+                preview: preview(block),
+            })
+        })
+        .collect())
+}
@@ -0,0 +1,136 @@
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::config::{AppConfig, AppState};
+use crate::events;
+use crate::queue;
+use crate::settings::update_config;
+
+// Tracks whether Notion has revoked the main API token. Any 401 for that
+// token persists `token_invalid`, so the next hotkey press opens settings
+// instead of a capture that would fail. Only a verified new token clears it.
+
+lazy_static::lazy_static! {
+    // The API client has no app handle of its own, so the app registers one at startup
+    static ref APP_HANDLE: Mutex<Option<AppHandle>> = Mutex::new(None);
+}
+
+pub fn init(app: &AppHandle) {
+    *APP_HANDLE.lock().unwrap() = Some(app.clone());
+}
+
+pub fn is_token_invalid(app: &AppHandle) -> bool {
+    app.state::<AppState>().config.lock().unwrap().token_invalid
+}
+
+// Whether captures with this token should wait for re-authentication
+pub fn blocks_token(app: &AppHandle, api_token: &str) -> bool {
+    token_blocked(&app.state::<AppState>().config.lock().unwrap(), api_token)
+}
+
+pub fn token_blocked(config: &AppConfig, api_token: &str) -> bool {
+    config.token_invalid && config.secrets.main_token() == api_token
+}
+
+// Whether a 401 for this token should flag the app: the main token, not flagged yet
+fn newly_unauthorized(config: &AppConfig, api_token: &str) -> bool {
+    !config.token_invalid && config.secrets.main_token() == api_token
+}
+
+// Called for every 401 from Notion. Only the main token is tracked; profile
+// tokens fail their own captures without flagging the app.
+pub fn report_unauthorized(api_token: &str) {
+    let Some(app) = APP_HANDLE.lock().unwrap().clone() else {
+        return;
+    };
+
+    if !newly_unauthorized(&app.state::<AppState>().config.lock().unwrap(), api_token) {
+        return;
+    }
+
+    eprintln!("Notion rejected the API token; marking it invalid");
+    if let Err(e) = update_config(&app, |config| {
+        config.token_invalid = true;
+        Ok(())
+    }) {
+        eprintln!("Failed to persist token_invalid: {}", e);
+    }
+    crate::refresh_tray(&app);
+    crate::capture::refresh_capture_banner(&app);
+    events::emit_app_event(&app, events::AppEvent::TokenInvalidated);
+}
+
+// A new token verified: clear the flag and send the captures that waited for it
+pub fn token_verified(app: &AppHandle) {
+    if !is_token_invalid(app) {
+        return;
+    }
+
+    if let Err(e) = update_config(app, |config| {
+        config.token_invalid = false;
+        Ok(())
+    }) {
+        eprintln!("Failed to clear token_invalid: {}", e);
+        return;
+    }
+    crate::refresh_tray(app);
+    crate::capture::refresh_capture_banner(app);
+
+    match queue::unblock_auth() {
+        Ok(0) => {}
+        Ok(count) => {
+            println!("Token updated, sending {} notes that were waiting for it", count);
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = queue::flush(&app).await {
+                    eprintln!("Queue flush failed: {}", e);
+                }
+            });
+        }
+        Err(e) => eprintln!("Failed to unblock queued notes: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_with_tokens() -> AppConfig {
+        let mut config = AppConfig::default();
+        config.secrets.set_main_token("secret_main".to_string());
+        config.secrets.set_profile_token("work", "secret_work".to_string());
+        config
+    }
+
+    #[test]
+    fn only_a_401_for_the_main_token_flags_it() {
+        let config = config_with_tokens();
+        assert!(newly_unauthorized(&config, "secret_main"));
+        assert!(!newly_unauthorized(&config, "secret_work"));
+        assert!(!newly_unauthorized(&config, "secret_unknown"));
+    }
+
+    #[test]
+    fn the_flag_blocks_the_main_token_until_a_new_one_verifies() {
+        let mut config = config_with_tokens();
+        assert!(!token_blocked(&config, "secret_main"));
+
+        // A 401 flips the flag; later 401s change nothing
+        config.token_invalid = true;
+        assert!(!newly_unauthorized(&config, "secret_main"));
+        assert!(token_blocked(&config, "secret_main"));
+        // Profile tokens keep working
+        assert!(!token_blocked(&config, "secret_work"));
+
+        // The flag outlives a restart
+        let saved: AppConfig = serde_json::from_value(serde_json::to_value(&config).unwrap()).unwrap();
+        assert!(saved.token_invalid);
+
+        // Until a new token verifies, it waits too; verifying clears the flag
+        config.secrets.set_main_token("secret_new".to_string());
+        assert!(token_blocked(&config, "secret_new"));
+        config.token_invalid = false;
+        assert!(!token_blocked(&config, "secret_new"));
+        assert!(newly_unauthorized(&config, "secret_new"));
+    }
+}
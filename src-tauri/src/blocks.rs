@@ -0,0 +1,1220 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::config::{RtlTimestampLayout, TextDirection};
+use crate::error::AppError;
+use crate::markdown::{hashtag_at, inline_runs, ListBuilder};
+use crate::notion::MAX_CHILDREN_PER_REQUEST;
+
+// Builders for the Notion block objects we append.
+//
+// Note text is user data and must only ever travel as a serde value: never
+// interpolate it into a JSON string with format!, so quotes, braces,
+// backslashes and the like reach Notion exactly as typed.
+
+// Notion rejects rich_text runs longer than this (measured in UTF-16 code units)
+pub const MAX_TEXT_CONTENT_LEN: usize = 2000;
+
+// Notion accepts at most this many rich_text runs in one block
+pub const MAX_RICH_TEXT_RUNS: usize = 100;
+
+// Split text into pieces that each fit in one rich_text run
+pub fn split_text_content(text: &str) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    let mut units = 0;
+
+    for (index, c) in text.char_indices() {
+        if units + c.len_utf16() > MAX_TEXT_CONTENT_LEN {
+            pieces.push(&text[start..index]);
+            start = index;
+            units = 0;
+        }
+        units += c.len_utf16();
+    }
+
+    if start < text.len() || pieces.is_empty() {
+        pieces.push(&text[start..]);
+    }
+    pieces
+}
+
+// Rich text runs for arbitrarily long content, split to respect Notion's per-run limit
+pub fn text_runs(content: &str, bold: bool) -> Vec<Value> {
+    split_text_content(content)
+        .into_iter()
+        .map(|piece| text_run(piece, bold))
+        .collect()
+}
+
+// A single rich_text run
+pub fn text_run(content: &str, bold: bool) -> Value {
+    json!({
+        "type": "text",
+        "text": {
+            "content": content
+        },
+        "annotations": {
+            "bold": bold,
+            "color": "default"
+        }
+    })
+}
+
+// A paragraph block made of the given rich_text runs
+pub fn paragraph(rich_text: Vec<Value>) -> Value {
+    json!({
+        "object": "block",
+        "type": "paragraph",
+        "paragraph": {
+            "rich_text": rich_text
+        }
+    })
+}
+
+// A bulleted list item block made of the given rich_text runs
+pub fn bulleted_list_item(rich_text: Vec<Value>) -> Value {
+    list_item("bulleted_list_item", rich_text)
+}
+
+// A list item block of the given type ("bulleted_list_item" or "numbered_list_item")
+pub fn list_item(block_type: &str, rich_text: Vec<Value>) -> Value {
+    json!({
+        "object": "block",
+        "type": block_type,
+        block_type: {
+            "rich_text": rich_text
+        }
+    })
+}
+
+// A to_do block, e.g. from a "[ ] " line
+pub fn to_do(rich_text: Vec<Value>, checked: bool) -> Value {
+    json!({
+        "object": "block",
+        "type": "to_do",
+        "to_do": {
+            "rich_text": rich_text,
+            "checked": checked
+        }
+    })
+}
+
+// A bookmark block, which Notion shows as a preview of the page at `url`
+pub fn bookmark(url: &str) -> Value {
+    json!({
+        "object": "block",
+        "type": "bookmark",
+        "bookmark": {
+            "url": url
+        }
+    })
+}
+
+// Where the timestamp sits relative to the note text
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NoteLayout {
+    TimestampPrefix,
+    TimestampSuffix,
+    TimestampBlock,
+}
+
+// How the note text is turned into blocks
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum NoteFormat {
+    // Paragraphs, lists, tasks and code fences by line, with inline **bold**, *italic*, `code` and links
+    #[default]
+    Plain,
+    // Parsed by markdown::markdown_blocks
+    Markdown,
+}
+
+// The block a note is appended as
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum NoteBlockType {
+    // The note's blocks as they are
+    #[default]
+    Paragraph,
+    // One callout: the first line beside an icon, the rest nested inside
+    Callout,
+    // One toggle titled with the timestamp, the whole note folded inside
+    Toggle,
+}
+
+// How one capture is built. No block type means the configured default.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoteStyle {
+    pub format: NoteFormat,
+    pub block_type: Option<NoteBlockType>,
+    // Toggles show the start of the note after the timestamp
+    pub toggle_preview: bool,
+    // Emphasis for the note's text; the timestamp keeps its own
+    pub bold: bool,
+    // One of NOTION_COLORS
+    pub color: &'static str,
+    // Color for #tags in the text; None leaves them as they are
+    pub tag_color: Option<&'static str>,
+}
+
+impl Default for NoteStyle {
+    fn default() -> Self {
+        NoteStyle {
+            format: NoteFormat::Plain,
+            block_type: None,
+            toggle_preview: false,
+            bold: false,
+            color: "default",
+            tag_color: None,
+        }
+    }
+}
+
+// Colors Notion accepts in rich_text annotations
+pub const NOTION_COLORS: &[&str] = &[
+    "default", "gray", "brown", "orange", "yellow", "green", "blue", "purple", "pink", "red",
+    "gray_background", "brown_background", "orange_background", "yellow_background", "green_background",
+    "blue_background", "purple_background", "pink_background", "red_background",
+];
+
+// The configured note color as one Notion accepts; anything else is sent as
+// "default" rather than failing the append
+pub fn note_color(name: &str) -> &'static str {
+    NOTION_COLORS.iter().find(|color| **color == name).copied().unwrap_or_else(|| {
+        eprintln!("Warning: \"{}\" isn't a Notion color; notes are sent in the default color", name);
+        "default"
+    })
+}
+
+// Callout icon for notes that don't start with an emoji
+pub const DEFAULT_CALLOUT_ICON: &str = "💡";
+
+// Characters of the note shown in a toggle's title, at most
+const TOGGLE_PREVIEW_CHARS: usize = 50;
+
+// Levels of nested children Notion accepts in a single append
+const MAX_APPEND_DEPTH: usize = 2;
+
+// Block types whose rich_text a timestamp can share. Not to_do: a timestamp in
+// a task's text would read as part of the task.
+const INLINE_TIMESTAMP_TYPES: &[&str] = &[
+    "paragraph", "heading_1", "heading_2", "heading_3", "bulleted_list_item", "numbered_list_item", "quote",
+    "callout", "toggle",
+];
+
+fn accepts_timestamp(block: &Value) -> bool {
+    block["type"].as_str().is_some_and(|block_type| INLINE_TIMESTAMP_TYPES.contains(&block_type))
+}
+
+// Characters from right-to-left scripts (Hebrew, Arabic, Syriac, Thaana, N'Ko and their presentation forms)
+fn is_rtl_char(c: char) -> bool {
+    matches!(c as u32,
+        0x0590..=0x08FF | 0xFB1D..=0xFDFF | 0xFE70..=0xFEFF | 0x10800..=0x10FFF | 0x1E800..=0x1EFFF)
+}
+
+// Direction of the first strongly directional character; punctuation, digits
+// and emoji carry no direction and are skipped. Text without letters is LTR.
+pub fn detect_direction(text: &str) -> TextDirection {
+    text.chars()
+        .find(|c| c.is_alphabetic())
+        .map(|c| if is_rtl_char(c) { TextDirection::Rtl } else { TextDirection::Ltr })
+        .unwrap_or(TextDirection::Ltr)
+}
+
+// Pick the layout for a note given the configured direction
+pub fn choose_layout(direction: TextDirection, rtl_layout: RtlTimestampLayout, note_text: &str) -> NoteLayout {
+    let direction = match direction {
+        TextDirection::Auto => detect_direction(note_text),
+        fixed => fixed,
+    };
+
+    match (direction, rtl_layout) {
+        (TextDirection::Rtl, RtlTimestampLayout::Suffix) => NoteLayout::TimestampSuffix,
+        (TextDirection::Rtl, RtlTimestampLayout::SeparateBlock) => NoteLayout::TimestampBlock,
+        _ => NoteLayout::TimestampPrefix,
+    }
+}
+
+// The children array for a note: the timestamp and the note text, arranged
+// per layout. With `split_paragraphs` each non-blank line of a plain note is a
+// block of its own (list and task lines as list items and to_dos, nested by
+// indentation), and only the first one carries the timestamp. A callout or
+// toggle style then puts all of it in one block.
+pub fn build_children(
+    timestamp: &str,
+    note_text: &str,
+    layout: NoteLayout,
+    style: NoteStyle,
+    split_paragraphs: bool,
+) -> Vec<Value> {
+    match style.block_type.unwrap_or_default() {
+        NoteBlockType::Paragraph => note_blocks(timestamp, note_text, layout, style, split_paragraphs),
+        NoteBlockType::Callout => {
+            let (icon, note_text) = leading_emoji(note_text).unwrap_or((DEFAULT_CALLOUT_ICON, note_text));
+            vec![callout_note(note_blocks(timestamp, note_text, layout, style, split_paragraphs), icon)]
+        }
+        NoteBlockType::Toggle => vec![toggle_note(timestamp, note_text, layout, style, split_paragraphs)],
+    }
+}
+
+// The note text as blocks, without the timestamp; empty when it is one
+// paragraph. A note that is only a URL is a bookmark.
+fn body_blocks(note_text: &str, style: NoteStyle, split_paragraphs: bool) -> Vec<Value> {
+    if let Some(url) = crate::markdown::sole_url(note_text) {
+        return vec![bookmark(url)];
+    }
+    let mut blocks = match style.format {
+        NoteFormat::Markdown => crate::markdown::markdown_blocks(note_text),
+        NoteFormat::Plain if split_paragraphs => plain_line_blocks(note_text),
+        NoteFormat::Plain => Vec::new(),
+    };
+    emphasize_blocks(&mut blocks, style);
+    blocks
+}
+
+// Rich text for a note kept as one paragraph
+fn note_runs(note_text: &str, style: NoteStyle) -> Vec<Value> {
+    let mut runs = tag_hashtags(inline_runs(note_text), style);
+    emphasize(&mut runs, style);
+    runs
+}
+
+// A #tag starts the text or follows a space or an opening bracket, so "C#" and
+// "page#anchor" stay text
+fn starts_tag(previous: Option<char>) -> bool {
+    previous.is_none_or(|c| c.is_whitespace() || matches!(c, '(' | '['))
+}
+
+// Runs with each #tag split out into a run of its own in the style's tag
+// color. Code and links are left as they are.
+fn tag_hashtags(runs: Vec<Value>, style: NoteStyle) -> Vec<Value> {
+    let Some(color) = style.tag_color else {
+        return runs;
+    };
+    let mut tagged = Vec::new();
+    let mut previous = None;
+    for run in runs {
+        let content = run["text"]["content"].as_str().unwrap_or_default().to_string();
+        if run["annotations"]["code"] == true || !run["text"]["link"].is_null() {
+            previous = content.chars().last().or(previous);
+            tagged.push(run);
+            continue;
+        }
+        let mut piece_start = 0;
+        let mut index = 0;
+        while index < content.len() {
+            let rest = &content[index..];
+            let tag = hashtag_at(rest).filter(|_| starts_tag(content[..index].chars().last().or(previous)));
+            let Some(tag) = tag else {
+                index += rest.chars().next().map_or(1, char::len_utf8);
+                continue;
+            };
+            for (piece, tag_color) in [(&content[piece_start..index], None), (tag, Some(color))] {
+                if piece.is_empty() {
+                    continue;
+                }
+                let mut split = run.clone();
+                split["text"]["content"] = json!(piece);
+                if let Some(tag_color) = tag_color {
+                    split["annotations"]["color"] = json!(tag_color);
+                }
+                tagged.push(split);
+            }
+            index += tag.len();
+            piece_start = index;
+        }
+        if piece_start == 0 {
+            tagged.push(run);
+        } else if piece_start < content.len() {
+            let mut split = run;
+            split["text"]["content"] = json!(&content[piece_start..]);
+            tagged.push(split);
+        }
+        previous = content.chars().last().or(previous);
+    }
+    tagged
+}
+
+// Whether a run is a note's timestamp, with the space that joins it to the text
+pub fn is_timestamp_run(run: &Value, timestamp: &str) -> bool {
+    run["text"]["content"].as_str().is_some_and(|content| content.trim() == timestamp)
+}
+
+// A note block's text replaced by `new_text`, keeping its timestamp run at the
+// start or end where it was
+pub fn edited_runs(old_runs: &[Value], timestamp: &str, new_text: &str, style: NoteStyle) -> Vec<Value> {
+    let mut runs = Vec::new();
+    if let Some(first) = old_runs.first().filter(|run| is_timestamp_run(run, timestamp)) {
+        runs.push(first.clone());
+    }
+    runs.extend(note_runs(new_text, style));
+    if let Some(last) = old_runs.last().filter(|run| old_runs.len() > 1 && is_timestamp_run(run, timestamp)) {
+        runs.push(last.clone());
+    }
+    runs
+}
+
+// The configured bold and color, over what the text's own markup asked for.
+// Runs that already have a color keep it.
+fn emphasize(runs: &mut [Value], style: NoteStyle) {
+    for run in runs {
+        let annotations = &mut run["annotations"];
+        if style.bold {
+            annotations["bold"] = json!(true);
+        }
+        if style.color != "default" && annotations["color"] == "default" {
+            annotations["color"] = json!(style.color);
+        }
+    }
+}
+
+// As `tag_hashtags` and `emphasize`, through every block and its children;
+// code is left as it is
+fn emphasize_blocks(blocks: &mut [Value], style: NoteStyle) {
+    for block in blocks {
+        let block_type = block["type"].as_str().unwrap_or_default().to_string();
+        if block_type == "code" {
+            continue;
+        }
+        if let Some(runs) = block[block_type.as_str()]["rich_text"].as_array_mut() {
+            *runs = tag_hashtags(std::mem::take(runs), style);
+            emphasize(runs, style);
+        }
+        if let Some(children) = block[block_type.as_str()]["children"].as_array_mut() {
+            emphasize_blocks(children, style);
+        }
+    }
+}
+
+fn note_blocks(
+    timestamp: &str,
+    note_text: &str,
+    layout: NoteLayout,
+    style: NoteStyle,
+    split_paragraphs: bool,
+) -> Vec<Value> {
+    let blocks = body_blocks(note_text, style, split_paragraphs);
+    if !blocks.is_empty() {
+        return place_timestamp(timestamp, blocks, layout);
+    }
+
+    match layout {
+        NoteLayout::TimestampPrefix => {
+            let mut runs = vec![text_run(&format!("{} ", timestamp), true)];
+            runs.extend(note_runs(note_text, style));
+            vec![paragraph(runs)]
+        }
+        NoteLayout::TimestampSuffix => {
+            let mut runs = note_runs(note_text, style);
+            runs.push(text_run(&format!(" {}", timestamp), true));
+            vec![paragraph(runs)]
+        }
+        NoteLayout::TimestampBlock => {
+            let mut dimmed = text_run(timestamp, false);
+            dimmed["annotations"]["color"] = json!("gray");
+            vec![paragraph(vec![dimmed]), paragraph(note_runs(note_text, style))]
+        }
+    }
+}
+
+// One block per non-blank line: to_dos for task lines, list items for
+// list lines, code blocks for ``` fences, paragraphs otherwise.
+// A paragraph ends the list, so a numbered list after it starts again at 1.
+fn plain_line_blocks(note_text: &str) -> Vec<Value> {
+    let lines: Vec<&str> = note_text.lines().collect();
+    let mut blocks = Vec::new();
+    let mut list = ListBuilder::default();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        i += 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some((indent, checked, task)) = crate::markdown::task_item(line) {
+            list.push(indent, to_do(inline_runs(task), checked), &mut blocks);
+            continue;
+        }
+        if let Some((indent, block_type, text)) = crate::markdown::plain_list_item(line) {
+            list.push(indent, list_item(block_type, inline_runs(text)), &mut blocks);
+            continue;
+        }
+        list.finish(&mut blocks);
+        match crate::markdown::code_fence(&lines, i - 1) {
+            Some((block, next)) => {
+                blocks.push(block);
+                i = next;
+            }
+            None => blocks.push(paragraph(inline_runs(line))),
+        }
+    }
+    list.finish(&mut blocks);
+    blocks
+}
+
+// The timestamp added to already built note blocks: inside the first block's
+// text where it has some, or otherwise (e.g. a note of tasks) as a leading paragraph
+fn place_timestamp(timestamp: &str, mut blocks: Vec<Value>, layout: NoteLayout) -> Vec<Value> {
+    let first = &mut blocks[0];
+    let block_type = first["type"].as_str().unwrap_or_default().to_string();
+    let inline = accepts_timestamp(first);
+    let runs = &mut first[block_type.as_str()]["rich_text"];
+
+    match layout {
+        NoteLayout::TimestampPrefix if inline => {
+            let mut prefixed = vec![text_run(&format!("{} ", timestamp), true)];
+            prefixed.extend(runs.as_array_mut().map(std::mem::take).unwrap_or_default());
+            *runs = Value::Array(prefixed);
+        }
+        NoteLayout::TimestampSuffix if inline => {
+            if let Some(runs) = runs.as_array_mut() {
+                runs.push(text_run(&format!(" {}", timestamp), true));
+            }
+        }
+        NoteLayout::TimestampPrefix | NoteLayout::TimestampSuffix => {
+            blocks.insert(0, paragraph(vec![text_run(timestamp, true)]))
+        }
+        NoteLayout::TimestampBlock => {
+            let mut dimmed = text_run(timestamp, false);
+            dimmed["annotations"]["color"] = json!("gray");
+            blocks.insert(0, paragraph(vec![dimmed]));
+        }
+    }
+    blocks
+}
+
+// Emoji code points: pictographs, symbols and dingbats
+fn is_emoji(c: char) -> bool {
+    matches!(c as u32, 0x1F000..=0x1FAFF | 0x2300..=0x23FF | 0x2600..=0x27BF | 0x2B00..=0x2BFF)
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    matches!(c as u32, 0x1F1E6..=0x1F1FF)
+}
+
+// An emoji the text starts with, whole (skin tones, variation selectors, flags
+// and joined sequences like 👩‍💻 included), and the text after it
+pub fn leading_emoji(text: &str) -> Option<(&str, &str)> {
+    let text = text.trim_start();
+    let first = text.chars().next().filter(|c| is_emoji(*c))?;
+    let mut end = first.len_utf8();
+    let mut joined = false;
+    for (index, c) in text[end..].char_indices() {
+        let flag = index == 0 && is_regional_indicator(first) && is_regional_indicator(c);
+        let modifier = matches!(c as u32, 0xFE0F | 0x200D | 0x20E3 | 0x1F3FB..=0x1F3FF | 0xE0020..=0xE007F);
+        if !(flag || modifier || (joined && is_emoji(c))) {
+            break;
+        }
+        joined = c == '\u{200D}';
+        end = first.len_utf8() + index + c.len_utf8();
+    }
+    Some((&text[..end], text[end..].trim_start()))
+}
+
+// The start of the note's first line for a toggle title, cut at a word
+fn toggle_preview(note_text: &str) -> Option<String> {
+    let line = note_text.lines().map(str::trim).find(|line| !line.is_empty())?;
+    if line.chars().count() <= TOGGLE_PREVIEW_CHARS {
+        return Some(line.to_string());
+    }
+    let cut: String = line.chars().take(TOGGLE_PREVIEW_CHARS).collect();
+    let cut = match cut.rsplit_once(char::is_whitespace) {
+        Some((head, _)) if !head.trim().is_empty() => head,
+        _ => cut.as_str(),
+    };
+    Some(format!("{}…", cut.trim_end()))
+}
+
+// The note folded under a toggle titled with its timestamp (and, optionally,
+// its first words); every block of the note is nested inside
+fn toggle_note(timestamp: &str, note_text: &str, layout: NoteLayout, style: NoteStyle, split_paragraphs: bool) -> Value {
+    let mut title = vec![text_run(timestamp, true)];
+    if let Some(preview) = toggle_preview(note_text).filter(|_| style.toggle_preview) {
+        let preview = text_runs(&preview, false);
+        title = match layout {
+            NoteLayout::TimestampSuffix => {
+                let mut runs = preview;
+                runs.push(text_run(&format!(" {}", timestamp), true));
+                runs
+            }
+            NoteLayout::TimestampPrefix | NoteLayout::TimestampBlock => {
+                title.push(text_run(" ", false));
+                title.extend(preview);
+                title
+            }
+        };
+    }
+    let mut body = body_blocks(note_text, style, split_paragraphs);
+    if body.is_empty() && !note_text.trim().is_empty() {
+        body.push(paragraph(note_runs(note_text, style)));
+    }
+    wrap("toggle", title, body)
+}
+
+// The note's blocks as one callout: the first block's text (when it has any
+// that a timestamp could share) becomes the callout's, and everything else is nested inside
+fn callout_note(mut blocks: Vec<Value>, icon: &str) -> Value {
+    let (rich_text, inner) = match blocks.first() {
+        Some(first) if accepts_timestamp(first) => {
+            let mut first = blocks.remove(0);
+            let first_type = first["type"].as_str().unwrap_or_default().to_string();
+            let rich_text = first[first_type.as_str()]["rich_text"].as_array_mut().map(std::mem::take).unwrap_or_default();
+            let mut inner = take_children(&mut first);
+            inner.append(&mut blocks);
+            (rich_text, inner)
+        }
+        _ => (Vec::new(), blocks),
+    };
+    let mut callout = wrap("callout", rich_text, inner);
+    callout["callout"]["icon"] = json!({ "type": "emoji", "emoji": icon });
+    callout
+}
+
+// A block of the given type holding `children`, flattened where they would
+// nest deeper than Notion allows
+fn wrap(block_type: &str, rich_text: Vec<Value>, mut children: Vec<Value>) -> Value {
+    flatten_below(&mut children, MAX_APPEND_DEPTH);
+    let mut block = json!({
+        "object": "block",
+        "type": block_type,
+        block_type: {
+            "rich_text": rich_text
+        }
+    });
+    if !children.is_empty() {
+        block[block_type]["children"] = Value::Array(children);
+    }
+    block
+}
+
+fn take_children(block: &mut Value) -> Vec<Value> {
+    let block_type = block["type"].as_str().unwrap_or_default().to_string();
+    match block[block_type.as_str()].as_object_mut().and_then(|content| content.remove("children")) {
+        Some(Value::Array(children)) => children,
+        _ => Vec::new(),
+    }
+}
+
+// Keep `levels` levels of nesting in `blocks`; anything deeper is moved up to
+// follow its parent at the last level allowed
+fn flatten_below(blocks: &mut Vec<Value>, levels: usize) {
+    let mut flattened = Vec::with_capacity(blocks.len());
+    for mut block in blocks.drain(..) {
+        let mut children = take_children(&mut block);
+        if levels > 1 && !children.is_empty() {
+            flatten_below(&mut children, levels - 1);
+            let block_type = block["type"].as_str().unwrap_or_default().to_string();
+            block[block_type.as_str()]["children"] = Value::Array(children);
+            flattened.push(block);
+        } else {
+            flattened.push(block);
+            flatten_below(&mut children, 1);
+            flattened.append(&mut children);
+        }
+    }
+    *blocks = flattened;
+}
+
+// Everything appended for one typed note: its blocks with the formatted
+// timestamp and any focus session label
+pub fn note_children(
+    note_text: &str,
+    timestamp: &str,
+    layout: NoteLayout,
+    style: NoteStyle,
+    split_paragraphs: bool,
+    focus_label: Option<&str>,
+) -> Vec<Value> {
+    let mut children = build_children(timestamp, note_text, layout, style, split_paragraphs);
+    if let Some(label) = focus_label {
+        append_dimmed_suffix(&mut children, &format_focus_suffix(label));
+    }
+    children
+}
+
+// The label added to notes captured during a focus session
+pub fn format_focus_suffix(label: &str) -> String {
+    format!(" [{}]", label.trim())
+}
+
+// Add a dimmed run, e.g. the focus session label, to the end of the last block's text,
+// or as a paragraph of its own after a block that shouldn't take it (code, a task)
+pub fn append_dimmed_suffix(children: &mut Vec<Value>, suffix: &str) {
+    let mut dimmed = text_run(suffix, false);
+    dimmed["annotations"]["color"] = json!("gray");
+    let Some(block) = children.last_mut().filter(|block| accepts_timestamp(block)) else {
+        children.push(paragraph(vec![dimmed]));
+        return;
+    };
+    let block_type = block["type"].as_str().unwrap_or("").to_string();
+    if let Some(runs) = block[block_type.as_str()]["rich_text"].as_array_mut() {
+        runs.push(dimmed);
+    }
+}
+
+// A batch of scratch captures: a bold heading followed by exactly one top-level
+// bullet per capture, each keeping its own timestamp
+pub fn build_scratch_children(heading: &str, items: &[(String, String)]) -> Vec<Value> {
+    let mut children = Vec::with_capacity(items.len() + 1);
+    children.push(paragraph(text_runs(heading, true)));
+    for (timestamp, text) in items {
+        let mut runs = vec![text_run(timestamp, true), text_run(" ", false)];
+        runs.extend(text_runs(text, false));
+        // Text past the run limit goes in paragraphs nested under the bullet,
+        // so each item stays exactly one top-level block
+        let overflow = runs.split_off(runs.len().min(MAX_RICH_TEXT_RUNS));
+        let mut bullet = bulleted_list_item(runs);
+        if !overflow.is_empty() {
+            bullet["bulleted_list_item"]["children"] = overflow
+                .chunks(MAX_RICH_TEXT_RUNS)
+                .map(|runs| paragraph(runs.to_vec()))
+                .collect();
+        }
+        children.push(bullet);
+    }
+    children
+}
+
+// The visible text of a block, whether built by us (text.content) or returned by Notion (plain_text)
+pub fn block_text(block: &Value) -> String {
+    let block_type = block["type"].as_str().unwrap_or("");
+    block[block_type]["rich_text"]
+        .as_array()
+        .map(|runs| {
+            runs.iter()
+                .filter_map(|run| run["plain_text"].as_str().or_else(|| run["text"]["content"].as_str()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// A block read back from Notion, reduced to what an append accepts (type and
+// content; ids, timestamps and authorship are dropped), with `children`
+// already made appendable nested under it.
+pub fn appendable_block(block: &Value, children: Vec<Value>) -> Option<Value> {
+    let block_type = block["type"].as_str()?;
+    let mut content = block.get(block_type)?.clone();
+    if !children.is_empty() {
+        content.as_object_mut()?.insert("children".into(), Value::Array(children));
+    }
+    Some(json!({
+        "object": "block",
+        "type": block_type,
+        block_type: content,
+    }))
+}
+
+// Block types accepted from raw JSON; anything else is rejected before Notion sees it
+const RAW_BLOCK_TYPES: &[&str] = &[
+    "audio", "bookmark", "breadcrumb", "bulleted_list_item", "callout", "code", "column", "column_list",
+    "divider", "embed", "equation", "file", "heading_1", "heading_2", "heading_3", "image",
+    "link_to_page", "numbered_list_item", "paragraph", "pdf", "quote", "synced_block", "table",
+    "table_of_contents", "table_row", "to_do", "toggle", "video",
+];
+
+// Notion's request body limit; raw blocks are sent as a single request
+pub const MAX_RAW_BLOCKS_BYTES: usize = 500 * 1024;
+
+// Parse user-supplied block JSON: an array of at most MAX_CHILDREN_PER_REQUEST
+// block objects, each with `object: "block"` and a known `type` whose payload is present
+pub fn validate_raw_blocks(blocks_json: &str) -> Result<Vec<Value>, AppError> {
+    if blocks_json.len() > MAX_RAW_BLOCKS_BYTES {
+        return Err(AppError::ValidationError(format!(
+            "Raw blocks are {} bytes; the limit is {}",
+            blocks_json.len(),
+            MAX_RAW_BLOCKS_BYTES
+        )));
+    }
+
+    let value: Value = serde_json::from_str(blocks_json)
+        .map_err(|e| AppError::ValidationError(format!("Raw blocks aren't valid JSON: {}", e)))?;
+    let Value::Array(blocks) = value else {
+        return Err(AppError::ValidationError("Raw blocks must be a JSON array".into()));
+    };
+    if blocks.is_empty() {
+        return Err(AppError::ValidationError("Raw blocks can't be empty".into()));
+    }
+    if blocks.len() > MAX_CHILDREN_PER_REQUEST {
+        return Err(AppError::ValidationError(format!(
+            "{} raw blocks given; at most {} can be sent at once",
+            blocks.len(),
+            MAX_CHILDREN_PER_REQUEST
+        )));
+    }
+
+    for (index, block) in blocks.iter().enumerate() {
+        let invalid = |reason: String| AppError::ValidationError(format!("Block {}: {}", index, reason));
+        if !block.is_object() {
+            return Err(invalid("not a JSON object".into()));
+        }
+        if block["object"] != "block" {
+            return Err(invalid("`object` must be \"block\"".into()));
+        }
+        let block_type = block["type"]
+            .as_str()
+            .ok_or_else(|| invalid("missing `type`".into()))?;
+        if !RAW_BLOCK_TYPES.contains(&block_type) {
+            return Err(invalid(format!("unsupported type \"{}\"", block_type)));
+        }
+        if !block[block_type].is_object() {
+            return Err(invalid(format!("missing the `{}` object for its type", block_type)));
+        }
+    }
+
+    Ok(blocks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMESTAMP: &str = "[2024-03-01 14:02]";
+
+    // The text of every run of a block, joined
+    fn block_content(block: &Value) -> String {
+        let block_type = block["type"].as_str().unwrap();
+        block[block_type]["rich_text"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|run| run["text"]["content"].as_str().unwrap())
+            .collect()
+    }
+
+    // What Notion would receive for the note, read back from the serialized request body
+    fn sent_content(note_text: &str) -> String {
+        let children = build_children(TIMESTAMP, note_text, NoteLayout::TimestampPrefix, NoteStyle::default(), false);
+        let body = serde_json::to_string(&json!({ "children": children })).unwrap();
+        let received: Value = serde_json::from_str(&body).unwrap();
+        let blocks = received["children"].as_array().unwrap();
+        assert_eq!(blocks.len(), 1, "{}", note_text);
+        block_content(&blocks[0])
+    }
+
+    fn assert_round_trips(note_text: &str) {
+        let content = sent_content(note_text);
+        let prefix = format!("{} ", TIMESTAMP);
+        assert!(content.starts_with(&prefix), "{:?}", content);
+        assert_eq!(&content.as_bytes()[prefix.len()..], note_text.as_bytes(), "{:?}", note_text);
+    }
+
+    #[test]
+    fn reserved_characters_reach_notion_as_typed() {
+        for note_text in [
+            r#"{"type":"block"}"#,
+            r#"{"object": "block", "paragraph": {"rich_text": []}}"#,
+            r"C:\Users\me\notes\\server\share \n \t \u0041 \",
+            "{date} {{not a placeholder}} {unknown} }{",
+            "\"quoted\" and 'single' and `unclosed",
+            "emoji 😀 👩‍💻 🇳🇱 and skin tones 👍🏽",
+            "שלום עולם {x} \"ציטוט\"",
+            "مرحبا بالعالم \\ {}",
+            "mixed: abc שלום 123 مرحبا",
+            "line one\nline two\r\nline three\n\n",
+            "tabs\tand\u{200b}zero width\u{feff}",
+        ] {
+            assert_round_trips(note_text);
+        }
+    }
+
+    // A small xorshift generator, so the fuzz cases are the same on every run
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+            items[(self.next() % items.len() as u64) as usize]
+        }
+    }
+
+    #[test]
+    fn arbitrary_printable_text_round_trips() {
+        // Everything but the inline markers (* _ `) and URLs, which plain notes style on purpose
+        let pieces = [
+            "a", "Z", "q", "7", " ", " ", "\"", "'", "{", "}", "\\", "\\\\", "/", ":", ",", ".", "[", "]", "(", ")",
+            "<", ">", "&", "%", "$", "#", "@", "!", "?", "=", "+", "-", "\n", "\t", "😀", "👩‍💻", "🇯🇵", "é", "ß", "ש",
+            "ל", "م", "ر", "\u{200f}", "\u{202e}", "中", "文", "\u{301}",
+        ];
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..500 {
+            let len = 1 + (rng.next() % 80) as usize;
+            let note_text: String = (0..len).map(|_| rng.pick(&pieces)).collect();
+            assert_round_trips(&note_text);
+        }
+    }
+
+    #[test]
+    fn long_notes_split_into_runs_without_losing_characters() {
+        let note_text = "{\"k\":\"v\\\\\"} 😀 שלום ".repeat(300);
+        assert_round_trips(&note_text);
+        let children = build_children(TIMESTAMP, &note_text, NoteLayout::TimestampPrefix, NoteStyle::default(), false);
+        let runs = children[0]["paragraph"]["rich_text"].as_array().unwrap();
+        assert!(runs.len() > 2);
+        assert!(runs.iter().all(|run| run["text"]["content"].as_str().unwrap().encode_utf16().count() <= MAX_TEXT_CONTENT_LEN));
+    }
+
+    fn raw_error(blocks_json: &str) -> String {
+        match validate_raw_blocks(blocks_json) {
+            Err(AppError::ValidationError(message)) => message,
+            other => panic!("{} was accepted: {:?}", blocks_json, other),
+        }
+    }
+
+    #[test]
+    fn raw_blocks_of_known_types_are_accepted_as_given() {
+        let blocks = json!([
+            { "object": "block", "type": "paragraph", "paragraph": { "rich_text": [] } },
+            { "object": "block", "type": "divider", "divider": {} },
+            { "object": "block", "type": "to_do", "to_do": { "rich_text": [], "checked": true }, "has_children": false },
+        ]);
+        let parsed = validate_raw_blocks(&blocks.to_string()).unwrap();
+        assert_eq!(Value::Array(parsed), blocks);
+
+        let most: Vec<Value> = (0..MAX_CHILDREN_PER_REQUEST).map(|_| json!({ "object": "block", "type": "divider", "divider": {} })).collect();
+        assert_eq!(validate_raw_blocks(&Value::Array(most).to_string()).unwrap().len(), MAX_CHILDREN_PER_REQUEST);
+    }
+
+    #[test]
+    fn malformed_raw_blocks_name_what_is_wrong() {
+        let cases = [
+            ("not json", "valid JSON"),
+            (r#"{"object":"block"}"#, "must be a JSON array"),
+            ("[]", "can't be empty"),
+            ("[1]", "Block 0: not a JSON object"),
+            (r#"[{"type":"divider","divider":{}}]"#, "Block 0: `object` must be \"block\""),
+            (r#"[{"object":"page","type":"divider","divider":{}}]"#, "`object` must be"),
+            (r#"[{"object":"block","divider":{}}]"#, "Block 0: missing `type`"),
+            (r#"[{"object":"block","type":7}]"#, "missing `type`"),
+            (r#"[{"object":"block","type":"child_page","child_page":{}}]"#, "unsupported type \"child_page\""),
+            (r#"[{"object":"block","type":"paragraph"}]"#, "missing the `paragraph` object"),
+            (r#"[{"object":"block","type":"paragraph","paragraph":"text"}]"#, "missing the `paragraph` object"),
+            (
+                r#"[{"object":"block","type":"divider","divider":{}},{"object":"block","type":"quote","heading_1":{}}]"#,
+                "Block 1: missing the `quote` object",
+            ),
+        ];
+        for (blocks_json, expected) in cases {
+            let message = raw_error(blocks_json);
+            assert!(message.contains(expected), "{}: {}", blocks_json, message);
+        }
+    }
+
+    #[test]
+    fn raw_blocks_over_the_limits_are_refused() {
+        let too_many: Vec<Value> = (0..=MAX_CHILDREN_PER_REQUEST).map(|_| json!({ "object": "block", "type": "divider", "divider": {} })).collect();
+        let message = raw_error(&Value::Array(too_many).to_string());
+        assert!(message.starts_with("101 raw blocks given"), "{}", message);
+
+        let text = "x".repeat(MAX_RAW_BLOCKS_BYTES);
+        let too_large = json!([{ "object": "block", "type": "paragraph", "paragraph": { "rich_text": [{ "text": { "content": text } }] } }]);
+        let message = raw_error(&too_large.to_string());
+        assert!(message.contains("bytes; the limit is"), "{}", message);
+    }
+
+    // A plain note with each line as its own block, as sent with the timestamp in front
+    fn plain_note(note_text: &str) -> Vec<Value> {
+        build_children(TIMESTAMP, note_text, NoteLayout::TimestampPrefix, NoteStyle::default(), true)
+    }
+
+    fn block_types(blocks: &[Value]) -> Vec<&str> {
+        blocks.iter().map(|block| block["type"].as_str().unwrap()).collect()
+    }
+
+    #[test]
+    fn each_line_of_a_plain_note_is_its_own_paragraph() {
+        let blocks = plain_note("first\n\n  second  \nthird");
+
+        assert_eq!(block_types(&blocks), vec!["paragraph"; 3]);
+        let contents: Vec<String> = blocks.iter().map(block_content).collect();
+        assert_eq!(contents, vec![format!("{} first", TIMESTAMP), "  second  ".to_string(), "third".to_string()]);
+
+        // Unsplit, the lines stay together in one paragraph
+        let unsplit = build_children(TIMESTAMP, "first\nsecond", NoteLayout::TimestampPrefix, NoteStyle::default(), false);
+        assert_eq!(unsplit.len(), 1);
+        assert_eq!(block_content(&unsplit[0]), format!("{} first\nsecond", TIMESTAMP));
+    }
+
+    #[test]
+    fn only_the_first_line_carries_the_timestamp() {
+        let suffixed = build_children(TIMESTAMP, "first\nsecond", NoteLayout::TimestampSuffix, NoteStyle::default(), true);
+        assert_eq!(block_content(&suffixed[0]), format!("first {}", TIMESTAMP));
+        assert_eq!(block_content(&suffixed[1]), "second");
+
+        let separate = build_children(TIMESTAMP, "first\nsecond", NoteLayout::TimestampBlock, NoteStyle::default(), true);
+        let contents: Vec<String> = separate.iter().map(block_content).collect();
+        assert_eq!(contents, vec![TIMESTAMP, "first", "second"]);
+    }
+
+    #[test]
+    fn bullet_lines_of_plain_notes_nest_by_indentation() {
+        let blocks = plain_note("Shopping\n- milk\n  * oat\n- eggs\nafter");
+
+        assert_eq!(block_types(&blocks), vec!["paragraph", "bulleted_list_item", "bulleted_list_item", "paragraph"]);
+        assert_eq!(block_content(&blocks[1]), "milk");
+        let nested = blocks[1]["bulleted_list_item"]["children"].as_array().unwrap();
+        assert_eq!(block_content(&nested[0]), "oat");
+        assert_eq!(block_content(&blocks[2]), "eggs");
+
+        // A note of only bullets gets the timestamp in the first one
+        assert_eq!(block_content(&plain_note("- milk")[0]), format!("{} milk", TIMESTAMP));
+    }
+
+    #[test]
+    fn a_paragraph_between_numbered_lines_starts_a_new_list() {
+        let blocks = plain_note("1. one\n2. two\n   1. two a\nthen\n1. again");
+
+        assert_eq!(
+            block_types(&blocks),
+            vec!["numbered_list_item", "numbered_list_item", "paragraph", "numbered_list_item"]
+        );
+        assert_eq!(block_content(&blocks[0]), format!("{} one", TIMESTAMP));
+        let nested = blocks[1]["numbered_list_item"]["children"].as_array().unwrap();
+        assert_eq!(block_content(&nested[0]), "two a");
+        assert_eq!(block_content(&blocks[3]), "again");
+    }
+
+    #[test]
+    fn checkbox_lines_become_to_dos_without_the_timestamp() {
+        let blocks = plain_note("[ ] buy milk\n[x] call Sam\n  [ ] before noon");
+
+        // A timestamp in a task would read as part of it, so it gets a paragraph
+        assert_eq!(block_types(&blocks), vec!["paragraph", "to_do", "to_do"]);
+        assert_eq!(block_content(&blocks[0]), TIMESTAMP);
+        assert_eq!(block_content(&blocks[1]), "buy milk");
+        assert_eq!(blocks[1]["to_do"]["checked"], false);
+        assert_eq!(blocks[2]["to_do"]["checked"], true);
+        let nested = blocks[2]["to_do"]["children"].as_array().unwrap();
+        assert_eq!(block_content(&nested[0]), "before noon");
+    }
+
+    #[test]
+    fn fenced_code_in_plain_notes_is_a_code_block() {
+        let blocks = plain_note("Try this:\n```rust\nfn main() {}\n\n- not a list\n```\ndone");
+
+        assert_eq!(block_types(&blocks), vec!["paragraph", "code", "paragraph"]);
+        assert_eq!(blocks[1]["code"]["language"], "rust");
+        assert_eq!(block_content(&blocks[1]), "fn main() {}\n\n- not a list");
+        assert_eq!(block_content(&blocks[2]), "done");
+
+        // Code is left out of the note's emphasis
+        let style = NoteStyle { bold: true, ..NoteStyle::default() };
+        let bold = build_children(TIMESTAMP, "```\ncode\n```", NoteLayout::TimestampPrefix, style, true);
+        assert_eq!(block_types(&bold), vec!["paragraph", "code"]);
+        assert_eq!(bold[1]["code"]["rich_text"][0]["annotations"]["bold"], false);
+    }
+
+    #[test]
+    fn plain_notes_are_styled_by_their_markup_not_all_bold() {
+        let blocks = build_children(TIMESTAMP, "ship **today**", NoteLayout::TimestampPrefix, NoteStyle::default(), false);
+        let bold: Vec<bool> = blocks[0]["paragraph"]["rich_text"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|run| run["annotations"]["bold"] == true)
+            .collect();
+        assert_eq!(bold, vec![true, false, true], "timestamp, text, **today**");
+
+        // Configured emphasis still bolds the whole note
+        let style = NoteStyle { bold: true, ..NoteStyle::default() };
+        let blocks = build_children(TIMESTAMP, "ship *today*", NoteLayout::TimestampPrefix, style, false);
+        let runs = blocks[0]["paragraph"]["rich_text"].as_array().unwrap();
+        assert!(runs.iter().all(|run| run["annotations"]["bold"] == true));
+        assert_eq!(runs[2]["annotations"]["italic"], true);
+    }
+
+    #[test]
+    fn dashed_lines_stay_text_in_plain_notes() {
+        let blocks = plain_note("above\n---\nbelow");
+        assert_eq!(block_types(&blocks), vec!["paragraph"; 3]);
+        assert_eq!(block_content(&blocks[1]), "---");
+    }
+
+    fn children_of(block: &Value) -> &[Value] {
+        let block_type = block["type"].as_str().unwrap();
+        block[block_type]["children"].as_array().map_or(&[], Vec::as_slice)
+    }
+
+    fn styled_note(note_text: &str, block_type: NoteBlockType) -> Vec<Value> {
+        let style = NoteStyle { block_type: Some(block_type), ..NoteStyle::default() };
+        build_children(TIMESTAMP, note_text, NoteLayout::TimestampPrefix, style, true)
+    }
+
+    #[test]
+    fn callout_notes_take_a_leading_emoji_as_their_icon() {
+        let blocks = styled_note("🚀 launch day\nbook the room", NoteBlockType::Callout);
+        assert_eq!(block_types(&blocks), vec!["callout"]);
+        assert_eq!(blocks[0]["callout"]["icon"], json!({ "type": "emoji", "emoji": "🚀" }));
+        assert_eq!(block_content(&blocks[0]), format!("{} launch day", TIMESTAMP));
+        let inner: Vec<String> = children_of(&blocks[0]).iter().map(block_content).collect();
+        assert_eq!(inner, vec!["book the room"]);
+
+        let plain = styled_note("no emoji here", NoteBlockType::Callout);
+        assert_eq!(plain[0]["callout"]["icon"]["emoji"], DEFAULT_CALLOUT_ICON);
+        assert_eq!(block_content(&plain[0]), format!("{} no emoji here", TIMESTAMP));
+        assert!(children_of(&plain[0]).is_empty());
+    }
+
+    #[test]
+    fn callouts_nest_no_deeper_than_notion_allows() {
+        let blocks = styled_note("Plan\n- a\n  - b\n    - c", NoteBlockType::Callout);
+        let a = &children_of(&blocks[0])[0];
+        assert_eq!(block_content(a), "a");
+        let below_a: Vec<String> = children_of(a).iter().map(block_content).collect();
+        assert_eq!(below_a, vec!["b", "c"], "c moves up to follow its parent");
+        assert!(children_of(&children_of(a)[0]).is_empty());
+    }
+
+    #[test]
+    fn leading_emoji_are_taken_whole() {
+        assert_eq!(leading_emoji("👍🏽 agreed"), Some(("👍🏽", "agreed")));
+        assert_eq!(leading_emoji("🇩🇪 Berlin"), Some(("🇩🇪", "Berlin")));
+        assert_eq!(leading_emoji("👩‍💻 pairing"), Some(("👩‍💻", "pairing")));
+        assert_eq!(leading_emoji("  ❤️ thanks"), Some(("❤️", "thanks")));
+        assert_eq!(leading_emoji("🚀"), Some(("🚀", "")));
+        assert_eq!(leading_emoji("launch 🚀"), None);
+        assert_eq!(leading_emoji("1. first"), None);
+    }
+
+    #[test]
+    fn toggle_notes_fold_the_note_under_the_timestamp() {
+        let blocks = styled_note("first\n- second", NoteBlockType::Toggle);
+        assert_eq!(block_types(&blocks), vec!["toggle"]);
+        assert_eq!(block_content(&blocks[0]), TIMESTAMP);
+        assert_eq!(block_types(children_of(&blocks[0])), vec!["paragraph", "bulleted_list_item"]);
+        assert_eq!(block_content(&children_of(&blocks[0])[0]), "first", "the timestamp is only in the title");
+
+        // Unsplit, the note is one paragraph inside
+        let style = NoteStyle { block_type: Some(NoteBlockType::Toggle), ..NoteStyle::default() };
+        let unsplit = build_children(TIMESTAMP, "first\nsecond", NoteLayout::TimestampPrefix, style, false);
+        let inner: Vec<String> = children_of(&unsplit[0]).iter().map(block_content).collect();
+        assert_eq!(inner, vec!["first\nsecond"]);
+    }
+
+    #[test]
+    fn toggle_titles_can_preview_the_first_line() {
+        let style = NoteStyle { block_type: Some(NoteBlockType::Toggle), toggle_preview: true, ..NoteStyle::default() };
+        let prefixed = build_children(TIMESTAMP, "\nfirst line\nmore", NoteLayout::TimestampPrefix, style, true);
+        assert_eq!(block_content(&prefixed[0]), format!("{} first line", TIMESTAMP));
+        let suffixed = build_children(TIMESTAMP, "first line", NoteLayout::TimestampSuffix, style, true);
+        assert_eq!(block_content(&suffixed[0]), format!("first line {}", TIMESTAMP));
+
+        let long = "word ".repeat(20);
+        let preview = toggle_preview(&long).unwrap();
+        assert!(preview.ends_with("word…"), "{}", preview);
+        assert!(preview.chars().count() <= TOGGLE_PREVIEW_CHARS + 1);
+        let unbroken = "x".repeat(80);
+        assert_eq!(toggle_preview(&unbroken).unwrap(), format!("{}…", "x".repeat(TOGGLE_PREVIEW_CHARS)));
+        assert_eq!(toggle_preview(" \n "), None);
+    }
+
+    #[test]
+    fn url_only_notes_are_bookmarks() {
+        let blocks = plain_note("https://example.com/article");
+        assert_eq!(block_types(&blocks), vec!["paragraph", "bookmark"]);
+        assert_eq!(block_content(&blocks[0]), TIMESTAMP);
+        assert_eq!(blocks[1]["bookmark"]["url"], "https://example.com/article");
+
+        let linked = build_children(TIMESTAMP, "read https://example.com", NoteLayout::TimestampPrefix, NoteStyle::default(), false);
+        assert_eq!(block_types(&linked), vec!["paragraph"]);
+        let runs = linked[0]["paragraph"]["rich_text"].as_array().unwrap();
+        assert_eq!(runs.last().unwrap()["text"]["link"]["url"], "https://example.com");
+    }
+
+    #[test]
+    fn direction_comes_from_the_first_letter() {
+        assert_eq!(detect_direction("שלום עולם"), TextDirection::Rtl);
+        assert_eq!(detect_direction("مرحبا بالعالم"), TextDirection::Rtl);
+        assert_eq!(detect_direction("Hello world"), TextDirection::Ltr);
+        // Mixed text goes by whichever script comes first
+        assert_eq!(detect_direction("פגישה with Dana at 10"), TextDirection::Rtl);
+        assert_eq!(detect_direction("Meeting עם דנה"), TextDirection::Ltr);
+        // Punctuation, digits and emoji carry no direction
+        assert_eq!(detect_direction("🎉 3. «שלום»"), TextDirection::Rtl);
+        assert_eq!(detect_direction("— 42!"), TextDirection::Ltr);
+        assert_eq!(detect_direction(""), TextDirection::Ltr);
+    }
+
+    #[test]
+    fn rtl_notes_move_the_timestamp_per_the_configured_layout() {
+        use RtlTimestampLayout::{SeparateBlock, Suffix};
+        assert_eq!(choose_layout(TextDirection::Auto, Suffix, "שלום"), NoteLayout::TimestampSuffix);
+        assert_eq!(choose_layout(TextDirection::Auto, SeparateBlock, "مرحبا"), NoteLayout::TimestampBlock);
+        assert_eq!(choose_layout(TextDirection::Auto, Suffix, "😀 hello"), NoteLayout::TimestampPrefix);
+        assert_eq!(choose_layout(TextDirection::Auto, SeparateBlock, "😀 שלום"), NoteLayout::TimestampBlock);
+
+        // A fixed direction overrides what the text looks like
+        assert_eq!(choose_layout(TextDirection::Ltr, Suffix, "שלום"), NoteLayout::TimestampPrefix);
+        assert_eq!(choose_layout(TextDirection::Rtl, Suffix, "hello"), NoteLayout::TimestampSuffix);
+        assert_eq!(choose_layout(TextDirection::Rtl, SeparateBlock, "hello"), NoteLayout::TimestampBlock);
+
+        let suffixed = build_children(TIMESTAMP, "שלום", NoteLayout::TimestampSuffix, NoteStyle::default(), false);
+        assert_eq!(block_content(&suffixed[0]), format!("שלום {}", TIMESTAMP));
+        let separate = build_children(TIMESTAMP, "שלום", NoteLayout::TimestampBlock, NoteStyle::default(), false);
+        assert_eq!(block_types(&separate), vec!["paragraph", "paragraph"]);
+        assert_eq!(block_content(&separate[0]), TIMESTAMP);
+        assert_eq!(separate[0]["paragraph"]["rich_text"][0]["annotations"]["color"], "gray");
+        assert_eq!(block_content(&separate[1]), "שלום");
+    }
+
+    // Each run's text and color
+    fn colored_runs(note_text: &str, tag_color: Option<&'static str>) -> Vec<(String, String)> {
+        note_runs(note_text, NoteStyle { tag_color, ..NoteStyle::default() })
+            .iter()
+            .map(|run| {
+                let color = run["annotations"]["color"].as_str().unwrap_or("default");
+                (run["text"]["content"].as_str().unwrap().to_string(), color.to_string())
+            })
+            .collect()
+    }
+
+    fn run(content: &str, color: &str) -> (String, String) {
+        (content.to_string(), color.to_string())
+    }
+
+    #[test]
+    fn hashtags_get_the_tag_color() {
+        let tag = Some("yellow_background");
+        assert_eq!(
+            colored_runs("#idea for later", tag),
+            vec![run("#idea", "yellow_background"), run(" for later", "default")]
+        );
+        assert_eq!(
+            colored_runs("ping Sam #followup, then (#done)", tag),
+            vec![
+                run("ping Sam ", "default"),
+                run("#followup", "yellow_background"),
+                run(", then (", "default"),
+                run("#done", "yellow_background"),
+                run(")", "default"),
+            ]
+        );
+        // Numbers aren't tags
+        assert_eq!(colored_runs("issue #1 and #2024", tag), vec![run("issue #1 and #2024", "default")]);
+    }
+
+    #[test]
+    fn hashes_in_urls_and_words_arent_tags() {
+        let tag = Some("yellow_background");
+        assert_eq!(colored_runs("C#sharp and page#anchor", tag), vec![run("C#sharp and page#anchor", "default")]);
+
+        let runs = note_runs("see https://example.com/#intro", NoteStyle { tag_color: tag, ..NoteStyle::default() });
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[1]["text"]["content"], "https://example.com/#intro");
+        assert_eq!(runs[1]["annotations"]["color"], "default");
+    }
+
+    #[test]
+    fn without_a_tag_color_runs_are_left_alone() {
+        // highlight_hashtags = false leaves tag_color unset
+        assert_eq!(colored_runs("#idea for #later", None), vec![run("#idea for #later", "default")]);
+        let runs = inline_runs("#idea **now**");
+        assert_eq!(tag_hashtags(runs.clone(), NoteStyle::default()), runs);
+    }
+}
@@ -0,0 +1,844 @@
+use chrono::{DateTime, Duration as TimeDelta, Local, NaiveDate, NaiveTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+use crate::clock;
+use crate::daily_pages::{self, DailyTarget};
+use crate::events;
+use crate::page_display;
+use crate::config::{AppConfig, AppState, CaptureProfile, TargetKind, TargetMode};
+use crate::timestamps::{self, TimestampOptions};
+use crate::abbreviations::expand_abbreviations;
+use crate::rate_limit::RATE_LIMITER;
+use crate::safe_mode::{self, Subsystem};
+use crate::typography::normalize_typography;
+
+// What the note window should tell the user about the note they're about to write
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CaptureBanner {
+    None,
+    // The note can't be sent until the connection is back
+    Offline { message: String },
+    // Sending will wait for Notion's rate limit to clear
+    RateLimited { retry_in_secs: u64 },
+    // Notion rejected the token: notes are queued until it is updated in Settings
+    SyncPaused,
+}
+
+// Where the capture pipeline sends a note written now
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CaptureRoute {
+    Send,
+    // Sent once the rate limiter lets the request through
+    Delay(Duration),
+    // Kept in the offline queue and sent later
+    Queue,
+}
+
+// Everything the banner and the pipeline's routing are decided from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CaptureConditions {
+    // As the webview last reported it; notes written offline are queued by the note window
+    pub online: bool,
+    pub rate_limited_for: Option<Duration>,
+    // The main token was rejected and captures with it wait for a new one
+    pub sync_paused: bool,
+}
+
+impl CaptureConditions {
+    // Conditions for captures with `api_token`, as of now
+    pub fn current(app: &AppHandle, api_token: &str, online: bool) -> Self {
+        CaptureConditions {
+            online,
+            rate_limited_for: (!api_token.is_empty()).then(|| RATE_LIMITER.limited_for(api_token)).flatten(),
+            sync_paused: crate::auth::blocks_token(app, api_token),
+        }
+    }
+
+    // Precedence: a paused sync needs the user to act, so it outranks being
+    // offline, which outranks a rate limit since nothing can be sent at all
+    pub fn route(&self) -> CaptureRoute {
+        if self.sync_paused || !self.online {
+            return CaptureRoute::Queue;
+        }
+        match self.rate_limited_for {
+            Some(wait) if !wait.is_zero() => CaptureRoute::Delay(wait),
+            _ => CaptureRoute::Send,
+        }
+    }
+
+    pub fn banner(&self) -> CaptureBanner {
+        match self.route() {
+            CaptureRoute::Queue if self.sync_paused => CaptureBanner::SyncPaused,
+            CaptureRoute::Queue => CaptureBanner::Offline {
+                message: "will sync later".into(),
+            },
+            // Round up so a sub-second wait never reads as "0s"
+            CaptureRoute::Delay(wait) => CaptureBanner::RateLimited {
+                retry_in_secs: wait.as_millis().div_ceil(1000) as u64,
+            },
+            CaptureRoute::Send => CaptureBanner::None,
+        }
+    }
+}
+
+// Whether the webview was online when it last asked for the banner
+static WEBVIEW_ONLINE: AtomicBool = AtomicBool::new(true);
+
+lazy_static::lazy_static! {
+    // The banner the note window was last told about
+    static ref SHOWN_BANNER: Mutex<Option<CaptureBanner>> = Mutex::new(None);
+}
+
+fn main_token(app: &AppHandle) -> String {
+    app.state::<AppState>().config.lock().unwrap().secrets.main_token().to_string()
+}
+
+// The webview knows whether it is online, so the frontend passes that in; it
+// asks again whenever its connectivity changes
+#[tauri::command]
+pub fn get_capture_banner(online: bool, app: AppHandle) -> Result<CaptureBanner, String> {
+    WEBVIEW_ONLINE.store(online, Ordering::Relaxed);
+    let banner = CaptureConditions::current(&app, &main_token(&app), online).banner();
+    *SHOWN_BANNER.lock().unwrap() = Some(banner.clone());
+    Ok(banner)
+}
+
+// Tell the note window when its banner no longer matches the state, e.g. a
+// rate limit started or ran out, or the token was rejected or replaced
+pub fn refresh_capture_banner(app: &AppHandle) {
+    let banner = CaptureConditions::current(app, &main_token(app), WEBVIEW_ONLINE.load(Ordering::Relaxed)).banner();
+    {
+        let mut shown = SHOWN_BANNER.lock().unwrap();
+        // Nothing to update before the window asked for its first banner
+        if shown.as_ref().is_none_or(|shown| *shown == banner) {
+            return;
+        }
+        *shown = Some(banner.clone());
+    }
+    if let Some(window) = app.get_window("main") {
+        events::emit_app_event(&window, events::AppEvent::CaptureBannerChanged(banner));
+    }
+}
+
+// Slack after a rate limit's end before the banner is re-checked
+const BANNER_EXPIRY_MARGIN: Duration = Duration::from_millis(100);
+
+// Background task: re-check the banner when a rate limit starts and when it runs out
+pub fn start_capture_banner_watch(app: &AppHandle) {
+    let app_handle = app.clone();
+    let registry = app.state::<AppState>().tasks.clone();
+
+    registry.spawn("capture-banner-watch", move |mut token| async move {
+        let mut rate_limit_changes = RATE_LIMITER.subscribe();
+        loop {
+            let limited_for = RATE_LIMITER.limited_for(&main_token(&app_handle));
+            tokio::select! {
+                _ = token.cancelled() => return,
+                changed = rate_limit_changes.changed() => {
+                    if changed.is_err() {
+                        return;
+                    }
+                }
+                // A little past the end, so the limit has certainly cleared
+                _ = tokio::time::sleep(limited_for.unwrap_or_default() + BANNER_EXPIRY_MARGIN), if limited_for.is_some() => {}
+            }
+            refresh_capture_banner(&app_handle);
+        }
+    });
+}
+
+// A capture as it enters the pipeline. `profile` pins the destination for this
+// capture only; `None` means the main selected page.
+#[derive(Debug, Clone)]
+pub struct CaptureRequest {
+    pub note_text: String,
+    pub profile: Option<String>,
+    // When the note says it happened, from a leading `@time`; `None` means now
+    pub captured_at: Option<DateTime<Local>>,
+}
+
+// A typed timestamp may run slightly ahead of our clock (minutes rounded up)
+const LEADING_TIMESTAMP_FUTURE_TOLERANCE_MINUTES: i64 = 5;
+
+// "HH:MM" or "H:MM", 00:00 through 23:59
+fn parse_clock_time(text: &str) -> Option<NaiveTime> {
+    let (hours, minutes) = text.split_once(':')?;
+    if hours.is_empty() || hours.len() > 2 || minutes.len() != 2 {
+        return None;
+    }
+    if !hours.chars().chain(minutes.chars()).all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    NaiveTime::from_hms_opt(hours.parse().ok()?, minutes.parse().ok()?, 0)
+}
+
+// A timestamp typed at the very start of a note: `@HH:MM`, `@yesterday HH:MM`
+// or `@YYYY-MM-DD HH:MM`, followed by the note itself. Returns the time and
+// the remaining text, or `None` to keep the text literal (no match, an
+// impossible time, a time in the future, or nothing after the timestamp).
+pub fn parse_leading_timestamp(text: &str, now: DateTime<Local>) -> Option<(DateTime<Local>, &str)> {
+    let rest = text.strip_prefix('@')?;
+    let mut words = rest.splitn(2, char::is_whitespace);
+    let first = words.next()?;
+    let after_first = words.next().unwrap_or("");
+
+    let today = now.date_naive();
+    let (date, time, note) = if let Some(time) = parse_clock_time(first) {
+        (today, time, after_first)
+    } else {
+        let date = if first.eq_ignore_ascii_case("yesterday") {
+            today.pred_opt()?
+        } else {
+            NaiveDate::parse_from_str(first, "%Y-%m-%d").ok()?
+        };
+        let mut words = after_first.trim_start().splitn(2, char::is_whitespace);
+        let time = parse_clock_time(words.next()?)?;
+        (date, time, words.next().unwrap_or(""))
+    };
+
+    let note = note.trim_start();
+    if note.is_empty() {
+        return None;
+    }
+
+    // Local times skipped by a DST change don't exist and stay literal
+    let captured_at = Local.from_local_datetime(&date.and_time(time)).earliest()?;
+    if captured_at > now + TimeDelta::minutes(LEADING_TIMESTAMP_FUTURE_TOLERANCE_MINUTES) {
+        return None;
+    }
+    Some((captured_at, note))
+}
+
+// The capture time and note text, honoring a leading timestamp when enabled
+pub fn split_leading_timestamp(enabled: bool, text: &str) -> (Option<DateTime<Local>>, &str) {
+    if !enabled || !safe_mode::enabled(Subsystem::LeadingTimestamps) {
+        return (None, text);
+    }
+    match parse_leading_timestamp(text, clock::now()) {
+        Some((captured_at, note)) => (Some(captured_at), note),
+        None => (None, text),
+    }
+}
+
+// Where a capture will be sent
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptureTarget {
+    pub api_token: String,
+    // Empty for a daily page that hasn't been looked up yet
+    pub page_id: String,
+    pub page_title: String,
+    pub stale: bool,
+    // Set in daily page mode; daily_pages::resolve gives the page id
+    pub daily: Option<DailyTarget>,
+    // A database target gets a new row per note instead of appended blocks
+    pub kind: TargetKind,
+}
+
+// The token a note sent through `profile` used: the profile's own, else the
+// main one. Unlike resolve_capture_target, this still works once the profile is gone.
+pub fn api_token_for(config: &AppConfig, profile: Option<&str>) -> Result<String, String> {
+    let api_token = profile
+        .and_then(|name| config.secrets.profile_token(name))
+        .unwrap_or(config.secrets.main_token());
+    if api_token.is_empty() {
+        return Err("Notion API token not set".into());
+    }
+    Ok(api_token.to_string())
+}
+
+// Resolve the destination for a capture, honoring a pinned profile override
+pub fn resolve_capture_target(config: &AppConfig, profile: Option<&str>) -> Result<CaptureTarget, String> {
+    if let Some(name) = profile {
+        let profile = config.capture_profiles
+            .iter()
+            .find(|p| p.name == name)
+            .ok_or_else(|| format!("Capture profile '{}' no longer exists", name))?;
+
+        return Ok(CaptureTarget {
+            api_token: api_token_for(config, Some(&profile.name))?,
+            page_id: profile.page_id.clone(),
+            page_title: profile.page_title.clone(),
+            stale: false,
+            daily: None,
+            kind: TargetKind::Page,
+        });
+    }
+
+    if config.secrets.main_token().is_empty() {
+        return Err("Notion API token not set".into());
+    }
+
+    if config.target_mode == TargetMode::Daily {
+        if config.daily_parent_page_id.is_empty() {
+            return Err("No parent page chosen for daily pages".into());
+        }
+        let today = timestamps::day_of(&clock::now(), &TimestampOptions::from_config(config));
+        let daily = daily_pages::target_for(config, today);
+        return Ok(CaptureTarget {
+            api_token: config.secrets.main_token().to_string(),
+            page_id: daily_pages::cached_id(&daily).unwrap_or_default(),
+            page_title: daily.title.clone(),
+            stale: false,
+            daily: Some(daily),
+            kind: TargetKind::Page,
+        });
+    }
+
+    if config.selected_page_id.is_empty() {
+        return Err("No Notion page selected".into());
+    }
+
+    Ok(CaptureTarget {
+        api_token: config.secrets.main_token().to_string(),
+        page_id: config.selected_page_id.clone(),
+        page_title: config.selected_page_title.clone(),
+        stale: config.selected_page_stale,
+        daily: None,
+        kind: config.selected_target_kind,
+    })
+}
+
+// Destination shown in the note window for the current capture
+#[derive(Serialize, Debug, Clone)]
+pub struct CaptureTargetInfo {
+    pub profile: Option<String>,
+    pub page_title: String,
+    // Read on every show, so spellcheck changes reach a window that is already open
+    pub spellcheck: crate::spellcheck::SpellcheckHints,
+}
+
+// How the note window names a destination; a daily page not looked up yet goes by its title
+fn display_label(app: &AppHandle, page_id: &str, page_title: &str) -> String {
+    if page_id.is_empty() {
+        return page_title.to_string();
+    }
+    page_display::display(app, page_id).label()
+}
+
+// The note window asks this each time it is shown, so a pinned target only lasts one capture
+#[tauri::command]
+pub fn get_capture_target(state: State<'_, AppState>, app: AppHandle) -> Result<CaptureTargetInfo, String> {
+    let profile = crate::pinned_profile();
+    let (target, spellcheck) = {
+        let config = state.config.lock().unwrap();
+        (resolve_capture_target(&config, profile.as_deref())?, crate::spellcheck::hints(&config))
+    };
+
+    Ok(CaptureTargetInfo {
+        profile,
+        page_title: display_label(&app, &target.page_id, &target.page_title),
+        spellcheck,
+    })
+}
+
+// Title of the page `create_sandbox_page` creates
+pub const SANDBOX_PAGE_TITLE: &str = "Quick Notes Sandbox";
+
+// Which rule picked a capture's destination. Precedence, highest first:
+// sandbox mode, an explicit override, a `/profile` route prefix typed in the
+// note, the profile pinned by a per-profile hotkey, then the default selected page.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DestinationRule {
+    Sandbox,
+    Override,
+    RoutePrefix,
+    Pinned,
+    Default,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct DestinationContext {
+    // Profile chosen explicitly for this capture
+    pub profile: Option<String>,
+    // Profile pinned by the per-profile hotkey that opened the note window
+    pub pinned_profile: Option<String>,
+    // The note window's scratch toggle
+    pub scratch: bool,
+}
+
+// Where a note would go, and why, without sending anything
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ResolvedDestination {
+    pub profile: Option<String>,
+    pub page_id: String,
+    pub page_title: String,
+    pub rule: DestinationRule,
+    // Scratch notes wait in the local buffer and go to the default page when flushed
+    pub scratch: bool,
+    // The block the note would be grouped under, when it isn't appended on its own
+    pub grouping: Option<String>,
+    // A full page would be continued on a new page before appending
+    pub rotates_when_full: bool,
+    // The note with any scratch or route prefix removed
+    pub note_text: String,
+}
+
+// "/name rest" where name is a capture profile, matched case-insensitively;
+// the longest matching name wins so "/work notes" beats "/work"
+pub fn strip_route_prefix<'a>(text: &'a str, profiles: &[CaptureProfile]) -> Option<(String, &'a str)> {
+    let rest = text.trim_start().strip_prefix('/')?;
+    profiles
+        .iter()
+        .filter(|profile| !profile.name.is_empty())
+        .filter(|profile| {
+            rest.get(..profile.name.len())
+                .is_some_and(|candidate| candidate.eq_ignore_ascii_case(&profile.name))
+                && rest[profile.name.len()..].chars().next().is_none_or(char::is_whitespace)
+        })
+        .max_by_key(|profile| profile.name.len())
+        .map(|profile| (profile.name.clone(), rest[profile.name.len()..].trim_start()))
+}
+
+// The routing stages of the capture pipeline, shared by real captures and the dry run
+pub fn resolve_destination_plan(
+    config: &AppConfig,
+    note_text: &str,
+    context: &DestinationContext,
+) -> Result<ResolvedDestination, String> {
+    // Sandbox mode sends everything to the sandbox page, prefixes and all
+    if config.sandbox_mode && safe_mode::enabled(Subsystem::Routing) {
+        let page_id = config.sandbox_page_id.clone()
+            .ok_or("Sandbox mode is on but there is no sandbox page; create one in Settings or turn sandbox mode off")?;
+        let text = crate::scratch::strip_scratch_prefix(note_text, &config.scratch_prefix).unwrap_or(note_text);
+        let text = strip_route_prefix(text, &config.capture_profiles).map_or(text, |(_, text)| text);
+        return Ok(ResolvedDestination {
+            profile: None,
+            page_id,
+            page_title: SANDBOX_PAGE_TITLE.to_string(),
+            rule: DestinationRule::Sandbox,
+            scratch: false,
+            grouping: None,
+            rotates_when_full: false,
+            note_text: text.to_string(),
+        });
+    }
+
+    // Safe mode: the selected page, the note as typed
+    if !safe_mode::enabled(Subsystem::Routing) {
+        let target = resolve_capture_target(config, None)?;
+        return Ok(ResolvedDestination {
+            profile: None,
+            page_id: target.page_id,
+            page_title: target.page_title,
+            rule: DestinationRule::Default,
+            scratch: false,
+            grouping: None,
+            rotates_when_full: false,
+            note_text: note_text.to_string(),
+        });
+    }
+
+    if let Some(text) = crate::scratch::strip_scratch_prefix(note_text, &config.scratch_prefix)
+        .or(context.scratch.then_some(note_text))
+    {
+        return Ok(ResolvedDestination {
+            profile: None,
+            page_id: config.selected_page_id.clone(),
+            page_title: config.selected_page_title.clone(),
+            rule: DestinationRule::Default,
+            scratch: true,
+            grouping: Some(
+                if config.target_mode == TargetMode::Fixed && config.selected_target_kind == TargetKind::Database {
+                    "A row of its own, added when the buffer is flushed".into()
+                } else {
+                    "Scratch notes heading, added when the buffer is flushed".into()
+                },
+            ),
+            rotates_when_full: false,
+            note_text: text.to_string(),
+        });
+    }
+
+    let (profile, rule, text) = if let Some(profile) = &context.profile {
+        (Some(profile.clone()), DestinationRule::Override, note_text)
+    } else if let Some((profile, text)) = strip_route_prefix(note_text, &config.capture_profiles) {
+        (Some(profile), DestinationRule::RoutePrefix, text)
+    } else if let Some(profile) = &context.pinned_profile {
+        (Some(profile.clone()), DestinationRule::Pinned, note_text)
+    } else {
+        (None, DestinationRule::Default, note_text)
+    };
+
+    let target = resolve_capture_target(config, profile.as_deref())?;
+    Ok(ResolvedDestination {
+        rotates_when_full: config.auto_rotate_pages && profile.is_none() && config.target_mode == TargetMode::Fixed,
+        profile,
+        page_id: target.page_id,
+        page_title: target.page_title,
+        rule,
+        scratch: false,
+        grouping: None,
+        note_text: text.to_string(),
+    })
+}
+
+// Dry run of the capture routing, for the note window's "will go to" subtitle.
+// Without an explicit pinned profile in the context, the current hotkey pin applies.
+#[tauri::command]
+pub fn resolve_destination(
+    note_text: String,
+    context: Option<DestinationContext>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<ResolvedDestination, String> {
+    let mut context = context.unwrap_or_default();
+    if context.pinned_profile.is_none() {
+        context.pinned_profile = crate::pinned_profile();
+    }
+    let mut destination = {
+        let config = state.config.lock().unwrap();
+        resolve_destination_plan(&config, &note_text, &context)?
+    };
+    destination.page_title = display_label(&app, &destination.page_id, &destination.page_title);
+    Ok(destination)
+}
+
+// The text transformations applied after routing: abbreviations, then typography cleanup
+pub fn prepare_note_text(config: &AppConfig, text: &str) -> String {
+    if !safe_mode::enabled(Subsystem::TextFormatting) {
+        return text.to_string();
+    }
+    let expanded = expand_abbreviations(text, &config.abbreviations);
+    if config.normalize_typography {
+        normalize_typography(&expanded, &config.typography)
+    } else {
+        expanded
+    }
+}
+
+// Exactly the text a capture would send, after routing prefixes, a leading
+// timestamp, abbreviations and typography cleanup
+#[tauri::command]
+pub fn preview_note(
+    note_text: String,
+    context: Option<DestinationContext>,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let config = state.config.lock().unwrap();
+    let destination = resolve_destination_plan(&config, &note_text, &context.unwrap_or_default())?;
+    let text = if destination.scratch {
+        destination.note_text.as_str()
+    } else {
+        split_leading_timestamp(config.leading_timestamps, &destination.note_text).1
+    };
+    Ok(prepare_note_text(&config, text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_notion::{FakeWorkspace, MockNotion, MockReply};
+    use crate::notion::NotionApiClient;
+    use std::sync::atomic::AtomicUsize;
+
+    fn conditions(online: bool, rate_limited_for: Option<Duration>, sync_paused: bool) -> CaptureConditions {
+        CaptureConditions { online, rate_limited_for, sync_paused }
+    }
+
+    #[test]
+    fn banner_precedence() {
+        let limited = Some(Duration::from_millis(2_100));
+        let offline = CaptureBanner::Offline { message: "will sync later".into() };
+        let cases = [
+            (conditions(true, None, false), CaptureBanner::None),
+            (conditions(true, Some(Duration::ZERO), false), CaptureBanner::None),
+            (conditions(true, limited, false), CaptureBanner::RateLimited { retry_in_secs: 3 }),
+            (conditions(true, Some(Duration::from_millis(1)), false), CaptureBanner::RateLimited { retry_in_secs: 1 }),
+            (conditions(false, None, false), offline.clone()),
+            (conditions(false, limited, false), offline),
+            (conditions(true, None, true), CaptureBanner::SyncPaused),
+            (conditions(true, limited, true), CaptureBanner::SyncPaused),
+            (conditions(false, limited, true), CaptureBanner::SyncPaused),
+        ];
+        for (conditions, expected) in cases {
+            assert_eq!(conditions.banner(), expected, "{:?}", conditions);
+        }
+    }
+
+    #[test]
+    fn every_banner_matches_the_route() {
+        for online in [true, false] {
+            for sync_paused in [true, false] {
+                for rate_limited_for in [None, Some(Duration::ZERO), Some(Duration::from_secs(4))] {
+                    let conditions = conditions(online, rate_limited_for, sync_paused);
+                    match (conditions.banner(), conditions.route()) {
+                        (CaptureBanner::None, CaptureRoute::Send) => {}
+                        (CaptureBanner::RateLimited { retry_in_secs }, CaptureRoute::Delay(wait)) => {
+                            assert_eq!(retry_in_secs, wait.as_secs_f64().ceil() as u64);
+                        }
+                        (CaptureBanner::Offline { .. } | CaptureBanner::SyncPaused, CaptureRoute::Queue) => {}
+                        (banner, route) => panic!("{:?} promises {:?} but the note is routed {:?}", conditions, banner, route),
+                    }
+                }
+            }
+        }
+    }
+
+    fn local(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(year, month, day, hour, minute, 0).earliest().unwrap()
+    }
+
+    #[test]
+    fn leading_timestamps_set_the_capture_time() {
+        let now = local(2026, 3, 10, 14, 30);
+        let cases = [
+            ("@09:15 standup notes", local(2026, 3, 10, 9, 15), "standup notes"),
+            ("@9:05 early start", local(2026, 3, 10, 9, 5), "early start"),
+            ("@yesterday 23:40 late thought", local(2026, 3, 9, 23, 40), "late thought"),
+            ("@Yesterday 08:00 coffee", local(2026, 3, 9, 8, 0), "coffee"),
+            ("@2026-02-28 18:00 invoice sent", local(2026, 2, 28, 18, 0), "invoice sent"),
+            ("@14:30   right now", local(2026, 3, 10, 14, 30), "right now"),
+            // Clocks that run a little behind still accept the current minute
+            ("@14:34 rounded up", local(2026, 3, 10, 14, 34), "rounded up"),
+            ("@10:00 first line\nsecond line", local(2026, 3, 10, 10, 0), "first line\nsecond line"),
+        ];
+        for (text, expected_time, expected_note) in cases {
+            assert_eq!(parse_leading_timestamp(text, now), Some((expected_time, expected_note)), "{}", text);
+        }
+    }
+
+    #[test]
+    fn anything_else_stays_literal() {
+        let now = local(2026, 3, 10, 14, 30);
+        for text in [
+            "09:15 no at sign",
+            "@alice can you check this",
+            "@09:15",
+            "@09:15   ",
+            "@24:00 past midnight",
+            "@09:60 bad minutes",
+            "@9:5 short minutes",
+            "@123:00 long hours",
+            "@-1:00 negative",
+            "@09:15:30 seconds",
+            "@15:00 later today",
+            "@yesterday no time",
+            "@yesterday 25:00 bad time",
+            "@2026-02-30 09:00 no such day",
+            "@2026-03-11 09:00 tomorrow",
+            "@10-03-2026 09:00 day first",
+            " @09:15 leading space",
+        ] {
+            assert_eq!(parse_leading_timestamp(text, now), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn leading_timestamps_are_only_read_when_enabled() {
+        assert_eq!(split_leading_timestamp(false, "@00:00 note"), (None, "@00:00 note"));
+        let (captured_at, note) = split_leading_timestamp(true, "@00:00 note");
+        assert!(captured_at.is_some());
+        assert_eq!(note, "note");
+        assert_eq!(split_leading_timestamp(true, "@nobody note"), (None, "@nobody note"));
+    }
+
+    #[tokio::test]
+    async fn rate_limited_notes_are_delayed_then_sent() {
+        let workspace = FakeWorkspace::shared();
+        workspace.lock().unwrap().add_page("page-1", "Inbox");
+        let limited_once = AtomicUsize::new(0);
+        let notion = workspace.clone();
+        let mock = MockNotion::start(move |request| {
+            if limited_once.fetch_add(1, Ordering::SeqCst) == 0 {
+                return MockReply::error(429, "rate_limited", "Slow down").header("retry-after", "1");
+            }
+            notion.lock().unwrap().answer(request)
+        })
+        .await;
+        let client = NotionApiClient::new(mock.token.clone()).unwrap();
+        // Any request can be the one Notion limits
+        let _ = client.fetch_page("page-1").await;
+
+        let conditions = conditions(true, RATE_LIMITER.limited_for(&mock.token), false);
+        assert_eq!(conditions.banner(), CaptureBanner::RateLimited { retry_in_secs: 1 });
+        let CaptureRoute::Delay(wait) = conditions.route() else {
+            panic!("expected a delay, got {:?}", conditions.route());
+        };
+
+        let started = std::time::Instant::now();
+        let note = crate::blocks::paragraph(crate::blocks::text_runs("[09:00] waited", false));
+        client.append_children("page-1", "Inbox", &[note]).await.unwrap();
+        assert!(started.elapsed() + Duration::from_millis(50) >= wait, "sent after {:?}", started.elapsed());
+        assert_eq!(workspace.lock().unwrap().children("page-1").len(), 1);
+    }
+
+    fn profile(name: &str) -> CaptureProfile {
+        CaptureProfile {
+            name: name.into(),
+            page_id: format!("{}-page", name),
+            page_title: name.into(),
+            ..Default::default()
+        }
+    }
+
+    async fn workspace_server(page_id: &str) -> (std::sync::Arc<Mutex<FakeWorkspace>>, MockNotion) {
+        let workspace = FakeWorkspace::shared();
+        workspace.lock().unwrap().add_page(page_id, page_id);
+        let notion = workspace.clone();
+        let mock = MockNotion::start(move |request| notion.lock().unwrap().answer(request)).await;
+        (workspace, mock)
+    }
+
+    async fn send(config: &AppConfig, text: &str, context: &DestinationContext) -> ResolvedDestination {
+        let destination = resolve_destination_plan(config, text, context).unwrap();
+        let target = resolve_capture_target(config, destination.profile.as_deref()).unwrap();
+        let note = crate::blocks::paragraph(crate::blocks::text_runs(&destination.note_text, false));
+        NotionApiClient::new(target.api_token)
+            .unwrap()
+            .append_children(&target.page_id, &target.page_title, &[note])
+            .await
+            .unwrap();
+        destination
+    }
+
+    #[tokio::test]
+    async fn profile_overrides_reach_their_own_workspace() {
+        let (main, main_mock) = workspace_server("main-page").await;
+        let (work, work_mock) = workspace_server("work-page").await;
+        main.lock().unwrap().add_page("home-page", "home");
+
+        let mut config = AppConfig {
+            selected_page_id: "main-page".into(),
+            selected_page_title: "Inbox".into(),
+            capture_profiles: vec![profile("work"), profile("home")],
+            ..Default::default()
+        };
+        config.secrets.set_main_token(main_mock.token.clone());
+        config.secrets.set_profile_token("work", work_mock.token.clone());
+
+        let pinned = DestinationContext { pinned_profile: Some("work".into()), ..Default::default() };
+        let destination = send(&config, "pinned", &pinned).await;
+        assert_eq!((destination.profile.as_deref(), destination.rule), (Some("work"), DestinationRule::Pinned));
+        assert_eq!(work.lock().unwrap().children("work-page").len(), 1);
+        assert_eq!(main_mock.requests().len(), 0);
+
+        // An explicit override beats the pinned profile; "home" has no token of its own
+        let overridden = DestinationContext { profile: Some("home".into()), pinned_profile: Some("work".into()), scratch: false };
+        let destination = send(&config, "override", &overridden).await;
+        assert_eq!(destination.rule, DestinationRule::Override);
+        assert_eq!(main.lock().unwrap().children("home-page").len(), 1);
+
+        // The next capture without a pin goes back to the selected page
+        let destination = send(&config, "plain", &DestinationContext::default()).await;
+        assert_eq!((destination.profile, destination.rule), (None, DestinationRule::Default));
+        assert_eq!(main.lock().unwrap().children("main-page").len(), 1);
+        assert_eq!(work_mock.count("PATCH", "/v1/blocks/"), 1);
+
+        let missing = DestinationContext { pinned_profile: Some("gone".into()), ..Default::default() };
+        assert!(resolve_destination_plan(&config, "lost", &missing).unwrap_err().contains("no longer exists"));
+
+        // Delivered notes name their profile; its token still works once the profile is gone
+        config.capture_profiles.clear();
+        assert_eq!(api_token_for(&config, Some("work")).unwrap(), work_mock.token);
+        assert_eq!(api_token_for(&config, Some("home")).unwrap(), main_mock.token);
+        assert_eq!(api_token_for(&config, None).unwrap(), main_mock.token);
+    }
+
+    fn context(profile: Option<&str>, pinned_profile: Option<&str>, scratch: bool) -> DestinationContext {
+        DestinationContext {
+            profile: profile.map(str::to_string),
+            pinned_profile: pinned_profile.map(str::to_string),
+            scratch,
+        }
+    }
+
+    fn routing_config() -> AppConfig {
+        let mut config = AppConfig {
+            selected_page_id: "main-page".into(),
+            selected_page_title: "Inbox".into(),
+            capture_profiles: vec![profile("work"), profile("work notes"), profile("home")],
+            auto_rotate_pages: true,
+            sandbox_page_id: Some("sandbox-page".into()),
+            ..Default::default()
+        };
+        config.secrets.set_main_token("secret_main".into());
+        config
+    }
+
+    #[test]
+    fn destination_precedence_matrix() {
+        use DestinationRule::*;
+        let config = routing_config();
+        let none = context(None, None, false);
+        let pinned = context(None, Some("home"), false);
+        let overridden = context(Some("home"), Some("work"), false);
+        let scratch = context(Some("home"), Some("work"), true);
+        // (text, context, profile, page, rule, scratch, note text)
+        let cases = [
+            ("plain", &none, None, "main-page", Default, false, "plain"),
+            ("/work plain", &none, Some("work"), "work-page", RoutePrefix, false, "plain"),
+            // The longest profile name wins, and a prefix needs a word boundary
+            ("/work notes plain", &none, Some("work notes"), "work notes-page", RoutePrefix, false, "plain"),
+            ("/workday plain", &none, None, "main-page", Default, false, "/workday plain"),
+            ("/WORK plain", &none, Some("work"), "work-page", RoutePrefix, false, "plain"),
+            // A route prefix beats the pinned profile, which beats the default
+            ("plain", &pinned, Some("home"), "home-page", Pinned, false, "plain"),
+            ("/work plain", &pinned, Some("work"), "work-page", RoutePrefix, false, "plain"),
+            // An explicit override beats both and leaves the text alone
+            ("plain", &overridden, Some("home"), "home-page", Override, false, "plain"),
+            ("/work plain", &overridden, Some("home"), "home-page", Override, false, "/work plain"),
+            // Scratch, by prefix or toggle, beats every profile and goes to the default page
+            (".. plain", &none, None, "main-page", Default, true, "plain"),
+            (".. /work plain", &pinned, None, "main-page", Default, true, "/work plain"),
+            ("/work plain", &scratch, None, "main-page", Default, true, "/work plain"),
+        ];
+        for (text, context, profile, page_id, rule, is_scratch, note_text) in cases {
+            let destination = resolve_destination_plan(&config, text, context).unwrap();
+            assert_eq!(
+                (destination.profile.as_deref(), destination.page_id.as_str(), destination.rule, destination.scratch, destination.note_text.as_str()),
+                (profile, page_id, rule, is_scratch, note_text),
+                "{:?} with {:?}",
+                text,
+                context,
+            );
+            // Only the default page of a fixed target rotates when full, and only a scratch note is grouped
+            assert_eq!(destination.rotates_when_full, rule == Default && !is_scratch, "{:?}", text);
+            assert_eq!(destination.grouping.is_some(), is_scratch, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn the_sandbox_beats_every_other_rule() {
+        let mut config = routing_config();
+        config.sandbox_mode = true;
+        let everything = context(Some("home"), Some("work"), true);
+        for (text, context) in [("plain", &context(None, None, false)), ("/work plain", &everything), (".. /work plain", &everything)] {
+            let destination = resolve_destination_plan(&config, text, context).unwrap();
+            assert_eq!(destination.rule, DestinationRule::Sandbox, "{:?}", text);
+            assert_eq!((destination.profile, destination.page_id.as_str()), (None, "sandbox-page"));
+            assert_eq!((destination.page_title.as_str(), destination.note_text.as_str()), (SANDBOX_PAGE_TITLE, "plain"));
+            assert!(!destination.scratch && !destination.rotates_when_full && destination.grouping.is_none());
+        }
+
+        config.sandbox_page_id = None;
+        let error = resolve_destination_plan(&config, "plain", &context(None, None, false)).unwrap_err();
+        assert!(error.contains("no sandbox page"), "{}", error);
+    }
+
+    #[test]
+    fn scratch_grouping_and_rotation_follow_the_target() {
+        let mut config = routing_config();
+        config.selected_target_kind = TargetKind::Database;
+        let destination = resolve_destination_plan(&config, ".. row", &context(None, None, false)).unwrap();
+        assert!(destination.grouping.unwrap().contains("A row of its own"));
+
+        config.selected_target_kind = TargetKind::Page;
+        let destination = resolve_destination_plan(&config, ".. note", &context(None, None, false)).unwrap();
+        assert!(destination.grouping.unwrap().contains("Scratch notes heading"));
+
+        // Rotation is off unless asked for
+        config.auto_rotate_pages = false;
+        assert!(!resolve_destination_plan(&config, "plain", &context(None, None, false)).unwrap().rotates_when_full);
+
+        // A profile that no longer exists is an error, whichever rule picked it
+        let error = resolve_destination_plan(&config, "plain", &context(Some("gone"), None, false)).unwrap_err();
+        assert!(error.contains("'gone' no longer exists"), "{}", error);
+    }
+}
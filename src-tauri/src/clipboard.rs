@@ -0,0 +1,180 @@
+use crate::config::AppConfig;
+
+// Thresholds applied to clipboard captures before anything is sent
+#[derive(Debug, Clone)]
+pub struct ClipboardLimits {
+    pub soft_limit_chars: usize,
+    pub hard_limit_chars: usize,
+    pub structured_min_lines: usize,
+    pub prose_ratio_threshold: f32,
+}
+
+impl ClipboardLimits {
+    pub fn from_config(config: &AppConfig) -> Self {
+        ClipboardLimits {
+            soft_limit_chars: config.clipboard_soft_limit_chars,
+            hard_limit_chars: config.clipboard_hard_limit_chars,
+            structured_min_lines: config.clipboard_structured_min_lines,
+            prose_ratio_threshold: config.clipboard_prose_ratio_threshold,
+        }
+    }
+}
+
+// What to do with a clipboard capture
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClipboardDecision {
+    // Send straight away (possibly wrapped in a code fence)
+    Send(String),
+    // Too big to send blindly; open the note window pre-filled so the user can confirm
+    Preview(String),
+    // Too big to send at all
+    Refuse { chars: usize },
+}
+
+// Decide how to handle clipboard text. Pure so the thresholds can be reasoned about in isolation.
+pub fn decide_clipboard_capture(text: &str, limits: &ClipboardLimits) -> ClipboardDecision {
+    let chars = text.chars().count();
+
+    if chars > limits.hard_limit_chars {
+        return ClipboardDecision::Refuse { chars };
+    }
+
+    let text = if looks_structured(text, limits) && !text.contains("```") {
+        format!("```\n{}\n```", text.trim_end())
+    } else {
+        text.to_string()
+    };
+
+    if chars > limits.soft_limit_chars {
+        ClipboardDecision::Preview(text)
+    } else {
+        ClipboardDecision::Send(text)
+    }
+}
+
+// Structured text (JSON, logs, CSV, code) has many lines and few of them read like prose
+pub fn looks_structured(text: &str, limits: &ClipboardLimits) -> bool {
+    let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+    if lines.len() < limits.structured_min_lines {
+        return false;
+    }
+
+    let prose_lines = lines.iter().filter(|l| is_prose_line(l)).count();
+    (prose_lines as f32 / lines.len() as f32) < limits.prose_ratio_threshold
+}
+
+// A prose line has a few words and is made almost entirely of letters, spaces and light punctuation
+fn is_prose_line(line: &str) -> bool {
+    let line = line.trim();
+    if line.split_whitespace().count() < 3 {
+        return false;
+    }
+
+    let total = line.chars().count();
+    let wordy = line
+        .chars()
+        .filter(|c| c.is_alphabetic() || c.is_whitespace() || matches!(c, '.' | ',' | '\'' | '!' | '?' | '-'))
+        .count();
+
+    wordy as f32 / total as f32 >= 0.9
+}
+
+// Human-readable size used in refusal notifications
+pub fn describe_size(chars: usize) -> String {
+    if chars >= 1_000_000 {
+        format!("{:.1}M characters", chars as f64 / 1_000_000.0)
+    } else if chars >= 1_000 {
+        format!("{:.1}k characters", chars as f64 / 1_000.0)
+    } else {
+        format!("{} characters", chars)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits() -> ClipboardLimits {
+        ClipboardLimits::from_config(&AppConfig::default())
+    }
+
+    const PROSE: &str = "Remember to call the landlord about the heating.\n\
+        He said he would be around on Thursday afternoon.\n\
+        Ask whether the boiler service is included in the rent.\n\
+        Also mention the window in the back bedroom, it still sticks.\n\
+        If he can't come, book the plumber we used last winter.";
+
+    const JSON: &str = "{\n  \"id\": 42,\n  \"name\": \"quick notes\",\n  \"tags\": [\"a\", \"b\"],\n  \"nested\": { \"ok\": true },\n  \"count\": 3\n}";
+
+    const LOG: &str = "2024-03-01T10:00:01Z INFO server started on :8080\n\
+        2024-03-01T10:00:02Z DEBUG pool size=16 idle=16\n\
+        2024-03-01T10:00:05Z WARN slow query took 1532ms\n\
+        2024-03-01T10:00:07Z ERROR upstream 502 from /api/v1/items\n\
+        2024-03-01T10:00:09Z INFO retry 1/3 succeeded";
+
+    const CSV: &str = "date,amount,category\n2024-01-01,12.50,food\n2024-01-02,80.00,rent\n2024-01-03,4.20,coffee\n2024-01-04,31.99,books";
+
+    #[test]
+    fn prose_is_sent_as_is() {
+        assert_eq!(decide_clipboard_capture(PROSE, &limits()), ClipboardDecision::Send(PROSE.to_string()));
+    }
+
+    #[test]
+    fn structured_text_is_fenced() {
+        for sample in [JSON, LOG, CSV] {
+            assert!(looks_structured(sample, &limits()), "{}", sample);
+            assert_eq!(
+                decide_clipboard_capture(sample, &limits()),
+                ClipboardDecision::Send(format!("```\n{}\n```", sample))
+            );
+        }
+    }
+
+    #[test]
+    fn short_snippets_are_not_fenced() {
+        let snippet = "{\"id\": 42}\n[1, 2, 3]";
+        assert_eq!(decide_clipboard_capture(snippet, &limits()), ClipboardDecision::Send(snippet.to_string()));
+    }
+
+    #[test]
+    fn already_fenced_text_is_left_alone() {
+        let fenced = format!("```\n{}\n```", CSV);
+        assert_eq!(decide_clipboard_capture(&fenced, &limits()), ClipboardDecision::Send(fenced.clone()));
+    }
+
+    #[test]
+    fn sizes_between_the_limits_are_previewed() {
+        let text = "word ".repeat(3_000);
+        assert_eq!(decide_clipboard_capture(&text, &limits()), ClipboardDecision::Preview(text.clone()));
+
+        let log = LOG.repeat(60);
+        match decide_clipboard_capture(&log, &limits()) {
+            ClipboardDecision::Preview(text) => assert!(text.starts_with("```\n")),
+            other => panic!("expected a preview, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn sizes_over_the_hard_limit_are_refused() {
+        let dump = "INSERT INTO t VALUES (1);\n".repeat(5_000);
+        assert_eq!(
+            decide_clipboard_capture(&dump, &limits()),
+            ClipboardDecision::Refuse { chars: dump.chars().count() }
+        );
+    }
+
+    #[test]
+    fn limits_count_characters_not_bytes() {
+        let limits = ClipboardLimits { soft_limit_chars: 3, hard_limit_chars: 5, ..limits() };
+        assert_eq!(decide_clipboard_capture("ééé", &limits), ClipboardDecision::Send("ééé".to_string()));
+        assert_eq!(decide_clipboard_capture("éééé", &limits), ClipboardDecision::Preview("éééé".to_string()));
+        assert_eq!(decide_clipboard_capture("éééééé", &limits), ClipboardDecision::Refuse { chars: 6 });
+    }
+
+    #[test]
+    fn sizes_are_described_for_people() {
+        assert_eq!(describe_size(999), "999 characters");
+        assert_eq!(describe_size(100_000), "100.0k characters");
+        assert_eq!(describe_size(20_000_000), "20.0M characters");
+    }
+}
@@ -0,0 +1,240 @@
+use chrono::{DateTime, Local, Utc};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Local clocks further than this from Notion's are treated as wrong
+pub const CLOCK_SKEW_THRESHOLD: Duration = Duration::from_secs(5 * 60);
+
+// How often the offset is measured again from a fresh Date header
+const REMEASURE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+// Latest measurement of how far Notion's clock is ahead of ours
+struct ClockState {
+    offset: Option<chrono::Duration>,
+    measured_at: Option<Instant>,
+    // Offset we last warned about, so a steady skew only notifies once
+    warned_offset: Option<chrono::Duration>,
+    pending_warning: bool,
+}
+
+impl ClockState {
+    fn new() -> Self {
+        ClockState {
+            offset: None,
+            measured_at: None,
+            warned_offset: None,
+            pending_warning: false,
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref CLOCK_STATE: Mutex<ClockState> = Mutex::new(ClockState::new());
+}
+
+// Mirrors config.correct_clock_skew
+static CORRECT_CLOCK_SKEW: AtomicBool = AtomicBool::new(true);
+
+pub fn set_correct_clock_skew(enabled: bool) {
+    CORRECT_CLOCK_SKEW.store(enabled, Ordering::SeqCst);
+}
+
+// How far the server is ahead of the local clock (negative when local is ahead)
+pub fn measure_offset(server_time: DateTime<Utc>, local_time: DateTime<Utc>) -> chrono::Duration {
+    server_time - local_time
+}
+
+// The offset if it is large enough to matter, None when the clocks agree closely enough
+pub fn significant_offset(offset: chrono::Duration) -> Option<chrono::Duration> {
+    let threshold = chrono::Duration::from_std(CLOCK_SKEW_THRESHOLD).unwrap_or_default();
+    if offset.abs() > threshold {
+        Some(offset)
+    } else {
+        None
+    }
+}
+
+// Parse an HTTP Date header ("Wed, 21 Oct 2015 07:28:00 GMT")
+pub fn parse_http_date(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc2822(value.trim())
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+// Record the server time from a successful response. Only measures on the first
+// response of the session and then once per REMEASURE_INTERVAL.
+pub fn observe_server_date(date_header: &str) {
+    observe_date(&mut CLOCK_STATE.lock().unwrap(), date_header, Utc::now());
+}
+
+fn observe_date(state: &mut ClockState, date_header: &str, local_time: DateTime<Utc>) {
+    if state.measured_at.is_some_and(|at| at.elapsed() < REMEASURE_INTERVAL) {
+        return;
+    }
+
+    let Some(server_time) = parse_http_date(date_header) else {
+        return;
+    };
+    apply_measurement(state, measure_offset(server_time, local_time));
+}
+
+fn apply_measurement(state: &mut ClockState, offset: chrono::Duration) {
+    state.offset = Some(offset);
+    state.measured_at = Some(Instant::now());
+
+    match significant_offset(offset) {
+        Some(skew) => {
+            // Warn again only if the skew moved by more than the threshold since last time
+            let already_warned = state.warned_offset
+                .is_some_and(|warned| significant_offset(skew - warned).is_none());
+            if !already_warned {
+                state.warned_offset = Some(skew);
+                state.pending_warning = true;
+            }
+        }
+        None => state.warned_offset = None,
+    }
+}
+
+// Measured offset in milliseconds, for diagnostics
+pub fn offset_ms() -> Option<i64> {
+    CLOCK_STATE.lock().unwrap().offset.map(|offset| offset.num_milliseconds())
+}
+
+// The offset to apply to local times, if correction is on and the skew is significant
+fn correction() -> chrono::Duration {
+    if !CORRECT_CLOCK_SKEW.load(Ordering::SeqCst) {
+        return chrono::Duration::zero();
+    }
+    CLOCK_STATE.lock().unwrap()
+        .offset
+        .and_then(significant_offset)
+        .unwrap_or_else(chrono::Duration::zero)
+}
+
+// Local time corrected by the measured skew; use for note timestamps
+pub fn now() -> DateTime<Local> {
+    Local::now() + correction()
+}
+
+// How long to wait until a server-provided instant (e.g. a Retry-After date),
+// comparing against server time rather than the possibly wrong local clock
+pub fn until_server_time(server_time: DateTime<Utc>) -> Duration {
+    (server_time - (Utc::now() + correction()))
+        .to_std()
+        .unwrap_or(Duration::ZERO)
+}
+
+// The skew to warn the user about, once per new measurement
+pub fn take_skew_warning() -> Option<chrono::Duration> {
+    take_warning(&mut CLOCK_STATE.lock().unwrap())
+}
+
+fn take_warning(state: &mut ClockState) -> Option<chrono::Duration> {
+    if !state.pending_warning {
+        return None;
+    }
+    state.pending_warning = false;
+    state.warned_offset
+}
+
+// Human-readable skew, e.g. "3 days behind" or "12 minutes ahead"
+pub fn describe_skew(offset: chrono::Duration) -> String {
+    // A positive offset means the server is ahead, i.e. our clock is behind
+    let direction = if offset > chrono::Duration::zero() { "behind" } else { "ahead" };
+    let offset = offset.abs();
+    if offset.num_days() > 0 {
+        format!("{} days {}", offset.num_days(), direction)
+    } else if offset.num_hours() > 0 {
+        format!("{} hours {}", offset.num_hours(), direction)
+    } else {
+        format!("{} minutes {}", offset.num_minutes(), direction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const NOTION_DATE: &str = "Fri, 01 Mar 2024 14:02:00 GMT";
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, 0).unwrap()
+    }
+
+    // A state whose last measurement is old enough to measure again
+    fn due_for_remeasuring(state: &mut ClockState) {
+        state.measured_at = Instant::now().checked_sub(REMEASURE_INTERVAL);
+    }
+
+    #[test]
+    fn the_offset_is_measured_from_the_date_header_in_both_directions() {
+        // A machine that booted with a 2019 clock is behind: the offset is positive
+        let mut state = ClockState::new();
+        observe_date(&mut state, NOTION_DATE, utc(2019, 1, 1, 0, 0));
+        let behind = state.offset.unwrap();
+        assert_eq!(behind, utc(2024, 3, 1, 14, 2) - utc(2019, 1, 1, 0, 0));
+        assert!(describe_skew(behind).ends_with("days behind"), "{}", describe_skew(behind));
+
+        let mut state = ClockState::new();
+        observe_date(&mut state, NOTION_DATE, utc(2024, 3, 1, 16, 32));
+        let ahead = state.offset.unwrap();
+        assert_eq!(ahead, chrono::Duration::minutes(-150));
+        assert_eq!(describe_skew(ahead), "2 hours ahead");
+
+        // Unparseable dates measure nothing
+        let mut state = ClockState::new();
+        observe_date(&mut state, "yesterday", utc(2024, 3, 1, 14, 2));
+        assert_eq!(state.offset, None);
+    }
+
+    #[test]
+    fn only_skew_past_the_threshold_warns() {
+        let threshold = chrono::Duration::from_std(CLOCK_SKEW_THRESHOLD).unwrap();
+        assert_eq!(significant_offset(threshold), None);
+        assert_eq!(significant_offset(-threshold), None);
+        let past = threshold + chrono::Duration::seconds(1);
+        assert_eq!(significant_offset(past), Some(past));
+        assert_eq!(significant_offset(-past), Some(-past));
+
+        let mut state = ClockState::new();
+        observe_date(&mut state, NOTION_DATE, utc(2024, 3, 1, 13, 59));
+        assert_eq!(state.offset, Some(chrono::Duration::minutes(3)));
+        assert_eq!(take_warning(&mut state), None);
+
+        let mut state = ClockState::new();
+        observe_date(&mut state, NOTION_DATE, utc(2024, 3, 1, 13, 52));
+        assert_eq!(take_warning(&mut state), Some(chrono::Duration::minutes(10)));
+        assert_eq!(take_warning(&mut state), None);
+    }
+
+    #[test]
+    fn a_steady_skew_warns_once_and_a_new_one_warns_again() {
+        let mut state = ClockState::new();
+        observe_date(&mut state, NOTION_DATE, utc(2024, 3, 1, 13, 52));
+        assert!(take_warning(&mut state).is_some());
+
+        // Within the remeasure interval the header is ignored
+        observe_date(&mut state, NOTION_DATE, utc(2019, 1, 1, 0, 0));
+        assert_eq!(state.offset, Some(chrono::Duration::minutes(10)));
+
+        due_for_remeasuring(&mut state);
+        observe_date(&mut state, NOTION_DATE, utc(2024, 3, 1, 13, 51));
+        assert_eq!(state.offset, Some(chrono::Duration::minutes(11)));
+        assert_eq!(take_warning(&mut state), None);
+
+        due_for_remeasuring(&mut state);
+        observe_date(&mut state, NOTION_DATE, utc(2024, 3, 1, 12, 2));
+        assert_eq!(take_warning(&mut state), Some(chrono::Duration::hours(2)));
+
+        // Once the clock is fixed, a later skew is news again
+        due_for_remeasuring(&mut state);
+        observe_date(&mut state, NOTION_DATE, utc(2024, 3, 1, 14, 2));
+        assert_eq!(state.warned_offset, None);
+        due_for_remeasuring(&mut state);
+        observe_date(&mut state, NOTION_DATE, utc(2024, 3, 1, 12, 2));
+        assert_eq!(take_warning(&mut state), Some(chrono::Duration::hours(2)));
+    }
+}
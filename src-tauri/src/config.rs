@@ -1,22 +1,270 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
+#[cfg(not(test))]
 use tauri::api::path::app_config_dir;
 
+use crate::abbreviations::Abbreviation;
+use crate::blocks::NoteBlockType;
+use crate::focus::FocusState;
+use crate::typography::TypographyOptions;
+use crate::quiet_hours::QuietHours;
+use crate::notion::PageRef;
+use crate::secrets::Secrets;
+use crate::tasks::TaskRegistry;
+use crate::verification::ValidationReport;
+use crate::window_effects::WindowEffect;
+
+// A named capture destination that can have its own hotkey (and workspace token)
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct CaptureProfile {
+    pub name: String,
+    // Where this profile's own token is stored; None means the main API token
+    pub token_ref: Option<String>,
+    pub page_id: String,
+    pub page_title: String,
+    // Captures straight into this profile without switching the main target
+    pub capture_hotkey: Option<String>,
+}
+
+// Writing direction used to lay out the timestamp around a note
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TextDirection {
+    // Detected from the note text
+    #[default]
+    Auto,
+    Ltr,
+    Rtl,
+}
+
+// Where the timestamp goes for right-to-left notes, so Notion doesn't render the block LTR
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RtlTimestampLayout {
+    // At the end of the same block
+    #[default]
+    Suffix,
+    // In its own dimmed block above the note
+    SeparateBlock,
+}
+
+// What the default destination is
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetMode {
+    // The selected page
+    #[default]
+    Fixed,
+    // A page per day, see daily_pages.rs
+    Daily,
+}
+
+// What kind of Notion object the selected target is
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetKind {
+    // Notes are appended as blocks
+    #[default]
+    Page,
+    // Each note becomes a new row, see database_rows.rs
+    Database,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
 pub struct AppConfig {
-    pub notion_api_token: String,
+    // API tokens and the webhook secret; never serialized, see secrets.rs
+    #[serde(skip)]
+    pub secrets: Secrets,
+    // Where the main API token is stored, e.g. "keyring:main"
+    pub token_ref: Option<String>,
     pub selected_page_id: String,
     pub selected_page_title: String,
+    pub selected_page_icon: Option<String>,
+    // Set when the selected page seems to have been archived or deleted
+    pub selected_page_stale: bool,
+    // Whether selected_page_id is a page or a database
+    pub selected_target_kind: TargetKind,
+    // Page searches stop after this many results and report the list as truncated
+    pub page_search_max_results: usize,
+    // Keep the text of sent notes locally for get_note_history and history search
+    pub note_history: bool,
+    // Delivered notes kept locally; older ones are dropped
+    pub note_history_max_entries: usize,
+    // Sends the clipboard as a note without opening the note window; None turns it off
+    pub clipboard_hotkey: Option<String>,
+    // Minutes after sending during which the last note can still be undone
+    pub undo_window_minutes: u32,
+    // Create the note window hidden at startup so the hotkey only has to show it
+    pub preload_note_window: bool,
+    // Clipboard captures above the soft limit are previewed, above the hard limit refused
+    pub clipboard_soft_limit_chars: usize,
+    pub clipboard_hard_limit_chars: usize,
+    // Clipboard text with at least this many lines and few prose-like lines gets code-fenced
+    pub clipboard_structured_min_lines: usize,
+    pub clipboard_prose_ratio_threshold: f32,
+    // Apps in which the global hotkey is ignored ("bundle:<id>" for exact macOS bundle ids)
+    pub hotkey_excluded_apps: Vec<String>,
+    pub capture_profiles: Vec<CaptureProfile>,
+    // Extra headers sent with every Notion request (for proxies); Authorization and Notion-Version are protected
+    pub extra_headers: BTreeMap<String, String>,
+    // Captures starting with this go to the scratch buffer instead of straight to Notion
+    pub scratch_prefix: String,
+    // Local "HH:MM" at which the scratch buffer is flushed each day
+    pub scratch_flush_time: Option<String>,
+    // Shift note timestamps by the measured offset when the system clock is badly off
+    pub correct_clock_skew: bool,
+    // When the selected page is full, continue on a new sibling page instead of failing
+    pub auto_rotate_pages: bool,
+    pub text_direction: TextDirection,
+    pub rtl_timestamp_layout: RtlTimestampLayout,
+    // Each line of a plain note becomes its own paragraph block; off keeps one block with line breaks
+    pub split_paragraphs: bool,
+    // What a note is appended as when the capture doesn't say: plain blocks, a callout or a toggle
+    pub note_block_type: NoteBlockType,
+    // Toggle notes show the first words of the note next to the timestamp
+    pub toggle_preview: bool,
+    // Annotations for the note text: bold, and one of Notion's color names
+    pub note_bold: bool,
+    pub note_color: String,
+    // #tags in a note get their own color (one of Notion's color names); off leaves them as text
+    pub highlight_hashtags: bool,
+    pub hashtag_color: String,
+    // Note timestamps use a 12-hour clock with AM/PM
+    pub timestamp_12_hour: bool,
+    // Locale for month names in timestamps, e.g. "de" or "pt-BR"; None follows the system
+    pub timestamp_locale: Option<String>,
+    // IANA zone note timestamps are shown in, e.g. "Europe/Bucharest"; None uses local time
+    pub timestamp_timezone: Option<String>,
+    // Notes go under a heading for their day ("2025-03-04"), created when the day's first note arrives
+    pub day_headings: bool,
+    // A top-level block of the selected page that notes go right after, newest
+    // first, instead of at the page's end; None appends at the end
+    pub anchor_block_id: Option<String>,
+    // Fixed: notes go to the selected page. Daily: to a page titled for the
+    // day under daily_parent_page_id, named with the chrono format daily_title_format
+    pub target_mode: TargetMode,
+    pub daily_parent_page_id: String,
+    pub daily_title_format: String,
+    // Shorthands expanded in notes before they are sent
+    pub abbreviations: Vec<Abbreviation>,
+    // Backdrop for the note window; applied when the window is next created
+    pub window_effects: WindowEffect,
+    // Announce capture results through OS notifications and give the note window a descriptive title
+    pub screen_reader_mode: bool,
+    // Treat `@09:30`, `@yesterday 16:00` or `@2024-05-01 09:30` at the start of a note as its timestamp
+    pub leading_timestamps: bool,
+    // Enables append_raw_blocks, which sends user-supplied block JSON unformatted
+    pub allow_raw_blocks: bool,
+    // Set when Notion rejected the main token (401); cleared only by setting a token that verifies
+    pub token_invalid: bool,
+    // How often settings are re-checked in the background, in hours; 0 turns it off
+    pub verification_interval_hours: u64,
+    // Show a notification when a focus session reaches its end time
+    pub focus_end_notification: bool,
+    // Clean up pasted text (quotes, dashes, stray spaces) before it is sent; see `typography`
+    pub normalize_typography: bool,
+    pub typography: TypographyOptions,
+    // Outbound Notion requests allowed in flight at once; read at startup
+    pub max_concurrent_requests: usize,
+    // Delivered captures are also POSTed here; see `webhook`
+    pub mirror_webhook_url: Option<String>,
+    // Where the secret sent as X-Mirror-Secret is stored; the receiving service authenticates us with it
+    pub mirror_webhook_secret_ref: Option<String>,
+    // Allow a plain http webhook, e.g. a service on localhost
+    pub allow_insecure_webhook: bool,
+    // Child page made by `create_sandbox_page`; cleared when Notion says it's gone
+    pub sandbox_page_id: Option<String>,
+    // Send every capture to the sandbox page, whatever the routing says
+    pub sandbox_mode: bool,
+    // Encrypt note text in local files; toggled with set_encrypt_local_data, which migrates them
+    pub encrypt_local_data: bool,
+    // Answer Notion requests from the built-in fake (see dry_run.rs); NQN_DRY_RUN=1 does the same
+    pub dry_run: bool,
+    // Spellcheck in the note window, and the languages to check against (empty: the OS default)
+    pub spellcheck_enabled: bool,
+    pub spellcheck_languages: Vec<String>,
+    // Local notification summarizing last week's captures; see digest.rs
+    pub weekly_digest_enabled: bool,
+    pub weekly_digest_day: String,
+    // Local "HH:MM" on that day
+    pub weekly_digest_time: String,
+    // When background work and notifications wait; see quiet_hours.rs
+    pub quiet_hours: QuietHours,
 }
 
 impl Default for AppConfig {
     fn default() -> Self {
         AppConfig {
-            notion_api_token: String::new(),
+            secrets: Secrets::default(),
+            token_ref: None,
             selected_page_id: String::new(),
             selected_page_title: String::new(),
+            selected_page_icon: None,
+            selected_page_stale: false,
+            selected_target_kind: TargetKind::Page,
+            page_search_max_results: 500,
+            note_history: true,
+            note_history_max_entries: 500,
+            clipboard_hotkey: Some("Alt+Shift+Q".to_string()),
+            undo_window_minutes: 5,
+            preload_note_window: true,
+            clipboard_soft_limit_chars: 10_000,
+            clipboard_hard_limit_chars: 100_000,
+            clipboard_structured_min_lines: 5,
+            clipboard_prose_ratio_threshold: 0.5,
+            hotkey_excluded_apps: Vec::new(),
+            capture_profiles: Vec::new(),
+            extra_headers: BTreeMap::new(),
+            scratch_prefix: "..".to_string(),
+            scratch_flush_time: None,
+            correct_clock_skew: true,
+            auto_rotate_pages: false,
+            text_direction: TextDirection::Auto,
+            rtl_timestamp_layout: RtlTimestampLayout::Suffix,
+            split_paragraphs: true,
+            note_block_type: NoteBlockType::Paragraph,
+            toggle_preview: true,
+            note_bold: false,
+            note_color: "default".to_string(),
+            highlight_hashtags: true,
+            hashtag_color: "yellow_background".to_string(),
+            timestamp_12_hour: false,
+            timestamp_locale: None,
+            timestamp_timezone: None,
+            day_headings: false,
+            anchor_block_id: None,
+            target_mode: TargetMode::Fixed,
+            daily_parent_page_id: String::new(),
+            daily_title_format: "%Y-%m-%d".to_string(),
+            abbreviations: Vec::new(),
+            window_effects: WindowEffect::None,
+            screen_reader_mode: false,
+            leading_timestamps: false,
+            allow_raw_blocks: false,
+            token_invalid: false,
+            verification_interval_hours: 6,
+            focus_end_notification: true,
+            normalize_typography: false,
+            typography: TypographyOptions::default(),
+            max_concurrent_requests: crate::rate_limit::DEFAULT_MAX_IN_FLIGHT,
+            mirror_webhook_url: None,
+            mirror_webhook_secret_ref: None,
+            allow_insecure_webhook: false,
+            sandbox_page_id: None,
+            sandbox_mode: false,
+            encrypt_local_data: false,
+            dry_run: false,
+            spellcheck_enabled: true,
+            spellcheck_languages: Vec::new(),
+            weekly_digest_enabled: false,
+            weekly_digest_day: "monday".to_string(),
+            weekly_digest_time: "09:00".to_string(),
+            quiet_hours: QuietHours::default(),
         }
     }
 }
@@ -31,12 +279,56 @@ impl AppConfig {
         
         let config_str = fs::read_to_string(&config_path)
             .map_err(|e| format!("Failed to read config file: {}", e))?;
-            
-        serde_json::from_str(&config_str)
-            .map_err(|e| format!("Failed to parse config: {}", e))
+        let raw: serde_json::Value = serde_json::from_str(&config_str)
+            .map_err(|e| format!("Failed to parse config: {}", e))?;
+        let migrated = crate::migrations::run_startup(raw);
+        
+        let mut config: AppConfig = serde_json::from_value(migrated.data.config.clone())
+            .map_err(|e| format!("Failed to parse config: {}", e))?;
+        config.secrets = Secrets::load(&config);
+        
+        // Secrets older versions kept in config.json go to the secret store with the save
+        if !migrated.applied.is_empty() {
+            config.secrets.merge_missing(migrated.data.legacy_secrets.clone());
+            // Keep the migrated config even if it can't be saved; unrecorded,
+            // the migrations run again on the next launch
+            if let Err(e) = config.save() {
+                eprintln!("Failed to save the migrated config: {}", e);
+                return Ok(config);
+            }
+        }
+        crate::migrations::record(&migrated);
+        Ok(config)
+    }
+    
+    // The selected page as a reference that can be reconciled against Notion
+    pub fn selected_page_ref(&self) -> PageRef {
+        PageRef {
+            id: self.selected_page_id.clone(),
+            title: self.selected_page_title.clone(),
+            icon: self.selected_page_icon.clone(),
+            stale: self.selected_page_stale,
+        }
+    }
+    
+    pub fn set_selected_page_ref(&mut self, page_ref: &PageRef) {
+        // The anchor is a block of the old page
+        if self.selected_page_id != page_ref.id {
+            self.anchor_block_id = None;
+        }
+        self.selected_page_id = page_ref.id.clone();
+        self.selected_page_title = page_ref.title.clone();
+        self.selected_page_icon = page_ref.icon.clone();
+        self.selected_page_stale = page_ref.stale;
     }
     
-    pub fn save(&self) -> Result<(), String> {
+    // Secrets go to the secret store first; config.json only gets their references
+    pub fn save(&mut self) -> Result<(), String> {
+        let mut secrets = std::mem::take(&mut self.secrets);
+        let persisted = secrets.persist(self);
+        self.secrets = secrets;
+        persisted?;
+        
         let config_path = get_config_path()?;
         
         // Create parent directories if they don't exist
@@ -47,6 +339,7 @@ impl AppConfig {
         
         let config_str = serde_json::to_string_pretty(self)
             .map_err(|e| format!("Failed to serialize config: {}", e))?;
+        self.secrets.debug_assert_absent(&config_str);
             
         fs::write(&config_path, config_str)
             .map_err(|e| format!("Failed to write config file: {}", e))
@@ -54,21 +347,109 @@ impl AppConfig {
 }
 
 fn get_config_path() -> Result<PathBuf, String> {
+    app_data_path("config.json")
+}
+
+// Path of a file stored alongside the config
+pub fn app_data_path(file_name: &str) -> Result<PathBuf, String> {
+    #[cfg(test)]
+    let app_config_dir = test_data_dir();
+    #[cfg(not(test))]
     let app_config_dir = app_config_dir(&tauri::Config::default())
         .ok_or("Failed to get app config directory")?;
         
-    Ok(app_config_dir.join("config.json"))
+    Ok(app_config_dir.join(file_name))
+}
+
+#[cfg(test)]
+thread_local! {
+    static TEST_DATA_DIR: std::cell::RefCell<Option<PathBuf>> = const { std::cell::RefCell::new(None) };
+}
+
+// Tests keep their files in a directory of their own instead of the user's config dir
+#[cfg(test)]
+fn test_data_dir() -> PathBuf {
+    TEST_DATA_DIR.with(|dir| dir.borrow().clone()).unwrap_or_else(|| {
+        std::env::temp_dir().join(format!("notion-quick-notes-test-{}", std::process::id()))
+    })
+}
+
+// Give the current test (thread) a fresh, empty data directory
+#[cfg(test)]
+pub fn use_test_data_dir() -> PathBuf {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    let dir = std::env::temp_dir().join(format!(
+        "notion-quick-notes-test-{}-{}",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).expect("create the test data dir");
+    TEST_DATA_DIR.with(|current| *current.borrow_mut() = Some(dir.clone()));
+    dir
 }
 
 // Create AppState to hold the config
 pub struct AppState {
     pub config: Arc<Mutex<AppConfig>>,
+    pub tasks: Arc<TaskRegistry>,
+    // Result of the most recent settings verification
+    pub last_verification: Arc<Mutex<Option<ValidationReport>>>,
+    // Active focus session and its audit trail, persisted to focus.json
+    pub focus: Arc<Mutex<FocusState>>,
+    // Captures made before setup was finished; see setup_captures.rs
+    pub pending_setup: Arc<Mutex<Vec<crate::setup_captures::PendingSetupCapture>>>,
+    // The note `undo_last_note` would remove; see undo.rs
+    pub last_append: Arc<Mutex<Option<crate::undo::LastAppend>>>,
 }
 
 // Initialize the application state with the loaded config
 pub fn init_app_state() -> AppState {
     let config = AppConfig::load().unwrap_or_default();
+    if let Err(e) = crate::notion::set_extra_headers(&config.extra_headers) {
+        eprintln!("Ignoring configured extra headers: {}", e);
+    }
+    crate::clock::set_correct_clock_skew(config.correct_clock_skew);
+    crate::rate_limit::RATE_LIMITER.set_max_in_flight(config.max_concurrent_requests);
+    crate::local_crypto::set_enabled(config.encrypt_local_data);
+    crate::receipts::set_history_options(config.note_history, config.note_history_max_entries);
+    crate::dry_run::set_from_settings(config.dry_run);
+    crate::quiet_hours::set_schedule(&config.quiet_hours);
     AppState {
         config: Arc::new(Mutex::new(config)),
+        tasks: Arc::new(TaskRegistry::new()),
+        last_verification: Arc::new(Mutex::new(None)),
+        focus: Arc::new(Mutex::new(crate::focus::load_focus_state())),
+        pending_setup: Arc::new(Mutex::new(crate::setup_captures::load_pending())),
+        last_append: Arc::new(Mutex::new(None)),
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEGACY_CONFIG: &str = include_str!("../fixtures/migrations/legacy_config.json");
+
+    #[test]
+    fn a_migrated_config_that_cant_be_saved_still_loads() {
+        let dir = use_test_data_dir();
+        fs::write(dir.join("config.json"), LEGACY_CONFIG).unwrap();
+        // A directory where the secrets file should be makes every save fail
+        fs::create_dir(dir.join("secrets.json")).unwrap();
+
+        let config = AppConfig::load().unwrap();
+        assert_eq!(config.selected_page_id, "3e2f0000-0000-0000-0000-0000000000ab");
+        assert_eq!(config.secrets.main_token(), "secret_fixture_main");
+        assert_eq!(config.secrets.profile_token("work"), Some("secret_fixture_work"));
+        assert!(crate::migrations::load_history().applied.is_empty());
+        assert_eq!(fs::read_to_string(dir.join("config.json")).unwrap(), LEGACY_CONFIG);
+
+        // Unrecorded, the migrations run again once saving works
+        fs::remove_dir(dir.join("secrets.json")).unwrap();
+        let config = AppConfig::load().unwrap();
+        assert_eq!(config.secrets.main_token(), "secret_fixture_main");
+        assert_eq!(crate::migrations::load_history().applied.len(), 2);
+        assert!(!fs::read_to_string(dir.join("config.json")).unwrap().contains("secret_fixture"));
+    }
+}
@@ -0,0 +1,77 @@
+use chrono::format::StrftimeItems;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+use crate::config::AppConfig;
+use crate::error::AppError;
+use crate::notion::{rotation_page_body, NotionApiClient};
+
+// Daily page mode: instead of one fixed page, notes go to a page titled for
+// the day ("2025-03-04") under a configured parent, created the first time a
+// day's note arrives. The title is decided at capture, so a note queued
+// offline still lands on its own day's page; the page id is looked up when
+// the note is sent and remembered until the title changes.
+
+// The day's page for a capture, before its id is known
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DailyTarget {
+    pub parent_page_id: String,
+    pub title: String,
+}
+
+lazy_static::lazy_static! {
+    // The page resolved last, with its id
+    static ref RESOLVED: Mutex<Option<(DailyTarget, String)>> = Mutex::new(None);
+    // Held from lookup to creation, so two quick notes can't create the page twice
+    static ref RESOLVE_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::new(());
+}
+
+// A title format chrono understands, e.g. "%Y-%m-%d" or "%A %d %B"
+pub fn validate_title_format(format: &str) -> Result<(), AppError> {
+    if format.trim().is_empty() {
+        return Err(AppError::ValidationError("The daily page title format can't be empty".into()));
+    }
+    StrftimeItems::new(format)
+        .parse()
+        .map(|_| ())
+        .map_err(|_| AppError::ValidationError(format!("\"{}\" isn't a valid date format", format)))
+}
+
+// The day's page for a capture made on `day`
+pub fn target_for(config: &AppConfig, day: NaiveDate) -> DailyTarget {
+    DailyTarget {
+        parent_page_id: config.daily_parent_page_id.clone(),
+        title: day.format(&config.daily_title_format).to_string(),
+    }
+}
+
+// The page id, when this day's page was resolved already
+pub fn cached_id(target: &DailyTarget) -> Option<String> {
+    RESOLVED
+        .lock()
+        .unwrap()
+        .as_ref()
+        .filter(|(resolved, _)| resolved == target)
+        .map(|(_, page_id)| page_id.clone())
+}
+
+// The id of the day's page, found among the parent's child pages or created there
+pub async fn resolve(client: &NotionApiClient, target: &DailyTarget) -> Result<String, String> {
+    let _serialized = RESOLVE_LOCK.lock().await;
+    if let Some(page_id) = cached_id(target) {
+        return Ok(page_id);
+    }
+    let page_id = match client.find_child_page(&target.parent_page_id, &target.title).await? {
+        Some(page_id) => page_id,
+        None => {
+            let created = client
+                .create_page(&rotation_page_body(&target.parent_page_id, &target.title))
+                .await?;
+            println!("Created daily page \"{}\"", target.title);
+            created["id"].as_str().ok_or("Notion didn't return the new daily page's id")?.to_string()
+        }
+    };
+    *RESOLVED.lock().unwrap() = Some((target.clone(), page_id.clone()));
+    Ok(page_id)
+}
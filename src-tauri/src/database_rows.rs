@@ -0,0 +1,141 @@
+use chrono::{DateTime, Local};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::blocks::text_runs;
+use crate::notion::{AppendFailure, AppendResponse, NotionApiClient};
+
+// Database targets: instead of appending blocks, each note becomes a new row
+// (a page in the database) with the note as its title and the capture time in
+// a date property. Property names come from the database's schema, so a
+// renamed title column still works. The schema is remembered per database and
+// read again once when Notion refuses a row, in case the columns changed.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatabaseSchema {
+    // The property of type "title"; every database has exactly one
+    pub title_property: String,
+    // Where the capture time goes; None when the database has no date property
+    pub date_property: Option<String>,
+}
+
+// Date properties with these names (any case) are preferred for the capture time
+const DATE_PROPERTY_NAMES: &[&str] = &["created", "date", "captured"];
+
+lazy_static::lazy_static! {
+    // Schemas by database id
+    static ref SCHEMAS: Mutex<HashMap<String, DatabaseSchema>> = Mutex::new(HashMap::new());
+}
+
+// A database's name as plain text
+pub fn database_title(database: &Value) -> String {
+    let title: String = database["title"]
+        .as_array()
+        .map(|runs| runs.iter().filter_map(|run| run["plain_text"].as_str()).collect())
+        .unwrap_or_default();
+    if title.trim().is_empty() {
+        "Untitled".to_string()
+    } else {
+        title
+    }
+}
+
+// The title and date properties of a database object as Notion returned it
+pub fn schema_of(database: &Value) -> Option<DatabaseSchema> {
+    let properties = database["properties"].as_object()?;
+    let title_property = properties
+        .iter()
+        .find(|(_, property)| property["type"] == "title")
+        .map(|(name, _)| name.clone())?;
+    let dates: Vec<&String> = properties
+        .iter()
+        .filter(|(_, property)| property["type"] == "date")
+        .map(|(name, _)| name)
+        .collect();
+    let date_property = dates
+        .iter()
+        .find(|name| DATE_PROPERTY_NAMES.iter().any(|preferred| name.eq_ignore_ascii_case(preferred)))
+        .or(dates.first())
+        .map(|name| name.to_string());
+    Some(DatabaseSchema {
+        title_property,
+        date_property,
+    })
+}
+
+// Row titles are a single line, so the note's lines are joined with spaces
+fn row_title(note_text: &str) -> String {
+    note_text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// The POST /v1/pages body for one note's row
+pub fn row_body(database_id: &str, schema: &DatabaseSchema, note_text: &str, captured_at: &DateTime<Local>) -> Value {
+    let mut properties = serde_json::Map::new();
+    properties.insert(
+        schema.title_property.clone(),
+        json!({ "title": text_runs(&row_title(note_text), false) }),
+    );
+    if let Some(date_property) = &schema.date_property {
+        properties.insert(
+            date_property.clone(),
+            json!({ "date": { "start": captured_at.to_rfc3339() } }),
+        );
+    }
+    json!({
+        "parent": { "database_id": database_id },
+        "properties": properties
+    })
+}
+
+// Read a database's schema from Notion and remember it
+pub async fn refresh_schema(client: &NotionApiClient, database_id: &str) -> Result<DatabaseSchema, String> {
+    let database = client
+        .fetch_database(database_id)
+        .await?
+        .ok_or("The database was deleted or isn't shared with the integration")?;
+    if database["archived"].as_bool() == Some(true) || database["in_trash"].as_bool() == Some(true) {
+        return Err("The database is archived".into());
+    }
+    let schema = schema_of(&database).ok_or("Notion didn't return the database's title property")?;
+    SCHEMAS.lock().unwrap().insert(database_id.to_string(), schema.clone());
+    Ok(schema)
+}
+
+fn forget(database_id: &str) {
+    SCHEMAS.lock().unwrap().remove(database_id);
+}
+
+// Add a note as a new row. The response lists the row's id as its only block,
+// so receipts and undo treat the row like an appended note.
+pub async fn create_row(
+    client: &NotionApiClient,
+    database_id: &str,
+    note_text: &str,
+    captured_at: &DateTime<Local>,
+) -> Result<AppendResponse, AppendFailure> {
+    let mut remembered = SCHEMAS.lock().unwrap().get(database_id).cloned();
+    loop {
+        let from_cache = remembered.is_some();
+        let schema = match remembered.take() {
+            Some(schema) => schema,
+            None => refresh_schema(client, database_id).await.map_err(AppendFailure::Failed)?,
+        };
+        match client.create_page(&row_body(database_id, &schema, note_text, captured_at)).await {
+            Ok(row) => {
+                let row_id = row["id"]
+                    .as_str()
+                    .ok_or_else(|| AppendFailure::Failed("Notion didn't return the new row's id".into()))?;
+                return Ok(AppendResponse {
+                    block_ids: vec![row_id.to_string()],
+                    request_id: None,
+                    status: 200,
+                    attempts: 1,
+                });
+            }
+            // A validation error from a remembered schema: the columns may have changed
+            Err(e) if from_cache && e.starts_with("API error: 400") => forget(database_id),
+            Err(e) => return Err(AppendFailure::Failed(e)),
+        }
+    }
+}
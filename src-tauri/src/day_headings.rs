@@ -0,0 +1,147 @@
+use chrono::NaiveDate;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::blocks::{self, text_runs};
+use crate::notion::{AppendFailure, AppendResponse, NotionApiClient};
+
+// Optional page layout with a heading_2 per day ("2025-03-04") and that day's
+// notes beneath it. The heading is looked for among the page's newest children
+// and created when missing; then the heading and the last block of its section
+// are remembered for the rest of the day, so later notes go straight after them
+// without another read. Appends under headings run one at a time, so two quick
+// notes can't both decide the heading is missing and create it twice.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DaySection {
+    pub heading: String,
+    pub heading_id: String,
+    // Where the next note goes: after the section's last block, or the heading itself
+    pub last_block_id: String,
+}
+
+lazy_static::lazy_static! {
+    // The current section by page id
+    static ref SECTIONS: Mutex<HashMap<String, DaySection>> = Mutex::new(HashMap::new());
+    // Held from looking up the heading until the note has landed
+    static ref APPEND_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::new(());
+}
+
+// "2025-03-04"
+pub fn heading_text(day: NaiveDate) -> String {
+    day.format("%Y-%m-%d").to_string()
+}
+
+fn heading_block(text: &str) -> Value {
+    json!({
+        "object": "block",
+        "type": "heading_2",
+        "heading_2": {
+            "rich_text": text_runs(text, false)
+        }
+    })
+}
+
+// The section under `heading` among a page's children as Notion returned
+// them: the newest such heading and the last block before the next heading_2
+pub fn find_section(children: &[Value], heading: &str) -> Option<DaySection> {
+    let start = children
+        .iter()
+        .rposition(|block| block["type"] == "heading_2" && blocks::block_text(block) == heading)?;
+    let end = children[start + 1..]
+        .iter()
+        .position(|block| block["type"] == "heading_2")
+        .map_or(children.len(), |offset| start + 1 + offset);
+    Some(DaySection {
+        heading: heading.to_string(),
+        heading_id: children[start]["id"].as_str()?.to_string(),
+        last_block_id: children[end - 1]["id"].as_str()?.to_string(),
+    })
+}
+
+fn cached(page_id: &str, heading: &str) -> Option<DaySection> {
+    SECTIONS.lock().unwrap().get(page_id).filter(|section| section.heading == heading).cloned()
+}
+
+fn forget(page_id: &str) {
+    SECTIONS.lock().unwrap().remove(page_id);
+}
+
+// The page's section for `heading`, read from the page or created at its end
+async fn load_section(
+    client: &NotionApiClient,
+    page_id: &str,
+    page_title: &str,
+    heading: &str,
+) -> Result<DaySection, AppendFailure> {
+    let children = client.last_children(page_id).await.map_err(AppendFailure::Failed)?.unwrap_or_default();
+    if let Some(section) = find_section(&children, heading) {
+        return Ok(section);
+    }
+    let response = client.append_children(page_id, page_title, &[heading_block(heading)]).await?;
+    let heading_id = response
+        .block_ids
+        .first()
+        .cloned()
+        .ok_or_else(|| AppendFailure::Failed("Notion didn't return the new day heading".into()))?;
+    Ok(DaySection {
+        heading: heading.to_string(),
+        heading_id: heading_id.clone(),
+        last_block_id: heading_id,
+    })
+}
+
+// Append a note's blocks at the end of its day's section. A remembered section
+// whose blocks were deleted meanwhile is read again from the page once.
+pub async fn append_under_heading(
+    client: &NotionApiClient,
+    page_id: &str,
+    page_title: &str,
+    heading: &str,
+    children: &[Value],
+) -> Result<AppendResponse, AppendFailure> {
+    let _serialized = APPEND_LOCK.lock().await;
+    let mut remembered = cached(page_id, heading);
+    loop {
+        let from_cache = remembered.is_some();
+        let mut section = match remembered.take() {
+            Some(section) => section,
+            None => load_section(client, page_id, page_title, heading).await?,
+        };
+        let result = client
+            .append_children_after(page_id, page_title, children, Some(&section.last_block_id))
+            .await;
+        let landed = match &result {
+            Ok(response) => response.block_ids.last(),
+            Err(AppendFailure::Partial(partial)) => partial.delivered.block_ids.last(),
+            Err(AppendFailure::Failed(_)) if from_cache => {
+                forget(page_id);
+                continue;
+            }
+            Err(_) => {
+                forget(page_id);
+                return result;
+            }
+        };
+        if let Some(last) = landed {
+            section.last_block_id = last.clone();
+        }
+        SECTIONS.lock().unwrap().insert(page_id.to_string(), section);
+        return result;
+    }
+}
+
+// Append a note, under its day heading when it has one
+pub async fn append(
+    client: &NotionApiClient,
+    page_id: &str,
+    page_title: &str,
+    heading: Option<&str>,
+    children: &[Value],
+) -> Result<AppendResponse, AppendFailure> {
+    match heading {
+        Some(heading) => append_under_heading(client, page_id, page_title, heading, children).await,
+        None => client.append_children(page_id, page_title, children).await,
+    }
+}
@@ -0,0 +1,76 @@
+use serde::Serialize;
+use tauri::{AppHandle, State};
+
+use crate::config::AppState;
+use crate::events::LoggedLifecycleEvent;
+use crate::profiling::StartupMark;
+use crate::receipts::{self, DeliveryReceipt};
+use crate::window_effects::EffectStatus;
+
+// How many receipts to include in diagnostics
+const DIAGNOSTIC_RECEIPTS: usize = 5;
+
+// Snapshot of runtime state for bug reports and the settings window
+#[derive(Serialize, Debug, Clone)]
+pub struct AppInfo {
+    pub version: String,
+    pub user_agent: String,
+    pub client_session_id: String,
+    // How far Notion's clock is ahead of ours, once measured
+    pub clock_offset_ms: Option<i64>,
+    pub recording: bool,
+    pub window_effect: EffectStatus,
+    pub spellcheck: crate::spellcheck::SpellcheckStatus,
+    pub startup_marks: Vec<StartupMark>,
+    pub running_tasks: Vec<String>,
+    pub last_hotkey_latency_ms: Option<u128>,
+    pub swallowed_hotkey_presses: u64,
+    pub last_swallowed_hotkey_app: Option<String>,
+    pub recent_receipts: Vec<DeliveryReceipt>,
+    pub lifecycle_events: Vec<LoggedLifecycleEvent>,
+    // Notion requests currently holding an in-flight slot
+    pub in_flight_requests: usize,
+    pub mirror_webhook: crate::webhook::MirrorStats,
+    pub health: crate::health::HealthStats,
+    // Retries per policy since startup
+    pub retries: std::collections::BTreeMap<String, crate::retry::RetryCounters>,
+    pub local_data_encrypted: bool,
+    // Encrypted stores can't be read until the system keyring is available again
+    pub local_data_locked: bool,
+    // Set while Notion requests are answered by the built-in fake
+    pub dry_run: Option<crate::dry_run::DryRunInfo>,
+    // Set when this launch runs in safe mode, with the subsystems it skipped
+    pub safe_mode: Option<crate::safe_mode::SafeModeInfo>,
+    pub reliability: crate::reliability::ReliabilitySummary,
+}
+
+#[tauri::command]
+pub fn get_app_info(app: AppHandle, state: State<'_, AppState>) -> Result<AppInfo, String> {
+    let (swallowed_hotkey_presses, last_swallowed_hotkey_app) = crate::swallowed_hotkey_presses();
+    
+    Ok(AppInfo {
+        version: app.package_info().version.to_string(),
+        user_agent: crate::notion::user_agent(),
+        client_session_id: crate::notion::client_session_id().to_string(),
+        clock_offset_ms: crate::clock::offset_ms(),
+        recording: crate::recording::is_recording(),
+        window_effect: crate::window_effects::effect_status(),
+        spellcheck: crate::spellcheck::status(&state.config.lock().unwrap()),
+        startup_marks: crate::profiling::startup_marks(),
+        running_tasks: state.tasks.running_tasks(),
+        last_hotkey_latency_ms: crate::last_hotkey_latency_ms(),
+        swallowed_hotkey_presses,
+        last_swallowed_hotkey_app,
+        recent_receipts: receipts::recent_receipts(DIAGNOSTIC_RECEIPTS),
+        lifecycle_events: crate::events::lifecycle_log(),
+        in_flight_requests: crate::rate_limit::RATE_LIMITER.in_flight(),
+        mirror_webhook: crate::webhook::stats(),
+        health: crate::health::stats(),
+        retries: crate::retry::stats(),
+        local_data_encrypted: crate::local_crypto::is_enabled(),
+        local_data_locked: crate::local_crypto::is_locked(),
+        dry_run: crate::dry_run::info(),
+        safe_mode: crate::safe_mode::info(),
+        reliability: crate::reliability::summary(crate::reliability::DEFAULT_SUMMARY_DAYS),
+    })
+}
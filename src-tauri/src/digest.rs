@@ -0,0 +1,248 @@
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Weekday};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::config::{app_data_path, AppState};
+use crate::queue::{self, QueueItemState, QueuedNote};
+use crate::receipts::{self, DeliveryReceipt};
+
+// Optional weekly summary of capture activity, shown as a local notification
+// at the configured day and time. A week missed while the machine was off is
+// caught up on the next launch; the last period shown is kept on disk so a
+// digest never fires twice, across restarts included.
+const DIGEST_FILE: &str = "digest.json";
+
+const DIGEST_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct DigestState {
+    // Date of the scheduled run last shown, e.g. "2024-05-06"
+    last_period: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DigestSummary {
+    pub captured: usize,
+    pub pending: usize,
+    pub failures: usize,
+}
+
+fn load_state() -> DigestState {
+    app_data_path(DIGEST_FILE)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_state(state: &DigestState) -> Result<(), String> {
+    let path = app_data_path(DIGEST_FILE)?;
+    let contents = serde_json::to_string_pretty(state).map_err(|e| format!("Failed to serialize digest state: {}", e))?;
+    fs::write(&path, contents).map_err(|e| format!("Failed to write digest state: {}", e))
+}
+
+// "monday", "mon" or any case of either; invalid days fall back to Monday
+pub fn parse_day(day: &str) -> Weekday {
+    day.trim().parse().unwrap_or(Weekday::Mon)
+}
+
+// The most recent scheduled run at or before `now`
+pub fn latest_occurrence(now: NaiveDateTime, day: Weekday, time: NaiveTime) -> NaiveDateTime {
+    let days_back = (7 + now.weekday().num_days_from_monday() - day.num_days_from_monday()) % 7;
+    let mut date = now.date() - ChronoDuration::days(days_back as i64);
+    if days_back == 0 && now.time() < time {
+        date -= ChronoDuration::days(7);
+    }
+    date.and_time(time)
+}
+
+// The run to show now, if any. Without a record of an earlier run the current
+// one is only recorded, so turning the digest on doesn't fire it straight away.
+pub fn due_period(last_period: Option<&str>, occurrence: NaiveDate) -> Option<NaiveDate> {
+    let last = last_period?.parse::<NaiveDate>().ok();
+    match last {
+        Some(last) if last >= occurrence => None,
+        _ => Some(occurrence),
+    }
+}
+
+fn within(time: &str, start: DateTime<Local>, end: DateTime<Local>) -> bool {
+    DateTime::parse_from_rfc3339(time).is_ok_and(|time| time >= start && time < end)
+}
+
+// Activity in [start, end), plus whatever is still waiting in the queue
+pub fn summarize(receipts: &[DeliveryReceipt], queued: &[QueuedNote], start: DateTime<Local>, end: DateTime<Local>) -> DigestSummary {
+    let delivered = receipts
+        .iter()
+        .filter(|receipt| within(receipt.captured_at.as_deref().unwrap_or(&receipt.delivered_at), start, end))
+        .count();
+    let still_queued = queued
+        .iter()
+        .filter(|item| within(item.captured_at.as_deref().unwrap_or(&item.queued_at), start, end))
+        .count();
+    DigestSummary {
+        captured: delivered + still_queued,
+        pending: queued.iter().filter(|item| item.state != QueueItemState::Dead).count(),
+        failures: queued
+            .iter()
+            .filter(|item| item.state == QueueItemState::Dead && within(&item.queued_at, start, end))
+            .count(),
+    }
+}
+
+fn count(n: usize, one: &str, many: &str) -> String {
+    format!("{} {}", n, if n == 1 { one } else { many })
+}
+
+pub fn summary_text(summary: &DigestSummary) -> String {
+    let mut parts = vec![count(summary.captured, "note captured", "notes captured")];
+    if summary.pending > 0 {
+        parts.push(format!("{} still pending", summary.pending));
+    }
+    if summary.failures > 0 {
+        parts.push(count(summary.failures, "delivery failure", "delivery failures"));
+    }
+    format!("Last week: {}", parts.join(", "))
+}
+
+fn check_digest(app: &AppHandle) -> Result<(), String> {
+    let (day, time) = {
+        let state = app.state::<AppState>();
+        let config = state.config.lock().unwrap();
+        if !config.weekly_digest_enabled {
+            return Ok(());
+        }
+        (config.weekly_digest_day.clone(), config.weekly_digest_time.clone())
+    };
+    let time = NaiveTime::parse_from_str(&time, "%H:%M")
+        .map_err(|_| format!("Invalid weekly digest time '{}'", time))?;
+
+    let now = Local::now();
+    let occurrence = latest_occurrence(now.naive_local(), parse_day(&day), time);
+    let mut state = load_state();
+    let due = due_period(state.last_period.as_deref(), occurrence.date());
+    if state.last_period.is_some() && due.is_none() {
+        return Ok(());
+    }
+
+    // Recorded before showing, so a crash can't lead to a second digest
+    state.last_period = Some(occurrence.date().to_string());
+    save_state(&state)?;
+    if due.is_none() {
+        return Ok(());
+    }
+
+    let end = Local.from_local_datetime(&occurrence).earliest().unwrap_or(now);
+    let start = end - ChronoDuration::days(7);
+    let summary = summarize(&receipts::recent_receipts(usize::MAX), &queue::load_queue()?, start, end);
+    crate::quiet_hours::notify_background(app, "Weekly digest", &summary_text(&summary));
+    Ok(())
+}
+
+// Background task showing the digest when its time comes
+pub fn start_weekly_digest(app: &AppHandle) {
+    let app_handle = app.clone();
+    let registry = app.state::<AppState>().tasks.clone();
+
+    registry.spawn("weekly-digest", move |mut token| async move {
+        loop {
+            if let Err(e) = check_digest(&app_handle) {
+                eprintln!("Weekly digest failed: {}", e);
+            }
+
+            tokio::select! {
+                _ = token.cancelled() => break,
+                _ = tokio::time::sleep(DIGEST_CHECK_INTERVAL) => {}
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn at(date: &str, time: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn nine() -> NaiveTime {
+        NaiveTime::from_hms_opt(9, 0, 0).unwrap()
+    }
+
+    fn date(date: &str) -> NaiveDate {
+        date.parse().unwrap()
+    }
+
+    #[test]
+    fn days_parse_loosely() {
+        assert_eq!(parse_day("monday"), Weekday::Mon);
+        assert_eq!(parse_day(" Fri "), Weekday::Fri);
+        assert_eq!(parse_day("SUNDAY"), Weekday::Sun);
+        assert_eq!(parse_day("someday"), Weekday::Mon);
+    }
+
+    #[test]
+    fn the_latest_occurrence_is_at_or_before_now() {
+        // Monday 2026-05-11, before, at and after the digest time
+        assert_eq!(latest_occurrence(at("2026-05-11", "08:59"), Weekday::Mon, nine()), at("2026-05-04", "09:00"));
+        assert_eq!(latest_occurrence(at("2026-05-11", "09:00"), Weekday::Mon, nine()), at("2026-05-11", "09:00"));
+        assert_eq!(latest_occurrence(at("2026-05-11", "17:00"), Weekday::Mon, nine()), at("2026-05-11", "09:00"));
+        // Later in the week, and a day later in the week than today
+        assert_eq!(latest_occurrence(at("2026-05-13", "07:00"), Weekday::Mon, nine()), at("2026-05-11", "09:00"));
+        assert_eq!(latest_occurrence(at("2026-05-11", "12:00"), Weekday::Fri, nine()), at("2026-05-08", "09:00"));
+    }
+
+    #[test]
+    fn each_period_fires_once() {
+        let monday = date("2026-05-11");
+        // Nothing recorded yet: the digest was just turned on, so it only records
+        assert_eq!(due_period(None, monday), None);
+        assert_eq!(due_period(Some("2026-05-04"), monday), Some(monday));
+        assert_eq!(due_period(Some("2026-05-11"), monday), None);
+        // A garbled record doesn't block the digest forever
+        assert_eq!(due_period(Some("last week"), monday), Some(monday));
+    }
+
+    #[test]
+    fn a_missed_week_is_caught_up_once_on_the_next_launch() {
+        // Off from Friday the 8th until Wednesday the 13th, past Monday's digest
+        let occurrence = latest_occurrence(at("2026-05-13", "08:15"), Weekday::Mon, nine());
+        assert_eq!(due_period(Some("2026-05-04"), occurrence.date()), Some(date("2026-05-11")));
+        // Two weeks off still shows one digest, for the latest week
+        let occurrence = latest_occurrence(at("2026-05-20", "08:15"), Weekday::Mon, nine());
+        assert_eq!(due_period(Some("2026-05-04"), occurrence.date()), Some(date("2026-05-18")));
+    }
+
+    #[test]
+    fn the_last_period_survives_a_restart() {
+        crate::config::use_test_data_dir();
+        assert_eq!(load_state().last_period, None);
+        save_state(&DigestState { last_period: Some("2026-05-11".into()) }).unwrap();
+        let last = load_state().last_period;
+        assert_eq!(due_period(last.as_deref(), date("2026-05-11")), None);
+    }
+
+    #[test]
+    fn the_summary_counts_last_weeks_activity() {
+        let receipts: Vec<DeliveryReceipt> = serde_json::from_str(include_str!("../fixtures/digest/receipts.json")).unwrap();
+        let queued: Vec<QueuedNote> = serde_json::from_str(include_str!("../fixtures/digest/queue.json")).unwrap();
+        let end = Utc.with_ymd_and_hms(2026, 5, 11, 9, 0, 0).unwrap().with_timezone(&Local);
+        let start = end - ChronoDuration::days(7);
+
+        let summary = summarize(&receipts, &queued, start, end);
+        // 3 delivered and 3 still queued from the week; pending is whatever isn't dead
+        assert_eq!(summary, DigestSummary { captured: 6, pending: 3, failures: 1 });
+        assert_eq!(summary_text(&summary), "Last week: 6 notes captured, 3 still pending, 1 delivery failure");
+    }
+
+    #[test]
+    fn summary_text_only_mentions_problems_when_there_are_some() {
+        assert_eq!(summary_text(&DigestSummary::default()), "Last week: 0 notes captured");
+        let summary = DigestSummary { captured: 1, pending: 0, failures: 2 };
+        assert_eq!(summary_text(&summary), "Last week: 1 note captured, 2 delivery failures");
+    }
+}
@@ -0,0 +1,313 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+// Dry-run mode for QA and demos: every Notion request is answered by a
+// built-in fake instead of the network. Everything above the transport
+// (rate limiting, retries, receipts, history, notifications) runs as usual.
+//
+// Turned on by NQN_DRY_RUN=1 or the hidden `dry_run` setting. Further knobs:
+//   NQN_DRY_RUN_LATENCY_MS        artificial latency per request (default 250)
+//   NQN_DRY_RUN_FAIL_EVERY        answer every Nth request with a 500
+//   NQN_DRY_RUN_RATE_LIMIT_EVERY  answer every Nth request with a 429
+
+const DEFAULT_LATENCY_MS: u64 = 250;
+
+// Appended blocks remembered per page, so duplicate checks see earlier appends
+const MAX_BLOCKS_PER_PAGE: usize = 100;
+
+const CANNED_PAGES: [(&str, &str, &str); 3] = [
+    ("00000000-0000-4000-8000-000000000001", "Dry run inbox", "📥"),
+    ("00000000-0000-4000-8000-000000000002", "Dry run journal", "📓"),
+    ("00000000-0000-4000-8000-000000000003", "Dry run meeting notes", "🗒️"),
+];
+
+static FROM_SETTINGS: AtomicBool = AtomicBool::new(false);
+static REQUESTS: AtomicU64 = AtomicU64::new(0);
+static INJECTED_FAILURES: AtomicU64 = AtomicU64::new(0);
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+lazy_static::lazy_static! {
+    static ref PAGE_BLOCKS: Mutex<HashMap<String, Vec<Value>>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct DryRunInfo {
+    // What turned it on: "env" or "settings"
+    pub source: &'static str,
+    pub latency_ms: u64,
+    pub fail_every: Option<u64>,
+    pub rate_limit_every: Option<u64>,
+    pub requests_served: u64,
+    pub injected_failures: u64,
+}
+
+// Tests set variables for their own thread, so one test can't put the others in dry-run mode
+#[cfg(test)]
+thread_local! {
+    static TEST_ENV: std::cell::RefCell<HashMap<String, String>> = std::cell::RefCell::new(HashMap::new());
+}
+
+#[cfg(test)]
+pub fn set_test_env(name: &str, value: &str) {
+    TEST_ENV.with(|env| env.borrow_mut().insert(name.to_string(), value.to_string()));
+}
+
+fn env_var(name: &str) -> Option<String> {
+    #[cfg(test)]
+    return TEST_ENV.with(|env| env.borrow().get(name).cloned());
+    #[cfg(not(test))]
+    std::env::var(name).ok()
+}
+
+fn env_u64(name: &str) -> Option<u64> {
+    env_var(name)?.trim().parse().ok().filter(|value| *value > 0)
+}
+
+pub fn from_env() -> bool {
+    env_var("NQN_DRY_RUN").is_some_and(|value| matches!(value.trim(), "1" | "true" | "yes"))
+}
+
+pub fn set_from_settings(enabled: bool) {
+    FROM_SETTINGS.store(enabled, Ordering::Relaxed);
+}
+
+// Whether Notion requests go to the fake
+pub fn is_active() -> bool {
+    from_env() || FROM_SETTINGS.load(Ordering::Relaxed)
+}
+
+pub fn info() -> Option<DryRunInfo> {
+    if !is_active() {
+        return None;
+    }
+    Some(DryRunInfo {
+        source: if from_env() { "env" } else { "settings" },
+        latency_ms: env_u64("NQN_DRY_RUN_LATENCY_MS").unwrap_or(DEFAULT_LATENCY_MS),
+        fail_every: env_u64("NQN_DRY_RUN_FAIL_EVERY"),
+        rate_limit_every: env_u64("NQN_DRY_RUN_RATE_LIMIT_EVERY"),
+        requests_served: REQUESTS.load(Ordering::Relaxed),
+        injected_failures: INJECTED_FAILURES.load(Ordering::Relaxed),
+    })
+}
+
+fn synthetic_id() -> String {
+    let n = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    format!("d27a0000-0000-4000-8000-{:012x}", n)
+}
+
+fn page_object(id: &str, title: &str, icon: Option<&str>) -> Value {
+    json!({
+        "object": "page",
+        "id": id,
+        "archived": false,
+        "in_trash": false,
+        "url": format!("https://www.notion.so/dry-run/{}", id.replace('-', "")),
+        "icon": icon.map(|emoji| json!({ "type": "emoji", "emoji": emoji })),
+        "properties": {
+            "title": { "title": [{ "text": { "content": title } }] }
+        }
+    })
+}
+
+fn error_body(status: u16, code: &str, message: &str) -> (u16, Value) {
+    (status, json!({ "object": "error", "status": status, "code": code, "message": message }))
+}
+
+// The page id in ".../blocks/{id}/children"
+fn children_parent(path: &str) -> Option<&str> {
+    path.strip_prefix("/v1/blocks/")?.strip_suffix("/children")
+}
+
+// Answer one request the way Notion would, minus the injected failures
+pub fn answer(method: &str, path: &str, body: Option<&Value>) -> (u16, Value) {
+    match (method, path) {
+        ("GET", "/v1/users/me") => (200, json!({ "object": "user", "id": "dry-run-bot", "type": "bot", "name": "Dry run" })),
+        ("POST", "/v1/search") => (200, json!({
+            "object": "list",
+            "results": CANNED_PAGES
+                .iter()
+                .map(|(id, title, icon)| page_object(id, title, Some(icon)))
+                .collect::<Vec<_>>(),
+            "has_more": false,
+        })),
+        ("POST", "/v1/pages") => {
+            let title = body
+                .and_then(|body| body["properties"]["title"]["title"][0]["text"]["content"].as_str())
+                .unwrap_or("Untitled");
+            (200, page_object(&synthetic_id(), title, None))
+        }
+        ("GET", _) if path.starts_with("/v1/pages/") => {
+            let id = &path["/v1/pages/".len()..];
+            let title = CANNED_PAGES
+                .iter()
+                .find(|(page_id, _, _)| crate::notion::same_page_id(page_id, id))
+                .map_or("Dry run page", |(_, title, _)| title);
+            (200, page_object(id, title, None))
+        }
+        ("PATCH", _) if children_parent(path).is_some() => {
+            let page_id = children_parent(path).unwrap_or_default().to_string();
+            let children = body.and_then(|body| body["children"].as_array()).cloned().unwrap_or_default();
+            let results: Vec<Value> = children
+                .into_iter()
+                .map(|mut block| {
+                    block["id"] = json!(synthetic_id());
+                    block["object"] = json!("block");
+                    block
+                })
+                .collect();
+            let mut pages = PAGE_BLOCKS.lock().unwrap();
+            let blocks = pages.entry(page_id).or_default();
+            blocks.extend(results.iter().cloned());
+            if blocks.len() > MAX_BLOCKS_PER_PAGE {
+                blocks.drain(..blocks.len() - MAX_BLOCKS_PER_PAGE);
+            }
+            (200, json!({ "object": "list", "results": results, "has_more": false }))
+        }
+        ("GET", _) if path.starts_with("/v1/blocks/") && path.ends_with("/children") => {
+            let page_id = children_parent(path).unwrap_or_default();
+            let blocks = PAGE_BLOCKS.lock().unwrap().get(page_id).cloned().unwrap_or_default();
+            (200, json!({ "object": "list", "results": blocks, "has_more": false, "next_cursor": null }))
+        }
+        ("GET", _) if path.starts_with("/v1/blocks/") => {
+            let id = &path["/v1/blocks/".len()..];
+            let block = PAGE_BLOCKS
+                .lock()
+                .unwrap()
+                .values()
+                .flatten()
+                .find(|block| block["id"].as_str() == Some(id))
+                .cloned();
+            match block {
+                Some(block) => (200, block),
+                None => error_body(404, "object_not_found", "Dry run: no such block"),
+            }
+        }
+        ("DELETE", _) if path.starts_with("/v1/blocks/") => {
+            let id = &path["/v1/blocks/".len()..];
+            for blocks in PAGE_BLOCKS.lock().unwrap().values_mut() {
+                blocks.retain(|block| block["id"].as_str() != Some(id));
+            }
+            (200, json!({ "object": "block", "id": id, "archived": true }))
+        }
+        _ => error_body(400, "invalid_request_url", "Dry run: endpoint not faked"),
+    }
+}
+
+// The scripted failure for the `count`th request, if any; a rate limit wins over a failure
+fn injected_error(count: u64, fail_every: Option<u64>, rate_limit_every: Option<u64>) -> Option<(u16, Value)> {
+    if rate_limit_every.is_some_and(|every| count.is_multiple_of(every)) {
+        Some(error_body(429, "rate_limited", "Dry run: simulated rate limit"))
+    } else if fail_every.is_some_and(|every| count.is_multiple_of(every)) {
+        Some(error_body(500, "internal_server_error", "Dry run: simulated failure"))
+    } else {
+        None
+    }
+}
+
+// Stand-in for sending `request` to Notion
+pub async fn respond(request: &reqwest::Request) -> reqwest::Response {
+    let count = REQUESTS.fetch_add(1, Ordering::Relaxed) + 1;
+    let latency = env_u64("NQN_DRY_RUN_LATENCY_MS").unwrap_or(DEFAULT_LATENCY_MS);
+    tokio::time::sleep(Duration::from_millis(latency)).await;
+
+    let injected = injected_error(
+        count,
+        env_u64("NQN_DRY_RUN_FAIL_EVERY"),
+        env_u64("NQN_DRY_RUN_RATE_LIMIT_EVERY"),
+    );
+    if injected.is_some() {
+        INJECTED_FAILURES.fetch_add(1, Ordering::Relaxed);
+    }
+
+    let (status, body) = injected.unwrap_or_else(|| {
+        let body = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .and_then(|bytes| serde_json::from_slice::<Value>(bytes).ok());
+        answer(request.method().as_str(), request.url().path(), body.as_ref())
+    });
+
+    let mut response = http::Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .header("x-request-id", format!("dry-run-{}", count));
+    if status == 429 {
+        response = response.header("retry-after", "2");
+    }
+    let response = response
+        .body(body.to_string())
+        .expect("a canned response is always valid");
+    reqwest::Response::from(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notion::NotionApiClient;
+
+    #[test]
+    fn the_env_var_turns_it_on() {
+        assert!(!from_env());
+        for (value, on) in [("1", true), ("true", true), ("yes", true), ("0", false), ("", false)] {
+            set_test_env("NQN_DRY_RUN", value);
+            assert_eq!(from_env(), on, "{:?}", value);
+        }
+
+        set_test_env("NQN_DRY_RUN", "1");
+        set_test_env("NQN_DRY_RUN_FAIL_EVERY", "3");
+        set_test_env("NQN_DRY_RUN_RATE_LIMIT_EVERY", "0");
+        let info = info().unwrap();
+        assert_eq!(info.source, "env");
+        assert_eq!(info.latency_ms, DEFAULT_LATENCY_MS);
+        assert_eq!((info.fail_every, info.rate_limit_every), (Some(3), None));
+    }
+
+    #[test]
+    fn every_nth_request_fails_as_scripted() {
+        let statuses: Vec<_> = (1..=6)
+            .map(|count| injected_error(count, Some(2), Some(3)).map(|(status, _)| status))
+            .collect();
+        assert_eq!(statuses, [None, Some(500), Some(429), Some(500), None, Some(429)]);
+        assert!(injected_error(4, None, None).is_none());
+    }
+
+    #[test]
+    fn unfaked_endpoints_and_unknown_blocks_are_errors() {
+        assert_eq!(answer("POST", "/v1/databases/x/query", None).0, 400);
+        assert_eq!(answer("GET", "/v1/blocks/no-such-block", None).0, 404);
+        let (status, user) = answer("GET", "/v1/users/me", None);
+        assert_eq!((status, user["type"].as_str()), (200, Some("bot")));
+    }
+
+    #[tokio::test]
+    async fn a_capture_round_trips_through_the_fake() {
+        set_test_env("NQN_DRY_RUN", "1");
+        set_test_env("NQN_DRY_RUN_LATENCY_MS", "1");
+        // Nothing listens for this token, so only the fake can answer
+        let client = NotionApiClient::new(crate::mock_notion::unreachable_token().await).unwrap();
+
+        assert!(client.verify_token().await.unwrap());
+        let pages = client.search_pages(10).await.unwrap();
+        let titles: Vec<_> = pages.iter().map(|page| page.title.as_str()).collect();
+        assert_eq!(titles, ["Dry run inbox", "Dry run journal", "Dry run meeting notes"]);
+
+        let page_id = "00000000-0000-4000-8000-0000000000aa";
+        let children = vec![crate::blocks::paragraph(crate::blocks::text_runs("Buy milk", false))];
+        let response = client.append_children(page_id, "Dry run inbox", &children).await.unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.block_ids.len(), 1);
+        assert!(response.request_id.is_some_and(|id| id.starts_with("dry-run-")));
+
+        let stored = client.first_children(page_id).await.unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(crate::blocks::block_text(&stored[0]), "Buy milk");
+        assert_eq!(stored[0]["id"].as_str(), Some(response.block_ids[0].as_str()));
+
+        client.delete_block(&response.block_ids[0]).await.unwrap();
+        assert!(client.first_children(page_id).await.unwrap().is_empty());
+    }
+}
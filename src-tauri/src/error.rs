@@ -18,40 +18,79 @@ pub enum AppError {
     #[error("Network error: {0}")]
     NetworkError(String),
     
+    #[error("Validation error: {0}")]
+    ValidationError(String),
+    
+    #[error("Page is full: {0}")]
+    PageFull(String),
+    
+    #[error("Edit conflict: {0}")]
+    Conflict(String),
+    
+    // Seconds Notion asked us to wait, from Retry-After
+    #[error("Notion rate limit reached, try again in {retry_after}s")]
+    RateLimitError { retry_after: u64 },
+    
     #[error("Unknown error: {0}")]
     UnknownError(String),
 }
 
-#[derive(Serialize)]
+// What the user (or the app) can do about an error
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Recovery {
+    None,
+    RetryLater,
+    FixSettings,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Serialize, Debug, Clone)]
 pub struct ErrorResponse {
     pub code: String,
     pub message: String,
     pub details: Option<String>,
+    pub recovery: Recovery,
+    pub severity: Severity,
 }
 
 impl From<AppError> for ErrorResponse {
     fn from(error: AppError) -> Self {
-        let (code, details) = match &error {
-            AppError::ConfigError(_) => ("CONFIG_ERROR", None),
+        use Severity::*;
+        
+        let (code, details, recovery, severity) = match &error {
+            AppError::ConfigError(_) => ("CONFIG_ERROR", None, Recovery::FixSettings, Error),
             AppError::NotionApiError(msg) => {
                 if msg.contains("rate limit") {
-                    ("NOTION_RATE_LIMIT", Some("Please try again later.".into()))
+                    ("NOTION_RATE_LIMIT", Some("Please try again later.".into()), Recovery::RetryLater, Warning)
                 } else if msg.contains("unauthorized") {
-                    ("NOTION_AUTH_ERROR", Some("Please check your API token.".into()))
+                    ("NOTION_AUTH_ERROR", Some("Please check your API token.".into()), Recovery::FixSettings, Error)
                 } else {
-                    ("NOTION_API_ERROR", None)
+                    ("NOTION_API_ERROR", None, Recovery::None, Error)
                 }
             },
-            AppError::HotkeyError(_) => ("HOTKEY_ERROR", None),
-            AppError::FsError(_) => ("FILESYSTEM_ERROR", None),
-            AppError::NetworkError(_) => ("NETWORK_ERROR", Some("Please check your internet connection.".into())),
-            AppError::UnknownError(_) => ("UNKNOWN_ERROR", None),
+            AppError::HotkeyError(_) => ("HOTKEY_ERROR", None, Recovery::FixSettings, Error),
+            AppError::FsError(_) => ("FILESYSTEM_ERROR", None, Recovery::None, Error),
+            AppError::NetworkError(_) => ("NETWORK_ERROR", Some("Please check your internet connection.".into()), Recovery::RetryLater, Error),
+            AppError::ValidationError(_) => ("VALIDATION_ERROR", None, Recovery::FixSettings, Error),
+            AppError::PageFull(_) => ("PAGE_FULL", Some("Choose a new page in Settings or turn on automatic page rotation.".into()), Recovery::FixSettings, Error),
+            AppError::Conflict(_) => ("NOTION_CONFLICT", Some("The page is being edited right now — retrying shortly.".into()), Recovery::RetryLater, Warning),
+            AppError::RateLimitError { .. } => ("NOTION_RATE_LIMIT", Some("Please try again later.".into()), Recovery::RetryLater, Warning),
+            AppError::UnknownError(_) => ("UNKNOWN_ERROR", None, Recovery::None, Error),
         };
         
         ErrorResponse {
             code: code.to_string(),
             message: error.to_string(),
             details,
+            recovery,
+            severity,
         }
     }
 }
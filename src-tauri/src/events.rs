@@ -0,0 +1,577 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, Window};
+
+use crate::capture::CaptureBanner;
+use crate::error::ErrorResponse;
+use crate::file_drop::DroppedFile;
+use crate::focus::FocusSession;
+use crate::page_display::PageDisplay;
+use crate::platform::AccessibilityPrefs;
+use crate::queue::NoteProgress;
+
+// Every event the backend sends to the frontend, with its payload type. All
+// emits go through `emit_app_event`, so an event name can only be spelled here.
+//
+// Window lifecycle events, e.g. to decide whether to keep a draft: each
+// show/hide is emitted as `window-shown`/`window-hidden` and kept in a short
+// log for diagnostics.
+
+pub const ACCESSIBILITY_CHANGED: &str = "accessibility-changed";
+pub const ANCHOR_MISSING: &str = "anchor-missing";
+pub const CAPTURE_BANNER_CHANGED: &str = "capture-banner-changed";
+pub const CLIPBOARD_CAPTURED: &str = "clipboard-captured";
+pub const CONFIG_CHANGED: &str = "config-changed";
+pub const FILE_DROPPED: &str = "file-dropped";
+pub const FOCUS_SESSION_CHANGED: &str = "focus-session-changed";
+pub const HOTKEY_LATENCY: &str = "hotkey-latency";
+pub const NOTE_PROGRESS: &str = "note-progress";
+pub const NOTE_UNDONE: &str = "note-undone";
+pub const OFFER_IMPORT: &str = "offer-import";
+pub const PAGE_DISPLAY_UPDATED: &str = "page-display-updated";
+pub const PAGE_FULL: &str = "page-full";
+pub const PAGE_ROTATED: &str = "page-rotated";
+pub const PREFILL_NOTE: &str = "prefill-note";
+pub const SELECTED_PAGE_MISSING: &str = "selected-page-missing";
+pub const SYSTEM_RESUMED: &str = "system-resumed";
+pub const TOKEN_INVALIDATED: &str = "token-invalidated";
+pub const WINDOW_HIDDEN: &str = "window-hidden";
+pub const WINDOW_SHOWN: &str = "window-shown";
+
+// A note was refused because its page reached Notion's block limit; the
+// ErrorResponse fields (code "PAGE_FULL") plus the page it was meant for
+#[derive(Serialize, Debug, Clone)]
+pub struct PageFull {
+    #[serde(flatten)]
+    pub error: ErrorResponse,
+    pub page_id: String,
+    pub page_title: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PageRotated {
+    pub from: String,
+    pub to: String,
+    pub title: String,
+}
+
+// How a clipboard capture went, for confirmation outside the note window
+#[derive(Serialize, Debug, Clone)]
+pub struct ClipboardCaptured {
+    pub sent: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum AppEvent {
+    AccessibilityChanged(AccessibilityPrefs),
+    // Id of the anchor block Notion refused; the note went to the end of the page
+    AnchorMissing(String),
+    // The note window's banner changed while it was open
+    CaptureBannerChanged(CaptureBanner),
+    ClipboardCaptured(ClipboardCaptured),
+    ConfigChanged,
+    FileDropped(DroppedFile),
+    // None once the session ended
+    FocusSessionChanged(Option<FocusSession>),
+    HotkeyLatency(u128),
+    NoteProgress(NoteProgress),
+    // Capture id of the note whose blocks were deleted
+    NoteUndone(String),
+    // Path of a file too large to load into the note window
+    OfferImport(String),
+    // A page title arrived after a shortened id was shown for it
+    PageDisplayUpdated(PageDisplay),
+    PageFull(PageFull),
+    PageRotated(PageRotated),
+    PrefillNote(String),
+    // Id of the selected page, which Notion no longer returns (deleted or unshared)
+    SelectedPageMissing(String),
+    // Roughly how many seconds the machine was asleep
+    SystemResumed(u64),
+    TokenInvalidated,
+    WindowHidden(WindowHidden),
+    WindowShown(WindowShown),
+}
+
+impl AppEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AppEvent::AccessibilityChanged(_) => ACCESSIBILITY_CHANGED,
+            AppEvent::AnchorMissing(_) => ANCHOR_MISSING,
+            AppEvent::CaptureBannerChanged(_) => CAPTURE_BANNER_CHANGED,
+            AppEvent::ClipboardCaptured(_) => CLIPBOARD_CAPTURED,
+            AppEvent::ConfigChanged => CONFIG_CHANGED,
+            AppEvent::FileDropped(_) => FILE_DROPPED,
+            AppEvent::FocusSessionChanged(_) => FOCUS_SESSION_CHANGED,
+            AppEvent::HotkeyLatency(_) => HOTKEY_LATENCY,
+            AppEvent::NoteProgress(_) => NOTE_PROGRESS,
+            AppEvent::NoteUndone(_) => NOTE_UNDONE,
+            AppEvent::OfferImport(_) => OFFER_IMPORT,
+            AppEvent::PageDisplayUpdated(_) => PAGE_DISPLAY_UPDATED,
+            AppEvent::PageFull(_) => PAGE_FULL,
+            AppEvent::PageRotated(_) => PAGE_ROTATED,
+            AppEvent::PrefillNote(_) => PREFILL_NOTE,
+            AppEvent::SelectedPageMissing(_) => SELECTED_PAGE_MISSING,
+            AppEvent::SystemResumed(_) => SYSTEM_RESUMED,
+            AppEvent::TokenInvalidated => TOKEN_INVALIDATED,
+            AppEvent::WindowHidden(_) => WINDOW_HIDDEN,
+            AppEvent::WindowShown(_) => WINDOW_SHOWN,
+        }
+    }
+
+    fn payload(&self) -> serde_json::Result<Value> {
+        match self {
+            AppEvent::AccessibilityChanged(prefs) => serde_json::to_value(prefs),
+            AppEvent::CaptureBannerChanged(banner) => serde_json::to_value(banner),
+            AppEvent::ClipboardCaptured(outcome) => serde_json::to_value(outcome),
+            AppEvent::ConfigChanged | AppEvent::TokenInvalidated => Ok(Value::Null),
+            AppEvent::FileDropped(dropped) => serde_json::to_value(dropped),
+            AppEvent::FocusSessionChanged(session) => serde_json::to_value(session),
+            AppEvent::HotkeyLatency(latency_ms) => serde_json::to_value(latency_ms),
+            AppEvent::SystemResumed(slept_secs) => serde_json::to_value(slept_secs),
+            AppEvent::NoteProgress(progress) => serde_json::to_value(progress),
+            AppEvent::AnchorMissing(block_id) => serde_json::to_value(block_id),
+            AppEvent::OfferImport(path) | AppEvent::PrefillNote(path) | AppEvent::SelectedPageMissing(path) => {
+                serde_json::to_value(path)
+            }
+            AppEvent::NoteUndone(capture_id) => serde_json::to_value(capture_id),
+            AppEvent::PageDisplayUpdated(display) => serde_json::to_value(display),
+            AppEvent::PageFull(full) => serde_json::to_value(full),
+            AppEvent::PageRotated(rotated) => serde_json::to_value(rotated),
+            AppEvent::WindowHidden(hidden) => serde_json::to_value(hidden),
+            AppEvent::WindowShown(shown) => serde_json::to_value(shown),
+        }
+    }
+}
+
+// Where an event goes: every window for an AppHandle, just that one for a Window
+pub trait EventTarget {
+    fn emit_payload(&self, name: &str, payload: Value) -> tauri::Result<()>;
+}
+
+impl EventTarget for AppHandle {
+    fn emit_payload(&self, name: &str, payload: Value) -> tauri::Result<()> {
+        self.emit_all(name, payload)
+    }
+}
+
+impl EventTarget for Window {
+    fn emit_payload(&self, name: &str, payload: Value) -> tauri::Result<()> {
+        self.emit(name, payload)
+    }
+}
+
+// The only place events are emitted; failures are logged, never returned
+pub fn emit_app_event(target: &impl EventTarget, event: AppEvent) {
+    let name = event.name();
+    let result = event
+        .payload()
+        .map_err(|e| e.to_string())
+        .and_then(|payload| target.emit_payload(name, payload).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        eprintln!("Failed to emit {}: {}", name, e);
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct EventDescription {
+    pub name: &'static str,
+    // JSON Schema of the payload
+    pub payload: Value,
+    pub description: &'static str,
+}
+
+fn describe(name: &'static str, description: &'static str, payload: Value) -> EventDescription {
+    EventDescription { name, payload, description }
+}
+
+fn object(properties: Value) -> Value {
+    json!({ "type": "object", "properties": properties })
+}
+
+// Hand-maintained next to AppEvent; a new variant needs an entry here too
+pub fn catalog() -> Vec<EventDescription> {
+    let string = json!({ "type": "string" });
+    let boolean = json!({ "type": "boolean" });
+    vec![
+        describe(ACCESSIBILITY_CHANGED, "OS accessibility settings changed", object(json!({
+            "reduced_motion": boolean,
+            "high_contrast": boolean,
+            "screen_reader_mode": boolean,
+        }))),
+        describe(ANCHOR_MISSING, "The anchor block is gone; notes are appended at the end of the page", string.clone()),
+        describe(CAPTURE_BANNER_CHANGED, "Whether a note written now is sent, delayed or queued changed", json!({
+            "oneOf": [
+                object(json!({ "kind": { "const": "none" } })),
+                object(json!({ "kind": { "const": "offline" }, "message": string })),
+                object(json!({ "kind": { "const": "rate_limited" }, "retry_in_secs": { "type": "integer" } })),
+                object(json!({ "kind": { "const": "sync_paused" } })),
+            ]
+        })),
+        describe(CLIPBOARD_CAPTURED, "The clipboard hotkey sent a note, or failed to", object(json!({
+            "sent": boolean,
+            "error": { "type": ["string", "null"] },
+        }))),
+        describe(CONFIG_CHANGED, "Settings were saved; re-read them", json!({ "type": "null" })),
+        describe(FILE_DROPPED, "A file was dropped on the note window", json!({
+            "oneOf": [
+                object(json!({ "kind": { "const": "text" }, "path": string, "text": string, "lossy": boolean })),
+                object(json!({ "kind": { "const": "import_offer" }, "path": string, "size_bytes": { "type": "integer" } })),
+            ]
+        })),
+        describe(FOCUS_SESSION_CHANGED, "A focus session started or ended", json!({
+            "oneOf": [
+                { "type": "null" },
+                object(json!({ "label": string, "started_at": string, "ends_at": string })),
+            ]
+        })),
+        describe(HOTKEY_LATENCY, "Milliseconds from hotkey press to note window focus", json!({ "type": "integer" })),
+        describe(NOTE_PROGRESS, "Where a capture is on its way to Notion", json!({
+            "oneOf": [
+                object(json!({
+                    "stage": { "const": "queued" },
+                    "id": string,
+                    "position": { "type": "integer" },
+                    "estimated_delivery_at": { "type": ["string", "null"] },
+                    "estimate": { "enum": ["estimated", "unknown"] },
+                })),
+                object(json!({
+                    "stage": { "const": "retrying" },
+                    "id": string,
+                    "attempt": { "type": "integer" },
+                    "max_attempts": { "type": "integer" },
+                    "retry_in_ms": { "type": "integer" },
+                    "reason": { "enum": ["conflict", "server_error", "connection_lost", "unreachable"] },
+                })),
+            ]
+        })),
+        describe(NOTE_UNDONE, "The last note was removed from Notion again", string.clone()),
+        describe(OFFER_IMPORT, "A dropped file is too large to load as one note", string.clone()),
+        describe(PAGE_DISPLAY_UPDATED, "A page's title was looked up; relabel it wherever its id is shown", object(json!({
+            "page_id": string,
+            "title": string,
+            "icon": { "type": ["string", "null"] },
+            "resolved": boolean,
+        }))),
+        describe(PAGE_FULL, "A note was refused because its page is full; code is always PAGE_FULL", object(json!({
+            "code": string,
+            "message": string,
+            "details": { "type": ["string", "null"] },
+            "recovery": string,
+            "severity": string,
+            "page_id": string,
+            "page_title": string,
+        }))),
+        describe(PAGE_ROTATED, "The selected page was full and capture moved to a new one", object(json!({
+            "from": string,
+            "to": string,
+            "title": string,
+        }))),
+        describe(PREFILL_NOTE, "Text to load into the note input", string.clone()),
+        describe(SELECTED_PAGE_MISSING, "The selected page was deleted or unshared; ask for a new target", string.clone()),
+        describe(SYSTEM_RESUMED, "The machine woke from sleep; re-check connectivity", json!({ "type": "integer" })),
+        describe(TOKEN_INVALIDATED, "Notion revoked the API token", json!({ "type": "null" })),
+        describe(WINDOW_HIDDEN, "A window was hidden", object(json!({
+            "kind": { "enum": ["note", "settings"] },
+            "reason": { "enum": ["user-dismiss", "blur", "capture-complete", "app-hide"] },
+        }))),
+        describe(WINDOW_SHOWN, "A window was shown", object(json!({
+            "kind": { "enum": ["note", "settings"] },
+            "trigger": { "enum": ["hotkey", "tray", "command", "uri"] },
+            "had_draft": boolean,
+            "preloaded": boolean,
+        }))),
+    ]
+}
+
+// How many lifecycle events diagnostics keep
+const LIFECYCLE_LOG_LEN: usize = 20;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowKind {
+    Note,
+    Settings,
+}
+
+// What asked for the window
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ShowTrigger {
+    Hotkey,
+    Tray,
+    // A command invoked by the frontend
+    Command,
+    Uri,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum HideReason {
+    UserDismiss,
+    Blur,
+    CaptureComplete,
+    // Hidden by the app itself, e.g. to make way for another window
+    AppHide,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct WindowShown {
+    pub kind: WindowKind,
+    pub trigger: ShowTrigger,
+    // The note window held unsent text when it was shown
+    pub had_draft: bool,
+    // The window already existed (preloaded or kept alive) rather than being built for this show
+    pub preloaded: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct WindowHidden {
+    pub kind: WindowKind,
+    pub reason: HideReason,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum LifecycleEvent {
+    WindowShown(WindowShown),
+    WindowHidden(WindowHidden),
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct LoggedLifecycleEvent {
+    pub at: String,
+    #[serde(flatten)]
+    pub event: LifecycleEvent,
+}
+
+lazy_static::lazy_static! {
+    static ref LIFECYCLE_LOG: Mutex<VecDeque<LoggedLifecycleEvent>> = Mutex::new(VecDeque::new());
+}
+
+// Whether the note window currently holds unsent text, as reported by the frontend
+static NOTE_HAS_DRAFT: AtomicBool = AtomicBool::new(false);
+
+lazy_static::lazy_static! {
+    // The unsent text itself, so a crashed note window can be rebuilt with it
+    static ref NOTE_DRAFT: Mutex<Option<String>> = Mutex::new(None);
+}
+
+fn log_event(event: LifecycleEvent) {
+    let mut log = LIFECYCLE_LOG.lock().unwrap();
+    if log.len() == LIFECYCLE_LOG_LEN {
+        log.pop_front();
+    }
+    log.push_back(LoggedLifecycleEvent {
+        at: chrono::Utc::now().to_rfc3339(),
+        event,
+    });
+}
+
+pub fn emit_window_shown(target: &impl EventTarget, kind: WindowKind, trigger: ShowTrigger, preloaded: bool) {
+    let payload = WindowShown {
+        kind,
+        trigger,
+        had_draft: kind == WindowKind::Note && NOTE_HAS_DRAFT.load(Ordering::Relaxed),
+        preloaded,
+    };
+    log_event(LifecycleEvent::WindowShown(payload.clone()));
+    emit_app_event(target, AppEvent::WindowShown(payload));
+}
+
+pub fn emit_window_hidden(target: &impl EventTarget, kind: WindowKind, reason: HideReason) {
+    let payload = WindowHidden { kind, reason };
+    log_event(LifecycleEvent::WindowHidden(payload.clone()));
+    emit_app_event(target, AppEvent::WindowHidden(payload));
+}
+
+// Oldest first
+pub fn lifecycle_log() -> Vec<LoggedLifecycleEvent> {
+    LIFECYCLE_LOG.lock().unwrap().iter().cloned().collect()
+}
+
+pub fn clear_lifecycle_log() {
+    LIFECYCLE_LOG.lock().unwrap().clear();
+}
+
+pub fn note_draft() -> Option<String> {
+    NOTE_DRAFT.lock().unwrap().clone()
+}
+
+// The note window reports when its input goes from empty to non-empty and back,
+// and (debounced) the text itself
+#[tauri::command]
+pub fn report_note_draft(has_draft: bool, text: Option<String>) {
+    NOTE_HAS_DRAFT.store(has_draft, Ordering::Relaxed);
+    *NOTE_DRAFT.lock().unwrap() = if has_draft { text } else { None };
+}
+
+// Every event name and payload schema, so dev builds can check their listeners
+#[tauri::command]
+pub fn get_event_catalog() -> Vec<EventDescription> {
+    catalog()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+    use std::path::{Path, PathBuf};
+
+    // The catalog as last reviewed; rewrite it with UPDATE_SNAPSHOTS=1 after a deliberate change
+    const CATALOG_SNAPSHOT: &str = "snapshots/event_catalog.json";
+
+    fn manifest_dir() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+    }
+
+    // Every file under `dir` with one of `extensions`, with its contents
+    fn sources(dir: &Path, extensions: &[&str]) -> Vec<(PathBuf, String)> {
+        let mut found = Vec::new();
+        for entry in std::fs::read_dir(dir).unwrap().map(Result::unwrap) {
+            let path = entry.path();
+            if path.is_dir() {
+                found.extend(sources(&path, extensions));
+            } else if path.extension().is_some_and(|extension| extensions.iter().any(|wanted| extension == *wanted)) {
+                let source = std::fs::read_to_string(&path).unwrap();
+                found.push((path, source));
+            }
+        }
+        found
+    }
+
+    // Keeps what would have been sent to the windows
+    #[derive(Default)]
+    struct Recorder {
+        sent: std::cell::RefCell<Vec<(String, Value)>>,
+    }
+
+    impl EventTarget for Recorder {
+        fn emit_payload(&self, name: &str, payload: Value) -> tauri::Result<()> {
+            self.sent.borrow_mut().push((name.to_string(), payload));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn the_trigger_is_carried_on_the_show_event() {
+        let windows = Recorder::default();
+        let shows = [
+            (WindowKind::Note, ShowTrigger::Hotkey, true),
+            (WindowKind::Note, ShowTrigger::Uri, false),
+            (WindowKind::Settings, ShowTrigger::Tray, true),
+            (WindowKind::Settings, ShowTrigger::Command, false),
+        ];
+        for (kind, trigger, preloaded) in shows {
+            emit_window_shown(&windows, kind, trigger, preloaded);
+        }
+
+        let sent = windows.sent.borrow();
+        assert!(sent.iter().all(|(name, _)| name == WINDOW_SHOWN));
+        let payloads: Vec<&Value> = sent.iter().map(|(_, payload)| payload).collect();
+        assert_eq!(payloads, [
+            &json!({ "kind": "note", "trigger": "hotkey", "had_draft": false, "preloaded": true }),
+            &json!({ "kind": "note", "trigger": "uri", "had_draft": false, "preloaded": false }),
+            &json!({ "kind": "settings", "trigger": "tray", "had_draft": false, "preloaded": true }),
+            &json!({ "kind": "settings", "trigger": "command", "had_draft": false, "preloaded": false }),
+        ]);
+
+        // Every trigger the catalog documents is one the payload can carry
+        let documented = catalog().into_iter().find(|event| event.name == WINDOW_SHOWN).unwrap();
+        let triggers: Vec<Value> = [ShowTrigger::Hotkey, ShowTrigger::Tray, ShowTrigger::Command, ShowTrigger::Uri]
+            .iter()
+            .map(|trigger| serde_json::to_value(trigger).unwrap())
+            .collect();
+        assert_eq!(documented.payload["properties"]["trigger"]["enum"], Value::Array(triggers));
+    }
+
+    #[test]
+    fn a_draft_only_shows_on_the_note_window_and_hides_carry_their_reason() {
+        let windows = Recorder::default();
+        report_note_draft(true, Some("half a thought".into()));
+        emit_window_shown(&windows, WindowKind::Note, ShowTrigger::Hotkey, true);
+        emit_window_shown(&windows, WindowKind::Settings, ShowTrigger::Hotkey, true);
+        report_note_draft(false, None);
+        emit_window_hidden(&windows, WindowKind::Note, HideReason::CaptureComplete);
+        emit_window_hidden(&windows, WindowKind::Settings, HideReason::AppHide);
+
+        let sent = windows.sent.borrow();
+        assert_eq!((sent[0].1["had_draft"].as_bool(), sent[1].1["had_draft"].as_bool()), (Some(true), Some(false)));
+        assert_eq!(sent[2], (WINDOW_HIDDEN.to_string(), json!({ "kind": "note", "reason": "capture-complete" })));
+        assert_eq!(sent[3], (WINDOW_HIDDEN.to_string(), json!({ "kind": "settings", "reason": "app-hide" })));
+    }
+
+    fn catalog_names() -> BTreeSet<&'static str> {
+        catalog().iter().map(|event| event.name).collect()
+    }
+
+    #[test]
+    fn every_event_name_is_cataloged_once() {
+        let source = std::fs::read_to_string(manifest_dir().join("src/events.rs")).unwrap();
+        let declared: BTreeSet<&str> = source
+            .lines()
+            .filter_map(|line| line.strip_prefix("pub const ")?.split_once(": &str = \"")?.1.strip_suffix("\";"))
+            .collect();
+        assert_eq!(catalog().len(), catalog_names().len(), "an event is described twice");
+        assert_eq!(declared, catalog_names());
+    }
+
+    #[test]
+    fn events_are_only_emitted_through_emit_app_event() {
+        let names = catalog_names();
+        for (path, source) in sources(&manifest_dir().join("src"), &["rs"]) {
+            if path.ends_with("events.rs") {
+                continue;
+            }
+            for (index, line) in source.lines().enumerate() {
+                if line.trim_start().starts_with("//") {
+                    continue;
+                }
+                let place = format!("{}:{}", path.display(), index + 1);
+                for call in [".emit(", ".emit_all(", ".emit_to(", ".emit_payload("] {
+                    assert!(!line.contains(call), "{} emits without emit_app_event: {}", place, line.trim());
+                }
+                for name in &names {
+                    assert!(!line.contains(&format!("\"{}\"", name)), "{} spells out the {} event", place, name);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn frontend_listeners_use_cataloged_events() {
+        let names = catalog_names();
+        let mut listened = BTreeSet::new();
+        for (path, source) in sources(&manifest_dir().join("../src"), &["ts", "tsx"]) {
+            for call in source.split("listen").skip(1) {
+                // listen('name', ...) or listen<Payload>('name', ...)
+                let call = call.strip_prefix('<').and_then(|rest| rest.split_once('>')).map_or(call, |(_, rest)| rest);
+                let Some(name) = call.strip_prefix("('").and_then(|rest| rest.split_once('\'')).map(|(name, _)| name) else {
+                    continue;
+                };
+                assert!(names.contains(name), "{} listens for '{}', which the backend never sends", path.display(), name);
+                listened.insert(name.to_string());
+            }
+        }
+        assert!(listened.contains(NOTE_PROGRESS) && listened.contains(PAGE_FULL), "{:?}", listened);
+    }
+
+    #[test]
+    fn catalog_matches_its_snapshot() {
+        let path = manifest_dir().join(CATALOG_SNAPSHOT);
+        let current = serde_json::to_string_pretty(&catalog()).unwrap() + "\n";
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, &current).unwrap();
+        }
+        let snapshot = std::fs::read_to_string(&path).unwrap_or_default();
+        assert!(
+            snapshot == current,
+            "The event catalog changed; check the frontend listeners, then rerun with UPDATE_SNAPSHOTS=1 to accept it"
+        );
+    }
+}
@@ -0,0 +1,213 @@
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, FileDropEvent, Window, WindowEvent};
+
+use crate::events::{emit_app_event, AppEvent};
+
+// Text files dropped onto the note window: small ones are loaded into the
+// input, large ones are offered for import instead of being pasted.
+
+// Files up to this size are loaded inline
+pub const INLINE_LIMIT_BYTES: u64 = 32 * 1024;
+
+const TEXT_EXTENSIONS: [&str; 4] = ["txt", "md", "markdown", "text"];
+
+// How much of a file the binary sniff looks at
+const SNIFF_BYTES: usize = 8 * 1024;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DroppedFile {
+    Text {
+        path: String,
+        text: String,
+        // The file wasn't valid UTF-8/UTF-16 and some characters were replaced
+        lossy: bool,
+    },
+    ImportOffer {
+        path: String,
+        size_bytes: u64,
+    },
+}
+
+// Decoded file text and whether decoding had to replace invalid bytes.
+// Handles UTF-8 with or without a BOM and BOM-marked UTF-16.
+pub fn decode_text(bytes: &[u8]) -> (String, bool) {
+    if let Some(rest) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        return decode_utf8(rest);
+    }
+    let utf16 = |rest: &[u8], from: fn([u8; 2]) -> u16| {
+        let units: Vec<u16> = rest.chunks_exact(2).map(|pair| from([pair[0], pair[1]])).collect();
+        let text = String::from_utf16_lossy(&units);
+        let lossy = !rest.len().is_multiple_of(2) || text.contains('\u{FFFD}');
+        (text, lossy)
+    };
+    if let Some(rest) = bytes.strip_prefix(&[0xFF, 0xFE]) {
+        return utf16(rest, u16::from_le_bytes);
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        return utf16(rest, u16::from_be_bytes);
+    }
+    decode_utf8(bytes)
+}
+
+fn decode_utf8(bytes: &[u8]) -> (String, bool) {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => (text.to_string(), false),
+        Err(_) => (String::from_utf8_lossy(bytes).into_owned(), true),
+    }
+}
+
+// A NUL byte or a high share of control characters near the start means binary.
+// UTF-16 text is full of NULs, so a UTF-16 BOM exempts the file.
+pub fn looks_binary(bytes: &[u8]) -> bool {
+    if bytes.starts_with(&[0xFF, 0xFE]) || bytes.starts_with(&[0xFE, 0xFF]) {
+        return false;
+    }
+    let sample = &bytes[..bytes.len().min(SNIFF_BYTES)];
+    if sample.contains(&0) {
+        return true;
+    }
+    let control = sample
+        .iter()
+        .filter(|byte| byte.is_ascii_control() && !matches!(byte, b'\n' | b'\r' | b'\t' | 0x0C))
+        .count();
+    control * 10 > sample.len()
+}
+
+pub fn has_text_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| TEXT_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+}
+
+// Decide what to do with a dropped file of `size_bytes`; `read` is only called for inline-sized files
+pub fn route_dropped_file(
+    path: &Path,
+    size_bytes: u64,
+    read: impl FnOnce() -> std::io::Result<Vec<u8>>,
+) -> Result<DroppedFile, String> {
+    let display = path.display().to_string();
+    let name = path.file_name().map_or(display.clone(), |name| name.to_string_lossy().into_owned());
+    if !has_text_extension(path) {
+        return Err(format!("{} isn't a text file; only .txt and .md files can be dropped", name));
+    }
+    if size_bytes > INLINE_LIMIT_BYTES {
+        return Ok(DroppedFile::ImportOffer { path: display, size_bytes });
+    }
+
+    let bytes = read().map_err(|e| format!("Couldn't read {}: {}", name, e))?;
+    if looks_binary(&bytes) {
+        return Err(format!("{} doesn't look like text", name));
+    }
+    let (text, lossy) = decode_text(&bytes);
+    Ok(DroppedFile::Text { path: display, text, lossy })
+}
+
+fn handle_drop(app: &AppHandle, window: &Window, paths: &[PathBuf]) {
+    // One file per drop; the input only holds one note
+    let Some(path) = paths.first() else {
+        return;
+    };
+    let size_bytes = fs::metadata(path).map(|metadata| metadata.len()).unwrap_or(0);
+    match route_dropped_file(path, size_bytes, || fs::read(path)) {
+        Ok(dropped) => {
+            if let DroppedFile::ImportOffer { path, .. } = &dropped {
+                emit_app_event(window, AppEvent::OfferImport(path.clone()));
+            }
+            emit_app_event(window, AppEvent::FileDropped(dropped));
+        }
+        Err(e) => crate::notify(app, "Can't use that file", &e),
+    }
+}
+
+// Listen for files dropped onto the note window
+pub fn attach(app: &AppHandle, window: &Window) {
+    let app_handle = app.clone();
+    let target = window.clone();
+    window.on_window_event(move |event| {
+        if let WindowEvent::FileDrop(FileDropEvent::Dropped(paths)) = event {
+            handle_drop(&app_handle, &target, paths);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unread() -> std::io::Result<Vec<u8>> {
+        panic!("large files aren't read")
+    }
+
+    #[test]
+    fn small_files_load_inline_and_large_ones_are_offered_for_import() {
+        let path = Path::new("notes/todo.md");
+        assert_eq!(
+            route_dropped_file(path, 9, || Ok(b"Buy milk\n".to_vec())),
+            Ok(DroppedFile::Text { path: path.display().to_string(), text: "Buy milk\n".into(), lossy: false })
+        );
+        assert!(matches!(
+            route_dropped_file(path, INLINE_LIMIT_BYTES, || Ok(b"x".to_vec())),
+            Ok(DroppedFile::Text { .. })
+        ));
+        assert_eq!(
+            route_dropped_file(path, INLINE_LIMIT_BYTES + 1, unread),
+            Ok(DroppedFile::ImportOffer { path: path.display().to_string(), size_bytes: INLINE_LIMIT_BYTES + 1 })
+        );
+    }
+
+    #[test]
+    fn only_text_files_are_accepted() {
+        assert!(has_text_extension(Path::new("a.txt")));
+        assert!(has_text_extension(Path::new("a.MD")));
+        assert!(has_text_extension(Path::new("a.markdown")));
+        assert!(!has_text_extension(Path::new("a.pdf")));
+        assert!(!has_text_extension(Path::new("README")));
+
+        let error = route_dropped_file(Path::new("scans/receipt.png"), 10, unread).unwrap_err();
+        assert_eq!(error, "receipt.png isn't a text file; only .txt and .md files can be dropped");
+        // A text extension doesn't get binary content through
+        let error = route_dropped_file(Path::new("renamed.txt"), 4, || Ok(vec![0x89, b'P', 0, 0])).unwrap_err();
+        assert_eq!(error, "renamed.txt doesn't look like text");
+        let error = route_dropped_file(Path::new("gone.txt"), 4, || Err(std::io::ErrorKind::NotFound.into())).unwrap_err();
+        assert!(error.starts_with("Couldn't read gone.txt"), "{}", error);
+    }
+
+    #[test]
+    fn utf8_and_utf16_decode_with_or_without_a_bom() {
+        assert_eq!(decode_text("Café".as_bytes()), ("Café".to_string(), false));
+        assert_eq!(decode_text(b"\xEF\xBB\xBFCaf\xC3\xA9"), ("Café".to_string(), false));
+
+        let le: Vec<u8> = [0xFF, 0xFE].into_iter().chain("Hé".encode_utf16().flat_map(u16::to_le_bytes)).collect();
+        assert_eq!(decode_text(&le), ("Hé".to_string(), false));
+        let be: Vec<u8> = [0xFE, 0xFF].into_iter().chain("Hé".encode_utf16().flat_map(u16::to_be_bytes)).collect();
+        assert_eq!(decode_text(&be), ("Hé".to_string(), false));
+        // An odd trailing byte is dropped and flagged
+        assert_eq!(decode_text(&[le.as_slice(), b"x"].concat()), ("Hé".to_string(), true));
+    }
+
+    #[test]
+    fn invalid_utf8_is_replaced_and_flagged() {
+        let (text, lossy) = decode_text(b"Caf\xE9 au lait");
+        assert_eq!(text, "Caf\u{FFFD} au lait");
+        assert!(lossy);
+    }
+
+    #[test]
+    fn binary_sniff() {
+        assert!(!looks_binary(b"Plain text\r\n\twith tabs\x0C"));
+        assert!(!looks_binary(b""));
+        assert!(looks_binary(b"PK\x03\x04\0\0"));
+        // Mostly control characters without a NUL
+        assert!(looks_binary(&[0x01, 0x02, 0x03, b'a', b'b']));
+        // One control character in plenty of text is fine
+        assert!(!looks_binary(b"\x1b[1mbold\x1b[0m and a long line of ordinary text"));
+        // UTF-16 text is full of NULs but has a BOM
+        assert!(!looks_binary(&[0xFF, 0xFE, b'H', 0, b'i', 0]));
+        // Only the start of the file is sniffed
+        let late_nul = [vec![b'a'; SNIFF_BYTES], vec![0]].concat();
+        assert!(!looks_binary(&late_nul));
+    }
+}
@@ -0,0 +1,354 @@
+use chrono::{DateTime, Duration as TimeDelta, Local};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+use crate::config::{app_data_path, AppState};
+use crate::error::AppError;
+use crate::events;
+
+// A time-boxed focus session: while one is active, every capture is suffixed
+// with its label. Kept in AppState and persisted so it survives restarts.
+const FOCUS_FILE: &str = "focus.json";
+
+// How often the focus task refreshes the tray countdown and checks for expiry
+const FOCUS_TICK_INTERVAL: Duration = Duration::from_secs(30);
+
+// Ended and replaced sessions kept for the audit trail
+const MAX_FOCUS_HISTORY: usize = 50;
+
+const MAX_FOCUS_MINUTES: i64 = 12 * 60;
+const MAX_LABEL_CHARS: usize = 100;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FocusSession {
+    pub label: String,
+    // RFC3339
+    pub started_at: String,
+    pub ends_at: String,
+}
+
+impl FocusSession {
+    fn ends_at(&self) -> Option<DateTime<Local>> {
+        DateTime::parse_from_rfc3339(&self.ends_at)
+            .ok()
+            .map(|time| time.with_timezone(&Local))
+    }
+
+    // Active strictly before the deadline; a capture at exactly `ends_at` is outside the session
+    pub fn is_active_at(&self, now: DateTime<Local>) -> bool {
+        self.ends_at().is_some_and(|ends_at| now < ends_at)
+    }
+
+    pub fn remaining_at(&self, now: DateTime<Local>) -> TimeDelta {
+        self.ends_at()
+            .map(|ends_at| (ends_at - now).max(TimeDelta::zero()))
+            .unwrap_or_else(TimeDelta::zero)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FocusEndReason {
+    Expired,
+    Ended,
+    Replaced,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FocusAuditEntry {
+    #[serde(flatten)]
+    pub session: FocusSession,
+    pub ended_at: String,
+    pub reason: FocusEndReason,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct FocusState {
+    pub active: Option<FocusSession>,
+    pub history: Vec<FocusAuditEntry>,
+}
+
+impl FocusState {
+    // Close the active session, if any, recording why
+    fn end(&mut self, reason: FocusEndReason, now: DateTime<Local>) -> Option<FocusSession> {
+        let session = self.active.take()?;
+        self.history.push(FocusAuditEntry {
+            session: session.clone(),
+            ended_at: now.to_rfc3339(),
+            reason,
+        });
+        if self.history.len() > MAX_FOCUS_HISTORY {
+            self.history.drain(..self.history.len() - MAX_FOCUS_HISTORY);
+        }
+        Some(session)
+    }
+}
+
+pub fn load_focus_state() -> FocusState {
+    let Ok(path) = app_data_path(FOCUS_FILE) else {
+        return FocusState::default();
+    };
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_focus_state(state: &FocusState) -> Result<(), String> {
+    let path = app_data_path(FOCUS_FILE)?;
+    let contents = serde_json::to_string_pretty(state)
+        .map_err(|e| format!("Failed to serialize focus session: {}", e))?;
+    fs::write(&path, contents).map_err(|e| format!("Failed to write focus session: {}", e))
+}
+
+fn modify_focus<T>(app: &AppHandle, change: impl FnOnce(&mut FocusState) -> T) -> Result<T, String> {
+    let state = app.state::<AppState>();
+    let mut focus = state.focus.lock().unwrap();
+    let result = change(&mut focus);
+    save_focus_state(&focus)?;
+    Ok(result)
+}
+
+// Forget ended sessions; the active one, if any, keeps running
+pub fn clear_history(app: &AppHandle) -> Result<usize, String> {
+    modify_focus(app, |focus| std::mem::take(&mut focus.history).len())
+}
+
+// The label to attach to a capture made at `now`. Captures ask once, with
+// their own timestamp, so a note never gets a label the tray said had ended.
+pub fn active_label(app: &AppHandle, now: DateTime<Local>) -> Option<String> {
+    let state = app.state::<AppState>();
+    let focus = state.focus.lock().unwrap();
+    focus.active
+        .as_ref()
+        .filter(|session| session.is_active_at(now))
+        .map(|session| session.label.clone())
+}
+
+// Tray tooltip text for the active session, e.g. "Focus: API refactor (12m left)"
+pub fn tray_status(app: &AppHandle) -> Option<String> {
+    let state = app.state::<AppState>();
+    let focus = state.focus.lock().unwrap();
+    let now = Local::now();
+    let session = focus.active.as_ref().filter(|session| session.is_active_at(now))?;
+    let minutes = (session.remaining_at(now).num_seconds() + 59) / 60;
+    Some(format!("Focus: {} ({}m left)", session.label, minutes))
+}
+
+// End the session once its deadline has passed; returns the session that expired
+fn expire(app: &AppHandle) -> Result<Option<FocusSession>, String> {
+    let now = Local::now();
+    modify_focus(app, |focus| {
+        let expired = focus.active.as_ref().is_some_and(|session| !session.is_active_at(now));
+        if expired {
+            focus.end(FocusEndReason::Expired, now)
+        } else {
+            None
+        }
+    })
+}
+
+// Background task: keep the tray countdown current and end sessions at their deadline
+pub fn start_focus_timer(app: &AppHandle) {
+    let app_handle = app.clone();
+    let registry = app.state::<AppState>().tasks.clone();
+
+    registry.spawn("focus-timer", move |mut token| async move {
+        loop {
+            match expire(&app_handle) {
+                Ok(Some(session)) => {
+                    let notify = app_handle.state::<AppState>().config.lock().unwrap().focus_end_notification;
+                    if notify {
+                        crate::quiet_hours::notify_background(&app_handle, "Focus session over", &format!("\"{}\" has ended", session.label));
+                    }
+                    emit_focus_changed(&app_handle);
+                }
+                Ok(None) => {}
+                Err(e) => eprintln!("Failed to end focus session: {}", e),
+            }
+            crate::refresh_tray(&app_handle);
+
+            tokio::select! {
+                _ = token.cancelled() => break,
+                _ = tokio::time::sleep(FOCUS_TICK_INTERVAL) => {}
+            }
+        }
+    });
+}
+
+fn emit_focus_changed(app: &AppHandle) {
+    let active = app.state::<AppState>().focus.lock().unwrap().active.clone();
+    events::emit_app_event(app, events::AppEvent::FocusSessionChanged(active));
+}
+
+fn validate_session(label: &str, duration_minutes: i64) -> Result<String, AppError> {
+    let label = label.trim();
+    if label.is_empty() {
+        return Err(AppError::ValidationError("Focus sessions need a label".into()));
+    }
+    if label.chars().count() > MAX_LABEL_CHARS {
+        return Err(AppError::ValidationError(format!(
+            "Focus labels can be at most {} characters",
+            MAX_LABEL_CHARS
+        )));
+    }
+    if !(1..=MAX_FOCUS_MINUTES).contains(&duration_minutes) {
+        return Err(AppError::ValidationError(format!(
+            "Focus sessions last between 1 and {} minutes",
+            MAX_FOCUS_MINUTES
+        )));
+    }
+    Ok(label.to_string())
+}
+
+// Start a session, replacing (and recording) any active one
+#[tauri::command]
+pub fn start_focus_session(label: String, duration_minutes: i64, app: AppHandle) -> Result<FocusSession, String> {
+    let label = validate_session(&label, duration_minutes).map_err(|e| e.to_string())?;
+    let now = Local::now();
+    let session = FocusSession {
+        label,
+        started_at: now.to_rfc3339(),
+        ends_at: (now + TimeDelta::minutes(duration_minutes)).to_rfc3339(),
+    };
+
+    modify_focus(&app, |focus| {
+        focus.end(FocusEndReason::Replaced, now);
+        focus.active = Some(session.clone());
+    })?;
+    crate::refresh_tray(&app);
+    emit_focus_changed(&app);
+    Ok(session)
+}
+
+#[tauri::command]
+pub fn end_focus_session(app: AppHandle) -> Result<Option<FocusSession>, String> {
+    let ended = modify_focus(&app, |focus| focus.end(FocusEndReason::Ended, Local::now()))?;
+    crate::refresh_tray(&app);
+    emit_focus_changed(&app);
+    Ok(ended)
+}
+
+// The active session, if it hasn't reached its deadline
+#[tauri::command]
+pub fn get_focus_session(state: State<'_, AppState>) -> Option<FocusSession> {
+    let focus = state.focus.lock().unwrap();
+    focus.active.clone().filter(|session| session.is_active_at(Local::now()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::{self, NoteLayout, NoteStyle};
+    use chrono::TimeZone;
+
+    fn at(hour: u32, minute: u32, second: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 3, 5, hour, minute, second).unwrap()
+    }
+
+    fn session(label: &str, started_at: DateTime<Local>, minutes: i64) -> FocusSession {
+        FocusSession {
+            label: label.to_string(),
+            started_at: started_at.to_rfc3339(),
+            ends_at: (started_at + TimeDelta::minutes(minutes)).to_rfc3339(),
+        }
+    }
+
+    #[test]
+    fn a_session_ends_exactly_at_its_deadline() {
+        let session = session("API refactor", at(9, 0, 0), 25);
+        assert!(session.is_active_at(at(9, 0, 0)));
+        assert!(session.is_active_at(at(9, 24, 59)));
+        assert!(!session.is_active_at(at(9, 25, 0)));
+        assert!(!session.is_active_at(at(10, 0, 0)));
+
+        assert_eq!(session.remaining_at(at(9, 13, 0)), TimeDelta::minutes(12));
+        assert_eq!(session.remaining_at(at(9, 25, 0)), TimeDelta::zero());
+        assert_eq!(session.remaining_at(at(11, 0, 0)), TimeDelta::zero());
+
+        let unreadable = FocusSession { ends_at: "soon".to_string(), ..session };
+        assert!(!unreadable.is_active_at(at(9, 0, 0)));
+    }
+
+    #[test]
+    fn ending_a_session_records_why() {
+        let mut focus = FocusState::default();
+        assert_eq!(focus.end(FocusEndReason::Ended, at(9, 0, 0)), None);
+        assert!(focus.history.is_empty());
+
+        focus.active = Some(session("Deep work", at(9, 0, 0), 50));
+        // Starting another session replaces this one
+        let replaced = focus.end(FocusEndReason::Replaced, at(9, 10, 0)).unwrap();
+        focus.active = Some(session("Email", at(9, 10, 0), 15));
+        assert_eq!(replaced.label, "Deep work");
+        assert_eq!(focus.active.as_ref().unwrap().label, "Email");
+        assert_eq!(focus.history.len(), 1);
+        assert_eq!(focus.history[0].reason, FocusEndReason::Replaced);
+        assert_eq!(focus.history[0].ended_at, at(9, 10, 0).to_rfc3339());
+
+        focus.end(FocusEndReason::Expired, at(9, 25, 0));
+        assert_eq!(focus.active, None);
+        assert_eq!(focus.history.iter().map(|entry| entry.reason).collect::<Vec<_>>(), vec![FocusEndReason::Replaced, FocusEndReason::Expired]);
+    }
+
+    #[test]
+    fn the_audit_trail_keeps_the_latest_sessions() {
+        let mut focus = FocusState::default();
+        for index in 0..MAX_FOCUS_HISTORY + 5 {
+            focus.active = Some(session(&format!("session {}", index), at(9, 0, 0), 25));
+            focus.end(FocusEndReason::Ended, at(9, 30, 0));
+        }
+        assert_eq!(focus.history.len(), MAX_FOCUS_HISTORY);
+        assert_eq!(focus.history[0].session.label, "session 5");
+    }
+
+    #[test]
+    fn sessions_survive_a_restart() {
+        let _data_dir = crate::config::use_test_data_dir();
+        assert!(load_focus_state().active.is_none());
+
+        let mut focus = FocusState { active: Some(session("API refactor", at(9, 0, 0), 25)), history: Vec::new() };
+        focus.end(FocusEndReason::Replaced, at(9, 5, 0));
+        focus.active = Some(session("Review", at(9, 5, 0), 30));
+        save_focus_state(&focus).unwrap();
+
+        let loaded = load_focus_state();
+        assert_eq!(loaded.active, focus.active);
+        assert_eq!(loaded.history.len(), 1);
+        assert_eq!(loaded.history[0].session.label, "API refactor");
+    }
+
+    #[test]
+    fn labels_and_durations_are_checked() {
+        assert_eq!(validate_session("  Deep work ", 25).unwrap(), "Deep work");
+        assert!(validate_session("   ", 25).is_err());
+        assert!(validate_session(&"x".repeat(MAX_LABEL_CHARS + 1), 25).is_err());
+        assert!(validate_session("Deep work", 0).is_err());
+        assert!(validate_session("Deep work", MAX_FOCUS_MINUTES).is_ok());
+        assert!(validate_session("Deep work", MAX_FOCUS_MINUTES + 1).is_err());
+    }
+
+    #[test]
+    fn the_label_follows_the_note_as_a_dimmed_suffix() {
+        assert_eq!(blocks::format_focus_suffix(" Deep work: API refactor "), " [Deep work: API refactor]");
+
+        let children = blocks::note_children("ship it", "[09:00]", NoteLayout::TimestampPrefix, NoteStyle::default(), false, Some("Deep work"));
+        assert_eq!(children.len(), 1);
+        let runs = children[0]["paragraph"]["rich_text"].as_array().unwrap();
+        let suffix = runs.last().unwrap();
+        assert_eq!(suffix["text"]["content"], " [Deep work]");
+        assert_eq!(suffix["annotations"]["color"], "gray");
+
+        // A task keeps its text; the label goes in a paragraph after it
+        let children = blocks::note_children("[ ] ship it", "[09:00]", NoteLayout::TimestampPrefix, NoteStyle::default(), true, Some("Deep work"));
+        let last = children.last().unwrap();
+        assert_eq!(last["type"], "paragraph");
+        assert_eq!(last["paragraph"]["rich_text"][0]["text"]["content"], " [Deep work]");
+
+        let children = blocks::note_children("ship it", "[09:00]", NoteLayout::TimestampPrefix, NoteStyle::default(), false, None);
+        assert_eq!(children, blocks::build_children("[09:00]", "ship it", NoteLayout::TimestampPrefix, NoteStyle::default(), false));
+    }
+}
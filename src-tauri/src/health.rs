@@ -0,0 +1,327 @@
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+use crate::config::AppState;
+use crate::events;
+
+// Self-healing for long uptimes: a webview whose renderer died shows a blank
+// window, and an Explorer restart on Windows drops the tray icon. Each window
+// is pinged through IPC once a minute and rebuilt if it doesn't answer; the
+// tray is rebuilt when the taskbar is recreated.
+
+const PROBE_INTERVAL: Duration = Duration::from_secs(60);
+
+// How long a window gets to answer a ping
+const PONG_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Recovery actions kept for diagnostics
+const RECOVERY_LOG_LEN: usize = 20;
+
+// Windows that are probed; others (e.g. About) are short-lived
+const PROBED_WINDOWS: [&str; 2] = ["main", "settings"];
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoveryKind {
+    WebviewRecreated,
+    TrayRestored,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RecoveryAction {
+    pub at: String,
+    pub kind: RecoveryKind,
+    pub target: String,
+}
+
+// Totals since startup, plus the most recent actions
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct HealthStats {
+    pub webview_recoveries: usize,
+    pub tray_recoveries: usize,
+    pub recent: Vec<RecoveryAction>,
+}
+
+lazy_static::lazy_static! {
+    static ref PONGS: Mutex<HashMap<String, Instant>> = Mutex::new(HashMap::new());
+    static ref RECOVERIES: Mutex<VecDeque<RecoveryAction>> = Mutex::new(VecDeque::new());
+    // Draft saved from a dead note window, handed to its replacement once that is ready
+    static ref RESTORED_DRAFT: Mutex<Option<String>> = Mutex::new(None);
+}
+
+static CAPTURES_IN_PROGRESS: AtomicUsize = AtomicUsize::new(0);
+static WEBVIEW_RECOVERIES: AtomicUsize = AtomicUsize::new(0);
+static TRAY_RECOVERIES: AtomicUsize = AtomicUsize::new(0);
+
+// Held for the duration of a capture; the probe skips its run while any is held
+pub struct CaptureGuard;
+
+impl Drop for CaptureGuard {
+    fn drop(&mut self) {
+        CAPTURES_IN_PROGRESS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub fn capture_started() -> CaptureGuard {
+    CAPTURES_IN_PROGRESS.fetch_add(1, Ordering::SeqCst);
+    CaptureGuard
+}
+
+// What the probe needs from a window host, so recovery can be driven without real webviews
+pub trait WindowHost {
+    fn exists(&self, label: &str) -> bool;
+    // Ask the window to answer with `webview_pong`
+    fn ping(&self, label: &str) -> Result<(), String>;
+    fn answered_since(&self, label: &str, since: Instant) -> bool;
+    // The unsent note text last reported by the window
+    fn draft(&self, label: &str) -> Option<String>;
+    fn recreate(&self, label: &str) -> Result<(), String>;
+}
+
+impl WindowHost for AppHandle {
+    fn exists(&self, label: &str) -> bool {
+        self.get_window(label).is_some()
+    }
+
+    fn ping(&self, label: &str) -> Result<(), String> {
+        let window = self.get_window(label).ok_or("Window is gone")?;
+        window
+            .eval(&format!("window.__TAURI_INVOKE__('webview_pong', {{ label: '{}' }})", label))
+            .map_err(|e| e.to_string())
+    }
+
+    fn answered_since(&self, label: &str, since: Instant) -> bool {
+        PONGS.lock().unwrap().get(label).is_some_and(|at| *at >= since)
+    }
+
+    fn draft(&self, label: &str) -> Option<String> {
+        (label == "main").then(crate::events::note_draft).flatten()
+    }
+
+    fn recreate(&self, label: &str) -> Result<(), String> {
+        let window = self.get_window(label).ok_or("Window is gone")?;
+        let visible = window.is_visible().unwrap_or(false);
+        window.close().map_err(|e| e.to_string())?;
+        match label {
+            "main" if visible => crate::show_note_input(self.clone(), crate::events::ShowTrigger::Command),
+            "main" => crate::preload_note_window(self),
+            "settings" if visible => crate::show_settings(self.clone(), crate::events::ShowTrigger::Command),
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+fn log_recovery(kind: RecoveryKind, target: &str) {
+    eprintln!("Recovered {}: {:?}", target, kind);
+    match kind {
+        RecoveryKind::WebviewRecreated => WEBVIEW_RECOVERIES.fetch_add(1, Ordering::Relaxed),
+        RecoveryKind::TrayRestored => TRAY_RECOVERIES.fetch_add(1, Ordering::Relaxed),
+    };
+    let mut log = RECOVERIES.lock().unwrap();
+    if log.len() == RECOVERY_LOG_LEN {
+        log.pop_front();
+    }
+    log.push_back(RecoveryAction {
+        at: chrono::Utc::now().to_rfc3339(),
+        kind,
+        target: target.to_string(),
+    });
+}
+
+// Ping each window, wait for answers, and rebuild the ones that stayed silent,
+// keeping the note window's draft for its replacement. Returns the rebuilt labels.
+pub async fn probe_windows(host: &impl WindowHost, labels: &[&str], timeout: Duration) -> Vec<String> {
+    let started = Instant::now();
+    let pinged: Vec<&str> = labels
+        .iter()
+        .copied()
+        .filter(|label| host.exists(label))
+        .filter(|label| host.ping(label).is_ok())
+        .collect();
+    tokio::time::sleep(timeout).await;
+
+    let mut recreated = Vec::new();
+    for label in pinged {
+        if host.answered_since(label, started) {
+            continue;
+        }
+        if let Some(draft) = host.draft(label).filter(|draft| !draft.trim().is_empty()) {
+            *RESTORED_DRAFT.lock().unwrap() = Some(draft);
+        }
+        match host.recreate(label) {
+            Ok(()) => {
+                log_recovery(RecoveryKind::WebviewRecreated, label);
+                recreated.push(label.to_string());
+            }
+            Err(e) => eprintln!("Failed to recreate unresponsive window {}: {}", label, e),
+        }
+    }
+    recreated
+}
+
+// Background task: probe the windows once a minute, skipping runs during a capture
+pub fn start_health_probe(app: &AppHandle) {
+    let app_handle = app.clone();
+    let registry = app.state::<AppState>().tasks.clone();
+
+    registry.spawn("health-probe", move |mut token| async move {
+        loop {
+            tokio::select! {
+                _ = token.cancelled() => break,
+                _ = tokio::time::sleep(PROBE_INTERVAL) => {}
+            }
+            if CAPTURES_IN_PROGRESS.load(Ordering::SeqCst) > 0 {
+                continue;
+            }
+            probe_windows(&app_handle, &PROBED_WINDOWS, PONG_TIMEOUT).await;
+        }
+    });
+
+    let app_handle = app.clone();
+    crate::platform::watch_taskbar_created(move || restore_tray(&app_handle));
+}
+
+// Rebuild the tray menu and tooltip after the taskbar came back. The icon
+// itself is re-added by the windowing layer on the same broadcast.
+pub fn restore_tray(app: &AppHandle) {
+    if let Err(e) = app.tray_handle().set_menu(crate::tray_menu()) {
+        eprintln!("Failed to rebuild tray menu: {}", e);
+    }
+    crate::refresh_tray(app);
+    log_recovery(RecoveryKind::TrayRestored, "tray");
+}
+
+// Give a rebuilt note window the draft its predecessor held
+pub fn deliver_restored_draft(app: &AppHandle) {
+    let Some(draft) = RESTORED_DRAFT.lock().unwrap().take() else {
+        return;
+    };
+    if let Some(window) = app.get_window("main") {
+        events::emit_app_event(&window, events::AppEvent::PrefillNote(draft));
+    }
+}
+
+pub fn stats() -> HealthStats {
+    HealthStats {
+        webview_recoveries: WEBVIEW_RECOVERIES.load(Ordering::Relaxed),
+        tray_recoveries: TRAY_RECOVERIES.load(Ordering::Relaxed),
+        recent: RECOVERIES.lock().unwrap().iter().cloned().collect(),
+    }
+}
+
+// Answer to the probe's ping
+#[tauri::command]
+pub fn webview_pong(label: String) {
+    PONGS.lock().unwrap().insert(label, Instant::now());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    // A window host whose windows answer, stay silent or are missing as told
+    #[derive(Default)]
+    struct FakeHost {
+        open: Vec<&'static str>,
+        answering: Vec<&'static str>,
+        drafts: HashMap<&'static str, &'static str>,
+        broken: Vec<&'static str>,
+        pinged: RefCell<Vec<String>>,
+        recreated: RefCell<Vec<String>>,
+    }
+
+    impl WindowHost for FakeHost {
+        fn exists(&self, label: &str) -> bool {
+            self.open.contains(&label)
+        }
+
+        fn ping(&self, label: &str) -> Result<(), String> {
+            self.pinged.borrow_mut().push(label.to_string());
+            Ok(())
+        }
+
+        fn answered_since(&self, label: &str, _since: Instant) -> bool {
+            self.answering.contains(&label)
+        }
+
+        fn draft(&self, label: &str) -> Option<String> {
+            self.drafts.get(label).map(|draft| draft.to_string())
+        }
+
+        fn recreate(&self, label: &str) -> Result<(), String> {
+            if self.broken.contains(&label) {
+                return Err("Window is gone".into());
+            }
+            self.recreated.borrow_mut().push(label.to_string());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn a_silent_window_is_recreated_with_its_draft_kept() {
+        let before = stats().webview_recoveries;
+        let host = FakeHost {
+            open: vec!["main", "settings"],
+            answering: vec!["settings"],
+            drafts: HashMap::from([("main", "Half-written note")]),
+            ..FakeHost::default()
+        };
+
+        let recreated = probe_windows(&host, &PROBED_WINDOWS, Duration::ZERO).await;
+        assert_eq!(recreated, vec!["main"]);
+        assert_eq!(*host.recreated.borrow(), vec!["main"]);
+        assert_eq!(RESTORED_DRAFT.lock().unwrap().take().as_deref(), Some("Half-written note"));
+
+        assert!(stats().webview_recoveries > before);
+    }
+
+    #[tokio::test]
+    async fn answering_and_missing_windows_are_left_alone() {
+        let host = FakeHost {
+            open: vec!["main"],
+            answering: vec!["main"],
+            ..FakeHost::default()
+        };
+        assert!(probe_windows(&host, &PROBED_WINDOWS, Duration::ZERO).await.is_empty());
+        // Settings isn't open, so it isn't pinged
+        assert_eq!(*host.pinged.borrow(), vec!["main"]);
+        assert!(host.recreated.borrow().is_empty());
+    }
+
+    #[tokio::test]
+    async fn a_failed_recreate_isnt_reported_as_recovered() {
+        let host = FakeHost {
+            open: vec!["settings"],
+            broken: vec!["settings"],
+            ..FakeHost::default()
+        };
+        assert!(probe_windows(&host, &PROBED_WINDOWS, Duration::ZERO).await.is_empty());
+        assert_eq!(*host.pinged.borrow(), vec!["settings"]);
+    }
+
+    #[test]
+    fn the_recovery_log_keeps_the_latest_actions() {
+        for _ in 0..RECOVERY_LOG_LEN + 5 {
+            log_recovery(RecoveryKind::TrayRestored, "tray");
+        }
+        let stats = stats();
+        assert_eq!(stats.recent.len(), RECOVERY_LOG_LEN);
+        assert!(stats.tray_recoveries >= RECOVERY_LOG_LEN + 5);
+    }
+
+    #[test]
+    fn capture_guards_count_captures_in_progress() {
+        let first = capture_started();
+        let second = capture_started();
+        assert!(CAPTURES_IN_PROGRESS.load(Ordering::SeqCst) >= 2);
+        drop(first);
+        drop(second);
+        assert_eq!(CAPTURES_IN_PROGRESS.load(Ordering::SeqCst), 0);
+    }
+}
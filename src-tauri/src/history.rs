@@ -0,0 +1,687 @@
+use chrono::{DateTime, FixedOffset};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::future::Future;
+use std::io::{BufRead, BufReader};
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, State};
+
+use crate::blocks;
+use crate::capture::api_token_for;
+use crate::config::AppState;
+use crate::notion::{same_page_id, AppendFailure, NotionApiClient};
+use crate::local_crypto;
+use crate::page_display;
+use crate::receipts::{self, DeliveryReceipt, MoveRecord, NoteOrigin};
+
+// Moving a delivered note to another page. The original blocks are read back
+// with their nested children and appended unchanged, so the note keeps its
+// original timestamp text. The originals are only deleted once the copy is
+// confirmed and recorded; if the delete fails, both copies stay and the
+// receipt is flagged `move_incomplete`.
+
+// Nesting levels below a note's own blocks that one append can carry
+const MAX_COPY_DEPTH: usize = 2;
+
+// Re-append a history entry's blocks to `target_page_id`, then delete the originals
+#[tauri::command]
+pub async fn move_note(
+    history_id: String,
+    target_page_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<DeliveryReceipt, String> {
+    let receipt = receipts::find_receipt(&history_id)?
+        .ok_or_else(|| format!("No delivered note with id {}", history_id))?;
+
+    // The note's own workspace may not be the main one
+    let api_token = {
+        let config = state.config.lock().unwrap();
+        api_token_for(&config, receipt.profile.as_deref())?
+    };
+    let client = NotionApiClient::new(api_token)?;
+    let target_title = page_display::display(&app, &target_page_id).title;
+    move_receipt(&client, receipt, &target_page_id, &target_title).await
+}
+
+// A block and everything nested under it, ready to append elsewhere
+fn copy_block<'a>(
+    client: &'a NotionApiClient,
+    block: &'a Value,
+    depth: usize,
+) -> Pin<Box<dyn Future<Output = Result<Value, String>> + Send + 'a>> {
+    Box::pin(async move {
+        let block_id = block["id"].as_str().unwrap_or_default();
+        let mut children = Vec::new();
+        if block["has_children"].as_bool() == Some(true) {
+            if depth == MAX_COPY_DEPTH {
+                return Err(format!("Block {} is nested too deeply to be moved", block_id));
+            }
+            for child in client.nested_children(block_id).await? {
+                children.push(copy_block(client, &child, depth + 1).await?);
+            }
+        }
+        blocks::appendable_block(block, children).ok_or_else(|| format!("Block {} can't be copied", block_id))
+    })
+}
+
+// The move itself, once the note's client and the target's title are known
+pub async fn move_receipt(
+    client: &NotionApiClient,
+    receipt: DeliveryReceipt,
+    target_page_id: &str,
+    target_title: &str,
+) -> Result<DeliveryReceipt, String> {
+    let history_id = receipt.capture_id.clone();
+    if receipt.move_incomplete {
+        return Err("A previous move of this note didn't finish; remove the duplicate in Notion first".into());
+    }
+    if same_page_id(&receipt.page_id, target_page_id) {
+        return Err("The note is already on that page".into());
+    }
+
+    let mut children: Vec<Value> = Vec::new();
+    for block_id in &receipt.block_ids {
+        let block = client.get_block(block_id).await?;
+        if block["archived"].as_bool() == Some(true) || block["in_trash"].as_bool() == Some(true) {
+            return Err("The note was deleted in Notion and can't be moved".into());
+        }
+        children.push(copy_block(client, &block, 0).await?);
+    }
+
+    let (response, partial) = match client.append_children(target_page_id, target_title, &children).await {
+        Ok(response) => (response, false),
+        Err(AppendFailure::Partial(partial)) => (partial.delivered, true),
+        Err(failure) => return Err(failure.into()),
+    };
+
+    // Record the copy before touching the original, flagged until the delete succeeds
+    let original = MoveRecord {
+        page_id: receipt.page_id.clone(),
+        block_ids: receipt.block_ids.clone(),
+        moved_at: chrono::Utc::now().to_rfc3339(),
+    };
+    receipts::update_receipt(&history_id, |receipt| {
+        receipt.page_id = target_page_id.to_string();
+        receipt.block_ids = response.block_ids.clone();
+        receipt.moved_from = Some(original.clone());
+        receipt.move_incomplete = true;
+    })?;
+    if partial {
+        return Err("Only part of the note was copied; the original was kept. Check both pages.".into());
+    }
+
+    for block_id in &original.block_ids {
+        if let Err(e) = client.delete_block(block_id).await {
+            eprintln!("Move of {} left the original in place: {}", history_id, e);
+            return Err(format!(
+                "The note was copied but the original couldn't be removed, so it is on both pages: {}",
+                e
+            ));
+        }
+    }
+
+    receipts::update_receipt(&history_id, |receipt| receipt.move_incomplete = false)
+}
+
+// Searching delivered notes. The receipts file is streamed line by line and
+// matching stops at MAX_MATCHES or SEARCH_TIME_BUDGET, whichever comes first;
+// the result says when it was cut short.
+
+const MAX_MATCHES: usize = 1000;
+const SEARCH_TIME_BUDGET: Duration = Duration::from_millis(500);
+const DEFAULT_PAGE_SIZE: usize = 50;
+const MAX_PAGE_SIZE: usize = 200;
+
+// Regex mode limits. The regex crate never backtracks, so matching is linear;
+// these bound the pattern and the compiled program instead.
+const MAX_PATTERN_LEN: usize = 256;
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryState {
+    Delivered,
+    Partial,
+    Moved,
+    // Copied to a new page but the original is still there
+    MoveIncomplete,
+}
+
+pub fn delivery_state(receipt: &DeliveryReceipt) -> DeliveryState {
+    if receipt.move_incomplete {
+        DeliveryState::MoveIncomplete
+    } else if receipt.partial.is_some() {
+        DeliveryState::Partial
+    } else if receipt.moved_from.is_some() {
+        DeliveryState::Moved
+    } else {
+        DeliveryState::Delivered
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct HistoryFilters {
+    // Treat the query as a regular expression rather than plain text
+    pub regex: bool,
+    // RFC3339 bounds on when the note was captured, inclusive
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub page_id: Option<String>,
+    pub source: Option<NoteOrigin>,
+    pub state: Option<DeliveryState>,
+    // `next_cursor` from the previous page
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
+}
+
+// A match in UTF-16 code units, so the frontend can slice the string directly
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HighlightRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct HistoryMatch {
+    pub capture_id: String,
+    pub page_id: String,
+    // Icon and title of `page_id`, filled in when results are returned
+    pub page_label: String,
+    pub note_text: Option<String>,
+    // RFC3339 time the note was captured
+    pub captured_at: String,
+    pub source: Option<NoteOrigin>,
+    pub state: DeliveryState,
+    pub highlights: Vec<HighlightRange>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct HistorySearchResult {
+    // Newest first
+    pub results: Vec<HistoryMatch>,
+    // Matches found; a lower bound when `truncated`
+    pub total: usize,
+    // Pass back as `cursor` for the next page; notes delivered since don't shift it
+    pub next_cursor: Option<String>,
+    pub truncated: bool,
+    // Notes whose text couldn't be decrypted, so weren't text-searched
+    pub locked: usize,
+}
+
+// The query as a case-insensitive matcher; plain text is escaped first
+pub fn build_matcher(query: &str, regex: bool) -> Result<Option<Regex>, String> {
+    if query.is_empty() {
+        return Ok(None);
+    }
+    if query.len() > MAX_PATTERN_LEN {
+        return Err(format!("Search is limited to {} characters", MAX_PATTERN_LEN));
+    }
+    let pattern = if regex { query.to_string() } else { regex::escape(query) };
+    RegexBuilder::new(&pattern)
+        .case_insensitive(true)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .dfa_size_limit(REGEX_SIZE_LIMIT)
+        .build()
+        .map(Some)
+        .map_err(|e| match e {
+            regex::Error::CompiledTooBig(_) => "That pattern is too complex to search with".to_string(),
+            e => format!("Invalid pattern: {}", e),
+        })
+}
+
+fn utf16_offset(text: &str, byte: usize) -> usize {
+    text[..byte].encode_utf16().count()
+}
+
+pub fn highlight_ranges(matcher: &Regex, text: &str) -> Vec<HighlightRange> {
+    matcher
+        .find_iter(text)
+        .filter(|found| !found.is_empty())
+        .map(|found| HighlightRange {
+            start: utf16_offset(text, found.start()),
+            end: utf16_offset(text, found.end()),
+        })
+        .collect()
+}
+
+fn parse_bound(value: &Option<String>) -> Result<Option<DateTime<FixedOffset>>, String> {
+    value
+        .as_deref()
+        .map(|value| DateTime::parse_from_rfc3339(value).map_err(|_| format!("'{}' isn't an RFC3339 time", value)))
+        .transpose()
+}
+
+pub enum Checked {
+    Match(HistoryMatch),
+    Skip,
+    // The text was needed but couldn't be decrypted
+    Locked,
+}
+
+// A search with its inputs parsed once
+pub struct HistorySearch {
+    matcher: Option<Regex>,
+    from: Option<DateTime<FixedOffset>>,
+    to: Option<DateTime<FixedOffset>>,
+    filters: HistoryFilters,
+}
+
+impl HistorySearch {
+    pub fn new(query: &str, filters: HistoryFilters) -> Result<Self, String> {
+        Ok(HistorySearch {
+            matcher: build_matcher(query.trim(), filters.regex)?,
+            from: parse_bound(&filters.from)?,
+            to: parse_bound(&filters.to)?,
+            filters,
+        })
+    }
+
+    // The receipt as a result if it passes every filter
+    pub fn check(&self, receipt: DeliveryReceipt) -> Checked {
+        let state = delivery_state(&receipt);
+        let captured_at = receipt.captured_at.clone().unwrap_or_else(|| receipt.delivered_at.clone());
+        if let Ok(time) = DateTime::parse_from_rfc3339(&captured_at) {
+            if self.from.is_some_and(|from| time < from) || self.to.is_some_and(|to| time > to) {
+                return Checked::Skip;
+            }
+        }
+        if self.filters.page_id.as_deref().is_some_and(|page_id| !same_page_id(page_id, &receipt.page_id))
+            || self.filters.source.is_some_and(|source| receipt.origin != Some(source))
+            || self.filters.state.is_some_and(|wanted| wanted != state)
+        {
+            return Checked::Skip;
+        }
+
+        let note_text = match local_crypto::open_optional(&receipt.note_text) {
+            Ok(text) => text,
+            Err(_) if self.matcher.is_none() => None,
+            Err(_) => return Checked::Locked,
+        };
+        let highlights = match (&self.matcher, &note_text) {
+            (Some(matcher), Some(text)) => {
+                let highlights = highlight_ranges(matcher, text);
+                if highlights.is_empty() {
+                    return Checked::Skip;
+                }
+                highlights
+            }
+            (Some(_), None) => return Checked::Skip,
+            (None, _) => Vec::new(),
+        };
+
+        Checked::Match(HistoryMatch {
+            capture_id: receipt.capture_id,
+            page_id: receipt.page_id,
+            page_label: String::new(),
+            note_text,
+            captured_at,
+            source: receipt.origin,
+            state,
+            highlights,
+        })
+    }
+}
+
+// Run `search` over receipt lines, oldest first, and return the requested page
+pub fn search_lines(
+    lines: impl Iterator<Item = String>,
+    search: &HistorySearch,
+    deadline: Instant,
+) -> HistorySearchResult {
+    let mut matches = Vec::new();
+    let mut locked = 0;
+    let mut truncated = false;
+    for line in lines {
+        if matches.len() >= MAX_MATCHES || Instant::now() >= deadline {
+            truncated = true;
+            break;
+        }
+        let Ok(receipt) = serde_json::from_str::<DeliveryReceipt>(&line) else {
+            continue;
+        };
+        match search.check(receipt) {
+            Checked::Match(found) => matches.push(found),
+            Checked::Skip => {}
+            Checked::Locked => locked += 1,
+        }
+    }
+
+    let total = matches.len();
+    matches.reverse();
+    let start = match &search.filters.cursor {
+        Some(cursor) => matches
+            .iter()
+            .position(|found| &found.capture_id == cursor)
+            .map_or(total, |index| index + 1),
+        None => 0,
+    };
+    let limit = search.filters.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let results: Vec<HistoryMatch> = matches.into_iter().skip(start).take(limit).collect();
+    let next_cursor = (start + results.len() < total)
+        .then(|| results.last().map(|found| found.capture_id.clone()))
+        .flatten();
+
+    HistorySearchResult { results, total, next_cursor, truncated, locked }
+}
+
+// Delivered notes newest first, `limit` at a time after skipping `offset`.
+// Empty while history is off.
+#[tauri::command]
+pub fn get_note_history(
+    limit: Option<usize>,
+    offset: Option<usize>,
+    app: AppHandle,
+) -> Result<Vec<HistoryMatch>, String> {
+    if !receipts::keeps_note_text() {
+        return Ok(Vec::new());
+    }
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let everything = HistorySearch::new("", HistoryFilters::default())?;
+    let mut entries: Vec<HistoryMatch> = receipts::history_page(offset.unwrap_or(0), limit)?
+        .into_iter()
+        .filter_map(|receipt| match everything.check(receipt) {
+            Checked::Match(entry) => Some(entry),
+            Checked::Skip | Checked::Locked => None,
+        })
+        .collect();
+    for entry in &mut entries {
+        entry.page_label = page_display::display(&app, &entry.page_id).label();
+    }
+    Ok(entries)
+}
+
+// Forget every delivered note kept locally, returning how many there were.
+// Notes already in Notion stay there; they just can't be moved from here anymore.
+#[tauri::command]
+pub fn clear_note_history() -> Result<usize, String> {
+    receipts::clear()
+}
+
+// Search delivered notes by text, with filters, newest first
+#[tauri::command]
+pub fn search_note_history(
+    query: String,
+    filters: Option<HistoryFilters>,
+    app: AppHandle,
+) -> Result<HistorySearchResult, String> {
+    let search = HistorySearch::new(&query, filters.unwrap_or_default())?;
+    let deadline = Instant::now() + SEARCH_TIME_BUDGET;
+
+    let _lock = receipts::read_lock();
+    let path = receipts::receipts_path()?;
+    let mut found = match std::fs::File::open(&path) {
+        Ok(file) => search_lines(BufReader::new(file).lines().map_while(Result::ok), &search, deadline),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => search_lines(std::iter::empty(), &search, deadline),
+        Err(e) => return Err(format!("Failed to read history: {}", e)),
+    };
+    for result in &mut found.results {
+        result.page_label = page_display::display(&app, &result.page_id).label();
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_notion::{FakeWorkspace, MockNotion, MockReply};
+    use serde_json::json;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    fn paragraph(text: &str) -> Value {
+        blocks::paragraph(blocks::text_runs(text, false))
+    }
+
+    // A toggle note with a nested paragraph, itself holding one more
+    fn toggle_note() -> Value {
+        let mut inner = paragraph("details");
+        inner["paragraph"]["children"] = json!([paragraph("more details")]);
+        json!({
+            "object": "block",
+            "type": "toggle",
+            "toggle": { "rich_text": blocks::text_runs("[09:00] trip plan", false), "children": [inner] },
+        })
+    }
+
+    // Deliver `children` to page-1 and record its receipt, as a capture would
+    async fn delivered(client: &NotionApiClient, children: &[Value]) -> DeliveryReceipt {
+        let response = client.append_children("page-1", "Inbox", children).await.unwrap();
+        let receipt = DeliveryReceipt::new(receipts::new_capture_id(), "page-1".into(), response, None);
+        receipts::record_receipt(&receipt).unwrap();
+        receipt
+    }
+
+    fn texts(workspace: &FakeWorkspace, parent: &str) -> Vec<String> {
+        workspace.children(parent).iter().map(blocks::block_text).collect()
+    }
+
+    // Two pages, with deletes refused while `refuse_deletes` is set
+    async fn two_pages(refuse_deletes: Arc<AtomicBool>) -> (Arc<std::sync::Mutex<FakeWorkspace>>, MockNotion, NotionApiClient) {
+        let workspace = FakeWorkspace::shared();
+        workspace.lock().unwrap().add_page("page-1", "Inbox");
+        workspace.lock().unwrap().add_page("page-2", "Trips");
+        let notion = workspace.clone();
+        let mock = MockNotion::start(move |request| {
+            if request.method == "DELETE" && refuse_deletes.load(Ordering::SeqCst) {
+                return MockReply::error(500, "internal_server_error", "Try again later");
+            }
+            notion.lock().unwrap().answer(request)
+        })
+        .await;
+        let client = NotionApiClient::new(mock.token.clone()).unwrap();
+        (workspace, mock, client)
+    }
+
+    #[tokio::test]
+    async fn a_moved_note_keeps_its_nested_blocks() {
+        let _data_dir = crate::config::use_test_data_dir();
+        let (workspace, _mock, client) = two_pages(Arc::new(AtomicBool::new(false))).await;
+        let receipt = delivered(&client, &[toggle_note(), paragraph("[09:01] second line")]).await;
+
+        let moved = move_receipt(&client, receipt.clone(), "page-2", "Trips").await.unwrap();
+
+        let workspace = workspace.lock().unwrap();
+        assert!(workspace.children("page-1").is_empty());
+        assert_eq!(texts(&workspace, "page-2"), ["[09:00] trip plan", "[09:01] second line"]);
+        let toggle = workspace.children("page-2")[0]["id"].as_str().unwrap().to_string();
+        assert_eq!(texts(&workspace, &toggle), ["details"]);
+        let inner = workspace.children(&toggle)[0]["id"].as_str().unwrap().to_string();
+        assert_eq!(texts(&workspace, &inner), ["more details"]);
+
+        assert_eq!(moved.page_id, "page-2");
+        assert!(!moved.move_incomplete);
+        assert_eq!(moved.moved_from.as_ref().unwrap().block_ids, receipt.block_ids);
+        assert_eq!(moved.block_ids, workspace.children("page-2").iter().map(|block| block["id"].as_str().unwrap().to_string()).collect::<Vec<_>>());
+        assert_eq!(receipts::find_receipt(&receipt.capture_id).unwrap().unwrap().page_id, "page-2");
+    }
+
+    #[tokio::test]
+    async fn notes_nested_deeper_than_one_append_carries_are_refused() {
+        let _data_dir = crate::config::use_test_data_dir();
+        let (workspace, mock, client) = two_pages(Arc::new(AtomicBool::new(false))).await;
+        let receipt = delivered(&client, &[toggle_note()]).await;
+        // Nest one more level under "more details", past what a single append takes
+        let deepest = {
+            let workspace = workspace.lock().unwrap();
+            let toggle = workspace.children("page-1")[0]["id"].as_str().unwrap().to_string();
+            let inner = workspace.children(&toggle)[0]["id"].as_str().unwrap().to_string();
+            workspace.children(&inner)[0]["id"].as_str().unwrap().to_string()
+        };
+        client.append_children(&deepest, "", &[paragraph("too deep")]).await.unwrap();
+        let appends_before = mock.count("PATCH", "/v1/blocks/");
+
+        let error = move_receipt(&client, receipt, "page-2", "Trips").await.unwrap_err();
+        assert!(error.contains("nested too deeply"), "{}", error);
+        assert_eq!(mock.count("PATCH", "/v1/blocks/"), appends_before);
+        assert_eq!(texts(&workspace.lock().unwrap(), "page-1"), ["[09:00] trip plan"]);
+    }
+
+    #[tokio::test]
+    async fn a_move_whose_delete_fails_is_left_incomplete() {
+        let _data_dir = crate::config::use_test_data_dir();
+        let refuse_deletes = Arc::new(AtomicBool::new(true));
+        let (workspace, _mock, client) = two_pages(refuse_deletes.clone()).await;
+        let receipt = delivered(&client, &[paragraph("[09:00] call the hotel")]).await;
+
+        let error = move_receipt(&client, receipt.clone(), "page-2", "Trips").await.unwrap_err();
+        assert!(error.contains("on both pages"), "{}", error);
+        {
+            let workspace = workspace.lock().unwrap();
+            assert_eq!(texts(&workspace, "page-1"), ["[09:00] call the hotel"]);
+            assert_eq!(texts(&workspace, "page-2"), ["[09:00] call the hotel"]);
+        }
+        let stored = receipts::find_receipt(&receipt.capture_id).unwrap().unwrap();
+        assert!(stored.move_incomplete);
+        assert_eq!(stored.page_id, "page-2");
+        assert_eq!(delivery_state(&stored), DeliveryState::MoveIncomplete);
+
+        // Moving again would make a third copy, so it waits for the user
+        refuse_deletes.store(false, Ordering::SeqCst);
+        let error = move_receipt(&client, stored, "page-1", "Inbox").await.unwrap_err();
+        assert!(error.contains("didn't finish"), "{}", error);
+        assert_eq!(texts(&workspace.lock().unwrap(), "page-1").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn deleted_notes_and_same_page_moves_are_refused() {
+        let _data_dir = crate::config::use_test_data_dir();
+        let (_workspace, mock, client) = two_pages(Arc::new(AtomicBool::new(false))).await;
+        let receipt = delivered(&client, &[paragraph("[09:00] gone soon")]).await;
+
+        let error = move_receipt(&client, receipt.clone(), "page-1", "Inbox").await.unwrap_err();
+        assert!(error.contains("already on that page"), "{}", error);
+
+        // A note deleted in Notion is never copied
+        client.delete_block(&receipt.block_ids[0]).await.unwrap();
+        assert!(move_receipt(&client, receipt, "page-2", "Trips").await.is_err());
+        assert_eq!(mock.count("PATCH", "/v1/blocks/page-2"), 0);
+    }
+
+    // A receipt as history keeps it, captured at 09:mm on 2026-05-04
+    fn kept(id: &str, page_id: &str, text: Option<&str>, minute: u32, origin: NoteOrigin) -> DeliveryReceipt {
+        let response = crate::notion::AppendResponse { block_ids: vec![format!("{}-block", id)], request_id: None, status: 200, attempts: 1 };
+        let mut receipt = DeliveryReceipt::new(id.into(), page_id.into(), response, None);
+        receipt.captured_at = Some(format!("2026-05-04T09:{:02}:00+03:00", minute));
+        receipt.note_text = text.map(str::to_string);
+        receipt.origin = Some(origin);
+        receipt
+    }
+
+    // History as it is on disk, oldest first
+    fn history() -> Vec<DeliveryReceipt> {
+        let mut partial = kept("n4", "page-2", Some("Pack the TENT"), 4, NoteOrigin::Queue);
+        partial.partial = Some(receipts::PartialReceipt { delivered_chunks: 1, total_chunks: 2, remainder_id: None });
+        let mut moved = kept("n5", "page-1", Some("tent pegs 🏕️ and tent poles"), 5, NoteOrigin::Typed);
+        moved.moved_from = Some(MoveRecord { page_id: "page-2".into(), block_ids: Vec::new(), moved_at: "2026-05-04T10:00:00+03:00".into() });
+        vec![
+            kept("n1", "page-1", Some("Buy milk"), 1, NoteOrigin::Typed),
+            kept("n2", "1c2b8f1e-52d4-80a1", Some("c++ talk at 10"), 2, NoteOrigin::Clipboard),
+            kept("n3", "page-1", None, 3, NoteOrigin::Cli),
+            partial,
+            moved,
+        ]
+    }
+
+    fn lines(receipts: &[DeliveryReceipt]) -> Vec<String> {
+        receipts.iter().map(|receipt| serde_json::to_string(receipt).unwrap()).collect()
+    }
+
+    fn search(query: &str, filters: HistoryFilters, receipts: &[DeliveryReceipt]) -> HistorySearchResult {
+        let search = HistorySearch::new(query, filters).unwrap();
+        search_lines(lines(receipts).into_iter(), &search, Instant::now() + Duration::from_secs(5))
+    }
+
+    fn ids(found: &HistorySearchResult) -> Vec<&str> {
+        found.results.iter().map(|found| found.capture_id.as_str()).collect()
+    }
+
+    #[test]
+    fn search_matches_text_case_insensitively_newest_first() {
+        let found = search("TENT", HistoryFilters::default(), &history());
+        assert_eq!(ids(&found), ["n5", "n4"]);
+        assert_eq!((found.total, found.truncated, found.locked, found.next_cursor.as_deref()), (2, false, 0, None));
+
+        // Ranges are in UTF-16 units: the emoji and its variation selector count as three
+        let ranges: Vec<(usize, usize)> = found.results[0].highlights.iter().map(|range| (range.start, range.end)).collect();
+        assert_eq!(ranges, [(0, 4), (18, 22)]);
+        assert_eq!(found.results[1].highlights, [HighlightRange { start: 9, end: 13 }]);
+
+        // Plain text isn't a pattern; regex mode is
+        assert_eq!(ids(&search("c++", HistoryFilters::default(), &history())), ["n2"]);
+        let regex = HistoryFilters { regex: true, ..Default::default() };
+        assert_eq!(ids(&search(r"^(buy|pack)\b", regex.clone(), &history())), ["n4", "n1"]);
+        assert_eq!(ids(&search("c++", regex, &history())), ["n4", "n2"]);
+
+        // Without a query every note is listed, including one with no text kept
+        let everything = search("  ", HistoryFilters::default(), &history());
+        assert_eq!(ids(&everything), ["n5", "n4", "n3", "n2", "n1"]);
+        assert!(everything.results.iter().all(|found| found.highlights.is_empty()));
+        assert_eq!(everything.results[2].note_text, None);
+    }
+
+    #[test]
+    fn each_filter_narrows_the_search() {
+        let filtered = |filters: HistoryFilters| ids(&search("", filters, &history())).join(",");
+        let from = HistoryFilters { from: Some("2026-05-04T09:02:00+03:00".into()), to: Some("2026-05-04T06:04:00Z".into()), ..Default::default() };
+        assert_eq!(filtered(from), "n4,n3,n2");
+        assert_eq!(filtered(HistoryFilters { page_id: Some("1C2B8F1E52D480A1".into()), ..Default::default() }), "n2");
+        assert_eq!(filtered(HistoryFilters { source: Some(NoteOrigin::Typed), ..Default::default() }), "n5,n1");
+        assert_eq!(filtered(HistoryFilters { state: Some(DeliveryState::Partial), ..Default::default() }), "n4");
+        assert_eq!(filtered(HistoryFilters { state: Some(DeliveryState::Moved), ..Default::default() }), "n5");
+        assert_eq!(filtered(HistoryFilters { state: Some(DeliveryState::Delivered), ..Default::default() }), "n3,n2,n1");
+
+        // Filters combine with the text match
+        let typed = HistoryFilters { source: Some(NoteOrigin::Typed), ..Default::default() };
+        assert_eq!(ids(&search("tent", typed, &history())), ["n5"]);
+        let error = HistorySearch::new("", HistoryFilters { from: Some("yesterday".into()), ..Default::default() }).err().unwrap();
+        assert!(error.contains("isn't an RFC3339 time"), "{}", error);
+    }
+
+    #[test]
+    fn pages_follow_the_cursor_even_as_notes_arrive() {
+        let mut receipts = history();
+        let page = |cursor: Option<&str>, limit: usize, receipts: &[DeliveryReceipt]| {
+            search("", HistoryFilters { cursor: cursor.map(str::to_string), limit: Some(limit), ..Default::default() }, receipts)
+        };
+
+        let first = page(None, 2, &receipts);
+        assert_eq!((ids(&first), first.total, first.next_cursor.as_deref()), (vec!["n5", "n4"], 5, Some("n4")));
+
+        // Newer notes land on top; the next page still starts after n4
+        receipts.push(kept("n6", "page-1", Some("later"), 6, NoteOrigin::Typed));
+        receipts.push(kept("n7", "page-1", Some("later still"), 7, NoteOrigin::Typed));
+        let second = page(Some("n4"), 2, &receipts);
+        assert_eq!((ids(&second), second.total, second.next_cursor.as_deref()), (vec!["n3", "n2"], 7, Some("n2")));
+        let last = page(Some("n2"), 2, &receipts);
+        assert_eq!((ids(&last), last.next_cursor.as_deref()), (vec!["n1"], None));
+
+        // Limits are kept between one and the page size cap; a cursor that's gone lists nothing
+        assert_eq!(page(None, 0, &receipts).results.len(), 1);
+        let many: Vec<DeliveryReceipt> = (0..MAX_PAGE_SIZE + 10).map(|index| kept(&format!("m{}", index), "page-1", Some("x"), 0, NoteOrigin::Typed)).collect();
+        assert_eq!(page(None, MAX_PAGE_SIZE * 2, &many).results.len(), MAX_PAGE_SIZE);
+        assert!(page(Some("forgotten"), 2, &receipts).results.is_empty());
+    }
+
+    #[test]
+    fn costly_patterns_and_searches_are_bounded() {
+        let regex = HistoryFilters { regex: true, ..Default::default() };
+        let error = HistorySearch::new(&"a".repeat(MAX_PATTERN_LEN + 1), HistoryFilters::default()).err().unwrap();
+        assert!(error.contains("limited to 256 characters"), "{}", error);
+        let error = HistorySearch::new(r"(\w{100}){100}", regex.clone()).err().unwrap();
+        assert!(error.contains("too complex"), "{}", error);
+        let error = HistorySearch::new("(unclosed", regex).err().unwrap();
+        assert!(error.starts_with("Invalid pattern"), "{}", error);
+
+        // The scan stops at the match cap, and at the deadline
+        let many: Vec<DeliveryReceipt> = (0..MAX_MATCHES + 5).map(|index| kept(&format!("m{}", index), "page-1", Some("x"), 0, NoteOrigin::Typed)).collect();
+        let capped = search("x", HistoryFilters::default(), &many);
+        assert_eq!((capped.total, capped.truncated), (MAX_MATCHES, true));
+        let everything = HistorySearch::new("", HistoryFilters::default()).unwrap();
+        let late = search_lines(lines(&history()).into_iter(), &everything, Instant::now());
+        assert_eq!((late.total, late.truncated), (0, true));
+    }
+}
@@ -1,52 +1,217 @@
-use tauri::{AppHandle, Manager, GlobalShortcutManager};
+use tauri::{AppHandle, CustomMenuItem, Manager, GlobalShortcutManager, SystemTrayMenu, SystemTrayMenuItem, SystemTraySubmenu};
+use std::sync::Mutex;
+use std::time::Instant;
 
 // Module imports
+pub mod abbreviations;
+pub mod accessibility;
+pub mod anchor;
+pub mod auth;
+pub mod blocks;
+pub mod capture;
+pub mod clock;
+pub mod clipboard;
 pub mod config;
+pub mod daily_pages;
+pub mod database_rows;
+pub mod day_headings;
+pub mod diagnostics;
+pub mod digest;
+pub mod dry_run;
 pub mod notion;
 pub mod error;
+pub mod events;
+pub mod file_drop;
+pub mod focus;
+pub mod health;
+pub mod history;
+pub mod local_crypto;
+pub mod markdown;
+pub mod migrations;
+#[cfg(test)]
+pub mod mock_notion;
+pub mod page_display;
+pub mod page_rotations;
+pub mod platform;
+pub mod profiling;
+pub mod queue;
+pub mod quiet_hours;
+pub mod rate_limit;
+pub mod retry;
+pub mod safe_mode;
+pub mod receipts;
+pub mod reliability;
+pub mod recording;
+pub mod reset;
+pub mod routing;
+pub mod secrets;
+pub mod scratch;
+pub mod settings;
+pub mod settings_schema;
+pub mod spellcheck;
+pub mod setup_captures;
+pub mod stats;
+pub mod suspend;
+pub mod tasks;
+pub mod timestamps;
+pub mod typography;
+pub mod undo;
+pub mod verification;
+pub mod webhook;
+pub mod window_effects;
+
+lazy_static::lazy_static! {
+    // When the global hotkey last fired, consumed by `window_ready`
+    static ref HOTKEY_PRESSED_AT: Mutex<Option<Instant>> = Mutex::new(None);
+    // Most recent hotkey-to-focus latency in milliseconds
+    static ref LAST_HOTKEY_LATENCY_MS: Mutex<Option<u128>> = Mutex::new(None);
+    // Hotkey presses ignored because an excluded app was in front: (count, last app)
+    static ref SWALLOWED_HOTKEY_PRESSES: Mutex<(u64, Option<String>)> = Mutex::new((0, None));
+    // Profile pinned by a per-profile hotkey for the current capture only
+    static ref PINNED_PROFILE: Mutex<Option<String>> = Mutex::new(None);
+    // Accelerators currently registered for capture profiles
+    static ref PROFILE_HOTKEYS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+    // The clipboard hotkey as currently registered
+    static ref CLIPBOARD_HOTKEY: Mutex<Option<String>> = Mutex::new(None);
+}
+
+// The main capture hotkey
+pub const PRIMARY_HOTKEY: &str = "Alt+Q";
 
 // Function to check if settings are configured before showing the note input
 pub fn check_settings_configured(app: &AppHandle) -> bool {
     let state = app.state::<config::AppState>();
     let config = state.config.lock().unwrap();
     
-    // Check if API token and page ID are set
-    !config.notion_api_token.is_empty() && !config.selected_page_id.is_empty()
+    // Check if API token and page ID are set, and Notion hasn't revoked the token
+    !config.secrets.main_token().is_empty() && !config.selected_page_id.is_empty() && !config.token_invalid
+}
+
+// Why settings are opened in place of a capture, for the settings page to explain
+fn setup_reason(app: &AppHandle) -> Option<&'static str> {
+    if auth::is_token_invalid(app) {
+        Some("token_invalid")
+    } else if setup_captures::pending_count(app) > 0 {
+        Some("pending_capture")
+    } else {
+        None
+    }
 }
 
 // Function to show the note input window
-pub fn show_note_input(app: AppHandle) {
+pub fn show_note_input(app: AppHandle, trigger: events::ShowTrigger) {
+    quiet_hours::release_held(&app);
     // Check if settings are configured
     if !check_settings_configured(&app) {
-        // If not configured, show settings window instead
-        show_settings(app);
+        // If not configured, show settings window instead, saying why when the token was revoked
+        show_settings_with_reason(app.clone(), trigger, setup_reason(&app));
         return;
     }
     
-    if let Some(window) = app.get_window("main") {
-        window.show().unwrap();
-        window.set_focus().unwrap();
+    let Some((window, preloaded)) = existing_or_build(app.get_window("main"), || build_note_window(&app)) else {
+        return;
+    };
+    
+    window.show().unwrap();
+    window.set_focus().unwrap();
+    events::emit_window_shown(&app, events::WindowKind::Note, trigger, preloaded);
+}
+
+// Show-only path: the window is kept alive across hides (and preloaded at
+// startup), so one is only built when none exists. True when it already did.
+fn existing_or_build<W, E: std::fmt::Display>(
+    existing: Option<W>,
+    build: impl FnOnce() -> Result<W, E>,
+) -> Option<(W, bool)> {
+    match existing {
+        Some(window) => Some((window, true)),
+        None => match build() {
+            Ok(window) => Some((window, false)),
+            Err(e) => {
+                eprintln!("Failed to create note window: {}", e);
+                None
+            }
+        },
+    }
+}
+
+// Create the note input window hidden; callers decide when to show it
+fn build_note_window(app: &AppHandle) -> tauri::Result<tauri::Window> {
+    let (requested_effect, screen_reader_mode) = {
+        let state = app.state::<config::AppState>();
+        let config = state.config.lock().unwrap();
+        (config.window_effects, config.screen_reader_mode)
+    };
+    // Transparency costs input and compositing work, so only pay for it when an effect will show
+    let transparent = window_effects::effect_for_new_window(requested_effect) != window_effects::WindowEffect::None;
+    
+    let window = tauri::WindowBuilder::new(
+        app,
+        "main", // the unique window label
+        tauri::WindowUrl::App("index.html".into()),
+    )
+    .title(if screen_reader_mode {
+        accessibility::ACCESSIBLE_NOTE_WINDOW_TITLE
     } else {
-        let _ = tauri::WindowBuilder::new(
-            &app,
-            "main", // the unique window label
-            tauri::WindowUrl::App("index.html".into()),
-        )
-        .title("Notion Quick Notes")
-        .resizable(false)
-        .decorations(false)
-        .inner_size(600.0, 80.0) // Extremely wide and very short
-        .min_inner_size(600.0, 80.0) // Force minimum size to be the same
-        .max_inner_size(600.0, 80.0) // Force maximum size to be the same
-        .center()
-        .build();
+        "Notion Quick Notes"
+    })
+    .resizable(false)
+    .decorations(false)
+    .inner_size(600.0, 80.0) // Extremely wide and very short
+    .min_inner_size(600.0, 80.0) // Force minimum size to be the same
+    .max_inner_size(600.0, 80.0) // Force maximum size to be the same
+    .center()
+    .visible(false) // Never flash on screen; show_note_input reveals it
+    .transparent(transparent)
+    .build()?;
+    
+    window_effects::apply(&window, requested_effect);
+    file_drop::attach(app, &window);
+    Ok(window)
+}
+
+// Pre-create the note window during setup to cut hotkey-to-focus latency
+pub fn preload_note_window(app: &AppHandle) {
+    let enabled = {
+        let state = app.state::<config::AppState>();
+        let config = state.config.lock().unwrap();
+        config.preload_note_window
+    };
+    
+    if !enabled || app.get_window("main").is_some() {
+        return;
+    }
+    
+    if let Err(e) = build_note_window(app) {
+        eprintln!("Failed to preload note window: {}", e);
     }
 }
 
+// Called by the frontend once the note window has focus; reports the latency since the hotkey press
+pub fn note_window_ready(app: &AppHandle) -> Option<u128> {
+    health::deliver_restored_draft(app);
+    
+    let pressed_at = HOTKEY_PRESSED_AT.lock().unwrap().take()?;
+    let latency_ms = pressed_at.elapsed().as_millis();
+    
+    *LAST_HOTKEY_LATENCY_MS.lock().unwrap() = Some(latency_ms);
+    println!("Hotkey-to-focus latency: {}ms", latency_ms);
+    
+    events::emit_app_event(app, events::AppEvent::HotkeyLatency(latency_ms));
+    
+    Some(latency_ms)
+}
+
+// Most recently measured hotkey-to-focus latency, for diagnostics
+pub fn last_hotkey_latency_ms() -> Option<u128> {
+    *LAST_HOTKEY_LATENCY_MS.lock().unwrap()
+}
+
 // Function to close the note input window
-pub fn close_note_input(app: AppHandle) {
+pub fn close_note_input(app: AppHandle, reason: events::HideReason) {
     if let Some(window) = app.get_window("main") {
         window.hide().unwrap();
+        events::emit_window_hidden(&app, events::WindowKind::Note, reason);
     }
 }
 
@@ -54,19 +219,28 @@ pub fn close_note_input(app: AppHandle) {
 pub fn close_settings(app: AppHandle) {
     if let Some(window) = app.get_window("settings") {
         window.hide().unwrap();
+        events::emit_window_hidden(&app, events::WindowKind::Settings, events::HideReason::UserDismiss);
     }
 }
 
 // Function to show the settings window
-pub fn show_settings(app: AppHandle) {
+pub fn show_settings(app: AppHandle, trigger: events::ShowTrigger) {
+    show_settings_with_reason(app, trigger, None);
+}
+
+// Show settings; `reason` is passed to the page as a query param so it can explain why it opened
+pub fn show_settings_with_reason(app: AppHandle, trigger: events::ShowTrigger, reason: Option<&str>) {
+    quiet_hours::release_held(&app);
     println!("Attempting to show settings window");
     
+    let reason_param = reason.map(|reason| format!("&reason={}", reason)).unwrap_or_default();
+    
     // Check if a window with this label already exists
     if let Some(existing_window) = app.get_window("settings") {
         println!("Found existing settings window");
         
         // Instead of closing, navigate to a fresh URL with timestamp to reset state
-        let fresh_url = format!("index.html?settings=true&t={}", chrono::Utc::now().timestamp_millis());
+        let fresh_url = format!("index.html?settings=true{}&t={}", reason_param, chrono::Utc::now().timestamp_millis());
         
         if let Err(e) = existing_window.eval(&format!("window.location.replace('{}')", fresh_url)) {
             eprintln!("Failed to navigate settings window: {}", e);
@@ -79,13 +253,14 @@ pub fn show_settings(app: AppHandle) {
         if let Err(e) = existing_window.set_focus() {
             eprintln!("Failed to focus settings window: {}", e);
         }
+        events::emit_window_shown(&app, events::WindowKind::Settings, trigger, true);
     } else {
         // Create a new window only if one doesn't exist
         println!("Creating new settings window");
         match tauri::WindowBuilder::new(
             &app,
             "settings",
-            tauri::WindowUrl::App(format!("index.html?settings=true&t={}", chrono::Utc::now().timestamp_millis()).into())
+            tauri::WindowUrl::App(format!("index.html?settings=true{}&t={}", reason_param, chrono::Utc::now().timestamp_millis()).into())
         )
         .title("Notion Quick Notes - Settings")
         .inner_size(500.0, 580.0)
@@ -103,21 +278,335 @@ pub fn show_settings(app: AppHandle) {
                         eprintln!("Failed to focus settings window: {}", e);
                     }
                 }
+                events::emit_window_shown(&app, events::WindowKind::Settings, trigger, false);
             },
             Err(e) => eprintln!("Failed to create settings window: {}", e),
         }
     }
 }
 
+// Show a desktop notification, logging rather than failing when unavailable
+pub fn notify(app: &AppHandle, title: &str, body: &str) {
+    if let Err(e) = tauri::api::notification::Notification::new(&app.config().tauri.bundle.identifier)
+        .title(title)
+        .body(body)
+        .show()
+    {
+        eprintln!("Failed to show notification: {}", e);
+    }
+}
+
+// Tell the user once when their system clock disagrees with Notion's
+pub fn warn_clock_skew(app: &AppHandle) {
+    let Some(skew) = clock::take_skew_warning() else {
+        return;
+    };
+    
+    let correcting = app.state::<config::AppState>().config.lock().unwrap().correct_clock_skew;
+    let body = format!(
+        "Your system clock is {} compared to Notion. {}",
+        clock::describe_skew(skew),
+        if correcting {
+            "Note timestamps are being corrected."
+        } else {
+            "Note timestamps may be wrong."
+        }
+    );
+    quiet_hours::notify_background(app, "System clock looks wrong", &body);
+}
+
+// Apply the clipboard guardrails; returns the text to send when it can go out directly
+pub fn handle_clipboard_capture(app: &AppHandle, text: &str) -> Option<String> {
+    // Not set up yet: keep the text for after setup instead of losing it
+    if !check_settings_configured(app) {
+        if let Err(e) = setup_captures::stash(app, text, pinned_profile(), setup_captures::CaptureOrigin::Clipboard, clock::now()) {
+            notify(app, "Note not captured", &e);
+        }
+        show_settings_with_reason(app.clone(), events::ShowTrigger::Hotkey, setup_reason(app));
+        return None;
+    }
+    
+    let limits = {
+        let state = app.state::<config::AppState>();
+        let config = state.config.lock().unwrap();
+        clipboard::ClipboardLimits::from_config(&config)
+    };
+    
+    match clipboard::decide_clipboard_capture(text, &limits) {
+        clipboard::ClipboardDecision::Send(text) => Some(text),
+        clipboard::ClipboardDecision::Preview(text) => {
+            // Let the user see what they're about to send instead of auto-sending
+            show_note_input(app.clone(), events::ShowTrigger::Hotkey);
+            if let Some(window) = app.get_window("main") {
+                events::emit_app_event(&window, events::AppEvent::PrefillNote(text));
+            }
+            None
+        }
+        clipboard::ClipboardDecision::Refuse { chars } => {
+            notify(
+                app,
+                "Clipboard capture refused",
+                &format!(
+                    "The clipboard holds {}, which is over the {} limit. Nothing was sent.",
+                    clipboard::describe_size(chars),
+                    clipboard::describe_size(limits.hard_limit_chars)
+                ),
+            );
+            None
+        }
+    }
+}
+
+// The tray menu; also used to rebuild it after the taskbar restarts
+pub fn tray_menu() -> SystemTrayMenu {
+    let mut menu = SystemTrayMenu::new()
+        .add_item(CustomMenuItem::new("settings".to_string(), "Settings"))
+        .add_item(CustomMenuItem::new("about".to_string(), "About"))
+        .add_item(CustomMenuItem::new("flush_scratch".to_string(), "Flush scratch notes"))
+        .add_item(CustomMenuItem::new("undo_last_note".to_string(), "Undo last note"))
+        .add_submenu(SystemTraySubmenu::new(
+            "Troubleshooting",
+            SystemTrayMenu::new()
+                .add_item(CustomMenuItem::new("record_start".to_string(), "Start recording session"))
+                .add_item(CustomMenuItem::new("record_stop".to_string(), "Stop recording and package"))
+                .add_item(CustomMenuItem::new("sandbox_mode".to_string(), "Sandbox mode")),
+        ))
+        .add_native_item(SystemTrayMenuItem::Separator);
+    if safe_mode::is_active() {
+        menu = menu.add_item(CustomMenuItem::new("exit_safe_mode".to_string(), "Exit safe mode"));
+    }
+    menu.add_item(CustomMenuItem::new("quit".to_string(), "Quit"))
+}
+
+// Update the tray tooltip to reflect the current capture target
+pub fn refresh_tray(app: &AppHandle) {
+    let (page_id, token_invalid, sandbox_mode) = {
+        let state = app.state::<config::AppState>();
+        let config = state.config.lock().unwrap();
+        (config.selected_page_id.clone(), config.token_invalid, config.sandbox_mode)
+    };
+    let title = if page_id.is_empty() {
+        String::new()
+    } else {
+        page_display::display(app, &page_id).label()
+    };
+    let failing_checks = app.state::<config::AppState>()
+        .last_verification
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map_or(0, |report| report.failed_checks().len());
+    
+    let mut tooltip = if token_invalid {
+        "Notion Quick Notes - Notion token is no longer valid, open Settings to fix".to_string()
+    } else if title.is_empty() {
+        "Notion Quick Notes".to_string()
+    } else {
+        format!("Notion Quick Notes - {}", title)
+    };
+    
+    if failing_checks > 0 && !token_invalid {
+        tooltip.push_str(" - settings need attention");
+    }
+    
+    if sandbox_mode {
+        tooltip.push_str(" - sandbox mode");
+    }
+    if dry_run::is_active() {
+        tooltip = format!("DRY RUN, not syncing - {}", tooltip);
+    }
+    if safe_mode::is_active() {
+        tooltip = format!("SAFE MODE - {}", tooltip);
+    }
+    if let Err(e) = app.tray_handle().get_item("sandbox_mode").set_selected(sandbox_mode) {
+        eprintln!("Failed to update sandbox menu item: {}", e);
+    }
+    
+    if let Some(until) = quiet_hours::state().until_local {
+        tooltip.push_str(&format!(" - quiet hours until {}", until));
+    }
+    
+    if let Some(status) = focus::tray_status(app) {
+        tooltip.push_str(&format!(" - {}", status));
+    }
+    
+    let scratch_count = scratch::pending_count();
+    if scratch_count > 0 {
+        tooltip.push_str(&format!(" ({} scratch)", scratch_count));
+    }
+    
+    if let Err(e) = app.tray_handle().set_tooltip(&tooltip) {
+        eprintln!("Failed to update tray tooltip: {}", e);
+    }
+}
+
+// Stop background tasks cleanly, then exit the app
+pub fn shutdown(app: AppHandle) {
+    let registry = app.state::<config::AppState>().tasks.clone();
+    
+    tauri::async_runtime::spawn(async move {
+        let stragglers = registry.shutdown(tasks::DEFAULT_SHUTDOWN_TIMEOUT).await;
+        if !stragglers.is_empty() {
+            eprintln!("Tasks aborted during shutdown: {}", stragglers.join(", "));
+        }
+        app.exit(0);
+    });
+}
+
+// Check the foreground app against the exclusion list; records swallowed presses for diagnostics.
+// Passing the keystroke through to the excluded app is not attempted: the OS
+// has already consumed it for our registration, so the press is simply ignored.
+fn hotkey_excluded(app: &AppHandle) -> bool {
+    let exclusions = {
+        let state = app.state::<config::AppState>();
+        let config = state.config.lock().unwrap();
+        config.hotkey_excluded_apps.clone()
+    };
+    
+    if exclusions.is_empty() {
+        return false;
+    }
+    
+    match platform::foreground_app() {
+        Some(foreground) if platform::is_excluded_app(&foreground, &exclusions) => {
+            println!("Hotkey ignored: {} is excluded", foreground.executable);
+            let mut swallowed = SWALLOWED_HOTKEY_PRESSES.lock().unwrap();
+            swallowed.0 += 1;
+            swallowed.1 = Some(foreground.executable);
+            true
+        }
+        _ => false,
+    }
+}
+
+// Number of hotkey presses ignored due to exclusions, and the last app responsible
+pub fn swallowed_hotkey_presses() -> (u64, Option<String>) {
+    SWALLOWED_HOTKEY_PRESSES.lock().unwrap().clone()
+}
+
 // Register the global hotkey
 pub fn register_global_hotkey(app_handle: AppHandle) {
     let app_handle_clone = app_handle.clone();
     
     app_handle.global_shortcut_manager()
-        .register("Alt+Q", move || {
-            show_note_input(app_handle_clone.clone());
+        .register(PRIMARY_HOTKEY, move || {
+            if hotkey_excluded(&app_handle_clone) {
+                return;
+            }
+            *HOTKEY_PRESSED_AT.lock().unwrap() = Some(Instant::now());
+            // A normal invocation always captures into the main target again
+            *PINNED_PROFILE.lock().unwrap() = None;
+            show_note_input(app_handle_clone.clone(), events::ShowTrigger::Hotkey);
         })
         .unwrap_or_else(|e| {
             eprintln!("Failed to register global hotkey: {}", e);
         });
+    
+    register_profile_hotkeys(&app_handle);
+    register_clipboard_hotkey(&app_handle);
+}
+
+// (Re-)register the hotkey that sends the clipboard without showing the note window
+pub fn register_clipboard_hotkey(app: &AppHandle) {
+    let accelerator = app.state::<config::AppState>().config.lock().unwrap().clipboard_hotkey.clone();
+    
+    let mut manager = app.global_shortcut_manager();
+    let mut registered = CLIPBOARD_HOTKEY.lock().unwrap();
+    if let Some(previous) = registered.take() {
+        if let Err(e) = manager.unregister(&previous) {
+            eprintln!("Failed to unregister clipboard hotkey {}: {}", previous, e);
+        }
+    }
+    let Some(accelerator) = accelerator else {
+        return;
+    };
+    
+    let app_handle = app.clone();
+    match manager.register(&accelerator, move || {
+        if hotkey_excluded(&app_handle) {
+            return;
+        }
+        let app_handle = app_handle.clone();
+        tauri::async_runtime::spawn(async move {
+            let state = app_handle.state::<config::AppState>();
+            match notion::append_clipboard(app_handle.clone(), state).await {
+                Ok(Some(result)) if result.queued => notify(&app_handle, "Clipboard saved", "It will be sent to Notion later"),
+                Ok(Some(result)) => notify(
+                    &app_handle,
+                    "Clipboard sent",
+                    &format!("Added to {}", page_display::display(&app_handle, &result.page_id).label()),
+                ),
+                // Already handled: previewed, refused with a notification, or held for setup
+                Ok(None) => {}
+                Err(e) => notify(&app_handle, "Clipboard not sent", &e),
+            }
+        });
+    }) {
+        Ok(()) => *registered = Some(accelerator),
+        Err(e) => eprintln!("Failed to register clipboard hotkey {}: {}", accelerator, e),
+    }
+}
+
+// (Re-)register the per-profile capture hotkeys from config
+pub fn register_profile_hotkeys(app: &AppHandle) {
+    let profiles = {
+        let state = app.state::<config::AppState>();
+        let config = state.config.lock().unwrap();
+        config.capture_profiles.clone()
+    };
+    
+    let mut manager = app.global_shortcut_manager();
+    let mut registered = PROFILE_HOTKEYS.lock().unwrap();
+    for accelerator in registered.drain(..) {
+        if let Err(e) = manager.unregister(&accelerator) {
+            eprintln!("Failed to unregister profile hotkey {}: {}", accelerator, e);
+        }
+    }
+    
+    for profile in profiles {
+        let Some(accelerator) = profile.capture_hotkey.clone() else {
+            continue;
+        };
+        
+        let app_handle = app.clone();
+        let name = profile.name.clone();
+        match manager.register(&accelerator, move || {
+            if hotkey_excluded(&app_handle) {
+                return;
+            }
+            *HOTKEY_PRESSED_AT.lock().unwrap() = Some(Instant::now());
+            *PINNED_PROFILE.lock().unwrap() = Some(name.clone());
+            show_note_input(app_handle.clone(), events::ShowTrigger::Hotkey);
+        }) {
+            Ok(()) => registered.push(accelerator),
+            Err(e) => eprintln!("Failed to register hotkey {} for profile '{}': {}", accelerator, profile.name, e),
+        }
+    }
+}
+
+// The profile pinned for the current capture, if any
+pub fn pinned_profile() -> Option<String> {
+    PINNED_PROFILE.lock().unwrap().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_preloaded_note_window_is_only_shown() {
+        let shown = existing_or_build(Some("main"), || -> Result<&str, String> { panic!("the window already exists") });
+        assert_eq!(shown, Some(("main", true)));
+    }
+
+    #[test]
+    fn a_missing_note_window_is_built_once() {
+        let mut builds = 0;
+        let shown = existing_or_build(None, || {
+            builds += 1;
+            Ok::<_, String>("main")
+        });
+        assert_eq!((shown, builds), (Some(("main", false)), 1));
+        assert_eq!(existing_or_build(None::<&str>, || Err("no display")), None);
+    }
 }
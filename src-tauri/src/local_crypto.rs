@@ -0,0 +1,314 @@
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde_json::Value;
+use std::fmt;
+
+// Opt-in encryption of note text in local files (`encrypt_local_data`). Only
+// the text fields are sealed; ids, states and timestamps stay readable so the
+// stores can still be compacted and scheduled. The key lives in the OS
+// keyring, never on disk next to the data.
+//
+// Sealed values look like "nqn-enc1:<hex nonce + ciphertext>". Anything
+// without the prefix is plaintext, so stores written before encryption was
+// turned on still read.
+
+const SEALED_PREFIX: &str = "nqn-enc1:";
+const NONCE_LEN: usize = 12;
+
+#[cfg(not(test))]
+const KEYRING_SERVICE: &str = "notion-quick-notes";
+#[cfg(not(test))]
+const KEYRING_USER: &str = "local-data-key";
+
+// The setting, and the key once it has been fetched
+#[derive(Default)]
+struct CryptoState {
+    enabled: bool,
+    // Set while the keyring can't be reached, e.g. in a locked session
+    locked: bool,
+    key: Option<Key>,
+}
+
+#[cfg(not(test))]
+lazy_static::lazy_static! {
+    static ref STATE: std::sync::Mutex<CryptoState> = std::sync::Mutex::new(CryptoState::default());
+}
+
+#[cfg(not(test))]
+fn with_state<R>(f: impl FnOnce(&mut CryptoState) -> R) -> R {
+    f(&mut STATE.lock().unwrap())
+}
+
+// Tests get state and a keyring of their own per thread, like their data
+// directory, and never touch the user's keyring. The keyring starts locked.
+#[cfg(test)]
+thread_local! {
+    static STATE: std::cell::RefCell<CryptoState> = std::cell::RefCell::new(CryptoState::default());
+    static TEST_KEYRING: std::cell::Cell<Option<Key>> = const { std::cell::Cell::new(None) };
+}
+
+#[cfg(test)]
+fn with_state<R>(f: impl FnOnce(&mut CryptoState) -> R) -> R {
+    STATE.with(|state| f(&mut state.borrow_mut()))
+}
+
+// Unlock the current test's keyring with `key`, or lock it with None
+#[cfg(test)]
+pub fn use_test_keyring(key: Option<Key>) {
+    TEST_KEYRING.with(|keyring| keyring.set(key));
+    with_state(|state| state.key = None);
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CryptoError {
+    // The keyring is unavailable; the data is fine and readable later
+    Locked(String),
+    // The data was changed or sealed with a different key
+    Tampered,
+    Malformed,
+}
+
+impl fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CryptoError::Locked(reason) => write!(f, "Local data is locked until the system keyring is available ({})", reason),
+            CryptoError::Tampered => write!(f, "Encrypted local data failed its integrity check"),
+            CryptoError::Malformed => write!(f, "Encrypted local data is malformed"),
+        }
+    }
+}
+
+impl From<CryptoError> for String {
+    fn from(error: CryptoError) -> Self {
+        error.to_string()
+    }
+}
+
+pub fn set_enabled(enabled: bool) {
+    with_state(|state| state.enabled = enabled);
+}
+
+pub fn is_enabled() -> bool {
+    with_state(|state| state.enabled)
+}
+
+// Whether the last attempt to fetch the key failed because the keyring was unavailable
+pub fn is_locked() -> bool {
+    with_state(|state| state.locked)
+}
+
+pub fn is_sealed(value: &str) -> bool {
+    value.starts_with(SEALED_PREFIX)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+pub fn generate_key() -> Key {
+    ChaCha20Poly1305::generate_key(&mut OsRng)
+}
+
+pub fn encrypt_with(key: &Key, plain: &str) -> String {
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = ChaCha20Poly1305::new(key)
+        .encrypt(&nonce, plain.as_bytes())
+        .expect("encrypting an in-memory buffer can't fail");
+    format!("{}{}{}", SEALED_PREFIX, to_hex(&nonce), to_hex(&ciphertext))
+}
+
+pub fn decrypt_with(key: &Key, sealed: &str) -> Result<String, CryptoError> {
+    let hex = sealed.strip_prefix(SEALED_PREFIX).ok_or(CryptoError::Malformed)?;
+    let bytes = from_hex(hex).ok_or(CryptoError::Malformed)?;
+    if bytes.len() < NONCE_LEN {
+        return Err(CryptoError::Malformed);
+    }
+    let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+    let plain = ChaCha20Poly1305::new(key)
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| CryptoError::Tampered)?;
+    String::from_utf8(plain).map_err(|_| CryptoError::Malformed)
+}
+
+// The key from the keyring, created on first use when `create` is set
+fn key(create: bool) -> Result<Key, CryptoError> {
+    if let Some(key) = with_state(|state| state.key) {
+        return Ok(key);
+    }
+
+    let result = fetch_key(create);
+    with_state(|state| {
+        state.locked = matches!(result, Err(CryptoError::Locked(_)));
+        state.key = result.as_ref().ok().copied();
+    });
+    result
+}
+
+#[cfg(test)]
+fn fetch_key(_create: bool) -> Result<Key, CryptoError> {
+    TEST_KEYRING
+        .with(|keyring| keyring.get())
+        .ok_or_else(|| CryptoError::Locked("the test keyring is locked".into()))
+}
+
+#[cfg(not(test))]
+fn fetch_key(create: bool) -> Result<Key, CryptoError> {
+    let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER)
+        .map_err(|e| CryptoError::Locked(e.to_string()))?;
+    match entry.get_password() {
+        Ok(hex) => from_hex(&hex)
+            .filter(|bytes| bytes.len() == 32)
+            .map(|bytes| *Key::from_slice(&bytes))
+            .ok_or(CryptoError::Malformed),
+        Err(keyring::Error::NoEntry) if create => {
+            let key = generate_key();
+            entry
+                .set_password(&to_hex(&key))
+                .map_err(|e| CryptoError::Locked(e.to_string()))?;
+            Ok(key)
+        }
+        // Without the key nothing sealed can be read; say so rather than calling it corrupt
+        Err(keyring::Error::NoEntry) => Err(CryptoError::Locked("the encryption key is missing".into())),
+        Err(e) => Err(CryptoError::Locked(e.to_string())),
+    }
+}
+
+// Text as it should be written to disk: sealed when encryption is on.
+// Already-sealed text is left alone, so sealing twice is harmless.
+pub fn seal(plain: &str) -> Result<String, CryptoError> {
+    if !is_enabled() || is_sealed(plain) {
+        return Ok(plain.to_string());
+    }
+    Ok(encrypt_with(&key(true)?, plain))
+}
+
+// Text as read from disk: sealed values are decrypted, plaintext passes through
+pub fn open(stored: &str) -> Result<String, CryptoError> {
+    if !is_sealed(stored) {
+        return Ok(stored.to_string());
+    }
+    decrypt_with(&key(false)?, stored)
+}
+
+// JSON payloads (e.g. Notion blocks) are sealed as one string
+pub fn seal_value(value: &Value) -> Result<Value, CryptoError> {
+    if !is_enabled() {
+        return Ok(value.clone());
+    }
+    Ok(Value::String(seal(&value.to_string())?))
+}
+
+pub fn open_value(stored: &Value) -> Result<Value, CryptoError> {
+    match stored {
+        Value::String(text) if is_sealed(text) => {
+            serde_json::from_str(&open(text)?).map_err(|_| CryptoError::Malformed)
+        }
+        _ => Ok(stored.clone()),
+    }
+}
+
+pub fn seal_optional(plain: &Option<String>) -> Result<Option<String>, CryptoError> {
+    plain.as_deref().map(seal).transpose()
+}
+
+pub fn open_optional(stored: &Option<String>) -> Result<Option<String>, CryptoError> {
+    stored.as_deref().map(open).transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // Flip one hex digit of the ciphertext, leaving the prefix and nonce alone
+    fn tampered(sealed: &str) -> String {
+        let mut chars: Vec<char> = sealed.chars().collect();
+        let last = chars.len() - 1;
+        chars[last] = if chars[last] == '0' { '1' } else { '0' };
+        chars.into_iter().collect()
+    }
+
+    #[test]
+    fn sealed_text_round_trips() {
+        let key = generate_key();
+        use_test_keyring(Some(key));
+        set_enabled(true);
+
+        let sealed = seal("buy milk").unwrap();
+        assert!(is_sealed(&sealed));
+        assert!(!sealed.contains("buy milk"));
+        assert_ne!(sealed, seal("buy milk").unwrap(), "every seal uses a fresh nonce");
+        assert_eq!(seal(&sealed).unwrap(), sealed, "sealing twice is harmless");
+        assert_eq!(open(&sealed).unwrap(), "buy milk");
+        assert_eq!(decrypt_with(&key, &encrypt_with(&key, "")).unwrap(), "");
+
+        let blocks = json!([{ "type": "paragraph", "paragraph": { "rich_text": [] } }]);
+        let sealed_blocks = seal_value(&blocks).unwrap();
+        assert!(sealed_blocks.as_str().is_some_and(is_sealed));
+        assert_eq!(open_value(&sealed_blocks).unwrap(), blocks);
+        assert_eq!(open_optional(&seal_optional(&Some("x".into())).unwrap()).unwrap().as_deref(), Some("x"));
+        assert_eq!(seal_optional(&None).unwrap(), None);
+    }
+
+    #[test]
+    fn changed_or_foreign_data_fails_the_integrity_check() {
+        let key = generate_key();
+        let sealed = encrypt_with(&key, "buy milk");
+
+        assert_eq!(decrypt_with(&key, &tampered(&sealed)), Err(CryptoError::Tampered));
+        assert_eq!(decrypt_with(&generate_key(), &sealed), Err(CryptoError::Tampered));
+        assert_eq!(decrypt_with(&key, "buy milk"), Err(CryptoError::Malformed));
+        assert_eq!(decrypt_with(&key, &format!("{}0a0b", SEALED_PREFIX)), Err(CryptoError::Malformed));
+        assert_eq!(decrypt_with(&key, &format!("{}zz", SEALED_PREFIX)), Err(CryptoError::Malformed));
+        assert_eq!(decrypt_with(&key, &sealed[..sealed.len() - 1]), Err(CryptoError::Malformed));
+
+        use_test_keyring(Some(key));
+        assert_eq!(open(&tampered(&sealed)), Err(CryptoError::Tampered));
+        assert!(!is_locked(), "tampering isn't a keyring problem");
+    }
+
+    #[test]
+    fn a_locked_keyring_refuses_without_losing_data() {
+        let key = generate_key();
+        let sealed = encrypt_with(&key, "buy milk");
+        use_test_keyring(None);
+        set_enabled(true);
+
+        assert!(matches!(seal("buy milk"), Err(CryptoError::Locked(_))));
+        assert!(matches!(open(&sealed), Err(CryptoError::Locked(_))));
+        assert!(matches!(seal_value(&json!({ "a": 1 })), Err(CryptoError::Locked(_))));
+        assert!(is_locked());
+
+        // Once the keyring is back the same data reads again
+        use_test_keyring(Some(key));
+        assert_eq!(open(&sealed).unwrap(), "buy milk");
+        assert!(!is_locked());
+    }
+
+    #[test]
+    fn plaintext_passes_through() {
+        use_test_keyring(None);
+
+        // Written before encryption was turned on: readable even while locked
+        assert_eq!(open("buy milk").unwrap(), "buy milk");
+        assert_eq!(open_value(&json!({ "a": 1 })).unwrap(), json!({ "a": 1 }));
+        assert_eq!(open_value(&json!("plain text")).unwrap(), json!("plain text"));
+        assert_eq!(open_optional(&None).unwrap(), None);
+        assert!(!is_locked());
+
+        // Encryption off: nothing is sealed and the keyring isn't needed
+        set_enabled(false);
+        assert_eq!(seal("buy milk").unwrap(), "buy milk");
+        assert_eq!(seal_value(&json!([1, 2])).unwrap(), json!([1, 2]));
+    }
+}
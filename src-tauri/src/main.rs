@@ -1,23 +1,25 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use notion_quick_notes::config;
-use tauri::{CustomMenuItem, SystemTray, SystemTrayEvent, SystemTrayMenu, SystemTrayMenuItem, Manager};
+use notion_quick_notes::events::{HideReason, ShowTrigger};
+use notion_quick_notes::safe_mode::{self, Subsystem};
+use notion_quick_notes::{config, profiling, spellcheck};
+use tauri::{SystemTray, SystemTrayEvent, Manager};
 
 // Define the commands with tauri::command attribute
 #[tauri::command]
 fn show_note_input(app: tauri::AppHandle) {
-    notion_quick_notes::show_note_input(app);
+    notion_quick_notes::show_note_input(app, ShowTrigger::Command);
 }
 
 #[tauri::command]
-fn close_note_input(app: tauri::AppHandle) {
-    notion_quick_notes::close_note_input(app);
+fn close_note_input(app: tauri::AppHandle, reason: Option<HideReason>) {
+    notion_quick_notes::close_note_input(app, reason.unwrap_or(HideReason::UserDismiss));
 }
 
 #[tauri::command]
 fn show_settings(app: tauri::AppHandle) {
-    notion_quick_notes::show_settings(app);
+    notion_quick_notes::show_settings(app, ShowTrigger::Command);
 }
 
 #[tauri::command]
@@ -25,18 +27,22 @@ fn close_settings(app: tauri::AppHandle) {
     notion_quick_notes::close_settings(app);
 }
 
+#[tauri::command]
+fn window_ready(app: tauri::AppHandle) -> Option<u128> {
+    notion_quick_notes::note_window_ready(&app)
+}
+
 fn main() {
+    profiling::mark("process_start");
+    safe_mode::begin_launch(std::env::args());
+    
     // Initialize app state
     let app_state = config::init_app_state();
+    profiling::mark("config_loaded");
+    spellcheck::prepare_process(&app_state.config.lock().unwrap());
 
     // Create system tray menu
-    let tray_menu = SystemTrayMenu::new()
-        .add_item(CustomMenuItem::new("settings".to_string(), "Settings"))
-        .add_item(CustomMenuItem::new("about".to_string(), "About"))
-        .add_native_item(SystemTrayMenuItem::Separator)
-        .add_item(CustomMenuItem::new("quit".to_string(), "Quit"));
-
-    let tray = SystemTray::new().with_menu(tray_menu);
+    let tray = SystemTray::new().with_menu(notion_quick_notes::tray_menu());
 
     tauri::Builder::default()
         .manage(app_state)
@@ -45,33 +51,143 @@ fn main() {
             close_note_input,
             show_settings,
             close_settings,
+            window_ready,
             notion_quick_notes::notion::get_notion_api_token,
             notion_quick_notes::notion::set_notion_api_token,
             notion_quick_notes::notion::search_notion_pages,
+            notion_quick_notes::notion::search_notion_databases,
             notion_quick_notes::notion::get_selected_page_id,
             notion_quick_notes::notion::set_selected_page_id,
+            notion_quick_notes::notion::verify_page_access,
+            notion_quick_notes::notion::get_page_info,
+            notion_quick_notes::notion::create_notion_page,
+            notion_quick_notes::notion::set_selected_database,
+            notion_quick_notes::notion::get_selected_target_kind,
+            notion_quick_notes::notion::probe_page_access,
+            notion_quick_notes::anchor::list_anchor_blocks,
             notion_quick_notes::notion::append_note,
+            notion_quick_notes::notion::append_clipboard,
+            notion_quick_notes::undo::undo_last_note,
+            notion_quick_notes::undo::edit_last_note,
+            notion_quick_notes::diagnostics::get_app_info,
+            notion_quick_notes::reliability::get_reliability_summary,
+            notion_quick_notes::quiet_hours::get_quiet_hours_state,
+            notion_quick_notes::page_display::get_page_displays,
+            notion_quick_notes::migrations::get_migration_history,
+            notion_quick_notes::capture::get_capture_banner,
+            notion_quick_notes::capture::get_capture_target,
+            notion_quick_notes::queue::queue_note,
+            notion_quick_notes::queue::get_queued_notes,
+            notion_quick_notes::queue::get_pending_notes,
+            notion_quick_notes::queue::flush_queue,
+            notion_quick_notes::queue::discard_queued_note,
+            notion_quick_notes::receipts::get_delivery_receipt,
+            notion_quick_notes::recording::start_recording,
+            notion_quick_notes::recording::stop_recording_and_package,
+            notion_quick_notes::settings::get_hotkey_excluded_apps,
+            notion_quick_notes::settings::set_hotkey_excluded_apps,
+            notion_quick_notes::scratch::get_scratch_buffer,
+            notion_quick_notes::scratch::flush_scratch_buffer,
+            notion_quick_notes::scratch::discard_scratch_item,
+            notion_quick_notes::stats::get_target_page_stats,
+            notion_quick_notes::profiling::get_memory_report,
+            notion_quick_notes::profiling::trim_memory,
+            notion_quick_notes::routing::get_routing_suggestions,
+            notion_quick_notes::accessibility::get_accessibility_prefs,
+            notion_quick_notes::notion::append_raw_blocks,
+            notion_quick_notes::events::report_note_draft,
+            notion_quick_notes::events::get_event_catalog,
+            notion_quick_notes::setup_captures::get_pending_setup_captures,
+            notion_quick_notes::setup_captures::discard_pending_setup_capture,
+            notion_quick_notes::settings::set_encrypt_local_data,
+            notion_quick_notes::history::move_note,
+            notion_quick_notes::history::search_note_history,
+            notion_quick_notes::history::get_note_history,
+            notion_quick_notes::history::clear_note_history,
+            notion_quick_notes::capture::resolve_destination,
+            notion_quick_notes::capture::preview_note,
+            notion_quick_notes::rate_limit::get_rate_limit_info,
+            notion_quick_notes::rate_limit::get_rate_limit_history,
+            notion_quick_notes::reset::request_reset_confirmation,
+            notion_quick_notes::reset::reset_app_data,
+            notion_quick_notes::verification::validate_settings,
+            notion_quick_notes::verification::get_last_verification,
+            notion_quick_notes::focus::start_focus_session,
+            notion_quick_notes::focus::end_focus_session,
+            notion_quick_notes::focus::get_focus_session,
+            notion_quick_notes::settings::set_screen_reader_mode,
+            notion_quick_notes::settings::set_typography,
+            notion_quick_notes::settings::set_mirror_webhook,
+            notion_quick_notes::settings::set_sandbox_mode,
+            notion_quick_notes::notion::create_sandbox_page,
+            notion_quick_notes::page_rotations::get_page_rotations,
+            notion_quick_notes::health::webview_pong,
+            notion_quick_notes::settings::set_window_effects,
+            notion_quick_notes::settings::set_spellcheck,
+            notion_quick_notes::settings::set_profile_token,
+            notion_quick_notes::window_effects::get_window_effect,
+            notion_quick_notes::settings::get_note_block_type,
+            notion_quick_notes::settings::set_note_block_type,
+            notion_quick_notes::settings::get_note_emphasis,
+            notion_quick_notes::settings::set_note_emphasis,
+            notion_quick_notes::settings::get_timezones,
+            notion_quick_notes::settings::get_target_mode,
+            notion_quick_notes::settings::set_target_mode,
+            notion_quick_notes::settings::set_timestamp_timezone,
+            notion_quick_notes::settings::get_extra_headers,
+            notion_quick_notes::settings::set_extra_headers,
+            notion_quick_notes::settings::get_config_collection,
+            notion_quick_notes::settings::mutate_config_collection,
+            notion_quick_notes::settings_schema::list_settings_schema,
+            notion_quick_notes::settings_schema::set_setting_by_path,
         ])
         .setup(|app| {
             let app_handle = app.handle();
             
+            notion_quick_notes::auth::init(&app_handle);
+            notion_quick_notes::refresh_tray(&app_handle);
+            profiling::mark("tray_ready");
+            safe_mode::tray_ready();
+            if let Some(notice) = safe_mode::notice() {
+                notion_quick_notes::notify(&app_handle, "Safe mode", &notice);
+            }
+            notion_quick_notes::notion::register_memory_reporters();
+            notion_quick_notes::preload_note_window(&app_handle);
+            profiling::mark("window_preloaded");
+            if safe_mode::enabled(Subsystem::Scratch) {
+                notion_quick_notes::scratch::start_scratch_flush(&app_handle);
+            }
+            if safe_mode::enabled(Subsystem::QueueFlush) {
+                notion_quick_notes::queue::start_queue_flush(&app_handle);
+            }
+            if safe_mode::enabled(Subsystem::BackgroundTasks) {
+                notion_quick_notes::suspend::start_suspend_watch(&app_handle);
+                notion_quick_notes::capture::start_capture_banner_watch(&app_handle);
+                notion_quick_notes::notion::start_page_refresh(&app_handle);
+                notion_quick_notes::digest::start_weekly_digest(&app_handle);
+                notion_quick_notes::accessibility::start_accessibility_watch(&app_handle);
+                notion_quick_notes::verification::start_settings_verification(&app_handle);
+                notion_quick_notes::focus::start_focus_timer(&app_handle);
+                notion_quick_notes::health::start_health_probe(&app_handle);
+            }
             notion_quick_notes::register_global_hotkey(app_handle);
+            profiling::mark("hotkey_registered");
             Ok(())
         })
         .system_tray(tray)
-        .on_system_tray_event(|app, event| match event {
-            SystemTrayEvent::MenuItemClick { id, .. } => {
+        .on_system_tray_event(|app, event| {
+            if let SystemTrayEvent::MenuItemClick { id, .. } = event {
                 match id.as_str() {
                     "settings" => {
                         println!("Opening settings from system tray");
                         
                         // Hide the note input window if visible
-                        if let Some(window) = app.get_window("main") {
-                            let _ = window.hide();
+                        if app.get_window("main").is_some_and(|window| window.is_visible().unwrap_or(false)) {
+                            notion_quick_notes::close_note_input(app.app_handle(), HideReason::AppHide);
                         }
                         
                         // Show settings window (will reuse if exists)
-                        notion_quick_notes::show_settings(app.app_handle());
+                        notion_quick_notes::show_settings(app.app_handle(), ShowTrigger::Tray);
                     }
                     "about" => {
                         let _ = tauri::WindowBuilder::new(
@@ -85,13 +201,66 @@ fn main() {
                         .center()
                         .build();
                     }
+                    "flush_scratch" => {
+                        let app_handle = app.app_handle();
+                        tauri::async_runtime::spawn(async move {
+                            match notion_quick_notes::scratch::flush(&app_handle, None).await {
+                                Ok(0) => {}
+                                Ok(count) => notion_quick_notes::notify(
+                                    &app_handle,
+                                    "Scratch notes sent",
+                                    &format!("{} scratch notes added to Notion", count),
+                                ),
+                                Err(e) => notion_quick_notes::notify(&app_handle, "Scratch flush failed", &e),
+                            }
+                        });
+                    }
+                    "undo_last_note" => {
+                        let app_handle = app.app_handle();
+                        tauri::async_runtime::spawn(async move {
+                            let state = app_handle.state::<config::AppState>();
+                            match notion_quick_notes::undo::undo_last_note(app_handle.clone(), state).await {
+                                Ok(_) => notion_quick_notes::notify(&app_handle, "Note removed", "The last note was removed from Notion"),
+                                Err(e) => notion_quick_notes::notify(&app_handle, "Undo failed", &e),
+                            }
+                        });
+                    }
+                    "record_start" => {
+                        match notion_quick_notes::recording::start() {
+                            Ok(_) => notion_quick_notes::notify(
+                                &app.app_handle(),
+                                "Recording started",
+                                "Notion responses are being recorded with your content removed",
+                            ),
+                            Err(e) => notion_quick_notes::notify(&app.app_handle(), "Recording failed", &e),
+                        }
+                    }
+                    "record_stop" => {
+                        match notion_quick_notes::recording::stop_recording_and_package() {
+                            Ok(path) => notion_quick_notes::notify(
+                                &app.app_handle(),
+                                "Recording saved",
+                                &format!("Attach {} to your issue", path),
+                            ),
+                            Err(e) => notion_quick_notes::notify(&app.app_handle(), "Recording failed", &e),
+                        }
+                    }
+                    "sandbox_mode" => {
+                        let enabled = !app.state::<config::AppState>().config.lock().unwrap().sandbox_mode;
+                        if let Err(e) = notion_quick_notes::settings::set_sandbox_mode(enabled, app.app_handle()) {
+                            notion_quick_notes::notify(&app.app_handle(), "Sandbox mode", &e);
+                        }
+                    }
+                    "exit_safe_mode" => {
+                        safe_mode::exit();
+                        app.restart();
+                    }
                     "quit" => {
-                        std::process::exit(0);
+                        notion_quick_notes::shutdown(app.app_handle());
                     }
                     _ => {}
                 }
             }
-            _ => {}
         })
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,682 @@
+use serde_json::{json, Value};
+
+use crate::blocks::{paragraph, split_text_content, text_run, text_runs, to_do};
+
+// Markdown in a note turned into Notion blocks: paragraphs, headings, quotes,
+// bulleted and numbered lists and tasks (nested by indentation), dividers and
+// code fences, with **bold**, *italic*, `code` and http(s) links inside them.
+// Anything that doesn't parse is kept as literal text, so a stray `*` never
+// loses what was typed.
+
+// Notion accepts at most two levels of children in one append
+const MAX_LIST_DEPTH: usize = 2;
+
+// Code fence languages Notion knows, with the aliases people type for them
+const CODE_LANGUAGES: &[(&str, &str)] = &[
+    ("bash", "bash"), ("sh", "shell"), ("shell", "shell"), ("zsh", "shell"),
+    ("c", "c"), ("cpp", "c++"), ("c++", "c++"), ("cs", "c#"), ("csharp", "c#"),
+    ("css", "css"), ("diff", "diff"), ("go", "go"), ("html", "html"),
+    ("java", "java"), ("js", "javascript"), ("javascript", "javascript"),
+    ("json", "json"), ("kotlin", "kotlin"), ("markdown", "markdown"), ("md", "markdown"),
+    ("php", "php"), ("powershell", "powershell"), ("ps1", "powershell"),
+    ("py", "python"), ("python", "python"), ("rb", "ruby"), ("ruby", "ruby"),
+    ("rs", "rust"), ("rust", "rust"), ("sql", "sql"), ("swift", "swift"),
+    ("ts", "typescript"), ("typescript", "typescript"), ("xml", "xml"),
+    ("yaml", "yaml"), ("yml", "yaml"),
+];
+
+// Notion rejects link URLs longer than this; longer ones stay plain text
+const MAX_URL_LEN: usize = 2000;
+
+// Characters that end a sentence rather than a URL when they come last
+const URL_TRAILING_PUNCTUATION: &[char] = &['.', ',', ';', ':', '!', '?', '\'', '"'];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Style {
+    bold: bool,
+    italic: bool,
+    code: bool,
+}
+
+fn styled_runs(content: &str, style: Style) -> Vec<Value> {
+    split_text_content(content)
+        .into_iter()
+        .map(|piece| {
+            let mut run = text_run(piece, style.bold);
+            run["annotations"]["italic"] = json!(style.italic);
+            run["annotations"]["code"] = json!(style.code);
+            run
+        })
+        .collect()
+}
+
+impl Style {
+    fn with(self, other: Style) -> Style {
+        Style {
+            bold: self.bold || other.bold,
+            italic: self.italic || other.italic,
+            code: self.code || other.code,
+        }
+    }
+}
+
+// The http(s) URL `text` starts with, if any. Punctuation after it that reads as
+// part of the sentence is left out, as is a closing bracket it didn't open:
+// "(see https://example.com/a)." gives "https://example.com/a". Bare "www."
+// addresses aren't links.
+pub fn url_at(text: &str) -> Option<&str> {
+    let host = text.strip_prefix("https://").or_else(|| text.strip_prefix("http://"))?;
+    let scheme_len = text.len() - host.len();
+    let end = host
+        .find(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"' | '`'))
+        .map_or(text.len(), |end| scheme_len + end);
+    let mut url = &text[..end];
+    loop {
+        let mut trimmed = url.trim_end_matches(URL_TRAILING_PUNCTUATION);
+        for (open, close) in [('(', ')'), ('[', ']')] {
+            if trimmed.ends_with(close) && trimmed.matches(open).count() < trimmed.matches(close).count() {
+                trimmed = &trimmed[..trimmed.len() - 1];
+            }
+        }
+        if trimmed.len() == url.len() {
+            break;
+        }
+        url = trimmed;
+    }
+    (url.len() > scheme_len && url.len() <= MAX_URL_LEN).then_some(url)
+}
+
+// The #tag `text` starts with, if any: a hash, then letters, digits, "_" and
+// "-" with at least one letter, so "#1" and "#2024" aren't tags. A trailing
+// "-" is left out, as in "#idea-".
+pub fn hashtag_at(text: &str) -> Option<&str> {
+    let name = text.strip_prefix('#')?;
+    let end = name
+        .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+        .unwrap_or(name.len());
+    let name = name[..end].trim_end_matches('-');
+    name.chars().any(char::is_alphabetic).then(|| &text[..name.len() + 1])
+}
+
+// The URL a note consists of, when it is nothing else
+pub fn sole_url(text: &str) -> Option<&str> {
+    let text = text.trim();
+    url_at(text).filter(|url| url.len() == text.len())
+}
+
+fn link_runs(url: &str, style: Style) -> Vec<Value> {
+    let mut runs = styled_runs(url, style);
+    for run in &mut runs {
+        run["text"]["link"] = json!({ "url": url });
+    }
+    runs
+}
+
+// Rich text for one line or paragraph of inline Markdown
+pub fn inline_runs(text: &str) -> Vec<Value> {
+    let runs = styled_inline(text, Style::default());
+    if runs.is_empty() {
+        return styled_runs("", Style::default());
+    }
+    runs
+}
+
+// Spans nest, so "**bold *and* more**" has an italic run inside the bold
+// ones; inside `code` everything is literal. A marker without a partner is
+// kept as a character of the text.
+fn styled_inline(text: &str, base: Style) -> Vec<Value> {
+    let mut runs = Vec::new();
+    let mut plain = String::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        // A URL is taken whole, so underscores and asterisks in it aren't emphasis
+        if let Some(url) = url_at(rest).filter(|_| !plain.ends_with(char::is_alphanumeric)) {
+            if !plain.is_empty() {
+                runs.extend(styled_runs(&plain, base));
+                plain.clear();
+            }
+            runs.extend(link_runs(url, base));
+            rest = &rest[url.len()..];
+            continue;
+        }
+        let (marker, style) = match c {
+            '`' => ("`", Style { code: true, ..Style::default() }),
+            '*' if rest.starts_with("**") => ("**", Style { bold: true, ..Style::default() }),
+            // Underscores inside a word (snake_case) aren't emphasis
+            '_' if plain.ends_with(char::is_alphanumeric) => {
+                plain.push(c);
+                rest = &rest[1..];
+                continue;
+            }
+            '_' if rest.starts_with("__") => ("__", Style { bold: true, ..Style::default() }),
+            '*' | '_' => (&rest[..1], Style { italic: true, ..Style::default() }),
+            _ => {
+                plain.push(c);
+                rest = &rest[c.len_utf8()..];
+                continue;
+            }
+        };
+        let inner = &rest[marker.len()..];
+        match inner.find(marker) {
+            // Emphasis needs text right inside the markers, so "2 * 3 * 4" stays as typed
+            Some(end) if end > 0 && (style.code || !inner.starts_with(' ') && !inner[..end].ends_with(' ')) => {
+                if !plain.is_empty() {
+                    runs.extend(styled_runs(&plain, base));
+                    plain.clear();
+                }
+                let span = &inner[..end];
+                if style.code {
+                    runs.extend(styled_runs(span, base.with(style)));
+                } else {
+                    runs.extend(styled_inline(span, base.with(style)));
+                }
+                rest = &inner[end + marker.len()..];
+            }
+            _ => {
+                plain.push_str(marker);
+                rest = inner;
+            }
+        }
+    }
+    if !plain.is_empty() {
+        runs.extend(styled_runs(&plain, base));
+    }
+    runs
+}
+
+fn text_block(block_type: &str, rich_text: Vec<Value>) -> Value {
+    json!({
+        "object": "block",
+        "type": block_type,
+        block_type: {
+            "rich_text": rich_text
+        }
+    })
+}
+
+fn code_block(language: &str, code: &str) -> Value {
+    let language = CODE_LANGUAGES
+        .iter()
+        .find(|(alias, _)| alias.eq_ignore_ascii_case(language))
+        .map_or("plain text", |(_, name)| name);
+    json!({
+        "object": "block",
+        "type": "code",
+        "code": {
+            "rich_text": text_runs(code, false),
+            "language": language
+        }
+    })
+}
+
+// A code block for the fence opening at `lines[start]`, and the index of the
+// line after it. The content is taken as typed, without inline Markdown; an
+// unterminated fence runs to the end of the note.
+pub fn code_fence(lines: &[&str], start: usize) -> Option<(Value, usize)> {
+    let language = lines[start].trim_start().strip_prefix("```")?.trim();
+    let content = &lines[start + 1..];
+    let (code, next) = match content.iter().position(|line| line.trim_start().starts_with("```")) {
+        Some(length) => (&content[..length], start + length + 2),
+        None => (content, lines.len()),
+    };
+    Some((code_block(language, &code.join("\n")), next))
+}
+
+// Three or more dashes and nothing else
+fn is_divider(line: &str) -> bool {
+    let line = line.trim_end();
+    line.len() >= 3 && line.chars().all(|c| c == '-')
+}
+
+fn divider() -> Value {
+    json!({
+        "object": "block",
+        "type": "divider",
+        "divider": {}
+    })
+}
+
+// "# Title" to "### Title", with any closing hashes dropped. "#tag" needs no
+// space after the hash, so it stays text.
+fn heading(line: &str) -> Option<Value> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let text = line[level..].strip_prefix(' ')?;
+    if level == 0 {
+        return None;
+    }
+    let text = text.trim();
+    // Closing hashes only count after a space, so "# Learning C#" keeps its "#"
+    let unclosed = text.trim_end_matches('#');
+    let text = if unclosed.is_empty() || unclosed.ends_with(' ') { unclosed.trim_end() } else { text };
+    // Notion has three heading levels; deeper ones become the smallest
+    Some(text_block(&format!("heading_{}", level.min(3)), inline_runs(text)))
+}
+
+// Leading whitespace of a line as an indent (a tab counts as four spaces), and the rest
+fn split_indent(line: &str) -> (usize, &str) {
+    let trimmed = line.trim_start_matches([' ', '\t']);
+    let indent = line[..line.len() - trimmed.len()]
+        .chars()
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum();
+    (indent, trimmed)
+}
+
+// A list line as (indent, block type, item text)
+fn list_item(line: &str) -> Option<(usize, &'static str, &str)> {
+    let (indent, trimmed) = split_indent(line);
+    if let Some(text) = ["- ", "* ", "+ "].iter().find_map(|marker| trimmed.strip_prefix(marker)) {
+        return Some((indent, "bulleted_list_item", text));
+    }
+    let digits = trimmed.chars().take_while(char::is_ascii_digit).count();
+    let text = trimmed[digits..].strip_prefix(". ").or_else(|| trimmed[digits..].strip_prefix(") "))?;
+    (digits > 0).then_some((indent, "numbered_list_item", text))
+}
+
+// A task line as (indent, checked, task text): "[ ] ", "[] " or "[x] ",
+// optionally after a "- " or "* " bullet
+pub fn task_item(line: &str) -> Option<(usize, bool, &str)> {
+    let (indent, trimmed) = split_indent(line);
+    let trimmed = ["- ", "* "].iter().find_map(|marker| trimmed.strip_prefix(marker)).unwrap_or(trimmed);
+    let (checked, rest) = [("[ ]", false), ("[]", false), ("[x]", true), ("[X]", true)]
+        .iter()
+        .find_map(|(checkbox, checked)| trimmed.strip_prefix(checkbox).map(|rest| (*checked, rest)))?;
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None;
+    }
+    Some((indent, checked, rest.trim()))
+}
+
+fn push_child(parent: &mut Value, child: Value) {
+    let block_type = parent["type"].as_str().unwrap_or_default().to_string();
+    match parent[block_type.as_str()]["children"].as_array_mut() {
+        Some(children) => children.push(child),
+        None => parent[block_type.as_str()]["children"] = json!([child]),
+    }
+}
+
+// A list line of a plain note as (indent, block type, item text): "- " and
+// "* " bullets, and "1. " numbered items in any order (Notion renumbers them).
+// "1.5 million" has no space after the dot, so it stays text.
+pub fn plain_list_item(line: &str) -> Option<(usize, &'static str, &str)> {
+    let item = list_item(line)?;
+    let marker = line.trim_start().chars().find(|c| !c.is_ascii_digit())?;
+    matches!(marker, '-' | '*' | '.').then_some(item)
+}
+
+// Nests list items by indentation. Items are open on a stack until one at
+// the same or a smaller indent arrives; deeper than Notion allows, they
+// become siblings at the deepest level.
+#[derive(Default)]
+pub struct ListBuilder {
+    open: Vec<(usize, Value)>,
+}
+
+impl ListBuilder {
+    fn close_one(&mut self, blocks: &mut Vec<Value>) {
+        if let Some((_, item)) = self.open.pop() {
+            match self.open.last_mut() {
+                Some((_, parent)) => push_child(parent, item),
+                None => blocks.push(item),
+            }
+        }
+    }
+
+    pub fn push(&mut self, indent: usize, item: Value, blocks: &mut Vec<Value>) {
+        while self.open.last().is_some_and(|(open, _)| *open >= indent) || self.open.len() > MAX_LIST_DEPTH {
+            self.close_one(blocks);
+        }
+        self.open.push((indent, item));
+    }
+
+    pub fn finish(&mut self, blocks: &mut Vec<Value>) {
+        while !self.open.is_empty() {
+            self.close_one(blocks);
+        }
+    }
+}
+
+// The blocks for a Markdown note; empty only for blank text
+pub fn markdown_blocks(text: &str) -> Vec<Value> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut blocks = Vec::new();
+    let mut paragraph_lines: Vec<&str> = Vec::new();
+    let mut list = ListBuilder::default();
+
+    let flush_paragraph = |paragraph_lines: &mut Vec<&str>, blocks: &mut Vec<Value>| {
+        if !paragraph_lines.is_empty() {
+            blocks.push(paragraph(inline_runs(&paragraph_lines.join("\n"))));
+            paragraph_lines.clear();
+        }
+    };
+
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim_start();
+
+        if let Some((indent, checked, task)) = task_item(line) {
+            flush_paragraph(&mut paragraph_lines, &mut blocks);
+            list.push(indent, to_do(inline_runs(task), checked), &mut blocks);
+            i += 1;
+            continue;
+        }
+        if let Some((indent, block_type, item_text)) = list_item(line) {
+            flush_paragraph(&mut paragraph_lines, &mut blocks);
+            list.push(indent, text_block(block_type, inline_runs(item_text.trim())), &mut blocks);
+            i += 1;
+            continue;
+        }
+        list.finish(&mut blocks);
+
+        if trimmed.is_empty() {
+            flush_paragraph(&mut paragraph_lines, &mut blocks);
+        } else if let Some((block, next)) = code_fence(&lines, i) {
+            flush_paragraph(&mut paragraph_lines, &mut blocks);
+            blocks.push(block);
+            i = next;
+            continue;
+        } else if is_divider(trimmed) {
+            flush_paragraph(&mut paragraph_lines, &mut blocks);
+            blocks.push(divider());
+        } else if let Some(block) = heading(trimmed) {
+            flush_paragraph(&mut paragraph_lines, &mut blocks);
+            blocks.push(block);
+        } else if let Some(quoted) = trimmed.strip_prefix('>') {
+            flush_paragraph(&mut paragraph_lines, &mut blocks);
+            blocks.push(text_block("quote", inline_runs(quoted.trim_start())));
+        } else {
+            paragraph_lines.push(line);
+        }
+        i += 1;
+    }
+    list.finish(&mut blocks);
+    flush_paragraph(&mut paragraph_lines, &mut blocks);
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::{build_children, NoteFormat, NoteLayout, NoteStyle};
+
+    fn block_types(blocks: &[Value]) -> Vec<&str> {
+        blocks.iter().map(|block| block["type"].as_str().unwrap()).collect()
+    }
+
+    // The text of a block's runs, joined
+    fn text(block: &Value) -> String {
+        let block_type = block["type"].as_str().unwrap();
+        block[block_type]["rich_text"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|run| run["text"]["content"].as_str().unwrap())
+            .collect()
+    }
+
+    fn children(block: &Value) -> &[Value] {
+        let block_type = block["type"].as_str().unwrap();
+        block[block_type]["children"].as_array().map_or(&[], Vec::as_slice)
+    }
+
+    #[test]
+    fn markdown_notes_become_blocks() {
+        let note = "# Plan\nfirst line\nsecond line\n\n> quoted\n- item\n  - nested\n1. one\n[x] done\n---\n```rs\nlet a = 1;\n```\nlast";
+        let blocks = markdown_blocks(note);
+
+        assert_eq!(
+            block_types(&blocks),
+            vec!["heading_1", "paragraph", "quote", "bulleted_list_item", "numbered_list_item", "to_do", "divider", "code", "paragraph"]
+        );
+        assert_eq!(text(&blocks[0]), "Plan");
+        assert_eq!(text(&blocks[1]), "first line\nsecond line", "lines without a blank between them are one paragraph");
+        assert_eq!(text(&blocks[2]), "quoted");
+        assert_eq!(block_types(children(&blocks[3])), vec!["bulleted_list_item"]);
+        assert_eq!(text(&children(&blocks[3])[0]), "nested");
+        assert_eq!(text(&blocks[7]), "let a = 1;");
+        assert_eq!(text(&blocks[8]), "last");
+    }
+
+    #[test]
+    fn lists_nest_as_deep_as_notion_allows() {
+        let blocks = markdown_blocks("- a\n  - b\n    - c\n      - d\n- e");
+
+        assert_eq!(block_types(&blocks), vec!["bulleted_list_item", "bulleted_list_item"]);
+        let b = &children(&blocks[0])[0];
+        assert_eq!(text(b), "b");
+        let deepest: Vec<String> = children(b).iter().map(text).collect();
+        assert_eq!(deepest, vec!["c", "d"], "a fourth level becomes a sibling of the third");
+        assert_eq!(text(&blocks[1]), "e");
+    }
+
+    #[test]
+    fn text_that_doesnt_parse_is_kept_as_typed() {
+        assert!(markdown_blocks("").is_empty());
+        assert!(markdown_blocks("  \n\n\t").is_empty());
+
+        let note = "2 * 3 * 4 and **unclosed, a_b_c, `tick and #tag";
+        let blocks = markdown_blocks(note);
+        assert_eq!(block_types(&blocks), vec!["paragraph"]);
+        assert_eq!(text(&blocks[0]), note);
+    }
+
+    #[test]
+    fn plain_notes_take_dash_and_star_bullets() {
+        assert_eq!(plain_list_item("- milk"), Some((0, "bulleted_list_item", "milk")));
+        assert_eq!(plain_list_item("* eggs"), Some((0, "bulleted_list_item", "eggs")));
+        assert_eq!(plain_list_item("  - nested"), Some((2, "bulleted_list_item", "nested")));
+        assert_eq!(plain_list_item("\t- tabbed"), Some((4, "bulleted_list_item", "tabbed")));
+
+        // Only Markdown notes take "+" bullets; a dash needs a space after it
+        assert_eq!(plain_list_item("+ plus"), None);
+        assert_eq!(plain_list_item("-5 degrees"), None);
+        assert_eq!(plain_list_item("--flag"), None);
+        assert_eq!(list_item("+ plus"), Some((0, "bulleted_list_item", "plus")));
+    }
+
+    #[test]
+    fn numbered_lines_in_any_order_are_list_items() {
+        assert_eq!(plain_list_item("1. first"), Some((0, "numbered_list_item", "first")));
+        assert_eq!(plain_list_item("7. any number"), Some((0, "numbered_list_item", "any number")));
+        assert_eq!(plain_list_item("  12. nested"), Some((2, "numbered_list_item", "nested")));
+
+        // Numbers that are part of the text
+        assert_eq!(plain_list_item("1.5 million"), None);
+        assert_eq!(plain_list_item("3 apples"), None);
+        assert_eq!(plain_list_item(". dot"), None);
+
+        // Markdown notes also take "1)"; plain notes leave it as text
+        assert_eq!(plain_list_item("1) first"), None);
+        assert_eq!(list_item("1) first"), Some((0, "numbered_list_item", "first")));
+    }
+
+    #[test]
+    fn a_note_of_only_numbered_lines_is_one_list() {
+        let blocks = markdown_blocks("1. milk\n2. eggs\n2. bread\n9. coffee");
+        assert_eq!(block_types(&blocks), vec!["numbered_list_item"; 4]);
+        let items: Vec<String> = blocks.iter().map(text).collect();
+        assert_eq!(items, vec!["milk", "eggs", "bread", "coffee"]);
+    }
+
+    #[test]
+    fn a_list_can_follow_a_paragraph() {
+        let blocks = markdown_blocks("Shopping for Saturday:\n1. milk\n2. eggs");
+        assert_eq!(block_types(&blocks), vec!["paragraph", "numbered_list_item", "numbered_list_item"]);
+        assert_eq!(text(&blocks[0]), "Shopping for Saturday:");
+        assert_eq!(text(&blocks[2]), "eggs");
+
+        // "1.5 million" after a paragraph is still part of it
+        let blocks = markdown_blocks("Growth this year:\n1.5 million users");
+        assert_eq!(block_types(&blocks), vec!["paragraph"]);
+        assert_eq!(text(&blocks[0]), "Growth this year:\n1.5 million users");
+    }
+
+    #[test]
+    fn checkbox_lines_are_tasks() {
+        assert_eq!(task_item("[ ] call Sam"), Some((0, false, "call Sam")));
+        assert_eq!(task_item("[] call Sam"), Some((0, false, "call Sam")));
+        assert_eq!(task_item("[x] done"), Some((0, true, "done")));
+        assert_eq!(task_item("[X] done"), Some((0, true, "done")));
+        assert_eq!(task_item("- [ ] bulleted"), Some((0, false, "bulleted")));
+        assert_eq!(task_item("  * [x] nested"), Some((2, true, "nested")));
+        assert_eq!(task_item("[ ]"), Some((0, false, "")));
+
+        assert_eq!(task_item("[x]ray"), None);
+        assert_eq!(task_item("[link](https://example.com)"), None);
+        assert_eq!(task_item("see [ ] later"), None);
+    }
+
+    #[test]
+    fn hashes_map_to_notion_heading_levels() {
+        let level = |line: &str| heading(line).map(|block| (block["type"].as_str().unwrap().to_string(), text(&block)));
+
+        assert_eq!(level("# One"), Some(("heading_1".into(), "One".into())));
+        assert_eq!(level("## Two"), Some(("heading_2".into(), "Two".into())));
+        assert_eq!(level("### Three"), Some(("heading_3".into(), "Three".into())));
+        assert_eq!(level("###### Six"), Some(("heading_3".into(), "Six".into())), "deeper levels are the smallest");
+
+        // Closing hashes are dropped, but only after a space
+        assert_eq!(level("## Two ##"), Some(("heading_2".into(), "Two".into())));
+        assert_eq!(level("# Learning C#"), Some(("heading_1".into(), "Learning C#".into())));
+
+        // Tags and bare hashes are text
+        assert_eq!(level("#idea"), None);
+        assert_eq!(level("##"), None);
+        assert_eq!(block_types(&markdown_blocks("#idea for later")), vec!["paragraph"]);
+    }
+
+    #[test]
+    fn code_fences_keep_their_content_and_language() {
+        let lines = ["before", "```Python", "x = **not bold**", "", "  indented", "```", "after"];
+        let (block, next) = code_fence(&lines, 1).unwrap();
+        assert_eq!(block["code"]["language"], "python");
+        assert_eq!(text(&block), "x = **not bold**\n\n  indented");
+        assert_eq!(block["code"]["rich_text"][0]["annotations"]["bold"], false);
+        assert_eq!(next, 6);
+        assert!(code_fence(&lines, 0).is_none());
+
+        let language = |fence: &str| code_fence(&[fence, "x", "```"], 0).unwrap().0["code"]["language"].clone();
+        assert_eq!(language("```ts"), "typescript");
+        assert_eq!(language("```sh"), "shell");
+        assert_eq!(language("```c++"), "c++");
+        assert_eq!(language("```"), "plain text");
+        assert_eq!(language("```brainfuck"), "plain text");
+
+        // An unterminated fence runs to the end of the note
+        let (block, next) = code_fence(&["```", "a", "b"], 0).unwrap();
+        assert_eq!((text(&block).as_str(), next), ("a\nb", 3));
+    }
+
+    // Each run of inline Markdown as its text and annotations, e.g. ("word", "bi")
+    fn spans(text: &str) -> Vec<(String, String)> {
+        inline_runs(text)
+            .iter()
+            .map(|run| {
+                let style: String = [("bold", 'b'), ("italic", 'i'), ("code", 'c')]
+                    .iter()
+                    .filter(|(name, _)| run["annotations"][*name] == true)
+                    .map(|(_, mark)| *mark)
+                    .collect();
+                (run["text"]["content"].as_str().unwrap().to_string(), style)
+            })
+            .collect()
+    }
+
+    fn expected(spans: &[(&str, &str)]) -> Vec<(String, String)> {
+        spans.iter().map(|(text, style)| (text.to_string(), style.to_string())).collect()
+    }
+
+    #[test]
+    fn inline_markers_style_their_spans() {
+        assert_eq!(
+            spans("**bold** and *it* and `code`"),
+            expected(&[("bold", "b"), (" and ", ""), ("it", "i"), (" and ", ""), ("code", "c")])
+        );
+        assert_eq!(spans("__bold__ _it_"), expected(&[("bold", "b"), (" ", ""), ("it", "i")]));
+        assert_eq!(
+            spans("**bold *and* more**"),
+            expected(&[("bold ", "b"), ("and", "bi"), (" more", "b")])
+        );
+        assert_eq!(spans("`**literal** _too_`"), expected(&[("**literal** _too_", "c")]));
+        assert_eq!(spans(""), expected(&[("", "")]));
+    }
+
+    #[test]
+    fn markers_without_a_partner_or_inside_words_stay_text() {
+        for literal in ["snake_case_name", "2 * 3 * 4", "** not bold **", "**unclosed", "a `tick", "5 * 3 = 15"] {
+            assert_eq!(spans(literal), expected(&[(literal, "")]), "{}", literal);
+        }
+    }
+
+    #[test]
+    fn dashed_lines_are_dividers() {
+        let blocks = markdown_blocks("above\n---\n-----  \nbelow");
+        assert_eq!(block_types(&blocks), vec!["paragraph", "divider", "divider", "paragraph"]);
+        assert_eq!(blocks[1]["divider"], serde_json::json!({}));
+
+        assert!(!is_divider("--"));
+        assert!(!is_divider("--- end"));
+        assert!(!is_divider("—"));
+        assert_eq!(block_types(&markdown_blocks("-- signed")), vec!["paragraph"]);
+    }
+
+    #[test]
+    fn dividers_at_either_end_bring_no_empty_paragraphs() {
+        let leading = markdown_blocks("---\nfirst thought");
+        assert_eq!(block_types(&leading), vec!["divider", "paragraph"]);
+        assert_eq!(text(&leading[1]), "first thought");
+
+        let trailing = markdown_blocks("last thought\n---\n");
+        assert_eq!(block_types(&trailing), vec!["paragraph", "divider"]);
+        assert_eq!(text(&trailing[0]), "last thought");
+
+        let both = markdown_blocks("\n---\n\nonly thought\n\n---\n\n");
+        assert_eq!(block_types(&both), vec!["divider", "paragraph", "divider"]);
+        assert_eq!(block_types(&markdown_blocks("---")), vec!["divider"]);
+
+        // With the timestamp, the note's blocks follow it as they are
+        let style = NoteStyle { format: NoteFormat::Markdown, ..NoteStyle::default() };
+        for note in ["---\nfirst thought", "last thought\n---"] {
+            let children = build_children("[09:00]", note, NoteLayout::TimestampPrefix, style, false);
+            let blank = children.iter().filter(|block| block["type"] == "paragraph" && text(block).trim().is_empty());
+            assert_eq!(blank.count(), 0, "{:?}", children);
+        }
+    }
+
+    #[test]
+    fn urls_end_where_the_sentence_takes_over() {
+        assert_eq!(url_at("https://example.com/a?b=1#c rest"), Some("https://example.com/a?b=1#c"));
+        assert_eq!(url_at("https://example.com/a)."), Some("https://example.com/a"));
+        assert_eq!(url_at("https://en.wikipedia.org/wiki/Rust_(language))."), Some("https://en.wikipedia.org/wiki/Rust_(language)"));
+        assert_eq!(url_at("http://example.com/x,"), Some("http://example.com/x"));
+        assert_eq!(url_at("https://example.com/<b>"), Some("https://example.com/"));
+
+        assert_eq!(url_at("https://"), None);
+        assert_eq!(url_at("www.example.com"), None);
+        assert_eq!(url_at("ftp://example.com"), None);
+        let too_long = format!("https://example.com/{}", "a".repeat(MAX_URL_LEN));
+        assert_eq!(url_at(&too_long), None);
+    }
+
+    #[test]
+    fn urls_become_links_and_keep_their_markers() {
+        let runs = inline_runs("see https://example.com/a_b_c*d* now");
+        let contents: Vec<&str> = runs.iter().map(|run| run["text"]["content"].as_str().unwrap()).collect();
+        assert_eq!(contents, vec!["see ", "https://example.com/a_b_c*d*", " now"]);
+        assert_eq!(runs[1]["text"]["link"]["url"], "https://example.com/a_b_c*d*");
+        assert!(runs[0]["text"]["link"].is_null());
+        assert_eq!(runs[1]["annotations"]["italic"], false);
+
+        // Inside emphasis the link keeps the style; glued to a word it isn't a link
+        assert_eq!(inline_runs("**https://example.com**")[0]["annotations"]["bold"], true);
+        assert!(inline_runs("xhttps://example.com")[0]["text"]["link"].is_null());
+    }
+
+    #[test]
+    fn a_note_of_only_a_url_is_that_url() {
+        assert_eq!(sole_url("  https://example.com/page \n"), Some("https://example.com/page"));
+        assert_eq!(sole_url("https://example.com/page."), None);
+        assert_eq!(sole_url("read https://example.com"), None);
+    }
+}
@@ -0,0 +1,594 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+
+use crate::config::app_data_path;
+use crate::secrets::{self, Secrets};
+
+// Changes to stored data between versions, run at startup in version order
+// before the config is read. Each step works on an in-memory copy of the
+// files it touches: the copy is backed up before the step, and the step's
+// result is only kept if it succeeds. A failed step stops the later ones; the
+// app starts with what the earlier steps produced, and the failure is kept in
+// the history until a later launch gets past it.
+const MIGRATIONS_FILE: &str = "migrations.json";
+const BACKUP_DIR: &str = "migration_backups";
+const CONFIG_FILE: &str = "config.json";
+
+// Read-only view of the config a step uses to decide whether it applies
+pub struct ConfigSnapshot<'a>(pub &'a Value);
+
+impl ConfigSnapshot<'_> {
+    pub fn get(&self, field: &str) -> &Value {
+        &self.0[field]
+    }
+}
+
+// The data migrations work on, before it is read into AppConfig
+#[derive(Debug, Clone, Default)]
+pub struct AppData {
+    // config.json as parsed JSON
+    pub config: Value,
+    // Secrets taken out of config.json, for AppConfig::load to put in the secret store
+    pub legacy_secrets: Secrets,
+}
+
+impl AppData {
+    pub fn snapshot(&self) -> ConfigSnapshot<'_> {
+        ConfigSnapshot(&self.config)
+    }
+
+    // Contents of `file` to back up: as it stands, but with inline secrets
+    // redacted. The secrets step only drops them from config.json once they
+    // are in the secret store, so the backup never needs them.
+    fn backup_contents(&self, file: &str) -> Option<String> {
+        match file {
+            CONFIG_FILE => {
+                let mut config = self.config.clone();
+                secrets::redact_legacy(&mut config);
+                serde_json::to_string_pretty(&config).ok()
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct MigrationReport {
+    pub version: u32,
+    pub name: String,
+    // One line per change, e.g. "selected_page_id: 3e2f... -> 3e2f-..."
+    pub changes: Vec<String>,
+}
+
+pub trait Migration {
+    // Position in the order; never reused once released
+    fn version(&self) -> u32;
+    fn name(&self) -> &'static str;
+    // Files the step may change, backed up before it runs
+    fn files(&self) -> &'static [&'static str];
+    fn applies(&self, config: &ConfigSnapshot) -> bool;
+    // Must be idempotent: running it on already migrated data changes nothing
+    fn run(&self, data: &mut AppData) -> Result<MigrationReport, String>;
+
+    // What `run` would change, without changing anything
+    fn dry_run(&self, data: &AppData) -> Result<MigrationReport, String> {
+        self.run(&mut data.clone())
+    }
+
+    // Runs whenever it applies, even if recorded; for data an older version can write again
+    fn repeatable(&self) -> bool {
+        false
+    }
+
+    fn report(&self, changes: Vec<String>) -> MigrationReport {
+        MigrationReport {
+            version: self.version(),
+            name: self.name().to_string(),
+            changes,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MigrationRecord {
+    pub version: u32,
+    pub name: String,
+    pub applied_at: String,
+    pub changes: Vec<String>,
+    // Backup file paths taken before the step
+    pub backups: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MigrationFailure {
+    pub version: u32,
+    pub name: String,
+    pub failed_at: String,
+    pub error: String,
+    pub backups: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct MigrationHistory {
+    // Oldest first
+    pub applied: Vec<MigrationRecord>,
+    // The last launch's failure, cleared once every step gets through
+    pub last_failure: Option<MigrationFailure>,
+}
+
+impl MigrationHistory {
+    fn has_applied(&self, version: u32) -> bool {
+        self.applied.iter().any(|record| record.version == version)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MigrationOutcome {
+    pub data: AppData,
+    pub applied: Vec<MigrationRecord>,
+    pub failure: Option<MigrationFailure>,
+}
+
+// Version 1: secrets written inline by older versions move to the secret store.
+// Repeatable, as running an older version again writes them inline again.
+pub struct InlineSecrets;
+
+impl Migration for InlineSecrets {
+    fn version(&self) -> u32 {
+        1
+    }
+
+    fn name(&self) -> &'static str {
+        "Move secrets out of config.json"
+    }
+
+    fn files(&self) -> &'static [&'static str] {
+        &[CONFIG_FILE]
+    }
+
+    fn applies(&self, config: &ConfigSnapshot) -> bool {
+        let inline = |node: &Value| node.get("notion_api_token").is_some();
+        inline(config.0)
+            || config.get("mirror_webhook_secret").is_string()
+            || config.get("capture_profiles").as_array().is_some_and(|profiles| profiles.iter().any(inline))
+    }
+
+    fn run(&self, data: &mut AppData) -> Result<MigrationReport, String> {
+        let taken = secrets::take_legacy(&mut data.config);
+        let mut changes = Vec::new();
+        if !taken.main_token().is_empty() {
+            changes.push("API token".to_string());
+        }
+        if taken.webhook_secret().is_some() {
+            changes.push("mirror webhook secret".to_string());
+        }
+        changes.extend(taken.profile_names().map(|name| format!("token of profile \"{}\"", name)));
+        data.legacy_secrets.merge_missing(taken);
+        Ok(self.report(changes))
+    }
+
+    fn repeatable(&self) -> bool {
+        true
+    }
+}
+
+// A page id in the form the Notion API returns ("3e2f0000-0000-...", lowercase,
+// dashed), or None if it isn't one
+pub fn normalize_page_id(id: &str) -> Option<String> {
+    let hex: String = id.trim().chars().filter(|c| *c != '-').collect::<String>().to_lowercase();
+    if hex.len() != 32 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some(format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..]))
+}
+
+// Version 2: page ids pasted by hand (no dashes, upper case) stored the way
+// Notion returns them, so plain comparisons and receipts line up
+pub struct NormalizePageIds;
+
+impl NormalizePageIds {
+    // Paths of the page-id fields in the config, with the value at each
+    fn page_ids(config: &Value) -> Vec<(String, String)> {
+        let mut ids = Vec::new();
+        for field in ["selected_page_id", "sandbox_page_id"] {
+            if let Some(id) = config[field].as_str() {
+                ids.push((field.to_string(), id.to_string()));
+            }
+        }
+        if let Some(profiles) = config["capture_profiles"].as_array() {
+            for (i, profile) in profiles.iter().enumerate() {
+                if let Some(id) = profile["page_id"].as_str() {
+                    ids.push((format!("capture_profiles/{}/page_id", i), id.to_string()));
+                }
+            }
+        }
+        ids
+    }
+
+    // Ids that would change, with their normalized form
+    fn changes(config: &Value) -> Vec<(String, String, String)> {
+        Self::page_ids(config)
+            .into_iter()
+            .filter_map(|(path, id)| {
+                let normalized = normalize_page_id(&id)?;
+                (normalized != id).then_some((path, id, normalized))
+            })
+            .collect()
+    }
+}
+
+impl Migration for NormalizePageIds {
+    fn version(&self) -> u32 {
+        2
+    }
+
+    fn name(&self) -> &'static str {
+        "Normalize stored page ids"
+    }
+
+    fn files(&self) -> &'static [&'static str] {
+        &[CONFIG_FILE]
+    }
+
+    fn applies(&self, config: &ConfigSnapshot) -> bool {
+        !Self::changes(config.0).is_empty()
+    }
+
+    fn run(&self, data: &mut AppData) -> Result<MigrationReport, String> {
+        let mut changes = Vec::new();
+        for (path, id, normalized) in Self::changes(&data.config) {
+            let slot = data
+                .config
+                .pointer_mut(&format!("/{}", path))
+                .ok_or_else(|| format!("{} disappeared while migrating", path))?;
+            *slot = Value::String(normalized.clone());
+            changes.push(format!("{}: {} -> {}", path, id, normalized));
+        }
+        Ok(self.report(changes))
+    }
+}
+
+// Every step, in version order
+pub fn steps() -> Vec<Box<dyn Migration>> {
+    vec![Box::new(InlineSecrets), Box::new(NormalizePageIds)]
+}
+
+// Run the steps that are due against `data`. `backup` is called before each
+// step with the data as it stands and returns the backup paths it wrote.
+pub fn migrate(
+    steps: &[Box<dyn Migration>],
+    mut data: AppData,
+    history: &MigrationHistory,
+    mut backup: impl FnMut(&dyn Migration, &AppData) -> Result<Vec<String>, String>,
+) -> MigrationOutcome {
+    let mut applied = Vec::new();
+    let mut failure = None;
+    for step in steps {
+        let due = step.repeatable() || !history.has_applied(step.version());
+        if !due || !step.applies(&data.snapshot()) {
+            continue;
+        }
+
+        let now = Utc::now().to_rfc3339();
+        let fail = |error: String, backups: Vec<String>| MigrationFailure {
+            version: step.version(),
+            name: step.name().to_string(),
+            failed_at: now.clone(),
+            error,
+            backups,
+        };
+        let backups = match backup(step.as_ref(), &data) {
+            Ok(backups) => backups,
+            Err(e) => {
+                failure = Some(fail(format!("Backup failed, step not run: {}", e), Vec::new()));
+                break;
+            }
+        };
+        let mut next = data.clone();
+        match step.run(&mut next) {
+            Ok(report) => {
+                data = next;
+                applied.push(MigrationRecord {
+                    version: report.version,
+                    name: report.name,
+                    applied_at: now.clone(),
+                    changes: report.changes,
+                    backups,
+                });
+            }
+            Err(e) => {
+                failure = Some(fail(e, backups));
+                break;
+            }
+        }
+    }
+    MigrationOutcome { data, applied, failure }
+}
+
+// What the due steps would change, without backups or writes
+pub fn plan(steps: &[Box<dyn Migration>], data: &AppData, history: &MigrationHistory) -> Result<Vec<MigrationReport>, String> {
+    let mut data = data.clone();
+    let mut reports = Vec::new();
+    for step in steps {
+        let due = step.repeatable() || !history.has_applied(step.version());
+        if due && step.applies(&data.snapshot()) {
+            reports.push(step.dry_run(&data)?);
+            step.run(&mut data)?;
+        }
+    }
+    Ok(reports)
+}
+
+pub fn load_history() -> MigrationHistory {
+    app_data_path(MIGRATIONS_FILE)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_history(history: &MigrationHistory) -> Result<(), String> {
+    let path = app_data_path(MIGRATIONS_FILE)?;
+    let contents = serde_json::to_string_pretty(history).map_err(|e| format!("Failed to serialize migration history: {}", e))?;
+    fs::write(&path, contents).map_err(|e| format!("Failed to write migration history: {}", e))
+}
+
+// Backups have secrets redacted but still describe the user's pages, so they
+// get the same file permissions as the secrets file
+fn write_backups(step: &dyn Migration, data: &AppData) -> Result<Vec<String>, String> {
+    let dir = app_data_path(BACKUP_DIR)?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create backup directory: {}", e))?;
+    let stamp = Utc::now().format("%Y%m%dT%H%M%S");
+    let mut written = Vec::new();
+    for file in step.files() {
+        let Some(contents) = data.backup_contents(file) else {
+            continue;
+        };
+        let path = dir.join(format!("{:03}-{}-{}", step.version(), stamp, file));
+        fs::write(&path, contents).map_err(|e| format!("Failed to back up {}: {}", file, e))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o600))
+                .map_err(|e| format!("Failed to restrict backup of {}: {}", file, e))?;
+        }
+        written.push(path.display().to_string());
+    }
+    Ok(written)
+}
+
+// Called by AppConfig::load with the parsed config.json
+pub fn run_startup(config: Value) -> MigrationOutcome {
+    let data = AppData { config, ..Default::default() };
+    let outcome = migrate(&steps(), data, &load_history(), write_backups);
+    if let Some(failure) = &outcome.failure {
+        eprintln!(
+            "Migration {} ({}) failed, later migrations skipped: {}",
+            failure.version, failure.name, failure.error
+        );
+    }
+    outcome
+}
+
+// Record the outcome once the migrated data has been saved
+pub fn record(outcome: &MigrationOutcome) {
+    let mut history = load_history();
+    if outcome.applied.is_empty() && outcome.failure.is_none() && history.last_failure.is_none() {
+        return;
+    }
+    for record in &outcome.applied {
+        history.applied.retain(|known| known.version != record.version);
+        history.applied.push(record.clone());
+    }
+    history.last_failure = outcome.failure.clone();
+    if let Err(e) = save_history(&history) {
+        eprintln!("{}", e);
+    }
+}
+
+// Delete every backup, e.g. when resetting secrets: backups written by older
+// versions still hold the inline tokens. Returns how many were deleted.
+pub fn clear_backups() -> Result<usize, String> {
+    let dir = app_data_path(BACKUP_DIR)?;
+    let count = fs::read_dir(&dir).map(|entries| entries.count()).unwrap_or(0);
+    if dir.exists() {
+        fs::remove_dir_all(&dir).map_err(|e| format!("Failed to delete migration backups: {}", e))?;
+    }
+
+    // The history shouldn't point at files that are gone
+    let mut history = load_history();
+    let listed = history
+        .applied
+        .iter_mut()
+        .map(|record| &mut record.backups)
+        .chain(history.last_failure.as_mut().map(|failure| &mut failure.backups));
+    let mut changed = false;
+    for backups in listed {
+        changed |= !backups.is_empty();
+        backups.clear();
+    }
+    if changed {
+        save_history(&history)?;
+    }
+    Ok(count)
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct MigrationDiagnostics {
+    pub history: MigrationHistory,
+    // What would run against config.json as it is now
+    pub pending: Vec<MigrationReport>,
+}
+
+// Applied migrations, the last failure and anything still pending
+#[tauri::command]
+pub fn get_migration_history() -> Result<MigrationDiagnostics, String> {
+    let history = load_history();
+    let path = app_data_path(CONFIG_FILE)?;
+    let pending = match fs::read_to_string(&path) {
+        Ok(contents) => {
+            let config = serde_json::from_str(&contents).map_err(|e| format!("Failed to parse config: {}", e))?;
+            plan(&steps(), &AppData { config, ..Default::default() }, &history)?
+        }
+        Err(_) => Vec::new(),
+    };
+    Ok(MigrationDiagnostics { history, pending })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{use_test_data_dir, AppConfig};
+    use serde_json::json;
+
+    // config.json as an older version wrote it, and as it should look once migrated
+    const LEGACY_CONFIG: &str = include_str!("../fixtures/migrations/legacy_config.json");
+    const MIGRATED_CONFIG: &str = include_str!("../fixtures/migrations/legacy_config.migrated.json");
+
+    fn legacy_data() -> AppData {
+        AppData {
+            config: serde_json::from_str(LEGACY_CONFIG).unwrap(),
+            ..Default::default()
+        }
+    }
+
+    fn no_backup(_: &dyn Migration, _: &AppData) -> Result<Vec<String>, String> {
+        Ok(Vec::new())
+    }
+
+    fn versions(records: &[MigrationRecord]) -> Vec<u32> {
+        records.iter().map(|record| record.version).collect()
+    }
+
+    // Fails whenever it runs
+    struct Broken;
+
+    impl Migration for Broken {
+        fn version(&self) -> u32 {
+            99
+        }
+
+        fn name(&self) -> &'static str {
+            "Broken"
+        }
+
+        fn files(&self) -> &'static [&'static str] {
+            &[CONFIG_FILE]
+        }
+
+        fn applies(&self, _: &ConfigSnapshot) -> bool {
+            true
+        }
+
+        fn run(&self, data: &mut AppData) -> Result<MigrationReport, String> {
+            data.config["half_done"] = json!(true);
+            Err("disk full".into())
+        }
+    }
+
+    #[test]
+    fn the_legacy_fixture_migrates_to_the_current_format() {
+        let outcome = migrate(&steps(), legacy_data(), &MigrationHistory::default(), no_backup);
+
+        assert_eq!(outcome.failure, None);
+        assert_eq!(versions(&outcome.applied), vec![1, 2]);
+        assert_eq!(outcome.data.config, serde_json::from_str::<Value>(MIGRATED_CONFIG).unwrap());
+        assert_eq!(
+            outcome.applied[0].changes,
+            vec!["API token", "mirror webhook secret", "token of profile \"work\""]
+        );
+        assert_eq!(outcome.applied[1].changes.len(), 2);
+
+        let secrets = &outcome.data.legacy_secrets;
+        assert_eq!(secrets.main_token(), "secret_fixture_main");
+        assert_eq!(secrets.profile_token("work"), Some("secret_fixture_work"));
+        assert_eq!(secrets.webhook_secret(), Some("whsec_fixture"));
+
+        let config: AppConfig = serde_json::from_value(outcome.data.config).unwrap();
+        assert_eq!(config.capture_profiles.len(), 2);
+    }
+
+    #[test]
+    fn migrated_data_is_left_alone() {
+        let first = migrate(&steps(), legacy_data(), &MigrationHistory::default(), no_backup);
+        let history = MigrationHistory { applied: first.applied, last_failure: None };
+
+        let data = AppData { config: first.data.config.clone(), ..Default::default() };
+        let second = migrate(&steps(), data, &history, no_backup);
+        assert!(second.applied.is_empty());
+        assert_eq!(second.data.config, first.data.config);
+        assert!(plan(&steps(), &second.data, &history).unwrap().is_empty());
+    }
+
+    #[test]
+    fn inline_secrets_written_again_by_an_older_version_move_again() {
+        let mut history = MigrationHistory::default();
+        history.applied = migrate(&steps(), legacy_data(), &history, no_backup).applied;
+
+        let outcome = migrate(&steps(), legacy_data(), &history, no_backup);
+        assert_eq!(versions(&outcome.applied), vec![1], "page ids are only normalized once");
+        assert_eq!(outcome.data.config.get("notion_api_token"), None);
+    }
+
+    #[test]
+    fn a_failing_step_keeps_earlier_results_and_skips_later_steps() {
+        let steps: Vec<Box<dyn Migration>> = vec![Box::new(InlineSecrets), Box::new(Broken), Box::new(NormalizePageIds)];
+        let outcome = migrate(&steps, legacy_data(), &MigrationHistory::default(), |step, _| {
+            Ok(vec![format!("backup-{}", step.version())])
+        });
+
+        assert_eq!(versions(&outcome.applied), vec![1]);
+        let failure = outcome.failure.unwrap();
+        assert_eq!((failure.version, failure.error.as_str()), (99, "disk full"));
+        assert_eq!(failure.backups, vec!["backup-99"]);
+        assert_eq!(outcome.data.config.get("half_done"), None, "the failed step's changes are dropped");
+        assert_eq!(outcome.data.config["selected_page_id"], "3E2F00000000000000000000000000AB");
+        assert_eq!(outcome.data.config.get("notion_api_token"), None);
+    }
+
+    #[test]
+    fn a_step_whose_backup_fails_doesnt_run() {
+        let outcome = migrate(&steps(), legacy_data(), &MigrationHistory::default(), |_, _| Err("read-only".into()));
+
+        assert!(outcome.applied.is_empty());
+        assert_eq!(outcome.failure.unwrap().version, 1);
+        assert_eq!(outcome.data.config, legacy_data().config);
+    }
+
+    #[test]
+    fn plan_reports_the_due_steps_without_changing_anything() {
+        let data = legacy_data();
+        let reports = plan(&steps(), &data, &MigrationHistory::default()).unwrap();
+
+        let applied = migrate(&steps(), data.clone(), &MigrationHistory::default(), no_backup).applied;
+        let planned: Vec<&Vec<String>> = reports.iter().map(|report| &report.changes).collect();
+        assert_eq!(planned, applied.iter().map(|record| &record.changes).collect::<Vec<_>>());
+        assert_eq!(data.config, legacy_data().config);
+    }
+
+    #[test]
+    fn backups_redact_inline_secrets_and_reset_deletes_them() {
+        let dir = use_test_data_dir();
+        let outcome = run_startup(legacy_data().config);
+        record(&outcome);
+
+        let backups: Vec<&String> = outcome.applied.iter().flat_map(|record| &record.backups).collect();
+        assert_eq!(backups.len(), 2);
+        for backup in &backups {
+            let contents = fs::read_to_string(backup).unwrap();
+            assert!(!contents.contains("secret_fixture") && !contents.contains("whsec_fixture"), "{}", contents);
+        }
+        let first: Value = serde_json::from_str(&fs::read_to_string(backups[0]).unwrap()).unwrap();
+        assert_eq!(first["notion_api_token"], secrets::REDACTED);
+        assert_eq!(first["capture_profiles"][0]["notion_api_token"], secrets::REDACTED);
+        assert_eq!(first["selected_page_id"], "3E2F00000000000000000000000000AB");
+
+        assert_eq!(clear_backups().unwrap(), 2);
+        assert!(!dir.join(BACKUP_DIR).exists());
+        assert!(load_history().applied.iter().all(|record| record.backups.is_empty()));
+        assert_eq!(clear_backups().unwrap(), 0);
+    }
+}
@@ -0,0 +1,533 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+// Test stand-in for api.notion.com: a local HTTP server answering with
+// whatever the test's handler says. Requests made with a mock's token are
+// redirected to it in NotionApiClient::send_raw, so the real client, rate
+// limiter and retry code run against it. `FakeWorkspace` is a small model of
+// pages and their children for handlers that need Notion's behaviour.
+
+static NEXT_TOKEN: AtomicU64 = AtomicU64::new(1);
+
+lazy_static::lazy_static! {
+    // Mocked tokens and where their requests go
+    static ref ROUTES: Mutex<HashMap<String, Route>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Route {
+    addr: SocketAddr,
+    // Replaces the client's request timeout, so timeouts can be tested quickly
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub method: String,
+    pub path: String,
+    pub query: HashMap<String, String>,
+    // Header names lowercased
+    pub headers: HashMap<String, String>,
+    pub body: Option<Value>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MockReply {
+    pub status: u16,
+    pub body: Value,
+    pub headers: Vec<(String, String)>,
+    // Waited after the handler ran, so a slow reply can still have had its effect
+    pub delay: Duration,
+    // Waited between the headers and the body
+    pub body_delay: Duration,
+    // Sent instead of `body`, for replies that aren't JSON
+    pub raw_body: Option<String>,
+    // Sent without a content-length, so the body ends when the connection closes
+    pub streamed: bool,
+}
+
+impl MockReply {
+    pub fn json(status: u16, body: Value) -> Self {
+        MockReply {
+            status,
+            body,
+            headers: Vec::new(),
+            delay: Duration::ZERO,
+            body_delay: Duration::ZERO,
+            raw_body: None,
+            streamed: false,
+        }
+    }
+
+    // A reply with any content type, e.g. a proxy's HTML error page
+    pub fn text(status: u16, content_type: &str, body: &str) -> Self {
+        let mut reply = MockReply::json(status, Value::Null).header("content-type", content_type);
+        reply.raw_body = Some(body.to_string());
+        reply
+    }
+
+    // A Notion error object
+    pub fn error(status: u16, code: &str, message: &str) -> Self {
+        MockReply::json(status, json!({ "object": "error", "status": status, "code": code, "message": message }))
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn delayed(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    pub fn slow_body(mut self, delay: Duration) -> Self {
+        self.body_delay = delay;
+        self
+    }
+
+    pub fn streamed(mut self) -> Self {
+        self.streamed = true;
+        self
+    }
+}
+
+// Requests a server is answering right now, and the most it ever answered at once
+#[derive(Default)]
+struct OpenRequests {
+    now: AtomicUsize,
+    most: AtomicUsize,
+}
+
+type Handler = dyn Fn(&MockRequest) -> MockReply + Send + Sync;
+
+pub struct MockNotion {
+    pub token: String,
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+    open: Arc<OpenRequests>,
+    // Extra tokens routed here by another_token
+    aliases: Mutex<Vec<String>>,
+    server: tokio::task::JoinHandle<()>,
+}
+
+impl MockNotion {
+    pub async fn start(handler: impl Fn(&MockRequest) -> MockReply + Send + Sync + 'static) -> MockNotion {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind the mock server");
+        let addr = listener.local_addr().expect("mock server address");
+        let token = format!("secret_mock{}", NEXT_TOKEN.fetch_add(1, Ordering::Relaxed));
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let open = Arc::new(OpenRequests::default());
+        let handler: Arc<Handler> = Arc::new(handler);
+
+        let recorded = requests.clone();
+        let counted = open.clone();
+        let server = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let handler = handler.clone();
+                let recorded = recorded.clone();
+                let counted = counted.clone();
+                tokio::spawn(async move {
+                    let _ = serve(stream, handler, recorded, counted).await;
+                });
+            }
+        });
+        ROUTES.lock().unwrap().insert(token.clone(), Route { addr, timeout: None });
+        MockNotion { token, addr, requests, open, aliases: Mutex::new(Vec::new()), server }
+    }
+
+    // A second integration's token answered by this same server
+    pub fn another_token(&self) -> String {
+        let token = format!("secret_mock{}", NEXT_TOKEN.fetch_add(1, Ordering::Relaxed));
+        let mut routes = ROUTES.lock().unwrap();
+        let route = routes[&self.token];
+        routes.insert(token.clone(), route);
+        self.aliases.lock().unwrap().push(token.clone());
+        token
+    }
+
+    // Plain URL of this server, for requests that aren't redirected by token (e.g. the mirror webhook)
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    // The most requests this server was answering at the same time
+    pub fn most_open(&self) -> usize {
+        self.open.most.load(Ordering::SeqCst)
+    }
+
+    // Requests with this token time out after `timeout` instead of the client's default
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        if let Some(route) = ROUTES.lock().unwrap().get_mut(&self.token) {
+            route.timeout = Some(timeout);
+        }
+        self
+    }
+
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    // How many requests had this method and a path starting with `path`
+    pub fn count(&self, method: &str, path: &str) -> usize {
+        self.requests().iter().filter(|request| request.method == method && request.path.starts_with(path)).count()
+    }
+}
+
+impl Drop for MockNotion {
+    fn drop(&mut self) {
+        let mut routes = ROUTES.lock().unwrap();
+        routes.remove(&self.token);
+        for alias in self.aliases.lock().unwrap().iter() {
+            routes.remove(alias);
+        }
+        self.server.abort();
+    }
+}
+
+// A token no server answers for; connections to it are refused
+pub async fn unreachable_token() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind a port");
+    let addr = listener.local_addr().expect("port address");
+    drop(listener);
+    let token = format!("secret_unreachable{}", NEXT_TOKEN.fetch_add(1, Ordering::Relaxed));
+    ROUTES.lock().unwrap().insert(token.clone(), Route { addr, timeout: None });
+    token
+}
+
+// Send a request made with a mocked token to its server instead of Notion
+pub fn redirect(token: &str, request: &mut reqwest::Request) {
+    let Some(route) = ROUTES.lock().unwrap().get(token).copied() else {
+        return;
+    };
+    let url = request.url_mut();
+    let _ = url.set_scheme("http");
+    let _ = url.set_host(Some(&route.addr.ip().to_string()));
+    let _ = url.set_port(Some(route.addr.port()));
+    if let Some(timeout) = route.timeout {
+        *request.timeout_mut() = Some(timeout);
+    }
+}
+
+async fn serve(
+    mut stream: TcpStream,
+    handler: Arc<Handler>,
+    recorded: Arc<Mutex<Vec<MockRequest>>>,
+    open: Arc<OpenRequests>,
+) -> std::io::Result<()> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let head_end = loop {
+        if let Some(end) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break end + 4;
+        }
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Ok(());
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..head_end]).to_string();
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let target = request_line.next().unwrap_or_default().to_string();
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+        .collect();
+    let content_length = headers
+        .get("content-length")
+        .and_then(|value| value.parse::<usize>().ok())
+        .unwrap_or(0);
+    while buffer.len() < head_end + content_length {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+
+    let (path, query) = target.split_once('?').unwrap_or((target.as_str(), ""));
+    let request = MockRequest {
+        method,
+        path: path.to_string(),
+        query: query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+        headers,
+        body: serde_json::from_slice(&buffer[head_end..]).ok(),
+    };
+    recorded.lock().unwrap().push(request.clone());
+    let answering = open.now.fetch_add(1, Ordering::SeqCst) + 1;
+    open.most.fetch_max(answering, Ordering::SeqCst);
+    let answered = answer(&mut stream, handler(&request)).await;
+    open.now.fetch_sub(1, Ordering::SeqCst);
+    answered
+}
+
+async fn answer(stream: &mut TcpStream, reply: MockReply) -> std::io::Result<()> {
+    tokio::time::sleep(reply.delay).await;
+
+    let body = reply.raw_body.clone().unwrap_or_else(|| reply.body.to_string());
+    let reason = reqwest::StatusCode::from_u16(reply.status)
+        .ok()
+        .and_then(|status| status.canonical_reason())
+        .unwrap_or("Unknown");
+    // Headers set on the reply replace the defaults of the same name
+    let mut headers = vec![("content-type".to_string(), "application/json".to_string())];
+    if !reply.streamed {
+        headers.push(("content-length".to_string(), body.len().to_string()));
+    }
+    headers.retain(|(name, _)| !reply.headers.iter().any(|(set, _)| set.eq_ignore_ascii_case(name)));
+    headers.extend(reply.headers.iter().cloned());
+    let mut response = format!("HTTP/1.1 {} {}\r\nconnection: close\r\n", reply.status, reason);
+    for (name, value) in &headers {
+        response.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    response.push_str("\r\n");
+    stream.write_all(response.as_bytes()).await?;
+    if !reply.body_delay.is_zero() {
+        stream.flush().await?;
+        tokio::time::sleep(reply.body_delay).await;
+    }
+    stream.write_all(body.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+// Pages and blocks as Notion keeps them, enough for appends, listings,
+// retrievals, updates and deletes to behave like the real thing
+#[derive(Default)]
+pub struct FakeWorkspace {
+    pages: HashMap<String, Value>,
+    // Child blocks by parent id, in page order
+    children: HashMap<String, Vec<Value>>,
+    next_id: u64,
+}
+
+impl FakeWorkspace {
+    pub fn new() -> Self {
+        FakeWorkspace::default()
+    }
+
+    // A shared workspace for a handler closure
+    pub fn shared() -> Arc<Mutex<FakeWorkspace>> {
+        Arc::new(Mutex::new(FakeWorkspace::new()))
+    }
+
+    fn new_id(&mut self) -> String {
+        self.next_id += 1;
+        format!("00000000-0000-4000-8000-{:012x}", self.next_id)
+    }
+
+    pub fn add_page(&mut self, id: &str, title: &str) {
+        self.pages.insert(id.to_string(), json!({
+            "object": "page",
+            "id": id,
+            "archived": false,
+            "in_trash": false,
+            "url": format!("https://www.notion.so/{}", id.replace('-', "")),
+            "parent": { "type": "workspace", "workspace": true },
+            "properties": { "title": { "type": "title", "title": [{ "plain_text": title, "text": { "content": title } }] } }
+        }));
+        self.children.entry(id.to_string()).or_default();
+    }
+
+    // A page nested under `parent`, listed among its children as Notion does
+    pub fn add_child_page(&mut self, parent: &str, id: &str, title: &str) {
+        self.add_page(id, title);
+        self.pages.get_mut(id).expect("just added")["parent"] = json!({ "type": "page_id", "page_id": parent });
+        let child = json!({ "object": "block", "id": id, "type": "child_page", "child_page": { "title": title } });
+        self.children.entry(parent.to_string()).or_default().push(child);
+    }
+
+    // Paragraphs already on a page, e.g. to make it longer than one listing
+    pub fn add_paragraphs(&mut self, page_id: &str, texts: &[&str]) {
+        for text in texts {
+            let block = self.store(json!({
+                "type": "paragraph",
+                "paragraph": { "rich_text": [{ "type": "text", "text": { "content": text } }] }
+            }));
+            self.children.entry(page_id.to_string()).or_default().push(block);
+        }
+    }
+
+    // The live children of a page or block, in order
+    pub fn children(&self, parent: &str) -> Vec<Value> {
+        self.children.get(parent).cloned().unwrap_or_default()
+    }
+
+    // Store a block from an append body, with any nested children under its new id
+    fn store(&mut self, mut block: Value) -> Value {
+        let id = self.new_id();
+        let block_type = block["type"].as_str().unwrap_or_default().to_string();
+        let nested = block[&block_type]
+            .as_object_mut()
+            .and_then(|content| content.remove("children"))
+            .and_then(|children| children.as_array().cloned())
+            .unwrap_or_default();
+        block["id"] = json!(id);
+        block["object"] = json!("block");
+        block["archived"] = json!(false);
+        block["has_children"] = json!(!nested.is_empty());
+        // Notion returns plain_text on every run
+        if let Some(runs) = block[&block_type]["rich_text"].as_array_mut() {
+            for run in runs {
+                let text = run["text"]["content"].clone();
+                run["plain_text"] = text;
+            }
+        }
+        let stored: Vec<Value> = nested.into_iter().map(|child| self.store(child)).collect();
+        self.children.insert(id, stored);
+        block
+    }
+
+    fn find_parent(&self, block_id: &str) -> Option<(String, usize)> {
+        self.children.iter().find_map(|(parent, blocks)| {
+            blocks
+                .iter()
+                .position(|block| block["id"].as_str() == Some(block_id))
+                .map(|index| (parent.clone(), index))
+        })
+    }
+
+    pub fn block(&self, block_id: &str) -> Option<Value> {
+        let (parent, index) = self.find_parent(block_id)?;
+        Some(self.children[&parent][index].clone())
+    }
+
+    pub fn append(&mut self, parent: &str, body: &Value) -> MockReply {
+        if !self.children.contains_key(parent) {
+            return MockReply::error(404, "object_not_found", "Could not find block");
+        }
+        let after = body["after"].as_str();
+        let mut position = self.children[parent].len();
+        if let Some(after) = after {
+            match self.children[parent].iter().position(|block| block["id"].as_str() == Some(after)) {
+                Some(index) => position = index + 1,
+                None => return MockReply::error(400, "validation_error", "body.after should be a child block id"),
+            }
+        }
+        let new_blocks: Vec<Value> = body["children"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|block| self.store(block))
+            .collect();
+        let list = self.children.get_mut(parent).expect("checked above");
+        for (offset, block) in new_blocks.iter().enumerate() {
+            list.insert(position + offset, block.clone());
+        }
+        // A block appended under now has children, as Notion reports it
+        if let Some((grandparent, index)) = self.find_parent(parent) {
+            self.children.get_mut(&grandparent).expect("found above")[index]["has_children"] = json!(!new_blocks.is_empty());
+        }
+        MockReply::json(200, json!({ "object": "list", "results": new_blocks, "has_more": false, "next_cursor": null }))
+    }
+
+    // Children in pages of `page_size`. As in Notion, a cursor is the id of the
+    // first block to list; one that isn't a child is refused.
+    pub fn list(&self, parent: &str, query: &HashMap<String, String>) -> MockReply {
+        let Some(children) = self.children.get(parent) else {
+            return MockReply::error(404, "object_not_found", "Could not find block");
+        };
+        let page_size = query.get("page_size").and_then(|size| size.parse().ok()).unwrap_or(100usize).min(100);
+        let start = match query.get("start_cursor") {
+            Some(cursor) => match children.iter().position(|block| block["id"].as_str() == Some(cursor.as_str())) {
+                Some(index) => index,
+                None => return MockReply::error(400, "validation_error", "start_cursor should be a valid cursor"),
+            },
+            None => 0,
+        };
+        let end = (start + page_size).min(children.len());
+        let has_more = end < children.len();
+        MockReply::json(200, json!({
+            "object": "list",
+            "results": children[start..end].to_vec(),
+            "has_more": has_more,
+            "next_cursor": if has_more { children[end]["id"].clone() } else { Value::Null },
+        }))
+    }
+
+    // A page deleted in Notion: gone from its parent, and a 404 from then on
+    pub fn delete_page(&mut self, id: &str) {
+        self.pages.remove(id);
+        self.children.remove(id);
+        for blocks in self.children.values_mut() {
+            blocks.retain(|block| block["id"].as_str() != Some(id));
+        }
+    }
+
+    pub fn delete(&mut self, block_id: &str) -> MockReply {
+        let Some((parent, index)) = self.find_parent(block_id) else {
+            return MockReply::error(404, "object_not_found", "Could not find block");
+        };
+        let mut block = self.children.get_mut(&parent).expect("found above").remove(index);
+        block["archived"] = json!(true);
+        MockReply::json(200, block)
+    }
+
+    // Answer a request the way Notion would
+    pub fn answer(&mut self, request: &MockRequest) -> MockReply {
+        let path = request.path.as_str();
+        let children_of = path.strip_prefix("/v1/blocks/").and_then(|rest| rest.strip_suffix("/children"));
+        match (request.method.as_str(), children_of) {
+            ("PATCH", Some(parent)) => self.append(parent, request.body.as_ref().unwrap_or(&Value::Null)),
+            ("GET", Some(parent)) => self.list(parent, &request.query),
+            _ => {
+                if let Some(id) = path.strip_prefix("/v1/pages/") {
+                    return match self.pages.get(id) {
+                        Some(page) if request.method == "GET" => MockReply::json(200, page.clone()),
+                        _ => MockReply::error(404, "object_not_found", "Could not find page"),
+                    };
+                }
+                if path == "/v1/pages" && request.method == "POST" {
+                    let body = request.body.clone().unwrap_or_default();
+                    let id = self.new_id();
+                    let title = body["properties"]["title"]["title"][0]["text"]["content"].as_str().unwrap_or_default();
+                    self.add_page(&id, title);
+                    if let Some(parent) = body["parent"]["page_id"].as_str() {
+                        let child = json!({ "object": "block", "id": id, "type": "child_page", "child_page": { "title": title } });
+                        self.children.entry(parent.to_string()).or_default().push(child);
+                    }
+                    let mut page = self.pages[&id].clone();
+                    page["parent"] = body["parent"].clone();
+                    return MockReply::json(200, page);
+                }
+                if let Some(id) = path.strip_prefix("/v1/blocks/") {
+                    return match request.method.as_str() {
+                        "GET" => match self.block(id) {
+                            Some(block) => MockReply::json(200, block),
+                            None => MockReply::error(404, "object_not_found", "Could not find block"),
+                        },
+                        "DELETE" => self.delete(id),
+                        "PATCH" => match self.find_parent(id) {
+                            Some((parent, index)) => {
+                                let body = request.body.clone().unwrap_or_default();
+                                let block = &mut self.children.get_mut(&parent).expect("found above")[index];
+                                let block_type = block["type"].as_str().unwrap_or_default().to_string();
+                                if let Some(rich_text) = body[&block_type].get("rich_text") {
+                                    block[&block_type]["rich_text"] = rich_text.clone();
+                                }
+                                MockReply::json(200, block.clone())
+                            }
+                            None => MockReply::error(404, "object_not_found", "Could not find block"),
+                        },
+                        _ => MockReply::error(400, "invalid_request_url", "Unsupported method"),
+                    };
+                }
+                MockReply::error(400, "invalid_request_url", "Not faked")
+            }
+        }
+    }
+}
@@ -1,13 +1,43 @@
 use reqwest::{Client, header};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use tauri::State;
-use chrono::{Local, Timelike, Datelike};
+use tauri::{AppHandle, ClipboardManager, Manager, State};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Mutex, Arc};
-use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{BuildHasher, Hash, Hasher};
 use std::time::{Duration, Instant};
 
-use crate::config::AppState;
+use crate::accessibility;
+use crate::auth;
+use crate::blocks::{self, NoteBlockType, NoteFormat, NoteStyle};
+use crate::clock;
+use crate::capture::{
+    prepare_note_text, resolve_capture_target, resolve_destination_plan, split_leading_timestamp, CaptureConditions,
+    CaptureRequest, CaptureRoute, DestinationContext, DestinationRule, SANDBOX_PAGE_TITLE,
+};
+use crate::config::{AppConfig, AppState, TargetKind};
+use crate::daily_pages;
+use crate::database_rows;
+use crate::day_headings;
+use crate::dry_run;
+use crate::error::AppError;
+use crate::events::{self, AppEvent, PageRotated};
+use crate::focus;
+use crate::webhook;
+use crate::profiling::{self, MemoryReporter, MemoryUsage};
+use crate::queue;
+use crate::quiet_hours;
+use crate::receipts::{self, DeliverySource, NoteOrigin};
+use crate::recording;
+use crate::reliability;
+use crate::scratch;
+use crate::suspend;
+use crate::timestamps::{self, TimestampOptions};
+use crate::rate_limit::{Priority, RateLimitSource, RequestPermit, RATE_LIMITER};
+use crate::retry::{self, Jitter, RetryPolicy};
 
 // Notion page representation
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -16,38 +46,523 @@ pub struct NotionPage {
     pub title: String,
     pub icon: Option<String>,
     pub url: String,
+    // Where the page lives, to tell same-named pages apart: "Workspace", the
+    // parent page's or database's title, or None when it couldn't be looked up
+    #[serde(default)]
+    pub parent_title: Option<String>,
+    // RFC3339; search results are sorted by it, newest first
+    #[serde(default)]
+    pub last_edited_time: Option<String>,
+    // Archived or in the trash; only listed when a search asks for them
+    #[serde(default)]
+    pub archived: bool,
 }
 
+// The parent a search result names
+#[derive(Debug, Clone, PartialEq)]
+enum SearchParent {
+    Workspace,
+    Page(String),
+    Database(String),
+    // A block, or something newer than this app knows
+    Other,
+}
+
+// One page search's results
+#[derive(Serialize, Debug, Clone)]
+pub struct PageSearch {
+    pub pages: Vec<NotionPage>,
+    // More pages matched than the cap allowed; a narrower query will find them
+    pub truncated: bool,
+}
+
+// Best-effort verdict on whether the integration can append to a page
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PageAccess {
+    Writable,
+    ReadOnly,
+    NotFound,
+    Unknown,
+}
+
+// Why a page can't take notes, for the settings window to explain
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessIssue {
+    // 404: not shared with the integration (or deleted for good); share it from the page's menu
+    NotShared,
+    // 403: the integration lacks the read or insert content capability
+    Forbidden,
+    // The page is in Notion's trash
+    Deleted,
+    // Any other answer
+    Unknown,
+}
+
+// What verify_page_access found out about a page
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PageAccessReport {
+    // The page could be retrieved
+    pub accessible: bool,
+    // Its children could be listed too, the best hint that appends will work
+    pub can_append: bool,
+    pub title: Option<String>,
+    // Set whenever `can_append` is false
+    pub reason: Option<AccessIssue>,
+}
+
+// What Notion told us about a successful append
+#[derive(Debug, Clone)]
+pub struct AppendResponse {
+    pub block_ids: Vec<String>,
+    pub request_id: Option<String>,
+    pub status: u16,
+    pub attempts: u32,
+}
+
+// What append_note tells the note window about a capture
+#[derive(Serialize, Debug, Clone)]
+pub struct AppendResult {
+    // The id the capture's receipt is stored under, or the scratch or queue item's id
+    pub capture_id: String,
+    // The blocks Notion created; empty when the note was held back
+    pub block_ids: Vec<String>,
+    pub page_id: String,
+    // Unix time in milliseconds
+    pub appended_at: i64,
+    // The note went to the scratch buffer or the offline queue instead of Notion
+    pub queued: bool,
+}
+
+impl AppendResult {
+    // A capture that is held locally and sent later
+    fn queued(id: String) -> Self {
+        AppendResult {
+            capture_id: id,
+            block_ids: Vec::new(),
+            page_id: String::new(),
+            appended_at: chrono::Utc::now().timestamp_millis(),
+            queued: true,
+        }
+    }
+}
+
+// What was delivered before a multi-request append failed
+#[derive(Debug, Clone)]
+pub struct PartialDelivery {
+    pub delivered: AppendResponse,
+    pub delivered_chunks: usize,
+    pub total_chunks: usize,
+    pub error: String,
+}
+
+// Why an append didn't fully succeed
+#[derive(Debug, Clone)]
+pub enum AppendFailure {
+    // Nothing landed
+    Failed(String),
+    // Nothing landed because the page has reached Notion's block children limit
+    PageFull(String),
+    // Some chunks landed before a later one failed
+    Partial(PartialDelivery),
+}
+
+impl From<AppendFailure> for String {
+    fn from(failure: AppendFailure) -> Self {
+        match failure {
+            AppendFailure::Failed(error) => error,
+            AppendFailure::PageFull(error) => AppError::PageFull(error).to_string(),
+            AppendFailure::Partial(partial) => format!(
+                "Only part of the note was sent ({} of {} parts): {}",
+                partial.delivered_chunks, partial.total_chunks, partial.error
+            ),
+        }
+    }
+}
+
+// Notion accepts at most this many children per append request
+pub const MAX_CHILDREN_PER_REQUEST: usize = 100;
+
+// One append attempt's failure, as seen by the retry policy
+#[derive(Debug)]
+enum ChunkAttemptError {
+    // 409 conflict or 5xx; the last one is reported like any other error response
+    Status(reqwest::Response),
+    // The connection died after Notion may have processed the request
+    Ambiguous(String),
+    // No connection was made, so the request never reached Notion
+    Unreachable(String),
+    Failed(AppendFailure),
+}
+
+impl ChunkAttemptError {
+    fn is_retryable(&self) -> bool {
+        !matches!(self, ChunkAttemptError::Failed(_))
+    }
+    
+    fn is_conflict(&self) -> bool {
+        matches!(self, ChunkAttemptError::Status(res) if res.status() == reqwest::StatusCode::CONFLICT)
+    }
+    
+    fn retry_reason(&self) -> RetryReason {
+        match self {
+            ChunkAttemptError::Status(res) if res.status() == reqwest::StatusCode::CONFLICT => RetryReason::Conflict,
+            ChunkAttemptError::Ambiguous(_) => RetryReason::ConnectionLost,
+            ChunkAttemptError::Unreachable(_) => RetryReason::Unreachable,
+            _ => RetryReason::ServerError,
+        }
+    }
+}
+
+// Why an append is being sent again
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RetryReason {
+    Conflict,
+    ServerError,
+    ConnectionLost,
+    Unreachable,
+}
+
+// An append attempt failed and another is coming, reported while the user waits
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct AppendRetry {
+    // Attempts made so far
+    pub attempt: u32,
+    pub max_attempts: u32,
+    pub retry_in_ms: u64,
+    pub reason: RetryReason,
+}
+
+// Called before each append retry; captures turn these into `note-progress` events
+pub type RetryObserver = Arc<dyn Fn(AppendRetry) + Send + Sync>;
+
+// Appends retry conflicts, server errors, failed connections and ambiguous
+// failures; 4xx answers aren't retried. 429s are left to the rate limiter and
+// SHORT_RATE_LIMIT_WAIT, not retried here.
+const APPEND_RETRY: RetryPolicy<ChunkAttemptError> = RetryPolicy {
+    name: "append",
+    max_attempts: 4,
+    base_delay: Duration::from_millis(1000),
+    max_delay: Duration::from_secs(16),
+    jitter: Jitter::Equal,
+    retryable: ChunkAttemptError::is_retryable,
+    jitter_source: retry::clock_jitter,
+};
+
+// Added to the backoff after a 409; the conflicting edit usually needs a moment longer than a 5xx
+const CONFLICT_EXTRA_WAIT: Duration = Duration::from_millis(500);
+
+// A 429 asking for a shorter wait than this is waited out and the append sent
+// once more; a longer one fails the append with a rate limit error
+const SHORT_RATE_LIMIT_WAIT: Duration = Duration::from_secs(5);
+
+// Cursor pages followed by last_children to reach the end of a page
+const MAX_LAST_CHILDREN_PAGES: usize = 10;
+
+// Cursor pages read when looking for a child page by title
+const MAX_CHILD_PAGE_SCAN_PAGES: usize = 20;
+
+// Most results Notion returns for one search request
+const MAX_SEARCH_PAGE_SIZE: usize = 100;
+
+// Parents looked up per search when they aren't among the results or cached;
+// the rest stay without a breadcrumb until a later search
+const MAX_PARENT_LOOKUPS: usize = 20;
+
+// Time allowed to establish a connection
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Overall ceiling for a request, including its body
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Time allowed to read a response body once the headers have arrived
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Largest response body we are willing to buffer
+const MAX_RESPONSE_BYTES: usize = 2 * 1024 * 1024;
+
 // Cache structure with expiration time
 struct CacheEntry<T> {
     data: T,
     expires_at: Instant,
 }
 
+// What a page search asks for; also its cache key
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct PageSearchOptions {
+    // "" searches every page
+    pub query: String,
+    pub limit: Option<u32>,
+    // The configured cap on results
+    pub max_results: usize,
+    // Keep archived and trashed pages, which can't be appended to
+    pub include_archived: bool,
+}
+
 // Global cache for API responses
 lazy_static::lazy_static! {
-    static ref PAGES_CACHE: Mutex<Option<CacheEntry<Vec<NotionPage>>>> = Mutex::new(None);
+    static ref PAGES_CACHE: Mutex<HashMap<PageSearchOptions, CacheEntry<PageSearch>>> = Mutex::new(HashMap::new());
     static ref CLIENT_POOL: Arc<Mutex<HashMap<String, Client>>> = Arc::new(Mutex::new(HashMap::new()));
+    // Advanced users' extra request headers, mirrored from config
+    static ref EXTRA_HEADERS: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+    // Random per-process id sent as X-Client-Session to correlate requests in support tickets
+    static ref CLIENT_SESSION_ID: String = {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default());
+        hasher.write_u32(std::process::id());
+        format!("{:016x}", hasher.finish())
+    };
+    static ref PAGE_ACCESS_CACHE: Mutex<HashMap<String, CacheEntry<PageAccess>>> = Mutex::new(HashMap::new());
+    // Parent titles by normalized id; None when the parent couldn't be read
+    static ref PARENT_TITLES: Mutex<HashMap<String, CacheEntry<Option<String>>>> = Mutex::new(HashMap::new());
+    // The newest block seen at the end of each page, by token and normalized
+    // page id. As a start_cursor it lists the end of the page in one request.
+    static ref PAGE_TAILS: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
+}
+
+struct PagesCacheReporter;
+
+impl MemoryReporter for PagesCacheReporter {
+    fn name(&self) -> &'static str {
+        "pages_cache"
+    }
+    
+    fn usage(&self) -> MemoryUsage {
+        let cache = PAGES_CACHE.lock().unwrap();
+        let pages: Vec<&NotionPage> = cache.values().flat_map(|entry| entry.data.pages.iter()).collect();
+        MemoryUsage {
+            bytes: pages.iter()
+                .map(|page| {
+                    std::mem::size_of::<NotionPage>()
+                        + page.id.len()
+                        + page.title.len()
+                        + page.url.len()
+                        + page.icon.as_ref().map_or(0, |icon| icon.len())
+                })
+                .sum(),
+            entries: pages.len(),
+        }
+    }
+    
+    fn trim(&self) {
+        PAGES_CACHE.lock().unwrap().clear();
+        PARENT_TITLES.lock().unwrap().clear();
+    }
+}
+
+struct ClientPoolReporter;
+
+impl MemoryReporter for ClientPoolReporter {
+    fn name(&self) -> &'static str {
+        "client_pool"
+    }
+    
+    // Each client holds a connection pool and TLS state; count a rough fixed cost per client
+    fn usage(&self) -> MemoryUsage {
+        let pool = CLIENT_POOL.lock().unwrap();
+        MemoryUsage {
+            bytes: pool.keys().map(|key| key.len() + 64 * 1024).sum(),
+            entries: pool.len(),
+        }
+    }
+    
+    // A single pooled client is the one in use; with several, drop them all and rebuild on demand
+    fn trim(&self) {
+        let mut pool = CLIENT_POOL.lock().unwrap();
+        if pool.len() > 1 {
+            pool.clear();
+        }
+    }
+}
+
+struct PageAccessCacheReporter;
+
+impl MemoryReporter for PageAccessCacheReporter {
+    fn name(&self) -> &'static str {
+        "page_access_cache"
+    }
+    
+    fn usage(&self) -> MemoryUsage {
+        let cache = PAGE_ACCESS_CACHE.lock().unwrap();
+        MemoryUsage {
+            bytes: cache.keys().map(|key| key.len() + std::mem::size_of::<CacheEntry<PageAccess>>()).sum(),
+            entries: cache.len(),
+        }
+    }
+    
+    fn trim(&self) {
+        PAGE_ACCESS_CACHE.lock().unwrap().clear();
+    }
+}
+
+// Drop every cached page list, access probe and pooled client; returns how many entries went
+pub fn clear_caches() -> usize {
+    let pages = std::mem::take(&mut *PAGES_CACHE.lock().unwrap())
+        .values()
+        .map(|entry| entry.data.pages.len())
+        .sum::<usize>();
+    let access = std::mem::take(&mut *PAGE_ACCESS_CACHE.lock().unwrap()).len();
+    let parents = std::mem::take(&mut *PARENT_TITLES.lock().unwrap()).len();
+    let clients = std::mem::take(&mut *CLIENT_POOL.lock().unwrap()).len();
+    pages + access + parents + clients
+}
+
+// Make the Notion caches visible in the memory report
+pub fn register_memory_reporters() {
+    profiling::register_reporter(Box::new(PagesCacheReporter));
+    profiling::register_reporter(Box::new(ClientPoolReporter));
+    profiling::register_reporter(Box::new(PageAccessCacheReporter));
+}
+
+// Pages from the unfiltered search cache while it is still fresh; never hits the network
+pub fn cached_pages() -> Option<Vec<NotionPage>> {
+    let cache = PAGES_CACHE.lock().unwrap();
+    cache.iter()
+        .find(|(options, entry)| {
+            options.query.is_empty()
+                && options.limit.is_none()
+                && !options.include_archived
+                && Instant::now() < entry.expires_at
+        })
+        .map(|(_, entry)| entry.data.pages.clone())
 }
 
 // Cache duration (5 minutes)
 const CACHE_DURATION: Duration = Duration::from_secs(300);
 
+// Interpret the status codes of a page retrieval and a children listing.
+// Notion has no "can I write?" endpoint, so this is a heuristic: a page we can
+// retrieve but whose children are forbidden is treated as read-restricted.
+pub fn interpret_access_probe(page_status: u16, children_status: Option<u16>) -> PageAccess {
+    match page_status {
+        200..=299 => match children_status {
+            Some(200..=299) => PageAccess::Writable,
+            Some(403) => PageAccess::ReadOnly,
+            Some(404) => PageAccess::NotFound,
+            _ => PageAccess::Unknown,
+        },
+        403 => PageAccess::ReadOnly,
+        404 => PageAccess::NotFound,
+        _ => PageAccess::Unknown,
+    }
+}
+
+// Turn a page retrieval (status and body) and a one-block children listing
+// into a report. Like interpret_access_probe, "can append" is a heuristic.
+pub fn access_report(page_status: u16, page: Option<&serde_json::Value>, children_status: Option<u16>) -> PageAccessReport {
+    let issue = |status: u16| match status {
+        403 => AccessIssue::Forbidden,
+        404 => AccessIssue::NotShared,
+        _ => AccessIssue::Unknown,
+    };
+    if !(200..=299).contains(&page_status) {
+        return PageAccessReport {
+            accessible: false,
+            can_append: false,
+            title: None,
+            reason: Some(issue(page_status)),
+        };
+    }
+    
+    let title = page.map(|page| crate::page_display::title_from_page(page).unwrap_or_else(|| "Untitled".to_string()));
+    let reason = if page.is_some_and(|page| page["archived"].as_bool() == Some(true) || page["in_trash"].as_bool() == Some(true)) {
+        Some(AccessIssue::Deleted)
+    } else {
+        match children_status {
+            Some(200..=299) => None,
+            Some(status) => Some(issue(status)),
+            None => Some(AccessIssue::Unknown),
+        }
+    };
+    PageAccessReport {
+        accessible: true,
+        can_append: reason.is_none(),
+        title,
+        reason,
+    }
+}
+
+// Headers that extra_headers may never override: auth and versioning, the
+// session id, and the ones reqwest derives from the request itself
+const DENIED_EXTRA_HEADERS: &[&str] = &[
+    "authorization",
+    "notion-version",
+    "x-client-session",
+    "host",
+    "content-length",
+    "content-type",
+    "user-agent",
+];
+
+// e.g. "notion-quick-notes/0.1.0 (windows; x86_64)"
+pub fn user_agent() -> String {
+    format!(
+        "notion-quick-notes/{} ({}; {})",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    )
+}
+
+pub fn client_session_id() -> &'static str {
+    &CLIENT_SESSION_ID
+}
+
+fn headers_hash(headers: &BTreeMap<String, String>) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    headers.hash(&mut hasher);
+    hasher.finish()
+}
+
+// Validate a configured extra header, rejecting malformed and protected ones
+pub fn parse_extra_header(name: &str, value: &str) -> Result<(header::HeaderName, header::HeaderValue), String> {
+    let header_name = header::HeaderName::from_bytes(name.trim().as_bytes())
+        .map_err(|_| AppError::ValidationError(format!("Invalid header name '{}'", name)).to_string())?;
+    if DENIED_EXTRA_HEADERS.contains(&header_name.as_str()) {
+        return Err(AppError::ValidationError(format!("Header '{}' can't be overridden", name)).to_string());
+    }
+    let header_value = header::HeaderValue::from_str(value)
+        .map_err(|_| AppError::ValidationError(format!("Invalid value for header '{}'", name)).to_string())?;
+    Ok((header_name, header_value))
+}
+
+// Validate all extra headers and apply them to subsequently created clients
+pub fn set_extra_headers(headers: &BTreeMap<String, String>) -> Result<(), String> {
+    for (name, value) in headers {
+        parse_extra_header(name, value)?;
+    }
+    *EXTRA_HEADERS.lock().unwrap() = headers.clone();
+    Ok(())
+}
+
 // Notion API client
-struct NotionApiClient {
+pub struct NotionApiClient {
     client: Client,
     api_token: String, 
+    priority: Priority,
+    on_retry: Option<RetryObserver>,
 }
 
 impl NotionApiClient {
     pub fn new(api_token: String) -> Result<Self, String> {
+        let extra_headers = EXTRA_HEADERS.lock().unwrap().clone();
+        // Changing the extra headers must produce a fresh client
+        let pool_key = format!("{}:{:x}", api_token, headers_hash(&extra_headers));
+        
         // Try to get a client from the pool first
         {
             let client_pool = CLIENT_POOL.lock().unwrap();
-            if let Some(client) = client_pool.get(&api_token) {
+            if let Some(client) = client_pool.get(&pool_key) {
                 return Ok(NotionApiClient {
                     client: client.clone(),
                     api_token: api_token.clone(),
+                    priority: Priority::Interactive,
+                    on_retry: None,
                 });
             }
         }
@@ -67,40 +582,281 @@ impl NotionApiClient {
             "Notion-Version",
             header::HeaderValue::from_static("2022-06-28")
         );
+        headers.insert(
+            "X-Client-Session",
+            header::HeaderValue::from_str(client_session_id())
+                .map_err(|e| format!("Invalid session id: {}", e))?
+        );
+        for (name, value) in &extra_headers {
+            let (name, value) = parse_extra_header(name, value)?;
+            headers.insert(name, value);
+        }
         
         let client = Client::builder()
+            .user_agent(user_agent())
             .default_headers(headers)
-            .timeout(Duration::from_secs(10)) // Add timeout for better error handling
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT) // Hard ceiling; body reads have their own limit in read_json
             .build()
             .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
         
         // Store the client in the pool
         {
             let mut client_pool = CLIENT_POOL.lock().unwrap();
-            client_pool.insert(api_token.clone(), client.clone());
+            client_pool.insert(pool_key, client.clone());
         }
             
         Ok(NotionApiClient {
             client,
             api_token,
+            priority: Priority::Interactive,
+            on_retry: None,
         })
     }
     
-    pub async fn verify_token(&self) -> Result<bool, String> {
-        let res = self.client
-            .get("https://api.notion.com/v1/users/me")
-            .send()
+    // Requests default to interactive priority; background work opts down
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+    
+    // Report append retries as they're scheduled
+    pub fn with_retry_observer(mut self, observer: RetryObserver) -> Self {
+        self.on_retry = Some(observer);
+        self
+    }
+    
+    // Shared request path: every call goes through the rate limiter first
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response, String> {
+        self.send_raw(request)
             .await
-            .map_err(|e| format!("API request failed: {}", e))?;
-            
-        Ok(res.status().is_success())
+            .map_err(|e| format!("API request failed: {}", e))
+    }
+    
+    // Like `send`, but keeps the transport error so callers can classify it
+    async fn send_raw(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response, reqwest::Error> {
+        let permit = RATE_LIMITER.acquire(&self.api_token, self.priority).await;
+        #[cfg_attr(not(test), allow(unused_mut))]
+        let mut request = request.build()?;
+        #[cfg(test)]
+        crate::mock_notion::redirect(&self.api_token, &mut request);
+        let request_line = recording::RequestLine {
+            method: request.method().to_string(),
+            path: request.url().path().to_string(),
+        };
+        // Dry-run mode answers from the built-in fake instead of the network
+        let mut res = if dry_run::is_active() {
+            dry_run::respond(&request).await
+        } else {
+            self.client.execute(request).await?
+        };
+        RATE_LIMITER.record_request(&self.api_token, Instant::now());
+        res.extensions_mut().insert(request_line);
+        // Dropped with the response, once its body is read
+        res.extensions_mut().insert(permit);
+        
+        if res.status().is_success() {
+            if let Some(date) = res.headers().get(header::DATE).and_then(|v| v.to_str().ok()) {
+                clock::observe_server_date(date);
+            }
+        }
+        
+        if res.status() == reqwest::StatusCode::UNAUTHORIZED {
+            auth::report_unauthorized(&self.api_token);
+        }
+        
+        if res.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            // Retry-After is either delta-seconds or an HTTP date in server time
+            let retry_after = res.headers()
+                .get(header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| {
+                    v.trim().parse::<u64>().ok().map(Duration::from_secs)
+                        .or_else(|| clock::parse_http_date(v).map(clock::until_server_time))
+                });
+            match retry_after {
+                Some(wait) => RATE_LIMITER.record_rate_limit(&self.api_token, wait, RateLimitSource::Headers),
+                None => RATE_LIMITER.record_rate_limit(&self.api_token, Duration::from_secs(1), RateLimitSource::Backoff),
+            }
+        }
+        
+        Ok(res)
+    }
+    
+    // Retried while Notion can't be reached; a rejected token is a plain `false`
+    pub async fn verify_token(&self) -> Result<bool, String> {
+        let outcome = retry::retry_async(
+            &retry::TOKEN_VERIFY_RETRY,
+            None,
+            |retry| eprintln!("Token check failed (attempt {}), retrying in {:?}: {}", retry.attempt, retry.delay, retry.error),
+            |_| self.send(self.client.get("https://api.notion.com/v1/users/me")),
+        ).await;
+        
+        Ok(outcome.result?.status().is_success())
+    }
+    
+    fn access_cache_key(&self, page_id: &str) -> String {
+        format!("{}:{}", self.api_token, page_id)
+    }
+    
+    fn tail_key(&self, page_id: &str) -> String {
+        format!("{}:{}", self.api_token, page_id.replace('-', "").to_lowercase())
+    }
+    
+    fn remember_tail(&self, page_id: &str, block_id: &str) {
+        PAGE_TAILS.lock().unwrap().insert(self.tail_key(page_id), block_id.to_string());
+    }
+    
+    fn known_tail(&self, page_id: &str) -> Option<String> {
+        PAGE_TAILS.lock().unwrap().get(&self.tail_key(page_id)).cloned()
+    }
+    
+    fn forget_tail(&self, page_id: &str) {
+        PAGE_TAILS.lock().unwrap().remove(&self.tail_key(page_id));
+    }
+    
+    fn remember_access(&self, page_id: &str, access: PageAccess) {
+        let mut cache = PAGE_ACCESS_CACHE.lock().unwrap();
+        cache.insert(self.access_cache_key(page_id), CacheEntry {
+            data: access,
+            expires_at: Instant::now() + CACHE_DURATION,
+        });
+    }
+    
+    // Retrieve a page object; Ok(None) when Notion says it doesn't exist (or isn't shared)
+    pub async fn fetch_page(&self, page_id: &str) -> Result<Option<serde_json::Value>, String> {
+        let res = self.send(
+            self.client.get(format!("https://api.notion.com/v1/pages/{}", page_id))
+        ).await?;
+        
+        if res.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !res.status().is_success() {
+            return Err(format!("API error: {}", res.status()));
+        }
+        
+        read_json(res).await.map(Some)
+    }
+    
+    // Retrieve a database object with its property schema; Ok(None) when Notion
+    // says it doesn't exist (or isn't shared)
+    pub async fn fetch_database(&self, database_id: &str) -> Result<Option<serde_json::Value>, String> {
+        let res = self.send(
+            self.client.get(format!("https://api.notion.com/v1/databases/{}", database_id))
+        ).await?;
+        
+        if res.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !res.status().is_success() {
+            return Err(format!("API error: {}", res.status()));
+        }
+        
+        read_json(res).await.map(Some)
+    }
+    
+    // Create a page from a full request body; returns the created page object
+    pub async fn create_page(&self, body: &serde_json::Value) -> Result<serde_json::Value, String> {
+        let res = self.send(
+            self.client.post("https://api.notion.com/v1/pages").json(body)
+        ).await?;
+        
+        if !res.status().is_success() {
+            let status = res.status();
+            let error_body = read_json(res).await.unwrap_or_default();
+            return Err(format!(
+                "API error: {} - {}",
+                status,
+                error_body["message"].as_str().unwrap_or("Unknown error")
+            ));
+        }
+        
+        read_json(res).await
+    }
+    
+    // Probe whether appends to the page are likely permitted (cached per token)
+    pub async fn probe_page_access(&self, page_id: &str) -> Result<PageAccess, String> {
+        {
+            let cache = PAGE_ACCESS_CACHE.lock().unwrap();
+            if let Some(entry) = cache.get(&self.access_cache_key(page_id)) {
+                if Instant::now() < entry.expires_at {
+                    return Ok(entry.data);
+                }
+            }
+        }
+        
+        // Keep just the status, so the response gives its in-flight slot back before the next request
+        let page_status = self.send(
+            self.client.get(format!("https://api.notion.com/v1/pages/{}", page_id))
+        ).await?.status();
+        
+        // Only look at the children when the page itself is visible
+        let children_status = if page_status.is_success() {
+            let children_res = self.send(
+                self.client.get(format!("https://api.notion.com/v1/blocks/{}/children?page_size=1", page_id))
+            ).await?;
+            Some(children_res.status().as_u16())
+        } else {
+            None
+        };
+        
+        let access = interpret_access_probe(page_status.as_u16(), children_status);
+        self.remember_access(page_id, access);
+        Ok(access)
+    }
+    
+    // Retrieve the page and list one of its children, reporting what that says
+    // about appending. Uncached, but the verdict refreshes the probe cache.
+    pub async fn verify_page_access(&self, page_id: &str) -> Result<PageAccessReport, String> {
+        let page_res = self.send(
+            self.client.get(format!("https://api.notion.com/v1/pages/{}", page_id))
+        ).await?;
+        let page_status = page_res.status().as_u16();
+        let page = if page_res.status().is_success() {
+            Some(read_json(page_res).await?)
+        } else {
+            None
+        };
+        
+        let children_status = match &page {
+            Some(_) => {
+                let children_res = self.send(
+                    self.client.get(format!("https://api.notion.com/v1/blocks/{}/children?page_size=1", page_id))
+                ).await?;
+                Some(children_res.status().as_u16())
+            }
+            None => None,
+        };
+        
+        self.remember_access(page_id, interpret_access_probe(page_status, children_status));
+        Ok(access_report(page_status, page.as_ref(), children_status))
+    }
+    
+    // Every live page shared with the integration, up to `max_results`
+    pub async fn search_pages(&self, max_results: usize) -> Result<Vec<NotionPage>, String> {
+        let options = PageSearchOptions {
+            max_results,
+            ..Default::default()
+        };
+        self.search_pages_matching(options).await.map(|search| search.pages)
     }
     
-    pub async fn search_pages(&self) -> Result<Vec<NotionPage>, String> {
+    // Pages whose title matches the query, at most `limit` of them and never
+    // more than `max_results`, most recently edited first. Follows Notion's
+    // cursors, one rate-limited request per 100 results.
+    pub async fn search_pages_matching(&self, options: PageSearchOptions) -> Result<PageSearch, String> {
+        let key = PageSearchOptions {
+            query: options.query.trim().to_string(),
+            limit: options.limit.map(|limit| limit.max(1)),
+            max_results: options.max_results.max(1),
+            ..options
+        };
+        
         // Check cache first
         {
             let cache = PAGES_CACHE.lock().unwrap();
-            if let Some(entry) = &*cache {
+            if let Some(entry) = cache.get(&key) {
                 if Instant::now() < entry.expires_at {
                     return Ok(entry.data.clone());
                 }
@@ -108,9 +864,148 @@ impl NotionApiClient {
         }
         
         // Cache miss or expired, fetch from API
+        let wanted = key.limit.map_or(key.max_results, |limit| (limit as usize).min(key.max_results));
+        let mut found = Vec::new();
+        let mut seen = 0;
+        let mut cursor: Option<String> = None;
+        let truncated = loop {
+            let mut search_body = json!({
+                "filter": {
+                    "value": "page",
+                    "property": "object"
+                },
+                "sort": {
+                    "direction": "descending",
+                    "timestamp": "last_edited_time"
+                },
+                "page_size": (wanted - seen).min(MAX_SEARCH_PAGE_SIZE)
+            });
+            if !key.query.is_empty() {
+                search_body["query"] = json!(key.query);
+            }
+            if let Some(cursor) = &cursor {
+                search_body["start_cursor"] = json!(cursor);
+            }
+            
+            let res = self.send(
+                self.client.post("https://api.notion.com/v1/search")
+                    .json(&search_body)
+            ).await?;
+            
+            if !res.status().is_success() {
+                return Err(format!("API error: {}", res.status()));
+            }
+            
+            let search_result = read_json(res).await?;
+            let results = search_result["results"]
+                .as_array()
+                .ok_or("Invalid response format")?;
+            seen += results.len();
+            // Appending to archived or trashed pages fails, so they're left out unless asked for
+            found.extend(
+                results
+                    .iter()
+                    .filter(|page| key.include_archived || !is_archived(page))
+                    .filter_map(page_from_search_result),
+            );
+            
+            let has_more = search_result["has_more"].as_bool() == Some(true);
+            match search_result["next_cursor"].as_str() {
+                // An empty batch can't advance the count, so it ends the search too
+                Some(next) if has_more && !results.is_empty() => {
+                    if seen >= wanted {
+                        break true;
+                    }
+                    cursor = Some(next.to_string());
+                }
+                _ => break has_more,
+            }
+        };
+        let search = PageSearch {
+            pages: self.with_parent_titles(found).await,
+            truncated,
+        };
+        
+        // Update cache with new data, dropping searches that have expired
+        {
+            let mut cache = PAGES_CACHE.lock().unwrap();
+            let now = Instant::now();
+            cache.retain(|_, entry| now < entry.expires_at);
+            cache.insert(key, CacheEntry {
+                data: search.clone(),
+                expires_at: now + CACHE_DURATION,
+            });
+        }
+        
+        Ok(search)
+    }
+    
+    // Fill in each page's breadcrumb. Parents among the results need no request;
+    // the others are read once each (at most MAX_PARENT_LOOKUPS per search) and
+    // remembered for CACHE_DURATION, unreadable ones included.
+    async fn with_parent_titles(&self, found: Vec<(NotionPage, SearchParent)>) -> Vec<NotionPage> {
+        let normalize = |id: &str| id.replace('-', "").to_lowercase();
+        let listed: HashMap<String, String> = found
+            .iter()
+            .map(|(page, _)| (normalize(&page.id), page.title.clone()))
+            .collect();
+        
+        let mut lookups = 0;
+        let mut pages = Vec::with_capacity(found.len());
+        for (mut page, parent) in found {
+            page.parent_title = match &parent {
+                SearchParent::Workspace => Some("Workspace".to_string()),
+                SearchParent::Page(id) | SearchParent::Database(id) => {
+                    let key = normalize(id);
+                    let cached = PARENT_TITLES
+                        .lock()
+                        .unwrap()
+                        .get(&key)
+                        .filter(|entry| Instant::now() < entry.expires_at)
+                        .map(|entry| entry.data.clone());
+                    match (listed.get(&key), cached) {
+                        (Some(title), _) => Some(title.clone()),
+                        (None, Some(title)) => title,
+                        (None, None) if lookups < MAX_PARENT_LOOKUPS => {
+                            lookups += 1;
+                            let title = self.parent_title(&parent).await;
+                            PARENT_TITLES.lock().unwrap().insert(key, CacheEntry {
+                                data: title.clone(),
+                                expires_at: Instant::now() + CACHE_DURATION,
+                            });
+                            title
+                        }
+                        (None, None) => None,
+                    }
+                }
+                SearchParent::Other => None,
+            };
+            pages.push(page);
+        }
+        pages
+    }
+    
+    // A parent page's or database's title, or None when it can't be read
+    async fn parent_title(&self, parent: &SearchParent) -> Option<String> {
+        match parent {
+            SearchParent::Page(id) => {
+                let page = self.fetch_page(id).await.ok()??;
+                Some(crate::page_display::title_from_page(&page).unwrap_or_else(|| "Untitled".to_string()))
+            }
+            SearchParent::Database(id) => {
+                let database = self.fetch_database(id).await.ok()??;
+                Some(database_rows::database_title(&database))
+            }
+            SearchParent::Workspace | SearchParent::Other => None,
+        }
+    }
+    
+    // Databases shared with the integration, most recently edited first. Not
+    // cached: it is only asked for when the settings window lists targets.
+    pub async fn search_databases(&self) -> Result<Vec<NotionPage>, String> {
         let search_body = json!({
             "filter": {
-                "value": "page",
+                "value": "database",
                 "property": "object"
             },
             "sort": {
@@ -119,159 +1014,663 @@ impl NotionApiClient {
             }
         });
         
-        let res = self.client
-            .post("https://api.notion.com/v1/search")
-            .json(&search_body)
-            .send()
-            .await
-            .map_err(|e| format!("API request failed: {}", e))?;
-            
+        let res = self.send(
+            self.client.post("https://api.notion.com/v1/search")
+                .json(&search_body)
+        ).await?;
+        
         if !res.status().is_success() {
             return Err(format!("API error: {}", res.status()));
         }
         
-        let search_result: serde_json::Value = res.json()
-            .await
-            .map_err(|e| format!("Failed to parse response: {}", e))?;
-            
-        let pages: Vec<NotionPage> = search_result["results"]
+        let search_result = read_json(res).await?;
+        let databases = search_result["results"]
             .as_array()
             .ok_or("Invalid response format")?
             .iter()
-            .filter_map(|page| {
-                // Extract page title from various possible properties
-                if let Some(props) = page["properties"].as_object() {
-                    // Try to find title in properties
-                    for (_, prop) in props {
-                        if let Some(title_content) = prop.get("title") {
-                            if let Some(title_array) = title_content.as_array() {
-                                if let Some(first_title) = title_array.first() {
-                                    if let Some(text) = first_title.get("text") {
-                                        if let Some(content) = text.get("content") {
-                                            if let Some(content_str) = content.as_str() {
-                                                return Some(NotionPage {
-                                                    id: page["id"].as_str().unwrap_or("").to_string(),
-                                                    title: content_str.to_string(),
-                                                    icon: page["icon"]["emoji"].as_str().map(|s| s.to_string()),
-                                                    url: page["url"].as_str().unwrap_or("").to_string(),
-                                                });
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-                
-                // Fallback to title from parent
-                if let Some(title) = page["parent"]["page"]["title"].as_str() {
-                    return Some(NotionPage {
-                        id: page["id"].as_str().unwrap_or("").to_string(),
-                        title: title.to_string(),
-                        icon: page["icon"]["emoji"].as_str().map(|s| s.to_string()),
-                        url: page["url"].as_str().unwrap_or("").to_string(),
-                    });
-                }
-                
-                None
+            .filter(|database| !is_archived(database))
+            .map(|database| NotionPage {
+                id: database["id"].as_str().unwrap_or("").to_string(),
+                title: database_rows::database_title(database),
+                icon: database["icon"]["emoji"].as_str().map(|s| s.to_string()),
+                url: database["url"].as_str().unwrap_or("").to_string(),
+                parent_title: None,
+                last_edited_time: database["last_edited_time"].as_str().map(str::to_string),
+                archived: is_archived(database),
             })
             .collect();
         
-        // Update cache with new data
-        {
-            let mut cache = PAGES_CACHE.lock().unwrap();
-            *cache = Some(CacheEntry {
-                data: pages.clone(),
-                expires_at: Instant::now() + CACHE_DURATION,
-            });
-        }
-            
-        Ok(pages)
-    }
-    
-    pub async fn append_note_to_page(
-        &self, 
-        page_id: &str, 
-        note_text: &str
-    ) -> Result<(), String> {
-        // Generate timestamp in format [DD MMM YY, HH:MM:SS]
-        let now = Local::now();
-        let timestamp = format!(
-            "[{:02} {} {:02}, {:02}:{:02}:{:02}]",
-            now.day(),
-            match now.month() {
-                1 => "Jan", 2 => "Feb", 3 => "Mar", 4 => "Apr", 5 => "May", 6 => "Jun",
-                7 => "Jul", 8 => "Aug", 9 => "Sep", 10 => "Oct", 11 => "Nov", 12 => "Dec",
-                _ => "Unknown",
-            },
-            now.year() % 100,
-            now.hour(),
-            now.minute(),
-            now.second()
-        );
+        Ok(databases)
+    }
+    
+    // Append blocks in requests of at most MAX_CHILDREN_PER_REQUEST, tracking what landed
+    // so a failure part-way through is reported as a partial delivery
+    pub async fn append_children(
+        &self,
+        page_id: &str,
+        page_title: &str,
+        children: &[serde_json::Value],
+    ) -> Result<AppendResponse, AppendFailure> {
+        self.append_children_after(page_id, page_title, children, None).await
+    }
+    
+    // As `append_children`, placed after the child block `after` instead of at the end
+    pub async fn append_children_after(
+        &self,
+        page_id: &str,
+        page_title: &str,
+        children: &[serde_json::Value],
+        after: Option<&str>,
+    ) -> Result<AppendResponse, AppendFailure> {
+        let after_anchor = after;
+        let chunks: Vec<&[serde_json::Value]> = children.chunks(MAX_CHILDREN_PER_REQUEST).collect();
+        let total_chunks = chunks.len();
+        let mut delivered = AppendResponse {
+            block_ids: Vec::new(),
+            request_id: None,
+            status: 0,
+            attempts: 0,
+        };
         
-        // Structure the request body for appending a block to the page
-        let append_body = json!({
-            "children": [
-                {
-                    "object": "block",
-                    "type": "paragraph",
-                    "paragraph": {
-                        "rich_text": [
-                            {
-                                "type": "text",
-                                "text": {
-                                    "content": format!("{} {}", timestamp, note_text)
-                                },
-                                "annotations": {
-                                    "bold": true,
-                                    "color": "default"
-                                }
-                            }
-                        ]
+        for (index, chunk) in chunks.into_iter().enumerate() {
+            // Later chunks follow the blocks the previous one added
+            let after = delivered.block_ids.last().map(String::as_str).or(after);
+            match self.append_chunk(page_id, page_title, chunk, after).await {
+                Ok(response) => {
+                    delivered.block_ids.extend(response.block_ids);
+                    // Appended at the end, so the last block is now the page's tail
+                    if let (None, Some(last)) = (after_anchor, delivered.block_ids.last()) {
+                        self.remember_tail(page_id, last);
                     }
+                    delivered.request_id = response.request_id;
+                    delivered.status = response.status;
+                    delivered.attempts += response.attempts;
                 }
-            ]
+                Err(failure) if index == 0 => return Err(failure),
+                Err(failure) => {
+                    return Err(AppendFailure::Partial(PartialDelivery {
+                        delivered,
+                        delivered_chunks: index,
+                        total_chunks,
+                        error: failure.into(),
+                    }));
+                }
+            }
+        }
+        
+        Ok(delivered)
+    }
+    
+    // One PATCH request, retried after ambiguous failures once we know it didn't land
+    async fn append_chunk(
+        &self,
+        page_id: &str,
+        page_title: &str,
+        chunk: &[serde_json::Value],
+        after: Option<&str>,
+    ) -> Result<AppendResponse, AppendFailure> {
+        // Structure the request body for appending blocks to the page
+        let mut append_body = json!({
+            "children": chunk
         });
+        if let Some(after) = after {
+            append_body["after"] = json!(after);
+        }
         
-        let res = self.client
-            .patch(&format!("https://api.notion.com/v1/blocks/{}/children", page_id))
-            .json(&append_body)
-            .send()
-            .await
-            .map_err(|e| format!("API request failed: {}", e))?;
-            
+        // Set after a failure that may still have applied the append; a conflict never does
+        let needs_check = AtomicBool::new(false);
+        let after_conflict = AtomicBool::new(false);
+        let outcome = retry::retry_async(
+            &APPEND_RETRY,
+            None,
+            |retry| {
+                let conflict = retry.error.is_conflict();
+                let delay = if conflict { retry.delay + CONFLICT_EXTRA_WAIT } else { retry.delay };
+                after_conflict.store(conflict, Ordering::Relaxed);
+                eprintln!(
+                    "Append failed (attempt {}/{}), retrying in {:?}: {}",
+                    retry.attempt, retry.max_attempts, delay, describe_attempt_error(retry.error)
+                );
+                if let Some(observer) = &self.on_retry {
+                    observer(AppendRetry {
+                        attempt: retry.attempt,
+                        max_attempts: retry.max_attempts,
+                        retry_in_ms: delay.as_millis() as u64,
+                        reason: retry.error.retry_reason(),
+                    });
+                }
+            },
+            |attempt| {
+                let needs_check = &needs_check;
+                let after_conflict = &after_conflict;
+                let append_body = &append_body;
+                async move {
+                    if after_conflict.swap(false, Ordering::Relaxed) {
+                        tokio::time::sleep(CONFLICT_EXTRA_WAIT).await;
+                    }
+                    // Look for our blocks before sending them a second time
+                    if needs_check.swap(false, Ordering::Relaxed) {
+                        if let Some(response) = self.already_appended(page_id, &append_body["children"], after, attempt - 1)
+                            .await
+                            .map_err(|e| ChunkAttemptError::Failed(AppendFailure::Failed(e)))?
+                        {
+                            return Ok(response);
+                        }
+                    }
+                    
+                    let send = || self.send_raw(self.client
+                        .patch(format!("https://api.notion.com/v1/blocks/{}/children", page_id))
+                        .json(append_body));
+                    let mut sent = send().await;
+                    // send_raw recorded the Retry-After, so the limiter holds the resend until it has passed
+                    if sent.as_ref().is_ok_and(|res| res.status() == reqwest::StatusCode::TOO_MANY_REQUESTS)
+                        && RATE_LIMITER.limited_for(&self.api_token).unwrap_or_default() < SHORT_RATE_LIMIT_WAIT
+                    {
+                        // The 429 gives its in-flight slot back before waiting for another
+                        drop(sent);
+                        sent = send().await;
+                    }
+                    match sent {
+                        Ok(res) if res.status() == reqwest::StatusCode::CONFLICT => {
+                            Err(ChunkAttemptError::Status(release_permit(res)))
+                        }
+                        Ok(res) if res.status().is_server_error() => {
+                            needs_check.store(true, Ordering::Relaxed);
+                            Err(ChunkAttemptError::Status(release_permit(res)))
+                        }
+                        Ok(res) => self.read_append_response(res, page_id, page_title, attempt)
+                            .await
+                            .map_err(ChunkAttemptError::Failed),
+                        Err(e) if is_ambiguous_failure(&e) => {
+                            needs_check.store(true, Ordering::Relaxed);
+                            Err(ChunkAttemptError::Ambiguous(e.to_string()))
+                        }
+                        Err(e) if e.is_connect() => Err(ChunkAttemptError::Unreachable(e.to_string())),
+                        Err(e) => Err(ChunkAttemptError::Failed(AppendFailure::Failed(format!("API request failed: {}", e)))),
+                    }
+                }
+            },
+        ).await;
+        
+        match outcome.result {
+            Ok(response) => {
+                if outcome.attempts > 1 {
+                    println!("Append succeeded after {} attempts", outcome.attempts);
+                }
+                Ok(response)
+            }
+            Err(ChunkAttemptError::Status(res)) => {
+                self.read_append_response(res, page_id, page_title, outcome.attempts).await
+            }
+            Err(ChunkAttemptError::Ambiguous(e)) => {
+                // The last attempt may have landed too
+                if let Some(response) = self.already_appended(page_id, &append_body["children"], after, outcome.attempts)
+                    .await
+                    .map_err(AppendFailure::Failed)?
+                {
+                    return Ok(response);
+                }
+                Err(AppendFailure::Failed(format!("API request failed: {}", e)))
+            }
+            Err(ChunkAttemptError::Unreachable(e)) => Err(AppendFailure::Failed(format!("API request failed: {}", e))),
+            Err(ChunkAttemptError::Failed(failure)) => Err(failure),
+        }
+    }
+    
+    // The receipt for an append that already landed after `attempts` sends, if it did
+    async fn already_appended(
+        &self,
+        page_id: &str,
+        children: &serde_json::Value,
+        after: Option<&str>,
+        attempts: u32,
+    ) -> Result<Option<AppendResponse>, String> {
+        let Some(block_ids) = self.find_appended_blocks(page_id, children, after).await? else {
+            return Ok(None);
+        };
+        println!("Append had already landed, not retrying");
+        Ok(Some(AppendResponse {
+            block_ids,
+            request_id: None,
+            status: 200,
+            attempts,
+        }))
+    }
+    
+    async fn read_append_response(
+        &self,
+        res: reqwest::Response,
+        page_id: &str,
+        page_title: &str,
+        attempts: u32,
+    ) -> Result<AppendResponse, AppendFailure> {
         if !res.status().is_success() {
             // Store the status code before moving res
             let status = res.status();
-            let error_body: serde_json::Value = res.json()
-                .await
-                .map_err(|e| format!("Failed to parse error response: {}", e))?;
+            
+            if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                let retry_after = RATE_LIMITER.limited_for(&self.api_token).unwrap_or_default();
+                return Err(AppendFailure::Failed(AppError::RateLimitError {
+                    retry_after: retry_after.as_secs_f64().ceil() as u64,
+                }.to_string()));
+            }
+            
+            if status == reqwest::StatusCode::FORBIDDEN {
+                self.remember_access(page_id, PageAccess::ReadOnly);
+                return Err(AppendFailure::Failed(format!(
+                    "Notion refused to append to \"{}\" (403). The integration may only have read access to this page - share it with edit permissions or choose another page in Settings.",
+                    page_title
+                )));
+            }
+            // An unreadable error body (e.g. an HTML page from a proxy) still reports the status
+            let error_body = read_json(res).await.unwrap_or_default();
+            
+            if status == reqwest::StatusCode::CONFLICT {
+                return Err(AppendFailure::Failed(AppError::Conflict(format!(
+                    "\"{}\" was being edited and the note couldn't be added",
+                    page_title
+                )).to_string()));
+            }
+            
+            if is_page_full_error(status.as_u16(), &error_body) {
+                return Err(AppendFailure::PageFull(format!(
+                    "\"{}\" has reached Notion's limit on blocks per page. Start a new page for your notes.",
+                    page_title
+                )));
+            }
                 
-            return Err(format!(
+            return Err(AppendFailure::Failed(format!(
                 "API error: {} - {}", 
                 status,
                 error_body["message"].as_str().unwrap_or("Unknown error")
-            ));
+            )));
+        }
+        
+        let status = res.status().as_u16();
+        let request_id = res.headers()
+            .get("x-request-id")
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string());
+        let body = read_json(res).await.map_err(AppendFailure::Failed)?;
+        let block_ids = body["results"]
+            .as_array()
+            .map(|results| {
+                results.iter()
+                    .filter_map(|block| block["id"].as_str().map(|id| id.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        
+        Ok(AppendResponse {
+            block_ids,
+            request_id,
+            status,
+            attempts,
+        })
+    }
+    
+    // The newest page of a block's children (up to 100). Bounded: follows at most
+    // MAX_LAST_CHILDREN_PAGES cursors. Ok(None) when Notion refuses the listing.
+    pub async fn last_children(&self, page_id: &str) -> Result<Option<Vec<serde_json::Value>>, String> {
+        let mut cursor: Option<String> = None;
+        let mut results = Vec::new();
+        for _ in 0..MAX_LAST_CHILDREN_PAGES {
+            let mut url = format!("https://api.notion.com/v1/blocks/{}/children?page_size=100", page_id);
+            if let Some(cursor) = &cursor {
+                url.push_str(&format!("&start_cursor={}", cursor));
+            }
+            
+            let res = self.send(self.client.get(url)).await?;
+            if !res.status().is_success() {
+                return Ok(None);
+            }
+            let body = read_json(res).await?;
+            
+            results = body["results"].as_array().cloned().unwrap_or_default();
+            match body["next_cursor"].as_str() {
+                Some(next) if body["has_more"].as_bool() == Some(true) => cursor = Some(next.to_string()),
+                _ => {
+                    if let Some(last) = results.last().and_then(|block| block["id"].as_str()) {
+                        self.remember_tail(page_id, last);
+                    }
+                    break;
+                }
+            }
+        }
+        
+        Ok(Some(results))
+    }
+    
+    // One page of children starting at `cursor` (a block id, listed first), or
+    // at the top of the page. Ok(None) when Notion refuses the listing.
+    async fn children_from(&self, page_id: &str, cursor: Option<&str>) -> Result<Option<Vec<serde_json::Value>>, String> {
+        let mut url = format!("https://api.notion.com/v1/blocks/{}/children?page_size=100", page_id);
+        if let Some(cursor) = cursor {
+            url.push_str(&format!("&start_cursor={}", cursor));
+        }
+        let res = self.send(self.client.get(url)).await?;
+        if !res.status().is_success() {
+            return Ok(None);
+        }
+        let body = read_json(res).await?;
+        Ok(Some(body["results"].as_array().cloned().unwrap_or_default()))
+    }
+    
+    // The oldest page of a block's children (up to 100), i.e. the top of a page
+    pub async fn first_children(&self, page_id: &str) -> Result<Vec<serde_json::Value>, String> {
+        let res = self.send(
+            self.client.get(format!("https://api.notion.com/v1/blocks/{}/children?page_size=100", page_id))
+        ).await?;
+        if !res.status().is_success() {
+            return Err(AppError::NotionApiError(format!(
+                "Failed to list the blocks of {}: HTTP {}",
+                page_id,
+                res.status()
+            )).to_string());
+        }
+        let body = read_json(res).await?;
+        Ok(body["results"].as_array().cloned().unwrap_or_default())
+    }
+    
+    // Every child of a nested block, to copy it; more than fit in one append is an error
+    pub async fn nested_children(&self, block_id: &str) -> Result<Vec<serde_json::Value>, String> {
+        let res = self.send(
+            self.client.get(format!("https://api.notion.com/v1/blocks/{}/children?page_size=100", block_id))
+        ).await?;
+        if !res.status().is_success() {
+            return Err(AppError::NotionApiError(format!(
+                "Failed to list the blocks of {}: HTTP {}",
+                block_id,
+                res.status()
+            )).to_string());
+        }
+        let body = read_json(res).await?;
+        if body["has_more"].as_bool() == Some(true) {
+            return Err(format!("Block {} has more than {} nested blocks", block_id, MAX_CHILDREN_PER_REQUEST));
+        }
+        Ok(body["results"].as_array().cloned().unwrap_or_default())
+    }
+    
+    // The newest child page of `parent_id` titled exactly `title`. Ok(None)
+    // when there is none among the first MAX_CHILD_PAGE_SCAN_PAGES pages of children.
+    pub async fn find_child_page(&self, parent_id: &str, title: &str) -> Result<Option<String>, String> {
+        let mut cursor: Option<String> = None;
+        let mut found = None;
+        for _ in 0..MAX_CHILD_PAGE_SCAN_PAGES {
+            let mut url = format!("https://api.notion.com/v1/blocks/{}/children?page_size=100", parent_id);
+            if let Some(cursor) = &cursor {
+                url.push_str(&format!("&start_cursor={}", cursor));
+            }
+            
+            let res = self.send(self.client.get(url)).await?;
+            if !res.status().is_success() {
+                return Err(AppError::NotionApiError(format!(
+                    "Failed to list the pages under {}: HTTP {}",
+                    parent_id,
+                    res.status()
+                )).to_string());
+            }
+            let body = read_json(res).await?;
+            
+            let results = body["results"].as_array().cloned().unwrap_or_default();
+            found = results
+                .iter()
+                .rev()
+                .find(|block| block["type"] == "child_page" && block["child_page"]["title"] == title)
+                .and_then(|block| block["id"].as_str().map(str::to_string))
+                .or(found);
+            match body["next_cursor"].as_str() {
+                Some(next) if body["has_more"].as_bool() == Some(true) => cursor = Some(next.to_string()),
+                _ => break,
+            }
+        }
+        
+        Ok(found)
+    }
+    
+    pub async fn get_block(&self, block_id: &str) -> Result<serde_json::Value, String> {
+        let res = self.send(
+            self.client.get(format!("https://api.notion.com/v1/blocks/{}", block_id))
+        ).await?;
+        
+        if !res.status().is_success() {
+            return Err(AppError::NotionApiError(format!("Failed to read block {}: HTTP {}", block_id, res.status())).to_string());
+        }
+        read_json(res).await
+    }
+    
+    // A block, or None when Notion no longer has it
+    pub async fn fetch_block(&self, block_id: &str) -> Result<Option<serde_json::Value>, String> {
+        let res = self.send(
+            self.client.get(format!("https://api.notion.com/v1/blocks/{}", block_id))
+        ).await?;
+        
+        if res.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !res.status().is_success() {
+            return Err(AppError::NotionApiError(format!("Failed to read block {}: HTTP {}", block_id, res.status())).to_string());
         }
+        read_json(res).await.map(Some)
+    }
+    
+    // Replace the text of a block of `block_type`; Ok(false) when the block no longer exists
+    pub async fn update_block_text(
+        &self,
+        block_id: &str,
+        block_type: &str,
+        rich_text: Vec<serde_json::Value>,
+    ) -> Result<bool, String> {
+        let res = self.send(
+            self.client
+                .patch(format!("https://api.notion.com/v1/blocks/{}", block_id))
+                .json(&json!({ block_type: { "rich_text": rich_text } }))
+        ).await?;
         
+        if res.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+        if !res.status().is_success() {
+            let status = res.status();
+            let error_body = read_json(res).await.unwrap_or_default();
+            return Err(format!(
+                "API error: {} - {}",
+                status,
+                error_body["message"].as_str().unwrap_or("Unknown error")
+            ));
+        }
+        Ok(true)
+    }
+    
+    // Archive a block; Notion keeps it in the page's trash
+    pub async fn delete_block(&self, block_id: &str) -> Result<(), String> {
+        let status = self.delete_block_status(block_id).await?;
+        if !status.is_success() {
+            return Err(AppError::NotionApiError(format!("Failed to delete block {}: HTTP {}", block_id, status)).to_string());
+        }
         Ok(())
     }
+    
+    // As `delete_block`, with Notion's answer for callers that handle 404 and 409 themselves
+    pub async fn delete_block_status(&self, block_id: &str) -> Result<reqwest::StatusCode, String> {
+        let res = self.send(
+            self.client.delete(format!("https://api.notion.com/v1/blocks/{}", block_id))
+        ).await?;
+        Ok(res.status())
+    }
+    
+    // Look for blocks matching the ones we tried to append where they would
+    // have landed: right after `after` when it is set, otherwise at the end of
+    // the page. Bounded to one children listing, which starts at `after` or at
+    // the last block we know the page ends with (the top of the page when we
+    // know none, so a long page unseen this session may not be checked fully).
+    pub async fn find_appended_blocks(
+        &self,
+        page_id: &str,
+        children: &serde_json::Value,
+        after: Option<&str>,
+    ) -> Result<Option<Vec<String>>, String> {
+        let expected: Vec<String> = match children.as_array() {
+            Some(children) if !children.is_empty() => children.iter().map(blocks::block_text).collect(),
+            _ => return Ok(None),
+        };
+        
+        let tail = after.is_none().then(|| self.known_tail(page_id)).flatten();
+        let Some(results) = self.children_from(page_id, after.or(tail.as_deref())).await? else {
+            // Most likely the remembered tail block was deleted; the next check starts from the top
+            if tail.is_some() {
+                self.forget_tail(page_id);
+            }
+            return Ok(None);
+        };
+        
+        // The timestamp prefix makes the first block's text close to unique
+        let texts: Vec<String> = results.iter().map(blocks::block_text).collect();
+        let found = texts
+            .windows(expected.len())
+            .rposition(|window| window == expected.as_slice());
+        
+        Ok(found.map(|start| {
+            results[start..start + expected.len()]
+                .iter()
+                .filter_map(|block| block["id"].as_str().map(|id| id.to_string()))
+                .collect()
+        }))
+    }
+}
+
+// Archived pages and, in newer API versions, pages in the trash
+fn is_archived(page: &serde_json::Value) -> bool {
+    page["archived"].as_bool() == Some(true) || page["in_trash"].as_bool() == Some(true)
+}
+
+// A page from a search response, with the parent it names. The title is every
+// run of its title property, whatever that is called; untitled pages are kept
+// as "Untitled".
+fn page_from_search_result(page: &serde_json::Value) -> Option<(NotionPage, SearchParent)> {
+    let parent = &page["parent"];
+    let parent = match parent["type"].as_str() {
+        Some("workspace") => SearchParent::Workspace,
+        Some("page_id") => parent["page_id"].as_str().map_or(SearchParent::Other, |id| SearchParent::Page(id.to_string())),
+        Some("database_id") => parent["database_id"]
+            .as_str()
+            .map_or(SearchParent::Other, |id| SearchParent::Database(id.to_string())),
+        _ => SearchParent::Other,
+    };
+    let page = NotionPage {
+        id: page["id"].as_str()?.to_string(),
+        title: crate::page_display::title_from_page(page).unwrap_or_else(|| "Untitled".to_string()),
+        icon: page["icon"]["emoji"].as_str().map(|s| s.to_string()),
+        url: page["url"].as_str().unwrap_or("").to_string(),
+        parent_title: None,
+        last_edited_time: page["last_edited_time"]
+            .as_str()
+            .and_then(|time| chrono::DateTime::parse_from_rfc3339(time).ok())
+            .map(|time| time.to_rfc3339()),
+        archived: is_archived(page),
+    };
+    Some((page, parent))
+}
+
+// Shared response handling: checks the content type, caps the body size and
+// bounds the read time, so a misbehaving proxy can't stall or bloat the app
+async fn read_json(res: reqwest::Response) -> Result<serde_json::Value, String> {
+    read_json_within(res, READ_TIMEOUT).await
+}
+
+async fn read_json_within(mut res: reqwest::Response, read_timeout: Duration) -> Result<serde_json::Value, String> {
+    let status = res.status();
+    let request_line = res.extensions().get::<recording::RequestLine>().cloned();
+    let content_type = res.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+    
+    if !content_type.starts_with("application/json") {
+        return Err(AppError::NetworkError(format!(
+            "Expected a JSON response but got '{}' (HTTP {})",
+            content_type, status
+        )).to_string());
+    }
+    
+    let too_large = || AppError::NetworkError(format!(
+        "Response body exceeds {} bytes (HTTP {})",
+        MAX_RESPONSE_BYTES, status
+    )).to_string();
+    
+    if res.content_length().is_some_and(|len| len > MAX_RESPONSE_BYTES as u64) {
+        return Err(too_large());
+    }
+    
+    let read = async {
+        let mut body = Vec::new();
+        while let Some(chunk) = res.chunk()
+            .await
+            .map_err(|e| AppError::NetworkError(format!("Failed to read response: {}", e)).to_string())?
+        {
+            if body.len() + chunk.len() > MAX_RESPONSE_BYTES {
+                return Err(too_large());
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body)
+    };
+    
+    let body = tokio::time::timeout(read_timeout, read)
+        .await
+        .map_err(|_| AppError::NetworkError(format!(
+            "Timed out reading response after {}s",
+            read_timeout.as_secs()
+        )).to_string())??;
+    
+    let parsed = serde_json::from_slice::<serde_json::Value>(&body);
+    if recording::is_recording() {
+        let recorded = match &parsed {
+            Ok(value) => value.clone(),
+            Err(_) => json!({ "unparseable_bytes": body.len() }),
+        };
+        recording::record_response(request_line.as_ref(), status.as_u16(), &recorded);
+    }
+    
+    parsed.map_err(|e| format!("Failed to parse response: {}", e))
+}
+
+// A response kept through a retry backoff gives its in-flight slot back first;
+// the small error body left in it is read without one
+fn release_permit(mut res: reqwest::Response) -> reqwest::Response {
+    res.extensions_mut().remove::<RequestPermit<'static>>();
+    res
+}
+
+fn describe_attempt_error(error: &ChunkAttemptError) -> String {
+    match error {
+        ChunkAttemptError::Status(res) => format!("Notion answered {}", res.status()),
+        ChunkAttemptError::Ambiguous(e) => format!("connection lost: {}", e),
+        ChunkAttemptError::Unreachable(e) => format!("couldn't connect: {}", e),
+        ChunkAttemptError::Failed(failure) => String::from(failure.clone()),
+    }
+}
+
+fn is_ambiguous_failure(error: &reqwest::Error) -> bool {
+    if error.is_connect() || error.is_builder() {
+        return false;
+    }
+    error.is_timeout() || error.is_request() || error.is_body()
 }
 
 // Tauri commands for Notion API integration
 
 // Function to invalidate cache (call when token changes)
 fn invalidate_cache() {
-    let mut cache = PAGES_CACHE.lock().unwrap();
-    *cache = None;
+    PAGES_CACHE.lock().unwrap().clear();
 }
 
 // Set and verify API token
 #[tauri::command]
 pub async fn set_notion_api_token(
     api_token: String,
+    app: AppHandle,
     state: State<'_, AppState>,
 ) -> Result<bool, String> {
     // Clear all caches when token changes
@@ -286,12 +1685,13 @@ pub async fn set_notion_api_token(
                         let token_to_save = api_token.clone();
                         {
                             let mut config = state.config.lock().unwrap();
-                            config.notion_api_token = token_to_save;
+                            config.secrets.set_main_token(token_to_save);
                             // Save to disk
                             if let Err(e) = config.save() {
                                 return Err(format!("Failed to save config: {}", e));
                             }
                         }
+                        auth::token_verified(&app);
                         Ok(true)
                     } else {
                         Err("Invalid API token".into())
@@ -308,71 +1708,2025 @@ pub async fn set_notion_api_token(
 #[tauri::command]
 pub fn get_notion_api_token(state: State<'_, AppState>) -> Result<String, String> {
     let config = state.config.lock().unwrap();
-    Ok(config.notion_api_token.clone())
+    Ok(config.secrets.main_token().to_string())
+}
+
+// Notion answers appends to an oversized page with a validation_error about the children limit
+pub fn is_page_full_error(status: u16, body: &serde_json::Value) -> bool {
+    if status != 400 || body["code"].as_str() != Some("validation_error") {
+        return false;
+    }
+    let message = body["message"].as_str().unwrap_or("").to_lowercase();
+    message.contains("children") && (message.contains("limit") || message.contains("exceed") || message.contains("maximum"))
+}
+
+// Title of the page that continues a full one, e.g. "Inbox (cont. 2026-10)". A
+// continuation that fills up in turn is titled after the original, not "(cont. …) (cont. …)".
+pub fn rotation_title(page_title: &str, now: &chrono::DateTime<chrono::Local>) -> String {
+    format!("{} (cont. {})", original_title(page_title), now.format("%Y-%m"))
+}
+
+// The title with any "(cont. YYYY-MM)" suffixes removed
+fn original_title(mut title: &str) -> &str {
+    while let Some((original, month)) = title.strip_suffix(')').and_then(|rest| rest.rsplit_once(" (cont. ")) {
+        if chrono::NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d").is_err() {
+            break;
+        }
+        title = original;
+    }
+    title
+}
+
+// Body for creating a child page, e.g. the continuation page under the full page's parent
+pub fn rotation_page_body(parent_page_id: &str, title: &str) -> serde_json::Value {
+    json!({
+        "parent": { "page_id": parent_page_id },
+        "properties": {
+            "title": {
+                "title": [blocks::text_run(title, false)]
+            }
+        }
+    })
+}
+
+// Create a continuation page next to the full one, under the same parent
+async fn create_continuation_page(client: &NotionApiClient, page_id: &str, page_title: &str) -> Result<PageRef, String> {
+    let page = client.fetch_page(page_id).await?
+        .ok_or("The full page no longer exists")?;
+    let parent_id = page["parent"]["page_id"].as_str()
+        .ok_or("Only pages nested under another page can be continued automatically")?;
+    
+    let title = rotation_title(page_title, &clock::now());
+    let created = client.create_page(&rotation_page_body(parent_id, &title)).await?;
+    Ok(PageRef {
+        id: created["id"].as_str().ok_or("Notion didn't return the new page's id")?.to_string(),
+        title,
+        icon: None,
+        stale: false,
+    })
+}
+
+// Create a continuation page next to the full one and make it the selected page
+async fn rotate_selected_page(
+    app: &AppHandle,
+    client: &NotionApiClient,
+    page_id: &str,
+    page_title: &str,
+) -> Result<PageRef, String> {
+    let new_ref = create_continuation_page(client, page_id, page_title).await?;
+    
+    {
+        let state = app.state::<AppState>();
+        let mut config = state.config.lock().unwrap();
+        config.set_selected_page_ref(&new_ref);
+        config.save()?;
+    }
+    println!("Selected page rotated from {} to {} (\"{}\")", page_id, new_ref.id, new_ref.title);
+    let audit = crate::page_rotations::record(crate::page_rotations::PageRotationEntry {
+        from_page_id: page_id.to_string(),
+        from_title: page_title.to_string(),
+        to_page_id: new_ref.id.clone(),
+        to_title: new_ref.title.clone(),
+        rotated_at: clock::now().to_rfc3339(),
+    });
+    if let Err(e) = audit {
+        eprintln!("Failed to record page rotation: {}", e);
+    }
+    
+    events::emit_app_event(app, AppEvent::PageRotated(PageRotated {
+        from: page_id.to_string(),
+        to: new_ref.id.clone(),
+        title: new_ref.title.clone(),
+    }));
+    events::emit_app_event(app, AppEvent::ConfigChanged);
+    crate::refresh_tray(app);
+    
+    Ok(new_ref)
+}
+
+// When `result` says the page is full, continue on the page `rotate` creates and
+// retry the append there. Both run at most once, so a full page never loops.
+async fn retry_on_new_page<Rotate, Rotated, Append, Appended>(
+    result: Result<AppendResponse, AppendFailure>,
+    rotate: Rotate,
+    append: Append,
+) -> (Result<AppendResponse, AppendFailure>, Option<PageRef>)
+where
+    Rotate: FnOnce() -> Rotated,
+    Rotated: Future<Output = Result<PageRef, String>>,
+    Append: FnOnce(PageRef) -> Appended,
+    Appended: Future<Output = Result<AppendResponse, AppendFailure>>,
+{
+    if !matches!(result, Err(AppendFailure::PageFull(_))) {
+        return (result, None);
+    }
+    match rotate().await {
+        Ok(page_ref) => (append(page_ref.clone()).await, Some(page_ref)),
+        Err(e) => {
+            eprintln!("Failed to rotate full page: {}", e);
+            (result, None)
+        }
+    }
 }
 
-// Search Notion pages with cache usage
+// After a failed sandbox append, drop the stored sandbox page if Notion no longer has it
+async fn forget_deleted_sandbox(app: &AppHandle, client: &NotionApiClient, page_id: &str) {
+    forget_deleted_sandbox_in(&app.state::<AppState>().config, app, client, page_id).await;
+}
+
+// Returns true when the stored id was cleared
+async fn forget_deleted_sandbox_in(
+    config: &Mutex<AppConfig>,
+    effects: &impl PageRefEffects,
+    client: &NotionApiClient,
+    page_id: &str,
+) -> bool {
+    if !matches!(client.fetch_page(page_id).await, Ok(None)) {
+        return false;
+    }
+    {
+        let mut config = config.lock().unwrap();
+        if config.sandbox_page_id.as_deref() != Some(page_id) {
+            // Another sandbox was created meanwhile
+            return false;
+        }
+        let mut updated = config.clone();
+        updated.sandbox_page_id = None;
+        if let Err(e) = effects.save(&mut updated) {
+            eprintln!("Failed to clear deleted sandbox page: {}", e);
+            return false;
+        }
+        *config = updated;
+    }
+    effects.config_changed();
+    true
+}
+
+// Create the sandbox as a child of the selected page and remember it
 #[tauri::command]
-pub async fn search_notion_pages(
+pub async fn create_sandbox_page(app: AppHandle, state: State<'_, AppState>) -> Result<PageRef, String> {
+    let (api_token, parent_id) = {
+        let config = state.config.lock().unwrap();
+        (config.secrets.main_token().to_string(), config.selected_page_id.clone())
+    };
+    if parent_id.is_empty() {
+        return Err("Select a page first; the sandbox is created inside it".into());
+    }
+    
+    let client = NotionApiClient::new(api_token)?;
+    let created = client.create_page(&rotation_page_body(&parent_id, SANDBOX_PAGE_TITLE)).await?;
+    let page_ref = PageRef {
+        id: created["id"].as_str().ok_or("Notion didn't return the new page's id")?.to_string(),
+        title: SANDBOX_PAGE_TITLE.to_string(),
+        icon: None,
+        stale: false,
+    };
+    
+    crate::settings::update_config(&app, |config| {
+        config.sandbox_page_id = Some(page_ref.id.clone());
+        Ok(())
+    })?;
+    Ok(page_ref)
+}
+
+// Create a page for captures under an existing page, optionally making it the
+// selected target. Notion doesn't let integrations create top-level pages, so
+// a parent is required.
+#[tauri::command]
+pub async fn create_notion_page(
+    title: String,
+    parent_page_id: String,
+    select: Option<bool>,
+    app: AppHandle,
     state: State<'_, AppState>,
-) -> Result<Vec<NotionPage>, String> {
-    // Extract what we need from the Mutex and immediately drop the lock
+) -> Result<NotionPage, String> {
+    let title = title.trim().to_string();
+    if title.is_empty() {
+        return Err(AppError::ValidationError("The new page needs a title".into()).to_string());
+    }
+    if parent_page_id.trim().is_empty() {
+        return Err(AppError::ValidationError(
+            "Notion requires a parent page for pages created through the API; choose one to create it under".into(),
+        ).to_string());
+    }
     let api_token = {
         let config = state.config.lock().unwrap();
-        let token = config.notion_api_token.clone();
-        if token.is_empty() {
+        if config.secrets.main_token().is_empty() {
             return Err("API token is not set".into());
         }
-        token
-    }; // MutexGuard is dropped here
+        config.secrets.main_token().to_string()
+    };
     
-    // Now we can safely use .await
     let client = NotionApiClient::new(api_token)?;
-    client.search_pages().await
+    let created = client.create_page(&rotation_page_body(parent_page_id.trim(), &title)).await?;
+    let page = NotionPage {
+        id: created["id"].as_str().ok_or("Notion didn't return the new page's id")?.to_string(),
+        title,
+        icon: None,
+        url: created["url"].as_str().unwrap_or("").to_string(),
+        parent_title: None,
+        last_edited_time: created["last_edited_time"].as_str().map(str::to_string),
+        archived: false,
+    };
+    // So the next search lists it
+    invalidate_cache();
+    
+    if select.unwrap_or(false) {
+        {
+            let mut config = state.config.lock().unwrap();
+            config.set_selected_page_ref(&PageRef {
+                id: page.id.clone(),
+                title: page.title.clone(),
+                icon: None,
+                stale: false,
+            });
+            config.selected_target_kind = TargetKind::Page;
+            config.save()?;
+        }
+        events::emit_app_event(&app, AppEvent::ConfigChanged);
+        crate::refresh_tray(&app);
+    }
+    Ok(page)
 }
 
-// Get the selected page ID
-#[tauri::command]
-pub fn get_selected_page_id(state: State<'_, AppState>) -> Result<String, String> {
-    let config = state.config.lock().unwrap();
-    Ok(config.selected_page_id.clone())
+// Page IDs come back with or without dashes depending on the endpoint
+pub fn same_page_id(a: &str, b: &str) -> bool {
+    let normalize = |id: &str| id.replace('-', "").to_lowercase();
+    normalize(a) == normalize(b)
 }
 
-// Set the selected page ID
-#[tauri::command]
-pub fn set_selected_page_id(
-    page_id: String,
-    page_title: String,
-    state: State<'_, AppState>,
-) -> Result<(), String> {
-    let mut config = state.config.lock().unwrap();
-    config.selected_page_id = page_id;
-    config.selected_page_title = page_title;
-    config.save()
+// Titles that differ only in whitespace are considered unchanged
+fn same_title(a: &str, b: &str) -> bool {
+    a.split_whitespace().eq(b.split_whitespace())
 }
 
-// Append a note to the selected Notion page
-#[tauri::command]
-pub async fn append_note(
-    note_text: String,
-    state: State<'_, AppState>,
-) -> Result<(), String> {
-    // Extract what we need and drop the lock before async operations
-    let (api_token, page_id) = {
-        let config = state.config.lock().unwrap();
-        
-        if config.notion_api_token.is_empty() {
-            return Err("Notion API token not set".into());
+// A page reference stored in config, enriched from search results
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PageRef {
+    pub id: String,
+    pub title: String,
+    pub icon: Option<String>,
+    // The page no longer shows up (archived, deleted, or unshared)
+    pub stale: bool,
+}
+
+// Outcome of reconciling a page reference against fresh search results
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Reconciled {
+    Unchanged,
+    Updated,
+    // Not in the results; the caller has to confirm whether it is really gone
+    Missing,
+}
+
+// Bring a stored reference up to date with the search results.
+// Titles that differ only in whitespace are not treated as renames.
+pub fn reconcile_page_ref(page_ref: &mut PageRef, pages: &[NotionPage]) -> Reconciled {
+    let page = match pages.iter().find(|p| same_page_id(&p.id, &page_ref.id)) {
+        Some(page) => page,
+        None => return Reconciled::Missing,
+    };
+    
+    let mut changed = false;
+    if !same_title(&page.title, &page_ref.title) {
+        println!("Page renamed: '{}' -> '{}'", page_ref.title, page.title);
+        page_ref.title = page.title.clone();
+        changed = true;
+    }
+    if page.icon.is_some() && page.icon != page_ref.icon {
+        page_ref.icon = page.icon.clone();
+        changed = true;
+    }
+    if page_ref.stale {
+        page_ref.stale = false;
+        changed = true;
+    }
+    
+    if changed {
+        Reconciled::Updated
+    } else {
+        Reconciled::Unchanged
+    }
+}
+
+// What storing a page reference does besides changing the config, so the
+// update path can be followed without the app
+pub trait PageRefEffects {
+    fn save(&self, config: &mut AppConfig) -> Result<(), String>;
+    // Let the UI and tray know
+    fn config_changed(&self);
+}
+
+impl PageRefEffects for AppHandle {
+    fn save(&self, config: &mut AppConfig) -> Result<(), String> {
+        config.save()
+    }
+    
+    fn config_changed(&self) {
+        events::emit_app_event(self, AppEvent::ConfigChanged);
+        crate::refresh_tray(self);
+    }
+}
+
+// Save an updated page reference and let the UI and tray know
+fn store_page_ref_in(config: &Mutex<AppConfig>, effects: &impl PageRefEffects, page_ref: &PageRef) {
+    {
+        let mut config = config.lock().unwrap();
+        if !same_page_id(&config.selected_page_id, &page_ref.id) {
+            // The selection changed while we were looking
+            return;
         }
-        
-        if config.selected_page_id.is_empty() {
-            return Err("No Notion page selected".into());
+        config.set_selected_page_ref(page_ref);
+        if let Err(e) = effects.save(&mut config) {
+            eprintln!("Failed to save page reference: {}", e);
         }
-        
-        (config.notion_api_token.clone(), config.selected_page_id.clone())
-    }; // MutexGuard is dropped here
+    }
     
-    // Now we can safely use .await
-    let client = NotionApiClient::new(api_token)?;
-    client.append_note_to_page(&page_id, &note_text).await
-}
\ No newline at end of file
+    effects.config_changed();
+}
+
+fn store_page_ref(app: &AppHandle, page_ref: &PageRef) {
+    store_page_ref_in(&app.state::<AppState>().config, app, page_ref);
+}
+
+// Reconcile the selected target against search results for targets of `kind`.
+// Returns true when the config was updated.
+pub fn refresh_selected_page_ref(app: &AppHandle, kind: TargetKind, pages: &[NotionPage]) -> bool {
+    refresh_selected_page_ref_in(&app.state::<AppState>().config, app, kind, pages)
+}
+
+fn refresh_selected_page_ref_in(
+    config: &Mutex<AppConfig>,
+    effects: &impl PageRefEffects,
+    kind: TargetKind,
+    pages: &[NotionPage],
+) -> bool {
+    let mut page_ref = {
+        let config = config.lock().unwrap();
+        if config.selected_page_id.is_empty() || config.selected_target_kind != kind {
+            return false;
+        }
+        config.selected_page_ref()
+    };
+    
+    if reconcile_page_ref(&mut page_ref, pages) == Reconciled::Updated {
+        store_page_ref_in(config, effects, &page_ref);
+        return true;
+    }
+    false
+}
+
+// Periodic enrichment pass: refresh titles and icons of stored page references,
+// and flag (never remove) references whose pages have disappeared
+pub async fn enrich_page_refs(app: &AppHandle) -> Result<(), String> {
+    enrich_page_refs_in(&app.state::<AppState>().config, app).await
+}
+
+async fn enrich_page_refs_in(config: &Mutex<AppConfig>, effects: &impl PageRefEffects) -> Result<(), String> {
+    let (api_token, mut page_ref, kind, max_results) = {
+        let config = config.lock().unwrap();
+        if config.secrets.main_token().is_empty() || config.selected_page_id.is_empty() {
+            return Ok(());
+        }
+        (
+            config.secrets.main_token().to_string(),
+            config.selected_page_ref(),
+            config.selected_target_kind,
+            config.page_search_max_results,
+        )
+    };
+    
+    let client = NotionApiClient::new(api_token)?.with_priority(Priority::Background);
+    let pages = match kind {
+        TargetKind::Page => client.search_pages(max_results).await?,
+        TargetKind::Database => client.search_databases().await?,
+    };
+    
+    match reconcile_page_ref(&mut page_ref, &pages) {
+        Reconciled::Unchanged => {}
+        Reconciled::Updated => store_page_ref_in(config, effects, &page_ref),
+        Reconciled::Missing => {
+            // Search results are incomplete, so confirm with the page itself
+            let fetched = match kind {
+                TargetKind::Page => client.fetch_page(&page_ref.id).await?,
+                TargetKind::Database => client.fetch_database(&page_ref.id).await?,
+            };
+            let gone = match fetched {
+                Some(page) => page["archived"].as_bool() == Some(true) || page["in_trash"].as_bool() == Some(true),
+                None => true,
+            };
+            if gone != page_ref.stale {
+                page_ref.stale = gone;
+                store_page_ref_in(config, effects, &page_ref);
+            }
+        }
+    }
+    
+    Ok(())
+}
+
+// How often the background enrichment pass runs
+const ENRICH_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+// Start the background page enrichment task
+pub fn start_page_refresh(app: &AppHandle) {
+    let app_handle = app.clone();
+    let registry = app.state::<AppState>().tasks.clone();
+    
+    registry.spawn("page-refresh", move |mut token| async move {
+        loop {
+            if quiet_hours::is_quiet() {
+                crate::refresh_tray(&app_handle);
+                if !quiet_hours::wait_until_loud(&mut token).await {
+                    break;
+                }
+                crate::refresh_tray(&app_handle);
+            }
+            if let Err(e) = enrich_page_refs(&app_handle).await {
+                eprintln!("Background page refresh failed: {}", e);
+            }
+            crate::warn_clock_skew(&app_handle);
+            
+            tokio::select! {
+                _ = token.cancelled() => break,
+                _ = tokio::time::sleep(ENRICH_INTERVAL) => {}
+            }
+        }
+    });
+}
+
+// Search Notion pages with cache usage. Without a query every page is listed;
+// `limit` caps how many come back. `truncated` says the configured cap cut
+// the list short, so the user should narrow it with a query. Archived and
+// trashed pages are left out unless `include_archived` is set.
+#[tauri::command]
+pub async fn search_notion_pages(
+    query: Option<String>,
+    limit: Option<u32>,
+    include_archived: Option<bool>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<PageSearch, String> {
+    // Extract what we need from the Mutex and immediately drop the lock
+    let (api_token, max_results) = {
+        let config = state.config.lock().unwrap();
+        let token = config.secrets.main_token().to_string();
+        if token.is_empty() {
+            return Err("API token is not set".into());
+        }
+        (token, config.page_search_max_results)
+    }; // MutexGuard is dropped here
+    
+    // Now we can safely use .await
+    let client = NotionApiClient::new(api_token)?;
+    let search = client
+        .search_pages_matching(PageSearchOptions {
+            query: query.unwrap_or_default(),
+            limit,
+            max_results,
+            include_archived: include_archived.unwrap_or(false),
+        })
+        .await?;
+    refresh_selected_page_ref(&app, TargetKind::Page, &search.pages);
+    Ok(search)
+}
+
+// Search the databases shared with the integration, for choosing a database target
+#[tauri::command]
+pub async fn search_notion_databases(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Vec<NotionPage>, String> {
+    let api_token = {
+        let config = state.config.lock().unwrap();
+        let token = config.secrets.main_token().to_string();
+        if token.is_empty() {
+            return Err("API token is not set".into());
+        }
+        token
+    };
+    
+    let client = NotionApiClient::new(api_token)?;
+    let databases = client.search_databases().await?;
+    refresh_selected_page_ref(&app, TargetKind::Database, &databases);
+    Ok(databases)
+}
+
+// Get whether the selected target is a page or a database
+#[tauri::command]
+pub fn get_selected_target_kind(state: State<'_, AppState>) -> Result<TargetKind, String> {
+    let config = state.config.lock().unwrap();
+    Ok(config.selected_target_kind)
+}
+
+// Get the selected page ID
+#[tauri::command]
+pub fn get_selected_page_id(state: State<'_, AppState>) -> Result<String, String> {
+    let config = state.config.lock().unwrap();
+    Ok(config.selected_page_id.clone())
+}
+
+// Probe whether the integration can append to a page
+#[tauri::command]
+pub async fn probe_page_access(
+    page_id: String,
+    state: State<'_, AppState>,
+) -> Result<PageAccess, String> {
+    let api_token = {
+        let config = state.config.lock().unwrap();
+        if config.secrets.main_token().is_empty() {
+            return Err("API token is not set".into());
+        }
+        config.secrets.main_token().to_string()
+    };
+    
+    let client = NotionApiClient::new(api_token)?;
+    client.probe_page_access(&page_id).await
+}
+
+// A page's current details as Notion reports them
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PageInfo {
+    pub id: String,
+    pub title: String,
+    pub icon: Option<String>,
+    pub url: String,
+    // False when Notion answers 404 or the page is in the trash
+    pub found: bool,
+}
+
+// Fetch a page's current title, icon and url. For the selected target the
+// stored title and icon are brought up to date, or, when the target is gone,
+// it is flagged stale and SelectedPageMissing is emitted so the UI can ask for
+// a new one.
+#[tauri::command]
+pub async fn get_page_info(
+    page_id: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<PageInfo, String> {
+    let (api_token, selected) = {
+        let config = state.config.lock().unwrap();
+        if config.secrets.main_token().is_empty() {
+            return Err("API token is not set".into());
+        }
+        let selected = (!config.selected_page_id.is_empty() && same_page_id(&config.selected_page_id, &page_id))
+            .then(|| (config.selected_page_ref(), config.selected_target_kind));
+        (config.secrets.main_token().to_string(), selected)
+    };
+    
+    let client = NotionApiClient::new(api_token)?;
+    let kind = selected.as_ref().map_or(TargetKind::Page, |(_, kind)| *kind);
+    let fetched = match kind {
+        TargetKind::Page => client.fetch_page(&page_id).await?,
+        TargetKind::Database => client.fetch_database(&page_id).await?,
+    };
+    let info = match fetched.filter(|object| !is_archived(object)) {
+        Some(object) => PageInfo {
+            id: page_id.clone(),
+            title: match kind {
+                TargetKind::Page => {
+                    crate::page_display::title_from_page(&object).unwrap_or_else(|| "Untitled".to_string())
+                }
+                TargetKind::Database => database_rows::database_title(&object),
+            },
+            icon: object["icon"]["emoji"].as_str().map(|s| s.to_string()),
+            url: object["url"].as_str().unwrap_or("").to_string(),
+            found: true,
+        },
+        None => PageInfo {
+            id: page_id.clone(),
+            title: String::new(),
+            icon: None,
+            url: String::new(),
+            found: false,
+        },
+    };
+    
+    if let Some((mut page_ref, _)) = selected {
+        if info.found {
+            let current = NotionPage {
+                id: info.id.clone(),
+                title: info.title.clone(),
+                icon: info.icon.clone(),
+                url: info.url.clone(),
+                parent_title: None,
+                last_edited_time: None,
+                archived: false,
+            };
+            if reconcile_page_ref(&mut page_ref, &[current]) == Reconciled::Updated {
+                store_page_ref(&app, &page_ref);
+            }
+        } else {
+            if !page_ref.stale {
+                page_ref.stale = true;
+                store_page_ref(&app, &page_ref);
+            }
+            events::emit_app_event(&app, AppEvent::SelectedPageMissing(page_id));
+        }
+    }
+    Ok(info)
+}
+
+// Check whether the integration can read a page and, as far as Notion lets us
+// tell, append to it
+#[tauri::command]
+pub async fn verify_page_access(
+    page_id: String,
+    state: State<'_, AppState>,
+) -> Result<PageAccessReport, String> {
+    let api_token = {
+        let config = state.config.lock().unwrap();
+        if config.secrets.main_token().is_empty() {
+            return Err("API token is not set".into());
+        }
+        config.secrets.main_token().to_string()
+    };
+    
+    let client = NotionApiClient::new(api_token)?;
+    client.verify_page_access(&page_id).await
+}
+
+// Set the selected page ID, refusing read-only pages unless explicitly overridden.
+// With `verify`, the saved page is then checked and the report returned as a
+// warning; a page that fails the check is still saved.
+#[tauri::command]
+pub async fn set_selected_page_id(
+    page_id: String,
+    page_title: String,
+    allow_read_only: Option<bool>,
+    verify: Option<bool>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Option<PageAccessReport>, String> {
+    let api_token = {
+        let config = state.config.lock().unwrap();
+        config.secrets.main_token().to_string()
+    };
+    
+    if !api_token.is_empty() && !allow_read_only.unwrap_or(false) {
+        let client = NotionApiClient::new(api_token.clone())?;
+        // A failed probe is not a verdict; only refuse on a read-only answer
+        if let Ok(PageAccess::ReadOnly) = client.probe_page_access(&page_id).await {
+            return Err(format!(
+                "\"{}\" appears to be read-only for your integration. Save again with the override to use it anyway.",
+                page_title
+            ));
+        }
+    }
+    
+    {
+        let mut config = state.config.lock().unwrap();
+        config.set_selected_page_ref(&PageRef {
+            id: page_id.clone(),
+            title: page_title.clone(),
+            icon: None,
+            stale: false,
+        });
+        config.selected_target_kind = TargetKind::Page;
+        config.save()?;
+    }
+    crate::refresh_tray(&app);
+    
+    if api_token.is_empty() || !verify.unwrap_or(false) {
+        return Ok(None);
+    }
+    // The page is saved either way; a check that can't run just gives no report
+    let report = match NotionApiClient::new(api_token)?.verify_page_access(&page_id).await {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Couldn't verify access to \"{}\": {}", page_title, e);
+            return Ok(None);
+        }
+    };
+    if !report.can_append {
+        eprintln!("Warning: \"{}\" may not accept notes ({:?})", page_title, report.reason);
+    }
+    Ok(Some(report))
+}
+
+// Select a database as the target; each note then becomes a new row in it.
+// Refused when Notion says the database doesn't exist or isn't shared.
+#[tauri::command]
+pub async fn set_selected_database(
+    database_id: String,
+    database_title: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let api_token = {
+        let config = state.config.lock().unwrap();
+        config.secrets.main_token().to_string()
+    };
+    
+    if !api_token.is_empty() {
+        let client = NotionApiClient::new(api_token)?;
+        // Reading the schema now also checks that the database has a usable title property
+        if let Err(e) = database_rows::refresh_schema(&client, &database_id).await {
+            // Being offline is not a verdict
+            if !e.starts_with("API request failed") {
+                return Err(format!("\"{}\" can't be used: {}", database_title, e));
+            }
+        }
+    }
+    
+    {
+        let mut config = state.config.lock().unwrap();
+        config.set_selected_page_ref(&PageRef {
+            id: database_id,
+            title: database_title,
+            icon: None,
+            stale: false,
+        });
+        config.selected_target_kind = TargetKind::Database;
+        config.save()?;
+    }
+    
+    crate::refresh_tray(&app);
+    Ok(())
+}
+
+// Append a note to the selected Notion page, returning the blocks it became
+// and the capture id its receipt is stored under
+#[tauri::command]
+pub async fn append_note(
+    note_text: String,
+    profile: Option<String>,
+    scratch: Option<bool>,
+    format: Option<NoteFormat>,
+    block_type: Option<NoteBlockType>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<AppendResult, String> {
+    let _capturing = crate::health::capture_started();
+    let style = NoteStyle {
+        format: format.unwrap_or_default(),
+        block_type,
+        ..Default::default()
+    };
+    let result = capture_note(note_text, profile, scratch, style, NoteOrigin::Typed, &app, &state).await;
+    accessibility::announce_capture_result(&app, &result);
+    result
+}
+
+// Send the clipboard's text as a note without showing the note window. None
+// when it wasn't sent now: too large (the note window opens to confirm it, or
+// the user is told it was refused) or held until setup is finished.
+#[tauri::command]
+pub async fn append_clipboard(app: AppHandle, state: State<'_, AppState>) -> Result<Option<AppendResult>, String> {
+    let _capturing = crate::health::capture_started();
+    let result = capture_clipboard(&app, &state).await;
+    let outcome = match &result {
+        Ok(Some(_)) => Some(events::ClipboardCaptured { sent: true, error: None }),
+        Ok(None) => None,
+        Err(e) => Some(events::ClipboardCaptured { sent: false, error: Some(e.clone()) }),
+    };
+    if let Some(outcome) = outcome {
+        events::emit_app_event(&app, events::AppEvent::ClipboardCaptured(outcome));
+    }
+    result
+}
+
+async fn capture_clipboard(app: &AppHandle, state: &State<'_, AppState>) -> Result<Option<AppendResult>, String> {
+    // Images and files aren't text; the clipboard manager reports them as an error or nothing
+    let text = app
+        .clipboard_manager()
+        .read_text()
+        .ok()
+        .flatten()
+        .filter(|text| !text.trim().is_empty())
+        .ok_or_else(|| {
+            AppError::ValidationError("The clipboard has no text to send (images and files can't be sent)".into())
+                .to_string()
+        })?;
+    let Some(text) = crate::handle_clipboard_capture(app, &text) else {
+        return Ok(None);
+    };
+    let result = capture_note(text, None, None, NoteStyle::default(), NoteOrigin::Clipboard, app, state).await;
+    accessibility::announce_capture_result(app, &result);
+    result.map(Some)
+}
+
+// Sends each append retry for this capture to the note window as `note-progress`
+fn retry_progress(app: &AppHandle, capture_id: &str) -> RetryObserver {
+    let app = app.clone();
+    let id = capture_id.to_string();
+    Arc::new(move |retry| {
+        events::emit_app_event(&app, AppEvent::NoteProgress(queue::NoteProgress::Retrying { id: id.clone(), retry }));
+    })
+}
+
+// Park a capture in the queue until a new token verifies; returns the message for the user
+fn queue_until_reauth(app: &AppHandle, note_text: &str, profile: Option<String>, style: NoteStyle) -> String {
+    match queue::enqueue(app, note_text, profile, style) {
+        Ok(_) => "Notion no longer accepts your API token. The note was queued and will be sent once you update the token in Settings.".into(),
+        Err(e) => format!("Notion no longer accepts your API token, and the note couldn't be queued: {}", e),
+    }
+}
+
+async fn capture_note(
+    note_text: String,
+    profile: Option<String>,
+    scratch: Option<bool>,
+    style: NoteStyle,
+    origin: NoteOrigin,
+    app: &AppHandle,
+    state: &State<'_, AppState>,
+) -> Result<AppendResult, String> {
+    let started_at = chrono::Utc::now();
+    let started_epoch = suspend::epoch();
+    // The note window passes the profile its hotkey pinned; a route prefix in the note outranks it
+    let context = DestinationContext {
+        profile: None,
+        pinned_profile: profile,
+        scratch: scratch.unwrap_or(false),
+    };
+    let (destination, request) = {
+        let config = state.config.lock().unwrap();
+        let destination = resolve_destination_plan(&config, &note_text, &context)?;
+        // Scratch captures are buffered locally and sent later as one block group
+        let (captured_at, note_text) = if destination.scratch {
+            (None, destination.note_text.as_str())
+        } else {
+            split_leading_timestamp(config.leading_timestamps, &destination.note_text)
+        };
+        let request = CaptureRequest {
+            note_text: prepare_note_text(&config, note_text),
+            profile: destination.profile.clone(),
+            captured_at,
+        };
+        (destination, request)
+    };
+    if destination.scratch {
+        let item = scratch::add_item(&request.note_text)?;
+        crate::refresh_tray(app);
+        return Ok(AppendResult::queued(item.id));
+    }
+    
+    // Extract what we need and drop the lock before async operations
+    let (mut target, layout, split_paragraphs, style, timestamp_options, use_day_headings) = {
+        let config = state.config.lock().unwrap();
+        (
+            resolve_capture_target(&config, request.profile.as_deref())?,
+            blocks::choose_layout(config.text_direction, config.rtl_timestamp_layout, &request.note_text),
+            config.split_paragraphs,
+            NoteStyle {
+                block_type: Some(style.block_type.unwrap_or(config.note_block_type)),
+                toggle_preview: config.toggle_preview,
+                bold: config.note_bold,
+                color: blocks::note_color(&config.note_color),
+                tag_color: config.highlight_hashtags.then(|| blocks::note_color(&config.hashtag_color)),
+                ..style
+            },
+            TimestampOptions::from_config(&config),
+            config.day_headings,
+        )
+    }; // MutexGuard is dropped here
+    if destination.rule == DestinationRule::Sandbox {
+        target.page_id = destination.page_id.clone();
+        target.page_title = destination.page_title.clone();
+        target.stale = false;
+        target.daily = None;
+        target.kind = TargetKind::Page;
+    }
+    
+    // Offline notes never get here, the note window queues them itself
+    if CaptureConditions::current(app, &target.api_token, true).route() == CaptureRoute::Queue {
+        return Err(queue_until_reauth(app, &destination.note_text, request.profile, style));
+    }
+    
+    if target.stale {
+        eprintln!(
+            "Warning: appending to \"{}\", which looks archived or deleted in Notion",
+            target.page_title
+        );
+    }
+    
+    // Now we can safely use .await
+    let capture_id = receipts::new_capture_id();
+    let timestamp = request.captured_at.unwrap_or_else(clock::now);
+    // Decided once from the capture time, so a retry after rotation can't drop or change it
+    let focus_label = focus::active_label(app, timestamp);
+    let client = NotionApiClient::new(target.api_token)?.with_retry_observer(retry_progress(app, &capture_id));
+    if let Some(daily) = &target.daily {
+        target.page_id = daily_pages::resolve(&client, daily).await?;
+    }
+    let formatted_timestamp = timestamps::format_timestamp(&timestamp, &timestamp_options);
+    let children = blocks::note_children(
+        &request.note_text,
+        &formatted_timestamp,
+        layout,
+        style,
+        split_paragraphs,
+        focus_label.as_deref(),
+    );
+    let day_heading = use_day_headings
+        .then(|| day_headings::heading_text(timestamps::day_of(&timestamp, &timestamp_options)));
+    let mut result = match target.kind {
+        TargetKind::Database => database_rows::create_row(&client, &target.page_id, &request.note_text, &timestamp).await,
+        TargetKind::Page => {
+            let anchor = crate::anchor::configured(app, &target.page_id);
+            crate::anchor::append(app, &client, &target.page_id, &target.page_title, day_heading.as_deref(), &children, anchor.as_deref())
+                .await
+        }
+    };
+    
+    // A full default page is continued on a new page, then the append is retried exactly once
+    if destination.rotates_when_full {
+        let (client, day_heading, children) = (&client, day_heading.as_deref(), &children);
+        let (retried, rotated) = retry_on_new_page(
+            result,
+            || rotate_selected_page(app, client, &target.page_id, &target.page_title),
+            |page_ref| async move {
+                let anchor = crate::anchor::configured(app, &page_ref.id);
+                crate::anchor::append(app, client, &page_ref.id, &page_ref.title, day_heading, children, anchor.as_deref())
+                    .await
+            },
+        )
+        .await;
+        result = retried;
+        if let Some(page_ref) = rotated {
+            target.page_id = page_ref.id;
+            target.page_title = page_ref.title;
+        }
+    }
+    if let Err(AppendFailure::PageFull(error)) = &result {
+        events::emit_app_event(app, AppEvent::PageFull(events::PageFull {
+            error: AppError::PageFull(error.clone()).into(),
+            page_id: target.page_id.clone(),
+            page_title: target.page_title.clone(),
+        }));
+    }
+    crate::warn_clock_skew(app);
+    let (response, partial) = match result {
+        Ok(response) => (response, None),
+        Err(AppendFailure::Partial(partial)) => (partial.delivered.clone(), Some(partial)),
+        Err(_) if auth::blocks_token(app, &client.api_token) => {
+            return Err(queue_until_reauth(app, &destination.note_text, request.profile, style));
+        }
+        Err(failure) if destination.rule == DestinationRule::Sandbox => {
+            forget_deleted_sandbox(app, &client, &target.page_id).await;
+            return Err(failure.into());
+        }
+        // The request died with the machine going to sleep: not a failure to
+        // report, the queue sends it once it knows the first attempt didn't land
+        Err(failure) if suspend::interrupted(started_epoch, std::time::Instant::now()) => {
+            return match queue::enqueue_interrupted(app, &destination.note_text, request.profile, timestamp, style) {
+                Ok(item) => Ok(AppendResult::queued(item.id)),
+                Err(e) => {
+                    eprintln!("Couldn't queue a capture interrupted by sleep: {}", e);
+                    Err(failure.into())
+                }
+            };
+        }
+        Err(failure) => return Err(failure.into()),
+    };
+    
+    // The chunks that didn't land are queued to follow the ones that did
+    let remainder = partial.as_ref().and_then(|partial| {
+        let landed = partial.delivered.block_ids.len().min(children.len());
+        let queued = queue::enqueue_remainder(
+            request.profile.clone(),
+            target.page_id.clone(),
+            target.page_title.clone(),
+            children[landed..].to_vec(),
+            day_heading.clone(),
+            partial.delivered.block_ids.last().cloned(),
+        );
+        match queued {
+            Ok(item) => Some(item.id),
+            Err(e) => {
+                eprintln!("Couldn't queue the rest of a partly sent note: {}", e);
+                None
+            }
+        }
+    });
+    
+    // Receipts are best-effort bookkeeping and must never fail a delivered note.
+    // A partial delivery still gets one, listing which chunks landed and where the rest went.
+    let mut receipt = receipts::DeliveryReceipt::new(capture_id.clone(), target.page_id, response, partial.as_ref());
+    if let Some(partial) = &mut receipt.partial {
+        partial.remainder_id = remainder.clone();
+    }
+    receipt.captured_at = request.captured_at.map(|time| time.to_rfc3339());
+    receipt.focus_label = focus_label;
+    receipt.note_text = Some(request.note_text.clone());
+    receipt.origin = Some(origin);
+    receipt.profile = request.profile.clone();
+    if let Err(e) = receipts::record_receipt(&receipt) {
+        eprintln!("Failed to record delivery receipt: {}", e);
+    }
+    
+    if let Some(partial) = partial {
+        return match remainder {
+            Some(remainder) => {
+                queue::publish_estimates(app);
+                Ok(AppendResult::queued(remainder))
+            }
+            None => Err(AppendFailure::Partial(partial).into()),
+        };
+    }
+    reliability::record_delivery(started_at);
+    
+    let result = AppendResult {
+        capture_id,
+        block_ids: receipt.block_ids.clone(),
+        page_id: receipt.page_id.clone(),
+        appended_at: chrono::Utc::now().timestamp_millis(),
+        queued: false,
+    };
+    crate::undo::remember(app, &result, receipt.profile.as_deref(), &formatted_timestamp, target.kind == TargetKind::Database);
+    webhook::mirror_delivery(app, webhook::MirrorPayload {
+        text: request.note_text,
+        captured_at: timestamp.to_rfc3339(),
+        page_id: receipt.page_id,
+        page_title: target.page_title,
+        source: receipt.source,
+        block_ids: receipt.block_ids,
+    });
+    Ok(result)
+}
+// Append user-supplied block JSON as-is, for blocks the formatter doesn't
+// build. Only available when `allow_raw_blocks` is set. Goes through the same
+// rate limiting, retries and receipts as notes, and is queued while offline.
+#[tauri::command]
+pub async fn append_raw_blocks(
+    blocks_json: String,
+    page_id: Option<String>,
+    online: Option<bool>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<String, String> {
+    let target = {
+        let config = state.config.lock().unwrap();
+        if !config.allow_raw_blocks {
+            return Err(AppError::ConfigError("Raw blocks are disabled in settings".into()).to_string());
+        }
+        resolve_capture_target(&config, None)?
+    };
+    let children = blocks::validate_raw_blocks(&blocks_json).map_err(|e| e.to_string())?;
+    
+    let (mut page_id, page_title, daily) = match page_id {
+        Some(page_id) if !same_page_id(&page_id, &target.page_id) => {
+            let title = crate::page_display::display(&app, &page_id).title;
+            (page_id, title, None)
+        }
+        _ if target.kind == TargetKind::Database => {
+            return Err("Raw blocks can't be added to a database; choose a page for them".into());
+        }
+        _ => (target.page_id, target.page_title, target.daily),
+    };
+    
+    if online == Some(false) {
+        return queue::enqueue_blocks(page_id, page_title, children, DeliverySource::Raw, daily).map(|item| item.id);
+    }
+    
+    let capture_id = receipts::new_capture_id();
+    let client = NotionApiClient::new(target.api_token)?;
+    if let Some(daily) = &daily {
+        page_id = daily_pages::resolve(&client, daily).await?;
+    }
+    let response = client.append_children(&page_id, &page_title, &children).await?;
+    
+    let mut receipt = receipts::DeliveryReceipt::new(capture_id.clone(), page_id, response, None);
+    receipt.source = DeliverySource::Raw;
+    if let Err(e) = receipts::record_receipt(&receipt) {
+        eprintln!("Failed to record delivery receipt: {}", e);
+    }
+    Ok(capture_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_notion::{FakeWorkspace, MockNotion, MockReply};
+    use serde_json::{json, Value};
+    use std::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn access_probe_matrix() {
+        let cases = [
+            (200, Some(200), PageAccess::Writable),
+            (200, Some(403), PageAccess::ReadOnly),
+            (200, Some(404), PageAccess::NotFound),
+            (200, Some(500), PageAccess::Unknown),
+            (200, None, PageAccess::Unknown),
+            (403, None, PageAccess::ReadOnly),
+            (404, None, PageAccess::NotFound),
+            (500, None, PageAccess::Unknown),
+            (429, None, PageAccess::Unknown),
+        ];
+        for (page_status, children_status, expected) in cases {
+            assert_eq!(interpret_access_probe(page_status, children_status), expected, "{} / {:?}", page_status, children_status);
+        }
+    }
+
+    // Notion answering the page retrieval and the children listing with the given statuses
+    async fn probe_against(page_status: u16, children_status: u16) -> (MockNotion, PageAccess) {
+        let mock = MockNotion::start(move |request| {
+            let status = if request.path.ends_with("/children") { children_status } else { page_status };
+            match status {
+                200 if request.path.ends_with("/children") => MockReply::json(200, json!({ "object": "list", "results": [], "has_more": false })),
+                200 => MockReply::json(200, json!({ "object": "page", "id": "page-1", "properties": {} })),
+                status => MockReply::error(status, "error", "refused"),
+            }
+        })
+        .await;
+        let client = NotionApiClient::new(mock.token.clone()).unwrap();
+        let access = client.probe_page_access("page-1").await.unwrap();
+        (mock, access)
+    }
+
+    #[tokio::test]
+    async fn probe_reads_the_page_then_its_children() {
+        let cases = [
+            (200, 200, PageAccess::Writable, 2),
+            (200, 403, PageAccess::ReadOnly, 2),
+            (403, 200, PageAccess::ReadOnly, 1),
+            (404, 200, PageAccess::NotFound, 1),
+            (500, 200, PageAccess::Unknown, 1),
+        ];
+        for (page_status, children_status, expected, requests) in cases {
+            let (mock, access) = probe_against(page_status, children_status).await;
+            assert_eq!(access, expected, "{} / {}", page_status, children_status);
+            assert_eq!(mock.requests().len(), requests);
+        }
+    }
+
+    #[tokio::test]
+    async fn probe_verdict_is_cached() {
+        let (mock, _) = probe_against(200, 403).await;
+        let client = NotionApiClient::new(mock.token.clone()).unwrap();
+        assert_eq!(client.probe_page_access("page-1").await.unwrap(), PageAccess::ReadOnly);
+        assert_eq!(mock.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn refused_append_names_the_page_and_marks_it_read_only() {
+        let mock = MockNotion::start(|_| MockReply::error(403, "restricted_resource", "Insufficient permissions")).await;
+        let client = NotionApiClient::new(mock.token.clone()).unwrap();
+        let children = vec![json!({ "type": "paragraph", "paragraph": { "rich_text": [] } })];
+        let error = String::from(client.append_children("page-1", "Reading List", &children).await.unwrap_err());
+        assert!(error.contains("\"Reading List\""), "{}", error);
+        assert!(error.contains("403"), "{}", error);
+        assert_eq!(client.probe_page_access("page-1").await.unwrap(), PageAccess::ReadOnly);
+    }
+
+    fn note(text: &str) -> Value {
+        blocks::paragraph(blocks::text_runs(text, false))
+    }
+
+    fn texts(blocks: &[Value]) -> Vec<String> {
+        blocks.iter().map(blocks::block_text).collect()
+    }
+
+    // A workspace whose next `slow_appends` appends are applied, then answered too late
+    async fn timing_out_workspace(workspace: Arc<Mutex<FakeWorkspace>>, slow_appends: Arc<AtomicUsize>) -> MockNotion {
+        MockNotion::start(move |request| {
+            let reply = workspace.lock().unwrap().answer(request);
+            let slow = request.method == "PATCH"
+                && slow_appends.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| left.checked_sub(1)).is_ok();
+            if slow {
+                return reply.delayed(Duration::from_secs(2));
+            }
+            reply
+        })
+        .await
+        .with_timeout(Duration::from_millis(300))
+    }
+
+    #[tokio::test]
+    async fn timed_out_append_that_landed_is_not_sent_again() {
+        let workspace = FakeWorkspace::shared();
+        workspace.lock().unwrap().add_page("page-1", "Inbox");
+        let mock = timing_out_workspace(workspace.clone(), Arc::new(AtomicUsize::new(1))).await;
+        let client = NotionApiClient::new(mock.token.clone()).unwrap();
+
+        let response = client.append_children("page-1", "Inbox", &[note("[09:00] hotel wifi")]).await.unwrap();
+
+        let children = workspace.lock().unwrap().children("page-1");
+        assert_eq!(texts(&children), vec!["[09:00] hotel wifi"]);
+        assert_eq!(response.block_ids, vec![children[0]["id"].as_str().unwrap()]);
+        assert_eq!(mock.count("PATCH", "/v1/blocks/"), 1);
+        assert_eq!(mock.count("GET", "/v1/blocks/"), 1);
+    }
+
+    #[tokio::test]
+    async fn duplicate_check_reads_only_the_end_of_a_long_page() {
+        let workspace = FakeWorkspace::shared();
+        {
+            let mut workspace = workspace.lock().unwrap();
+            workspace.add_page("page-1", "Inbox");
+            let filler: Vec<String> = (0..250).map(|index| format!("old note {}", index)).collect();
+            workspace.add_paragraphs("page-1", &filler.iter().map(String::as_str).collect::<Vec<_>>());
+        }
+        let slow_appends = Arc::new(AtomicUsize::new(0));
+        let mock = timing_out_workspace(workspace.clone(), slow_appends.clone()).await;
+        let client = NotionApiClient::new(mock.token.clone()).unwrap();
+        let first = client.append_children("page-1", "Inbox", &[note("[09:00] first")]).await.unwrap();
+        let requests_before = mock.requests().len();
+
+        // The next append times out after landing
+        slow_appends.store(1, Ordering::SeqCst);
+        client.append_children("page-1", "Inbox", &[note("[09:05] second")]).await.unwrap();
+
+        let children = texts(&workspace.lock().unwrap().children("page-1"));
+        assert_eq!(children.len(), 252);
+        assert_eq!(children[250..], ["[09:00] first", "[09:05] second"]);
+        let listings: Vec<_> = mock.requests()[requests_before..].iter().filter(|request| request.method == "GET").cloned().collect();
+        assert_eq!(listings.len(), 1);
+        assert_eq!(listings[0].query.get("start_cursor"), Some(&first.block_ids[0]));
+    }
+
+    #[tokio::test]
+    async fn duplicate_check_after_an_anchor_looks_below_it() {
+        let workspace = FakeWorkspace::shared();
+        let anchor = {
+            let mut workspace = workspace.lock().unwrap();
+            workspace.add_page("page-1", "Inbox");
+            workspace.add_paragraphs("page-1", &["Inbox"]);
+            let filler: Vec<String> = (0..150).map(|index| format!("old note {}", index)).collect();
+            workspace.add_paragraphs("page-1", &filler.iter().map(String::as_str).collect::<Vec<_>>());
+            workspace.children("page-1")[0]["id"].as_str().unwrap().to_string()
+        };
+        let mock = timing_out_workspace(workspace.clone(), Arc::new(AtomicUsize::new(1))).await;
+        let client = NotionApiClient::new(mock.token.clone()).unwrap();
+
+        client.append_children_after("page-1", "Inbox", &[note("[09:00] on top")], Some(&anchor)).await.unwrap();
+
+        let children = texts(&workspace.lock().unwrap().children("page-1"));
+        assert_eq!(children.len(), 152);
+        assert_eq!(children[..2], ["Inbox", "[09:00] on top"]);
+        assert_eq!(mock.count("PATCH", "/v1/blocks/"), 1);
+        let listings: Vec<_> = mock.requests().into_iter().filter(|request| request.method == "GET").collect();
+        assert_eq!(listings.len(), 1);
+        assert_eq!(listings[0].query.get("start_cursor"), Some(&anchor));
+    }
+
+    #[tokio::test]
+    async fn unambiguous_failures_skip_the_duplicate_check() {
+        let mock = MockNotion::start(|_| MockReply::error(400, "validation_error", "body failed validation")).await;
+        let client = NotionApiClient::new(mock.token.clone()).unwrap();
+        assert!(client.append_children("page-1", "Inbox", &[note("[09:00] x")]).await.is_err());
+        assert_eq!(mock.count("GET", "/"), 0);
+
+        // Refused connections never reached Notion
+        let token = crate::mock_notion::unreachable_token().await;
+        let client = NotionApiClient::new(token).unwrap();
+        let error = String::from(client.append_children("page-1", "Inbox", &[note("[09:00] x")]).await.unwrap_err());
+        assert!(error.starts_with("API request failed"), "{}", error);
+    }
+
+    fn numbered_notes(count: usize) -> Vec<Value> {
+        (0..count).map(|index| note(&format!("line {}", index))).collect()
+    }
+
+    #[tokio::test]
+    async fn long_notes_are_sent_in_chunks_of_at_most_a_hundred() {
+        let workspace = FakeWorkspace::shared();
+        workspace.lock().unwrap().add_page("page-1", "Inbox");
+        let notion = workspace.clone();
+        let mock = MockNotion::start(move |request| notion.lock().unwrap().answer(request)).await;
+        let client = NotionApiClient::new(mock.token.clone()).unwrap();
+
+        for count in [1, MAX_CHILDREN_PER_REQUEST, MAX_CHILDREN_PER_REQUEST + 1, 250] {
+            let requests_before = mock.count("PATCH", "/v1/blocks/");
+            let children = numbered_notes(count);
+            let response = client.append_children("page-1", "Inbox", &children).await.unwrap();
+            assert_eq!(response.block_ids.len(), count);
+            assert_eq!(mock.count("PATCH", "/v1/blocks/") - requests_before, count.div_ceil(MAX_CHILDREN_PER_REQUEST));
+            let landed = workspace.lock().unwrap().children("page-1");
+            assert_eq!(texts(&landed[landed.len() - count..]), texts(&children));
+        }
+        let sizes: Vec<usize> = mock
+            .requests()
+            .iter()
+            .filter_map(|request| Some(request.body.as_ref()?["children"].as_array()?.len()))
+            .collect();
+        assert_eq!(sizes, [1, 100, 100, 1, 100, 100, 50]);
+    }
+
+    #[tokio::test]
+    async fn a_failed_chunk_reports_what_landed_before_it() {
+        let children = numbered_notes(250);
+        for failing_chunk in 0..3 {
+            let workspace = FakeWorkspace::shared();
+            workspace.lock().unwrap().add_page("page-1", "Inbox");
+            let notion = workspace.clone();
+            let appends = AtomicUsize::new(0);
+            let mock = MockNotion::start(move |request| {
+                if request.method == "PATCH" && appends.fetch_add(1, Ordering::SeqCst) == failing_chunk {
+                    return MockReply::error(400, "validation_error", "body failed validation");
+                }
+                notion.lock().unwrap().answer(request)
+            })
+            .await;
+            let client = NotionApiClient::new(mock.token.clone()).unwrap();
+
+            let failure = client.append_children("page-1", "Inbox", &children).await.unwrap_err();
+            let landed = workspace.lock().unwrap().children("page-1");
+            assert_eq!(landed.len(), failing_chunk * MAX_CHILDREN_PER_REQUEST);
+            // Chunks after the failed one are never sent
+            assert_eq!(mock.count("PATCH", "/v1/blocks/"), failing_chunk + 1);
+            let partial = match failure {
+                AppendFailure::Failed(error) if failing_chunk == 0 => {
+                    assert!(error.contains("400"), "{}", error);
+                    continue;
+                }
+                AppendFailure::Partial(partial) => partial,
+                failure => panic!("chunk {}: {}", failing_chunk, String::from(failure)),
+            };
+            assert_eq!((partial.delivered_chunks, partial.total_chunks), (failing_chunk, 3));
+            let ids: Vec<&str> = landed.iter().map(|block| block["id"].as_str().unwrap()).collect();
+            assert_eq!(partial.delivered.block_ids, ids);
+
+            // The rest, sent after the last block that landed, completes the note in order
+            let rest = &children[partial.delivered.block_ids.len()..];
+            client
+                .append_children_after("page-1", "Inbox", rest, partial.delivered.block_ids.last().map(String::as_str))
+                .await
+                .unwrap();
+            assert_eq!(texts(&workspace.lock().unwrap().children("page-1")), texts(&children));
+        }
+    }
+
+    #[tokio::test]
+    async fn conflicts_are_retried_and_reported_until_the_note_lands() {
+        let workspace = FakeWorkspace::shared();
+        workspace.lock().unwrap().add_page("page-1", "Inbox");
+        let notion = workspace.clone();
+        let appends = AtomicUsize::new(0);
+        let mock = MockNotion::start(move |request| {
+            if request.method == "PATCH" && appends.fetch_add(1, Ordering::SeqCst) < 2 {
+                return MockReply::error(409, "conflict_error", "Conflict occurred while saving");
+            }
+            notion.lock().unwrap().answer(request)
+        })
+        .await;
+        let progress = Arc::new(Mutex::new(Vec::new()));
+        let reported = progress.clone();
+        let client = NotionApiClient::new(mock.token.clone())
+            .unwrap()
+            .with_retry_observer(Arc::new(move |retry| reported.lock().unwrap().push(retry)));
+
+        let response = client.append_children("page-1", "Inbox", &[note("[09:00] busy page")]).await.unwrap();
+
+        let children = workspace.lock().unwrap().children("page-1");
+        assert_eq!(texts(&children), vec!["[09:00] busy page"]);
+        assert_eq!(response.attempts, 3);
+        assert_eq!(mock.count("PATCH", "/v1/blocks/"), 3);
+        // A conflict never applied the append, so nothing is read back before resending
+        assert_eq!(mock.count("GET", "/v1/blocks/"), 0);
+        let progress = progress.lock().unwrap().clone();
+        let attempts: Vec<(u32, u32, RetryReason)> = progress.iter().map(|retry| (retry.attempt, retry.max_attempts, retry.reason)).collect();
+        assert_eq!(attempts, [(1, 4, RetryReason::Conflict), (2, 4, RetryReason::Conflict)]);
+        for retry in &progress {
+            let backoff = APPEND_RETRY.delay_after(retry.attempt, 0.0).as_millis() as u64;
+            assert!(retry.retry_in_ms >= backoff + CONFLICT_EXTRA_WAIT.as_millis() as u64, "{:?}", retry);
+        }
+
+        let event = serde_json::to_value(queue::NoteProgress::Retrying { id: "capture-1".into(), retry: progress[0].clone() }).unwrap();
+        assert_eq!(event["stage"], "retrying");
+        assert_eq!(event["id"], "capture-1");
+        assert_eq!(event["reason"], "conflict");
+        assert_eq!(event["attempt"], 1);
+    }
+
+    #[test]
+    fn user_agent_names_the_app_version_and_platform() {
+        let agent = user_agent();
+        let (product, platform) = agent.split_once(' ').unwrap();
+        assert_eq!(product, format!("notion-quick-notes/{}", env!("CARGO_PKG_VERSION")));
+        assert_eq!(platform, format!("({}; {})", std::env::consts::OS, std::env::consts::ARCH));
+        assert!(header::HeaderValue::from_str(&agent).is_ok());
+    }
+
+    #[test]
+    fn protected_headers_are_refused_in_any_case() {
+        for name in DENIED_EXTRA_HEADERS {
+            for name in [name.to_string(), name.to_uppercase(), format!(" {} ", name)] {
+                let error = parse_extra_header(&name, "x").unwrap_err();
+                assert!(error.contains("can't be overridden"), "{}: {}", name, error);
+            }
+        }
+        for name in ["Host", "Content-Length", "Content-Type", "User-Agent", "Authorization"] {
+            assert!(set_extra_headers(&BTreeMap::from([(name.to_string(), "x".to_string())])).is_err(), "{}", name);
+        }
+        assert!(parse_extra_header("bad header", "x").unwrap_err().contains("Invalid header name"));
+        assert!(parse_extra_header("X-Trace", "line\nbreak").unwrap_err().contains("Invalid value"));
+        let (name, value) = parse_extra_header("X-Proxy-Auth", "abc").unwrap();
+        assert_eq!((name.as_str(), value.to_str().unwrap()), ("x-proxy-auth", "abc"));
+    }
+
+    #[test]
+    fn session_id_is_stable_for_the_process() {
+        let id = client_session_id();
+        assert_eq!(id.len(), 16);
+        assert!(id.chars().all(|c| c.is_ascii_hexdigit()), "{}", id);
+        let again = std::thread::spawn(|| client_session_id().to_string()).join().unwrap();
+        assert_eq!(again, id);
+        assert!(std::ptr::eq(id, client_session_id()));
+    }
+
+    #[test]
+    fn page_full_is_told_apart_from_other_validation_errors() {
+        let error = |code: &str, message: &str| json!({ "object": "error", "code": code, "message": message });
+        let full = "body.children length should be ≤ `100`, instead was `120`. The block has exceeded the maximum children limit.";
+        assert!(is_page_full_error(400, &error("validation_error", full)));
+        assert!(is_page_full_error(400, &error("validation_error", "Block children exceed the limit")));
+        assert!(!is_page_full_error(400, &error("validation_error", "body.children[0].paragraph.rich_text should be defined")));
+        assert!(!is_page_full_error(400, &error("invalid_json", full)));
+        assert!(!is_page_full_error(429, &error("validation_error", full)));
+        assert!(!is_page_full_error(400, &Value::Null));
+    }
+
+    #[test]
+    fn continuation_titles_never_stack() {
+        let now = chrono::TimeZone::with_ymd_and_hms(&chrono::Local, 2026, 10, 16, 9, 0, 0).unwrap();
+        assert_eq!(rotation_title("Inbox", &now), "Inbox (cont. 2026-10)");
+        assert_eq!(rotation_title("Inbox (cont. 2026-09)", &now), "Inbox (cont. 2026-10)");
+        assert_eq!(rotation_title("Inbox (cont. 2026-08) (cont. 2026-09)", &now), "Inbox (cont. 2026-10)");
+        // Only the app's own suffix is removed
+        assert_eq!(rotation_title("Ideas (cont. later)", &now), "Ideas (cont. later) (cont. 2026-10)");
+        assert_eq!(rotation_title("(cont. 2026-09)", &now), "(cont. 2026-09) (cont. 2026-10)");
+    }
+
+    // A workspace whose "Inbox" page, nested under "parent", refuses every append as full
+    async fn full_page_workspace() -> (Arc<Mutex<FakeWorkspace>>, MockNotion, NotionApiClient) {
+        let workspace = FakeWorkspace::shared();
+        {
+            let mut workspace = workspace.lock().unwrap();
+            workspace.add_page("parent", "Projects");
+            workspace.add_child_page("parent", "inbox", "Inbox (cont. 2026-09)");
+        }
+        let notion = workspace.clone();
+        let mock = MockNotion::start(move |request| {
+            if request.method == "PATCH" && request.path.ends_with("/children") {
+                return MockReply::error(400, "validation_error", "The block has exceeded the maximum children limit");
+            }
+            notion.lock().unwrap().answer(request)
+        })
+        .await;
+        let client = NotionApiClient::new(mock.token.clone()).unwrap();
+        (workspace, mock, client)
+    }
+
+    #[tokio::test]
+    async fn a_full_page_is_reported_as_page_full() {
+        let (_workspace, _mock, client) = full_page_workspace().await;
+        let failure = client.append_children("inbox", "Inbox", &[note("[09:00] x")]).await.unwrap_err();
+        assert!(matches!(failure, AppendFailure::PageFull(_)), "{}", String::from(failure.clone()));
+        let response = crate::error::ErrorResponse::from(AppError::PageFull(String::from(failure)));
+        assert_eq!(response.code, "PAGE_FULL");
+        assert_eq!(response.recovery, crate::error::Recovery::FixSettings);
+    }
+
+    #[tokio::test]
+    async fn continuation_page_is_created_next_to_the_full_one() {
+        let (workspace, mock, client) = full_page_workspace().await;
+        let page_ref = create_continuation_page(&client, "inbox", "Inbox (cont. 2026-09)").await.unwrap();
+
+        let created: Vec<_> = mock.requests().into_iter().filter(|request| request.method == "POST").collect();
+        assert_eq!(created.len(), 1);
+        let body = created[0].body.clone().unwrap();
+        assert_eq!(body, rotation_page_body("parent", &page_ref.title));
+        assert_eq!(body["parent"], json!({ "page_id": "parent" }));
+        assert_eq!(page_ref.title, rotation_title("Inbox", &clock::now()));
+        assert!(!page_ref.title.contains("2026-09"), "{}", page_ref.title);
+        let siblings = workspace.lock().unwrap().children("parent");
+        assert_eq!(siblings.last().unwrap()["id"].as_str(), Some(page_ref.id.as_str()));
+    }
+
+    #[tokio::test]
+    async fn a_full_page_is_rotated_and_retried_only_once() {
+        let (_workspace, mock, client) = full_page_workspace().await;
+        let children = [note("[09:00] x")];
+        let rotations = AtomicUsize::new(0);
+        let first = client.append_children("inbox", "Inbox", &children).await;
+
+        let (client, children, rotations) = (&client, &children, &rotations);
+        let (result, rotated) = retry_on_new_page(
+            first,
+            || async move {
+                rotations.fetch_add(1, Ordering::SeqCst);
+                create_continuation_page(client, "inbox", "Inbox").await
+            },
+            // The new page is full too
+            |page_ref| async move { client.append_children(&page_ref.id, &page_ref.title, children).await },
+        )
+        .await;
+
+        assert!(matches!(result, Err(AppendFailure::PageFull(_))));
+        assert!(rotated.is_some());
+        assert_eq!(rotations.load(Ordering::SeqCst), 1);
+        assert_eq!(mock.count("PATCH", "/v1/blocks/"), 2);
+        assert_eq!(mock.count("POST", "/v1/pages"), 1);
+    }
+
+    #[tokio::test]
+    async fn only_a_full_page_is_rotated() {
+        let failed: Result<AppendResponse, AppendFailure> = Err(AppendFailure::Failed("API error: 500".into()));
+        let (result, rotated) = retry_on_new_page(
+            failed,
+            || async { panic!("rotated after an ordinary failure") },
+            |_| async { panic!("retried after an ordinary failure") },
+        )
+        .await;
+        assert!(matches!(result, Err(AppendFailure::Failed(_))));
+        assert!(rotated.is_none());
+
+        // A failed rotation keeps the original error and doesn't retry
+        let full: Result<AppendResponse, AppendFailure> = Err(AppendFailure::PageFull("full".into()));
+        let (result, rotated) = retry_on_new_page(
+            full,
+            || async { Err("Only pages nested under another page can be continued automatically".to_string()) },
+            |_| async { panic!("retried without a new page") },
+        )
+        .await;
+        assert!(matches!(result, Err(AppendFailure::PageFull(_))));
+        assert!(rotated.is_none());
+    }
+
+    // A success response to PATCH /v1/blocks/{id}/children, as Notion sent it
+    const APPEND_RESPONSE: &str = include_str!("../fixtures/notion/append_children.json");
+    const APPEND_REQUEST_ID: &str = "b3f1c9e2-4d7a-4e8b-a1c6-0f2d9e8b7a65";
+
+    #[tokio::test]
+    async fn append_returns_the_blocks_notion_created() {
+        let mock = MockNotion::start(|_| {
+            MockReply::json(200, serde_json::from_str(APPEND_RESPONSE).unwrap()).header("x-request-id", APPEND_REQUEST_ID)
+        })
+        .await;
+        let client = NotionApiClient::new(mock.token.clone()).unwrap();
+        let children = vec![note("call the venue"), blocks::to_do(blocks::text_runs("book the room", false), false)];
+
+        let response = client.append_children("3e2f0000-0000-0000-0000-0000000000ab", "Inbox", &children).await.unwrap();
+
+        assert_eq!(
+            response.block_ids,
+            vec!["1c2b8f1e-52d4-80a1-9f0e-d3c5a1b7e001", "1c2b8f1e-52d4-80a1-9f0e-d3c5a1b7e002"]
+        );
+        assert_eq!(response.request_id.as_deref(), Some(APPEND_REQUEST_ID));
+        assert_eq!((response.status, response.attempts), (200, 1));
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].path, "/v1/blocks/3e2f0000-0000-0000-0000-0000000000ab/children");
+        assert_eq!(requests[0].body.as_ref().unwrap()["children"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn append_results_serialize_for_the_note_window() {
+        let result = AppendResult {
+            capture_id: "capture-1".to_string(),
+            block_ids: vec!["block-1".to_string()],
+            page_id: "page-1".to_string(),
+            appended_at: 1_709_301_720_000,
+            queued: false,
+        };
+        assert_eq!(
+            serde_json::to_value(&result).unwrap(),
+            json!({
+                "capture_id": "capture-1",
+                "block_ids": ["block-1"],
+                "page_id": "page-1",
+                "appended_at": 1_709_301_720_000_i64,
+                "queued": false
+            })
+        );
+
+        let queued = serde_json::to_value(AppendResult::queued("queued-1".to_string())).unwrap();
+        assert_eq!((&queued["block_ids"], &queued["queued"], &queued["page_id"]), (&json!([]), &json!(true), &json!("")));
+    }
+
+    // Notion's 429 with the given Retry-After for the first `limited` appends, then success
+    async fn rate_limited(limited: usize, retry_after: Option<String>) -> MockNotion {
+        let answered = AtomicUsize::new(0);
+        MockNotion::start(move |_| {
+            if answered.fetch_add(1, Ordering::SeqCst) < limited {
+                let reply = MockReply::error(429, "rate_limited", "You have been rate limited.");
+                return match &retry_after {
+                    Some(value) => reply.header("retry-after", value),
+                    None => reply,
+                };
+            }
+            MockReply::json(200, json!({ "object": "list", "results": [{ "object": "block", "id": "block-1" }] }))
+        })
+        .await
+    }
+
+    fn http_date_in(seconds: i64) -> String {
+        (chrono::Utc::now() + chrono::Duration::seconds(seconds)).format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+    }
+
+    // The wait a RateLimitError message asks for
+    fn rate_limit_wait(error: &str) -> u64 {
+        error
+            .strip_prefix("Notion rate limit reached, try again in ")
+            .and_then(|rest| rest.strip_suffix('s'))
+            .and_then(|seconds| seconds.parse().ok())
+            .unwrap_or_else(|| panic!("not a rate limit error: {}", error))
+    }
+
+    #[tokio::test]
+    async fn a_short_retry_after_is_waited_out_and_the_append_sent_again() {
+        // Without a Retry-After the limiter backs off for a second
+        for form in ["seconds", "date", "none"] {
+            let retry_after = match form {
+                "seconds" => Some("1".to_string()),
+                "date" => Some(http_date_in(2)),
+                _ => None,
+            };
+            let mock = rate_limited(1, retry_after.clone()).await;
+            let client = NotionApiClient::new(mock.token.clone()).unwrap();
+            let started = Instant::now();
+
+            let response = client.append_children("page-1", "Inbox", &[note("[09:00] x")]).await.unwrap();
+
+            assert_eq!(response.block_ids, vec!["block-1"], "{:?}", retry_after);
+            assert_eq!(mock.count("PATCH", "/v1/blocks/page-1/children"), 2, "{:?}", retry_after);
+            // The resend waited for the limiter; a date only has whole seconds
+            assert!(started.elapsed() >= Duration::from_millis(900), "{:?} {:?}", retry_after, started.elapsed());
+        }
+    }
+
+    #[tokio::test]
+    async fn a_long_retry_after_fails_the_append_as_rate_limited() {
+        for (form, expected) in [("seconds", 29..=30), ("date", 58..=60)] {
+            let retry_after = if form == "seconds" { "30".to_string() } else { http_date_in(60) };
+            let mock = rate_limited(usize::MAX, Some(retry_after.clone())).await;
+            let client = NotionApiClient::new(mock.token.clone()).unwrap();
+
+            let error = String::from(client.append_children("page-1", "Inbox", &[note("[09:00] x")]).await.unwrap_err());
+
+            let wait = rate_limit_wait(&error);
+            assert!(expected.contains(&wait), "{}: waited {}", retry_after, wait);
+            assert_eq!(mock.count("PATCH", "/"), 1, "{}", retry_after);
+            let limited_for = RATE_LIMITER.limited_for(&mock.token).unwrap();
+            assert!(limited_for > SHORT_RATE_LIMIT_WAIT, "{:?}", limited_for);
+        }
+    }
+
+    // A /v1/search response as Notion sent it
+    const SEARCH_RESULTS: &str = include_str!("../fixtures/notion/search_results.json");
+
+    fn search_results() -> Vec<(NotionPage, SearchParent)> {
+        let response: Value = serde_json::from_str(SEARCH_RESULTS).unwrap();
+        response["results"].as_array().unwrap().iter().filter_map(page_from_search_result).collect()
+    }
+
+    #[test]
+    fn search_results_keep_every_page_with_its_whole_title() {
+        let results = search_results();
+        let titles: Vec<&str> = results.iter().map(|(page, _)| page.title.as_str()).collect();
+        // A custom-named title property, runs joined including a mention's plain_text,
+        // an untitled page, a database item and a trashed page
+        assert_eq!(titles, vec!["Inbox March 1, 2024", "Untitled", "Book the venue", "Old ideas"]);
+
+        let (inbox, _) = &results[0];
+        assert_eq!(inbox.id, "1c2b8f1e-52d4-80a1-9f0e-d3c5a1b7e101");
+        assert_eq!(inbox.icon.as_deref(), Some("📥"));
+        assert_eq!(inbox.url, "https://www.notion.so/Inbox-March-1-2024-1c2b8f1e52d480a19f0ed3c5a1b7e101");
+        assert_eq!(inbox.last_edited_time.as_deref(), Some("2024-03-01T14:02:00+00:00"));
+        // Only emoji icons are shown
+        assert_eq!(results[2].0.icon, None);
+    }
+
+    #[test]
+    fn search_results_name_their_parent() {
+        let parents: Vec<SearchParent> = search_results().into_iter().map(|(_, parent)| parent).collect();
+        assert_eq!(
+            parents,
+            vec![
+                SearchParent::Workspace,
+                SearchParent::Page("1c2b8f1e-52d4-80a1-9f0e-d3c5a1b7e101".to_string()),
+                SearchParent::Database("1c2b8f1e-52d4-80a1-9f0e-d3c5a1b7e200".to_string()),
+                SearchParent::Other,
+            ]
+        );
+    }
+
+    #[test]
+    fn trashed_and_archived_pages_are_marked_archived() {
+        let archived: Vec<bool> = search_results().iter().map(|(page, _)| page.archived).collect();
+        assert_eq!(archived, vec![false, false, false, true]);
+
+        let older_api = json!({ "id": "page-1", "archived": true, "properties": {} });
+        let (page, _) = page_from_search_result(&older_api).unwrap();
+        assert!(page.archived);
+        assert_eq!(page.title, "Untitled");
+        // Without an id there is nothing to select
+        assert!(page_from_search_result(&json!({ "properties": {} })).is_none());
+    }
+    
+    // Counts what storing a page reference did
+    #[derive(Default)]
+    struct CountingEffects {
+        saves: std::cell::Cell<usize>,
+        changes: std::cell::Cell<usize>,
+    }
+    
+    impl PageRefEffects for CountingEffects {
+        fn save(&self, _config: &mut AppConfig) -> Result<(), String> {
+            self.saves.set(self.saves.get() + 1);
+            Ok(())
+        }
+        
+        fn config_changed(&self) {
+            self.changes.set(self.changes.get() + 1);
+        }
+    }
+    
+    fn selected(id: &str, title: &str) -> Mutex<AppConfig> {
+        Mutex::new(AppConfig {
+            selected_page_id: id.to_string(),
+            selected_page_title: title.to_string(),
+            selected_page_icon: Some("📥".to_string()),
+            ..AppConfig::default()
+        })
+    }
+    
+    #[tokio::test]
+    async fn a_renamed_page_in_search_results_is_saved_once() {
+        let mock = MockNotion::start(|request| match request.path.as_str() {
+            "/v1/search" => MockReply::json(200, serde_json::from_str(SEARCH_RESULTS).unwrap()),
+            _ => MockReply::error(404, "object_not_found", "Not found"),
+        })
+        .await;
+        let pages = NotionApiClient::new(mock.token.clone()).unwrap().search_pages(100).await.unwrap();
+        let config = selected("1c2b8f1e52d480a19f0ed3c5a1b7e101", "Inbox");
+        let effects = CountingEffects::default();
+        
+        assert!(refresh_selected_page_ref_in(&config, &effects, TargetKind::Page, &pages));
+        assert_eq!((effects.saves.get(), effects.changes.get()), (1, 1));
+        assert_eq!(config.lock().unwrap().selected_page_title, "Inbox March 1, 2024");
+        
+        // The stored title now matches, so the same results change nothing
+        assert!(!refresh_selected_page_ref_in(&config, &effects, TargetKind::Page, &pages));
+        assert_eq!((effects.saves.get(), effects.changes.get()), (1, 1));
+    }
+    
+    #[test]
+    fn whitespace_or_another_kind_of_target_is_not_a_rename() {
+        let pages: Vec<NotionPage> = search_results().into_iter().map(|(page, _)| page).collect();
+        let effects = CountingEffects::default();
+        
+        let config = selected("1c2b8f1e-52d4-80a1-9f0e-d3c5a1b7e101", "  Inbox   March 1,\t2024 ");
+        assert!(!refresh_selected_page_ref_in(&config, &effects, TargetKind::Page, &pages));
+        
+        let config = selected("1c2b8f1e-52d4-80a1-9f0e-d3c5a1b7e101", "Inbox");
+        config.lock().unwrap().selected_target_kind = TargetKind::Database;
+        assert!(!refresh_selected_page_ref_in(&config, &effects, TargetKind::Page, &pages));
+        assert_eq!((effects.saves.get(), effects.changes.get()), (0, 0));
+    }
+    
+    fn page(id: &str, title: &str, icon: Option<&str>) -> NotionPage {
+        NotionPage {
+            id: id.to_string(),
+            title: title.to_string(),
+            icon: icon.map(str::to_string),
+            url: String::new(),
+            parent_title: None,
+            last_edited_time: None,
+            archived: false,
+        }
+    }
+    
+    #[test]
+    fn reconciling_picks_up_renames_icons_and_returns() {
+        let mut page_ref = PageRef { id: "1c2b8f1e-52d4-80a1".into(), title: "Inbox".into(), icon: None, stale: false };
+        
+        // Ids match with or without dashes
+        assert_eq!(reconcile_page_ref(&mut page_ref, &[page("1C2B8F1E52D480A1", "Inbox", None)]), Reconciled::Unchanged);
+        assert_eq!(reconcile_page_ref(&mut page_ref, &[page("1c2b8f1e52d480a1", "Inbox 2024", None)]), Reconciled::Updated);
+        assert_eq!(page_ref.title, "Inbox 2024");
+        
+        // A new icon is taken, a missing one doesn't clear the stored icon
+        assert_eq!(reconcile_page_ref(&mut page_ref, &[page("1c2b8f1e52d480a1", "Inbox 2024", Some("📥"))]), Reconciled::Updated);
+        assert_eq!(reconcile_page_ref(&mut page_ref, &[page("1c2b8f1e52d480a1", "Inbox 2024", None)]), Reconciled::Unchanged);
+        assert_eq!(page_ref.icon.as_deref(), Some("📥"));
+        
+        assert_eq!(reconcile_page_ref(&mut page_ref, &[page("other", "Inbox 2024", None)]), Reconciled::Missing);
+        assert_eq!(page_ref.title, "Inbox 2024");
+        
+        // A stale reference that shows up again is no longer stale
+        page_ref.stale = true;
+        assert_eq!(reconcile_page_ref(&mut page_ref, &[page("1c2b8f1e52d480a1", "Inbox 2024", Some("📥"))]), Reconciled::Updated);
+        assert!(!page_ref.stale);
+    }
+    
+    // A workspace whose search finds `listed` and whose pages endpoint knows `fetched`
+    async fn enrich_server(listed: Vec<Value>, fetched: Option<Value>) -> MockNotion {
+        MockNotion::start(move |request| match request.path.as_str() {
+            "/v1/search" => MockReply::json(200, json!({ "object": "list", "results": listed, "has_more": false, "next_cursor": null })),
+            _ => match &fetched {
+                Some(page) => MockReply::json(200, page.clone()),
+                None => MockReply::error(404, "object_not_found", "Not found"),
+            },
+        })
+        .await
+    }
+    
+    fn listed(id: &str, title: &str, emoji: &str) -> Value {
+        json!({
+            "object": "page",
+            "id": id,
+            "archived": false,
+            "icon": { "type": "emoji", "emoji": emoji },
+            "properties": { "title": { "type": "title", "title": [{ "plain_text": title }] } },
+        })
+    }
+    
+    fn selected_with_token(id: &str, title: &str, mock: &MockNotion) -> Mutex<AppConfig> {
+        // Searches are cached by their options, so each workspace gets a cap of its own
+        static NEXT_CAP: AtomicUsize = AtomicUsize::new(1000);
+        let config = selected(id, title);
+        {
+            let mut config = config.lock().unwrap();
+            config.secrets.set_main_token(mock.token.clone());
+            config.page_search_max_results = NEXT_CAP.fetch_add(1, Ordering::SeqCst);
+        }
+        config
+    }
+    
+    #[tokio::test]
+    async fn enrichment_stores_a_rename_and_an_icon_change() {
+        let mock = enrich_server(vec![listed("enrich-renamed", "Inbox (old)", "🗂️")], None).await;
+        let config = selected_with_token("enrich-renamed", "Inbox", &mock);
+        let effects = CountingEffects::default();
+        
+        enrich_page_refs_in(&config, &effects).await.unwrap();
+        let page_ref = config.lock().unwrap().selected_page_ref();
+        assert_eq!((page_ref.title.as_str(), page_ref.icon.as_deref(), page_ref.stale), ("Inbox (old)", Some("🗂️"), false));
+        assert_eq!((effects.saves.get(), effects.changes.get()), (1, 1));
+        assert_eq!(mock.count("GET", "/v1/pages/"), 0);
+        
+        // Nothing new the second time round
+        enrich_page_refs_in(&config, &effects).await.unwrap();
+        assert_eq!((effects.saves.get(), effects.changes.get()), (1, 1));
+    }
+    
+    #[tokio::test]
+    async fn enrichment_flags_archived_and_deleted_pages_and_unflags_them_on_return() {
+        let effects = CountingEffects::default();
+        
+        // Missing from search and archived: flagged, not removed
+        let archived = json!({ "object": "page", "id": "enrich-archived", "archived": true, "properties": {} });
+        let mock = enrich_server(Vec::new(), Some(archived)).await;
+        let config = selected_with_token("enrich-archived", "Inbox", &mock);
+        enrich_page_refs_in(&config, &effects).await.unwrap();
+        let page_ref = config.lock().unwrap().selected_page_ref();
+        assert_eq!((page_ref.id.as_str(), page_ref.title.as_str(), page_ref.stale), ("enrich-archived", "Inbox", true));
+        assert_eq!(mock.count("GET", "/v1/pages/enrich-archived"), 1);
+        assert_eq!((effects.saves.get(), effects.changes.get()), (1, 1));
+        
+        // Already flagged: nothing to store again
+        enrich_page_refs_in(&config, &effects).await.unwrap();
+        assert_eq!((effects.saves.get(), effects.changes.get()), (1, 1));
+        
+        // Deleted outright
+        let mock = enrich_server(Vec::new(), None).await;
+        let config = selected_with_token("enrich-deleted", "Inbox", &mock);
+        enrich_page_refs_in(&config, &effects).await.unwrap();
+        assert!(config.lock().unwrap().selected_page_stale);
+        assert_eq!((effects.saves.get(), effects.changes.get()), (2, 2));
+        
+        // Only missing from search results: confirmed live, so left alone
+        let live = json!({ "object": "page", "id": "enrich-unlisted", "archived": false, "in_trash": false, "properties": {} });
+        let mock = enrich_server(Vec::new(), Some(live)).await;
+        let config = selected_with_token("enrich-unlisted", "Inbox", &mock);
+        enrich_page_refs_in(&config, &effects).await.unwrap();
+        assert!(!config.lock().unwrap().selected_page_stale);
+        assert_eq!((effects.saves.get(), effects.changes.get()), (2, 2));
+        
+        // Restored from the trash and listed again
+        let mock = enrich_server(vec![listed("enrich-restored", "Inbox", "📥")], None).await;
+        let config = selected_with_token("enrich-restored", "Inbox", &mock);
+        config.lock().unwrap().selected_page_stale = true;
+        enrich_page_refs_in(&config, &effects).await.unwrap();
+        assert!(!config.lock().unwrap().selected_page_stale);
+        assert_eq!((effects.saves.get(), effects.changes.get()), (3, 3));
+    }
+    
+    // The raw response a mock reply turns into, before any client handling
+    async fn raw_response(reply: MockReply) -> (MockNotion, reqwest::Response) {
+        let mock = MockNotion::start(move |_| reply.clone()).await;
+        let res = reqwest::Client::new().get(mock.url("/v1/pages/page-1")).send().await.unwrap();
+        (mock, res)
+    }
+    
+    #[tokio::test]
+    async fn read_json_parses_a_json_body() {
+        let (_mock, res) = raw_response(MockReply::json(200, json!({ "object": "page", "id": "page-1" }))).await;
+        assert_eq!(read_json(res).await.unwrap()["id"], "page-1");
+        
+        // A charset parameter is still JSON
+        let reply = MockReply::text(200, "application/json; charset=utf-8", r#"{"id":"page-1"}"#);
+        let (_mock, res) = raw_response(reply).await;
+        assert_eq!(read_json(res).await.unwrap()["id"], "page-1");
+    }
+    
+    #[tokio::test]
+    async fn read_json_refuses_another_content_type() {
+        let reply = MockReply::text(502, "text/html", "<html><body>Bad gateway</body></html>");
+        let (_mock, res) = raw_response(reply).await;
+        let error = read_json(res).await.unwrap_err();
+        assert!(error.contains("Expected a JSON response but got 'text/html' (HTTP 502 Bad Gateway)"), "{}", error);
+        
+        let (_mock, res) = raw_response(MockReply::text(200, "", "{}")).await;
+        assert!(read_json(res).await.unwrap_err().contains("got ''"));
+    }
+    
+    #[tokio::test]
+    async fn read_json_refuses_an_oversized_content_length_without_reading() {
+        let announced = (MAX_RESPONSE_BYTES + 1).to_string();
+        let reply = MockReply::json(200, json!({})).header("content-length", &announced);
+        let (_mock, res) = raw_response(reply).await;
+        let error = read_json(res).await.unwrap_err();
+        assert!(error.contains(&format!("Response body exceeds {} bytes", MAX_RESPONSE_BYTES)), "{}", error);
+    }
+    
+    #[tokio::test]
+    async fn read_json_stops_a_streamed_body_past_the_cap() {
+        let oversized = json!({ "padding": "x".repeat(MAX_RESPONSE_BYTES) });
+        let (_mock, res) = raw_response(MockReply::json(200, oversized).streamed()).await;
+        assert_eq!(res.content_length(), None);
+        let error = read_json(res).await.unwrap_err();
+        assert!(error.contains("exceeds"), "{}", error);
+        
+        // Right under the cap is fine
+        let fits = json!({ "padding": "x".repeat(MAX_RESPONSE_BYTES - 100) });
+        let (_mock, res) = raw_response(MockReply::json(200, fits).streamed()).await;
+        assert_eq!(read_json(res).await.unwrap()["padding"].as_str().unwrap().len(), MAX_RESPONSE_BYTES - 100);
+    }
+    
+    #[tokio::test]
+    async fn read_json_gives_up_on_a_stalled_body() {
+        let reply = MockReply::json(200, json!({ "id": "page-1" })).slow_body(Duration::from_secs(5));
+        let (_mock, res) = raw_response(reply).await;
+        let started = Instant::now();
+        let error = read_json_within(res, Duration::from_millis(1500)).await.unwrap_err();
+        assert!(error.contains("Timed out reading response after 1s"), "{}", error);
+        assert!(started.elapsed() < Duration::from_secs(4));
+        
+        // A body that only takes a moment still arrives
+        let reply = MockReply::json(200, json!({ "id": "page-1" })).slow_body(Duration::from_millis(100));
+        let (_mock, res) = raw_response(reply).await;
+        assert_eq!(read_json_within(res, Duration::from_secs(2)).await.unwrap()["id"], "page-1");
+    }
+    
+    #[tokio::test]
+    async fn a_deleted_sandbox_page_is_forgotten_and_routing_falls_back() {
+        use crate::capture::{resolve_destination_plan, DestinationContext, DestinationRule};
+        let workspace = FakeWorkspace::shared();
+        workspace.lock().unwrap().add_page("main-page", "Inbox");
+        let notion = workspace.clone();
+        let mock = MockNotion::start(move |request| notion.lock().unwrap().answer(request)).await;
+        let client = NotionApiClient::new(mock.token.clone()).unwrap();
+        let created = client.create_page(&rotation_page_body("main-page", SANDBOX_PAGE_TITLE)).await.unwrap();
+        let sandbox_id = created["id"].as_str().unwrap().to_string();
+        
+        let config = selected("main-page", "Inbox");
+        {
+            let mut config = config.lock().unwrap();
+            config.secrets.set_main_token(mock.token.clone());
+            config.sandbox_mode = true;
+            config.sandbox_page_id = Some(sandbox_id.clone());
+        }
+        let effects = CountingEffects::default();
+        let note = |text: &str| vec![crate::blocks::paragraph(crate::blocks::text_runs(text, false))];
+        
+        // Sandbox mode beats the route prefix
+        let config_now = config.lock().unwrap().clone();
+        let destination = resolve_destination_plan(&config_now, "/work try this", &DestinationContext::default()).unwrap();
+        assert_eq!((destination.rule, destination.page_id.as_str()), (DestinationRule::Sandbox, sandbox_id.as_str()));
+        client.append_children(&destination.page_id, &destination.page_title, &note(&destination.note_text)).await.unwrap();
+        assert_eq!(workspace.lock().unwrap().children(&sandbox_id).len(), 1);
+        
+        // A failure on a page that still exists keeps it
+        assert!(!forget_deleted_sandbox_in(&config, &effects, &client, &sandbox_id).await);
+        assert_eq!(config.lock().unwrap().sandbox_page_id.as_deref(), Some(sandbox_id.as_str()));
+        
+        // Deleted in Notion: the append fails, and the page is found to be gone
+        workspace.lock().unwrap().delete_page(&sandbox_id);
+        assert!(client.append_children(&sandbox_id, SANDBOX_PAGE_TITLE, &note("lost")).await.is_err());
+        assert!(forget_deleted_sandbox_in(&config, &effects, &client, &sandbox_id).await);
+        assert_eq!(config.lock().unwrap().sandbox_page_id, None);
+        assert_eq!((effects.saves.get(), effects.changes.get()), (1, 1));
+        assert!(workspace.lock().unwrap().children("main-page").is_empty());
+        
+        // Still in sandbox mode: nothing is sent until a new sandbox exists
+        let config_now = config.lock().unwrap().clone();
+        let error = resolve_destination_plan(&config_now, "next", &DestinationContext::default()).unwrap_err();
+        assert!(error.contains("there is no sandbox page"), "{}", error);
+        // Turned off: normal routing again
+        let config_now = AppConfig { sandbox_mode: false, ..config_now };
+        let destination = resolve_destination_plan(&config_now, "next", &DestinationContext::default()).unwrap();
+        assert_eq!((destination.rule, destination.page_id.as_str()), (DestinationRule::Default, "main-page"));
+        
+        // A newer sandbox stored meanwhile isn't cleared for the old one's 404
+        config.lock().unwrap().sandbox_page_id = Some("newer-sandbox".into());
+        assert!(!forget_deleted_sandbox_in(&config, &effects, &client, &sandbox_id).await);
+        assert_eq!(config.lock().unwrap().sandbox_page_id.as_deref(), Some("newer-sandbox"));
+        assert_eq!((effects.saves.get(), effects.changes.get()), (1, 1));
+    }
+}
@@ -0,0 +1,340 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+use crate::capture::SANDBOX_PAGE_TITLE;
+use crate::config::{AppConfig, AppState};
+use crate::events::{self, AppEvent};
+use crate::notion::{self, same_page_id, NotionApiClient, NotionPage};
+use crate::rate_limit::Priority;
+
+// How every user-facing surface names a page. The best title available right
+// now comes from config references, the page search cache or earlier lookups;
+// without one the page shows as a shortened id and a background fetch is
+// started, whose result arrives as a `page-display-updated` event. Callers
+// never wait on the network.
+
+// Titles remembered from fetches; the oldest is dropped past this
+const MAX_RESOLVED: usize = 200;
+
+// A fetch that takes longer than this is abandoned; the id stays the label
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+// A page whose lookup failed isn't tried again for this long
+const RETRY_AFTER: Duration = Duration::from_secs(600);
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct PageDisplay {
+    pub page_id: String,
+    // The page title, or the shortened id while it isn't known
+    pub title: String,
+    pub icon: Option<String>,
+    // False while `title` is the shortened-id fallback
+    pub resolved: bool,
+}
+
+impl PageDisplay {
+    fn fallback(page_id: &str) -> Self {
+        PageDisplay {
+            page_id: page_id.to_string(),
+            title: short_id(page_id),
+            icon: None,
+            resolved: false,
+        }
+    }
+
+    // Icon and title together, e.g. "📓 Journal"
+    pub fn label(&self) -> String {
+        match &self.icon {
+            Some(icon) => format!("{} {}", icon, self.title),
+            None => self.title.clone(),
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    // In insertion order, for eviction
+    static ref RESOLVED: Mutex<Vec<PageDisplay>> = Mutex::new(Vec::new());
+    // Page ids with a fetch under way, so concurrent lookups share one
+    static ref FETCHING: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+    // When each failed lookup happened
+    static ref FAILED: Mutex<HashMap<String, Instant>> = Mutex::new(HashMap::new());
+}
+
+// "3e2f…a1" for "3e2f0000-...-0000000000a1"
+pub fn short_id(page_id: &str) -> String {
+    let compact: String = page_id.chars().filter(|c| *c != '-').collect();
+    if compact.chars().count() <= 8 {
+        return compact;
+    }
+    let head: String = compact.chars().take(4).collect();
+    let tail: String = compact.chars().skip(compact.chars().count() - 2).collect();
+    format!("{}…{}", head, tail)
+}
+
+// What is known without the network: config first (it carries titles the user
+// picked), then the search cache, then earlier fetches
+pub fn lookup(
+    config: &AppConfig,
+    cached: &[NotionPage],
+    resolved: &[PageDisplay],
+    page_id: &str,
+) -> Option<PageDisplay> {
+    let known = |title: &str, icon: Option<&String>| {
+        (!title.is_empty()).then(|| PageDisplay {
+            page_id: page_id.to_string(),
+            title: title.to_string(),
+            icon: icon.cloned(),
+            resolved: true,
+        })
+    };
+    if same_page_id(&config.selected_page_id, page_id) {
+        if let Some(display) = known(&config.selected_page_title, config.selected_page_icon.as_ref()) {
+            return Some(display);
+        }
+    }
+    if config.sandbox_page_id.as_deref().is_some_and(|sandbox| same_page_id(sandbox, page_id)) {
+        return known(SANDBOX_PAGE_TITLE, None);
+    }
+    config
+        .capture_profiles
+        .iter()
+        .filter(|profile| same_page_id(&profile.page_id, page_id))
+        .find_map(|profile| known(&profile.page_title, None))
+        .or_else(|| {
+            cached
+                .iter()
+                .find(|page| same_page_id(&page.id, page_id))
+                .and_then(|page| known(&page.title, page.icon.as_ref()))
+        })
+        .or_else(|| resolved.iter().find(|display| same_page_id(&display.page_id, page_id)).cloned())
+}
+
+fn remember(display: PageDisplay) {
+    let mut resolved = RESOLVED.lock().unwrap();
+    resolved.retain(|known| !same_page_id(&known.page_id, &display.page_id));
+    resolved.push(display);
+    if resolved.len() > MAX_RESOLVED {
+        resolved.remove(0);
+    }
+}
+
+// The title property of a page object, whatever the property is called
+pub fn title_from_page(page: &Value) -> Option<String> {
+    let title: String = page["properties"]
+        .as_object()?
+        .values()
+        .find(|property| property["type"] == "title" || property.get("title").is_some())?["title"]
+        .as_array()?
+        .iter()
+        .filter_map(|part| part["plain_text"].as_str().or_else(|| part["text"]["content"].as_str()))
+        .collect();
+    (!title.trim().is_empty()).then_some(title)
+}
+
+// Best display for a page right now; starts a background fetch when it isn't known
+pub fn display(app: &AppHandle, page_id: &str) -> PageDisplay {
+    if page_id.is_empty() {
+        return PageDisplay::fallback(page_id);
+    }
+    let known = {
+        let state = app.state::<AppState>();
+        let config = state.config.lock().unwrap();
+        lookup(&config, &notion::cached_pages().unwrap_or_default(), &RESOLVED.lock().unwrap(), page_id)
+    };
+    known.unwrap_or_else(|| {
+        fetch_in_background(app, page_id);
+        PageDisplay::fallback(page_id)
+    })
+}
+
+// Displays for several pages, keyed by id as given
+pub fn display_all<'a>(app: &AppHandle, page_ids: impl IntoIterator<Item = &'a str>) -> HashMap<String, PageDisplay> {
+    page_ids
+        .into_iter()
+        .map(|page_id| (page_id.to_string(), display(app, page_id)))
+        .collect()
+}
+
+async fn fetch(api_token: String, page_id: &str) -> Result<Option<Value>, String> {
+    let client = NotionApiClient::new(api_token)?.with_priority(Priority::Background);
+    tokio::time::timeout(FETCH_TIMEOUT, client.fetch_page(page_id))
+        .await
+        .map_err(|_| "timed out".to_string())?
+}
+
+// Claim the fetch for a page; false when one is under way or it failed recently
+fn start_fetch(page_id: &str) -> bool {
+    let recently_failed = FAILED
+        .lock()
+        .unwrap()
+        .get(page_id)
+        .is_some_and(|failed_at| failed_at.elapsed() < RETRY_AFTER);
+    !recently_failed && FETCHING.lock().unwrap().insert(page_id.to_string())
+}
+
+// Fetch the page's title and remember it; None when there's nothing better than the id
+async fn resolve_remote(api_token: String, page_id: &str) -> Option<PageDisplay> {
+    let fetched = fetch(api_token, page_id).await;
+    if !matches!(fetched, Ok(Some(_))) {
+        FAILED.lock().unwrap().insert(page_id.to_string(), Instant::now());
+    }
+    let display = match fetched {
+        Ok(Some(page)) => title_from_page(&page).map(|title| PageDisplay {
+            page_id: page_id.to_string(),
+            title,
+            icon: page["icon"]["emoji"].as_str().map(str::to_string),
+            resolved: true,
+        }),
+        Ok(None) => None,
+        Err(e) => {
+            eprintln!("Couldn't look up the title of page {}: {}", short_id(page_id), e);
+            None
+        }
+    };
+    if let Some(display) = &display {
+        remember(display.clone());
+    }
+    FETCHING.lock().unwrap().remove(page_id);
+    display
+}
+
+fn fetch_in_background(app: &AppHandle, page_id: &str) {
+    if !start_fetch(page_id) {
+        return;
+    }
+    let api_token = app.state::<AppState>().config.lock().unwrap().secrets.main_token().to_string();
+    let app_handle = app.clone();
+    let page_id = page_id.to_string();
+    tauri::async_runtime::spawn(async move {
+        if let Some(display) = resolve_remote(api_token, &page_id).await {
+            crate::refresh_tray(&app_handle);
+            events::emit_app_event(&app_handle, AppEvent::PageDisplayUpdated(display));
+        }
+    });
+}
+
+// Display names for pages the frontend only knows by id
+#[tauri::command]
+pub fn get_page_displays(page_ids: Vec<String>, app: AppHandle) -> HashMap<String, PageDisplay> {
+    display_all(&app, page_ids.iter().map(String::as_str))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CaptureProfile;
+    use crate::mock_notion::{MockNotion, MockReply};
+    use serde_json::json;
+
+    fn page(id: &str, title: &str) -> NotionPage {
+        NotionPage {
+            id: id.to_string(),
+            title: title.to_string(),
+            icon: None,
+            url: String::new(),
+            parent_title: None,
+            last_edited_time: None,
+            archived: false,
+        }
+    }
+
+    #[test]
+    fn unknown_pages_show_a_shortened_id() {
+        assert_eq!(short_id("3e2f0000-0000-0000-0000-0000000000a1"), "3e2f…a1");
+        assert_eq!(short_id("3e2f00000000000000000000000000a1"), "3e2f…a1");
+        assert_eq!(short_id("page-1"), "page1");
+        assert_eq!(short_id(""), "");
+
+        let display = PageDisplay::fallback("3e2f0000-0000-0000-0000-0000000000a1");
+        assert_eq!((display.label(), display.resolved), ("3e2f…a1".to_string(), false));
+    }
+
+    #[test]
+    fn config_outranks_the_cache_and_earlier_fetches() {
+        let id = "3e2f0000-0000-0000-0000-0000000000a1";
+        let mut config = AppConfig {
+            selected_page_id: id.to_string(),
+            selected_page_title: "Journal".to_string(),
+            selected_page_icon: Some("📓".to_string()),
+            ..AppConfig::default()
+        };
+        let cached = [page(id, "Old journal")];
+        let resolved = [PageDisplay { page_id: id.into(), title: "Fetched".into(), icon: None, resolved: true }];
+
+        let display = lookup(&config, &cached, &resolved, &id.replace('-', "")).unwrap();
+        assert_eq!((display.label(), display.resolved), ("📓 Journal".to_string(), true));
+
+        config.selected_page_title.clear();
+        assert_eq!(lookup(&config, &cached, &resolved, id).unwrap().title, "Old journal");
+        assert_eq!(lookup(&config, &[], &resolved, id).unwrap().title, "Fetched");
+        assert_eq!(lookup(&config, &[], &[], id), None);
+    }
+
+    #[test]
+    fn sandbox_and_profile_pages_are_named_from_config() {
+        let config = AppConfig {
+            sandbox_page_id: Some("sandbox-page".into()),
+            capture_profiles: vec![CaptureProfile {
+                name: "work".into(),
+                page_id: "work-page".into(),
+                page_title: "Work log".into(),
+                ..CaptureProfile::default()
+            }],
+            ..AppConfig::default()
+        };
+        assert_eq!(lookup(&config, &[], &[], "sandbox-page").unwrap().title, SANDBOX_PAGE_TITLE);
+        assert_eq!(lookup(&config, &[], &[], "work-page").unwrap().title, "Work log");
+    }
+
+    #[test]
+    fn titles_come_from_whichever_property_is_the_title() {
+        let page = json!({ "properties": {
+            "Status": { "type": "select", "select": null },
+            "Name": { "type": "title", "title": [{ "plain_text": "Reading " }, { "text": { "content": "list" } }] }
+        }});
+        assert_eq!(title_from_page(&page).as_deref(), Some("Reading list"));
+        assert_eq!(title_from_page(&json!({ "properties": { "title": { "title": [] } } })), None);
+        assert_eq!(title_from_page(&json!({})), None);
+    }
+
+    #[tokio::test]
+    async fn a_fetched_title_is_remembered_for_later_lookups() {
+        let id = "3e2f0000-0000-0000-0000-00000000f001";
+        let mock = MockNotion::start(|_| {
+            MockReply::json(200, json!({
+                "object": "page",
+                "id": "3e2f0000-0000-0000-0000-00000000f001",
+                "icon": { "type": "emoji", "emoji": "📥" },
+                "properties": { "title": { "type": "title", "title": [{ "plain_text": "Inbox" }] } }
+            }))
+        })
+        .await;
+
+        assert!(start_fetch(id));
+        // A second lookup while the first is under way shares it
+        assert!(!start_fetch(id));
+        let display = resolve_remote(mock.token.clone(), id).await.unwrap();
+        assert_eq!((display.label(), display.resolved), ("📥 Inbox".to_string(), true));
+        assert_eq!(mock.count("GET", "/v1/pages/"), 1);
+
+        // Now a cache hit, with no request
+        let known = lookup(&AppConfig::default(), &[], &RESOLVED.lock().unwrap(), id).unwrap();
+        assert_eq!(known, display);
+        assert_eq!(mock.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn a_failed_fetch_isnt_retried_straight_away() {
+        let id = "3e2f0000-0000-0000-0000-00000000f404";
+        let mock = MockNotion::start(|_| MockReply::error(404, "object_not_found", "Could not find page")).await;
+
+        assert!(start_fetch(id));
+        assert_eq!(resolve_remote(mock.token.clone(), id).await, None);
+        assert!(!start_fetch(id));
+        assert_eq!(lookup(&AppConfig::default(), &[], &RESOLVED.lock().unwrap(), id), None);
+    }
+}
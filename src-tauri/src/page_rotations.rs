@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::sync::Mutex;
+
+use crate::config::app_data_path;
+
+// Audit trail of automatic page rotations, the one time the app changes the
+// selected page without the user choosing it
+const ROTATIONS_FILE: &str = "page_rotations.json";
+
+// Oldest entries are dropped past this many
+const MAX_ROTATIONS: usize = 100;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PageRotationEntry {
+    pub from_page_id: String,
+    pub from_title: String,
+    pub to_page_id: String,
+    pub to_title: String,
+    // RFC3339
+    pub rotated_at: String,
+}
+
+lazy_static::lazy_static! {
+    // Serializes access to the rotations file
+    static ref ROTATIONS_LOCK: Mutex<()> = Mutex::new(());
+}
+
+fn read_entries() -> Result<Vec<PageRotationEntry>, String> {
+    let path = app_data_path(ROTATIONS_FILE)?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let contents = fs::read_to_string(&path).map_err(|e| format!("Failed to read page rotations: {}", e))?;
+    serde_json::from_str(&contents).map_err(|e| format!("Failed to parse page rotations: {}", e))
+}
+
+// Rotations, oldest first
+pub fn load() -> Result<Vec<PageRotationEntry>, String> {
+    let _lock = ROTATIONS_LOCK.lock().unwrap();
+    read_entries()
+}
+
+pub fn record(entry: PageRotationEntry) -> Result<(), String> {
+    let _lock = ROTATIONS_LOCK.lock().unwrap();
+    let mut entries = read_entries()?;
+    entries.push(entry);
+    if entries.len() > MAX_ROTATIONS {
+        entries.drain(..entries.len() - MAX_ROTATIONS);
+    }
+    let contents = serde_json::to_string_pretty(&entries)
+        .map_err(|e| format!("Failed to serialize page rotations: {}", e))?;
+    fs::write(app_data_path(ROTATIONS_FILE)?, contents).map_err(|e| format!("Failed to write page rotations: {}", e))
+}
+
+// Remove the audit trail; returns how many rotations it held
+pub fn clear() -> Result<usize, String> {
+    let _lock = ROTATIONS_LOCK.lock().unwrap();
+    let count = read_entries()?.len();
+    let path = app_data_path(ROTATIONS_FILE)?;
+    if path.exists() {
+        fs::remove_file(&path).map_err(|e| format!("Failed to remove page rotations: {}", e))?;
+    }
+    Ok(count)
+}
+
+// Every automatic page rotation, oldest first
+#[tauri::command]
+pub fn get_page_rotations() -> Result<Vec<PageRotationEntry>, String> {
+    load()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(index: usize) -> PageRotationEntry {
+        PageRotationEntry {
+            from_page_id: format!("page-{}", index),
+            from_title: "Inbox".into(),
+            to_page_id: format!("page-{}", index + 1),
+            to_title: "Inbox (cont. 2026-10)".into(),
+            rotated_at: "2026-10-16T09:00:00+00:00".into(),
+        }
+    }
+
+    #[test]
+    fn rotations_are_kept_oldest_first_up_to_the_cap() {
+        let _data_dir = crate::config::use_test_data_dir();
+        for index in 0..MAX_ROTATIONS + 5 {
+            record(entry(index)).unwrap();
+        }
+        let entries = load().unwrap();
+        assert_eq!(entries.len(), MAX_ROTATIONS);
+        assert_eq!(entries[0], entry(5));
+        assert_eq!(clear().unwrap(), MAX_ROTATIONS);
+        assert!(load().unwrap().is_empty());
+    }
+}
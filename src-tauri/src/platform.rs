@@ -0,0 +1,392 @@
+// Platform-specific helpers. Everything here is best-effort: when the OS
+// doesn't let us find out, callers get `None` and carry on as usual.
+
+// The application that currently has keyboard focus
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForegroundApp {
+    // Executable file name, e.g. "Code.exe" or "firefox"
+    pub executable: String,
+    // Bundle identifier, only available on macOS
+    pub bundle_id: Option<String>,
+}
+
+// Windows: resolve the foreground window's process image path
+#[cfg(windows)]
+pub fn foreground_app() -> Option<ForegroundApp> {
+    use windows_sys::Win32::Foundation::CloseHandle;
+    use windows_sys::Win32::System::Threading::{
+        OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+    use windows_sys::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId};
+
+    unsafe {
+        let hwnd = GetForegroundWindow();
+        if hwnd == 0 {
+            return None;
+        }
+
+        let mut pid = 0u32;
+        GetWindowThreadProcessId(hwnd, &mut pid);
+        if pid == 0 {
+            return None;
+        }
+
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if process == 0 {
+            return None;
+        }
+
+        let mut buffer = [0u16; 1024];
+        let mut len = buffer.len() as u32;
+        let ok = QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, buffer.as_mut_ptr(), &mut len);
+        CloseHandle(process);
+        if ok == 0 {
+            return None;
+        }
+
+        let path = String::from_utf16_lossy(&buffer[..len as usize]);
+        Some(ForegroundApp {
+            executable: executable_name(&path),
+            bundle_id: None,
+        })
+    }
+}
+
+// macOS: ask System Events for the frontmost process and its bundle id
+#[cfg(target_os = "macos")]
+pub fn foreground_app() -> Option<ForegroundApp> {
+    let script = "tell application \"System Events\" to set p to first application process whose frontmost is true\n\
+                  return (name of p) & \"|\" & (bundle identifier of p)";
+    let output = std::process::Command::new("osascript").arg("-e").arg(script).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let text = String::from_utf8_lossy(&output.stdout);
+    let (name, bundle_id) = text.trim().split_once('|')?;
+    Some(ForegroundApp {
+        executable: name.to_string(),
+        bundle_id: Some(bundle_id.to_string()),
+    })
+}
+
+// Linux: X11 only, via xdotool and /proc
+#[cfg(target_os = "linux")]
+pub fn foreground_app() -> Option<ForegroundApp> {
+    let output = std::process::Command::new("xdotool")
+        .args(["getactivewindow", "getwindowpid"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let pid = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let comm = std::fs::read_to_string(format!("/proc/{}/comm", pid)).ok()?;
+    Some(ForegroundApp {
+        executable: comm.trim().to_string(),
+        bundle_id: None,
+    })
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+pub fn foreground_app() -> Option<ForegroundApp> {
+    None
+}
+
+// File name component of an executable path
+#[cfg_attr(not(windows), allow(dead_code))]
+fn executable_name(path: &str) -> String {
+    path.rsplit(['\\', '/']).next().unwrap_or(path).to_string()
+}
+
+// Whether the hotkey should be ignored for this app. Entries prefixed with
+// "bundle:" match a macOS bundle id exactly; anything else is a
+// case-insensitive substring of the executable name.
+pub fn is_excluded_app(app: &ForegroundApp, exclusions: &[String]) -> bool {
+    let executable = app.executable.to_lowercase();
+
+    exclusions.iter().any(|entry| {
+        let entry = entry.trim();
+        if entry.is_empty() {
+            return false;
+        }
+
+        match entry.strip_prefix("bundle:") {
+            Some(bundle_id) => app.bundle_id.as_deref() == Some(bundle_id.trim()),
+            None => executable.contains(&entry.to_lowercase()),
+        }
+    })
+}
+
+// Accessibility settings as the OS reports them; `None` when unknown
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DetectedAccessibility {
+    pub reduced_motion: Option<bool>,
+    pub high_contrast: Option<bool>,
+}
+
+// What the frontend should honor
+#[derive(serde::Serialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct AccessibilityPrefs {
+    pub reduced_motion: bool,
+    pub high_contrast: bool,
+    pub screen_reader_mode: bool,
+}
+
+// Combine OS settings with the app's screen reader flag. Unknown settings are
+// off, except reduced motion, which screen reader users get by default.
+pub fn resolve_accessibility_prefs(detected: DetectedAccessibility, screen_reader_mode: bool) -> AccessibilityPrefs {
+    AccessibilityPrefs {
+        reduced_motion: detected.reduced_motion.unwrap_or(screen_reader_mode),
+        high_contrast: detected.high_contrast.unwrap_or(false),
+        screen_reader_mode,
+    }
+}
+
+// Windows: client area animations off means reduced motion
+#[cfg(windows)]
+pub fn detect_accessibility() -> DetectedAccessibility {
+    use windows_sys::Win32::UI::Accessibility::{HCF_HIGHCONTRASTON, HIGHCONTRASTW};
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        SystemParametersInfoW, SPI_GETCLIENTAREAANIMATION, SPI_GETHIGHCONTRAST,
+    };
+
+    unsafe {
+        let mut animations = 0i32;
+        let reduced_motion = (SystemParametersInfoW(
+            SPI_GETCLIENTAREAANIMATION,
+            0,
+            &mut animations as *mut i32 as *mut std::ffi::c_void,
+            0,
+        ) != 0)
+            .then_some(animations == 0);
+
+        let mut contrast: HIGHCONTRASTW = std::mem::zeroed();
+        contrast.cbSize = std::mem::size_of::<HIGHCONTRASTW>() as u32;
+        let high_contrast = (SystemParametersInfoW(
+            SPI_GETHIGHCONTRAST,
+            contrast.cbSize,
+            &mut contrast as *mut HIGHCONTRASTW as *mut std::ffi::c_void,
+            0,
+        ) != 0)
+            .then_some(contrast.dwFlags & HCF_HIGHCONTRASTON != 0);
+
+        DetectedAccessibility { reduced_motion, high_contrast }
+    }
+}
+
+// macOS: read the Accessibility > Display preferences
+#[cfg(target_os = "macos")]
+pub fn detect_accessibility() -> DetectedAccessibility {
+    let read_flag = |key: &str| {
+        let output = std::process::Command::new("defaults")
+            .args(["read", "com.apple.universalaccess", key])
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        Some(String::from_utf8_lossy(&output.stdout).trim() == "1")
+    };
+
+    DetectedAccessibility {
+        reduced_motion: read_flag("reduceMotion"),
+        high_contrast: read_flag("increaseContrast"),
+    }
+}
+
+// Linux: GNOME settings; other desktops report unknown
+#[cfg(target_os = "linux")]
+pub fn detect_accessibility() -> DetectedAccessibility {
+    let read_flag = |schema: &str, key: &str| {
+        let output = std::process::Command::new("gsettings")
+            .args(["get", schema, key])
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        Some(String::from_utf8_lossy(&output.stdout).trim() == "true")
+    };
+
+    DetectedAccessibility {
+        reduced_motion: read_flag("org.gnome.desktop.interface", "enable-animations").map(|enabled| !enabled),
+        high_contrast: read_flag("org.gnome.desktop.a11y.interface", "high-contrast"),
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+pub fn detect_accessibility() -> DetectedAccessibility {
+    DetectedAccessibility::default()
+}
+
+// Windows: Explorer broadcasts "TaskbarCreated" when it (re)starts, after which
+// tray icons have to be added again. Broadcasts only reach top-level windows,
+// so a hidden one is created on its own thread to listen for it.
+#[cfg(windows)]
+pub fn watch_taskbar_created(on_created: impl Fn() + Send + Sync + 'static) {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::OnceLock;
+    use windows_sys::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
+    use windows_sys::Win32::System::LibraryLoader::GetModuleHandleW;
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, RegisterClassW, RegisterWindowMessageW,
+        MSG, WNDCLASSW, WS_OVERLAPPED,
+    };
+
+    static TASKBAR_CREATED: AtomicU32 = AtomicU32::new(0);
+    static CALLBACK: OnceLock<Box<dyn Fn() + Send + Sync>> = OnceLock::new();
+
+    unsafe extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+        let taskbar_created = TASKBAR_CREATED.load(Ordering::Relaxed);
+        if taskbar_created != 0 && msg == taskbar_created {
+            if let Some(callback) = CALLBACK.get() {
+                callback();
+            }
+            return 0;
+        }
+        DefWindowProcW(hwnd, msg, wparam, lparam)
+    }
+
+    if CALLBACK.set(Box::new(on_created)).is_err() {
+        return;
+    }
+
+    std::thread::spawn(|| unsafe {
+        let wide = |text: &str| text.encode_utf16().chain(std::iter::once(0)).collect::<Vec<u16>>();
+        let message_name = wide("TaskbarCreated");
+        let class_name = wide("NotionQuickNotesTaskbarWatcher");
+
+        TASKBAR_CREATED.store(RegisterWindowMessageW(message_name.as_ptr()), Ordering::Relaxed);
+
+        let instance = GetModuleHandleW(std::ptr::null());
+        let mut class: WNDCLASSW = std::mem::zeroed();
+        class.lpfnWndProc = Some(window_proc);
+        class.hInstance = instance;
+        class.lpszClassName = class_name.as_ptr();
+        if RegisterClassW(&class) == 0 {
+            eprintln!("Failed to register the taskbar watcher window class");
+            return;
+        }
+
+        let hwnd = CreateWindowExW(
+            0,
+            class_name.as_ptr(),
+            class_name.as_ptr(),
+            WS_OVERLAPPED,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            instance,
+            std::ptr::null(),
+        );
+        if hwnd == 0 {
+            eprintln!("Failed to create the taskbar watcher window");
+            return;
+        }
+
+        let mut msg: MSG = std::mem::zeroed();
+        while GetMessageW(&mut msg, 0, 0, 0) > 0 {
+            DispatchMessageW(&msg);
+        }
+    });
+}
+
+// Other platforms keep their tray icons across shell restarts
+#[cfg(not(windows))]
+pub fn watch_taskbar_created(_on_created: impl Fn() + Send + Sync + 'static) {}
+
+// Suspend/resume, detected the same way everywhere: a ticker compares how much
+// wall-clock time passed against how much it expected. Monotonic clocks stop
+// during sleep on Linux and macOS and keep going on Windows, and timers fire
+// late either way, so a wall-clock gap well past the tick is the one signal
+// that holds on every OS. A manual clock change forward looks the same, which
+// is harmless: resume handling only re-checks state.
+pub struct SuspendDetector {
+    last_wall: chrono::DateTime<chrono::Utc>,
+    last_monotonic: std::time::Instant,
+}
+
+// Gaps shorter than this are scheduling noise
+pub const SUSPEND_GAP_THRESHOLD: std::time::Duration = std::time::Duration::from_secs(15);
+
+impl SuspendDetector {
+    pub fn new(wall: chrono::DateTime<chrono::Utc>, monotonic: std::time::Instant) -> Self {
+        SuspendDetector { last_wall: wall, last_monotonic: monotonic }
+    }
+
+    // Called every `expected`; returns roughly how long the machine was asleep
+    pub fn observe(
+        &mut self,
+        wall: chrono::DateTime<chrono::Utc>,
+        monotonic: std::time::Instant,
+        expected: std::time::Duration,
+    ) -> Option<std::time::Duration> {
+        let wall_elapsed = (wall - self.last_wall).to_std().unwrap_or_default();
+        let monotonic_elapsed = monotonic.saturating_duration_since(self.last_monotonic);
+        self.last_wall = wall;
+        self.last_monotonic = monotonic;
+
+        let slept = wall_elapsed
+            .saturating_sub(monotonic_elapsed)
+            .max(wall_elapsed.saturating_sub(expected));
+        (slept > SUSPEND_GAP_THRESHOLD).then_some(slept)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app(executable: &str, bundle_id: Option<&str>) -> ForegroundApp {
+        ForegroundApp {
+            executable: executable.to_string(),
+            bundle_id: bundle_id.map(str::to_string),
+        }
+    }
+
+    fn exclusions(entries: &[&str]) -> Vec<String> {
+        entries.iter().map(|entry| entry.to_string()).collect()
+    }
+
+    #[test]
+    fn executable_names_match_case_insensitively() {
+        let excluded = exclusions(&["photoshop"]);
+        assert!(is_excluded_app(&app("Photoshop.exe", None), &excluded));
+        assert!(is_excluded_app(&app("PHOTOSHOP", None), &excluded));
+        assert!(!is_excluded_app(&app("Illustrator.exe", None), &excluded));
+        assert!(is_excluded_app(&app("photoshop.exe", None), &exclusions(&["  PhotoShop "])));
+    }
+
+    #[test]
+    fn entries_match_with_or_without_the_exe_suffix() {
+        assert!(is_excluded_app(&app("Code.exe", None), &exclusions(&["code"])));
+        assert!(is_excluded_app(&app("Code.exe", None), &exclusions(&["CODE.EXE"])));
+        // A substring of the name is enough, so "code" also covers "code-insiders"
+        assert!(is_excluded_app(&app("code-insiders", None), &exclusions(&["code"])));
+        assert!(!is_excluded_app(&app("code", None), &exclusions(&["code.exe"])));
+        assert_eq!(executable_name(r"C:\Program Files\Microsoft VS Code\Code.exe"), "Code.exe");
+        assert_eq!(executable_name("/usr/bin/kitty"), "kitty");
+        assert_eq!(executable_name("kitty"), "kitty");
+    }
+
+    #[test]
+    fn bundle_entries_match_the_bundle_id_exactly() {
+        let excluded = exclusions(&["bundle:com.adobe.Photoshop"]);
+        assert!(is_excluded_app(&app("Adobe Photoshop 2024", Some("com.adobe.Photoshop")), &excluded));
+        assert!(!is_excluded_app(&app("Adobe Photoshop 2024", Some("com.adobe.photoshop")), &excluded));
+        assert!(!is_excluded_app(&app("Adobe Photoshop 2024", Some("com.adobe.Photoshop.beta")), &excluded));
+        // Windows and Linux report no bundle id
+        assert!(!is_excluded_app(&app("com.adobe.Photoshop", None), &excluded));
+    }
+
+    #[test]
+    fn blank_entries_exclude_nothing() {
+        assert!(!is_excluded_app(&app("Code.exe", None), &exclusions(&["", "  ", "bundle:"])));
+        assert!(!is_excluded_app(&app("Code.exe", None), &[]));
+    }
+}
@@ -0,0 +1,225 @@
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::Instant;
+
+// Lightweight self-reporting for memory use and startup time, so reports of a
+// heavy tray app can be traced to a specific cache or a slow startup step
+
+// Approximate footprint of one cache or store
+#[derive(Serialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct MemoryUsage {
+    pub bytes: usize,
+    pub entries: usize,
+}
+
+// Implemented by every cache that wants to show up in the memory report
+pub trait MemoryReporter: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn usage(&self) -> MemoryUsage;
+    // Shed entries down to a floor; caches rebuild on demand
+    fn trim(&self);
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CacheReport {
+    pub name: String,
+    pub bytes: usize,
+    pub entries: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct MemoryReport {
+    // Resident set size of the whole process, when the platform exposes it
+    pub process_rss_bytes: Option<u64>,
+    pub caches: Vec<CacheReport>,
+}
+
+// A named point during startup, in milliseconds since the process started
+#[derive(Serialize, Debug, Clone)]
+pub struct StartupMark {
+    pub name: String,
+    pub elapsed_ms: u128,
+}
+
+lazy_static::lazy_static! {
+    static ref REPORTERS: Mutex<Vec<Box<dyn MemoryReporter>>> = Mutex::new(Vec::new());
+    static ref PROCESS_START: Instant = Instant::now();
+    static ref STARTUP_MARKS: Mutex<Vec<StartupMark>> = Mutex::new(Vec::new());
+}
+
+pub fn register_reporter(reporter: Box<dyn MemoryReporter>) {
+    let mut reporters = REPORTERS.lock().unwrap();
+    // Registering twice (e.g. after a window reload) must not double-count
+    if reporters.iter().any(|existing| existing.name() == reporter.name()) {
+        return;
+    }
+    reporters.push(reporter);
+}
+
+pub fn memory_report() -> MemoryReport {
+    let reporters = REPORTERS.lock().unwrap();
+    MemoryReport {
+        process_rss_bytes: process_rss_bytes(),
+        caches: reporters
+            .iter()
+            .map(|reporter| {
+                let usage = reporter.usage();
+                CacheReport {
+                    name: reporter.name().to_string(),
+                    bytes: usage.bytes,
+                    entries: usage.entries,
+                }
+            })
+            .collect(),
+    }
+}
+
+pub fn trim_all() {
+    for reporter in REPORTERS.lock().unwrap().iter() {
+        reporter.trim();
+    }
+}
+
+// Record that startup reached a step; the first call also pins the process start
+pub fn mark(name: &str) {
+    let elapsed_ms = PROCESS_START.elapsed().as_millis();
+    STARTUP_MARKS.lock().unwrap().push(StartupMark {
+        name: name.to_string(),
+        elapsed_ms,
+    });
+}
+
+pub fn startup_marks() -> Vec<StartupMark> {
+    STARTUP_MARKS.lock().unwrap().clone()
+}
+
+#[cfg(windows)]
+fn process_rss_bytes() -> Option<u64> {
+    use windows_sys::Win32::System::ProcessStatus::{K32GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS};
+    use windows_sys::Win32::System::Threading::GetCurrentProcess;
+
+    let size = std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32;
+    let mut counters: PROCESS_MEMORY_COUNTERS = unsafe { std::mem::zeroed() };
+    counters.cb = size;
+    let ok = unsafe { K32GetProcessMemoryInfo(GetCurrentProcess(), &mut counters, size) };
+    (ok != 0).then_some(counters.WorkingSetSize as u64)
+}
+
+#[cfg(target_os = "linux")]
+fn process_rss_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+#[cfg(target_os = "macos")]
+fn process_rss_bytes() -> Option<u64> {
+    let output = std::process::Command::new("ps")
+        .args(["-o", "rss=", "-p", &std::process::id().to_string()])
+        .output()
+        .ok()?;
+    let kb: u64 = String::from_utf8_lossy(&output.stdout).trim().parse().ok()?;
+    Some(kb * 1024)
+}
+
+#[cfg(not(any(windows, target_os = "linux", target_os = "macos")))]
+fn process_rss_bytes() -> Option<u64> {
+    None
+}
+
+#[tauri::command]
+pub fn get_memory_report() -> MemoryReport {
+    memory_report()
+}
+
+// Ask every cache to shed what it can; returns the report afterwards
+#[tauri::command]
+pub fn trim_memory() -> MemoryReport {
+    trim_all();
+    memory_report()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    // A cache of fixed-size entries that trims down to `floor` of them
+    struct FakeCache {
+        name: &'static str,
+        entries: Arc<Mutex<Vec<[u8; 64]>>>,
+        floor: usize,
+    }
+
+    impl MemoryReporter for FakeCache {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn usage(&self) -> MemoryUsage {
+            let entries = self.entries.lock().unwrap().len();
+            MemoryUsage {
+                bytes: entries * 64,
+                entries,
+            }
+        }
+
+        fn trim(&self) {
+            self.entries.lock().unwrap().truncate(self.floor);
+        }
+    }
+
+    // Registers a populated fake cache and returns its entries
+    fn register_fake(name: &'static str, entries: usize, floor: usize) -> Arc<Mutex<Vec<[u8; 64]>>> {
+        let shared = Arc::new(Mutex::new(vec![[0; 64]; entries]));
+        register_reporter(Box::new(FakeCache { name, entries: shared.clone(), floor }));
+        shared
+    }
+
+    fn reported(name: &str) -> Vec<CacheReport> {
+        memory_report().caches.into_iter().filter(|cache| cache.name == name).collect()
+    }
+
+    #[test]
+    fn registered_reporters_show_up_once_in_the_report() {
+        register_fake("test: page cache", 10, 10);
+        // A second registration under the same name is ignored
+        register_fake("test: page cache", 99, 0);
+
+        let caches = reported("test: page cache");
+        assert_eq!(caches.len(), 1);
+        assert_eq!((caches[0].entries, caches[0].bytes), (10, 640));
+        assert!(reported("test: never registered").is_empty());
+    }
+
+    #[test]
+    fn trimming_sheds_down_to_each_cache_floor() {
+        let icons = register_fake("test: icon cache", 50, 0);
+        register_fake("test: history aggregates", 30, 5);
+
+        let report = trim_memory().caches;
+        let usage = |name: &str| report.iter().find(|cache| cache.name == name).map(|cache| (cache.entries, cache.bytes));
+        assert_eq!(usage("test: icon cache"), Some((0, 0)));
+        assert_eq!(usage("test: history aggregates"), Some((5, 320)));
+
+        // Caches fill up again on demand and are reported as they are
+        icons.lock().unwrap().push([0; 64]);
+        assert_eq!(reported("test: icon cache")[0].entries, 1);
+    }
+
+    #[test]
+    fn startup_marks_are_kept_in_order() {
+        mark("test: config loaded");
+        mark("test: tray ready");
+        let marks: Vec<StartupMark> = startup_marks().into_iter().filter(|mark| mark.name.starts_with("test: ")).collect();
+        assert_eq!(marks.iter().map(|mark| mark.name.as_str()).collect::<Vec<_>>(), vec!["test: config loaded", "test: tray ready"]);
+        assert!(marks[0].elapsed_ms <= marks[1].elapsed_ms);
+    }
+
+    #[cfg(any(windows, target_os = "linux", target_os = "macos"))]
+    #[test]
+    fn process_rss_is_reported() {
+        assert!(memory_report().process_rss_bytes.is_some_and(|bytes| bytes > 0));
+    }
+}
@@ -0,0 +1,1383 @@
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::anchor;
+use crate::auth;
+use crate::blocks::{self, NoteBlockType, NoteFormat, NoteStyle};
+use crate::capture::{
+    prepare_note_text, resolve_capture_target, resolve_destination_plan, split_leading_timestamp, DestinationContext,
+    DestinationRule,
+};
+use crate::clock;
+use crate::webhook;
+use crate::config::{app_data_path, AppState, TargetKind};
+use crate::daily_pages::{self, DailyTarget};
+use crate::database_rows;
+use crate::day_headings;
+use crate::local_crypto;
+use crate::notion::{AppendFailure, AppendResponse, AppendRetry, NotionApiClient};
+use crate::events;
+use crate::notion::MAX_CHILDREN_PER_REQUEST;
+use crate::rate_limit::{Priority, RateLimitSource, BACKGROUND_RATE_FRACTION, RATE_LIMITER, REQUEST_SPACING};
+use crate::reliability;
+use crate::receipts::{self, DeliveryReceipt, DeliverySource, NoteOrigin};
+use crate::retry::QUEUE_RETRY;
+use crate::safe_mode::{self, Subsystem};
+use crate::suspend;
+use crate::timestamps::{self, TimestampOptions};
+
+// Notes captured while offline wait here until they can be delivered.
+// Delivery is two-phase so a crash mid-flush never causes a duplicate block:
+// an item is persisted as in-flight before it is sent, and only removed after
+// its receipt is written. In-flight items found at startup are checked
+// against the page before being resent.
+const QUEUE_FILE: &str = "queue.json";
+
+// How often the background task tries to deliver queued notes
+const QUEUE_FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+
+// Lifecycle of a queued note:
+//   pending -> in_flight -> delivered (removed) / failed
+//   failed -> in_flight (retry) or dead (out of attempts)
+//   in_flight -> pending (startup found it didn't land, or the token was revoked)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueItemState {
+    Pending,
+    InFlight,
+    Delivered,
+    Failed,
+    Dead,
+}
+
+impl QueueItemState {
+    pub fn can_transition(self, to: QueueItemState) -> bool {
+        use QueueItemState::*;
+        matches!(
+            (self, to),
+            (Pending, InFlight)
+                | (InFlight, Delivered)
+                | (InFlight, Failed)
+                | (InFlight, Pending)
+                | (Failed, InFlight)
+                | (Failed, Dead)
+        )
+    }
+
+    // Items in these states are picked up by the next flush
+    pub fn is_sendable(self) -> bool {
+        matches!(self, QueueItemState::Pending | QueueItemState::Failed)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct QueuedNote {
+    // Doubles as the capture id of the eventual receipt
+    pub id: String,
+    pub profile: Option<String>,
+    pub page_id: String,
+    pub page_title: String,
+    // Built at capture time so resends are byte-identical and can be recognised on the page
+    pub children: Vec<Value>,
+    pub state: QueueItemState,
+    pub attempts: u32,
+    pub queued_at: String,
+    // Set when a leading timestamp in the note overrode the capture time
+    #[serde(default)]
+    pub captured_at: Option<String>,
+    #[serde(default)]
+    pub source: DeliverySource,
+    // Waiting for a new API token after Notion revoked the old one; not sent until then
+    #[serde(default)]
+    pub blocked_on_auth: bool,
+    // The prepared note text, for the mirror webhook; None for raw blocks
+    #[serde(default)]
+    pub note_text: Option<String>,
+    // RFC3339 time before which a failed note isn't resent (see retry::QUEUE_RETRY)
+    #[serde(default)]
+    pub retry_at: Option<String>,
+    // A send was cut off by a suspend and may have landed; look on the page before resending
+    #[serde(default)]
+    pub check_before_send: bool,
+    // The day heading the note goes under, when day headings were on at capture
+    #[serde(default)]
+    pub day_heading: Option<String>,
+    // In daily page mode, the day's page; `page_id` is filled in when it is sent
+    #[serde(default)]
+    pub daily: Option<DailyTarget>,
+    // A database target gets a new row from note_text instead of the blocks
+    #[serde(default)]
+    pub target_kind: TargetKind,
+    // The rest of a partly delivered note goes right after the block that landed last
+    #[serde(default)]
+    pub after: Option<String>,
+    pub last_error: Option<String>,
+}
+
+impl QueuedNote {
+    // The note as written to disk: its blocks and text sealed when local encryption is on
+    fn sealed(&self) -> Result<QueuedNote, String> {
+        Ok(QueuedNote {
+            children: self.children.iter().map(local_crypto::seal_value).collect::<Result<_, _>>()?,
+            note_text: local_crypto::seal_optional(&self.note_text)?,
+            ..self.clone()
+        })
+    }
+
+    fn opened(self) -> Result<QueuedNote, String> {
+        Ok(QueuedNote {
+            children: self.children.iter().map(local_crypto::open_value).collect::<Result<_, _>>()?,
+            note_text: local_crypto::open_optional(&self.note_text)?,
+            ..self
+        })
+    }
+
+    fn transition(&mut self, to: QueueItemState) -> Result<(), String> {
+        if !self.state.can_transition(to) {
+            return Err(format!("Queued note {} can't go from {:?} to {:?}", self.id, self.state, to));
+        }
+        self.state = to;
+        Ok(())
+    }
+}
+
+lazy_static::lazy_static! {
+    // Serializes access to the queue file
+    static ref QUEUE_LOCK: Mutex<()> = Mutex::new(());
+    // Only one flush may run at a time
+    static ref FLUSH_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::new(());
+    // When the background task flushes next; None while a flush is due or running
+    static ref NEXT_FLUSH_AT: Mutex<Option<DateTime<Utc>>> = Mutex::new(None);
+}
+
+fn read_queue() -> Result<Vec<QueuedNote>, String> {
+    let path = app_data_path(QUEUE_FILE)?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let contents = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read queue: {}", e))?;
+    let items: Vec<QueuedNote> = serde_json::from_str(&contents).map_err(|e| format!("Failed to parse queue: {}", e))?;
+    items.into_iter().map(QueuedNote::opened).collect()
+}
+
+// Write to a temporary file and rename so a crash never leaves a half-written queue
+fn write_queue(items: &[QueuedNote]) -> Result<(), String> {
+    let path = app_data_path(QUEUE_FILE)?;
+    let temp_path = path.with_extension("json.tmp");
+    let sealed = items.iter().map(QueuedNote::sealed).collect::<Result<Vec<_>, _>>()?;
+    let contents = serde_json::to_string_pretty(&sealed)
+        .map_err(|e| format!("Failed to serialize queue: {}", e))?;
+    fs::write(&temp_path, contents).map_err(|e| format!("Failed to write queue: {}", e))?;
+    fs::rename(&temp_path, &path).map_err(|e| format!("Failed to write queue: {}", e))
+}
+
+fn modify_queue<T>(change: impl FnOnce(&mut Vec<QueuedNote>) -> Result<T, String>) -> Result<T, String> {
+    let _lock = QUEUE_LOCK.lock().unwrap();
+    let mut items = read_queue()?;
+    let result = change(&mut items)?;
+    write_queue(&items)?;
+    Ok(result)
+}
+
+// Rewrite the queue file in the current encryption mode
+pub fn reseal() -> Result<(), String> {
+    modify_queue(|_| Ok(()))
+}
+
+pub fn load_queue() -> Result<Vec<QueuedNote>, String> {
+    let _lock = QUEUE_LOCK.lock().unwrap();
+    read_queue()
+}
+
+// Move one item to a new state, persisting the change
+fn transition(id: &str, to: QueueItemState, error: Option<String>) -> Result<(), String> {
+    modify_queue(|items| {
+        let item = items.iter_mut()
+            .find(|item| item.id == id)
+            .ok_or_else(|| format!("Queued note {} not found", id))?;
+        item.transition(to)?;
+        if to == QueueItemState::InFlight {
+            item.attempts += 1;
+        }
+        if error.is_some() {
+            item.last_error = error;
+        }
+        Ok(())
+    })
+}
+
+// Delivered: the receipt is written first, then the item removed. A crash in
+// between leaves an in-flight item whose receipt already exists, which
+// recovery treats as delivered.
+fn complete(item: &QueuedNote, receipt: &DeliveryReceipt) -> Result<(), String> {
+    if let Err(e) = receipts::record_receipt(receipt) {
+        eprintln!("Failed to record delivery receipt: {}", e);
+    }
+    reliability::record_delivery_since(&item.queued_at);
+    modify_queue(|items| {
+        if let Some(queued) = items.iter_mut().find(|queued| queued.id == item.id) {
+            queued.transition(QueueItemState::Delivered)?;
+        }
+        items.retain(|queued| queued.state != QueueItemState::Delivered);
+        Ok(())
+    })
+}
+
+// Sent with a token Notion has since revoked: back to pending until a new token verifies
+fn block_on_auth(id: &str) -> Result<(), String> {
+    modify_queue(|items| {
+        let item = items.iter_mut()
+            .find(|item| item.id == id)
+            .ok_or_else(|| format!("Queued note {} not found", id))?;
+        item.transition(QueueItemState::Pending)?;
+        item.attempts = item.attempts.saturating_sub(1);
+        item.blocked_on_auth = true;
+        Ok(())
+    })
+}
+
+// Cut off by a suspend: back to pending without using up an attempt, checked
+// against the page before it is sent again
+fn park_after_suspend(id: &str, error: String) -> Result<(), String> {
+    modify_queue(|items| {
+        let item = items.iter_mut()
+            .find(|item| item.id == id)
+            .ok_or_else(|| format!("Queued note {} not found", id))?;
+        item.transition(QueueItemState::Pending)?;
+        item.check_before_send = true;
+        item.last_error = Some(error);
+        Ok(())
+    })
+}
+
+// Release every note waiting on re-authentication; returns how many were released
+pub fn unblock_auth() -> Result<usize, String> {
+    modify_queue(|items| {
+        let mut released = 0;
+        for item in items.iter_mut().filter(|item| item.blocked_on_auth) {
+            item.blocked_on_auth = false;
+            released += 1;
+        }
+        Ok(released)
+    })
+}
+
+// After a failure: back off for another try, or give up once out of attempts.
+// `item` was read before the send, so it doesn't count the attempt that just failed.
+fn fail(item: &QueuedNote, error: String) -> Result<(), String> {
+    let attempts = item.attempts + 1;
+    let retry = QUEUE_RETRY.should_retry(attempts, &error);
+    transition(&item.id, QueueItemState::Failed, Some(error))?;
+    if !retry {
+        reliability::record_failure();
+        return transition(&item.id, QueueItemState::Dead, None);
+    }
+
+    let delay = QUEUE_RETRY.delay_after(attempts, (QUEUE_RETRY.jitter_source)());
+    let retry_at = chrono::Utc::now() + chrono::Duration::from_std(delay).unwrap_or_default();
+    modify_queue(|items| {
+        if let Some(queued) = items.iter_mut().find(|queued| queued.id == item.id) {
+            queued.retry_at = Some(retry_at.to_rfc3339());
+        }
+        Ok(())
+    })
+}
+
+// Still backing off from an earlier failure
+fn is_backing_off(item: &QueuedNote, now: chrono::DateTime<chrono::Utc>) -> bool {
+    item.retry_at
+        .as_deref()
+        .and_then(|retry_at| chrono::DateTime::parse_from_rfc3339(retry_at).ok())
+        .is_some_and(|retry_at| now < retry_at)
+}
+
+fn api_token_for(app: &AppHandle, profile: Option<&str>) -> Result<String, String> {
+    let state = app.state::<AppState>();
+    let config = state.config.lock().unwrap();
+    resolve_capture_target(&config, profile).map(|target| target.api_token)
+}
+
+// Add a note to the queue, building its blocks now so the capture time is kept
+pub fn enqueue(app: &AppHandle, note_text: &str, profile: Option<String>, style: NoteStyle) -> Result<QueuedNote, String> {
+    enqueue_at(app, note_text, profile, None, style)
+}
+
+// As `enqueue`, for a note captured earlier: `captured_at` stands in for the
+// capture time unless the note has its own leading timestamp
+pub fn enqueue_at(
+    app: &AppHandle,
+    note_text: &str,
+    profile: Option<String>,
+    captured_at: Option<DateTime<Local>>,
+    style: NoteStyle,
+) -> Result<QueuedNote, String> {
+    let default_captured_at = captured_at;
+    let now = clock::now();
+    let (profile, target, blocked_on_auth, captured_at, note_text, children, day_heading) = {
+        let state = app.state::<AppState>();
+        let config = state.config.lock().unwrap();
+        let context = DestinationContext {
+            pinned_profile: profile,
+            ..Default::default()
+        };
+        let destination = resolve_destination_plan(&config, note_text, &context)?;
+        let note_text = destination.note_text.as_str();
+        let profile = destination.profile;
+        let mut target = resolve_capture_target(&config, profile.as_deref())?;
+        // Sandbox mode swaps the page but keeps the token
+        target.page_id = destination.page_id.clone();
+        target.page_title = destination.page_title.clone();
+        if destination.rule == DestinationRule::Sandbox {
+            target.daily = None;
+            target.kind = TargetKind::Page;
+        }
+        let blocked_on_auth = auth::token_blocked(&config, &target.api_token);
+        let (captured_at, note_text) = split_leading_timestamp(config.leading_timestamps, note_text);
+        let captured_at = captured_at.or(default_captured_at);
+        let note_text = prepare_note_text(&config, note_text);
+        let layout = blocks::choose_layout(config.text_direction, config.rtl_timestamp_layout, &note_text);
+        let style = NoteStyle {
+            block_type: Some(style.block_type.unwrap_or(config.note_block_type)),
+            toggle_preview: config.toggle_preview,
+            bold: config.note_bold,
+            color: blocks::note_color(&config.note_color),
+            tag_color: config.highlight_hashtags.then(|| blocks::note_color(&config.hashtag_color)),
+            ..style
+        };
+        let timestamp_options = TimestampOptions::from_config(&config);
+        let timestamp = captured_at.unwrap_or(now);
+        let children = blocks::build_children(
+            &timestamps::format_timestamp(&timestamp, &timestamp_options),
+            &note_text,
+            layout,
+            style,
+            config.split_paragraphs,
+        );
+        let day_heading = config
+            .day_headings
+            .then(|| day_headings::heading_text(timestamps::day_of(&timestamp, &timestamp_options)));
+        (profile, target, blocked_on_auth, captured_at, note_text, children, day_heading)
+    };
+
+    let item = QueuedNote {
+        id: receipts::new_capture_id(),
+        profile,
+        page_id: target.page_id,
+        page_title: target.page_title,
+        children,
+        state: QueueItemState::Pending,
+        attempts: 0,
+        queued_at: now.to_rfc3339(),
+        captured_at: captured_at.map(|time| time.to_rfc3339()),
+        source: DeliverySource::Note,
+        blocked_on_auth,
+        note_text: Some(note_text),
+        retry_at: None,
+        check_before_send: false,
+        day_heading,
+        daily: target.daily,
+        target_kind: target.kind,
+        after: None,
+        last_error: None,
+    };
+    let item = push(item)?;
+    publish_estimates(app);
+    Ok(item)
+}
+
+// A direct capture cut off by a suspend: queue it, marked so the flush first
+// checks whether the interrupted send landed after all
+pub fn enqueue_interrupted(
+    app: &AppHandle,
+    note_text: &str,
+    profile: Option<String>,
+    captured_at: DateTime<Local>,
+    style: NoteStyle,
+) -> Result<QueuedNote, String> {
+    let item = enqueue_at(app, note_text, profile, Some(captured_at), style)?;
+    modify_queue(|items| {
+        if let Some(queued) = items.iter_mut().find(|queued| queued.id == item.id) {
+            queued.check_before_send = true;
+        }
+        Ok(())
+    })?;
+    Ok(item)
+}
+
+// Queue already-built blocks for a page, e.g. raw blocks sent while offline
+pub fn enqueue_blocks(
+    page_id: String,
+    page_title: String,
+    children: Vec<Value>,
+    source: DeliverySource,
+    daily: Option<DailyTarget>,
+) -> Result<QueuedNote, String> {
+    push(blocks_item(page_id, page_title, children, source, daily))
+}
+
+fn blocks_item(
+    page_id: String,
+    page_title: String,
+    children: Vec<Value>,
+    source: DeliverySource,
+    daily: Option<DailyTarget>,
+) -> QueuedNote {
+    QueuedNote {
+        id: receipts::new_capture_id(),
+        profile: None,
+        page_id,
+        page_title,
+        children,
+        state: QueueItemState::Pending,
+        attempts: 0,
+        queued_at: clock::now().to_rfc3339(),
+        captured_at: None,
+        source,
+        blocked_on_auth: false,
+        note_text: None,
+        retry_at: None,
+        check_before_send: false,
+        day_heading: None,
+        daily,
+        target_kind: TargetKind::Page,
+        after: None,
+        last_error: None,
+    }
+}
+
+// The chunks of a direct capture that didn't land after earlier ones did, queued
+// for the same page and profile to follow the last delivered block
+pub fn enqueue_remainder(
+    profile: Option<String>,
+    page_id: String,
+    page_title: String,
+    children: Vec<Value>,
+    day_heading: Option<String>,
+    after: Option<String>,
+) -> Result<QueuedNote, String> {
+    push(QueuedNote {
+        profile,
+        day_heading,
+        after,
+        ..blocks_item(page_id, page_title, children, DeliverySource::Note, None)
+    })
+}
+
+fn push(item: QueuedNote) -> Result<QueuedNote, String> {
+    modify_queue(|items| {
+        items.push(item.clone());
+        Ok(())
+    })?;
+    Ok(item)
+}
+
+// Settle items left in flight by a previous run: a receipt or a matching block
+// on the page means it landed; otherwise it goes back to pending and is resent
+pub async fn recover_in_flight(app: &AppHandle) -> Result<(), String> {
+    let in_flight: Vec<QueuedNote> = load_queue()?
+        .into_iter()
+        .filter(|item| item.state == QueueItemState::InFlight)
+        .collect();
+
+    for item in in_flight {
+        if let Some(receipt) = receipts::find_receipt(&item.id)? {
+            complete(&item, &receipt)?;
+            continue;
+        }
+
+        let client = NotionApiClient::new(api_token_for(app, item.profile.as_deref())?)?
+            .with_priority(Priority::Background);
+        settle_in_flight(&client, &item, landing_after(app, &item).as_deref()).await?;
+    }
+    Ok(())
+}
+
+// The block a page item is appended after: the one it continues, else the
+// page's anchor. Day headings place notes themselves.
+fn landing_after(app: &AppHandle, item: &QueuedNote) -> Option<String> {
+    if item.day_heading.is_some() {
+        return None;
+    }
+    item.after.clone().or_else(|| anchor::configured(app, &item.page_id))
+}
+
+// An in-flight item without a receipt: blocks matching it on the page (right
+// after `after`, when it was sent there) mean the send landed before the app
+// stopped, so it's completed instead of sent again
+async fn settle_in_flight(client: &NotionApiClient, item: &QueuedNote, after: Option<&str>) -> Result<(), String> {
+    match client.find_appended_blocks(&item.page_id, &Value::Array(item.children.clone()), after).await? {
+        Some(block_ids) => {
+            println!("Queued note {} had already landed before the restart", item.id);
+            let receipt = DeliveryReceipt {
+                capture_id: item.id.clone(),
+                page_id: item.page_id.clone(),
+                block_ids,
+                request_id: None,
+                status: 200,
+                delivered_at: chrono::Utc::now().to_rfc3339(),
+                attempts: item.attempts,
+                partial: None,
+                captured_at: item.captured_at.clone(),
+                source: item.source,
+                moved_from: None,
+                move_incomplete: false,
+                focus_label: None,
+                note_text: item.note_text.clone(),
+                origin: Some(NoteOrigin::Queue),
+                profile: item.profile.clone(),
+            };
+            complete(item, &receipt)
+        }
+        None => transition(&item.id, QueueItemState::Pending, None),
+    }
+}
+
+// Notes that can share an append request with their neighbours: plain blocks
+// at the end of a fixed page, with nothing to look up or check first
+fn batchable(item: &QueuedNote) -> bool {
+    item.target_kind == TargetKind::Page
+        && item.daily.is_none()
+        && item.day_heading.is_none()
+        && !item.check_before_send
+        && !item.blocked_on_auth
+        && item.after.is_none()
+        && item.children.len() < MAX_CHILDREN_PER_REQUEST
+}
+
+// Whether `next` can go in the same request as a batch that starts with
+// `first` and holds `size` blocks so far
+fn joins_batch(first: &QueuedNote, size: usize, next: &QueuedNote, now: DateTime<Utc>) -> bool {
+    batchable(first)
+        && batchable(next)
+        && next.page_id == first.page_id
+        && next.profile == first.profile
+        && !is_backing_off(next, now)
+        && size + next.children.len() <= MAX_CHILDREN_PER_REQUEST
+}
+
+// The receipt for a queued note Notion confirmed
+fn queue_receipt(item: &QueuedNote, response: AppendResponse) -> DeliveryReceipt {
+    let mut receipt = DeliveryReceipt::new(item.id.clone(), item.page_id.clone(), response, None);
+    receipt.captured_at = item.captured_at.clone();
+    receipt.source = item.source;
+    receipt.note_text = item.note_text.clone();
+    receipt.origin = Some(NoteOrigin::Queue);
+    receipt.profile = item.profile.clone();
+    receipt
+}
+
+// Record a delivered note's receipt, remove it from the queue and mirror it
+fn deliver(app: &AppHandle, item: &QueuedNote, response: AppendResponse) -> Result<(), String> {
+    let receipt = queue_receipt(item, response);
+    complete(item, &receipt)?;
+    if let Some(text) = item.note_text.clone() {
+        webhook::mirror_delivery(app, webhook::MirrorPayload {
+            text,
+            captured_at: item.captured_at.clone().unwrap_or_else(|| item.queued_at.clone()),
+            page_id: receipt.page_id,
+            page_title: item.page_title.clone(),
+            source: item.source,
+            block_ids: receipt.block_ids,
+        });
+    }
+    Ok(())
+}
+
+// A send that landed nothing. A revoked token isn't the note's fault: park it
+// without using up an attempt, as with a send cut off by a suspend.
+fn settle_failure(app: &AppHandle, item: &QueuedNote, api_token: &str, started_epoch: u64, error: String) -> Result<(), String> {
+    if auth::blocks_token(app, api_token) {
+        block_on_auth(&item.id)
+    } else if suspend::interrupted(started_epoch, std::time::Instant::now()) {
+        park_after_suspend(&item.id, error)
+    } else {
+        fail(item, error)
+    }
+}
+
+// Send a run of notes for the same page as one request and
+// give each its own receipt with the blocks that are its. The request lands
+// all of them or none, so after a failure every note of the batch stays
+// queued. Returns whether the batch was delivered.
+async fn flush_batch(app: &AppHandle, batch: &[QueuedNote]) -> Result<bool, String> {
+    let first = &batch[0];
+    let api_token = api_token_for(app, first.profile.as_deref())?;
+    let client = NotionApiClient::new(api_token.clone())?
+        .with_priority(Priority::Background);
+
+    for item in batch {
+        transition(&item.id, QueueItemState::InFlight, None)?;
+    }
+    let started_epoch = suspend::epoch();
+
+    // After an anchor the newest note goes first, as when the notes are sent one by one
+    let anchor = anchor::configured(app, &first.page_id);
+    let mut order: Vec<&QueuedNote> = batch.iter().collect();
+    if anchor.is_some() {
+        order.reverse();
+    }
+    let children: Vec<Value> = order.iter().flat_map(|item| item.children.iter().cloned()).collect();
+    match anchor::append(app, &client, &first.page_id, &first.page_title, None, &children, anchor.as_deref()).await {
+        Ok(response) => {
+            let AppendResponse { block_ids, request_id, status, attempts } = response;
+            let mut block_ids = block_ids.into_iter();
+            for item in order {
+                let response = AppendResponse {
+                    block_ids: block_ids.by_ref().take(item.children.len()).collect(),
+                    request_id: request_id.clone(),
+                    status,
+                    attempts,
+                };
+                deliver(app, item, response)?;
+            }
+            println!("Sent {} queued notes in one request", batch.len());
+            Ok(true)
+        }
+        Err(failure) => {
+            let error: String = failure.into();
+            for item in batch {
+                settle_failure(app, item, &api_token, started_epoch, error.clone())?;
+            }
+            Ok(false)
+        }
+    }
+}
+
+// Deliver queued notes oldest first; stops at the first failure to keep order.
+// Consecutive notes for the same page go out together (see flush_batch).
+// Returns how many were delivered.
+pub async fn flush(app: &AppHandle) -> Result<usize, String> {
+    // Queued notes stay on disk until safe mode is exited
+    if !safe_mode::enabled(Subsystem::QueueFlush) {
+        return Ok(0);
+    }
+    let _flushing = FLUSH_LOCK.lock().await;
+
+    let mut sendable = load_queue()?
+        .into_iter()
+        .filter(|item| item.state.is_sendable() && !item.blocked_on_auth)
+        .peekable();
+
+    let mut delivered = 0;
+    while let Some(mut item) = sendable.next() {
+        let now = chrono::Utc::now();
+        // Later notes wait too, to keep delivery in order
+        if is_backing_off(&item, now) {
+            break;
+        }
+
+        let mut batch = vec![item.clone()];
+        let mut size = item.children.len();
+        while let Some(next) = sendable.next_if(|next| joins_batch(&item, size, next, now)) {
+            size += next.children.len();
+            batch.push(next);
+        }
+        if batch.len() > 1 {
+            if !flush_batch(app, &batch).await? {
+                break;
+            }
+            delivered += batch.len();
+            continue;
+        }
+
+        let api_token = api_token_for(app, item.profile.as_deref())?;
+        let client = NotionApiClient::new(api_token.clone())?
+            .with_priority(Priority::Background);
+
+        // Phase one: persist that this item is about to be sent
+        transition(&item.id, QueueItemState::InFlight, None)?;
+        let started_epoch = suspend::epoch();
+
+        // A daily page is looked up, or created, when its first note goes out
+        if let Some(daily) = &item.daily {
+            match daily_pages::resolve(&client, daily).await {
+                Ok(page_id) => item.page_id = page_id,
+                Err(e) => {
+                    if auth::blocks_token(app, &api_token) {
+                        block_on_auth(&item.id)?;
+                    } else {
+                        fail(&item, e)?;
+                    }
+                    break;
+                }
+            }
+        }
+
+        // Rows in a database can't be found among page children, so they're sent again
+        let after = landing_after(app, &item);
+        let landed = if item.check_before_send && item.target_kind == TargetKind::Page {
+            match client.find_appended_blocks(&item.page_id, &Value::Array(item.children.clone()), after.as_deref()).await {
+                Ok(landed) => landed,
+                Err(e) => {
+                    // Still can't tell whether it landed; try again on the next flush
+                    park_after_suspend(&item.id, e)?;
+                    break;
+                }
+            }
+        } else {
+            None
+        };
+        let result = match landed {
+            Some(block_ids) => Ok(AppendResponse {
+                block_ids,
+                request_id: None,
+                status: 200,
+                attempts: item.attempts,
+            }),
+            None if item.target_kind == TargetKind::Database => {
+                let captured_at = item.captured_at.as_deref().unwrap_or(&item.queued_at);
+                let captured_at = DateTime::parse_from_rfc3339(captured_at)
+                    .map(|time| time.with_timezone(&Local))
+                    .unwrap_or_else(|_| clock::now());
+                let note_text = item.note_text.as_deref().unwrap_or_default();
+                database_rows::create_row(&client, &item.page_id, note_text, &captured_at).await
+            }
+            None => {
+                anchor::append(app, &client, &item.page_id, &item.page_title, item.day_heading.as_deref(), &item.children, after.as_deref())
+                    .await
+            }
+        };
+        match result {
+            Ok(response) => {
+                deliver(app, &item, response)?;
+                delivered += 1;
+            }
+            Err(AppendFailure::Partial(partial)) => {
+                // Keep only what didn't land so the retry doesn't duplicate the rest
+                let landed = partial.delivered.block_ids.len();
+                let last = partial.delivered.block_ids.last().cloned();
+                let mut receipt = DeliveryReceipt::new(item.id.clone(), item.page_id.clone(), partial.delivered.clone(), Some(&partial));
+                receipt.source = item.source;
+                receipt.note_text = item.note_text.clone();
+                receipt.origin = Some(NoteOrigin::Queue);
+                receipt.profile = item.profile.clone();
+                if let Err(e) = receipts::record_receipt(&receipt) {
+                    eprintln!("Failed to record delivery receipt: {}", e);
+                }
+                modify_queue(|items| {
+                    if let Some(queued) = items.iter_mut().find(|queued| queued.id == item.id) {
+                        queued.children.drain(..landed.min(queued.children.len()));
+                        queued.after = last.or(queued.after.take());
+                    }
+                    Ok(())
+                })?;
+                fail(&item, partial.error)?;
+                break;
+            }
+            Err(failure) => {
+                settle_failure(app, &item, &api_token, started_epoch, failure.into())?;
+                break;
+            }
+        }
+    }
+
+    Ok(delivered)
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EstimateKind {
+    Estimated,
+    // Rate limited without a reset time from Notion, or waiting on something
+    // (a new token) with no predictable end
+    Unknown,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DeliveryEstimate {
+    pub id: String,
+    // 1-based place in the send order
+    pub position: usize,
+    // RFC3339; None when `estimate` is unknown
+    pub estimated_delivery_at: Option<String>,
+    pub estimate: EstimateKind,
+}
+
+// The limiter as the estimate sees it
+#[derive(Debug, Clone, Copy)]
+pub struct LimiterSnapshot {
+    // Remaining wait after a 429, and where it came from
+    pub limited: Option<(Duration, RateLimitSource)>,
+    // Spacing between background requests
+    pub spacing: Duration,
+}
+
+// When each waiting note should go out. Mirrors flush: notes go oldest first,
+// one request per MAX_CHILDREN_PER_REQUEST blocks, or one for a batch of notes
+// for the same page, spaced like background requests, not before the next
+// flush, the end of a rate limit or the note's own retry time. Profile tokens
+// are assumed to share the main token's limiter.
+pub fn estimate_deliveries(
+    items: &[QueuedNote],
+    limiter: LimiterSnapshot,
+    next_flush: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Vec<DeliveryEstimate> {
+    let to_chrono = |wait: Duration| chrono::Duration::from_std(wait).unwrap_or_else(|_| chrono::Duration::zero());
+    let spacing = to_chrono(limiter.spacing);
+    // Only a reset time Notion told us about is worth predicting from
+    let known_limit = match limiter.limited {
+        Some((wait, RateLimitSource::Headers)) => Some(now + to_chrono(wait)),
+        Some((_, RateLimitSource::Backoff)) => None,
+        None => Some(now),
+    };
+
+    let mut cursor = known_limit.map(|limit_end| limit_end.max(next_flush).max(now));
+    // The batch the previous note went out in: its first note, size so far and delivery time
+    let mut batch: Option<(&QueuedNote, usize, Option<DateTime<Utc>>)> = None;
+    let estimate = |index: usize, item: &QueuedNote, at: Option<DateTime<Utc>>| DeliveryEstimate {
+        id: item.id.clone(),
+        position: index + 1,
+        estimated_delivery_at: at.map(|at| at.to_rfc3339()),
+        estimate: if at.is_some() { EstimateKind::Estimated } else { EstimateKind::Unknown },
+    };
+    items
+        .iter()
+        .filter(|item| !matches!(item.state, QueueItemState::Delivered | QueueItemState::Dead))
+        .enumerate()
+        .map(|(index, item)| {
+            // A note that joins the previous one's batch goes out with it
+            if let Some((first, size, at)) = batch.as_mut() {
+                if joins_batch(first, *size, item, now) {
+                    *size += item.children.len();
+                    let at = *at;
+                    return estimate(index, item, at);
+                }
+            }
+            batch = None;
+            let at = if item.blocked_on_auth {
+                None
+            } else if item.state == QueueItemState::InFlight {
+                Some(now)
+            } else {
+                let at = cursor.map(|earliest| {
+                    let retry_at = item
+                        .retry_at
+                        .as_deref()
+                        .and_then(|at| DateTime::parse_from_rfc3339(at).ok())
+                        .map(|at| at.with_timezone(&Utc));
+                    let start = retry_at.map_or(earliest, |retry_at| retry_at.max(earliest));
+                    let requests = item.children.len().div_ceil(MAX_CHILDREN_PER_REQUEST).max(1) as i32;
+                    let delivered = start + spacing * (requests - 1);
+                    cursor = Some(delivered + spacing);
+                    delivered
+                });
+                batch = Some((item, item.children.len(), at));
+                at
+            };
+            estimate(index, item, at)
+        })
+        .collect()
+}
+
+fn current_estimates(app: &AppHandle) -> Result<Vec<DeliveryEstimate>, String> {
+    let token = app.state::<AppState>().config.lock().unwrap().secrets.main_token().to_string();
+    let limiter = LimiterSnapshot {
+        limited: RATE_LIMITER.limit_state(&token, std::time::Instant::now()),
+        spacing: REQUEST_SPACING.div_f64(BACKGROUND_RATE_FRACTION),
+    };
+    let now = Utc::now();
+    let next_flush = NEXT_FLUSH_AT.lock().unwrap().unwrap_or(now);
+    Ok(estimate_deliveries(&load_queue()?, limiter, next_flush, now))
+}
+
+// Send every waiting note's estimate to the frontend
+pub fn publish_estimates(app: &AppHandle) {
+    match current_estimates(app) {
+        Ok(estimates) => {
+            for estimate in estimates {
+                events::emit_app_event(app, events::AppEvent::NoteProgress(NoteProgress::Queued(estimate)));
+            }
+        }
+        Err(e) => eprintln!("Failed to estimate queued deliveries: {}", e),
+    }
+}
+
+// A capture's progress towards Notion, as sent in `note-progress`
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum NoteProgress {
+    Queued(DeliveryEstimate),
+    // An append for capture `id` failed and is being sent again
+    Retrying {
+        id: String,
+        #[serde(flatten)]
+        retry: AppendRetry,
+    },
+}
+
+// Background task: settle in-flight items from the last run, then flush periodically
+pub fn start_queue_flush(app: &AppHandle) {
+    let app_handle = app.clone();
+    let registry = app.state::<AppState>().tasks.clone();
+
+    registry.spawn("queue-flush", move |mut token| async move {
+        if let Err(e) = recover_in_flight(&app_handle).await {
+            eprintln!("Failed to recover in-flight queued notes: {}", e);
+        }
+
+        let mut rate_limit_changes = RATE_LIMITER.subscribe();
+        let mut resumes = suspend::subscribe();
+        loop {
+            // Interactive captures still go out; only the background retries wait
+            if !crate::quiet_hours::wait_until_loud(&mut token).await {
+                return;
+            }
+            *NEXT_FLUSH_AT.lock().unwrap() = None;
+            match flush(&app_handle).await {
+                Ok(0) => {}
+                Ok(count) => println!("Delivered {} queued notes", count),
+                Err(e) => eprintln!("Queue flush failed: {}", e),
+            }
+
+            let mut next_flush = tokio::time::Instant::now() + QUEUE_FLUSH_INTERVAL;
+            *NEXT_FLUSH_AT.lock().unwrap() = Some(Utc::now() + chrono::Duration::from_std(QUEUE_FLUSH_INTERVAL).unwrap_or_default());
+            publish_estimates(&app_handle);
+            // A new rate limit moves every estimate, so re-publish without flushing early
+            loop {
+                tokio::select! {
+                    _ = token.cancelled() => return,
+                    _ = tokio::time::sleep_until(next_flush) => break,
+                    changed = rate_limit_changes.changed() => {
+                        if changed.is_err() {
+                            tokio::time::sleep_until(next_flush).await;
+                            break;
+                        }
+                        publish_estimates(&app_handle);
+                    }
+                    // The timer can't be trusted across a suspend: re-arm it for shortly after waking
+                    Ok(()) = resumes.changed() => {
+                        next_flush = tokio::time::Instant::now() + suspend::RESUME_SETTLE;
+                        *NEXT_FLUSH_AT.lock().unwrap() = Some(
+                            Utc::now() + chrono::Duration::from_std(suspend::RESUME_SETTLE).unwrap_or_default(),
+                        );
+                    }
+                }
+            }
+        }
+    });
+}
+
+// Queue a note for delivery once back online; returns its capture id
+// Notes still waiting to be delivered (anything not yet delivered or given up on)
+pub fn pending_count() -> Result<usize, String> {
+    Ok(load_queue()?
+        .iter()
+        .filter(|item| !matches!(item.state, QueueItemState::Delivered | QueueItemState::Dead))
+        .count())
+}
+
+// Remove the queue file; returns how many items it held
+pub fn clear() -> Result<usize, String> {
+    let _lock = QUEUE_LOCK.lock().unwrap();
+    let count = read_queue()?.len();
+    let path = app_data_path(QUEUE_FILE)?;
+    if path.exists() {
+        fs::remove_file(&path).map_err(|e| format!("Failed to remove queue: {}", e))?;
+    }
+    Ok(count)
+}
+
+#[tauri::command]
+pub fn queue_note(
+    note_text: String,
+    profile: Option<String>,
+    format: Option<NoteFormat>,
+    block_type: Option<NoteBlockType>,
+    app: AppHandle,
+) -> Result<String, String> {
+    let style = NoteStyle {
+        format: format.unwrap_or_default(),
+        block_type,
+        ..Default::default()
+    };
+    enqueue(&app, &note_text, profile, style).map(|item| item.id)
+}
+
+#[tauri::command]
+pub fn get_queued_notes() -> Result<Vec<QueuedNote>, String> {
+    load_queue()
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PendingNote {
+    pub note: QueuedNote,
+    pub estimate: DeliveryEstimate,
+}
+
+// Notes still waiting, oldest first, each with its estimated delivery time
+#[tauri::command]
+pub fn get_pending_notes(app: AppHandle) -> Result<Vec<PendingNote>, String> {
+    let estimates = current_estimates(&app)?;
+    Ok(load_queue()?
+        .into_iter()
+        .filter_map(|note| {
+            let estimate = estimates.iter().find(|estimate| estimate.id == note.id)?.clone();
+            Some(PendingNote { note, estimate })
+        })
+        .collect())
+}
+
+// Try to deliver queued notes now
+#[tauri::command]
+pub async fn flush_queue(app: AppHandle) -> Result<usize, String> {
+    flush(&app).await
+}
+
+// Drop a queued note that is not currently being sent
+#[tauri::command]
+pub fn discard_queued_note(id: String) -> Result<(), String> {
+    modify_queue(|items| {
+        let item = items.iter()
+            .find(|item| item.id == id)
+            .ok_or_else(|| format!("Queued note {} not found", id))?;
+        if item.state == QueueItemState::InFlight {
+            return Err("This note is being sent right now".into());
+        }
+        items.retain(|item| item.id != id);
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_notion::{FakeWorkspace, MockNotion, MockReply};
+    use chrono::TimeZone;
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn only_the_documented_transitions_are_allowed() {
+        use QueueItemState::*;
+        let all = [Pending, InFlight, Delivered, Failed, Dead];
+        let allowed = [
+            (Pending, InFlight),
+            (InFlight, Delivered),
+            (InFlight, Failed),
+            (InFlight, Pending),
+            (Failed, InFlight),
+            (Failed, Dead),
+        ];
+        for from in all {
+            for to in all {
+                assert_eq!(from.can_transition(to), allowed.contains(&(from, to)), "{:?} -> {:?}", from, to);
+            }
+        }
+        // Delivered and dead are final
+        assert!(all.iter().all(|to| !Delivered.can_transition(*to) && !Dead.can_transition(*to)));
+        assert_eq!(all.map(QueueItemState::is_sendable), [true, false, false, true, false]);
+    }
+
+    #[test]
+    fn a_refused_transition_leaves_the_item_alone() {
+        let _data_dir = crate::config::use_test_data_dir();
+        let item = enqueue_blocks("page-1".into(), "Inbox".into(), Vec::new(), DeliverySource::Raw, None).unwrap();
+        let error = transition(&item.id, QueueItemState::Delivered, None).unwrap_err();
+        assert!(error.contains("can't go from Pending to Delivered"), "{}", error);
+        transition(&item.id, QueueItemState::InFlight, None).unwrap();
+        let stored = &load_queue().unwrap()[0];
+        assert_eq!((stored.state, stored.attempts), (QueueItemState::InFlight, 1));
+    }
+
+    #[test]
+    fn notes_blocked_on_auth_wait_until_released() {
+        let _data_dir = crate::config::use_test_data_dir();
+        let blocked = enqueue_blocks("page-1".into(), "Inbox".into(), Vec::new(), DeliverySource::Note, None).unwrap();
+        let other = enqueue_blocks("page-1".into(), "Inbox".into(), Vec::new(), DeliverySource::Note, None).unwrap();
+
+        // A send refused with a 401 goes back to pending without using up an attempt
+        transition(&blocked.id, QueueItemState::InFlight, None).unwrap();
+        block_on_auth(&blocked.id).unwrap();
+        let stored = load_queue().unwrap();
+        assert_eq!((stored[0].state, stored[0].attempts, stored[0].blocked_on_auth), (QueueItemState::Pending, 0, true));
+        assert!(!stored[1].blocked_on_auth);
+        assert!(!batchable(&stored[0]));
+        assert!(block_on_auth(&other.id).is_err(), "only a note being sent can be blocked");
+
+        assert_eq!(unblock_auth().unwrap(), 1);
+        let stored = load_queue().unwrap();
+        assert!(stored.iter().all(|item| !item.blocked_on_auth && item.state == QueueItemState::Pending));
+        assert_eq!(unblock_auth().unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn only_delivered_notes_get_a_receipt() {
+        let _data_dir = crate::config::use_test_data_dir();
+        let mock = MockNotion::start(|request| {
+            if request.body.as_ref().is_some_and(|body| body.to_string().contains("rejected")) {
+                MockReply::error(400, "validation_error", "body failed validation")
+            } else {
+                MockReply::json(200, json!({ "results": [{ "id": "block-1" }], "request_id": "req-1" }))
+            }
+        }).await;
+        let client = NotionApiClient::new(mock.token.clone()).unwrap().with_priority(Priority::Background);
+
+        // Queued and never sent: no receipt
+        let waiting = enqueue_blocks("page-1".into(), "Inbox".into(), queued_note("[09:00] waiting"), DeliverySource::Note, None).unwrap();
+        assert!(receipts::find_receipt(&waiting.id).unwrap().is_none());
+
+        // Refused by Notion: failed and backing off, still no receipt
+        let rejected = enqueue_blocks("page-1".into(), "Inbox".into(), queued_note("[09:01] rejected"), DeliverySource::Note, None).unwrap();
+        transition(&rejected.id, QueueItemState::InFlight, None).unwrap();
+        let failure: String = client.append_children("page-1", "Inbox", &rejected.children).await.unwrap_err().into();
+        fail(&rejected, failure).unwrap();
+        let stored = load_queue().unwrap();
+        let stored = stored.iter().find(|item| item.id == rejected.id).unwrap();
+        assert_eq!(stored.state, QueueItemState::Failed);
+        assert!(stored.retry_at.is_some());
+        assert!(receipts::find_receipt(&rejected.id).unwrap().is_none());
+
+        // Confirmed: exactly one receipt, written as it is delivered
+        let sent = enqueue_blocks("page-1".into(), "Inbox".into(), queued_note("[09:02] sent"), DeliverySource::Note, None).unwrap();
+        transition(&sent.id, QueueItemState::InFlight, None).unwrap();
+        assert!(receipts::find_receipt(&sent.id).unwrap().is_none());
+        let response = client.append_children("page-1", "Inbox", &sent.children).await.unwrap();
+        complete(&sent, &queue_receipt(&sent, response)).unwrap();
+
+        let receipt = receipts::find_receipt(&sent.id).unwrap().unwrap();
+        assert_eq!((receipt.status, receipt.block_ids.as_slice()), (200, ["block-1".to_string()].as_slice()));
+        assert_eq!(receipt.origin, Some(NoteOrigin::Queue));
+        let all = receipts::history_page(0, 100).unwrap();
+        assert_eq!(all.iter().filter(|receipt| receipt.capture_id == sent.id).count(), 1);
+        assert_eq!(all.len(), 1);
+        assert!(receipts::find_receipt(&waiting.id).unwrap().is_none());
+    }
+
+    fn queued_note(text: &str) -> Vec<Value> {
+        vec![blocks::paragraph(blocks::text_runs(text, false))]
+    }
+
+    async fn workspace_server() -> (Arc<Mutex<FakeWorkspace>>, MockNotion, NotionApiClient) {
+        let workspace = FakeWorkspace::shared();
+        workspace.lock().unwrap().add_page("page-1", "Inbox");
+        let notion = workspace.clone();
+        let mock = MockNotion::start(move |request| notion.lock().unwrap().answer(request)).await;
+        let client = NotionApiClient::new(mock.token.clone()).unwrap().with_priority(Priority::Background);
+        (workspace, mock, client)
+    }
+
+    // Phase one of a flush, then the send, then the app dies before the item is removed
+    async fn send_then_die(client: &NotionApiClient, text: &str) -> QueuedNote {
+        let item = enqueue_blocks("page-1".into(), "Inbox".into(), queued_note(text), DeliverySource::Note, None).unwrap();
+        transition(&item.id, QueueItemState::InFlight, None).unwrap();
+        client.append_children("page-1", "Inbox", &item.children).await.unwrap();
+        load_queue().unwrap().remove(0)
+    }
+
+    #[tokio::test]
+    async fn a_send_that_landed_before_a_crash_is_not_sent_again() {
+        let _data_dir = crate::config::use_test_data_dir();
+        let (workspace, mock, client) = workspace_server().await;
+        workspace.lock().unwrap().add_paragraphs("page-1", &["[08:00] older note"]);
+        let item = send_then_die(&client, "[09:00] landed").await;
+        assert_eq!(item.state, QueueItemState::InFlight);
+        let appends_before = mock.count("PATCH", "/v1/blocks/");
+
+        // Restart: no receipt, so the page is checked
+        assert!(receipts::find_receipt(&item.id).unwrap().is_none());
+        settle_in_flight(&client, &item, None).await.unwrap();
+
+        assert_eq!(mock.count("PATCH", "/v1/blocks/"), appends_before);
+        let children = workspace.lock().unwrap().children("page-1");
+        let texts: Vec<String> = children.iter().map(blocks::block_text).collect();
+        assert_eq!(texts, ["[08:00] older note", "[09:00] landed"]);
+        assert!(load_queue().unwrap().is_empty());
+        let receipt = receipts::find_receipt(&item.id).unwrap().unwrap();
+        assert_eq!(receipt.block_ids, [children[1]["id"].as_str().unwrap()]);
+    }
+
+    #[tokio::test]
+    async fn a_send_that_landed_after_the_anchor_is_found_there() {
+        let _data_dir = crate::config::use_test_data_dir();
+        let (workspace, mock, client) = workspace_server().await;
+        // The anchor sits past the first listing of a long page
+        let filler: Vec<String> = (0..150).map(|index| format!("old note {}", index)).collect();
+        workspace.lock().unwrap().add_paragraphs("page-1", &filler.iter().map(String::as_str).collect::<Vec<_>>());
+        let anchor_id = workspace.lock().unwrap().children("page-1")[120]["id"].as_str().unwrap().to_string();
+
+        let item = enqueue_blocks("page-1".into(), "Inbox".into(), queued_note("[09:00] anchored"), DeliverySource::Note, None).unwrap();
+        transition(&item.id, QueueItemState::InFlight, None).unwrap();
+        client.append_children_after("page-1", "Inbox", &item.children, Some(&anchor_id)).await.unwrap();
+        let appends_before = mock.count("PATCH", "/v1/blocks/");
+
+        // Restart with nothing remembered about the page
+        let restarted = NotionApiClient::new(mock.another_token()).unwrap().with_priority(Priority::Background);
+        settle_in_flight(&restarted, &load_queue().unwrap()[0], Some(&anchor_id)).await.unwrap();
+
+        assert_eq!(mock.count("PATCH", "/v1/blocks/"), appends_before);
+        assert!(load_queue().unwrap().is_empty());
+        let landed = workspace.lock().unwrap().children("page-1")[121].clone();
+        assert_eq!(blocks::block_text(&landed), "[09:00] anchored");
+        assert_eq!(receipts::find_receipt(&item.id).unwrap().unwrap().block_ids, [landed["id"].as_str().unwrap()]);
+    }
+
+    #[tokio::test]
+    async fn a_send_that_never_landed_goes_back_to_pending() {
+        let _data_dir = crate::config::use_test_data_dir();
+        let (workspace, _mock, client) = workspace_server().await;
+        let item = enqueue_blocks("page-1".into(), "Inbox".into(), queued_note("[09:00] lost"), DeliverySource::Note, None).unwrap();
+        transition(&item.id, QueueItemState::InFlight, None).unwrap();
+
+        settle_in_flight(&client, &load_queue().unwrap()[0], None).await.unwrap();
+
+        assert!(workspace.lock().unwrap().children("page-1").is_empty());
+        let stored = &load_queue().unwrap()[0];
+        assert_eq!((stored.state, stored.attempts), (QueueItemState::Pending, 1));
+        assert!(receipts::find_receipt(&item.id).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn a_crash_after_the_receipt_completes_from_the_receipt() {
+        let _data_dir = crate::config::use_test_data_dir();
+        let (_workspace, mock, client) = workspace_server().await;
+        let item = send_then_die(&client, "[09:00] receipted").await;
+        let receipt = DeliveryReceipt::new(item.id.clone(), item.page_id.clone(), AppendResponse {
+            block_ids: vec!["block-1".into()],
+            request_id: None,
+            status: 200,
+            attempts: 1,
+        }, None);
+        receipts::record_receipt(&receipt).unwrap();
+        let requests_before = mock.requests().len();
+
+        // What recover_in_flight does when the receipt exists
+        let found = receipts::find_receipt(&item.id).unwrap().unwrap();
+        complete(&item, &found).unwrap();
+
+        assert_eq!(mock.requests().len(), requests_before);
+        assert!(load_queue().unwrap().is_empty());
+    }
+
+    #[test]
+    fn a_partial_remainder_keeps_its_profile_and_place() {
+        let _data_dir = crate::config::use_test_data_dir();
+        let children = vec![json!({ "type": "paragraph", "paragraph": { "rich_text": [] } })];
+        let item = enqueue_remainder(
+            Some("work".into()),
+            "page-1".into(),
+            "Inbox".into(),
+            children.clone(),
+            Some("Monday".into()),
+            Some("block-100".into()),
+        )
+        .unwrap();
+
+        let stored = load_queue().unwrap();
+        assert_eq!(stored.len(), 1);
+        let stored = &stored[0];
+        assert_eq!(stored.id, item.id);
+        assert_eq!(stored.profile.as_deref(), Some("work"));
+        assert_eq!(stored.day_heading.as_deref(), Some("Monday"));
+        assert_eq!(stored.after.as_deref(), Some("block-100"));
+        assert_eq!(stored.children, children);
+        assert_eq!(stored.state, QueueItemState::Pending);
+        // It has to land right after the delivered part, not wherever a batch ends up
+        assert!(!batchable(stored));
+    }
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 5, 4, 12, 0, 0).unwrap() + chrono::Duration::seconds(seconds)
+    }
+
+    // A pending note of `blocks` paragraphs; notes for different pages never share a request
+    fn waiting(page_id: &str, blocks: usize) -> QueuedNote {
+        let children = (0..blocks).map(|index| queued_note(&format!("line {}", index)).remove(0)).collect();
+        blocks_item(page_id.into(), "Inbox".into(), children, DeliverySource::Note, None)
+    }
+
+    fn limiter(limited: Option<(Duration, RateLimitSource)>) -> LimiterSnapshot {
+        LimiterSnapshot { limited, spacing: Duration::from_secs(1) }
+    }
+
+    // Seconds after `at(0)` each note is expected, None when unknown
+    fn offsets(estimates: &[DeliveryEstimate]) -> Vec<Option<i64>> {
+        estimates
+            .iter()
+            .map(|estimate| {
+                assert_eq!(estimate.estimate == EstimateKind::Unknown, estimate.estimated_delivery_at.is_none());
+                estimate.estimated_delivery_at.as_deref().map(|time| (DateTime::parse_from_rfc3339(time).unwrap().with_timezone(&Utc) - at(0)).num_seconds())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn estimates_space_out_queues_of_any_length() {
+        let free = limiter(None);
+        assert!(estimate_deliveries(&[], free, at(0), at(0)).is_empty());
+        for length in [1, 3, 10] {
+            let items: Vec<QueuedNote> = (0..length).map(|index| waiting(&format!("page-{}", index), 1)).collect();
+            let estimates = estimate_deliveries(&items, free, at(0), at(0));
+            assert_eq!(offsets(&estimates), (0..length as i64).map(Some).collect::<Vec<_>>());
+            assert_eq!(estimates.iter().map(|estimate| estimate.position).collect::<Vec<_>>(), (1..=length).collect::<Vec<_>>());
+            assert_eq!(estimates[length - 1].id, items[length - 1].id);
+        }
+
+        // Nothing goes before the next flush
+        let items = [waiting("page-1", 1), waiting("page-2", 1)];
+        assert_eq!(offsets(&estimate_deliveries(&items, free, at(20), at(0))), [Some(20), Some(21)]);
+    }
+
+    #[test]
+    fn batches_share_a_request_and_long_notes_take_several() {
+        let free = limiter(None);
+        // Three notes for one page go in one request, the next page's after it
+        let items = [waiting("page-1", 1), waiting("page-1", 2), waiting("page-1", 1), waiting("page-2", 1)];
+        assert_eq!(offsets(&estimate_deliveries(&items, free, at(0), at(0))), [Some(0), Some(0), Some(0), Some(1)]);
+
+        // A note too long for one request is delivered with its last one
+        let items = [waiting("page-1", MAX_CHILDREN_PER_REQUEST * 2 + 1), waiting("page-2", 1)];
+        assert_eq!(offsets(&estimate_deliveries(&items, free, at(0), at(0))), [Some(2), Some(3)]);
+    }
+
+    #[test]
+    fn only_a_reset_time_from_notion_gives_an_estimate() {
+        let items = [waiting("page-1", 1), waiting("page-2", 1)];
+        let headers = limiter(Some((Duration::from_secs(30), RateLimitSource::Headers)));
+        let estimates = estimate_deliveries(&items, headers, at(0), at(0));
+        assert_eq!(offsets(&estimates), [Some(30), Some(31)]);
+        assert!(estimates.iter().all(|estimate| estimate.estimate == EstimateKind::Estimated));
+
+        // Our own backoff guess isn't worth a time
+        let backoff = limiter(Some((Duration::from_secs(30), RateLimitSource::Backoff)));
+        let estimates = estimate_deliveries(&items, backoff, at(0), at(0));
+        assert_eq!(offsets(&estimates), [None, None]);
+        let json = serde_json::to_value(&estimates[0]).unwrap();
+        assert_eq!((&json["estimate"], &json["estimated_delivery_at"], &json["position"]), (&json!("unknown"), &Value::Null, &json!(1)));
+
+        // A limit ending before the next flush waits for the flush
+        let short = limiter(Some((Duration::from_secs(5), RateLimitSource::Headers)));
+        assert_eq!(offsets(&estimate_deliveries(&items, short, at(10), at(0))), [Some(10), Some(11)]);
+    }
+
+    #[test]
+    fn each_note_state_is_estimated_as_flush_would_send_it() {
+        let mut sending = waiting("page-1", 1);
+        sending.state = QueueItemState::InFlight;
+        let mut delivered = waiting("page-2", 1);
+        delivered.state = QueueItemState::Delivered;
+        let mut dead = waiting("page-3", 1);
+        dead.state = QueueItemState::Dead;
+        let mut blocked = waiting("page-4", 1);
+        blocked.blocked_on_auth = true;
+        let mut backing_off = waiting("page-5", 1);
+        backing_off.state = QueueItemState::Failed;
+        backing_off.retry_at = Some(at(60).to_rfc3339());
+        let after = waiting("page-6", 1);
+
+        let items = [sending, delivered, dead, blocked, backing_off, after];
+        let estimates = estimate_deliveries(&items, limiter(None), at(0), at(0));
+        // Delivered and dead notes aren't waiting; a blocked one takes no slot
+        let ids: Vec<&str> = estimates.iter().map(|estimate| estimate.id.as_str()).collect();
+        assert_eq!(ids, [&items[0].id, &items[3].id, &items[4].id, &items[5].id]);
+        assert_eq!(estimates.iter().map(|estimate| estimate.position).collect::<Vec<_>>(), [1, 2, 3, 4]);
+        assert_eq!(offsets(&estimates), [Some(0), None, Some(60), Some(61)]);
+    }
+}
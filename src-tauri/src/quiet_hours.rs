@@ -0,0 +1,269 @@
+use chrono::{Datelike, Duration as ChronoDuration, Local, NaiveDateTime, NaiveTime, TimeZone, Weekday};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::AppHandle;
+
+use crate::tasks::CancellationToken;
+
+// Quiet hours: a nightly (or any) window in which background work waits and
+// background notifications are held back until the user next opens a window.
+// Interactive captures are unaffected. Times are local wall-clock times, so a
+// window keeps its clock times across DST changes; only its length changes.
+
+// How often a paused task re-reads the schedule, in case it was changed
+const RECHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+// Notifications held back at most; older ones are dropped first
+const MAX_HELD_NOTIFICATIONS: usize = 10;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct QuietHours {
+    pub enabled: bool,
+    // Local "HH:MM"; an end at or before the start means the window crosses midnight
+    pub start: String,
+    pub end: String,
+    // Days the window starts on ("mon", "tuesday", ...); empty means every day
+    pub days: Vec<String>,
+}
+
+impl Default for QuietHours {
+    fn default() -> Self {
+        QuietHours {
+            enabled: false,
+            start: "22:00".to_string(),
+            end: "07:00".to_string(),
+            days: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct QuietHoursState {
+    pub active: bool,
+    // RFC3339 end of the current window, while active
+    pub until: Option<String>,
+    // Local "HH:MM" of that end, for the tray
+    pub until_local: Option<String>,
+    pub held_notifications: usize,
+}
+
+lazy_static::lazy_static! {
+    // Mirrors config.quiet_hours
+    static ref SCHEDULE: Mutex<QuietHours> = Mutex::new(QuietHours::default());
+    static ref HELD: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+}
+
+pub fn set_schedule(schedule: &QuietHours) {
+    *SCHEDULE.lock().unwrap() = schedule.clone();
+}
+
+fn parse_time(time: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(time.trim(), "%H:%M").ok()
+}
+
+fn applies_on(days: &[String], day: Weekday) -> bool {
+    days.is_empty() || days.iter().any(|name| name.trim().parse::<Weekday>() == Ok(day))
+}
+
+// End of the window `now` falls in, as a local wall-clock time, or None when
+// it falls in none. A window belongs to the day it starts on, so Friday's
+// 22:00-07:00 still holds at 03:00 on Saturday.
+pub fn window_end(schedule: &QuietHours, now: NaiveDateTime) -> Option<NaiveDateTime> {
+    if !schedule.enabled {
+        return None;
+    }
+    let (start, end) = (parse_time(&schedule.start)?, parse_time(&schedule.end)?);
+    if start == end {
+        return None;
+    }
+    let today = now.date();
+    let time = now.time();
+
+    if start < end {
+        return (applies_on(&schedule.days, today.weekday()) && time >= start && time < end)
+            .then(|| today.and_time(end));
+    }
+    // Crosses midnight: either the window that started today, or yesterday's
+    if time >= start && applies_on(&schedule.days, today.weekday()) {
+        return Some((today + ChronoDuration::days(1)).and_time(end));
+    }
+    let yesterday = today - ChronoDuration::days(1);
+    (time < end && applies_on(&schedule.days, yesterday.weekday())).then(|| today.and_time(end))
+}
+
+// A local wall-clock time as an instant. A time skipped by a DST change
+// resolves to the first valid time after it; a repeated one to its first occurrence.
+pub fn resolve_local<Tz: TimeZone>(tz: &Tz, local: NaiveDateTime) -> chrono::DateTime<Tz> {
+    let mut candidate = local;
+    loop {
+        if let Some(time) = tz.from_local_datetime(&candidate).earliest() {
+            return time;
+        }
+        candidate += ChronoDuration::minutes(15);
+    }
+}
+
+fn current_end() -> Option<chrono::DateTime<Local>> {
+    let schedule = SCHEDULE.lock().unwrap().clone();
+    let end = window_end(&schedule, Local::now().naive_local())?;
+    Some(resolve_local(&Local, end))
+}
+
+pub fn is_quiet() -> bool {
+    current_end().is_some()
+}
+
+pub fn state() -> QuietHoursState {
+    let end = current_end();
+    QuietHoursState {
+        active: end.is_some(),
+        until: end.map(|end| end.to_rfc3339()),
+        until_local: end.map(|end| end.format("%H:%M").to_string()),
+        held_notifications: HELD.lock().unwrap().len(),
+    }
+}
+
+// For background tasks: wait out quiet hours before doing network work.
+// Returns false if the task was cancelled meanwhile.
+pub async fn wait_until_loud(token: &mut CancellationToken) -> bool {
+    while let Some(end) = current_end() {
+        let remaining = (end - Local::now()).to_std().unwrap_or_default();
+        tokio::select! {
+            _ = token.cancelled() => return false,
+            _ = tokio::time::sleep(remaining.min(RECHECK_INTERVAL)) => {}
+        }
+    }
+    true
+}
+
+// Show a background notification, or hold it until the next interaction during quiet hours
+pub fn notify_background(app: &AppHandle, title: &str, body: &str) {
+    if !is_quiet() {
+        crate::notify(app, title, body);
+        return;
+    }
+    let mut held = HELD.lock().unwrap();
+    held.push((title.to_string(), body.to_string()));
+    if held.len() > MAX_HELD_NOTIFICATIONS {
+        held.remove(0);
+    }
+}
+
+// The user is back: show what was held back
+pub fn release_held(app: &AppHandle) {
+    let held = std::mem::take(&mut *HELD.lock().unwrap());
+    match held.as_slice() {
+        [] => {}
+        [(title, body)] => crate::notify(app, title, body),
+        [.., (title, body)] => crate::notify(
+            app,
+            &format!("{} notifications during quiet hours", held.len()),
+            &format!("Latest: {} - {}", title, body),
+        ),
+    }
+}
+
+#[tauri::command]
+pub fn get_quiet_hours_state() -> QuietHoursState {
+    state()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use chrono_tz::America::New_York;
+
+    fn schedule(start: &str, end: &str, days: &[&str]) -> QuietHours {
+        QuietHours {
+            enabled: true,
+            start: start.to_string(),
+            end: end.to_string(),
+            days: days.iter().map(|day| day.to_string()).collect(),
+        }
+    }
+
+    // 2026-10-16 is a Friday
+    fn at(day: u32, time: &str) -> NaiveDateTime {
+        local((2026, 10, day), time)
+    }
+
+    fn local(date: (i32, u32, u32), time: &str) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(date.0, date.1, date.2).unwrap().and_time(parse_time(time).unwrap())
+    }
+
+    #[test]
+    fn a_window_within_one_day() {
+        let quiet = schedule("13:00", "14:30", &[]);
+        assert_eq!(window_end(&quiet, at(16, "13:00")), Some(at(16, "14:30")));
+        assert_eq!(window_end(&quiet, at(16, "14:29")), Some(at(16, "14:30")));
+        assert_eq!(window_end(&quiet, at(16, "14:30")), None, "the end is exclusive");
+        assert_eq!(window_end(&quiet, at(16, "12:59")), None);
+    }
+
+    #[test]
+    fn a_window_across_midnight_ends_the_next_morning() {
+        let quiet = schedule("22:00", "07:00", &[]);
+        assert_eq!(window_end(&quiet, at(16, "21:59")), None);
+        assert_eq!(window_end(&quiet, at(16, "22:00")), Some(at(17, "07:00")));
+        assert_eq!(window_end(&quiet, at(16, "23:59")), Some(at(17, "07:00")));
+        assert_eq!(window_end(&quiet, at(17, "00:00")), Some(at(17, "07:00")));
+        assert_eq!(window_end(&quiet, at(17, "06:59")), Some(at(17, "07:00")));
+        assert_eq!(window_end(&quiet, at(17, "07:00")), None);
+
+        // Ending exactly at midnight
+        let until_midnight = schedule("22:00", "00:00", &[]);
+        assert_eq!(window_end(&until_midnight, at(16, "23:59")), Some(at(17, "00:00")));
+        assert_eq!(window_end(&until_midnight, at(17, "00:00")), None);
+    }
+
+    #[test]
+    fn a_window_belongs_to_the_day_it_starts_on() {
+        let friday_night = schedule("22:00", "07:00", &["fri"]);
+        assert_eq!(window_end(&friday_night, at(17, "03:00")), Some(at(17, "07:00")), "Friday's window on Saturday");
+        assert_eq!(window_end(&friday_night, at(16, "03:00")), None, "Thursday's window isn't scheduled");
+        assert_eq!(window_end(&friday_night, at(17, "23:00")), None);
+
+        let weekdays = schedule("09:00", "17:00", &["monday", "tue", "Wed", " thu ", "fri"]);
+        assert!(window_end(&weekdays, at(16, "10:00")).is_some());
+        assert!(window_end(&weekdays, at(17, "10:00")).is_none());
+    }
+
+    #[test]
+    fn disabled_empty_or_unreadable_schedules_are_never_quiet() {
+        let mut disabled = schedule("00:00", "23:59", &[]);
+        disabled.enabled = false;
+        for quiet in [disabled, schedule("22:00", "22:00", &[]), schedule("10pm", "07:00", &[])] {
+            assert_eq!(window_end(&quiet, at(16, "22:30")), None, "{:?}", quiet);
+        }
+    }
+
+    #[test]
+    fn an_end_skipped_by_spring_forward_resolves_to_the_next_valid_time() {
+        // 2026-03-08 02:00-03:00 doesn't exist in New York
+        let quiet = schedule("01:00", "02:30", &[]);
+        let end = window_end(&quiet, local((2026, 3, 8), "01:15")).unwrap();
+        let resolved = resolve_local(&New_York, end);
+        assert_eq!(resolved.naive_local(), local((2026, 3, 8), "03:00"));
+        assert_eq!(resolved.offset().to_string(), "EDT");
+    }
+
+    #[test]
+    fn a_window_keeps_its_clock_times_across_dst_changes() {
+        let quiet = schedule("22:00", "07:00", &[]);
+
+        // Spring forward: the night is an hour shorter
+        let start = resolve_local(&New_York, local((2026, 3, 7), "22:00"));
+        let end = resolve_local(&New_York, window_end(&quiet, local((2026, 3, 8), "01:00")).unwrap());
+        assert_eq!(end.naive_local(), local((2026, 3, 8), "07:00"));
+        assert_eq!((end - start).num_hours(), 8);
+
+        // Fall back: an hour longer, and a repeated 01:30 resolves to its first occurrence
+        let start = resolve_local(&New_York, local((2026, 10, 31), "22:00"));
+        let end = resolve_local(&New_York, window_end(&quiet, local((2026, 11, 1), "01:30")).unwrap());
+        assert_eq!((end - start).num_hours(), 10);
+        assert_eq!(resolve_local(&New_York, local((2026, 11, 1), "01:30")).offset().to_string(), "EDT");
+    }
+}
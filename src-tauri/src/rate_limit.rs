@@ -0,0 +1,661 @@
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::State;
+
+use crate::config::AppState;
+use crate::secrets::token_key;
+
+// Notion allows an average of three requests per second per integration
+pub const REQUEST_SPACING: Duration = Duration::from_millis(334);
+
+// Share of the allowed rate that background work may use
+pub const BACKGROUND_RATE_FRACTION: f64 = 0.5;
+
+// How often waiting requests re-check the limiter
+const POLL_INTERVAL: Duration = Duration::from_millis(25);
+
+// Requests allowed in flight at once across all tokens, unless configured otherwise
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 4;
+
+// Per-minute request counts kept per token for the settings usage panel
+pub const HISTORY_MINUTES: usize = 60;
+
+// Window used for the current request rate
+const RATE_WINDOW: Duration = Duration::from_secs(10);
+
+// Who is asking: interactive requests (appends, settings verification) always
+// go ahead of background ones (cache refresh, icon downloads, queue flush)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Priority {
+    Interactive,
+    Background,
+}
+
+// Requests sent for one token: per-minute counts for the last HISTORY_MINUTES
+// minutes, plus the send times within the last minute for the current rate.
+// Times are passed in, so rollover can be driven without waiting.
+pub struct RequestHistory {
+    started: Instant,
+    // (minutes since `started`, requests in that minute), oldest first
+    minutes: VecDeque<(u64, u32)>,
+    last_minute: VecDeque<Instant>,
+}
+
+impl RequestHistory {
+    pub fn new(now: Instant) -> Self {
+        RequestHistory {
+            started: now,
+            minutes: VecDeque::new(),
+            last_minute: VecDeque::new(),
+        }
+    }
+
+    fn minute_index(&self, now: Instant) -> u64 {
+        now.saturating_duration_since(self.started).as_secs() / 60
+    }
+
+    fn prune(&mut self, now: Instant) {
+        let current = self.minute_index(now);
+        while self.minutes.front().is_some_and(|(minute, _)| minute + (HISTORY_MINUTES as u64) <= current) {
+            self.minutes.pop_front();
+        }
+        let one_minute = Duration::from_secs(60);
+        while self.last_minute.front().is_some_and(|sent| now.saturating_duration_since(*sent) >= one_minute) {
+            self.last_minute.pop_front();
+        }
+    }
+
+    pub fn record(&mut self, now: Instant) {
+        self.prune(now);
+        let current = self.minute_index(now);
+        match self.minutes.back_mut() {
+            Some((minute, count)) if *minute == current => *count += 1,
+            _ => self.minutes.push_back((current, 1)),
+        }
+        self.last_minute.push_back(now);
+    }
+
+    // Requests per minute for the last `minutes` minutes (at most HISTORY_MINUTES),
+    // oldest first, ending with the current minute
+    pub fn per_minute(&self, minutes: usize, now: Instant) -> Vec<u32> {
+        let minutes = minutes.clamp(1, HISTORY_MINUTES) as u64;
+        let current = self.minute_index(now);
+        (0..minutes)
+            .rev()
+            .map(|ago| {
+                current.checked_sub(ago).map_or(0, |minute| {
+                    self.minutes
+                        .iter()
+                        .find(|(bucket, _)| *bucket == minute)
+                        .map_or(0, |(_, count)| *count)
+                })
+            })
+            .collect()
+    }
+
+    pub fn requests_in(&self, window: Duration, now: Instant) -> usize {
+        self.last_minute
+            .iter()
+            .filter(|sent| now.saturating_duration_since(**sent) < window)
+            .count()
+    }
+}
+
+// Where a rate-limit wait came from: Notion's Retry-After header, or our own
+// fallback when a 429 arrived without one
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitSource {
+    Headers,
+    Backoff,
+}
+
+// Per-token throttle state
+struct TokenState {
+    next_allowed: Instant,
+    next_background_allowed: Instant,
+    limited_until: Option<Instant>,
+    // The same deadline on the wall clock, which keeps running while the machine sleeps
+    limited_until_wall: Option<chrono::DateTime<chrono::Utc>>,
+    limited_source: RateLimitSource,
+    history: RequestHistory,
+}
+
+impl TokenState {
+    fn new(now: Instant) -> Self {
+        TokenState {
+            next_allowed: now,
+            next_background_allowed: now,
+            limited_until: None,
+            limited_until_wall: None,
+            limited_source: RateLimitSource::Backoff,
+            history: RequestHistory::new(now),
+        }
+    }
+}
+
+// `Unknown` when nothing has been sent with the token yet (or there is no token)
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitState {
+    Unknown,
+    Ok,
+    Limited,
+}
+
+// What the settings window shows about a token's API usage
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RateLimitInfo {
+    pub state: RateLimitState,
+    // Same as `state == Limited`; kept for older frontends
+    pub limited: bool,
+    // Only set while limited by a recorded 429
+    pub retry_in_secs: Option<u64>,
+    // Where `retry_in_secs` came from, when it is set
+    pub source: Option<RateLimitSource>,
+    // Notion doesn't publish quota headers today; filled in if a response ever carries them
+    pub limit: Option<u64>,
+    pub remaining: Option<u64>,
+    pub allowed_rps: f64,
+    pub current_rps: f64,
+    pub recent_requests_last_minute: usize,
+}
+
+// Waiters holding the lock in turn; tokio's mutex is first come, first served
+type WaitLine = Arc<tokio::sync::Mutex<()>>;
+
+// Spaces out requests per token, caps how many are in flight at once and admits
+// interactive requests ahead of background ones. Within a priority, waiters are
+// admitted in the order they arrived.
+pub struct RateLimitManager {
+    // Keyed by secrets::token_key, so the map never holds a token
+    states: Mutex<HashMap<String, TokenState>>,
+    // Waiters for a token's rate slot, one line per token and priority
+    slot_lines: Mutex<HashMap<(String, Priority), WaitLine>>,
+    // Waiters for an in-flight slot, per priority
+    interactive_line: tokio::sync::Mutex<()>,
+    background_line: tokio::sync::Mutex<()>,
+    interactive_waiting: AtomicUsize,
+    in_flight: AtomicUsize,
+    max_in_flight: AtomicUsize,
+    // Bumped whenever a token is newly rate limited, for anything estimating delivery times
+    changes: tokio::sync::watch::Sender<u64>,
+}
+
+lazy_static::lazy_static! {
+    pub static ref RATE_LIMITER: RateLimitManager = RateLimitManager::new();
+}
+
+// Decrements the waiting-interactive counter even if the acquiring future is dropped
+struct InteractiveGuard<'a>(&'a AtomicUsize);
+
+impl Drop for InteractiveGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// One in-flight request; the slot is released when the permit is dropped, which
+// the client's request timeout guarantees even for a hung connection. The client
+// keeps it in the response, so the slot is held until the body has been read.
+pub struct RequestPermit<'a>(&'a AtomicUsize);
+
+impl Drop for RequestPermit<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Default for RateLimitManager {
+    fn default() -> Self {
+        RateLimitManager::new()
+    }
+}
+
+impl RateLimitManager {
+    pub fn new() -> Self {
+        RateLimitManager {
+            states: Mutex::new(HashMap::new()),
+            slot_lines: Mutex::new(HashMap::new()),
+            interactive_line: tokio::sync::Mutex::new(()),
+            background_line: tokio::sync::Mutex::new(()),
+            interactive_waiting: AtomicUsize::new(0),
+            in_flight: AtomicUsize::new(0),
+            max_in_flight: AtomicUsize::new(DEFAULT_MAX_IN_FLIGHT),
+            changes: tokio::sync::watch::channel(0).0,
+        }
+    }
+
+    // Wakes when any token's rate-limit state changes
+    pub fn subscribe(&self) -> tokio::sync::watch::Receiver<u64> {
+        self.changes.subscribe()
+    }
+
+    // Change the in-flight cap; requests already in flight keep their slots
+    pub fn set_max_in_flight(&self, permits: usize) {
+        self.max_in_flight.store(permits.max(1), Ordering::SeqCst);
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    // Wait until a request for this token may be sent, and hold an in-flight
+    // slot for it. The rate slot is taken first so a token held off by a 429
+    // doesn't sit on a slot other tokens could use.
+    pub async fn acquire(&self, token: &str, priority: Priority) -> RequestPermit<'_> {
+        let _guard = match priority {
+            Priority::Interactive => {
+                self.interactive_waiting.fetch_add(1, Ordering::SeqCst);
+                Some(InteractiveGuard(&self.interactive_waiting))
+            }
+            Priority::Background => None,
+        };
+
+        {
+            let line = self.slot_line(token, priority);
+            let _turn = line.lock().await;
+            loop {
+                match self.try_acquire(token, priority, Instant::now()) {
+                    None => break,
+                    Some(wait) => tokio::time::sleep(wait.min(POLL_INTERVAL)).await,
+                }
+            }
+        }
+        let _turn = match priority {
+            Priority::Interactive => self.interactive_line.lock().await,
+            Priority::Background => self.background_line.lock().await,
+        };
+        loop {
+            if let Some(permit) = self.try_acquire_permit(priority) {
+                return permit;
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    fn slot_line(&self, token: &str, priority: Priority) -> WaitLine {
+        self.slot_lines
+            .lock()
+            .unwrap()
+            .entry((token_key(token), priority))
+            .or_default()
+            .clone()
+    }
+
+    // Take an in-flight slot if one is free; background work leaves free slots
+    // to interactive requests that are waiting
+    pub fn try_acquire_permit(&self, priority: Priority) -> Option<RequestPermit<'_>> {
+        if priority == Priority::Background && self.interactive_waiting.load(Ordering::SeqCst) > 0 {
+            return None;
+        }
+        let max = self.max_in_flight.load(Ordering::SeqCst);
+        self.in_flight
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |current| (current < max).then_some(current + 1))
+            .ok()
+            .map(|_| RequestPermit(&self.in_flight))
+    }
+
+    // Reserve a slot if one is free at `now`, otherwise return how long to wait
+    pub fn try_acquire(&self, token: &str, priority: Priority, now: Instant) -> Option<Duration> {
+        // Background work yields whenever an interactive request is queued
+        if priority == Priority::Background && self.interactive_waiting.load(Ordering::SeqCst) > 0 {
+            return Some(POLL_INTERVAL);
+        }
+
+        let mut states = self.states.lock().unwrap();
+        let state = states.entry(token_key(token)).or_insert_with(|| TokenState::new(now));
+
+        let mut earliest = state.next_allowed;
+        if let Some(limited_until) = state.limited_until {
+            earliest = earliest.max(limited_until);
+        }
+        if priority == Priority::Background {
+            earliest = earliest.max(state.next_background_allowed);
+        }
+
+        if earliest > now {
+            return Some(earliest - now);
+        }
+
+        state.next_allowed = now + REQUEST_SPACING;
+        if priority == Priority::Background {
+            state.next_background_allowed = now + REQUEST_SPACING.div_f64(BACKGROUND_RATE_FRACTION);
+        }
+        None
+    }
+
+    // Hold off all requests for this token until the server says we may retry
+    pub fn record_rate_limit(&self, token: &str, retry_after: Duration, source: RateLimitSource) {
+        let now = Instant::now();
+        let mut states = self.states.lock().unwrap();
+        let state = states.entry(token_key(token)).or_insert_with(|| TokenState::new(now));
+        state.limited_until = Some(now + retry_after);
+        state.limited_until_wall = Some(chrono::Utc::now() + chrono::Duration::from_std(retry_after).unwrap_or_default());
+        state.limited_source = source;
+        drop(states);
+        self.changes.send_modify(|version| *version += 1);
+    }
+
+    // How much longer this token is held off after a 429, and where that wait came from
+    pub fn limit_state(&self, token: &str, now: Instant) -> Option<(Duration, RateLimitSource)> {
+        let states = self.states.lock().unwrap();
+        let state = states.get(&token_key(token))?;
+        state
+            .limited_until
+            .filter(|until| *until > now)
+            .map(|until| (until - now, state.limited_source))
+    }
+
+    // How much longer this token is held off after a 429, if at all
+    pub fn limited_for(&self, token: &str) -> Option<Duration> {
+        let now = Instant::now();
+        let states = self.states.lock().unwrap();
+        states
+            .get(&token_key(token))
+            .and_then(|state| state.limited_until)
+            .filter(|until| *until > now)
+            .map(|until| until - now)
+    }
+
+    // After a suspend the stored Instants can't be trusted: monotonic time may
+    // not have moved while the wall clock did. Rebuild every 429 deadline from
+    // its wall-clock time and let spaced-out requests go now.
+    pub fn resync_after_suspend(&self, now: Instant, wall_now: chrono::DateTime<chrono::Utc>) {
+        let mut states = self.states.lock().unwrap();
+        for state in states.values_mut() {
+            let remaining = state
+                .limited_until_wall
+                .and_then(|until| (until - wall_now).to_std().ok())
+                .filter(|remaining| !remaining.is_zero());
+            state.limited_until = remaining.map(|remaining| now + remaining);
+            if remaining.is_none() {
+                state.limited_until_wall = None;
+            }
+            state.next_allowed = state.next_allowed.min(now);
+            state.next_background_allowed = state.next_background_allowed.min(now);
+        }
+        drop(states);
+        self.changes.send_modify(|version| *version += 1);
+    }
+
+    // Count a request that reached Notion, whatever the response
+    pub fn record_request(&self, token: &str, now: Instant) {
+        let mut states = self.states.lock().unwrap();
+        let state = states.entry(token_key(token)).or_insert_with(|| TokenState::new(now));
+        state.history.record(now);
+    }
+
+    // Usage for a token; a token nothing was recorded for is `Unknown`, not "ok",
+    // and so is a missing one
+    pub fn info(&self, token: &str, now: Instant) -> RateLimitInfo {
+        if token.trim().is_empty() {
+            return RateLimitManager::unknown_info();
+        }
+        let states = self.states.lock().unwrap();
+        let state = states.get(&token_key(token));
+        let retry_in = state
+            .and_then(|state| state.limited_until)
+            .filter(|until| *until > now)
+            .map(|until| until - now);
+        let recent = state.map_or(0, |state| state.history.requests_in(RATE_WINDOW, now));
+        let status = match (state, retry_in) {
+            (None, _) => RateLimitState::Unknown,
+            (Some(_), Some(_)) => RateLimitState::Limited,
+            (Some(_), None) => RateLimitState::Ok,
+        };
+        RateLimitInfo {
+            state: status,
+            limited: status == RateLimitState::Limited,
+            retry_in_secs: retry_in.map(|wait| wait.as_secs().max(1)),
+            source: retry_in.and(state.map(|state| state.limited_source)),
+            limit: None,
+            remaining: None,
+            allowed_rps: 1.0 / REQUEST_SPACING.as_secs_f64(),
+            current_rps: recent as f64 / RATE_WINDOW.as_secs_f64(),
+            recent_requests_last_minute: state.map_or(0, |state| state.history.requests_in(Duration::from_secs(60), now)),
+        }
+    }
+
+    // Requests per minute for a token, oldest first; zeros when nothing was sent
+    pub fn history(&self, token: &str, minutes: usize, now: Instant) -> Vec<u32> {
+        let states = self.states.lock().unwrap();
+        match states.get(&token_key(token)) {
+            Some(state) => state.history.per_minute(minutes, now),
+            None => vec![0; minutes.clamp(1, HISTORY_MINUTES)],
+        }
+    }
+
+    // What to report when there is no token to ask about
+    pub fn unknown_info() -> RateLimitInfo {
+        RateLimitInfo {
+            state: RateLimitState::Unknown,
+            limited: false,
+            retry_in_secs: None,
+            source: None,
+            limit: None,
+            remaining: None,
+            allowed_rps: 1.0 / REQUEST_SPACING.as_secs_f64(),
+            current_rps: 0.0,
+            recent_requests_last_minute: 0,
+        }
+    }
+
+    // Forget everything about a token (e.g. when it is replaced)
+    pub fn clear(&self, token: &str) {
+        self.states.lock().unwrap().remove(&token_key(token));
+    }
+}
+
+// Usage of the main token, for the settings window's API usage panel
+#[tauri::command]
+pub fn get_rate_limit_info(state: State<'_, AppState>) -> RateLimitInfo {
+    let token = state.config.lock().unwrap().secrets.main_token().to_string();
+    RATE_LIMITER.info(&token, Instant::now())
+}
+
+// Requests per minute over the last `minutes` minutes for the main token, oldest first
+#[tauri::command]
+pub fn get_rate_limit_history(minutes: usize, state: State<'_, AppState>) -> Vec<u32> {
+    let token = state.config.lock().unwrap().secrets.main_token().to_string();
+    RATE_LIMITER.history(&token, minutes, Instant::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_notion::{MockNotion, MockReply};
+    use crate::notion::NotionApiClient;
+    use serde_json::{json, Value};
+
+    // Answers every block read, taking `body_delay` to send the body
+    async fn slow_blocks(body_delay: Duration) -> MockNotion {
+        MockNotion::start(move |request| {
+            let id = request.path.trim_start_matches("/v1/blocks/");
+            MockReply::json(200, json!({ "object": "block", "id": id, "type": "divider", "divider": {} })).slow_body(body_delay)
+        })
+        .await
+    }
+
+    // Read block `id` in the background with `token` at `priority`
+    fn read_block(token: String, priority: Priority, id: String) -> tokio::task::JoinHandle<Result<(), String>> {
+        tokio::spawn(async move {
+            let client = NotionApiClient::new(token)?.with_priority(priority);
+            client.get_block(&id).await.map(|_| ())
+        })
+    }
+
+    fn read_order(mock: &MockNotion) -> Vec<String> {
+        mock.requests().iter().map(|request| request.path.trim_start_matches("/v1/blocks/").to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn in_flight_slots_are_held_until_the_body_is_read() {
+        let mock = slow_blocks(Duration::from_millis(400)).await;
+        let mut tokens = vec![mock.token.clone()];
+        tokens.extend((1..2 * DEFAULT_MAX_IN_FLIGHT).map(|_| mock.another_token()));
+
+        // One request per token, so request spacing doesn't hold any of them back
+        let reads: Vec<_> = tokens
+            .into_iter()
+            .enumerate()
+            .map(|(index, token)| read_block(token, Priority::Interactive, format!("block-{}", index)))
+            .collect();
+        for read in reads {
+            read.await.unwrap().unwrap();
+        }
+
+        assert_eq!(mock.requests().len(), 2 * DEFAULT_MAX_IN_FLIGHT);
+        assert!(mock.most_open() <= DEFAULT_MAX_IN_FLIGHT, "{} answered at once", mock.most_open());
+    }
+
+    #[tokio::test]
+    async fn interactive_requests_go_ahead_of_queued_background_ones() {
+        let mock = slow_blocks(Duration::ZERO).await;
+        let background: Vec<_> = (0..4)
+            .map(|index| read_block(mock.token.clone(), Priority::Background, format!("background-{}", index)))
+            .collect();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let interactive = read_block(mock.token.clone(), Priority::Interactive, "interactive".into());
+
+        interactive.await.unwrap().unwrap();
+        for read in background {
+            read.await.unwrap().unwrap();
+        }
+
+        // Only the background read already sent when the interactive one arrived goes first
+        let order = read_order(&mock);
+        assert_eq!(order.len(), 5);
+        assert!(order.iter().position(|id| id == "interactive") <= Some(1), "{:?}", order);
+    }
+
+    #[tokio::test]
+    async fn waiting_requests_are_sent_in_arrival_order() {
+        let mock = slow_blocks(Duration::ZERO).await;
+        let mut reads = Vec::new();
+        for index in 0..5 {
+            reads.push(read_block(mock.token.clone(), Priority::Interactive, format!("block-{}", index)));
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        for read in reads {
+            read.await.unwrap().unwrap();
+        }
+
+        let expected: Vec<String> = (0..5).map(|index| format!("block-{}", index)).collect();
+        assert_eq!(read_order(&mock), expected);
+    }
+
+    fn minutes(count: u64) -> Duration {
+        Duration::from_secs(60 * count)
+    }
+
+    #[test]
+    fn requests_roll_over_into_per_minute_buckets() {
+        let start = Instant::now();
+        let mut history = RequestHistory::new(start);
+        history.record(start);
+        history.record(start + Duration::from_secs(59));
+        history.record(start + Duration::from_secs(60));
+        // Two quiet minutes, then one more request
+        history.record(start + minutes(3) + Duration::from_secs(30));
+
+        let now = start + minutes(3) + Duration::from_secs(45);
+        assert_eq!(history.per_minute(5, now), [0, 2, 1, 0, 1]);
+        assert_eq!(history.per_minute(1, now), [1]);
+        // Asking for none still gives the current minute; asking for more is capped
+        assert_eq!(history.per_minute(0, now), [1]);
+        assert_eq!(history.per_minute(HISTORY_MINUTES * 2, now).len(), HISTORY_MINUTES);
+
+        assert_eq!(history.requests_in(Duration::from_secs(60), now), 1);
+        assert_eq!(history.requests_in(Duration::from_secs(10), now), 0);
+    }
+
+    #[test]
+    fn old_minutes_fall_off_the_end() {
+        let start = Instant::now();
+        let mut history = RequestHistory::new(start);
+        // A request every minute for three times as long as the history is kept
+        let kept = HISTORY_MINUTES as u64;
+        for minute in 0..kept * 3 {
+            history.record(start + minutes(minute));
+            history.record(start + minutes(minute) + Duration::from_secs(30));
+            assert!(history.minutes.len() <= HISTORY_MINUTES);
+            assert!(history.last_minute.len() <= 3);
+        }
+
+        let now = start + minutes(kept * 3 - 1);
+        let counts = history.per_minute(HISTORY_MINUTES, now);
+        assert_eq!(counts, vec![2; HISTORY_MINUTES]);
+        assert_eq!(history.minutes.front().unwrap().0, kept * 2);
+
+        // After a long silence the next request finds nothing left over
+        let later = now + minutes(kept * 2);
+        history.record(later);
+        assert_eq!(history.minutes.len(), 1);
+        assert_eq!(history.last_minute.len(), 1);
+        let mut expected = vec![0; HISTORY_MINUTES];
+        expected[HISTORY_MINUTES - 1] = 1;
+        assert_eq!(history.per_minute(HISTORY_MINUTES, later), expected);
+    }
+
+    #[test]
+    fn successes_feed_the_history_before_any_limit() {
+        let limiter = RateLimitManager::new();
+        let start = Instant::now();
+        assert_eq!(limiter.history("secret_history", 3, start), [0, 0, 0]);
+
+        for second in [0, 20, 40, 70] {
+            limiter.record_request("secret_history", start + Duration::from_secs(second));
+        }
+        let now = start + Duration::from_secs(75);
+        assert_eq!(limiter.history("secret_history", 3, now), [0, 3, 1]);
+        let info = limiter.info("secret_history", now);
+        assert_eq!((info.state, info.recent_requests_last_minute), (RateLimitState::Ok, 3));
+        assert_eq!(info.current_rps, 0.1);
+        // Another token's history is its own
+        assert_eq!(limiter.history("secret_other", 3, now), [0, 0, 0]);
+    }
+
+    #[test]
+    fn usage_is_unknown_then_limited_then_ok_again() {
+        let limiter = RateLimitManager::new();
+        let now = Instant::now();
+
+        // Nothing sent yet, or no token at all: unknown, without any state being made
+        for token in ["secret_states", "", "  "] {
+            let info = limiter.info(token, now);
+            assert_eq!(info, RateLimitManager::unknown_info());
+            let json = serde_json::to_value(&info).unwrap();
+            assert_eq!((&json["state"], &json["limited"], &json["retry_in_secs"], &json["source"]), (&json!("unknown"), &json!(false), &Value::Null, &Value::Null));
+        }
+        assert!(limiter.states.lock().unwrap().is_empty());
+
+        limiter.record_request("secret_states", now);
+        let json = serde_json::to_value(limiter.info("secret_states", now)).unwrap();
+        assert_eq!((&json["state"], &json["limited"], &json["source"]), (&json!("ok"), &json!(false), &Value::Null));
+
+        // A 429 with Retry-After: limited, with the wait and where it came from
+        limiter.record_rate_limit("secret_states", Duration::from_secs(30), RateLimitSource::Headers);
+        let limited_at = Instant::now();
+        let json = serde_json::to_value(limiter.info("secret_states", limited_at)).unwrap();
+        assert_eq!((&json["state"], &json["limited"], &json["source"]), (&json!("limited"), &json!(true), &json!("headers")));
+        assert!((29..=30).contains(&json["retry_in_secs"].as_u64().unwrap()), "{}", json);
+
+        // Once the wait is over it recovers to ok, and the wait and source go away
+        let info = limiter.info("secret_states", limited_at + Duration::from_secs(31));
+        assert_eq!((info.state, info.limited, info.retry_in_secs, info.source), (RateLimitState::Ok, false, None, None));
+    }
+
+    #[test]
+    fn a_fallback_wait_is_tagged_as_backoff() {
+        let limiter = RateLimitManager::new();
+        limiter.record_rate_limit("secret_backoff", Duration::from_millis(200), RateLimitSource::Backoff);
+        let json = serde_json::to_value(limiter.info("secret_backoff", Instant::now())).unwrap();
+        assert_eq!((&json["state"], &json["source"], &json["retry_in_secs"]), (&json!("limited"), &json!("backoff"), &json!(1)));
+        // Another token isn't held up by it
+        assert_eq!(limiter.info("secret_not_limited", Instant::now()).state, RateLimitState::Unknown);
+    }
+}
@@ -0,0 +1,415 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::config::app_data_path;
+use crate::local_crypto;
+use crate::notion::{AppendResponse, PartialDelivery};
+
+// Receipts are kept in a JSONL file next to the config, compacted to the newest
+// entries. They double as the local note history, so the cap and whether note
+// text is kept at all follow the history settings.
+const RECEIPTS_FILE: &str = "receipts.jsonl";
+static MAX_RECEIPTS: AtomicUsize = AtomicUsize::new(500);
+static KEEP_NOTE_TEXT: AtomicBool = AtomicBool::new(true);
+
+// What produced the delivered blocks
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliverySource {
+    // A note formatted by the app
+    #[default]
+    Note,
+    // Block JSON passed through unformatted
+    Raw,
+}
+
+// How the note entered the app, for history search
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NoteOrigin {
+    Typed,
+    Clipboard,
+    Cli,
+    Uri,
+    // Sent later from the offline queue
+    Queue,
+}
+
+// Proof that a capture reached Notion; only written after a confirmed 2xx
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeliveryReceipt {
+    pub capture_id: String,
+    pub page_id: String,
+    pub block_ids: Vec<String>,
+    // Notion's x-request-id, useful when talking to Notion support
+    pub request_id: Option<String>,
+    pub status: u16,
+    // RFC3339 time the delivery was confirmed
+    pub delivered_at: String,
+    pub attempts: u32,
+    // Set when only some of a multi-request append landed
+    #[serde(default)]
+    pub partial: Option<PartialReceipt>,
+    // RFC3339 time the note says it was written, when a leading timestamp overrode the send time
+    #[serde(default)]
+    pub captured_at: Option<String>,
+    #[serde(default)]
+    pub source: DeliverySource,
+    // Where the note was before it was moved to `page_id`
+    #[serde(default)]
+    pub moved_from: Option<MoveRecord>,
+    // The note was copied to its new page but the original couldn't be removed;
+    // both copies exist until the user resolves it
+    #[serde(default)]
+    pub move_incomplete: bool,
+    // Label of the focus session the note was captured in
+    #[serde(default)]
+    pub focus_label: Option<String>,
+    // The note as sent, for history search; sealed on disk when local encryption is on
+    #[serde(default)]
+    pub note_text: Option<String>,
+    #[serde(default)]
+    pub origin: Option<NoteOrigin>,
+    // Capture profile whose token delivered the note; None for the main token
+    #[serde(default)]
+    pub profile: Option<String>,
+}
+
+// Audit trail of a move between pages
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MoveRecord {
+    pub page_id: String,
+    pub block_ids: Vec<String>,
+    pub moved_at: String,
+}
+
+impl DeliveryReceipt {
+    // Receipt for what an append delivered, including which chunks landed when it was partial
+    pub fn new(capture_id: String, page_id: String, response: AppendResponse, partial: Option<&PartialDelivery>) -> Self {
+        DeliveryReceipt {
+            capture_id,
+            page_id,
+            block_ids: response.block_ids,
+            request_id: response.request_id,
+            status: response.status,
+            delivered_at: chrono::Utc::now().to_rfc3339(),
+            attempts: response.attempts,
+            partial: partial.map(|p| PartialReceipt {
+                delivered_chunks: p.delivered_chunks,
+                total_chunks: p.total_chunks,
+                remainder_id: None,
+            }),
+            captured_at: None,
+            source: DeliverySource::Note,
+            moved_from: None,
+            move_incomplete: false,
+            focus_label: None,
+            note_text: None,
+            origin: None,
+            profile: None,
+        }
+    }
+}
+
+// Which chunks of a multi-request append were delivered (the first `delivered_chunks`)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PartialReceipt {
+    pub delivered_chunks: usize,
+    pub total_chunks: usize,
+    // The queued item sending the chunks that didn't land
+    #[serde(default)]
+    pub remainder_id: Option<String>,
+}
+
+lazy_static::lazy_static! {
+    // Serializes access to the receipts file
+    static ref RECEIPTS_LOCK: Mutex<()> = Mutex::new(());
+    // Lines in each receipts file as of the last write, counted once on first use
+    static ref RECEIPT_LINES: Mutex<HashMap<PathBuf, usize>> = Mutex::new(HashMap::new());
+}
+
+// Receipts allowed past the cap before the file is compacted, so a capture
+// appends one line instead of rewriting the file every time
+fn compaction_slack(max_receipts: usize) -> usize {
+    (max_receipts / 10).max(10)
+}
+
+static CAPTURE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+// Identifier for a single capture, unique within this machine
+pub fn new_capture_id() -> String {
+    format!(
+        "{:x}-{:x}",
+        chrono::Utc::now().timestamp_millis(),
+        CAPTURE_COUNTER.fetch_add(1, Ordering::SeqCst)
+    )
+}
+
+// Mirror the history settings; with history off, new receipts are written without their text
+pub fn set_history_options(keep_note_text: bool, max_entries: usize) {
+    KEEP_NOTE_TEXT.store(keep_note_text, Ordering::Relaxed);
+    MAX_RECEIPTS.store(max_entries.max(1), Ordering::Relaxed);
+}
+
+pub fn keeps_note_text() -> bool {
+    KEEP_NOTE_TEXT.load(Ordering::Relaxed)
+}
+
+// A receipt as one JSONL line, with its note text sealed
+fn to_line(receipt: &DeliveryReceipt) -> Result<String, String> {
+    let sealed = DeliveryReceipt {
+        note_text: local_crypto::seal_optional(&receipt.note_text)?,
+        ..receipt.clone()
+    };
+    serde_json::to_string(&sealed).map_err(|e| format!("Failed to serialize receipt: {}", e))
+}
+
+// Note text stays as stored (possibly sealed); callers that show it open it
+pub fn receipts_path() -> Result<std::path::PathBuf, String> {
+    app_data_path(RECEIPTS_FILE)
+}
+
+fn read_all() -> Result<Vec<DeliveryReceipt>, String> {
+    let path = app_data_path(RECEIPTS_FILE)?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let contents = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read receipts: {}", e))?;
+
+    // Skip lines that don't parse rather than losing the whole file
+    let mut receipts: Vec<DeliveryReceipt> = contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    // Up to the slack's worth of the oldest are still on disk, waiting for compaction
+    let max_receipts = MAX_RECEIPTS.load(Ordering::Relaxed);
+    if receipts.len() > max_receipts {
+        receipts.drain(..receipts.len() - max_receipts);
+    }
+    Ok(receipts)
+}
+
+fn write_all(receipts: &[DeliveryReceipt]) -> Result<(), String> {
+    let path = app_data_path(RECEIPTS_FILE)?;
+    let mut contents = String::new();
+    for receipt in receipts {
+        let line = to_line(receipt)?;
+        contents.push_str(&line);
+        contents.push('\n');
+    }
+
+    fs::write(&path, contents).map_err(|e| format!("Failed to write receipts: {}", e))?;
+    RECEIPT_LINES.lock().unwrap().insert(path, receipts.len());
+    Ok(())
+}
+
+// Append a receipt, compacting the file once it grows well past the cap
+pub fn record_receipt(receipt: &DeliveryReceipt) -> Result<(), String> {
+    record_receipt_capped(receipt, MAX_RECEIPTS.load(Ordering::Relaxed))
+}
+
+fn record_receipt_capped(receipt: &DeliveryReceipt, max_receipts: usize) -> Result<(), String> {
+    let _lock = RECEIPTS_LOCK.lock().unwrap();
+    let path = app_data_path(RECEIPTS_FILE)?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create data directory: {}", e))?;
+    }
+
+    let line = if keeps_note_text() {
+        to_line(receipt)?
+    } else {
+        to_line(&DeliveryReceipt { note_text: None, ..receipt.clone() })?
+    };
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("Failed to open receipts: {}", e))?;
+    writeln!(file, "{}", line).map_err(|e| format!("Failed to write receipt: {}", e))?;
+
+    let lines = {
+        let mut counts = RECEIPT_LINES.lock().unwrap();
+        let lines = match counts.get(&path) {
+            Some(lines) => lines + 1,
+            None => fs::read_to_string(&path).map(|contents| contents.lines().count()).unwrap_or(1),
+        };
+        counts.insert(path, lines);
+        lines
+    };
+    if lines > max_receipts + compaction_slack(max_receipts) {
+        let receipts = read_all()?;
+        write_all(&receipts[receipts.len().saturating_sub(max_receipts)..])?;
+    }
+
+    Ok(())
+}
+
+// Rewrite the file in the current encryption mode
+pub fn reseal() -> Result<(), String> {
+    let _lock = RECEIPTS_LOCK.lock().unwrap();
+    let receipts = read_all()?
+        .into_iter()
+        .map(|receipt| {
+            Ok(DeliveryReceipt {
+                note_text: local_crypto::open_optional(&receipt.note_text)?,
+                ..receipt
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    write_all(&receipts)
+}
+
+// Held while searching, so compaction can't rewrite the file mid-read
+pub fn read_lock() -> std::sync::MutexGuard<'static, ()> {
+    RECEIPTS_LOCK.lock().unwrap()
+}
+
+// Remove every receipt; returns how many there were
+pub fn clear() -> Result<usize, String> {
+    let _lock = RECEIPTS_LOCK.lock().unwrap();
+    let count = read_all()?.len();
+    let path = app_data_path(RECEIPTS_FILE)?;
+    if path.exists() {
+        fs::remove_file(&path).map_err(|e| format!("Failed to remove receipts: {}", e))?;
+    }
+    RECEIPT_LINES.lock().unwrap().insert(path, 0);
+    Ok(count)
+}
+
+// Look up the receipt for a capture, if it was delivered
+pub fn find_receipt(capture_id: &str) -> Result<Option<DeliveryReceipt>, String> {
+    let _lock = RECEIPTS_LOCK.lock().unwrap();
+    Ok(read_all()?.into_iter().rev().find(|r| r.capture_id == capture_id))
+}
+
+// Change the newest receipt for a capture in place, returning the updated receipt
+pub fn update_receipt(
+    capture_id: &str,
+    change: impl FnOnce(&mut DeliveryReceipt),
+) -> Result<DeliveryReceipt, String> {
+    let _lock = RECEIPTS_LOCK.lock().unwrap();
+    let mut receipts = read_all()?;
+    let receipt = receipts
+        .iter_mut()
+        .rev()
+        .find(|r| r.capture_id == capture_id)
+        .ok_or_else(|| format!("No delivery receipt for capture {}", capture_id))?;
+    change(receipt);
+    let updated = receipt.clone();
+    write_all(&receipts)?;
+    Ok(updated)
+}
+
+// Receipts newest first, skipping `offset`; note text stays as stored (possibly sealed)
+pub fn history_page(offset: usize, limit: usize) -> Result<Vec<DeliveryReceipt>, String> {
+    let _lock = RECEIPTS_LOCK.lock().unwrap();
+    Ok(read_all()?.into_iter().rev().skip(offset).take(limit).collect())
+}
+
+// The newest receipts, newest first. Note text is left out: these go into diagnostics.
+pub fn recent_receipts(limit: usize) -> Vec<DeliveryReceipt> {
+    let _lock = RECEIPTS_LOCK.lock().unwrap();
+    read_all()
+        .unwrap_or_default()
+        .into_iter()
+        .rev()
+        .take(limit)
+        .map(|receipt| DeliveryReceipt { note_text: None, ..receipt })
+        .collect()
+}
+
+#[tauri::command]
+pub fn get_delivery_receipt(capture_id: String) -> Result<Option<DeliveryReceipt>, String> {
+    find_receipt(&capture_id)?
+        .map(|receipt| {
+            Ok(DeliveryReceipt {
+                note_text: local_crypto::open_optional(&receipt.note_text)?,
+                ..receipt
+            })
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::use_test_data_dir;
+
+    fn receipt(capture_id: &str) -> DeliveryReceipt {
+        let response = AppendResponse {
+            block_ids: vec![format!("block-{}", capture_id)],
+            request_id: Some("7f3a0c1e".to_string()),
+            status: 200,
+            attempts: 1,
+        };
+        DeliveryReceipt::new(capture_id.to_string(), "page-1".to_string(), response, None)
+    }
+
+    fn lines_on_disk() -> usize {
+        fs::read_to_string(receipts_path().unwrap()).unwrap().lines().count()
+    }
+
+    #[test]
+    fn recorded_receipts_can_be_found() {
+        use_test_data_dir();
+        record_receipt(&receipt("a")).unwrap();
+        record_receipt(&receipt("b")).unwrap();
+
+        let found = find_receipt("b").unwrap().unwrap();
+        assert_eq!(found.block_ids, vec!["block-b"]);
+        assert_eq!(found.request_id.as_deref(), Some("7f3a0c1e"));
+        assert!(find_receipt("never-sent").unwrap().is_none());
+        let newest: Vec<String> = recent_receipts(5).into_iter().map(|r| r.capture_id).collect();
+        assert_eq!(newest, vec!["b", "a"]);
+    }
+
+    #[test]
+    fn compaction_waits_for_the_slack_then_keeps_the_newest() {
+        use_test_data_dir();
+        let max = 5;
+        let slack = compaction_slack(max);
+        for index in 0..max + slack {
+            record_receipt_capped(&receipt(&index.to_string()), max).unwrap();
+        }
+        // Every capture so far only appended a line
+        assert_eq!(lines_on_disk(), max + slack);
+
+        record_receipt_capped(&receipt("last"), max).unwrap();
+        assert_eq!(lines_on_disk(), max);
+        assert!(find_receipt("last").unwrap().is_some());
+        assert!(find_receipt("0").unwrap().is_none());
+    }
+
+    #[test]
+    fn line_count_survives_other_rewrites() {
+        use_test_data_dir();
+        let max = 3;
+        for index in 0..5 {
+            record_receipt_capped(&receipt(&index.to_string()), max).unwrap();
+        }
+        assert_eq!(clear().unwrap(), 5);
+        for index in 0..max + compaction_slack(max) {
+            record_receipt_capped(&receipt(&index.to_string()), max).unwrap();
+        }
+        assert_eq!(lines_on_disk(), max + compaction_slack(max));
+    }
+
+    #[test]
+    fn updates_rewrite_the_matching_receipt() {
+        use_test_data_dir();
+        record_receipt(&receipt("a")).unwrap();
+        let updated = update_receipt("a", |r| r.move_incomplete = true).unwrap();
+        assert!(updated.move_incomplete);
+        assert!(find_receipt("a").unwrap().unwrap().move_incomplete);
+        assert!(update_receipt("missing", |_| {}).is_err());
+    }
+}
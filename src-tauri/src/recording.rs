@@ -0,0 +1,323 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde_json::{json, Value};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::config::app_data_path;
+
+// Recording mode writes every Notion response, sanitized, to a session directory
+// so users can attach exact payloads to bug reports without leaking their notes
+const RECORDINGS_DIR: &str = "recordings";
+
+// Recording stops on its own once either cap is reached
+const MAX_RECORDING_BYTES: u64 = 20 * 1024 * 1024;
+const MAX_RECORDING_DURATION: Duration = Duration::from_secs(30 * 60);
+
+// Keys whose string values are structure (ids, types, timestamps, cursors and
+// error codes) and are kept; every other string is treated as user content
+const STRUCTURAL_KEYS: &[&str] = &[
+    "object",
+    "id",
+    "type",
+    "created_time",
+    "last_edited_time",
+    "next_cursor",
+    "request_id",
+    "code",
+    "color",
+];
+
+fn is_structural(key: &str) -> bool {
+    STRUCTURAL_KEYS.contains(&key) || key.ends_with("_id")
+}
+
+// Method and path of the request a response belongs to, attached to the response by the client
+#[derive(Debug, Clone)]
+pub struct RequestLine {
+    pub method: String,
+    pub path: String,
+}
+
+struct Session {
+    dir: PathBuf,
+    started_at: Instant,
+    bytes_written: u64,
+    responses: u32,
+}
+
+#[derive(Default)]
+struct RecordingState {
+    active: Option<Session>,
+    // Last session directory, kept after an auto-stop so it can still be packaged
+    last_dir: Option<PathBuf>,
+}
+
+lazy_static::lazy_static! {
+    static ref RECORDING: Mutex<RecordingState> = Mutex::new(RecordingState::default());
+}
+
+pub fn is_recording() -> bool {
+    RECORDING.lock().unwrap().active.is_some()
+}
+
+// Replace user content with same-length placeholders, keeping keys and structure intact.
+// Strings are kept only under structural keys; numbers and booleans are kept.
+pub fn sanitize(value: &Value) -> Value {
+    sanitize_value(value, false)
+}
+
+fn sanitize_value(value: &Value, structural: bool) -> Value {
+    match value {
+        Value::String(text) if !structural => Value::String(placeholder(text)),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| (key.clone(), sanitize_value(value, is_structural(key))))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(|item| sanitize_value(item, structural)).collect()),
+        other => other.clone(),
+    }
+}
+
+// Same number of characters, none of the content
+fn placeholder(text: &str) -> String {
+    "x".repeat(text.chars().count())
+}
+
+// File-name-safe form of a request path
+fn path_slug(path: &str) -> String {
+    path.trim_matches('/')
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+// Start a new recording session; returns its directory
+pub fn start() -> Result<PathBuf, String> {
+    let mut state = RECORDING.lock().unwrap();
+    if let Some(session) = &state.active {
+        return Ok(session.dir.clone());
+    }
+
+    let dir = app_data_path(RECORDINGS_DIR)?
+        .join(format!("session-{}", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create recording directory: {}", e))?;
+
+    state.active = Some(Session {
+        dir: dir.clone(),
+        started_at: Instant::now(),
+        bytes_written: 0,
+        responses: 0,
+    });
+    state.last_dir = Some(dir.clone());
+    Ok(dir)
+}
+
+// Stop any recording and delete every recorded session; returns how many were removed
+pub fn clear() -> Result<usize, String> {
+    let mut state = RECORDING.lock().unwrap();
+    state.active.take();
+    state.last_dir.take();
+
+    let dir = app_data_path(RECORDINGS_DIR)?;
+    if !dir.exists() {
+        return Ok(0);
+    }
+    let sessions = fs::read_dir(&dir)
+        .map_err(|e| format!("Failed to read recordings: {}", e))?
+        .count();
+    fs::remove_dir_all(&dir).map_err(|e| format!("Failed to remove recordings: {}", e))?;
+    Ok(sessions)
+}
+
+// Stop recording (if active); returns the session directory
+pub fn stop() -> Option<PathBuf> {
+    let mut state = RECORDING.lock().unwrap();
+    state.active.take();
+    state.last_dir.clone()
+}
+
+// Write one sanitized response to the active session. Never fails the request it belongs to.
+pub fn record_response(request: Option<&RequestLine>, status: u16, body: &Value) {
+    let mut state = RECORDING.lock().unwrap();
+    let Some(session) = state.active.as_mut() else {
+        return;
+    };
+
+    if session.started_at.elapsed() > MAX_RECORDING_DURATION || session.bytes_written > MAX_RECORDING_BYTES {
+        eprintln!("Recording reached its cap and was stopped");
+        state.active = None;
+        return;
+    }
+
+    let (method, path) = request
+        .map(|line| (line.method.as_str(), line.path.as_str()))
+        .unwrap_or(("UNKNOWN", ""));
+    let entry = json!({
+        "method": method,
+        "path": path,
+        "status": status,
+        "body": sanitize(body),
+    });
+
+    session.responses += 1;
+    let file = session.dir.join(format!("{:04}-{}-{}.json", session.responses, method, path_slug(path)));
+    match serde_json::to_vec_pretty(&entry) {
+        Ok(contents) => match fs::write(&file, &contents) {
+            Ok(()) => session.bytes_written += contents.len() as u64,
+            Err(e) => eprintln!("Failed to write recorded response: {}", e),
+        },
+        Err(e) => eprintln!("Failed to serialize recorded response: {}", e),
+    }
+}
+
+// Compress a session directory into a .tar.gz next to it
+pub fn package(dir: &Path) -> Result<PathBuf, String> {
+    let archive_path = dir.with_extension("tar.gz");
+    let file = File::create(&archive_path).map_err(|e| format!("Failed to create archive: {}", e))?;
+
+    let mut archive = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    let name = dir.file_name().map(PathBuf::from).unwrap_or_else(|| PathBuf::from("session"));
+    archive.append_dir_all(name, dir).map_err(|e| format!("Failed to package recording: {}", e))?;
+    archive
+        .into_inner()
+        .and_then(|encoder| encoder.finish())
+        .map_err(|e| format!("Failed to package recording: {}", e))?;
+
+    Ok(archive_path)
+}
+
+// Start recording sanitized Notion responses; returns the session directory
+#[tauri::command]
+pub fn start_recording() -> Result<String, String> {
+    start().map(|dir| dir.to_string_lossy().into_owned())
+}
+
+// Stop recording and package the session; returns the archive path to attach to an issue
+#[tauri::command]
+pub fn stop_recording_and_package() -> Result<String, String> {
+    let dir = stop().ok_or("No recording session to package")?;
+    package(&dir).map(|path| path.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "Meet Dana at 5";
+
+    // Every string left in the payload, with the key it sits under
+    fn strings(value: &Value, key: &str, found: &mut Vec<(String, String)>) {
+        match value {
+            Value::String(text) => found.push((key.to_string(), text.clone())),
+            Value::Object(map) => map.iter().for_each(|(key, value)| strings(value, key, found)),
+            Value::Array(items) => items.iter().for_each(|item| strings(item, key, found)),
+            _ => {}
+        }
+    }
+
+    fn assert_scrubbed(payload: Value) -> Value {
+        let sanitized = sanitize(&payload);
+        let mut found = Vec::new();
+        strings(&sanitized, "", &mut found);
+        for (key, text) in &found {
+            assert!(!text.contains("Dana") && !text.contains("555"), "{} leaked: {}", key, text);
+            if !is_structural(key) {
+                assert!(text.chars().all(|c| c == 'x'), "{} kept: {}", key, text);
+            }
+        }
+        sanitized
+    }
+
+    fn rich_text(text: &str) -> Value {
+        json!([{
+            "type": "text",
+            "text": { "content": text, "link": { "url": "https://dana.example" } },
+            "annotations": { "bold": false, "color": "default" },
+            "plain_text": text,
+            "href": "https://dana.example"
+        }])
+    }
+
+    #[test]
+    fn search_results_keep_ids_and_lose_titles() {
+        let sanitized = assert_scrubbed(json!({
+            "object": "list",
+            "results": [{
+                "object": "page",
+                "id": "page-1",
+                "created_time": "2024-03-01T09:00:00.000Z",
+                "last_edited_time": "2024-03-01T09:05:00.000Z",
+                "parent": { "type": "workspace", "workspace": true },
+                "url": "https://www.notion.so/Dana-page-1",
+                "icon": { "type": "emoji", "emoji": "📞" },
+                "properties": { "title": { "id": "title", "type": "title", "title": rich_text(SECRET) } }
+            }],
+            "next_cursor": "cursor-2",
+            "has_more": true
+        }));
+        let page = &sanitized["results"][0];
+        assert_eq!((page["id"].as_str(), page["object"].as_str()), (Some("page-1"), Some("page")));
+        assert_eq!(page["last_edited_time"], "2024-03-01T09:05:00.000Z");
+        assert_eq!(sanitized["next_cursor"], "cursor-2");
+        assert_eq!(sanitized["has_more"], true);
+        let title = &page["properties"]["title"]["title"][0];
+        assert_eq!(title["plain_text"].as_str().unwrap().len(), SECRET.len());
+        assert_eq!(title["annotations"]["color"], "default");
+    }
+
+    #[test]
+    fn block_children_lose_every_kind_of_text() {
+        assert_scrubbed(json!({
+            "object": "list",
+            "results": [
+                { "object": "block", "id": "b1", "type": "paragraph", "paragraph": { "rich_text": rich_text(SECRET) } },
+                { "object": "block", "id": "b2", "type": "code", "code": { "rich_text": rich_text(SECRET), "caption": rich_text(SECRET), "language": "Dana script" } },
+                { "object": "block", "id": "b3", "type": "equation", "equation": { "expression": "Dana^2" } },
+                { "object": "block", "id": "b4", "type": "bookmark", "bookmark": { "url": "https://dana.example", "caption": [] } },
+                { "object": "block", "id": "b5", "type": "callout", "callout": { "rich_text": [], "icon": { "type": "emoji", "emoji": "Dana" } } },
+                { "object": "block", "id": "b6", "type": "child_page", "child_page": { "title": SECRET } }
+            ],
+            "next_cursor": null,
+            "has_more": false
+        }));
+    }
+
+    #[test]
+    fn page_properties_lose_every_value() {
+        let sanitized = assert_scrubbed(json!({
+            "object": "page",
+            "id": "row-1",
+            "parent": { "type": "database_id", "database_id": "db-1" },
+            "properties": {
+                "Phone": { "id": "a", "type": "phone_number", "phone_number": "555-0100" },
+                "Email": { "id": "b", "type": "email", "email": "dana@example.com" },
+                "Due": { "id": "c", "type": "date", "date": { "start": "2024-03-05", "end": null, "time_zone": "Dana/Time" } },
+                "Score": { "id": "d", "type": "formula", "formula": { "type": "string", "string": SECRET } },
+                "Tags": { "id": "e", "type": "multi_select", "multi_select": [{ "id": "t1", "name": "Dana", "color": "blue" }] },
+                "Sum": { "id": "f", "type": "rollup", "rollup": { "type": "array", "array": [{ "type": "rich_text", "rich_text": rich_text(SECRET) }], "function": "show_original" } },
+                "Name": { "id": "title", "type": "title", "title": rich_text(SECRET) }
+            }
+        }));
+        assert_eq!(sanitized["parent"]["database_id"], "db-1");
+        assert_eq!(sanitized["properties"]["Due"]["date"]["start"], "xxxxxxxxxx");
+        assert_eq!(sanitized["properties"]["Tags"]["multi_select"][0]["color"], "blue");
+    }
+
+    #[test]
+    fn error_messages_are_scrubbed_but_codes_kept() {
+        let sanitized = assert_scrubbed(json!({
+            "object": "error",
+            "status": 400,
+            "code": "validation_error",
+            "message": "body.children[0].paragraph.rich_text[0].text.content should be shorter: Meet Dana at 5",
+            "request_id": "req-1"
+        }));
+        assert_eq!(sanitized["code"], "validation_error");
+        assert_eq!(sanitized["status"], 400);
+        assert_eq!(sanitized["request_id"], "req-1");
+    }
+}
@@ -0,0 +1,321 @@
+use chrono::{DateTime, Duration as ChronoDuration, Local, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::Mutex;
+
+use crate::config::app_data_path;
+
+// Capture reliability kept as per-day aggregates, updated as each note is
+// delivered or given up on. They are stored apart from the receipts, so
+// compacting those doesn't lose them. A summary over N days reads N entries.
+const RELIABILITY_FILE: &str = "reliability.json";
+
+// Days kept; older aggregates are dropped when a new day starts
+const MAX_DAYS: i64 = 400;
+
+pub const DEFAULT_SUMMARY_DAYS: u32 = 30;
+
+// Upper bounds of the latency buckets, in milliseconds; the last bucket is open-ended
+const LATENCY_BOUNDS_MS: [u64; 14] = [
+    250, 500, 750, 1_000, 1_500, 2_000, 3_000, 5_000, 10_000, 30_000, 60_000, 300_000, 3_600_000, 86_400_000,
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct DayAggregate {
+    pub delivered: u64,
+    // Queued notes that ran out of attempts
+    pub failed: u64,
+    // Deliveries per latency bucket, one more than LATENCY_BOUNDS_MS
+    pub latency_buckets: Vec<u64>,
+}
+
+impl DayAggregate {
+    fn add_delivery(&mut self, latency_ms: u64) {
+        self.delivered += 1;
+        self.latency_buckets.resize(LATENCY_BOUNDS_MS.len() + 1, 0);
+        let bucket = LATENCY_BOUNDS_MS
+            .iter()
+            .position(|bound| latency_ms < *bound)
+            .unwrap_or(LATENCY_BOUNDS_MS.len());
+        self.latency_buckets[bucket] += 1;
+    }
+}
+
+// Keyed by local date ("2024-05-06") of the outcome
+pub type DailyAggregates = BTreeMap<String, DayAggregate>;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ReliabilitySummary {
+    pub days: u32,
+    pub delivered: u64,
+    pub failed: u64,
+    // Delivered / (delivered + failed); None before anything was attempted
+    pub success_rate: Option<f64>,
+    pub median_latency_ms: Option<u64>,
+    pub p95_latency_ms: Option<u64>,
+}
+
+lazy_static::lazy_static! {
+    // Serializes access to the aggregates file
+    static ref RELIABILITY_LOCK: Mutex<()> = Mutex::new(());
+}
+
+fn load() -> DailyAggregates {
+    app_data_path(RELIABILITY_FILE)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save(aggregates: &DailyAggregates) -> Result<(), String> {
+    let path = app_data_path(RELIABILITY_FILE)?;
+    let contents = serde_json::to_string(aggregates).map_err(|e| format!("Failed to serialize reliability data: {}", e))?;
+    fs::write(&path, contents).map_err(|e| format!("Failed to write reliability data: {}", e))
+}
+
+fn modify(change: impl FnOnce(&mut DailyAggregates)) {
+    let _lock = RELIABILITY_LOCK.lock().unwrap();
+    let mut aggregates = load();
+    change(&mut aggregates);
+    if let Err(e) = save(&aggregates) {
+        eprintln!("{}", e);
+    }
+}
+
+fn day_key(date: NaiveDate) -> String {
+    date.to_string()
+}
+
+fn prune(aggregates: &mut DailyAggregates, today: NaiveDate) {
+    let oldest = day_key(today - ChronoDuration::days(MAX_DAYS));
+    aggregates.retain(|day, _| *day >= oldest);
+}
+
+// A delivery that took from `captured_at` until `delivered_at`, counted on
+// the day it was delivered in `zone`
+pub fn add_delivery(
+    aggregates: &mut DailyAggregates,
+    captured_at: DateTime<Utc>,
+    delivered_at: DateTime<Utc>,
+    zone: &impl TimeZone,
+) {
+    let today = delivered_at.with_timezone(zone).date_naive();
+    let latency_ms = (delivered_at - captured_at).num_milliseconds().max(0) as u64;
+    aggregates.entry(day_key(today)).or_default().add_delivery(latency_ms);
+    prune(aggregates, today);
+}
+
+pub fn add_failure(aggregates: &mut DailyAggregates, failed_at: DateTime<Utc>, zone: &impl TimeZone) {
+    let today = failed_at.with_timezone(zone).date_naive();
+    aggregates.entry(day_key(today)).or_default().failed += 1;
+    prune(aggregates, today);
+}
+
+// Latency at quantile `q` (0..1), interpolated within its bucket
+fn latency_quantile(buckets: &[u64], q: f64) -> Option<u64> {
+    let total: u64 = buckets.iter().sum();
+    if total == 0 {
+        return None;
+    }
+    let rank = (q * total as f64).ceil().max(1.0);
+    let mut seen = 0u64;
+    for (i, count) in buckets.iter().enumerate() {
+        if *count == 0 {
+            continue;
+        }
+        if (seen + count) as f64 >= rank {
+            let lower = if i == 0 { 0 } else { LATENCY_BOUNDS_MS[i - 1] };
+            let Some(upper) = LATENCY_BOUNDS_MS.get(i) else {
+                return Some(lower);
+            };
+            let fraction = (rank - seen as f64) / *count as f64;
+            return Some(lower + ((upper - lower) as f64 * fraction).round() as u64);
+        }
+        seen += count;
+    }
+    None
+}
+
+// The last `days` local days up to and including `today`
+pub fn summarize(aggregates: &DailyAggregates, days: u32, today: NaiveDate) -> ReliabilitySummary {
+    let first = day_key(today - ChronoDuration::days(i64::from(days.max(1)) - 1));
+    let last = day_key(today);
+    let mut delivered = 0;
+    let mut failed = 0;
+    let mut buckets = vec![0u64; LATENCY_BOUNDS_MS.len() + 1];
+    for (_, day) in aggregates.range(first..=last) {
+        delivered += day.delivered;
+        failed += day.failed;
+        for (total, count) in buckets.iter_mut().zip(&day.latency_buckets) {
+            *total += count;
+        }
+    }
+
+    let attempted = delivered + failed;
+    ReliabilitySummary {
+        days,
+        delivered,
+        failed,
+        success_rate: (attempted > 0).then(|| delivered as f64 / attempted as f64),
+        median_latency_ms: latency_quantile(&buckets, 0.5),
+        p95_latency_ms: latency_quantile(&buckets, 0.95),
+    }
+}
+
+// Record a delivered capture; never fails the delivery itself
+pub fn record_delivery(captured_at: DateTime<Utc>) {
+    let now = Utc::now();
+    modify(|aggregates| add_delivery(aggregates, captured_at, now, &Local));
+}
+
+// Same, for a capture time stored as RFC3339
+pub fn record_delivery_since(captured_at: &str) {
+    match DateTime::parse_from_rfc3339(captured_at) {
+        Ok(time) => record_delivery(time.with_timezone(&Utc)),
+        Err(e) => eprintln!("Not counting delivery with capture time '{}': {}", captured_at, e),
+    }
+}
+
+pub fn record_failure() {
+    let now = Utc::now();
+    modify(|aggregates| add_failure(aggregates, now, &Local));
+}
+
+pub fn summary(days: u32) -> ReliabilitySummary {
+    let _lock = RELIABILITY_LOCK.lock().unwrap();
+    summarize(&load(), days, Local::now().date_naive())
+}
+
+#[tauri::command]
+pub fn get_reliability_summary(days: Option<u32>) -> ReliabilitySummary {
+    summary(days.unwrap_or(DEFAULT_SUMMARY_DAYS).clamp(1, MAX_DAYS as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::Tz;
+
+    const BUCHAREST: Tz = chrono_tz::Europe::Bucharest;
+    const LOS_ANGELES: Tz = chrono_tz::America::Los_Angeles;
+
+    fn utc(time: &str) -> DateTime<Utc> {
+        time.parse().unwrap()
+    }
+
+    fn date(date: &str) -> NaiveDate {
+        date.parse().unwrap()
+    }
+
+    // A delivery that took `seconds`, confirmed at `delivered_at`
+    fn deliver(aggregates: &mut DailyAggregates, delivered_at: &str, seconds: i64, zone: &Tz) {
+        let delivered_at = utc(delivered_at);
+        add_delivery(aggregates, delivered_at - ChronoDuration::seconds(seconds), delivered_at, zone);
+    }
+
+    #[test]
+    fn outcomes_count_on_the_local_day() {
+        let mut aggregates = DailyAggregates::new();
+        // 00:30 in Bucharest is still the previous evening in Los Angeles
+        deliver(&mut aggregates, "2026-05-10T21:30:00Z", 1, &BUCHAREST);
+        deliver(&mut aggregates, "2026-05-10T21:30:00Z", 1, &LOS_ANGELES);
+        add_failure(&mut aggregates, utc("2026-05-11T06:00:00Z"), &LOS_ANGELES);
+        add_failure(&mut aggregates, utc("2026-05-11T06:00:00Z"), &BUCHAREST);
+
+        let days: Vec<_> = aggregates.iter().map(|(day, aggregate)| (day.as_str(), aggregate.delivered, aggregate.failed)).collect();
+        assert_eq!(days, [("2026-05-10", 1, 1), ("2026-05-11", 1, 1)]);
+    }
+
+    #[test]
+    fn days_follow_the_dst_switch() {
+        // Bucharest moves from +02:00 to +03:00 at 01:00 UTC on 2026-03-29
+        let mut aggregates = DailyAggregates::new();
+        deliver(&mut aggregates, "2026-03-28T21:59:00Z", 1, &BUCHAREST);
+        deliver(&mut aggregates, "2026-03-28T22:00:00Z", 1, &BUCHAREST);
+        deliver(&mut aggregates, "2026-03-29T20:59:00Z", 1, &BUCHAREST);
+        deliver(&mut aggregates, "2026-03-29T21:00:00Z", 1, &BUCHAREST);
+
+        let days: Vec<_> = aggregates.iter().map(|(day, aggregate)| (day.as_str(), aggregate.delivered)).collect();
+        assert_eq!(days, [("2026-03-28", 1), ("2026-03-29", 2), ("2026-03-30", 1)]);
+    }
+
+    #[test]
+    fn latency_across_a_dst_switch_is_real_elapsed_time() {
+        // Captured at 02:59:30 EET, delivered at 04:00:20 EEST: 50 seconds, not an hour
+        let mut aggregates = DailyAggregates::new();
+        add_delivery(&mut aggregates, utc("2026-03-29T00:59:30Z"), utc("2026-03-29T01:00:20Z"), &BUCHAREST);
+        let summary = summarize(&aggregates, 1, date("2026-03-29"));
+        assert_eq!(summary.median_latency_ms, Some(60_000));
+        // A capture time after the delivery counts as instant
+        add_delivery(&mut aggregates, utc("2026-03-29T10:00:00Z"), utc("2026-03-29T09:00:00Z"), &BUCHAREST);
+        assert_eq!(aggregates["2026-03-29"].latency_buckets[0], 1);
+    }
+
+    #[test]
+    fn the_summary_covers_the_last_n_days() {
+        let mut aggregates = DailyAggregates::new();
+        // Three one-second deliveries a day through May, a failure, and a slow note long ago
+        for day in 1..=31 {
+            for _ in 0..3 {
+                deliver(&mut aggregates, &format!("2026-05-{:02}T12:00:00Z", day), 1, &BUCHAREST);
+            }
+        }
+        add_failure(&mut aggregates, utc("2026-05-20T12:00:00Z"), &BUCHAREST);
+        deliver(&mut aggregates, "2026-03-01T12:00:00Z", 120, &BUCHAREST);
+
+        // May 2nd to 31st
+        let summary = summarize(&aggregates, 30, date("2026-05-31"));
+        assert_eq!((summary.delivered, summary.failed), (90, 1));
+        let rate = summary.success_rate.unwrap();
+        assert!((rate - 90.0 / 91.0).abs() < 1e-9, "{}", rate);
+        // Every delivery in range took a second; the slow one is out of range
+        for latency in [summary.median_latency_ms, summary.p95_latency_ms] {
+            assert!(latency.is_some_and(|ms| (1_000..=1_500).contains(&ms)), "{:?}", latency);
+        }
+        assert_eq!(summarize(&aggregates, 120, date("2026-05-31")).delivered, 94);
+
+        let empty = summarize(&aggregates, 30, date("2026-07-31"));
+        assert_eq!((empty.success_rate, empty.median_latency_ms, empty.p95_latency_ms), (None, None, None));
+    }
+
+    #[test]
+    fn quantiles_interpolate_within_their_bucket() {
+        let mut buckets = vec![0u64; LATENCY_BOUNDS_MS.len() + 1];
+        // 10 under 250ms, 10 between 1s and 1.5s
+        buckets[0] = 10;
+        buckets[4] = 10;
+        assert_eq!(latency_quantile(&buckets, 0.5), Some(250));
+        assert_eq!(latency_quantile(&buckets, 0.75), Some(1_250));
+        assert_eq!(latency_quantile(&buckets, 0.95), Some(1_450));
+        // The open-ended bucket reports its lower bound
+        let mut slow = vec![0u64; LATENCY_BOUNDS_MS.len() + 1];
+        slow[LATENCY_BOUNDS_MS.len()] = 1;
+        assert_eq!(latency_quantile(&slow, 0.5), Some(86_400_000));
+        assert_eq!(latency_quantile(&[], 0.5), None);
+    }
+
+    #[test]
+    fn old_days_are_pruned() {
+        let mut aggregates = DailyAggregates::new();
+        deliver(&mut aggregates, "2025-01-01T12:00:00Z", 1, &BUCHAREST);
+        deliver(&mut aggregates, "2026-05-11T12:00:00Z", 1, &BUCHAREST);
+        assert_eq!(aggregates.keys().collect::<Vec<_>>(), ["2026-05-11"]);
+    }
+
+    #[test]
+    fn aggregates_outlive_the_receipts() {
+        crate::config::use_test_data_dir();
+        record_delivery(Utc::now() - ChronoDuration::milliseconds(800));
+        record_delivery_since(&(Utc::now() - ChronoDuration::seconds(2)).to_rfc3339());
+        record_delivery_since("not a time");
+        record_failure();
+
+        crate::receipts::clear().unwrap();
+        let summary = summary(1);
+        assert_eq!((summary.delivered, summary.failed), (2, 1));
+        assert!(summary.median_latency_ms.is_some());
+    }
+}
@@ -0,0 +1,363 @@
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+use crate::config::{AppConfig, AppState};
+use crate::rate_limit::RATE_LIMITER;
+
+// "Start fresh" for support: clears app data by scope through the module that
+// owns it, so in-memory state (caches, client pool, rate limiter, hotkeys)
+// is reset along with the files and the app keeps running.
+
+// How long a confirmation token from the reset dialog stays valid
+const CONFIRMATION_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ResetScope {
+    Settings,
+    Caches,
+    // Offline queue and scratch buffer
+    Queue,
+    // Delivery receipts, focus session history and the page rotation log
+    History,
+    // Debug recordings and the lifecycle log
+    Logs,
+    // API tokens, main and per-profile, and migration backups that may hold old ones
+    Secrets,
+    All,
+}
+
+impl ResetScope {
+    // Settings and secrets can't be recovered, so they need a confirmation token
+    fn needs_confirmation(self) -> bool {
+        matches!(self, ResetScope::Settings | ResetScope::Secrets | ResetScope::All)
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ResetItem {
+    pub scope: ResetScope,
+    pub item: String,
+    // What was removed, or why it couldn't be
+    pub detail: String,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct ResetReport {
+    pub removed: Vec<ResetItem>,
+    pub failed: Vec<ResetItem>,
+    // Undelivered notes in the queue and scratch buffer
+    pub pending_notes: usize,
+    // Nothing was removed because pending notes would have been lost without `force`
+    pub refused: bool,
+}
+
+impl ResetReport {
+    fn record(&mut self, scope: ResetScope, item: &str, result: Result<String, String>) {
+        let entry = |detail| ResetItem {
+            scope,
+            item: item.to_string(),
+            detail,
+        };
+        match result {
+            Ok(detail) => self.removed.push(entry(detail)),
+            Err(error) => self.failed.push(entry(error)),
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref CONFIRMATION: Mutex<Option<(String, Instant)>> = Mutex::new(None);
+}
+
+// Expand `All` and drop duplicates, keeping a fixed order
+fn expand_scopes(scopes: &[ResetScope]) -> Vec<ResetScope> {
+    use ResetScope::*;
+    let all = scopes.contains(&All);
+    [Queue, History, Logs, Caches, Secrets, Settings]
+        .into_iter()
+        .filter(|scope| all || scopes.contains(scope))
+        .collect()
+}
+
+// One-time use: a token is consumed whether or not it matches
+fn take_confirmation(token: Option<&str>) -> bool {
+    let stored = CONFIRMATION.lock().unwrap().take();
+    match (stored, token) {
+        (Some((expected, issued_at)), Some(token)) => expected == token && issued_at.elapsed() < CONFIRMATION_TTL,
+        _ => false,
+    }
+}
+
+fn count(result: Result<usize, String>, noun: &str) -> Result<String, String> {
+    result.map(|count| format!("{} {}", count, noun))
+}
+
+fn update_config(app: &AppHandle, change: impl FnOnce(&mut AppConfig)) -> Result<String, String> {
+    let mut tokens = Vec::new();
+    crate::settings::update_config(app, |config| {
+        tokens.extend(config.secrets.tokens().into_iter().map(str::to_string));
+        change(config);
+        Ok(())
+    })?;
+    for token in tokens.iter().filter(|token| !token.is_empty()) {
+        RATE_LIMITER.clear(token);
+    }
+    Ok("reset".to_string())
+}
+
+fn reset_secrets(config: &mut AppConfig) {
+    config.secrets.set_main_token(String::new());
+    config.secrets.clear_profile_tokens();
+    config.token_invalid = false;
+}
+
+// Settings alone keep the main token; profiles go with the rest of the config
+fn reset_settings(config: &mut AppConfig) {
+    let token_invalid = config.token_invalid;
+    let mut secrets = std::mem::take(&mut config.secrets);
+    secrets.clear_profile_tokens();
+    secrets.set_webhook_secret(None);
+    *config = AppConfig::default();
+    config.secrets = secrets;
+    config.token_invalid = token_invalid;
+}
+
+// Bring in-memory state in line with the config after it was replaced
+fn reinitialize(app: &AppHandle) {
+    *app.state::<AppState>().last_verification.lock().unwrap() = None;
+    crate::settings::apply_runtime_config(app);
+}
+
+// Issued by the reset dialog once the user confirms; required for Settings, Secrets and All
+#[tauri::command]
+pub fn request_reset_confirmation() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default(),
+    );
+    let token = format!("{:016x}", hasher.finish());
+    *CONFIRMATION.lock().unwrap() = Some((token.clone(), Instant::now()));
+    token
+}
+
+// Clear the chosen scopes and report what went and what couldn't be removed.
+// A queue holding undelivered notes is only cleared with `force`.
+#[tauri::command]
+pub fn reset_app_data(
+    scopes: Vec<ResetScope>,
+    confirmation: Option<String>,
+    force: Option<bool>,
+    app: AppHandle,
+) -> Result<ResetReport, String> {
+    let scopes = expand_scopes(&scopes);
+    if scopes.iter().any(|scope| scope.needs_confirmation()) && !take_confirmation(confirmation.as_deref()) {
+        return Err("Resetting settings or secrets needs confirmation from the reset dialog".into());
+    }
+
+    let mut report = ResetReport::default();
+    if scopes.contains(&ResetScope::Queue) {
+        report.pending_notes = crate::queue::pending_count().unwrap_or(0)
+            + crate::scratch::pending_count()
+            + crate::setup_captures::pending_count(&app);
+        if report.pending_notes > 0 && !force.unwrap_or(false) {
+            report.refused = true;
+            return Ok(report);
+        }
+    }
+
+    let mut config_changed = false;
+    for scope in scopes {
+        match scope {
+            ResetScope::Queue => {
+                report.record(scope, "queue", count(crate::queue::clear(), "queued notes"));
+                report.record(scope, "scratch", count(crate::scratch::clear(), "scratch notes"));
+                report.record(scope, "pending_setup", count(crate::setup_captures::clear(&app), "notes waiting for setup"));
+            }
+            ResetScope::History => {
+                report.record(scope, "receipts", count(crate::receipts::clear(), "receipts"));
+                report.record(scope, "focus_history", count(crate::focus::clear_history(&app), "focus sessions"));
+                report.record(scope, "page_rotations", count(crate::page_rotations::clear(), "page rotations"));
+            }
+            ResetScope::Logs => {
+                report.record(scope, "recordings", count(crate::recording::clear(), "recording sessions"));
+                crate::events::clear_lifecycle_log();
+                report.record(scope, "lifecycle_log", Ok("cleared".into()));
+            }
+            ResetScope::Caches => {
+                report.record(scope, "notion_caches", count(Ok(crate::notion::clear_caches()), "entries"));
+            }
+            ResetScope::Secrets => {
+                let result = update_config(&app, reset_secrets);
+                // Pooled clients are keyed by token
+                crate::notion::clear_caches();
+                report.record(scope, "api_tokens", result);
+                report.record(scope, "migration_backups", count(crate::migrations::clear_backups(), "migration backups"));
+                config_changed = true;
+            }
+            ResetScope::Settings => {
+                let result = update_config(&app, reset_settings);
+                report.record(scope, "config", result);
+                config_changed = true;
+            }
+            ResetScope::All => {}
+        }
+    }
+
+    if config_changed {
+        reinitialize(&app);
+    }
+    crate::refresh_tray(&app);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::use_test_data_dir;
+    use std::fs;
+
+    #[test]
+    fn all_expands_to_every_scope_once() {
+        use ResetScope::*;
+        let every = vec![Queue, History, Logs, Caches, Secrets, Settings];
+        assert_eq!(expand_scopes(&[All]), every);
+        assert_eq!(expand_scopes(&[Settings, All, Queue]), every);
+        assert_eq!(expand_scopes(&[Settings, Queue, Queue]), vec![Queue, Settings]);
+        assert!(expand_scopes(&[]).is_empty());
+
+        let confirmed: Vec<_> = [Settings, Caches, Queue, History, Logs, Secrets, All]
+            .into_iter()
+            .filter(|scope| scope.needs_confirmation())
+            .collect();
+        assert_eq!(confirmed, vec![Settings, Secrets, All]);
+    }
+
+    #[test]
+    fn a_confirmation_token_is_used_once() {
+        assert!(!take_confirmation(Some("anything")));
+
+        let token = request_reset_confirmation();
+        assert!(!take_confirmation(None));
+        // The failed attempt used it up
+        assert!(!take_confirmation(Some(&token)));
+
+        let token = request_reset_confirmation();
+        assert!(!take_confirmation(Some("wrong")));
+        assert!(!take_confirmation(Some(&token)));
+
+        let token = request_reset_confirmation();
+        assert!(take_confirmation(Some(&token)));
+        assert!(!take_confirmation(Some(&token)));
+
+        let token = request_reset_confirmation();
+        CONFIRMATION.lock().unwrap().as_mut().unwrap().1 -= CONFIRMATION_TTL;
+        assert!(!take_confirmation(Some(&token)));
+    }
+
+    #[test]
+    fn queue_history_and_logs_are_cleared_with_counts() {
+        let dir = use_test_data_dir();
+        crate::queue::enqueue_blocks("page-1".into(), "Inbox".into(), Vec::new(), crate::receipts::DeliverySource::Raw, None)
+            .unwrap();
+        crate::scratch::add_item("first").unwrap();
+        crate::scratch::add_item("second").unwrap();
+        let response = crate::notion::AppendResponse {
+            block_ids: vec!["block-1".into()],
+            request_id: None,
+            status: 200,
+            attempts: 1,
+        };
+        crate::receipts::record_receipt(&crate::receipts::DeliveryReceipt::new("a".into(), "page-1".into(), response, None))
+            .unwrap();
+        crate::page_rotations::record(crate::page_rotations::PageRotationEntry {
+            from_page_id: "page-1".into(),
+            from_title: "March".into(),
+            to_page_id: "page-2".into(),
+            to_title: "April".into(),
+            rotated_at: "2026-04-01T00:00:00Z".into(),
+        })
+        .unwrap();
+        fs::create_dir_all(dir.join("recordings").join("session-1")).unwrap();
+        assert_eq!(crate::queue::pending_count().unwrap() + crate::scratch::pending_count(), 3);
+
+        let mut report = ResetReport::default();
+        report.record(ResetScope::Queue, "queue", count(crate::queue::clear(), "queued notes"));
+        report.record(ResetScope::Queue, "scratch", count(crate::scratch::clear(), "scratch notes"));
+        report.record(ResetScope::History, "receipts", count(crate::receipts::clear(), "receipts"));
+        report.record(ResetScope::History, "page_rotations", count(crate::page_rotations::clear(), "page rotations"));
+        report.record(ResetScope::Logs, "recordings", count(crate::recording::clear(), "recording sessions"));
+
+        let details: Vec<_> = report.removed.iter().map(|item| item.detail.as_str()).collect();
+        assert_eq!(
+            details,
+            ["1 queued notes", "2 scratch notes", "1 receipts", "1 page rotations", "1 recording sessions"]
+        );
+        assert!(report.failed.is_empty());
+        assert_eq!(crate::queue::pending_count().unwrap() + crate::scratch::pending_count(), 0);
+        assert!(crate::receipts::find_receipt("a").unwrap().is_none());
+        assert!(crate::page_rotations::load().unwrap().is_empty());
+        assert!(!dir.join("recordings").exists());
+
+        // Clearing again finds nothing rather than failing
+        assert_eq!(crate::queue::clear(), Ok(0));
+        assert_eq!(crate::recording::clear(), Ok(0));
+    }
+
+    #[test]
+    fn failures_are_reported_apart_from_removals() {
+        let mut report = ResetReport::default();
+        report.record(ResetScope::Caches, "notion_caches", Ok("3 entries".into()));
+        report.record(ResetScope::Secrets, "migration_backups", Err("Permission denied".into()));
+        assert_eq!(report.removed.len(), 1);
+        assert_eq!(report.failed[0].scope, ResetScope::Secrets);
+        assert_eq!(report.failed[0].detail, "Permission denied");
+    }
+
+    #[test]
+    fn secrets_reset_drops_every_token_and_backup() {
+        let dir = use_test_data_dir();
+        fs::create_dir_all(dir.join("migration_backups")).unwrap();
+        fs::write(dir.join("migration_backups").join("config-v1.json"), "{}").unwrap();
+        let mut config = AppConfig::default();
+        config.secrets.set_main_token("secret_main".into());
+        config.secrets.set_profile_token("work", "secret_work".into());
+        config.secrets.set_webhook_secret(Some("hook".into()));
+        config.token_invalid = true;
+        config.selected_page_id = "page-1".into();
+
+        reset_secrets(&mut config);
+        assert_eq!(config.secrets.main_token(), "");
+        assert_eq!(config.secrets.profile_token("work"), None);
+        assert!(!config.token_invalid);
+        // Settings stay put
+        assert_eq!(config.selected_page_id, "page-1");
+
+        assert_eq!(crate::migrations::clear_backups(), Ok(1));
+        assert!(!dir.join("migration_backups").exists());
+    }
+
+    #[test]
+    fn settings_reset_keeps_only_the_main_token() {
+        let mut config = AppConfig::default();
+        config.secrets.set_main_token("secret_main".into());
+        config.secrets.set_profile_token("work", "secret_work".into());
+        config.secrets.set_webhook_secret(Some("hook".into()));
+        config.token_invalid = true;
+        config.selected_page_id = "page-1".into();
+
+        reset_settings(&mut config);
+        assert_eq!(config.secrets.main_token(), "secret_main");
+        assert_eq!(config.secrets.profile_token("work"), None);
+        assert_eq!(config.secrets.webhook_secret(), None);
+        assert!(config.token_invalid);
+        assert_eq!(config.selected_page_id, AppConfig::default().selected_page_id);
+    }
+}
@@ -0,0 +1,363 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::tasks::CancellationToken;
+
+// One retry/backoff policy type for every subsystem that retries: appends,
+// the offline queue, the mirror webhook and token verification. Delays double
+// from `base_delay` up to `max_delay`, then jitter is applied.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Jitter {
+    None,
+    // Anywhere between zero and the computed delay
+    Full,
+    // Half the computed delay plus a random share of the other half
+    Equal,
+}
+
+pub struct RetryPolicy<E> {
+    // Name used in logs and retry metrics
+    pub name: &'static str,
+    // Total attempts, including the first
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub jitter: Jitter,
+    // Whether a failure is worth another attempt
+    pub retryable: fn(&E) -> bool,
+    // Returns a value in [0, 1); swapped for a fixed source where delays must be predictable
+    pub jitter_source: fn() -> f64,
+}
+
+impl<E> Clone for RetryPolicy<E> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<E> Copy for RetryPolicy<E> {}
+
+// Jitter from the clock's sub-second nanos; good enough to spread retries, not for anything else
+pub fn clock_jitter() -> f64 {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.subsec_nanos())
+        .unwrap_or(0);
+    // Scramble the low bits so back-to-back calls don't land on nearly the same value
+    let mixed = nanos.wrapping_mul(2_654_435_761) % 1_000_000;
+    mixed as f64 / 1_000_000.0
+}
+
+impl<E> RetryPolicy<E> {
+    // Delay before attempt `attempt + 1`, after `attempt` attempts have failed
+    pub fn delay_after(&self, attempt: u32, roll: f64) -> Duration {
+        let doublings = attempt.saturating_sub(1).min(16);
+        let delay = self.base_delay.saturating_mul(1 << doublings).min(self.max_delay);
+        let roll = roll.clamp(0.0, 1.0);
+        match self.jitter {
+            Jitter::None => delay,
+            Jitter::Full => delay.mul_f64(roll),
+            Jitter::Equal => delay / 2 + (delay / 2).mul_f64(roll),
+        }
+    }
+
+    // Whether `attempts` failures leave room for another try
+    pub fn exhausted(&self, attempts: u32) -> bool {
+        attempts >= self.max_attempts
+    }
+
+    // Whether to try again after `attempt` attempts ended in `error`
+    pub fn should_retry(&self, attempt: u32, error: &E) -> bool {
+        !self.exhausted(attempt) && (self.retryable)(error)
+    }
+}
+
+// Passed to the observer before each backoff, for progress events and logs
+pub struct RetryAttempt<'a, E> {
+    pub policy: &'static str,
+    // Attempts made so far
+    pub attempt: u32,
+    pub max_attempts: u32,
+    pub delay: Duration,
+    pub error: &'a E,
+}
+
+pub struct RetryOutcome<T, E> {
+    pub result: Result<T, E>,
+    pub attempts: u32,
+    // The backoff was interrupted by cancellation; `result` holds the last failure
+    pub cancelled: bool,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct RetryCounters {
+    pub retries: u64,
+    // Gave up after the last allowed attempt
+    pub exhausted: u64,
+    pub cancelled: u64,
+}
+
+lazy_static::lazy_static! {
+    static ref RETRY_STATS: Mutex<BTreeMap<&'static str, RetryCounters>> = Mutex::new(BTreeMap::new());
+}
+
+fn count(policy: &'static str, update: impl FnOnce(&mut RetryCounters)) {
+    update(RETRY_STATS.lock().unwrap().entry(policy).or_default());
+}
+
+// Retry counters per policy, for diagnostics
+pub fn stats() -> BTreeMap<String, RetryCounters> {
+    RETRY_STATS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(name, counters)| (name.to_string(), counters.clone()))
+        .collect()
+}
+
+// Run `op` until it succeeds, fails with a non-retryable error or runs out of
+// attempts. `op` gets the 1-based attempt number. Cancelling `cancel` stops
+// the wait between attempts, never an attempt already running.
+pub async fn retry_async<T, E, F, Fut>(
+    policy: &RetryPolicy<E>,
+    mut cancel: Option<CancellationToken>,
+    mut on_retry: impl FnMut(&RetryAttempt<E>),
+    mut op: F,
+) -> RetryOutcome<T, E>
+where
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let error = match op(attempt).await {
+            Ok(value) => {
+                return RetryOutcome { result: Ok(value), attempts: attempt, cancelled: false };
+            }
+            Err(error) => error,
+        };
+
+        if !policy.should_retry(attempt, &error) {
+            if policy.exhausted(attempt) && (policy.retryable)(&error) {
+                count(policy.name, |counters| counters.exhausted += 1);
+            }
+            return RetryOutcome { result: Err(error), attempts: attempt, cancelled: false };
+        }
+
+        let delay = policy.delay_after(attempt, (policy.jitter_source)());
+        on_retry(&RetryAttempt {
+            policy: policy.name,
+            attempt,
+            max_attempts: policy.max_attempts,
+            delay,
+            error: &error,
+        });
+        count(policy.name, |counters| counters.retries += 1);
+
+        let cancelled = match cancel.as_mut() {
+            Some(token) => tokio::select! {
+                _ = token.cancelled() => true,
+                _ = tokio::time::sleep(delay) => false,
+            },
+            None => {
+                tokio::time::sleep(delay).await;
+                false
+            }
+        };
+        if cancelled {
+            count(policy.name, |counters| counters.cancelled += 1);
+            return RetryOutcome { result: Err(error), attempts: attempt, cancelled: true };
+        }
+    }
+}
+
+// Transport failures, as worded by NotionApiClient::send
+pub fn is_transport_error(error: &str) -> bool {
+    error.starts_with("API request failed")
+}
+
+// Token checks only retry when Notion couldn't be reached; a rejection is final
+pub const TOKEN_VERIFY_RETRY: RetryPolicy<String> = RetryPolicy {
+    name: "token-verify",
+    max_attempts: 3,
+    base_delay: Duration::from_millis(500),
+    max_delay: Duration::from_secs(2),
+    jitter: Jitter::Equal,
+    retryable: |error| is_transport_error(error),
+    jitter_source: clock_jitter,
+};
+
+// Mirror webhook: answers other than 4xx are retried, and so is 429
+fn webhook_retryable(error: &str) -> bool {
+    !error.starts_with("Webhook returned 4") || error.starts_with("Webhook returned 429")
+}
+
+pub const WEBHOOK_RETRY: RetryPolicy<String> = RetryPolicy {
+    name: "webhook",
+    max_attempts: 3,
+    base_delay: Duration::from_secs(1),
+    max_delay: Duration::from_secs(5),
+    jitter: Jitter::Equal,
+    retryable: |error| webhook_retryable(error),
+    jitter_source: clock_jitter,
+};
+
+// Offline queue: each flush makes one attempt per note, so the delay is the
+// earliest time a failed note is tried again rather than an in-process wait
+pub const QUEUE_RETRY: RetryPolicy<String> = RetryPolicy {
+    name: "queue",
+    max_attempts: 5,
+    base_delay: Duration::from_secs(30),
+    max_delay: Duration::from_secs(10 * 60),
+    jitter: Jitter::Equal,
+    retryable: |_| true,
+    jitter_source: clock_jitter,
+};
+
+// Scheduled scratch flush: the check normally runs every minute; while flushes
+// keep failing it waits one to two minutes, doubling up to half an hour, and never gives up
+pub const SCRATCH_FLUSH_RETRY: RetryPolicy<String> = RetryPolicy {
+    name: "scratch-flush",
+    max_attempts: u32::MAX,
+    base_delay: Duration::from_secs(2 * 60),
+    max_delay: Duration::from_secs(30 * 60),
+    jitter: Jitter::Equal,
+    retryable: |_| true,
+    jitter_source: clock_jitter,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::TaskRegistry;
+    use std::sync::Arc;
+
+    fn policy(name: &'static str, jitter: Jitter) -> RetryPolicy<String> {
+        RetryPolicy {
+            name,
+            max_attempts: 4,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(500),
+            jitter,
+            retryable: |error| error != "fatal",
+            jitter_source: || 0.5,
+        }
+    }
+
+    fn millis(policy: &RetryPolicy<String>, roll: f64) -> Vec<u128> {
+        (1..=6).map(|attempt| policy.delay_after(attempt, roll).as_millis()).collect()
+    }
+
+    #[test]
+    fn delays_double_up_to_the_cap() {
+        assert_eq!(millis(&policy("test-none", Jitter::None), 0.7), [100, 200, 400, 500, 500, 500]);
+        // Far past the cap the doubling saturates instead of overflowing
+        assert_eq!(policy("test-none", Jitter::None).delay_after(u32::MAX, 0.0), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn jitter_stays_within_its_share_of_the_delay() {
+        let full = policy("test-full", Jitter::Full);
+        assert_eq!(millis(&full, 0.0), [0, 0, 0, 0, 0, 0]);
+        assert_eq!(millis(&full, 0.5), [50, 100, 200, 250, 250, 250]);
+        let equal = policy("test-equal", Jitter::Equal);
+        assert_eq!(millis(&equal, 0.0), [50, 100, 200, 250, 250, 250]);
+        assert_eq!(millis(&equal, 1.0), [100, 200, 400, 500, 500, 500]);
+        // Rolls out of range are clamped rather than stretching the delay
+        assert_eq!(equal.delay_after(1, 7.0), Duration::from_millis(100));
+        assert_eq!(equal.delay_after(1, -1.0), Duration::from_millis(50));
+    }
+
+    #[test]
+    fn retries_stop_at_the_last_attempt_or_a_final_error() {
+        let policy = policy("test-should", Jitter::None);
+        assert!(policy.should_retry(1, &"busy".to_string()));
+        assert!(policy.should_retry(3, &"busy".to_string()));
+        assert!(!policy.should_retry(4, &"busy".to_string()));
+        assert!(!policy.should_retry(1, &"fatal".to_string()));
+    }
+
+    #[tokio::test]
+    async fn retry_async_reports_each_retry_until_success() {
+        let policy = policy("test-success", Jitter::None);
+        let mut reported = Vec::new();
+        let outcome = retry_async(
+            &policy,
+            None,
+            |retry| reported.push((retry.policy, retry.attempt, retry.max_attempts, retry.delay, retry.error.clone())),
+            |attempt| async move { if attempt < 3 { Err(format!("busy {}", attempt)) } else { Ok(attempt) } },
+        )
+        .await;
+
+        assert_eq!((outcome.result, outcome.attempts, outcome.cancelled), (Ok(3), 3, false));
+        assert_eq!(
+            reported,
+            [
+                ("test-success", 1, 4, Duration::from_millis(100), "busy 1".to_string()),
+                ("test-success", 2, 4, Duration::from_millis(200), "busy 2".to_string()),
+            ]
+        );
+        assert_eq!(stats()["test-success"].retries, 2);
+    }
+
+    #[tokio::test]
+    async fn retry_async_gives_up_on_final_errors_and_after_the_last_attempt() {
+        let fatal = policy("test-fatal", Jitter::None);
+        let outcome = retry_async(&fatal, None, |_| panic!("a final error is not retried"), |_| async { Err::<(), _>("fatal".to_string()) }).await;
+        assert_eq!((outcome.result, outcome.attempts), (Err("fatal".to_string()), 1));
+        assert!(!stats().contains_key("test-fatal"));
+
+        let busy = RetryPolicy { base_delay: Duration::from_millis(1), ..policy("test-exhausted", Jitter::None) };
+        let outcome = retry_async(&busy, None, |_| {}, |_| async { Err::<(), _>("busy".to_string()) }).await;
+        assert_eq!((outcome.result, outcome.attempts), (Err("busy".to_string()), 4));
+        let counters = &stats()["test-exhausted"];
+        assert_eq!((counters.retries, counters.exhausted, counters.cancelled), (3, 1, 0));
+    }
+
+    #[tokio::test]
+    async fn cancelling_interrupts_the_wait_between_attempts() {
+        let slow = RetryPolicy { base_delay: Duration::from_secs(60), max_delay: Duration::from_secs(60), ..policy("test-cancel", Jitter::None) };
+        let finished = Arc::new(Mutex::new(None));
+        let registry = TaskRegistry::new();
+        let recorded = finished.clone();
+        registry.spawn("retrying", move |cancel| async move {
+            let outcome = retry_async(&slow, Some(cancel), |_| {}, |_| async { Err::<(), _>("down".to_string()) }).await;
+            *recorded.lock().unwrap() = Some((outcome.attempts, outcome.cancelled, outcome.result));
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert!(registry.shutdown(Duration::from_secs(1)).await.is_empty());
+        assert_eq!(*finished.lock().unwrap(), Some((1, true, Err("down".to_string()))));
+        assert_eq!(stats()["test-cancel"].cancelled, 1);
+    }
+
+    #[test]
+    fn subsystem_policies_retry_what_they_should() {
+        assert!((TOKEN_VERIFY_RETRY.retryable)(&"API request failed: connection refused".to_string()));
+        assert!(!(TOKEN_VERIFY_RETRY.retryable)(&"API error: 401 Unauthorized".to_string()));
+
+        assert!((WEBHOOK_RETRY.retryable)(&"Webhook returned 500 Internal Server Error".to_string()));
+        assert!((WEBHOOK_RETRY.retryable)(&"Webhook returned 429 Too Many Requests".to_string()));
+        assert!((WEBHOOK_RETRY.retryable)(&"Webhook request failed: timed out".to_string()));
+        assert!(!(WEBHOOK_RETRY.retryable)(&"Webhook returned 404 Not Found".to_string()));
+
+        assert!(!QUEUE_RETRY.should_retry(QUEUE_RETRY.max_attempts, &"down".to_string()));
+        assert_eq!(QUEUE_RETRY.delay_after(100, 1.0), Duration::from_secs(10 * 60));
+    }
+
+    #[test]
+    fn scheduled_scratch_flushes_back_off_without_giving_up() {
+        let policy = SCRATCH_FLUSH_RETRY;
+        assert!(policy.should_retry(10_000, &"offline".to_string()));
+        let first = (policy.delay_after(1, 0.0), policy.delay_after(1, 1.0));
+        assert_eq!(first, (Duration::from_secs(60), Duration::from_secs(2 * 60)));
+        assert_eq!(policy.delay_after(2, 1.0), Duration::from_secs(4 * 60));
+        assert_eq!(policy.delay_after(50, 1.0), Duration::from_secs(30 * 60));
+        assert_eq!(policy.delay_after(50, 0.0), Duration::from_secs(15 * 60));
+    }
+}
@@ -0,0 +1,266 @@
+use serde::Serialize;
+use tauri::{AppHandle, State};
+
+use crate::config::AppState;
+use crate::notion::{self, same_page_id};
+use crate::page_display;
+use crate::receipts;
+
+// Most suggestions the note window shows at once
+const MAX_SUGGESTIONS: usize = 10;
+
+// How many recent receipts to scan for recently used pages
+const RECENT_RECEIPTS_SCANNED: usize = 50;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestionKind {
+    // A capture profile, addressed as `/name`
+    Route,
+    // A page notes were recently sent to
+    Recent,
+    // Any page from the search cache
+    Page,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RoutingSuggestion {
+    pub display: String,
+    pub kind: SuggestionKind,
+    // What the note window puts in place of the typed `/partial`
+    pub insert_text: String,
+    pub page_id: String,
+}
+
+// A page known by id and title
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageCandidate {
+    pub page_id: String,
+    pub title: String,
+}
+
+// Subsequence match of `query` in `text`, case-insensitive. Higher is better;
+// consecutive characters and word starts score extra. None when not all of
+// `query` appears in order.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<u32> {
+    let query: Vec<char> = query.to_lowercase().chars().filter(|c| !c.is_whitespace()).collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous_match: Option<usize> = None;
+    for q in query {
+        let found = (next..text.len()).find(|&i| text[i] == q)?;
+        score += 1;
+        if previous_match == Some(found.wrapping_sub(1)) {
+            score += 2;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        previous_match = Some(found);
+        next = found + 1;
+    }
+    Some(score)
+}
+
+fn starts_with_ignore_case(text: &str, prefix: &str) -> bool {
+    text.to_lowercase().starts_with(&prefix.to_lowercase())
+}
+
+fn page_suggestion(page: &PageCandidate, kind: SuggestionKind) -> RoutingSuggestion {
+    RoutingSuggestion {
+        display: page.title.clone(),
+        kind,
+        insert_text: format!("/{} ", page.title),
+        page_id: page.page_id.clone(),
+    }
+}
+
+// Merge routes, recent pages (most recent first) and cached pages into one
+// ranked list: prefix matches first, then recently used pages, then the best
+// fuzzy matches. Pages appear once, at their best rank.
+pub fn rank_suggestions(
+    partial: &str,
+    routes: &[(String, PageCandidate)],
+    recent: &[PageCandidate],
+    pages: &[PageCandidate],
+) -> Vec<RoutingSuggestion> {
+    let partial = partial.trim_start_matches('/').trim();
+
+    let route_suggestions = routes.iter().map(|(name, page)| RoutingSuggestion {
+        display: format!("/{} → {}", name, page.title),
+        kind: SuggestionKind::Route,
+        insert_text: format!("/{} ", name),
+        page_id: page.page_id.clone(),
+    });
+    let recent_suggestions = recent.iter().map(|page| page_suggestion(page, SuggestionKind::Recent));
+    let page_suggestions = pages.iter().map(|page| page_suggestion(page, SuggestionKind::Page));
+
+    // (tier, order within tier, suggestion); the iteration order above keeps
+    // routes ahead of recent pages ahead of cached pages within a tier
+    let mut ranked: Vec<(u8, i64, RoutingSuggestion)> = Vec::new();
+    for (position, suggestion) in route_suggestions.chain(recent_suggestions).chain(page_suggestions).enumerate() {
+        let name = suggestion.insert_text.trim_start_matches('/').trim_end();
+        let position = position as i64;
+        if starts_with_ignore_case(name, partial) {
+            ranked.push((0, position, suggestion));
+        } else if let Some(score) = fuzzy_score(partial, name) {
+            if suggestion.kind == SuggestionKind::Recent {
+                ranked.push((1, position, suggestion));
+            } else {
+                ranked.push((2, -(score as i64), suggestion));
+            }
+        }
+    }
+    ranked.sort_by_key(|(tier, order, _)| (*tier, *order));
+
+    let mut suggestions: Vec<RoutingSuggestion> = Vec::new();
+    for (_, _, suggestion) in ranked {
+        let duplicate = suggestions.iter().any(|existing| {
+            existing.insert_text == suggestion.insert_text
+                || (suggestion.kind != SuggestionKind::Route
+                    && existing.kind != SuggestionKind::Route
+                    && same_page_id(&existing.page_id, &suggestion.page_id))
+        });
+        if !duplicate {
+            suggestions.push(suggestion);
+        }
+        if suggestions.len() == MAX_SUGGESTIONS {
+            break;
+        }
+    }
+    suggestions
+}
+
+// Suggestions for `/partial` typed at the start of a note. Built only from
+// config, receipts and the page cache, so it is safe to call on every keystroke.
+#[tauri::command]
+pub fn get_routing_suggestions(partial: String, state: State<'_, AppState>, app: AppHandle) -> Vec<RoutingSuggestion> {
+    let (routes, mut known) = {
+        let config = state.config.lock().unwrap();
+        if config.secrets.main_token().is_empty() && config.capture_profiles.is_empty() {
+            return Vec::new();
+        }
+
+        let routes: Vec<(String, PageCandidate)> = config.capture_profiles
+            .iter()
+            .map(|profile| (profile.name.clone(), PageCandidate {
+                page_id: profile.page_id.clone(),
+                title: profile.page_title.clone(),
+            }))
+            .collect();
+        let mut known: Vec<PageCandidate> = routes.iter().map(|(_, page)| page.clone()).collect();
+        if !config.selected_page_id.is_empty() {
+            known.push(PageCandidate {
+                page_id: config.selected_page_id.clone(),
+                title: config.selected_page_title.clone(),
+            });
+        }
+        (routes, known)
+    };
+
+    let pages: Vec<PageCandidate> = notion::cached_pages()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|page| PageCandidate { page_id: page.id, title: page.title })
+        .collect();
+    known.extend(pages.iter().cloned());
+
+    // Receipts only carry page ids; titles come from whatever page we already know,
+    // and unknown ones are looked up in the background for the next keystroke
+    let mut recent: Vec<PageCandidate> = Vec::new();
+    for receipt in receipts::recent_receipts(RECENT_RECEIPTS_SCANNED) {
+        if recent.iter().any(|page| same_page_id(&page.page_id, &receipt.page_id)) {
+            continue;
+        }
+        if let Some(page) = known.iter().find(|page| same_page_id(&page.page_id, &receipt.page_id)) {
+            recent.push(page.clone());
+            continue;
+        }
+        let display = page_display::display(&app, &receipt.page_id);
+        if display.resolved {
+            recent.push(PageCandidate { page_id: display.page_id, title: display.title });
+        }
+    }
+
+    rank_suggestions(&partial, &routes, &recent, &pages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(page_id: &str, title: &str) -> PageCandidate {
+        PageCandidate { page_id: page_id.into(), title: title.into() }
+    }
+
+    fn inserts(suggestions: &[RoutingSuggestion]) -> Vec<&str> {
+        suggestions.iter().map(|suggestion| suggestion.insert_text.as_str()).collect()
+    }
+
+    #[test]
+    fn fuzzy_scores_favour_runs_and_word_starts() {
+        assert_eq!(fuzzy_score("rl", "Reading List"), Some(8));
+        assert!(fuzzy_score("rea", "Reading List") > fuzzy_score("rea", "Three areas"));
+        assert!(fuzzy_score("wl", "Work Log") > fuzzy_score("wl", "Wallpaper"));
+        assert_eq!(fuzzy_score("READ", "reading"), fuzzy_score("read", "Reading"));
+        assert_eq!(fuzzy_score("r l", "Reading List"), fuzzy_score("rl", "Reading List"));
+        assert_eq!(fuzzy_score("lr", "Reading List"), None);
+        assert_eq!(fuzzy_score("", "Inbox"), Some(0));
+    }
+
+    #[test]
+    fn prefix_matches_rank_ahead_of_recent_and_fuzzy_ones() {
+        let routes = vec![("work".to_string(), page("p-work", "Work Log"))];
+        let recent = vec![page("p-wiki", "Team wiki")];
+        let pages = vec![page("p-wish", "Wishlist"), page("p-wk", "Weekly review")];
+
+        let suggestions = rank_suggestions("/w", &routes, &recent, &pages);
+        // Every name starts with "w" except "Team wiki", which is still used recently
+        assert_eq!(inserts(&suggestions), ["/work ", "/Wishlist ", "/Weekly review ", "/Team wiki "]);
+        assert_eq!(suggestions[0].kind, SuggestionKind::Route);
+        assert_eq!(suggestions[0].display, "/work → Work Log");
+        assert_eq!(suggestions[3].kind, SuggestionKind::Recent);
+    }
+
+    #[test]
+    fn recent_pages_keep_their_order_and_fuzzy_pages_follow_by_score() {
+        let recent = vec![page("p-2", "Project notes"), page("p-1", "Personal notes")];
+        let pages = vec![page("p-3", "Open questions"), page("p-4", "Notes on tools")];
+
+        let suggestions = rank_suggestions("notes", &[], &recent, &pages);
+        assert_eq!(
+            inserts(&suggestions),
+            ["/Notes on tools ", "/Project notes ", "/Personal notes "]
+        );
+
+        // Among fuzzy matches from the cache, the better score wins regardless of cache order
+        let pages = vec![page("p-5", "Glossary of terms"), page("p-6", "Groceries")];
+        let suggestions = rank_suggestions("gro", &[], &[], &pages);
+        assert_eq!(inserts(&suggestions), ["/Groceries ", "/Glossary of terms "]);
+    }
+
+    #[test]
+    fn a_page_appears_once_at_its_best_rank() {
+        let routes = vec![("inbox".to_string(), page("abc-123", "Inbox"))];
+        let recent = vec![page("abc-123", "Inbox"), page("def-456", "Ideas")];
+        // The cache lists the same pages with undashed ids
+        let pages = vec![page("abc123", "Inbox"), page("def456", "Ideas"), page("ghi789", "Ideas")];
+
+        let suggestions = rank_suggestions("i", &routes, &recent, &pages);
+        // A route and its page are different insertions, so both stay
+        assert_eq!(inserts(&suggestions), ["/inbox ", "/Inbox ", "/Ideas "]);
+        assert_eq!(suggestions[1].kind, SuggestionKind::Recent);
+        assert_eq!(suggestions[2].kind, SuggestionKind::Recent);
+    }
+
+    #[test]
+    fn suggestions_are_capped() {
+        let pages: Vec<PageCandidate> = (0..25).map(|index| page(&format!("p-{}", index), &format!("Page {}", index))).collect();
+        let suggestions = rank_suggestions("page", &[], &[], &pages);
+        assert_eq!(suggestions.len(), MAX_SUGGESTIONS);
+        assert_eq!(suggestions[0].insert_text, "/Page 0 ");
+        assert!(rank_suggestions("zzz", &[], &[], &pages).is_empty());
+    }
+}
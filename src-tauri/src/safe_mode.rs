@@ -0,0 +1,243 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::sync::Mutex;
+
+use crate::config::app_data_path;
+
+// Safe mode runs only the core path: config, tray, hotkey, note window and a
+// direct append to the selected page. Entered with `--safe-mode`, or on its
+// own when the previous launches crashed before the tray was ready. It lasts
+// until "Exit safe mode" in the tray clears the crash counter.
+const STARTUP_MARKER_FILE: &str = "startup_marker.json";
+
+pub const CLI_FLAG: &str = "--safe-mode";
+
+// Launches in a row that crashed before the tray was ready
+pub const CRASHES_BEFORE_SAFE_MODE: u32 = 2;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StartupMarker {
+    // Set at launch, cleared once the tray is ready
+    pub starting: bool,
+    pub consecutive_crashes: u32,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SafeModeReason {
+    Flag,
+    Crashes,
+}
+
+// Optional parts of the app that safe mode skips
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Subsystem {
+    Routing,
+    Scratch,
+    LeadingTimestamps,
+    TextFormatting,
+    QueueFlush,
+    MirrorWebhook,
+    BackgroundTasks,
+}
+
+const SUBSYSTEMS: [Subsystem; 7] = [
+    Subsystem::Routing,
+    Subsystem::Scratch,
+    Subsystem::LeadingTimestamps,
+    Subsystem::TextFormatting,
+    Subsystem::QueueFlush,
+    Subsystem::MirrorWebhook,
+    Subsystem::BackgroundTasks,
+];
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct SafeModeInfo {
+    pub reason: SafeModeReason,
+    pub consecutive_crashes: u32,
+    pub skipped: Vec<Subsystem>,
+}
+
+lazy_static::lazy_static! {
+    static ref SAFE_MODE: Mutex<Option<SafeModeInfo>> = Mutex::new(None);
+}
+
+// The marker for this launch, given the one the previous launch left behind
+pub fn next_launch(previous: StartupMarker) -> StartupMarker {
+    StartupMarker {
+        starting: true,
+        consecutive_crashes: if previous.starting {
+            previous.consecutive_crashes + 1
+        } else {
+            previous.consecutive_crashes
+        },
+    }
+}
+
+pub fn decide(flag: bool, marker: StartupMarker) -> Option<SafeModeReason> {
+    if flag {
+        Some(SafeModeReason::Flag)
+    } else if marker.consecutive_crashes >= CRASHES_BEFORE_SAFE_MODE {
+        Some(SafeModeReason::Crashes)
+    } else {
+        None
+    }
+}
+
+// Safe mode for a launch, if it is entered; every optional subsystem is skipped
+fn launch_info(flag: bool, marker: StartupMarker) -> Option<SafeModeInfo> {
+    decide(flag, marker).map(|reason| SafeModeInfo {
+        reason,
+        consecutive_crashes: marker.consecutive_crashes,
+        skipped: SUBSYSTEMS.to_vec(),
+    })
+}
+
+// The marker once the tray is up; in safe mode the counter is kept
+fn ready_marker(marker: StartupMarker, safe_mode: bool) -> StartupMarker {
+    StartupMarker {
+        starting: false,
+        consecutive_crashes: if safe_mode { marker.consecutive_crashes } else { 0 },
+    }
+}
+
+fn runs(info: Option<&SafeModeInfo>, subsystem: Subsystem) -> bool {
+    info.is_none_or(|info| !info.skipped.contains(&subsystem))
+}
+
+fn load_marker() -> StartupMarker {
+    app_data_path(STARTUP_MARKER_FILE)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_marker(marker: StartupMarker) {
+    let result = app_data_path(STARTUP_MARKER_FILE).and_then(|path| {
+        let contents = serde_json::to_string(&marker).map_err(|e| e.to_string())?;
+        fs::write(path, contents).map_err(|e| e.to_string())
+    });
+    if let Err(e) = result {
+        eprintln!("Failed to write startup marker: {}", e);
+    }
+}
+
+// Called first thing in main: count an unfinished previous launch and decide
+pub fn begin_launch(args: impl IntoIterator<Item = String>) {
+    let flag = args.into_iter().any(|arg| arg == CLI_FLAG);
+    let marker = next_launch(load_marker());
+    save_marker(marker);
+    *SAFE_MODE.lock().unwrap() = launch_info(flag, marker);
+}
+
+// The tray is up, so this launch didn't crash during startup. Outside safe
+// mode that also resets the counter; in safe mode only exiting it does.
+pub fn tray_ready() {
+    save_marker(ready_marker(load_marker(), is_active()));
+}
+
+// Clear the crash counter so the next launch starts normally
+pub fn exit() {
+    save_marker(StartupMarker::default());
+}
+
+pub fn is_active() -> bool {
+    SAFE_MODE.lock().unwrap().is_some()
+}
+
+pub fn info() -> Option<SafeModeInfo> {
+    SAFE_MODE.lock().unwrap().clone()
+}
+
+// Whether `subsystem` runs in this launch
+pub fn enabled(subsystem: Subsystem) -> bool {
+    runs(SAFE_MODE.lock().unwrap().as_ref(), subsystem)
+}
+
+pub fn notice() -> Option<String> {
+    info().map(|info| notice_for(&info))
+}
+
+fn notice_for(info: &SafeModeInfo) -> String {
+    match info.reason {
+        SafeModeReason::Flag => "Started with --safe-mode. Only plain notes to the selected page are sent; routing, formatting, the queue and background tasks are off. Use \"Exit safe mode\" in the tray to restart normally.".to_string(),
+        SafeModeReason::Crashes => format!(
+            "The last {} launches crashed during startup, so optional features are off. Notes still go to the selected page. Use \"Exit safe mode\" in the tray to restart normally.",
+            info.consecutive_crashes
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // One launch against the marker on disk; `crash` stops it before the tray is ready
+    fn launch(flag: bool, crash: bool) -> Option<SafeModeInfo> {
+        let marker = next_launch(load_marker());
+        save_marker(marker);
+        let info = launch_info(flag, marker);
+        if !crash {
+            save_marker(ready_marker(load_marker(), info.is_some()));
+        }
+        info
+    }
+
+    #[test]
+    fn two_crashed_launches_in_a_row_enter_safe_mode() {
+        crate::config::use_test_data_dir();
+        assert_eq!(launch(false, true), None);
+        assert_eq!(load_marker(), StartupMarker { starting: true, consecutive_crashes: 0 });
+        assert_eq!(launch(false, true), None);
+        let info = launch(false, false).unwrap();
+        assert_eq!((info.reason, info.consecutive_crashes), (SafeModeReason::Crashes, 2));
+
+        // Safe mode lasts across clean launches until it is exited
+        assert_eq!(launch(false, false).map(|info| info.reason), Some(SafeModeReason::Crashes));
+        exit();
+        assert_eq!(launch(false, false), None);
+        assert_eq!(load_marker(), StartupMarker::default());
+    }
+
+    #[test]
+    fn a_clean_launch_resets_the_count() {
+        crate::config::use_test_data_dir();
+        assert_eq!(launch(false, true), None);
+        assert_eq!(launch(false, false), None);
+        assert_eq!(load_marker(), StartupMarker::default());
+        assert_eq!(launch(false, true), None);
+        assert_eq!(launch(false, false), None);
+    }
+
+    #[test]
+    fn the_flag_enters_safe_mode_without_crashes() {
+        assert_eq!(decide(true, StartupMarker::default()), Some(SafeModeReason::Flag));
+        assert_eq!(decide(false, StartupMarker { starting: false, consecutive_crashes: 1 }), None);
+        assert_eq!(
+            next_launch(StartupMarker { starting: false, consecutive_crashes: 1 }),
+            StartupMarker { starting: true, consecutive_crashes: 1 }
+        );
+    }
+
+    #[test]
+    fn safe_mode_skips_every_optional_subsystem() {
+        assert!(SUBSYSTEMS.iter().all(|subsystem| runs(None, *subsystem)));
+        let info = launch_info(true, StartupMarker::default()).unwrap();
+        assert_eq!(info.skipped, SUBSYSTEMS);
+        assert!(SUBSYSTEMS.iter().all(|subsystem| !runs(Some(&info), *subsystem)));
+
+        let partial = SafeModeInfo { skipped: vec![Subsystem::Routing], ..info };
+        assert!(!runs(Some(&partial), Subsystem::Routing));
+        assert!(runs(Some(&partial), Subsystem::QueueFlush));
+    }
+
+    #[test]
+    fn the_notice_says_why() {
+        let crashes = launch_info(false, StartupMarker { starting: true, consecutive_crashes: 3 }).unwrap();
+        assert!(notice_for(&crashes).starts_with("The last 3 launches crashed during startup"));
+        let flag = launch_info(true, StartupMarker::default()).unwrap();
+        assert!(notice_for(&flag).starts_with("Started with --safe-mode."));
+    }
+}
@@ -0,0 +1,507 @@
+use chrono::{DateTime, Local, NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::blocks;
+use crate::capture::resolve_capture_target;
+use crate::clock;
+use crate::daily_pages;
+use crate::database_rows;
+use crate::config::{app_data_path, AppState, TargetKind};
+use crate::local_crypto;
+use crate::notion::{AppendFailure, NotionApiClient};
+use crate::receipts;
+use crate::retry::SCRATCH_FLUSH_RETRY;
+use crate::timestamps::{self, TimestampOptions};
+
+// Scratch captures wait here, grouped by the local day they were taken,
+// until they are flushed to Notion as one bulleted block group per day
+const SCRATCH_FILE: &str = "scratch.json";
+
+// How often the background task checks for midnight rollover and the daily flush time
+const SCRATCH_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScratchItem {
+    pub id: String,
+    pub text: String,
+    // RFC3339 local capture time
+    pub captured_at: String,
+}
+
+impl ScratchItem {
+    fn captured_at(&self) -> DateTime<Local> {
+        DateTime::parse_from_rfc3339(&self.captured_at)
+            .map(|time| time.with_timezone(&Local))
+            .unwrap_or_else(|_| Local::now())
+    }
+
+    pub fn day(&self) -> NaiveDate {
+        self.captured_at().date_naive()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ScratchBuffer {
+    pub items: Vec<ScratchItem>,
+    // Day (YYYY-MM-DD) the configured flush time last fired, so it runs once per day
+    pub last_auto_flush: Option<String>,
+}
+
+lazy_static::lazy_static! {
+    // Serializes access to the scratch file
+    static ref SCRATCH_LOCK: Mutex<()> = Mutex::new(());
+    // Only one flush may talk to Notion at a time
+    static ref FLUSH_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::new(());
+}
+
+fn read_buffer() -> Result<ScratchBuffer, String> {
+    let path = app_data_path(SCRATCH_FILE)?;
+    if !path.exists() {
+        return Ok(ScratchBuffer::default());
+    }
+
+    let contents = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read scratch notes: {}", e))?;
+    let mut buffer: ScratchBuffer = serde_json::from_str(&contents)
+        .map_err(|e| format!("Failed to parse scratch notes: {}", e))?;
+    for item in &mut buffer.items {
+        item.text = local_crypto::open(&item.text)?;
+    }
+    Ok(buffer)
+}
+
+fn write_buffer(buffer: &ScratchBuffer) -> Result<(), String> {
+    let path = app_data_path(SCRATCH_FILE)?;
+    // Item text is sealed on disk when local encryption is on
+    let mut sealed = buffer.clone();
+    for item in &mut sealed.items {
+        item.text = local_crypto::seal(&item.text)?;
+    }
+    let contents = serde_json::to_string_pretty(&sealed)
+        .map_err(|e| format!("Failed to serialize scratch notes: {}", e))?;
+    fs::write(&path, contents).map_err(|e| format!("Failed to write scratch notes: {}", e))
+}
+
+// Load, change and save the buffer under the file lock
+fn modify_buffer<T>(change: impl FnOnce(&mut ScratchBuffer) -> T) -> Result<T, String> {
+    let _lock = SCRATCH_LOCK.lock().unwrap();
+    let mut buffer = read_buffer()?;
+    let result = change(&mut buffer);
+    write_buffer(&buffer)?;
+    Ok(result)
+}
+
+// Rewrite the scratch file in the current encryption mode
+pub fn reseal() -> Result<(), String> {
+    modify_buffer(|_| ())
+}
+
+pub fn load_buffer() -> Result<ScratchBuffer, String> {
+    let _lock = SCRATCH_LOCK.lock().unwrap();
+    read_buffer()
+}
+
+// Remove the scratch buffer file; returns how many items it held
+pub fn clear() -> Result<usize, String> {
+    let _lock = SCRATCH_LOCK.lock().unwrap();
+    let count = read_buffer()?.items.len();
+    let path = app_data_path(SCRATCH_FILE)?;
+    if path.exists() {
+        fs::remove_file(&path).map_err(|e| format!("Failed to remove scratch buffer: {}", e))?;
+    }
+    Ok(count)
+}
+
+// Number of captures waiting, for the tray tooltip
+pub fn pending_count() -> usize {
+    load_buffer().map(|buffer| buffer.items.len()).unwrap_or(0)
+}
+
+// Returns the note text without the prefix when the capture is marked for the scratch buffer
+pub fn strip_scratch_prefix<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    if prefix.is_empty() {
+        return None;
+    }
+    text.trim_start().strip_prefix(prefix).map(|rest| rest.trim_start())
+}
+
+pub fn add_item(text: &str) -> Result<ScratchItem, String> {
+    let item = ScratchItem {
+        id: receipts::new_capture_id(),
+        text: text.to_string(),
+        captured_at: clock::now().to_rfc3339(),
+    };
+    modify_buffer(|buffer| buffer.items.push(item.clone()))?;
+    Ok(item)
+}
+
+// The block group for one day's captures, each bullet keeping its own timestamp
+pub fn day_children(day: NaiveDate, items: &[ScratchItem], options: &TimestampOptions) -> Vec<serde_json::Value> {
+    let heading = format!("Scratch notes - {}", timestamps::format_day(day, options));
+    let lines: Vec<(String, String)> = items
+        .iter()
+        .map(|item| (timestamps::format_timestamp(&item.captured_at(), options), item.text.clone()))
+        .collect();
+    blocks::build_scratch_children(&heading, &lines)
+}
+
+// Group items by the day they were captured, oldest day first
+fn group_by_day(items: Vec<ScratchItem>) -> BTreeMap<NaiveDate, Vec<ScratchItem>> {
+    let mut days: BTreeMap<NaiveDate, Vec<ScratchItem>> = BTreeMap::new();
+    for item in items {
+        days.entry(item.day()).or_default().push(item);
+    }
+    days
+}
+
+// A database target gets one row per buffered capture, oldest first
+async fn flush_rows(
+    app: &AppHandle,
+    client: &NotionApiClient,
+    database_id: &str,
+    items: Vec<ScratchItem>,
+) -> Result<usize, String> {
+    let mut flushed = 0;
+    for item in items {
+        let response = match database_rows::create_row(client, database_id, &item.text, &item.captured_at()).await {
+            Ok(response) => response,
+            Err(failure) => {
+                crate::refresh_tray(app);
+                return Err(failure.into());
+            }
+        };
+        modify_buffer(|buffer| buffer.items.retain(|buffered| buffered.id != item.id))?;
+        flushed += 1;
+
+        let mut receipt =
+            receipts::DeliveryReceipt::new(receipts::new_capture_id(), database_id.to_string(), response, None);
+        receipt.note_text = Some(item.text);
+        receipt.origin = Some(receipts::NoteOrigin::Typed);
+        if let Err(e) = receipts::record_receipt(&receipt) {
+            eprintln!("Failed to record delivery receipt: {}", e);
+        }
+    }
+
+    crate::refresh_tray(app);
+    Ok(flushed)
+}
+
+// Send the buffered captures to the default page, one block group per day.
+// With `before`, only days earlier than it are flushed (midnight rollover).
+// Delivered items are removed; captures added meanwhile stay buffered.
+pub async fn flush(app: &AppHandle, before: Option<NaiveDate>) -> Result<usize, String> {
+    let _flushing = FLUSH_LOCK.lock().await;
+
+    let items = buffered_before(before)?;
+    if items.is_empty() {
+        return Ok(0);
+    }
+
+    let (mut target, timestamp_options) = {
+        let state = app.state::<AppState>();
+        let config = state.config.lock().unwrap();
+        (resolve_capture_target(&config, None)?, TimestampOptions::from_config(&config))
+    };
+    let client = NotionApiClient::new(target.api_token)?;
+    if let Some(daily) = &target.daily {
+        target.page_id = daily_pages::resolve(&client, daily).await?;
+    }
+    if target.kind == TargetKind::Database {
+        return flush_rows(app, &client, &target.page_id, items).await;
+    }
+
+    let flushed = flush_days(&client, &target.page_id, &target.page_title, items, &timestamp_options).await;
+    crate::refresh_tray(app);
+    flushed
+}
+
+// The buffered captures, or only those from days before `before`
+fn buffered_before(before: Option<NaiveDate>) -> Result<Vec<ScratchItem>, String> {
+    Ok(load_buffer()?
+        .items
+        .into_iter()
+        .filter(|item| before.is_none_or(|day| item.day() < day))
+        .collect())
+}
+
+// Append one block group per day, removing each capture from the buffer once it landed
+async fn flush_days(
+    client: &NotionApiClient,
+    page_id: &str,
+    page_title: &str,
+    items: Vec<ScratchItem>,
+    timestamp_options: &TimestampOptions,
+) -> Result<usize, String> {
+    let mut flushed = 0;
+    for (day, items) in group_by_day(items) {
+        let children = day_children(day, &items, timestamp_options);
+        let (response, partial) = match client.append_children(page_id, page_title, &children).await {
+            Ok(response) => (response, None),
+            Err(AppendFailure::Partial(partial)) => (partial.delivered.clone(), Some(partial)),
+            Err(failure) => return Err(failure.into()),
+        };
+
+        // The heading comes first and every capture is one top-level block after it,
+        // so the blocks that landed past the heading are the first captures in order
+        let delivered = response.block_ids.len().saturating_sub(1).min(items.len());
+        let delivered_ids: Vec<&str> = items[..delivered].iter().map(|item| item.id.as_str()).collect();
+        modify_buffer(|buffer| buffer.items.retain(|item| !delivered_ids.contains(&item.id.as_str())))?;
+        flushed += delivered;
+
+        let mut receipt = receipts::DeliveryReceipt::new(
+            receipts::new_capture_id(),
+            page_id.to_string(),
+            response,
+            partial.as_ref(),
+        );
+        receipt.note_text = Some(
+            items[..delivered]
+                .iter()
+                .map(|item| item.text.as_str())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+        receipt.origin = Some(receipts::NoteOrigin::Typed);
+        if let Err(e) = receipts::record_receipt(&receipt) {
+            eprintln!("Failed to record delivery receipt: {}", e);
+        }
+
+        if let Some(partial) = partial {
+            return Err(AppendFailure::Partial(partial).into());
+        }
+    }
+    Ok(flushed)
+}
+
+// Whether the configured daily flush time has passed today and hasn't fired yet
+fn auto_flush_due(flush_time: Option<&str>, last_auto_flush: Option<&str>, now: DateTime<Local>) -> bool {
+    let Some(flush_time) = flush_time else {
+        return false;
+    };
+    let Ok(time) = NaiveTime::parse_from_str(flush_time, "%H:%M") else {
+        eprintln!("Ignoring invalid scratch flush time '{}'", flush_time);
+        return false;
+    };
+    now.time() >= time && last_auto_flush != Some(now.date_naive().to_string().as_str())
+}
+
+async fn scheduled_flush(app: &AppHandle) -> Result<(), String> {
+    let now = Local::now();
+    let today = now.date_naive();
+
+    // Roll over at midnight: earlier days go out before anything else
+    let buffer = load_buffer()?;
+    if buffer.items.iter().any(|item| item.day() < today) {
+        flush(app, Some(today)).await?;
+    }
+
+    let flush_time = {
+        let state = app.state::<AppState>();
+        let config = state.config.lock().unwrap();
+        config.scratch_flush_time.clone()
+    };
+    if auto_flush_due(flush_time.as_deref(), buffer.last_auto_flush.as_deref(), now) {
+        flush(app, None).await?;
+        modify_buffer(|buffer| buffer.last_auto_flush = Some(today.to_string()))?;
+    }
+    Ok(())
+}
+
+// Background task handling midnight rollover and the configured daily flush
+pub fn start_scratch_flush(app: &AppHandle) {
+    let app_handle = app.clone();
+    let registry = app.state::<AppState>().tasks.clone();
+
+    registry.spawn("scratch-flush", move |mut token| async move {
+        // Consecutive failed flushes; each one waits longer before the next try
+        let mut failures = 0;
+        loop {
+            if !crate::quiet_hours::wait_until_loud(&mut token).await {
+                break;
+            }
+            let wait = match scheduled_flush(&app_handle).await {
+                Ok(()) => {
+                    failures = 0;
+                    SCRATCH_CHECK_INTERVAL
+                }
+                Err(e) => {
+                    failures += 1;
+                    let delay = SCRATCH_FLUSH_RETRY.delay_after(failures, (SCRATCH_FLUSH_RETRY.jitter_source)());
+                    eprintln!("Scheduled scratch flush failed, trying again in {}s: {}", delay.as_secs(), e);
+                    delay
+                }
+            };
+
+            tokio::select! {
+                _ = token.cancelled() => break,
+                _ = tokio::time::sleep(wait) => {}
+            }
+        }
+    });
+}
+
+// Captures waiting in the scratch buffer
+#[tauri::command]
+pub fn get_scratch_buffer() -> Result<Vec<ScratchItem>, String> {
+    Ok(load_buffer()?.items)
+}
+
+// Send all buffered captures now; returns how many were delivered
+#[tauri::command]
+pub async fn flush_scratch_buffer(app: AppHandle) -> Result<usize, String> {
+    flush(&app, None).await
+}
+
+// Drop one capture from the buffer without sending it
+#[tauri::command]
+pub fn discard_scratch_item(id: String, app: AppHandle) -> Result<(), String> {
+    discard(&id)?;
+    crate::refresh_tray(&app);
+    Ok(())
+}
+
+fn discard(id: &str) -> Result<(), String> {
+    let removed = modify_buffer(|buffer| {
+        let before = buffer.items.len();
+        buffer.items.retain(|item| item.id != id);
+        before != buffer.items.len()
+    })?;
+    if !removed {
+        return Err(format!("Scratch note '{}' not found", id));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::mock_notion::{FakeWorkspace, MockNotion, MockReply};
+    use chrono::TimeZone;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    fn item(id: &str, text: &str, day: u32, hour: u32) -> ScratchItem {
+        ScratchItem {
+            id: id.into(),
+            text: text.into(),
+            captured_at: Local.with_ymd_and_hms(2024, 3, day, hour, 0, 0).unwrap().to_rfc3339(),
+        }
+    }
+
+    fn buffer_with(items: Vec<ScratchItem>) {
+        modify_buffer(|buffer| buffer.items = items).unwrap();
+    }
+
+    fn buffered_ids() -> Vec<String> {
+        load_buffer().unwrap().items.into_iter().map(|item| item.id).collect()
+    }
+
+    fn options() -> TimestampOptions {
+        TimestampOptions::from_config(&AppConfig::default())
+    }
+
+    async fn workspace_server(
+        fail_append: Option<usize>,
+    ) -> (Arc<Mutex<FakeWorkspace>>, MockNotion, NotionApiClient) {
+        let workspace = FakeWorkspace::shared();
+        workspace.lock().unwrap().add_page("page-1", "Inbox");
+        let notion = workspace.clone();
+        let appends = AtomicUsize::new(0);
+        let mock = MockNotion::start(move |request| {
+            if request.method == "PATCH" && Some(appends.fetch_add(1, Ordering::SeqCst)) == fail_append {
+                return MockReply::error(400, "validation_error", "body failed validation");
+            }
+            notion.lock().unwrap().answer(request)
+        })
+        .await;
+        let client = NotionApiClient::new(mock.token.clone()).unwrap();
+        (workspace, mock, client)
+    }
+
+    #[test]
+    fn every_capture_is_one_top_level_block() {
+        let long = "x".repeat(blocks::MAX_TEXT_CONTENT_LEN * 150);
+        let items = [("[09:00]".to_string(), "short".to_string()), ("[09:05]".to_string(), long.clone())];
+        let children = blocks::build_scratch_children("Scratch notes", &items);
+        assert_eq!(children.len(), items.len() + 1);
+
+        let bullet = &children[2]["bulleted_list_item"];
+        assert_eq!(bullet["rich_text"].as_array().unwrap().len(), blocks::MAX_RICH_TEXT_RUNS);
+        let nested = bullet["children"].as_array().unwrap();
+        assert!(nested.iter().all(|block| block["paragraph"]["rich_text"].as_array().unwrap().len() <= blocks::MAX_RICH_TEXT_RUNS));
+        let text: String = std::iter::once(&children[2]).chain(nested).map(blocks::block_text).collect();
+        assert_eq!(text, format!("[09:05] {}", long));
+        assert!(children[1]["bulleted_list_item"].get("children").is_none());
+    }
+
+    #[test]
+    fn rollover_takes_only_earlier_days() {
+        let _data_dir = crate::config::use_test_data_dir();
+        buffer_with(vec![item("a", "late", 1, 23), item("b", "early", 2, 0), item("c", "later", 2, 9)]);
+        let today = NaiveDate::from_ymd_opt(2024, 3, 2).unwrap();
+        let ids = |items: Vec<ScratchItem>| items.into_iter().map(|item| item.id).collect::<Vec<_>>();
+        assert_eq!(ids(buffered_before(Some(today)).unwrap()), ["a"]);
+        assert_eq!(ids(buffered_before(None).unwrap()), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn auto_flush_fires_once_a_day_after_its_time() {
+        let at = |day, hour| Local.with_ymd_and_hms(2024, 3, day, hour, 0, 0).unwrap();
+        assert!(!auto_flush_due(None, None, at(2, 23)));
+        assert!(!auto_flush_due(Some("18:00"), None, at(2, 17)));
+        assert!(auto_flush_due(Some("18:00"), None, at(2, 18)));
+        assert!(auto_flush_due(Some("18:00"), Some("2024-03-01"), at(2, 19)));
+        assert!(!auto_flush_due(Some("18:00"), Some("2024-03-02"), at(2, 19)));
+        assert!(!auto_flush_due(Some("6pm"), None, at(2, 19)));
+    }
+
+    #[tokio::test]
+    async fn flush_sends_one_group_per_day_and_empties_the_buffer() {
+        let _data_dir = crate::config::use_test_data_dir();
+        buffer_with(vec![item("a", "one", 1, 9), item("b", "two", 2, 9), item("c", "three", 1, 10)]);
+        let (workspace, mock, client) = workspace_server(None).await;
+
+        let flushed = flush_days(&client, "page-1", "Inbox", buffered_before(None).unwrap(), &options()).await;
+        assert_eq!(flushed, Ok(3));
+        assert!(buffered_ids().is_empty());
+        assert_eq!(mock.count("PATCH", "/v1/blocks/"), 2);
+        let texts: Vec<String> = workspace.lock().unwrap().children("page-1").iter().map(blocks::block_text).collect();
+        assert_eq!(texts.len(), 5);
+        assert!(texts[0].starts_with("Scratch notes - "));
+        assert!(texts[1].ends_with(" one") && texts[2].ends_with(" three"), "{:?}", texts);
+        assert!(texts[4].ends_with(" two"), "{:?}", texts);
+    }
+
+    #[tokio::test]
+    async fn a_partial_flush_keeps_exactly_the_undelivered_captures() {
+        let _data_dir = crate::config::use_test_data_dir();
+        let items: Vec<ScratchItem> = (0..150).map(|index| item(&format!("n{}", index), "note", 1, 9)).collect();
+        buffer_with(items);
+        // The heading and the first 99 captures fill the first request; the second fails
+        let (workspace, _mock, client) = workspace_server(Some(1)).await;
+
+        let error = flush_days(&client, "page-1", "Inbox", buffered_before(None).unwrap(), &options()).await.unwrap_err();
+        assert!(error.contains("1 of 2 parts"), "{}", error);
+        assert_eq!(workspace.lock().unwrap().children("page-1").len(), 100);
+        let left = buffered_ids();
+        assert_eq!(left.len(), 51);
+        assert_eq!(left[0], "n99");
+    }
+
+    #[test]
+    fn discard_removes_one_capture() {
+        let _data_dir = crate::config::use_test_data_dir();
+        buffer_with(vec![item("a", "keep", 1, 9), item("b", "drop", 1, 10)]);
+        discard("b").unwrap();
+        assert_eq!(buffered_ids(), ["a"]);
+        assert!(discard("b").unwrap_err().contains("not found"));
+        assert_eq!(pending_count(), 1);
+    }
+}
@@ -0,0 +1,479 @@
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+
+use crate::config::{app_data_path, AppConfig};
+
+// Secrets (API tokens, the webhook signing secret) live outside config.json.
+// AppConfig holds them in a `Secrets` value that is skipped by serde and has
+// no Serialize impl, so no dump of the config can carry them; the serialized
+// config only has opaque references like "keyring:main". Values go to the OS
+// keyring where there is a persistent one, and to secrets.json otherwise.
+
+const KEYRING_SERVICE: &str = "notion-quick-notes";
+const SECRETS_FILE: &str = "secrets.json";
+
+const MAIN_TOKEN_KEY: &str = "main";
+const WEBHOOK_SECRET_KEY: &str = "mirror-webhook";
+
+// Stands in for a secret in files that must not hold it
+pub const REDACTED: &str = "<redacted>";
+
+fn profile_key(name: &str) -> String {
+    format!("profile:{}", name)
+}
+
+pub trait SecretStore: Send + Sync {
+    // Prefix of the references this store hands out
+    fn name(&self) -> &'static str;
+    fn get(&self, key: &str) -> Result<Option<String>, String>;
+    fn set(&self, key: &str, value: &str) -> Result<(), String>;
+    fn delete(&self, key: &str) -> Result<(), String>;
+}
+
+pub struct KeyringStore;
+
+impl SecretStore for KeyringStore {
+    fn name(&self) -> &'static str {
+        "keyring"
+    }
+
+    fn get(&self, key: &str) -> Result<Option<String>, String> {
+        let entry = keyring::Entry::new(KEYRING_SERVICE, key).map_err(|e| e.to_string())?;
+        match entry.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(format!("Keyring unavailable: {}", e)),
+        }
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<(), String> {
+        keyring::Entry::new(KEYRING_SERVICE, key)
+            .and_then(|entry| entry.set_password(value))
+            .map_err(|e| format!("Keyring unavailable: {}", e))
+    }
+
+    fn delete(&self, key: &str) -> Result<(), String> {
+        let entry = keyring::Entry::new(KEYRING_SERVICE, key).map_err(|e| e.to_string())?;
+        match entry.delete_password() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(format!("Keyring unavailable: {}", e)),
+        }
+    }
+}
+
+// secrets.json next to the config, readable only by the user where the OS allows
+pub struct FileStore;
+
+lazy_static::lazy_static! {
+    // Serializes access to the secrets file
+    static ref FILE_LOCK: Mutex<()> = Mutex::new(());
+}
+
+impl FileStore {
+    fn read(&self) -> Result<BTreeMap<String, String>, String> {
+        let path = app_data_path(SECRETS_FILE)?;
+        if !path.exists() {
+            return Ok(BTreeMap::new());
+        }
+        let contents = fs::read_to_string(&path).map_err(|e| format!("Failed to read secrets: {}", e))?;
+        serde_json::from_str(&contents).map_err(|e| format!("Failed to parse secrets: {}", e))
+    }
+
+    fn write(&self, secrets: &BTreeMap<String, String>) -> Result<(), String> {
+        let path = app_data_path(SECRETS_FILE)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create data directory: {}", e))?;
+        }
+        let contents = serde_json::to_string_pretty(secrets).map_err(|e| format!("Failed to serialize secrets: {}", e))?;
+        fs::write(&path, contents).map_err(|e| format!("Failed to write secrets: {}", e))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o600))
+                .map_err(|e| format!("Failed to restrict secrets file: {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+impl SecretStore for FileStore {
+    fn name(&self) -> &'static str {
+        "file"
+    }
+
+    fn get(&self, key: &str) -> Result<Option<String>, String> {
+        let _lock = FILE_LOCK.lock().unwrap();
+        Ok(self.read()?.remove(key))
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<(), String> {
+        let _lock = FILE_LOCK.lock().unwrap();
+        let mut secrets = self.read()?;
+        secrets.insert(key.to_string(), value.to_string());
+        self.write(&secrets)
+    }
+
+    fn delete(&self, key: &str) -> Result<(), String> {
+        let _lock = FILE_LOCK.lock().unwrap();
+        let mut secrets = self.read()?;
+        if secrets.remove(key).is_some() {
+            self.write(&secrets)?;
+        }
+        Ok(())
+    }
+}
+
+// The preferred store: the keyring on Windows and macOS. Linux only has the
+// session keyring without Secret Service, which forgets everything on logout.
+// Tests use the file in their own data directory, never the user's keyring.
+fn preferred_store() -> &'static dyn SecretStore {
+    if cfg!(any(windows, target_os = "macos")) && !cfg!(test) {
+        &KeyringStore
+    } else {
+        &FileStore
+    }
+}
+
+fn store_named(name: &str) -> Option<&'static dyn SecretStore> {
+    match name {
+        "keyring" => Some(&KeyringStore),
+        "file" => Some(&FileStore),
+        _ => None,
+    }
+}
+
+// Store the value and return its reference, falling back to the file when the keyring fails
+fn put(key: &str, value: &str) -> Result<String, String> {
+    let store = preferred_store();
+    match store.set(key, value) {
+        Ok(()) => Ok(format!("{}:{}", store.name(), key)),
+        Err(e) if store.name() != FileStore.name() => {
+            eprintln!("Storing {} in the secrets file: {}", key, e);
+            FileStore.set(key, value)?;
+            Ok(format!("{}:{}", FileStore.name(), key))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn resolve(reference: &str) -> Result<Option<String>, String> {
+    let (store, key) = reference
+        .split_once(':')
+        .and_then(|(store, key)| Some((store_named(store)?, key)))
+        .ok_or_else(|| format!("Unknown secret reference '{}'", reference))?;
+    store.get(key)
+}
+
+fn remove(reference: &str) {
+    let Some((store, key)) = reference.split_once(':').and_then(|(store, key)| Some((store_named(store)?, key))) else {
+        return;
+    };
+    if let Err(e) = store.delete(key) {
+        eprintln!("Failed to remove secret {}: {}", reference, e);
+    }
+}
+
+// Secret material for one config. Deliberately not Serialize; Debug is redacted.
+#[derive(Clone, Default, PartialEq)]
+pub struct Secrets {
+    main_token: String,
+    // By profile name; a profile without one uses the main token
+    profile_tokens: BTreeMap<String, String>,
+    webhook_secret: Option<String>,
+    // References written by the last load or save, to delete the ones no longer used
+    stored: BTreeSet<String>,
+    // References that couldn't be read (e.g. a locked keyring); kept as they are on save
+    unreadable: BTreeSet<String>,
+}
+
+impl fmt::Debug for Secrets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Secrets")
+            .field("main_token", &(!self.main_token.is_empty()).then_some("<redacted>"))
+            .field("profile_tokens", &self.profile_tokens.keys().collect::<Vec<_>>())
+            .field("webhook_secret", &self.webhook_secret.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+impl Secrets {
+    pub fn main_token(&self) -> &str {
+        &self.main_token
+    }
+
+    pub fn set_main_token(&mut self, token: String) {
+        self.main_token = token;
+    }
+
+    pub fn profile_token(&self, profile: &str) -> Option<&str> {
+        self.profile_tokens.get(profile).map(String::as_str).filter(|token| !token.is_empty())
+    }
+
+    // An empty token clears it, so the profile falls back to the main token
+    pub fn set_profile_token(&mut self, profile: &str, token: String) {
+        if token.is_empty() {
+            self.profile_tokens.remove(profile);
+        } else {
+            self.profile_tokens.insert(profile.to_string(), token);
+        }
+    }
+
+    pub fn profile_names(&self) -> impl Iterator<Item = &str> {
+        self.profile_tokens.keys().map(String::as_str)
+    }
+
+    pub fn clear_profile_tokens(&mut self) {
+        self.profile_tokens.clear();
+    }
+
+    pub fn webhook_secret(&self) -> Option<&str> {
+        self.webhook_secret.as_deref()
+    }
+
+    pub fn set_webhook_secret(&mut self, secret: Option<String>) {
+        self.webhook_secret = secret.filter(|secret| !secret.is_empty());
+    }
+
+    // Every token in use, main first
+    pub fn tokens(&self) -> Vec<&str> {
+        std::iter::once(self.main_token.as_str())
+            .chain(self.profile_tokens.values().map(String::as_str))
+            .filter(|token| !token.is_empty())
+            .collect()
+    }
+
+    fn values(&self) -> impl Iterator<Item = &str> {
+        self.tokens().into_iter().chain(self.webhook_secret.as_deref())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values().next().is_none()
+    }
+
+    // Fill in anything not already set from `other`
+    pub fn merge_missing(&mut self, other: Secrets) {
+        if self.main_token.is_empty() {
+            self.main_token = other.main_token;
+        }
+        if self.webhook_secret.is_none() {
+            self.webhook_secret = other.webhook_secret;
+        }
+        for (name, token) in other.profile_tokens {
+            self.profile_tokens.entry(name).or_insert(token);
+        }
+    }
+
+    // Read the secrets the config's references point to
+    pub fn load(config: &AppConfig) -> Secrets {
+        let mut secrets = Secrets::default();
+        let mut read = |reference: &Option<String>| -> Option<String> {
+            let reference = reference.as_deref()?;
+            secrets.stored.insert(reference.to_string());
+            match resolve(reference) {
+                Ok(value) => value,
+                Err(e) => {
+                    eprintln!("Couldn't read secret {}: {}", reference, e);
+                    secrets.unreadable.insert(reference.to_string());
+                    None
+                }
+            }
+        };
+        let main_token = read(&config.token_ref).unwrap_or_default();
+        let webhook_secret = read(&config.mirror_webhook_secret_ref);
+        let profile_tokens: BTreeMap<String, String> = config
+            .capture_profiles
+            .iter()
+            .filter_map(|profile| Some((profile.name.clone(), read(&profile.token_ref)?)))
+            .collect();
+        secrets.main_token = main_token;
+        secrets.webhook_secret = webhook_secret;
+        secrets.profile_tokens = profile_tokens;
+        secrets
+    }
+
+    // Write the secrets to the store and point the config's references at them.
+    // Tokens of profiles that no longer exist are dropped.
+    pub fn persist(&mut self, config: &mut AppConfig) -> Result<(), String> {
+        let names: BTreeSet<&str> = config.capture_profiles.iter().map(|profile| profile.name.as_str()).collect();
+        self.profile_tokens.retain(|name, _| names.contains(name.as_str()));
+
+        let mut stored = BTreeSet::new();
+        let unreadable = &self.unreadable;
+        let mut write = |key: &str, value: Option<&str>, current: Option<String>| -> Result<Option<String>, String> {
+            let Some(value) = value.filter(|value| !value.is_empty()) else {
+                // Not being able to read a secret must not delete it
+                let kept = current.filter(|reference| unreadable.contains(reference));
+                stored.extend(kept.clone());
+                return Ok(kept);
+            };
+            let reference = put(key, value)?;
+            stored.insert(reference.clone());
+            Ok(Some(reference))
+        };
+        config.token_ref = write(MAIN_TOKEN_KEY, Some(&self.main_token), config.token_ref.take())?;
+        config.mirror_webhook_secret_ref = write(
+            WEBHOOK_SECRET_KEY,
+            self.webhook_secret.as_deref(),
+            config.mirror_webhook_secret_ref.take(),
+        )?;
+        for profile in &mut config.capture_profiles {
+            let current = profile.token_ref.take();
+            profile.token_ref = write(&profile_key(&profile.name), self.profile_token(&profile.name), current)?;
+        }
+
+        for reference in self.stored.difference(&stored) {
+            remove(reference);
+        }
+        self.stored = stored;
+        Ok(())
+    }
+
+    // Debug builds check that serialized output never contains a secret
+    pub fn debug_assert_absent(&self, serialized: &str) {
+        if cfg!(debug_assertions) {
+            for value in self.values() {
+                assert!(!serialized.contains(value), "a secret leaked into serialized output");
+            }
+        }
+    }
+}
+
+// Secrets written inline by older versions, taken out of the raw config JSON.
+// The caller puts them in the store and saves the config without them.
+pub fn take_legacy(raw: &mut Value) -> Secrets {
+    let take = |node: &mut Value, field: &str| -> Option<String> {
+        let value = node.as_object_mut()?.remove(field)?;
+        value.as_str().map(str::to_string).filter(|value| !value.is_empty())
+    };
+    let mut secrets = Secrets {
+        main_token: take(raw, "notion_api_token").unwrap_or_default(),
+        webhook_secret: take(raw, "mirror_webhook_secret"),
+        ..Default::default()
+    };
+    if let Some(profiles) = raw.get_mut("capture_profiles").and_then(Value::as_array_mut) {
+        for profile in profiles {
+            let name = profile["name"].as_str().unwrap_or_default().to_string();
+            if let Some(token) = take(profile, "notion_api_token") {
+                secrets.profile_tokens.insert(name, token);
+            }
+        }
+    }
+    secrets
+}
+
+// The same inline secrets replaced with a marker, e.g. for a backup of the
+// config before they are moved; the marker says a value was there
+pub fn redact_legacy(raw: &mut Value) {
+    let redact = |node: &mut Value, field: &str| {
+        if let Some(value) = node.get_mut(field).filter(|value| value.as_str().is_some_and(|value| !value.is_empty())) {
+            *value = Value::String(REDACTED.to_string());
+        }
+    };
+    redact(raw, "notion_api_token");
+    redact(raw, "mirror_webhook_secret");
+    if let Some(profiles) = raw.get_mut("capture_profiles").and_then(Value::as_array_mut) {
+        for profile in profiles {
+            redact(profile, "notion_api_token");
+        }
+    }
+}
+
+// Identifies a token without holding it, e.g. for keying rate-limit state
+pub fn token_key(token: &str) -> String {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    token.hash(&mut hasher);
+    format!("token-{:016x}", hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{use_test_data_dir, CaptureProfile};
+    use serde_json::json;
+
+    const SENTINEL: &str = "secret_SENTINEL_7c1e";
+
+    // A config holding the sentinel as every kind of secret
+    fn config_with_secrets() -> AppConfig {
+        let mut config = AppConfig::default();
+        config.capture_profiles.push(CaptureProfile {
+            name: "work".into(),
+            token_ref: None,
+            page_id: "page-1".into(),
+            page_title: "Work".into(),
+            capture_hotkey: None,
+        });
+        config.secrets.set_main_token(format!("{}-main", SENTINEL));
+        config.secrets.set_profile_token("work", format!("{}-work", SENTINEL));
+        config.secrets.set_webhook_secret(Some(format!("{}-webhook", SENTINEL)));
+        config
+    }
+
+    #[test]
+    fn serialized_config_never_contains_a_secret() {
+        let config = config_with_secrets();
+
+        let serialized = serde_json::to_string(&config).unwrap();
+        assert!(!serialized.contains(SENTINEL), "{}", serialized);
+        assert!(!serde_json::to_value(&config).unwrap().to_string().contains(SENTINEL));
+        assert!(!format!("{:?}", config).contains(SENTINEL));
+        assert!(!format!("{:#?}", config.secrets).contains(SENTINEL));
+    }
+
+    #[test]
+    fn saved_config_holds_references_and_loads_the_secrets_back() {
+        let dir = use_test_data_dir();
+        let mut config = config_with_secrets();
+        config.save().unwrap();
+
+        let on_disk = fs::read_to_string(dir.join("config.json")).unwrap();
+        assert!(!on_disk.contains(SENTINEL), "{}", on_disk);
+        assert_eq!(config.token_ref.as_deref(), Some("file:main"));
+        assert_eq!(config.capture_profiles[0].token_ref.as_deref(), Some("file:profile:work"));
+        assert_eq!(config.mirror_webhook_secret_ref.as_deref(), Some("file:mirror-webhook"));
+
+        let loaded = Secrets::load(&serde_json::from_str(&on_disk).unwrap());
+        assert_eq!(loaded.main_token(), format!("{}-main", SENTINEL));
+        assert_eq!(loaded.profile_token("work"), Some(format!("{}-work", SENTINEL).as_str()));
+        assert_eq!(loaded.webhook_secret(), Some(format!("{}-webhook", SENTINEL).as_str()));
+    }
+
+    #[test]
+    fn removed_secrets_are_deleted_from_the_store() {
+        use_test_data_dir();
+        let mut config = config_with_secrets();
+        config.save().unwrap();
+
+        config.capture_profiles.clear();
+        config.secrets.set_webhook_secret(None);
+        config.save().unwrap();
+
+        assert_eq!(FileStore.get("profile:work").unwrap(), None);
+        assert_eq!(FileStore.get(WEBHOOK_SECRET_KEY).unwrap(), None);
+        assert_eq!(FileStore.get(MAIN_TOKEN_KEY).unwrap(), Some(format!("{}-main", SENTINEL)));
+        assert_eq!(config.mirror_webhook_secret_ref, None);
+    }
+
+    #[test]
+    fn legacy_inline_secrets_are_taken_out_of_the_raw_config() {
+        let mut raw = json!({
+            "notion_api_token": "secret_main",
+            "mirror_webhook_secret": "",
+            "capture_profiles": [
+                { "name": "work", "notion_api_token": "secret_work" },
+                { "name": "home" }
+            ]
+        });
+
+        let secrets = take_legacy(&mut raw);
+        assert_eq!(secrets.main_token(), "secret_main");
+        assert_eq!(secrets.profile_token("work"), Some("secret_work"));
+        assert_eq!(secrets.profile_token("home"), None);
+        assert_eq!(secrets.webhook_secret(), None);
+        assert!(!raw.to_string().contains("secret_"), "{}", raw);
+    }
+}
@@ -0,0 +1,745 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use serde_json::Value;
+use tauri::{AppHandle, Manager, State};
+
+use crate::abbreviations::{validate_abbreviations, Abbreviation};
+use crate::blocks::{NoteBlockType, NOTION_COLORS};
+use crate::config::{AppConfig, AppState, CaptureProfile, TargetMode};
+use crate::error::AppError;
+use crate::events;
+use crate::typography::TypographyOptions;
+use crate::window_effects::WindowEffect;
+
+// Tauri commands for app settings that aren't tied to the Notion API
+
+// Central config update: apply the change, save, and tell every window about it
+pub fn update_config<F>(app: &AppHandle, change: F) -> Result<(), String>
+where
+    F: FnOnce(&mut AppConfig) -> Result<(), AppError>,
+{
+    let state = app.state::<AppState>();
+    {
+        let mut config = state.config.lock().unwrap();
+        let mut updated = config.clone();
+        change(&mut updated).map_err(|e| e.to_string())?;
+        updated.save()?;
+        *config = updated;
+    }
+
+    events::emit_app_event(app, events::AppEvent::ConfigChanged);
+    Ok(())
+}
+
+// Validate and normalize the hotkey exclusion list, dropping blank and duplicate entries
+fn validate_excluded_apps(apps: Vec<String>) -> Result<Vec<String>, AppError> {
+    let mut cleaned: Vec<String> = Vec::new();
+    for app in apps {
+        let app = app.trim().to_string();
+        if app == "bundle:" {
+            return Err(AppError::ValidationError("bundle: entries need a bundle id".into()));
+        }
+        if !app.is_empty() && !cleaned.iter().any(|existing| existing.eq_ignore_ascii_case(&app)) {
+            cleaned.push(app);
+        }
+    }
+    Ok(cleaned)
+}
+
+// Accelerators compare case- and order-insensitively on their modifiers ("alt+ctrl+1" == "Ctrl+Alt+1")
+fn normalize_accelerator(accelerator: &str) -> String {
+    let mut parts: Vec<String> = accelerator
+        .split('+')
+        .map(|part| part.trim().to_lowercase())
+        .filter(|part| !part.is_empty())
+        .collect();
+    let key = parts.pop().unwrap_or_default();
+    parts.sort();
+    parts.push(key);
+    parts.join("+")
+}
+
+// The clipboard hotkey trimmed, or None when blank; it can't take the main hotkey
+fn validate_clipboard_hotkey(hotkey: Option<String>) -> Result<Option<String>, AppError> {
+    let hotkey = hotkey.map(|hotkey| hotkey.trim().to_string()).filter(|hotkey| !hotkey.is_empty());
+    if hotkey.as_deref().is_some_and(|hotkey| normalize_accelerator(hotkey) == normalize_accelerator(crate::PRIMARY_HOTKEY)) {
+        return Err(AppError::ValidationError("The clipboard hotkey conflicts with the main capture hotkey".into()));
+    }
+    Ok(hotkey)
+}
+
+// Validate capture profiles, including hotkey conflicts between profiles and
+// with the main and clipboard hotkeys
+fn validate_profiles(profiles: Vec<CaptureProfile>, clipboard_hotkey: Option<&str>) -> Result<Vec<CaptureProfile>, AppError> {
+    let mut seen_names: Vec<String> = Vec::new();
+    let mut seen_hotkeys: Vec<(String, String)> = vec![(normalize_accelerator(crate::PRIMARY_HOTKEY), "the main capture hotkey".into())];
+    if let Some(hotkey) = clipboard_hotkey {
+        seen_hotkeys.push((normalize_accelerator(hotkey), "the clipboard hotkey".into()));
+    }
+    let mut cleaned = Vec::new();
+
+    for mut profile in profiles {
+        profile.name = profile.name.trim().to_string();
+        if profile.name.is_empty() {
+            return Err(AppError::ValidationError("Capture profiles need a name".into()));
+        }
+        if seen_names.iter().any(|name| name.eq_ignore_ascii_case(&profile.name)) {
+            return Err(AppError::ValidationError(format!("Duplicate capture profile '{}'", profile.name)));
+        }
+        if profile.page_id.trim().is_empty() {
+            return Err(AppError::ValidationError(format!("Capture profile '{}' has no page", profile.name)));
+        }
+
+        profile.capture_hotkey = profile.capture_hotkey
+            .map(|hotkey| hotkey.trim().to_string())
+            .filter(|hotkey| !hotkey.is_empty());
+        if let Some(hotkey) = &profile.capture_hotkey {
+            let normalized = normalize_accelerator(hotkey);
+            if let Some((_, owner)) = seen_hotkeys.iter().find(|(existing, _)| *existing == normalized) {
+                return Err(AppError::ValidationError(format!(
+                    "Hotkey {} for '{}' conflicts with {}",
+                    hotkey, profile.name, owner
+                )));
+            }
+            seen_hotkeys.push((normalized, format!("profile '{}'", profile.name)));
+        }
+
+        seen_names.push(profile.name.clone());
+        cleaned.push(profile);
+    }
+
+    Ok(cleaned)
+}
+
+// Get the apps in which the global hotkey is ignored
+#[tauri::command]
+pub fn get_hotkey_excluded_apps(state: State<'_, AppState>) -> Result<Vec<String>, String> {
+    let config = state.config.lock().unwrap();
+    Ok(config.hotkey_excluded_apps.clone())
+}
+
+// Replace the hotkey exclusion list
+#[tauri::command]
+pub fn set_hotkey_excluded_apps(apps: Vec<String>, app: AppHandle) -> Result<(), String> {
+    update_config(&app, |config| {
+        config.hotkey_excluded_apps = validate_excluded_apps(apps)?;
+        Ok(())
+    })
+}
+
+// Get what notes are appended as by default
+#[tauri::command]
+pub fn get_note_block_type(state: State<'_, AppState>) -> Result<NoteBlockType, String> {
+    let config = state.config.lock().unwrap();
+    Ok(config.note_block_type)
+}
+
+// Change what notes are appended as when a capture doesn't choose
+#[tauri::command]
+pub fn set_note_block_type(block_type: NoteBlockType, app: AppHandle) -> Result<(), String> {
+    update_config(&app, |config| {
+        config.note_block_type = block_type;
+        Ok(())
+    })
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct NoteEmphasis {
+    pub bold: bool,
+    pub color: String,
+    pub highlight_hashtags: bool,
+    pub hashtag_color: String,
+}
+
+// Get the bold and color applied to note text, and to its #tags
+#[tauri::command]
+pub fn get_note_emphasis(state: State<'_, AppState>) -> Result<NoteEmphasis, String> {
+    let config = state.config.lock().unwrap();
+    Ok(NoteEmphasis {
+        bold: config.note_bold,
+        color: config.note_color.clone(),
+        highlight_hashtags: config.highlight_hashtags,
+        hashtag_color: config.hashtag_color.clone(),
+    })
+}
+
+// Change the bold and color applied to note text; the colors must be ones
+// Notion knows. The #tag settings are kept as they are when not given.
+#[tauri::command]
+pub fn set_note_emphasis(
+    bold: bool,
+    color: String,
+    highlight_hashtags: Option<bool>,
+    hashtag_color: Option<String>,
+    app: AppHandle,
+) -> Result<(), String> {
+    for color in std::iter::once(&color).chain(&hashtag_color) {
+        if !NOTION_COLORS.contains(&color.as_str()) {
+            return Err(format!("\"{}\" isn't a Notion color", color));
+        }
+    }
+    update_config(&app, |config| {
+        config.note_bold = bold;
+        config.note_color = color;
+        if let Some(highlight_hashtags) = highlight_hashtags {
+            config.highlight_hashtags = highlight_hashtags;
+        }
+        if let Some(hashtag_color) = hashtag_color {
+            config.hashtag_color = hashtag_color;
+        }
+        Ok(())
+    })
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TargetModeSettings {
+    pub mode: TargetMode,
+    pub daily_parent_page_id: String,
+    pub daily_title_format: String,
+}
+
+// Get whether notes go to the selected page or to a page per day
+#[tauri::command]
+pub fn get_target_mode(state: State<'_, AppState>) -> Result<TargetModeSettings, String> {
+    let config = state.config.lock().unwrap();
+    Ok(TargetModeSettings {
+        mode: config.target_mode,
+        daily_parent_page_id: config.daily_parent_page_id.clone(),
+        daily_title_format: config.daily_title_format.clone(),
+    })
+}
+
+// Switch between the fixed page and daily pages; the parent and title format
+// are kept as they are when not given
+#[tauri::command]
+pub fn set_target_mode(
+    mode: TargetMode,
+    daily_parent_page_id: Option<String>,
+    daily_title_format: Option<String>,
+    app: AppHandle,
+) -> Result<(), String> {
+    update_config(&app, |config| {
+        config.target_mode = mode;
+        if let Some(parent) = daily_parent_page_id {
+            config.daily_parent_page_id = parent.trim().to_string();
+        }
+        if let Some(format) = daily_title_format {
+            config.daily_title_format = format;
+        }
+        validate_config(config)
+    })
+}
+
+// Timezones a note timestamp can be shown in
+#[tauri::command]
+pub fn get_timezones() -> Vec<&'static str> {
+    crate::timestamps::timezone_names()
+}
+
+// Show note timestamps in another timezone; None or a blank name goes back to local time
+#[tauri::command]
+pub fn set_timestamp_timezone(timezone: Option<String>, app: AppHandle) -> Result<(), String> {
+    update_config(&app, |config| {
+        config.timestamp_timezone = crate::timestamps::validate_timezone(timezone)?;
+        Ok(())
+    })
+}
+
+// Get the extra headers sent with Notion requests
+#[tauri::command]
+pub fn get_extra_headers(state: State<'_, AppState>) -> Result<BTreeMap<String, String>, String> {
+    let config = state.config.lock().unwrap();
+    Ok(config.extra_headers.clone())
+}
+
+// Replace the extra headers; invalid or protected headers are rejected as a whole
+#[tauri::command]
+pub fn set_extra_headers(headers: BTreeMap<String, String>, app: AppHandle) -> Result<(), String> {
+    // Applied only once saved, so a failed save leaves the old headers in use
+    update_config(&app, |config| {
+        for (name, value) in &headers {
+            crate::notion::parse_extra_header(name, value).map_err(AppError::ValidationError)?;
+        }
+        config.extra_headers = headers.clone();
+        Ok(())
+    })?;
+    crate::notion::set_extra_headers(&headers)
+}
+
+// Change the note window backdrop. Transparency is fixed when a window is created,
+// so the hidden note window is closed and rebuilt with the new effect on next show.
+#[tauri::command]
+pub fn set_window_effects(effect: WindowEffect, app: AppHandle) -> Result<(), String> {
+    update_config(&app, |config| {
+        config.window_effects = effect;
+        Ok(())
+    })?;
+    
+    if let Some(window) = app.get_window("main") {
+        if !window.is_visible().unwrap_or(false) {
+            window.close().map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+// Change note window spellcheck. Turning it on or off reaches the note window
+// the next time it is shown; where languages are fixed at startup,
+// get_app_info reports that a restart is needed.
+#[tauri::command]
+pub fn set_spellcheck(enabled: bool, languages: Vec<String>, app: AppHandle) -> Result<crate::spellcheck::SpellcheckStatus, String> {
+    update_config(&app, |config| {
+        let normalized = crate::spellcheck::normalize_languages(&languages);
+        if let Some(invalid) = languages
+            .iter()
+            .map(|language| language.trim())
+            .find(|language| !normalized.iter().any(|valid| valid.eq_ignore_ascii_case(language)))
+        {
+            return Err(AppError::ValidationError(format!("'{}' isn't a language tag like en-US", invalid)));
+        }
+        config.spellcheck_enabled = enabled;
+        config.spellcheck_languages = normalized;
+        Ok(())
+    })?;
+    let state = app.state::<AppState>();
+    let config = state.config.lock().unwrap();
+    Ok(crate::spellcheck::status(&config))
+}
+
+// Turn typography cleanup on or off, optionally changing which passes run
+#[tauri::command]
+pub fn set_typography(enabled: bool, options: Option<TypographyOptions>, app: AppHandle) -> Result<(), String> {
+    update_config(&app, |config| {
+        config.normalize_typography = enabled;
+        if let Some(options) = options {
+            config.typography = options;
+        }
+        Ok(())
+    })
+}
+
+// Give a capture profile its own API token, or clear it (empty) to use the main one
+#[tauri::command]
+pub fn set_profile_token(profile: String, token: String, app: AppHandle) -> Result<(), String> {
+    update_config(&app, |config| {
+        if !config.capture_profiles.iter().any(|existing| existing.name == profile) {
+            return Err(AppError::ValidationError(format!("No capture profile named '{}'", profile)));
+        }
+        config.secrets.set_profile_token(&profile, token.trim().to_string());
+        Ok(())
+    })
+}
+
+// Set or clear (empty URL) the webhook that delivered captures are mirrored to
+#[tauri::command]
+pub fn set_mirror_webhook(
+    url: String,
+    secret: Option<String>,
+    allow_insecure: bool,
+    app: AppHandle,
+) -> Result<(), String> {
+    let url = url.trim().to_string();
+    update_config(&app, |config| {
+        if !url.is_empty() {
+            crate::webhook::validate_webhook_url(&url, allow_insecure)?;
+        }
+        config.mirror_webhook_url = (!url.is_empty()).then_some(url);
+        config.secrets.set_webhook_secret(secret);
+        config.allow_insecure_webhook = allow_insecure;
+        Ok(())
+    })
+}
+
+// Turn sandbox mode on (needs a sandbox page) or off
+#[tauri::command]
+pub fn set_sandbox_mode(enabled: bool, app: AppHandle) -> Result<(), String> {
+    update_config(&app, |config| {
+        if enabled && config.sandbox_page_id.is_none() {
+            return Err(AppError::ValidationError("Create a sandbox page before turning sandbox mode on".into()));
+        }
+        config.sandbox_mode = enabled;
+        Ok(())
+    })?;
+    crate::refresh_tray(&app);
+    Ok(())
+}
+
+// Turn local encryption on or off, rewriting the local stores in the new mode.
+// Nothing changes if a store can't be rewritten, e.g. while the keyring is locked.
+#[tauri::command]
+pub fn set_encrypt_local_data(enabled: bool, app: AppHandle) -> Result<(), String> {
+    let previous = crate::local_crypto::is_enabled();
+    crate::local_crypto::set_enabled(enabled);
+    let switched = reseal_local_data(&app).and_then(|_| {
+        update_config(&app, |config| {
+            config.encrypt_local_data = enabled;
+            Ok(())
+        })
+    });
+    if let Err(e) = switched {
+        crate::local_crypto::set_enabled(previous);
+        // Put back whatever was already rewritten, so the files match the saved setting
+        let _ = reseal_local_data(&app);
+        return Err(format!("Couldn't {} local data: {}", if enabled { "encrypt" } else { "decrypt" }, e));
+    }
+    Ok(())
+}
+
+// Rewrite every store holding note text under the current encryption setting
+fn reseal_local_data(app: &AppHandle) -> Result<(), String> {
+    crate::queue::reseal()
+        .and_then(|_| crate::scratch::reseal())
+        .and_then(|_| crate::setup_captures::reseal(app))
+        .and_then(|_| crate::receipts::reseal())
+}
+
+// Toggle screen reader mode. The note window title is set when the window is
+// built, so the hidden note window is closed and rebuilt on next show.
+#[tauri::command]
+pub fn set_screen_reader_mode(enabled: bool, app: AppHandle) -> Result<(), String> {
+    update_config(&app, |config| {
+        config.screen_reader_mode = enabled;
+        Ok(())
+    })?;
+    
+    if let Some(window) = app.get_window("main") {
+        if !window.is_visible().unwrap_or(false) {
+            window.close().map_err(|e| e.to_string())?;
+        }
+    }
+    crate::accessibility::refresh_prefs(&app);
+    Ok(())
+}
+
+// Re-run every field validation on a whole config, e.g. after a change by key path
+pub fn validate_config(config: &mut AppConfig) -> Result<(), AppError> {
+    config.hotkey_excluded_apps = validate_excluded_apps(std::mem::take(&mut config.hotkey_excluded_apps))?;
+    config.clipboard_hotkey = validate_clipboard_hotkey(config.clipboard_hotkey.take())?;
+    config.capture_profiles = validate_profiles(std::mem::take(&mut config.capture_profiles), config.clipboard_hotkey.as_deref())?;
+    config.abbreviations = validate_abbreviations(std::mem::take(&mut config.abbreviations))?;
+    for (name, value) in &config.extra_headers {
+        crate::notion::parse_extra_header(name, value).map_err(AppError::ValidationError)?;
+    }
+    if let Some(url) = &config.mirror_webhook_url {
+        crate::webhook::validate_webhook_url(url, config.allow_insecure_webhook)?;
+    }
+    config.timestamp_timezone = crate::timestamps::validate_timezone(config.timestamp_timezone.take())?;
+    config.anchor_block_id = config.anchor_block_id.take().map(|id| id.trim().to_string()).filter(|id| !id.is_empty());
+    crate::daily_pages::validate_title_format(&config.daily_title_format)?;
+    if config.target_mode == TargetMode::Daily && config.daily_parent_page_id.trim().is_empty() {
+        return Err(AppError::ValidationError("Choose a parent page for daily pages".into()));
+    }
+    if config.sandbox_mode && config.sandbox_page_id.is_none() {
+        return Err(AppError::ValidationError("Create a sandbox page before turning sandbox mode on".into()));
+    }
+    Ok(())
+}
+
+// Bring in-memory state in line with the stored config after it changed wholesale
+pub fn apply_runtime_config(app: &AppHandle) {
+    let config = app.state::<AppState>().config.lock().unwrap().clone();
+    if let Err(e) = crate::notion::set_extra_headers(&config.extra_headers) {
+        eprintln!("Ignoring configured extra headers: {}", e);
+    }
+    crate::clock::set_correct_clock_skew(config.correct_clock_skew);
+    crate::rate_limit::RATE_LIMITER.set_max_in_flight(config.max_concurrent_requests);
+    crate::dry_run::set_from_settings(config.dry_run);
+    crate::quiet_hours::set_schedule(&config.quiet_hours);
+    crate::receipts::set_history_options(config.note_history, config.note_history_max_entries);
+    crate::refresh_tray(app);
+    crate::register_profile_hotkeys(app);
+    crate::register_clipboard_hotkey(app);
+    crate::accessibility::refresh_prefs(app);
+}
+
+// Generic list editing for list-shaped config, so each new list doesn't need its own commands
+#[derive(Deserialize, Debug)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum CollectionOp {
+    Add { item: Value },
+    Update { index: usize, item: Value },
+    Remove { index: usize },
+    Reorder { from: usize, to: usize },
+}
+
+fn unknown_collection(name: &str) -> AppError {
+    AppError::ValidationError(format!("Unknown config collection '{}'", name))
+}
+
+fn invalid_items(name: &str, e: serde_json::Error) -> AppError {
+    AppError::ValidationError(format!("Invalid item for collection '{}': {}", name, e))
+}
+
+// Read a collection as a list of JSON items
+fn read_collection(config: &AppConfig, name: &str) -> Result<Vec<Value>, AppError> {
+    let items = match name {
+        "hotkey_excluded_apps" => serde_json::to_value(&config.hotkey_excluded_apps),
+        "capture_profiles" => serde_json::to_value(&config.capture_profiles),
+        "abbreviations" => serde_json::to_value(&config.abbreviations),
+        _ => return Err(unknown_collection(name)),
+    };
+    let items = items.map_err(|e| AppError::UnknownError(e.to_string()))?;
+    Ok(items.as_array().cloned().unwrap_or_default())
+}
+
+// Validate a list of JSON items with the collection's own rules and store it
+fn write_collection(config: &mut AppConfig, name: &str, items: Vec<Value>) -> Result<(), AppError> {
+    match name {
+        "hotkey_excluded_apps" => {
+            let apps: Vec<String> = serde_json::from_value(Value::Array(items))
+                .map_err(|e| invalid_items(name, e))?;
+            config.hotkey_excluded_apps = validate_excluded_apps(apps)?;
+        }
+        "capture_profiles" => {
+            let profiles: Vec<CaptureProfile> = serde_json::from_value(Value::Array(items))
+                .map_err(|e| invalid_items(name, e))?;
+            config.capture_profiles = validate_profiles(profiles, config.clipboard_hotkey.as_deref())?;
+        }
+        "abbreviations" => {
+            let abbreviations: Vec<Abbreviation> = serde_json::from_value(Value::Array(items))
+                .map_err(|e| invalid_items(name, e))?;
+            config.abbreviations = validate_abbreviations(abbreviations)?;
+        }
+        _ => return Err(unknown_collection(name)),
+    }
+    Ok(())
+}
+
+// Apply an operation to a list, checking indices
+fn apply_collection_op(name: &str, items: &mut Vec<Value>, op: CollectionOp) -> Result<(), AppError> {
+    let out_of_range = |index: usize| {
+        AppError::ValidationError(format!("Index {} is out of range for collection '{}'", index, name))
+    };
+
+    match op {
+        CollectionOp::Add { item } => items.push(item),
+        CollectionOp::Update { index, item } => {
+            *items.get_mut(index).ok_or_else(|| out_of_range(index))? = item;
+        }
+        CollectionOp::Remove { index } => {
+            if index >= items.len() {
+                return Err(out_of_range(index));
+            }
+            items.remove(index);
+        }
+        CollectionOp::Reorder { from, to } => {
+            if from >= items.len() {
+                return Err(out_of_range(from));
+            }
+            if to >= items.len() {
+                return Err(out_of_range(to));
+            }
+            let item = items.remove(from);
+            items.insert(to, item);
+        }
+    }
+    Ok(())
+}
+
+// Apply an operation to a named collection and return the stored result
+fn mutate_collection(config: &mut AppConfig, name: &str, op: CollectionOp) -> Result<Vec<Value>, AppError> {
+    let mut items = read_collection(config, name)?;
+    apply_collection_op(name, &mut items, op)?;
+    write_collection(config, name, items)?;
+    read_collection(config, name)
+}
+
+#[tauri::command]
+pub fn get_config_collection(name: String, state: State<'_, AppState>) -> Result<Vec<Value>, String> {
+    let config = state.config.lock().unwrap();
+    read_collection(&config, &name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn mutate_config_collection(name: String, op: CollectionOp, app: AppHandle) -> Result<Vec<Value>, String> {
+    let mut result = Vec::new();
+    update_config(&app, |config| {
+        result = mutate_collection(config, &name, op)?;
+        Ok(())
+    })?;
+    
+    if name == "capture_profiles" {
+        crate::register_profile_hotkeys(&app);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn profile(name: &str, hotkey: Option<&str>) -> CaptureProfile {
+        CaptureProfile {
+            name: name.to_string(),
+            page_id: format!("{}-page", name),
+            page_title: name.to_string(),
+            capture_hotkey: hotkey.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    fn conflict(profiles: Vec<CaptureProfile>, clipboard_hotkey: Option<&str>) -> String {
+        validate_profiles(profiles, clipboard_hotkey).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn accelerators_compare_regardless_of_modifier_order_and_case() {
+        assert_eq!(normalize_accelerator("Ctrl+Alt+1"), normalize_accelerator("alt + CTRL + 1"));
+        assert_ne!(normalize_accelerator("Ctrl+Alt+1"), normalize_accelerator("Ctrl+Alt+2"));
+        assert_ne!(normalize_accelerator("Ctrl+1"), normalize_accelerator("Alt+1"));
+    }
+
+    #[test]
+    fn distinct_profile_hotkeys_are_accepted() {
+        let profiles = validate_profiles(
+            vec![profile(" Work ", Some(" Ctrl+Alt+1 ")), profile("Home", Some("Ctrl+Alt+2")), profile("Later", None)],
+            Some("Alt+Shift+Q"),
+        )
+        .unwrap();
+        assert_eq!(profiles[0].name, "Work");
+        assert_eq!(profiles[0].capture_hotkey.as_deref(), Some("Ctrl+Alt+1"));
+        assert_eq!(profiles[2].capture_hotkey, None);
+    }
+
+    #[test]
+    fn hotkey_conflicts_are_detected_at_save_time() {
+        let error = conflict(vec![profile("Work", Some("Ctrl+Alt+1")), profile("Home", Some("alt+ctrl+1"))], None);
+        assert!(error.contains("'Home'") && error.contains("profile 'Work'"), "{}", error);
+
+        let error = conflict(vec![profile("Work", Some("alt+q"))], None);
+        assert!(error.contains("the main capture hotkey"), "{}", error);
+
+        let error = conflict(vec![profile("Work", Some("Shift+Alt+Q"))], Some("Alt+Shift+Q"));
+        assert!(error.contains("the clipboard hotkey"), "{}", error);
+    }
+
+    #[test]
+    fn blank_hotkeys_mean_none() {
+        let profiles = validate_profiles(vec![profile("Work", Some("  ")), profile("Home", Some(""))], None).unwrap();
+        assert!(profiles.iter().all(|profile| profile.capture_hotkey.is_none()));
+    }
+
+    #[test]
+    fn profiles_need_unique_names_and_a_page() {
+        assert!(conflict(vec![profile("Work", None), profile("work", None)], None).contains("Duplicate"));
+        assert!(conflict(vec![profile(" ", None)], None).contains("need a name"));
+        let mut no_page = profile("Work", None);
+        no_page.page_id.clear();
+        assert!(conflict(vec![no_page], None).contains("has no page"));
+    }
+
+    fn add(item: Value) -> CollectionOp {
+        CollectionOp::Add { item }
+    }
+
+    // Runs an op the way the command does: on a copy, kept only when it succeeds
+    fn mutate(config: &mut AppConfig, name: &str, op: CollectionOp) -> Result<Vec<Value>, String> {
+        let mut updated = config.clone();
+        let items = mutate_collection(&mut updated, name, op).map_err(|e| e.to_string())?;
+        *config = updated;
+        Ok(items)
+    }
+
+    #[test]
+    fn excluded_apps_support_every_op() {
+        let mut config = AppConfig::default();
+        let name = "hotkey_excluded_apps";
+        mutate(&mut config, name, add(json!(" code.exe "))).unwrap();
+        mutate(&mut config, name, add(json!("bundle:com.apple.Terminal"))).unwrap();
+        assert_eq!(mutate(&mut config, name, add(json!("Code.EXE"))).unwrap(), [json!("code.exe"), json!("bundle:com.apple.Terminal")]);
+
+        let items = mutate(&mut config, name, CollectionOp::Update { index: 0, item: json!("slack.exe") }).unwrap();
+        assert_eq!(items, [json!("slack.exe"), json!("bundle:com.apple.Terminal")]);
+        let items = mutate(&mut config, name, CollectionOp::Reorder { from: 1, to: 0 }).unwrap();
+        assert_eq!(items, [json!("bundle:com.apple.Terminal"), json!("slack.exe")]);
+        let items = mutate(&mut config, name, CollectionOp::Remove { index: 0 }).unwrap();
+        assert_eq!(items, [json!("slack.exe")]);
+        assert_eq!(config.hotkey_excluded_apps, ["slack.exe"]);
+        assert_eq!(read_collection(&config, name).unwrap(), items);
+
+        let error = mutate(&mut config, name, add(json!("bundle:"))).unwrap_err();
+        assert!(error.contains("need a bundle id"), "{}", error);
+        let error = mutate(&mut config, name, add(json!(42))).unwrap_err();
+        assert!(error.contains("Invalid item for collection 'hotkey_excluded_apps'"), "{}", error);
+        assert_eq!(config.hotkey_excluded_apps, ["slack.exe"]);
+    }
+
+    #[test]
+    fn capture_profiles_support_every_op() {
+        let mut config = AppConfig::default();
+        let name = "capture_profiles";
+        let work = serde_json::to_value(profile("Work", Some("Ctrl+Alt+1"))).unwrap();
+        let home = serde_json::to_value(profile("Home", None)).unwrap();
+        mutate(&mut config, name, add(work)).unwrap();
+        let items = mutate(&mut config, name, add(home)).unwrap();
+        assert_eq!(items.len(), 2);
+
+        let renamed = serde_json::to_value(profile(" Later ", None)).unwrap();
+        let items = mutate(&mut config, name, CollectionOp::Update { index: 1, item: renamed }).unwrap();
+        assert_eq!(items[1]["name"], "Later");
+        mutate(&mut config, name, CollectionOp::Reorder { from: 0, to: 1 }).unwrap();
+        assert_eq!(config.capture_profiles.iter().map(|profile| profile.name.as_str()).collect::<Vec<_>>(), ["Later", "Work"]);
+        mutate(&mut config, name, CollectionOp::Remove { index: 0 }).unwrap();
+        assert_eq!(config.capture_profiles.len(), 1);
+
+        // The profile validator runs on the whole list, against the clipboard hotkey too
+        let clash = serde_json::to_value(profile("Home", Some("alt+ctrl+1"))).unwrap();
+        let error = mutate(&mut config, name, add(clash)).unwrap_err();
+        assert!(error.contains("profile 'Work'"), "{}", error);
+        config.clipboard_hotkey = Some("Alt+Shift+V".into());
+        let clash = serde_json::to_value(profile("Home", Some("Shift+Alt+V"))).unwrap();
+        let error = mutate(&mut config, name, add(clash)).unwrap_err();
+        assert!(error.contains("the clipboard hotkey"), "{}", error);
+        let duplicate = serde_json::to_value(profile("work", None)).unwrap();
+        assert!(mutate(&mut config, name, add(duplicate)).unwrap_err().contains("Duplicate"));
+        assert_eq!(config.capture_profiles.len(), 1);
+    }
+
+    #[test]
+    fn abbreviations_support_every_op() {
+        let mut config = AppConfig::default();
+        let name = "abbreviations";
+        mutate(&mut config, name, add(json!({ "abbreviation": " mtg ", "expansion": "meeting" }))).unwrap();
+        mutate(&mut config, name, add(json!({ "abbreviation": "b/c", "expansion": "because" }))).unwrap();
+        let items = mutate(&mut config, name, CollectionOp::Update { index: 0, item: json!({ "abbreviation": "mtgs", "expansion": "meetings" }) }).unwrap();
+        assert_eq!(items[0], json!({ "abbreviation": "mtgs", "expansion": "meetings" }));
+        let items = mutate(&mut config, name, CollectionOp::Reorder { from: 0, to: 1 }).unwrap();
+        assert_eq!(items[0]["abbreviation"], "b/c");
+        let items = mutate(&mut config, name, CollectionOp::Remove { index: 1 }).unwrap();
+        assert_eq!(items, [json!({ "abbreviation": "b/c", "expansion": "because" })]);
+
+        let error = mutate(&mut config, name, add(json!({ "abbreviation": "B/C", "expansion": "x" }))).unwrap_err();
+        assert!(error.contains("Duplicate abbreviation"), "{}", error);
+        let error = mutate(&mut config, name, add(json!({ "abbreviation": "a b", "expansion": "x" }))).unwrap_err();
+        assert!(error.contains("can't contain spaces"), "{}", error);
+        let error = mutate(&mut config, name, add(json!("asap"))).unwrap_err();
+        assert!(error.contains("Invalid item for collection 'abbreviations'"), "{}", error);
+        assert_eq!(config.abbreviations.len(), 1);
+    }
+
+    #[test]
+    fn unknown_collections_and_bad_indices_name_the_collection() {
+        let mut config = AppConfig::default();
+        let error = mutate(&mut config, "routes", add(json!("x"))).unwrap_err();
+        assert!(error.contains("Unknown config collection 'routes'"), "{}", error);
+        assert!(read_collection(&config, "routes").is_err());
+
+        mutate(&mut config, "hotkey_excluded_apps", add(json!("code.exe"))).unwrap();
+        let ops = [
+            CollectionOp::Update { index: 1, item: json!("slack.exe") },
+            CollectionOp::Remove { index: 1 },
+            CollectionOp::Reorder { from: 0, to: 1 },
+            CollectionOp::Reorder { from: 3, to: 0 },
+        ];
+        for op in ops {
+            let error = mutate(&mut config, "hotkey_excluded_apps", op).unwrap_err();
+            assert!(error.contains("out of range for collection 'hotkey_excluded_apps'"), "{}", error);
+        }
+        assert_eq!(config.hotkey_excluded_apps, ["code.exe"]);
+
+        // Ops arrive from the frontend tagged by name
+        let op: CollectionOp = serde_json::from_value(json!({ "op": "reorder", "from": 0, "to": 0 })).unwrap();
+        assert!(matches!(op, CollectionOp::Reorder { from: 0, to: 0 }));
+    }
+}
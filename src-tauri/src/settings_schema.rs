@@ -0,0 +1,478 @@
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, State};
+
+use crate::config::{AppConfig, AppState};
+use crate::error::AppError;
+
+// Every config field by key path, for a command palette and scripted
+// configuration. The registry below is maintained by hand next to AppConfig;
+// `unregistered_paths` reports fields that were added without an entry.
+// Paths use dots, with `*` matching any list index or map key:
+// "capture_profiles.0.page_id", "extra_headers.X-Proxy-Auth".
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SettingKind {
+    Bool,
+    Integer { min: i64, max: i64 },
+    Number { min: f64, max: f64 },
+    String,
+    // A string or null
+    OptionalString,
+    Enum { values: &'static [&'static str] },
+    StringList,
+    // Items are addressed as "<path>.<index>"
+    List,
+    // Entries are addressed as "<path>.<key>"
+    Map,
+    Object,
+}
+
+struct SettingSpec {
+    path: &'static str,
+    kind: SettingKind,
+    // Shown masked and never echoed back
+    secret: bool,
+    // Managed by a dedicated command or by the app itself
+    read_only: bool,
+}
+
+const fn spec(path: &'static str, kind: SettingKind) -> SettingSpec {
+    SettingSpec { path, kind, secret: false, read_only: false }
+}
+
+const fn secret(path: &'static str, kind: SettingKind) -> SettingSpec {
+    SettingSpec { path, kind, secret: true, read_only: false }
+}
+
+const fn read_only(path: &'static str, kind: SettingKind) -> SettingSpec {
+    SettingSpec { path, kind, secret: false, read_only: true }
+}
+
+const fn int(min: i64, max: i64) -> SettingKind {
+    SettingKind::Integer { min, max }
+}
+
+use SettingKind::{Bool, List, Map, Object, OptionalString, StringList};
+
+const SETTINGS: &[SettingSpec] = &[
+    // Secrets themselves aren't part of the config, only where they are stored.
+    // The main token is set through set_notion_api_token, which verifies it first.
+    read_only("token_ref", OptionalString),
+    read_only("selected_page_id", SettingKind::String),
+    read_only("selected_page_title", SettingKind::String),
+    read_only("selected_page_icon", OptionalString),
+    read_only("selected_page_stale", Bool),
+    read_only("selected_target_kind", SettingKind::Enum { values: &["page", "database"] }),
+    spec("page_search_max_results", int(1, 10_000)),
+    spec("note_history", Bool),
+    spec("note_history_max_entries", int(10, 100_000)),
+    spec("clipboard_hotkey", OptionalString),
+    spec("undo_window_minutes", int(1, 60)),
+    spec("preload_note_window", Bool),
+    spec("clipboard_soft_limit_chars", int(1, 10_000_000)),
+    spec("clipboard_hard_limit_chars", int(1, 10_000_000)),
+    spec("clipboard_structured_min_lines", int(1, 10_000)),
+    spec("clipboard_prose_ratio_threshold", SettingKind::Number { min: 0.0, max: 1.0 }),
+    spec("hotkey_excluded_apps", StringList),
+    spec("capture_profiles", List),
+    spec("capture_profiles.*", Object),
+    spec("capture_profiles.*.name", SettingKind::String),
+    // Set through set_profile_token
+    read_only("capture_profiles.*.token_ref", OptionalString),
+    spec("capture_profiles.*.page_id", SettingKind::String),
+    spec("capture_profiles.*.page_title", SettingKind::String),
+    spec("capture_profiles.*.capture_hotkey", OptionalString),
+    spec("extra_headers", Map),
+    // Proxy headers often carry credentials
+    secret("extra_headers.*", SettingKind::String),
+    spec("scratch_prefix", SettingKind::String),
+    spec("scratch_flush_time", OptionalString),
+    spec("correct_clock_skew", Bool),
+    spec("auto_rotate_pages", Bool),
+    spec("text_direction", SettingKind::Enum { values: &["auto", "ltr", "rtl"] }),
+    spec("rtl_timestamp_layout", SettingKind::Enum { values: &["suffix", "separate_block"] }),
+    spec("split_paragraphs", Bool),
+    spec("note_block_type", SettingKind::Enum { values: &["paragraph", "callout", "toggle"] }),
+    spec("toggle_preview", Bool),
+    spec("note_bold", Bool),
+    spec("note_color", SettingKind::Enum { values: crate::blocks::NOTION_COLORS }),
+    spec("highlight_hashtags", Bool),
+    spec("hashtag_color", SettingKind::Enum { values: crate::blocks::NOTION_COLORS }),
+    spec("timestamp_12_hour", Bool),
+    spec("timestamp_locale", OptionalString),
+    spec("timestamp_timezone", OptionalString),
+    spec("day_headings", Bool),
+    spec("anchor_block_id", OptionalString),
+    spec("target_mode", SettingKind::Enum { values: &["fixed", "daily"] }),
+    spec("daily_parent_page_id", SettingKind::String),
+    spec("daily_title_format", SettingKind::String),
+    spec("abbreviations", List),
+    spec("abbreviations.*", Object),
+    spec("abbreviations.*.abbreviation", SettingKind::String),
+    spec("abbreviations.*.expansion", SettingKind::String),
+    spec("window_effects", SettingKind::Enum { values: &["none", "acrylic", "vibrancy"] }),
+    spec("screen_reader_mode", Bool),
+    spec("leading_timestamps", Bool),
+    spec("allow_raw_blocks", Bool),
+    read_only("token_invalid", Bool),
+    spec("verification_interval_hours", int(0, 24 * 7)),
+    spec("focus_end_notification", Bool),
+    spec("normalize_typography", Bool),
+    spec("typography", Object),
+    spec("typography.straighten_quotes", Bool),
+    spec("typography.normalize_dashes", Bool),
+    spec("typography.strip_trailing_whitespace", Bool),
+    spec("typography.collapse_spaces", Bool),
+    spec("max_concurrent_requests", int(1, 16)),
+    spec("mirror_webhook_url", OptionalString),
+    // Set through set_mirror_webhook
+    read_only("mirror_webhook_secret_ref", OptionalString),
+    spec("allow_insecure_webhook", Bool),
+    read_only("sandbox_page_id", OptionalString),
+    spec("sandbox_mode", Bool),
+    // Set through set_encrypt_local_data, which migrates the local files
+    read_only("encrypt_local_data", Bool),
+    spec("dry_run", Bool),
+    spec("spellcheck_enabled", Bool),
+    spec("spellcheck_languages", StringList),
+    spec("weekly_digest_enabled", Bool),
+    spec(
+        "weekly_digest_day",
+        SettingKind::Enum { values: &["monday", "tuesday", "wednesday", "thursday", "friday", "saturday", "sunday"] },
+    ),
+    spec("weekly_digest_time", SettingKind::String),
+    spec("quiet_hours", Object),
+    spec("quiet_hours.enabled", Bool),
+    spec("quiet_hours.start", SettingKind::String),
+    spec("quiet_hours.end", SettingKind::String),
+    spec("quiet_hours.days", StringList),
+];
+
+const MASK: &str = "********";
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SettingEntry {
+    pub path: String,
+    pub kind: SettingKind,
+    // Secrets are masked; null secrets stay null so "not set" is still visible
+    pub value: Value,
+    // Key for the setting's description in the frontend's strings
+    pub description_key: String,
+    pub secret: bool,
+    pub read_only: bool,
+}
+
+fn matches_pattern(pattern: &str, path: &str) -> bool {
+    let mut pattern_parts = pattern.split('.');
+    let mut path_parts = path.split('.');
+    loop {
+        match (pattern_parts.next(), path_parts.next()) {
+            (None, None) => return true,
+            (Some(expected), Some(actual)) if expected == "*" || expected == actual => {}
+            _ => return false,
+        }
+    }
+}
+
+fn find_spec(path: &str) -> Option<&'static SettingSpec> {
+    SETTINGS.iter().find(|spec| matches_pattern(spec.path, path))
+}
+
+fn join(parent: &str, child: &str) -> String {
+    if parent.is_empty() {
+        child.to_string()
+    } else {
+        format!("{}.{}", parent, child)
+    }
+}
+
+// Children of a container node, as (path segment, value)
+fn children(value: &Value) -> Vec<(String, &Value)> {
+    match value {
+        Value::Object(map) => map.iter().map(|(key, value)| (key.clone(), value)).collect(),
+        Value::Array(items) => items.iter().enumerate().map(|(index, value)| (index.to_string(), value)).collect(),
+        _ => Vec::new(),
+    }
+}
+
+// The value at `path` with every non-null secret in it masked, including
+// secrets nested inside a list or map
+fn masked(path: &str, value: &Value) -> Value {
+    if find_spec(path).is_some_and(|spec| spec.secret) && !value.is_null() {
+        return Value::String(MASK.into());
+    }
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter().map(|(key, child)| (key.clone(), masked(&join(path, key), child))).collect(),
+        ),
+        Value::Array(items) => Value::Array(
+            items.iter().enumerate().map(|(index, child)| masked(&join(path, &index.to_string()), child)).collect(),
+        ),
+        _ => value.clone(),
+    }
+}
+
+fn walk(path: &str, value: &Value, entries: &mut Vec<SettingEntry>, unregistered: &mut Vec<String>) {
+    for (segment, child) in children(value) {
+        let child_path = join(path, &segment);
+        let Some(spec) = find_spec(&child_path) else {
+            unregistered.push(child_path);
+            continue;
+        };
+        entries.push(SettingEntry {
+            path: child_path.clone(),
+            kind: spec.kind,
+            value: masked(&child_path, child),
+            description_key: format!("settings.{}", spec.path),
+            secret: spec.secret,
+            read_only: spec.read_only,
+        });
+        if matches!(spec.kind, List | Map | Object) {
+            walk(&child_path, child, entries, unregistered);
+        }
+    }
+}
+
+fn config_value(config: &AppConfig) -> Result<Value, AppError> {
+    let value = serde_json::to_value(config).map_err(|e| AppError::UnknownError(e.to_string()))?;
+    config.secrets.debug_assert_absent(&value.to_string());
+    Ok(value)
+}
+
+// Schema entries for the current config, plus any fields missing from the registry
+pub fn schema(config: &AppConfig) -> Result<(Vec<SettingEntry>, Vec<String>), AppError> {
+    let mut entries = Vec::new();
+    let mut unregistered = Vec::new();
+    walk("", &config_value(config)?, &mut entries, &mut unregistered);
+    Ok((entries, unregistered))
+}
+
+// Config fields without a registry entry; should always be empty
+pub fn unregistered_paths(config: &AppConfig) -> Vec<String> {
+    schema(config).map(|(_, unregistered)| unregistered).unwrap_or_default()
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "a list",
+        Value::Object(_) => "an object",
+    }
+}
+
+// Check a value against the setting's kind and range
+fn check_value(path: &str, kind: SettingKind, value: &Value) -> Result<(), AppError> {
+    let mismatch = |expected: &str| {
+        AppError::ValidationError(format!("'{}' expects {}, got {}", path, expected, type_name(value)))
+    };
+    match kind {
+        Bool if !value.is_boolean() => Err(mismatch("a boolean")),
+        SettingKind::Integer { min, max } => {
+            let number = value.as_i64().ok_or_else(|| mismatch("a whole number"))?;
+            if !(min..=max).contains(&number) {
+                return Err(AppError::ValidationError(format!(
+                    "'{}' must be between {} and {}, got {}",
+                    path, min, max, number
+                )));
+            }
+            Ok(())
+        }
+        SettingKind::Number { min, max } => {
+            let number = value.as_f64().ok_or_else(|| mismatch("a number"))?;
+            if !(min..=max).contains(&number) {
+                return Err(AppError::ValidationError(format!(
+                    "'{}' must be between {} and {}, got {}",
+                    path, min, max, number
+                )));
+            }
+            Ok(())
+        }
+        SettingKind::String if !value.is_string() => Err(mismatch("a string")),
+        OptionalString if !value.is_string() && !value.is_null() => Err(mismatch("a string or null")),
+        SettingKind::Enum { values } => {
+            let choice = value.as_str().ok_or_else(|| mismatch("a string"))?;
+            if !values.contains(&choice) {
+                return Err(AppError::ValidationError(format!(
+                    "'{}' must be one of {}, got '{}'",
+                    path,
+                    values.join(", "),
+                    choice
+                )));
+            }
+            Ok(())
+        }
+        StringList if !value.as_array().is_some_and(|items| items.iter().all(Value::is_string)) => {
+            Err(mismatch("a list of strings"))
+        }
+        List if !value.is_array() => Err(mismatch("a list")),
+        Map | Object if !value.is_object() => Err(mismatch("an object")),
+        _ => Ok(()),
+    }
+}
+
+// Replace the node at `path`; list indices must exist, map keys may be new
+fn set_at_path(root: &mut Value, path: &str, value: Value) -> Result<(), AppError> {
+    let segments: Vec<&str> = path.split('.').collect();
+    let (last, parents) = segments.split_last().ok_or_else(|| unknown_path(path))?;
+    let mut node = root;
+    for segment in parents {
+        node = match node {
+            Value::Object(map) => map.get_mut(*segment),
+            Value::Array(items) => segment.parse::<usize>().ok().and_then(|index| items.get_mut(index)),
+            _ => None,
+        }
+        .ok_or_else(|| unknown_path(path))?;
+    }
+    match node {
+        Value::Object(map) => {
+            map.insert(last.to_string(), value);
+            Ok(())
+        }
+        Value::Array(items) => {
+            let slot = last.parse::<usize>().ok().and_then(|index| items.get_mut(index)).ok_or_else(|| {
+                AppError::ValidationError(format!("'{}' is past the end of the list", path))
+            })?;
+            *slot = value;
+            Ok(())
+        }
+        _ => Err(unknown_path(path)),
+    }
+}
+
+fn unknown_path(path: &str) -> AppError {
+    AppError::ValidationError(format!("Unknown setting '{}'", path))
+}
+
+// Apply one setting to a config: type and range checks, then the same
+// validation the dedicated commands use for the collection it belongs to
+pub fn apply_setting(config: &mut AppConfig, path: &str, value: Value) -> Result<(), AppError> {
+    let spec = find_spec(path).ok_or_else(|| unknown_path(path))?;
+    if spec.read_only {
+        return Err(AppError::ValidationError(format!("'{}' can't be set by path", path)));
+    }
+    check_value(path, spec.kind, &value)?;
+
+    let mut tree = config_value(config)?;
+    set_at_path(&mut tree, path, value)?;
+    let mut updated: AppConfig = serde_json::from_value(tree)
+        .map_err(|e| AppError::ValidationError(format!("Invalid value for '{}': {}", path, e)))?;
+    // Secrets don't round-trip through JSON
+    updated.secrets = config.secrets.clone();
+    crate::settings::validate_config(&mut updated)?;
+    *config = updated;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_settings_schema(state: State<'_, AppState>) -> Result<Vec<SettingEntry>, String> {
+    let config = state.config.lock().unwrap();
+    let (entries, unregistered) = schema(&config).map_err(|e| e.to_string())?;
+    if !unregistered.is_empty() {
+        eprintln!("Settings missing from the schema registry: {}", unregistered.join(", "));
+    }
+    Ok(entries)
+}
+
+// Set a single setting; `value_json` is the JSON encoding of the new value
+#[tauri::command]
+pub fn set_setting_by_path(path: String, value_json: String, app: AppHandle) -> Result<(), String> {
+    let value: Value = serde_json::from_str(&value_json)
+        .map_err(|e| AppError::ValidationError(format!("Value for '{}' isn't valid JSON: {}", path, e)).to_string())?;
+    crate::settings::update_config(&app, |config| apply_setting(config, &path, value))?;
+    crate::settings::apply_runtime_config(&app);
+    crate::refresh_tray(&app);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abbreviations::Abbreviation;
+    use crate::config::CaptureProfile;
+
+    // Every collection holds an entry, so wildcard paths have something to match
+    fn populated_config() -> AppConfig {
+        let mut config = AppConfig {
+            selected_page_id: "page-1".into(),
+            selected_page_title: "Inbox".into(),
+            capture_profiles: vec![CaptureProfile {
+                name: "work".into(),
+                page_id: "work-page".into(),
+                page_title: "Work".into(),
+                ..Default::default()
+            }],
+            abbreviations: vec![Abbreviation { abbreviation: "mtg".into(), expansion: "meeting".into() }],
+            daily_parent_page_id: "daily-parent".into(),
+            ..Default::default()
+        };
+        config.extra_headers.insert("X-Proxy-Auth".into(), "proxy-secret".into());
+        config
+    }
+
+    #[test]
+    fn every_config_field_is_registered() {
+        for config in [AppConfig::default(), populated_config()] {
+            assert_eq!(unregistered_paths(&config), Vec::<String>::new());
+        }
+    }
+
+    #[test]
+    fn every_registered_path_is_a_config_field() {
+        let (entries, _) = schema(&populated_config()).unwrap();
+        for spec in SETTINGS {
+            assert!(
+                entries.iter().any(|entry| matches_pattern(spec.path, &entry.path)),
+                "'{}' is registered but AppConfig has no such field",
+                spec.path
+            );
+        }
+    }
+
+    #[test]
+    fn registered_kinds_match_the_serialized_values() {
+        let (entries, _) = schema(&populated_config()).unwrap();
+        for entry in entries.iter().filter(|entry| !entry.secret) {
+            check_value(&entry.path, entry.kind, &entry.value).unwrap_or_else(|e| panic!("{}: {}", entry.path, e));
+        }
+    }
+
+    #[test]
+    fn every_listed_choice_deserializes_into_the_config() {
+        for spec in SETTINGS.iter().filter(|spec| !spec.read_only) {
+            let SettingKind::Enum { values } = spec.kind else {
+                continue;
+            };
+            for choice in values {
+                let mut config = populated_config();
+                apply_setting(&mut config, spec.path, Value::from(*choice))
+                    .unwrap_or_else(|e| panic!("{} = {}: {}", spec.path, choice, e));
+                let stored = config_value(&config).unwrap();
+                assert_eq!(stored.pointer(&format!("/{}", spec.path.replace('.', "/"))), Some(&Value::from(*choice)));
+            }
+        }
+    }
+
+    #[test]
+    fn secrets_are_masked_and_read_only_paths_refused() {
+        let (entries, _) = schema(&populated_config()).unwrap();
+        let header = entries.iter().find(|entry| entry.path == "extra_headers.X-Proxy-Auth").unwrap();
+        assert_eq!(header.value, Value::String(MASK.into()));
+        assert!(header.secret);
+        // Nor does the secret show through the map holding it
+        assert!(!serde_json::to_string(&entries).unwrap().contains("proxy-secret"));
+
+        let mut config = populated_config();
+        let error = apply_setting(&mut config, "encrypt_local_data", Value::Bool(true)).unwrap_err();
+        assert!(error.to_string().contains("can't be set by path"), "{}", error);
+        assert!(apply_setting(&mut config, "no_such_setting", Value::Bool(true)).is_err());
+        assert!(apply_setting(&mut config, "undo_window_minutes", Value::from(0)).is_err());
+
+        apply_setting(&mut config, "capture_profiles.0.page_title", Value::from("Work notes")).unwrap();
+        assert_eq!(config.capture_profiles[0].page_title, "Work notes");
+    }
+}
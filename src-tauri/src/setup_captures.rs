@@ -0,0 +1,321 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+
+use crate::blocks::NoteStyle;
+use crate::config::{app_data_path, AppState};
+use crate::local_crypto;
+use crate::queue;
+
+// Captures that arrived before the app was set up. They wait in AppState and
+// are sent, with their original capture time, once settings validate. Only
+// captures from outside the app (URI, command line) are written to disk; a
+// clipboard or hotkey capture the user can simply repeat.
+const PENDING_SETUP_FILE: &str = "pending_setup.json";
+
+// Held at most; further captures are refused until setup is finished
+pub const MAX_PENDING_SETUP_CAPTURES: usize = 5;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureOrigin {
+    Hotkey,
+    Clipboard,
+    Uri,
+    Cli,
+}
+
+impl CaptureOrigin {
+    fn persists(self) -> bool {
+        matches!(self, CaptureOrigin::Uri | CaptureOrigin::Cli)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PendingSetupCapture {
+    pub id: String,
+    pub note_text: String,
+    pub profile: Option<String>,
+    // RFC3339; a leading timestamp in the note still outranks it
+    pub captured_at: String,
+    pub origin: CaptureOrigin,
+}
+
+// Text that can't be decrypted yet (locked keyring) stays sealed in memory
+// and is opened again when it is needed
+pub fn load_pending() -> Vec<PendingSetupCapture> {
+    let Ok(path) = app_data_path(PENDING_SETUP_FILE) else {
+        return Vec::new();
+    };
+    let pending: Vec<PendingSetupCapture> = fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default();
+    pending
+        .into_iter()
+        .map(|mut capture| {
+            if let Ok(text) = local_crypto::open(&capture.note_text) {
+                capture.note_text = text;
+            }
+            capture
+        })
+        .collect()
+}
+
+fn save_persistent(pending: &[PendingSetupCapture]) -> Result<(), String> {
+    let persistent = pending
+        .iter()
+        .filter(|capture| capture.origin.persists())
+        .map(|capture| {
+            Ok(PendingSetupCapture {
+                note_text: local_crypto::seal(&capture.note_text)?,
+                ..capture.clone()
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let path = app_data_path(PENDING_SETUP_FILE)?;
+    let contents = serde_json::to_string_pretty(&persistent)
+        .map_err(|e| format!("Failed to serialize pending captures: {}", e))?;
+    fs::write(&path, contents).map_err(|e| format!("Failed to write pending captures: {}", e))
+}
+
+fn modify<T>(
+    pending: &Mutex<Vec<PendingSetupCapture>>,
+    change: impl FnOnce(&mut Vec<PendingSetupCapture>) -> Result<T, String>,
+) -> Result<T, String> {
+    let mut pending = pending.lock().unwrap();
+    let result = change(&mut pending)?;
+    save_persistent(&pending)?;
+    Ok(result)
+}
+
+fn modify_pending<T>(
+    app: &AppHandle,
+    change: impl FnOnce(&mut Vec<PendingSetupCapture>) -> Result<T, String>,
+) -> Result<T, String> {
+    modify(&app.state::<AppState>().pending_setup, change)
+}
+
+// Add a capture to the pending list, refusing once it is full
+pub fn push_bounded(pending: &mut Vec<PendingSetupCapture>, capture: PendingSetupCapture) -> Result<(), String> {
+    if pending.len() >= MAX_PENDING_SETUP_CAPTURES {
+        return Err(format!(
+            "{} notes are already waiting for setup; finish setup before capturing more",
+            pending.len()
+        ));
+    }
+    pending.push(capture);
+    Ok(())
+}
+
+fn stash_in(
+    pending: &Mutex<Vec<PendingSetupCapture>>,
+    note_text: &str,
+    profile: Option<String>,
+    origin: CaptureOrigin,
+    captured_at: DateTime<Local>,
+) -> Result<PendingSetupCapture, String> {
+    let capture = PendingSetupCapture {
+        id: crate::receipts::new_capture_id(),
+        note_text: note_text.to_string(),
+        profile,
+        captured_at: captured_at.to_rfc3339(),
+        origin,
+    };
+    modify(pending, |pending| push_bounded(pending, capture.clone()))?;
+    Ok(capture)
+}
+
+// Hold a capture that arrived before setup was finished
+pub fn stash(
+    app: &AppHandle,
+    note_text: &str,
+    profile: Option<String>,
+    origin: CaptureOrigin,
+    captured_at: DateTime<Local>,
+) -> Result<PendingSetupCapture, String> {
+    stash_in(&app.state::<AppState>().pending_setup, note_text, profile, origin, captured_at)
+}
+
+// Rewrite the file in the current encryption mode
+pub fn reseal(app: &AppHandle) -> Result<(), String> {
+    modify_pending(app, |_| Ok(()))
+}
+
+pub fn pending_count(app: &AppHandle) -> usize {
+    app.state::<AppState>().pending_setup.lock().unwrap().len()
+}
+
+pub fn clear(app: &AppHandle) -> Result<usize, String> {
+    modify_pending(app, |pending| Ok(std::mem::take(pending).len()))
+}
+
+// Setup is done: hand every held capture to the queue, with its original
+// capture time, and flush. Captures that fail to queue stay pending.
+pub fn resume(app: &AppHandle) {
+    let queued = requeue(&app.state::<AppState>().pending_setup, |capture, note_text, captured_at| {
+        queue::enqueue_at(app, note_text, capture.profile.clone(), captured_at, NoteStyle::default()).map(|_| ())
+    });
+    if queued == 0 {
+        return;
+    }
+    let app_handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = queue::flush(&app_handle).await {
+            eprintln!("Failed to flush captures made before setup: {}", e);
+        }
+    });
+    let body = match queued {
+        1 => "The note captured before setup is being sent.".to_string(),
+        count => format!("{} notes captured before setup are being sent.", count),
+    };
+    crate::notify(app, "Setup complete", &body);
+}
+
+// Hand each held capture to `enqueue` and drop the ones it took; returns how many
+fn requeue(
+    pending: &Mutex<Vec<PendingSetupCapture>>,
+    mut enqueue: impl FnMut(&PendingSetupCapture, &str, Option<DateTime<Local>>) -> Result<(), String>,
+) -> usize {
+    let held = pending.lock().unwrap().clone();
+    if held.is_empty() {
+        return 0;
+    }
+
+    let mut queued = Vec::new();
+    for capture in &held {
+        let captured_at = DateTime::parse_from_rfc3339(&capture.captured_at)
+            .ok()
+            .map(|time| time.with_timezone(&Local));
+        let note_text = match local_crypto::open(&capture.note_text) {
+            Ok(text) => text,
+            Err(e) => {
+                eprintln!("Keeping note {} captured before setup: {}", capture.id, e);
+                continue;
+            }
+        };
+        match enqueue(capture, &note_text, captured_at) {
+            Ok(()) => queued.push(capture.id.clone()),
+            Err(e) => eprintln!("Couldn't send note {} captured before setup: {}", capture.id, e),
+        }
+    }
+    if let Err(e) = modify(pending, |pending| {
+        pending.retain(|capture| !queued.contains(&capture.id));
+        Ok(())
+    }) {
+        eprintln!("Failed to update pending captures: {}", e);
+    }
+    queued.len()
+}
+
+#[tauri::command]
+pub fn get_pending_setup_captures(state: State<'_, AppState>) -> Vec<PendingSetupCapture> {
+    let mut pending = state.pending_setup.lock().unwrap().clone();
+    for capture in &mut pending {
+        if let Ok(text) = local_crypto::open(&capture.note_text) {
+            capture.note_text = text;
+        } else {
+            capture.note_text = "(locked until the system keyring is available)".into();
+        }
+    }
+    pending
+}
+
+#[tauri::command]
+pub fn discard_pending_setup_capture(id: String, app: AppHandle) -> Result<(), String> {
+    modify_pending(&app, |pending| {
+        let before = pending.len();
+        pending.retain(|capture| capture.id != id);
+        if pending.len() == before {
+            return Err(format!("No pending capture with id {}", id));
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::use_test_data_dir;
+    use chrono::TimeZone;
+
+    fn captured_at() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 5, 4, 9, 30, 0).unwrap()
+    }
+
+    #[test]
+    fn stashed_captures_resume_with_their_capture_time() {
+        use_test_data_dir();
+        let pending = Mutex::new(Vec::new());
+        stash_in(&pending, "From the hotkey", None, CaptureOrigin::Hotkey, captured_at()).unwrap();
+        stash_in(&pending, "From a link", Some("work".into()), CaptureOrigin::Uri, captured_at()).unwrap();
+
+        let mut sent = Vec::new();
+        let queued = requeue(&pending, |capture, text, at| {
+            sent.push((text.to_string(), capture.profile.clone(), at));
+            Ok(())
+        });
+        assert_eq!(queued, 2);
+        assert_eq!(
+            sent,
+            vec![
+                ("From the hotkey".to_string(), None, Some(captured_at())),
+                ("From a link".to_string(), Some("work".to_string()), Some(captured_at())),
+            ]
+        );
+        assert!(pending.lock().unwrap().is_empty());
+        assert!(load_pending().is_empty());
+    }
+
+    #[test]
+    fn captures_that_fail_to_queue_stay_pending() {
+        use_test_data_dir();
+        let pending = Mutex::new(Vec::new());
+        stash_in(&pending, "Sent", None, CaptureOrigin::Cli, captured_at()).unwrap();
+        stash_in(&pending, "Kept", None, CaptureOrigin::Cli, captured_at()).unwrap();
+
+        let queued = requeue(&pending, |_, text, _| match text {
+            "Kept" => Err("Queue is full".into()),
+            _ => Ok(()),
+        });
+        assert_eq!(queued, 1);
+        let texts: Vec<_> = load_pending().into_iter().map(|capture| capture.note_text).collect();
+        assert_eq!(texts, ["Kept"]);
+        assert_eq!(requeue(&Mutex::new(Vec::new()), |_, _, _| panic!("nothing to queue")), 0);
+    }
+
+    #[test]
+    fn only_uri_and_cli_captures_are_written_to_disk() {
+        use_test_data_dir();
+        let pending = Mutex::new(Vec::new());
+        for (text, origin) in [
+            ("hotkey", CaptureOrigin::Hotkey),
+            ("clipboard", CaptureOrigin::Clipboard),
+            ("uri", CaptureOrigin::Uri),
+            ("cli", CaptureOrigin::Cli),
+        ] {
+            stash_in(&pending, text, None, origin, captured_at()).unwrap();
+        }
+        assert_eq!(pending.lock().unwrap().len(), 4);
+
+        let stored = load_pending();
+        let origins: Vec<_> = stored.iter().map(|capture| capture.origin).collect();
+        assert_eq!(origins, [CaptureOrigin::Uri, CaptureOrigin::Cli]);
+        assert_eq!(stored[0].note_text, "uri");
+        assert_eq!(stored[0].captured_at, captured_at().to_rfc3339());
+    }
+
+    #[test]
+    fn the_pending_list_is_bounded() {
+        use_test_data_dir();
+        let pending = Mutex::new(Vec::new());
+        for i in 0..MAX_PENDING_SETUP_CAPTURES {
+            stash_in(&pending, &format!("note {}", i), None, CaptureOrigin::Cli, captured_at()).unwrap();
+        }
+        let error = stash_in(&pending, "one too many", None, CaptureOrigin::Cli, captured_at()).unwrap_err();
+        assert!(error.contains("5 notes are already waiting"), "{}", error);
+        assert_eq!(load_pending().len(), MAX_PENDING_SETUP_CAPTURES);
+    }
+}
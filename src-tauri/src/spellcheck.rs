@@ -0,0 +1,235 @@
+use serde::Serialize;
+use std::sync::Mutex;
+
+use crate::config::AppConfig;
+
+// Spellcheck for the note window. Turning it on or off is an element-level
+// hint the frontend applies on every platform. Languages depend on the webview:
+// WebView2 takes them as a browser argument when its first webview starts, so
+// a change needs a restart; WKWebView follows the system spelling settings and
+// WebKitGTK's per-context languages aren't wired up, so both keep the OS default.
+
+// Tauri's own WebView2 arguments, kept because the environment variable replaces them
+#[cfg(windows)]
+const TAURI_BROWSER_ARGS: &str = "--disable-features=msWebOOUI,msPdfOOUI,msSmartScreenProtection";
+
+// What the platform webview lets us control
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpellcheckSupport {
+    pub toggle: bool,
+    pub languages: bool,
+    // Languages only take effect when the app starts
+    pub languages_at_startup_only: bool,
+}
+
+pub fn platform_support() -> SpellcheckSupport {
+    SpellcheckSupport {
+        toggle: true,
+        languages: cfg!(windows),
+        languages_at_startup_only: cfg!(windows),
+    }
+}
+
+// Requested vs what the note window actually gets, for diagnostics
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct SpellcheckStatus {
+    pub enabled: bool,
+    pub requested_languages: Vec<String>,
+    // Empty means the OS default
+    pub applied_languages: Vec<String>,
+    pub restart_required: bool,
+    pub fallback_reason: Option<String>,
+}
+
+// Values the note window sets on its text area
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct SpellcheckHints {
+    pub enabled: bool,
+    pub languages: Vec<String>,
+}
+
+lazy_static::lazy_static! {
+    // Languages handed to the webview at startup, where the platform takes them
+    static ref STARTUP_LANGUAGES: Mutex<Option<Vec<String>>> = Mutex::new(None);
+}
+
+// A loose BCP 47 check: "en", "en-US", "sr-Latn-RS"
+fn is_language_tag(tag: &str) -> bool {
+    let mut parts = tag.split('-');
+    let primary = parts.next().unwrap_or_default();
+    (2..=3).contains(&primary.len())
+        && primary.chars().all(|c| c.is_ascii_alphabetic())
+        && parts.all(|part| (1..=8).contains(&part.len()) && part.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+// Trimmed, valid and de-duplicated, in the order given
+pub fn normalize_languages(languages: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for language in languages.iter().map(|language| language.trim()) {
+        if is_language_tag(language) && !normalized.iter().any(|seen| seen.eq_ignore_ascii_case(language)) {
+            normalized.push(language.to_string());
+        }
+    }
+    normalized
+}
+
+// Requested settings vs platform capability -> what applies. `startup` is the
+// language list the webview was started with, where that is fixed at startup.
+pub fn resolve_spellcheck(
+    enabled: bool,
+    languages: &[String],
+    support: SpellcheckSupport,
+    startup: Option<&[String]>,
+) -> SpellcheckStatus {
+    let requested_languages = normalize_languages(languages);
+    let mut status = SpellcheckStatus {
+        enabled: enabled && support.toggle,
+        requested_languages: requested_languages.clone(),
+        applied_languages: Vec::new(),
+        restart_required: false,
+        fallback_reason: None,
+    };
+
+    if enabled && !support.toggle {
+        status.fallback_reason = Some("Spellcheck can't be turned off or on here; the webview default is used".into());
+    }
+    if requested_languages.len() < languages.len() {
+        status.fallback_reason = Some("Some spellcheck languages aren't valid language tags and were ignored".into());
+    }
+    if !status.enabled || requested_languages.is_empty() {
+        return status;
+    }
+
+    if !support.languages {
+        status.fallback_reason = Some("This platform's webview follows the system spelling languages".into());
+        return status;
+    }
+    match startup {
+        Some(started_with) if support.languages_at_startup_only => {
+            status.applied_languages = started_with.to_vec();
+            status.restart_required = started_with != requested_languages.as_slice();
+        }
+        _ => status.applied_languages = requested_languages,
+    }
+    status
+}
+
+// Hand the configured languages to the webview before any window exists
+pub fn prepare_process(config: &AppConfig) {
+    let languages = if config.spellcheck_enabled {
+        normalize_languages(&config.spellcheck_languages)
+    } else {
+        Vec::new()
+    };
+    *STARTUP_LANGUAGES.lock().unwrap() = Some(languages.clone());
+    set_browser_languages(&languages);
+}
+
+#[cfg(windows)]
+fn set_browser_languages(languages: &[String]) {
+    const VAR: &str = "WEBVIEW2_ADDITIONAL_BROWSER_ARGUMENTS";
+    // Respect arguments someone set deliberately
+    if languages.is_empty() || std::env::var_os(VAR).is_some() {
+        return;
+    }
+    std::env::set_var(VAR, format!("{} --accept-lang={}", TAURI_BROWSER_ARGS, languages.join(",")));
+}
+
+#[cfg(not(windows))]
+fn set_browser_languages(_languages: &[String]) {}
+
+pub fn status(config: &AppConfig) -> SpellcheckStatus {
+    let startup = STARTUP_LANGUAGES.lock().unwrap().clone();
+    resolve_spellcheck(
+        config.spellcheck_enabled,
+        &config.spellcheck_languages,
+        platform_support(),
+        startup.as_deref(),
+    )
+}
+
+pub fn hints(config: &AppConfig) -> SpellcheckHints {
+    SpellcheckHints {
+        enabled: config.spellcheck_enabled,
+        languages: normalize_languages(&config.spellcheck_languages),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WEBVIEW2: SpellcheckSupport = SpellcheckSupport { toggle: true, languages: true, languages_at_startup_only: true };
+    const WKWEBVIEW: SpellcheckSupport = SpellcheckSupport { toggle: true, languages: false, languages_at_startup_only: false };
+    const LIVE: SpellcheckSupport = SpellcheckSupport { toggle: true, languages: true, languages_at_startup_only: false };
+
+    fn langs(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(|tag| tag.to_string()).collect()
+    }
+
+    #[test]
+    fn languages_are_trimmed_checked_and_deduplicated() {
+        assert_eq!(
+            normalize_languages(&langs(&[" en-US ", "de", "EN-us", "sr-Latn-RS", "english", "e", "fr_FR", ""])),
+            langs(&["en-US", "de", "sr-Latn-RS"])
+        );
+    }
+
+    #[test]
+    fn languages_apply_where_the_webview_takes_them() {
+        let status = resolve_spellcheck(true, &langs(&["en-US", "de"]), LIVE, None);
+        assert_eq!(status.applied_languages, langs(&["en-US", "de"]));
+        assert!(!status.restart_required);
+        assert_eq!(status.fallback_reason, None);
+
+        let status = resolve_spellcheck(true, &langs(&["en-US", "de"]), WKWEBVIEW, None);
+        assert!(status.enabled);
+        assert!(status.applied_languages.is_empty());
+        assert_eq!(status.fallback_reason.as_deref(), Some("This platform's webview follows the system spelling languages"));
+    }
+
+    #[test]
+    fn startup_only_languages_need_a_restart_to_change() {
+        let started_with = langs(&["en-US"]);
+        let status = resolve_spellcheck(true, &langs(&["en-US"]), WEBVIEW2, Some(&started_with));
+        assert_eq!((status.applied_languages.clone(), status.restart_required), (started_with.clone(), false));
+
+        let status = resolve_spellcheck(true, &langs(&["en-US", "de"]), WEBVIEW2, Some(&started_with));
+        assert_eq!(status.requested_languages, langs(&["en-US", "de"]));
+        assert_eq!((status.applied_languages, status.restart_required), (started_with, true));
+    }
+
+    #[test]
+    fn disabled_or_unsupported_spellcheck_applies_no_languages() {
+        let status = resolve_spellcheck(false, &langs(&["de"]), LIVE, None);
+        assert!(!status.enabled);
+        assert!(status.applied_languages.is_empty());
+        assert_eq!(status.fallback_reason, None);
+
+        let no_toggle = SpellcheckSupport { toggle: false, ..LIVE };
+        let status = resolve_spellcheck(true, &langs(&["de"]), no_toggle, None);
+        assert!(!status.enabled);
+        assert!(status.applied_languages.is_empty());
+        assert!(status.fallback_reason.is_some_and(|reason| reason.contains("can't be turned off or on")));
+    }
+
+    #[test]
+    fn invalid_tags_are_reported() {
+        let status = resolve_spellcheck(true, &langs(&["de", "not a tag"]), LIVE, None);
+        assert_eq!(status.applied_languages, langs(&["de"]));
+        assert_eq!(
+            status.fallback_reason.as_deref(),
+            Some("Some spellcheck languages aren't valid language tags and were ignored")
+        );
+    }
+
+    #[test]
+    fn hints_follow_the_config() {
+        let config = AppConfig {
+            spellcheck_enabled: false,
+            spellcheck_languages: langs(&["fr", "bad tag", "fr"]),
+            ..AppConfig::default()
+        };
+        assert_eq!(hints(&config), SpellcheckHints { enabled: false, languages: langs(&["fr"]) });
+    }
+}
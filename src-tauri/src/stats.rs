@@ -0,0 +1,174 @@
+use chrono::{DateTime, Local, NaiveDate};
+use serde::Serialize;
+use serde_json::Value;
+use tauri::State;
+
+use crate::config::AppState;
+use crate::notion::{same_page_id, NotionApiClient};
+use crate::rate_limit::RATE_LIMITER;
+use crate::receipts::{self, DeliveryReceipt};
+
+// What our own delivery receipts say about a page
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct LocalPageStats {
+    // RFC3339 time of the newest delivery to the page
+    pub last_note_at: Option<String>,
+    pub notes_today: usize,
+}
+
+// What Notion says, when the user asked to verify
+#[derive(Serialize, Debug, Clone)]
+pub struct RemotePageStats {
+    // created_time of the page's newest block, whoever wrote it
+    pub newest_block_at: Option<String>,
+}
+
+// Both sources side by side, so notes added by other tools show up as a difference
+#[derive(Serialize, Debug, Clone)]
+pub struct PageStats {
+    pub local: LocalPageStats,
+    pub remote: Option<RemotePageStats>,
+    // Why remote verification didn't run, when it was requested
+    pub remote_skipped: Option<String>,
+}
+
+fn delivered_at(receipt: &DeliveryReceipt) -> Option<DateTime<Local>> {
+    DateTime::parse_from_rfc3339(&receipt.delivered_at)
+        .ok()
+        .map(|time| time.with_timezone(&Local))
+}
+
+// Aggregate receipts for one page; `today` is the local date to count against
+pub fn local_page_stats(receipts: &[DeliveryReceipt], page_id: &str, today: NaiveDate) -> LocalPageStats {
+    let times: Vec<DateTime<Local>> = receipts
+        .iter()
+        .filter(|receipt| same_page_id(&receipt.page_id, page_id))
+        .filter_map(delivered_at)
+        .collect();
+
+    LocalPageStats {
+        last_note_at: times.iter().max().map(|time| time.to_rfc3339()),
+        notes_today: times.iter().filter(|time| time.date_naive() == today).count(),
+    }
+}
+
+// Newest created_time among a page's children (RFC3339 UTC strings sort chronologically)
+pub fn newest_block_time(children: &[Value]) -> Option<String> {
+    children
+        .iter()
+        .filter_map(|block| block["created_time"].as_str())
+        .max()
+        .map(|time| time.to_string())
+}
+
+// Local stats for a page, plus Notion's view when `verify` is set. Verification
+// is skipped while offline or rate limited rather than queued behind the limiter.
+#[tauri::command]
+pub async fn get_target_page_stats(
+    page_id: String,
+    verify: Option<bool>,
+    online: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<PageStats, String> {
+    let local = local_page_stats(&receipts::recent_receipts(usize::MAX), &page_id, Local::now().date_naive());
+
+    let mut stats = PageStats {
+        local,
+        remote: None,
+        remote_skipped: None,
+    };
+    if !verify.unwrap_or(false) {
+        return Ok(stats);
+    }
+
+    let api_token = state.config.lock().unwrap().secrets.main_token().to_string();
+    if online == Some(false) {
+        stats.remote_skipped = Some("offline".into());
+    } else if let Some(wait) = RATE_LIMITER.limited_for(&api_token) {
+        stats.remote_skipped = Some(format!("rate limited for {}s", wait.as_secs().max(1)));
+    } else {
+        let client = NotionApiClient::new(api_token)?;
+        match client.last_children(&page_id).await? {
+            Some(children) => {
+                stats.remote = Some(RemotePageStats {
+                    newest_block_at: newest_block_time(&children),
+                });
+            }
+            None => stats.remote_skipped = Some("Notion refused to list the page's blocks".into()),
+        }
+    }
+
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notion::AppendResponse;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    const PAGE: &str = "3e2f0000-0000-0000-0000-0000000000ab";
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 3, day, hour, minute, 0).unwrap()
+    }
+
+    fn receipt(page_id: &str, delivered_at: DateTime<Local>) -> DeliveryReceipt {
+        let response = AppendResponse {
+            block_ids: vec!["block-1".to_string()],
+            request_id: None,
+            status: 200,
+            attempts: 1,
+        };
+        let mut receipt = DeliveryReceipt::new("capture".to_string(), page_id.to_string(), response, None);
+        receipt.delivered_at = delivered_at.to_rfc3339();
+        receipt
+    }
+
+    #[test]
+    fn local_stats_count_only_the_page_and_today() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 5).unwrap();
+        let receipts = vec![
+            receipt(PAGE, at(4, 23, 50)),
+            receipt(PAGE, at(5, 9, 15)),
+            // The same page, written without dashes and in upper case
+            receipt("3E2F00000000000000000000000000AB", at(5, 14, 32)),
+            receipt("another-page", at(5, 16, 0)),
+        ];
+
+        let stats = local_page_stats(&receipts, PAGE, today);
+        assert_eq!(stats.notes_today, 2);
+        assert_eq!(stats.last_note_at, Some(at(5, 14, 32).to_rfc3339()));
+
+        let other = local_page_stats(&receipts, "another-page", today);
+        assert_eq!((other.notes_today, other.last_note_at), (1, Some(at(5, 16, 0).to_rfc3339())));
+    }
+
+    #[test]
+    fn pages_without_deliveries_have_empty_stats() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 5).unwrap();
+        assert_eq!(local_page_stats(&[], PAGE, today), LocalPageStats::default());
+
+        let mut unreadable = receipt(PAGE, at(5, 9, 15));
+        unreadable.delivered_at = "not a time".to_string();
+        assert_eq!(local_page_stats(&[unreadable], PAGE, today), LocalPageStats::default());
+
+        // Yesterday's notes still give the last note time
+        let yesterday = local_page_stats(&[receipt(PAGE, at(4, 9, 15))], PAGE, today);
+        assert_eq!((yesterday.notes_today, yesterday.last_note_at), (0, Some(at(4, 9, 15).to_rfc3339())));
+    }
+
+    #[test]
+    fn the_newest_block_is_found_by_created_time() {
+        let children = vec![
+            json!({ "id": "a", "created_time": "2024-03-05T09:15:00.000Z" }),
+            json!({ "id": "b", "created_time": "2024-03-05T14:32:00.000Z" }),
+            json!({ "id": "c", "created_time": "2024-03-05T11:00:00.000Z" }),
+            json!({ "id": "d" }),
+        ];
+        assert_eq!(newest_block_time(&children).as_deref(), Some("2024-03-05T14:32:00.000Z"));
+        assert_eq!(newest_block_time(&children[3..]), None);
+        assert_eq!(newest_block_time(&[]), None);
+    }
+}
@@ -0,0 +1,189 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tokio::sync::watch;
+
+use crate::config::AppState;
+use crate::events::{self, AppEvent};
+use crate::platform::SuspendDetector;
+use crate::rate_limit::{RateLimitManager, RATE_LIMITER};
+
+// What happens when the machine wakes up. Timers and stored Instants are
+// unreliable across a suspend, and requests that were open when it started
+// die with transport errors. On resume every time-based piece of state is
+// re-evaluated: 429 deadlines are rebuilt from wall-clock time, the queue
+// flush is re-armed, settings are re-checked (which doubles as the
+// connectivity probe), and failures that straddled the suspend are retried
+// rather than reported.
+
+// How often the detector ticks
+const TICK: Duration = Duration::from_secs(5);
+
+// Failures this soon after a resume are blamed on it; the network is often
+// still coming back
+pub const RESUME_GRACE: Duration = Duration::from_secs(30);
+
+// Wait before the first request after a resume, so Wi-Fi can reconnect
+pub const RESUME_SETTLE: Duration = Duration::from_secs(5);
+
+// Time-based state that doesn't need the app, so it can be driven directly
+pub struct ResumeTracker {
+    epoch: AtomicU64,
+    last_resume: Mutex<Option<Instant>>,
+    resumes: watch::Sender<u64>,
+}
+
+impl ResumeTracker {
+    pub fn new() -> Self {
+        ResumeTracker {
+            epoch: AtomicU64::new(0),
+            last_resume: Mutex::new(None),
+            resumes: watch::channel(0).0,
+        }
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.epoch.load(Ordering::SeqCst)
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.resumes.subscribe()
+    }
+
+    pub fn interrupted(&self, started_epoch: u64, now: Instant) -> bool {
+        self.epoch() != started_epoch
+            || self
+                .last_resume
+                .lock()
+                .unwrap()
+                .is_some_and(|resumed| now.saturating_duration_since(resumed) < RESUME_GRACE)
+    }
+
+    pub fn resume(&self, limiter: &RateLimitManager, now: Instant, wall_now: chrono::DateTime<chrono::Utc>) {
+        self.epoch.fetch_add(1, Ordering::SeqCst);
+        *self.last_resume.lock().unwrap() = Some(now);
+        limiter.resync_after_suspend(now, wall_now);
+        self.resumes.send_modify(|count| *count += 1);
+    }
+}
+
+impl Default for ResumeTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref RESUMES: ResumeTracker = ResumeTracker::new();
+}
+
+// Bumped on every resume; a request compares the value from before it started
+pub fn epoch() -> u64 {
+    RESUMES.epoch()
+}
+
+// Wakes on every resume, for tasks that sleep on timers
+pub fn subscribe() -> watch::Receiver<u64> {
+    RESUMES.subscribe()
+}
+
+// Whether a failure of something started at `started_epoch` is down to a suspend:
+// one happened while it ran, or it failed while the network was still coming back
+pub fn interrupted(started_epoch: u64, now: Instant) -> bool {
+    RESUMES.interrupted(started_epoch, now)
+}
+
+pub fn on_resume(app: &AppHandle, slept: Duration) {
+    println!("Resumed after about {}s asleep", slept.as_secs());
+    RESUMES.resume(&RATE_LIMITER, Instant::now(), chrono::Utc::now());
+    events::emit_app_event(app, AppEvent::SystemResumed(slept.as_secs()));
+    crate::queue::publish_estimates(app);
+
+    let app_handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(RESUME_SETTLE).await;
+        crate::verification::recheck(&app_handle).await;
+    });
+}
+
+// Background task watching for suspend/resume
+pub fn start_suspend_watch(app: &AppHandle) {
+    let app_handle = app.clone();
+    let registry = app.state::<AppState>().tasks.clone();
+
+    registry.spawn("suspend-watch", move |mut token| async move {
+        let mut detector = SuspendDetector::new(chrono::Utc::now(), Instant::now());
+        loop {
+            tokio::select! {
+                _ = token.cancelled() => break,
+                _ = tokio::time::sleep(TICK) => {}
+            }
+            if let Some(slept) = detector.observe(chrono::Utc::now(), Instant::now(), TICK) {
+                on_resume(&app_handle, slept);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::SuspendDetector;
+    use crate::rate_limit::{RateLimitSource, RateLimitState};
+
+    #[test]
+    fn the_detector_reports_a_clock_jump_as_sleep() {
+        let wall = chrono::Utc::now();
+        let start = Instant::now();
+        let mut detector = SuspendDetector::new(wall, start);
+        // An ordinary tick
+        assert_eq!(detector.observe(wall + chrono::Duration::seconds(5), start + TICK, TICK), None);
+        // The wall clock moved ten minutes while the monotonic clock stood still
+        let slept = detector.observe(wall + chrono::Duration::seconds(605), start + TICK + Duration::from_secs(5), TICK);
+        assert_eq!(slept, Some(Duration::from_secs(595)));
+    }
+
+    #[tokio::test]
+    async fn a_resume_marks_open_requests_interrupted_and_wakes_timers() {
+        let tracker = ResumeTracker::new();
+        let limiter = RateLimitManager::new();
+        let mut resumes = tracker.subscribe();
+        let before = tracker.epoch();
+        let now = Instant::now();
+        assert!(!tracker.interrupted(before, now));
+
+        tracker.resume(&limiter, now, chrono::Utc::now());
+        assert_eq!(tracker.epoch(), before + 1);
+        assert!(resumes.has_changed().unwrap());
+        resumes.changed().await.unwrap();
+        assert_eq!(*resumes.borrow(), 1);
+
+        // Started before the suspend
+        assert!(tracker.interrupted(before, now + RESUME_GRACE * 2));
+        // Started after it, failing while the network comes back, then once it is up
+        assert!(tracker.interrupted(tracker.epoch(), now + RESUME_GRACE - Duration::from_secs(1)));
+        assert!(!tracker.interrupted(tracker.epoch(), now + RESUME_GRACE));
+    }
+
+    #[test]
+    fn rate_limit_deadlines_follow_the_wall_clock_after_a_resume() {
+        let tracker = ResumeTracker::new();
+        let limiter = RateLimitManager::new();
+        limiter.record_rate_limit("secret_short", Duration::from_secs(60), RateLimitSource::Headers);
+        limiter.record_rate_limit("secret_long", Duration::from_secs(600), RateLimitSource::Headers);
+
+        // 45 seconds passed on the wall clock while the monotonic clock was paused
+        let now = Instant::now();
+        tracker.resume(&limiter, now, chrono::Utc::now() + chrono::Duration::seconds(45));
+        let (remaining, source) = limiter.limit_state("secret_short", now).unwrap();
+        assert!(remaining <= Duration::from_secs(15) && remaining > Duration::from_secs(13), "{:?}", remaining);
+        assert_eq!(source, RateLimitSource::Headers);
+
+        // Another two minutes: the short limit is over, the long one still runs
+        tracker.resume(&limiter, now, chrono::Utc::now() + chrono::Duration::seconds(165));
+        assert_eq!(limiter.limit_state("secret_short", now), None);
+        assert_eq!(limiter.info("secret_short", now).state, RateLimitState::Ok);
+        assert!(limiter.limit_state("secret_long", now).is_some_and(|(remaining, _)| remaining > Duration::from_secs(400)));
+    }
+}
@@ -0,0 +1,194 @@
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::async_runtime::{self, JoinHandle};
+use tokio::sync::watch;
+
+// Default time each task gets to stop once cancelled
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Handed to every background task so it can notice shutdown and flush before exiting
+#[derive(Clone)]
+pub struct CancellationToken {
+    receiver: watch::Receiver<bool>,
+}
+
+impl CancellationToken {
+    pub fn is_cancelled(&self) -> bool {
+        *self.receiver.borrow()
+    }
+
+    // Resolves once cancellation has been requested; use in a `tokio::select!` next to the task's work
+    pub async fn cancelled(&mut self) {
+        while !*self.receiver.borrow() {
+            if self.receiver.changed().await.is_err() {
+                // The registry is gone, which can only mean we're shutting down
+                return;
+            }
+        }
+    }
+}
+
+struct RegisteredTask {
+    name: String,
+    cancel: watch::Sender<bool>,
+    handle: JoinHandle<()>,
+}
+
+// Tracks long-running background tasks so quitting can stop them cleanly instead of aborting mid-write
+#[derive(Default)]
+pub struct TaskRegistry {
+    tasks: Mutex<Vec<RegisteredTask>>,
+}
+
+impl TaskRegistry {
+    pub fn new() -> Self {
+        TaskRegistry::default()
+    }
+
+    // Spawn a named task; the closure receives the token it must watch for cancellation.
+    // Tasks that write to disk (queue, history, config) must flush before returning.
+    pub fn spawn<F, Fut>(&self, name: &str, task: F)
+    where
+        F: FnOnce(CancellationToken) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let (cancel, receiver) = watch::channel(false);
+        let handle = async_runtime::spawn(task(CancellationToken { receiver }));
+
+        let mut tasks = self.tasks.lock().unwrap();
+        // Drop bookkeeping for tasks that already finished on their own
+        tasks.retain(|t| !t.handle.inner().is_finished());
+        tasks.push(RegisteredTask {
+            name: name.to_string(),
+            cancel,
+            handle,
+        });
+    }
+
+    // Names of the tasks still running, for diagnostics
+    pub fn running_tasks(&self) -> Vec<String> {
+        let tasks = self.tasks.lock().unwrap();
+        tasks
+            .iter()
+            .filter(|t| !t.handle.inner().is_finished())
+            .map(|t| t.name.clone())
+            .collect()
+    }
+
+    // Cancel every task, then wait for each with a per-task timeout.
+    // Returns the names of tasks that didn't stop in time (they are aborted).
+    pub async fn shutdown(&self, per_task_timeout: Duration) -> Vec<String> {
+        let tasks: Vec<RegisteredTask> = {
+            let mut tasks = self.tasks.lock().unwrap();
+            tasks.drain(..).collect()
+        };
+
+        // Signal everyone first so tasks wind down in parallel regardless of registration order
+        for task in &tasks {
+            let _ = task.cancel.send(true);
+        }
+
+        let mut timed_out = Vec::new();
+        for task in tasks {
+            let RegisteredTask { name, handle, .. } = task;
+            let abort_handle = handle.inner().abort_handle();
+            match tokio::time::timeout(per_task_timeout, handle).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => eprintln!("Background task '{}' ended with an error: {}", name, e),
+                Err(_) => {
+                    eprintln!("Background task '{}' did not stop within {:?}", name, per_task_timeout);
+                    abort_handle.abort();
+                    timed_out.push(name);
+                }
+            }
+        }
+
+        timed_out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tokio::sync::Notify;
+
+    #[tokio::test]
+    async fn shutdown_does_not_depend_on_registration_order() {
+        let registry = TaskRegistry::new();
+        let flushed = Arc::new(Mutex::new(Vec::new()));
+        let second_flushed = Arc::new(Notify::new());
+
+        // The first task can only finish after the second has flushed,
+        // which works because every task is cancelled before any is awaited
+        let (log, waiting_on) = (flushed.clone(), second_flushed.clone());
+        registry.spawn("first", move |mut token| async move {
+            token.cancelled().await;
+            waiting_on.notified().await;
+            log.lock().unwrap().push("first");
+        });
+        let (log, done) = (flushed.clone(), second_flushed.clone());
+        registry.spawn("second", move |mut token| async move {
+            token.cancelled().await;
+            log.lock().unwrap().push("second");
+            done.notify_one();
+        });
+
+        assert_eq!(registry.running_tasks(), vec!["first", "second"]);
+        let stragglers = registry.shutdown(Duration::from_secs(2)).await;
+        assert!(stragglers.is_empty(), "{:?}", stragglers);
+        assert_eq!(*flushed.lock().unwrap(), vec!["second", "first"]);
+        assert!(registry.running_tasks().is_empty());
+    }
+
+    #[tokio::test]
+    async fn tasks_ignoring_cancellation_are_reported_and_aborted() {
+        let registry = TaskRegistry::new();
+        let stopped = Arc::new(Mutex::new(false));
+
+        registry.spawn("stubborn", |_token| async {
+            std::future::pending::<()>().await;
+        });
+        let flag = stopped.clone();
+        registry.spawn("polite", move |mut token| async move {
+            token.cancelled().await;
+            *flag.lock().unwrap() = true;
+        });
+
+        let started = std::time::Instant::now();
+        let stragglers = registry.shutdown(Duration::from_millis(100)).await;
+        assert_eq!(stragglers, vec!["stubborn"]);
+        assert!(*stopped.lock().unwrap());
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn finished_tasks_drop_out_of_the_running_list() {
+        let registry = TaskRegistry::new();
+        registry.spawn("one-shot", |_token| async {});
+        registry.spawn("long-lived", |mut token| async move { token.cancelled().await });
+
+        tokio::time::timeout(Duration::from_secs(2), async {
+            while registry.running_tasks().len() > 1 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(registry.running_tasks(), vec!["long-lived"]);
+        assert!(registry.shutdown(Duration::from_secs(1)).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn cancelled_tokens_say_so() {
+        let registry = TaskRegistry::new();
+        let (sender, receiver) = tokio::sync::oneshot::channel();
+        registry.spawn("watcher", move |mut token| async move {
+            token.cancelled().await;
+            let _ = sender.send(token.is_cancelled());
+        });
+        assert!(registry.shutdown(Duration::from_secs(1)).await.is_empty());
+        assert!(receiver.await.unwrap());
+    }
+}
@@ -0,0 +1,242 @@
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveDateTime, Timelike};
+use chrono_tz::Tz;
+
+use crate::config::AppConfig;
+use crate::error::AppError;
+
+// The timestamp at the start of every note, e.g. "[05 Mar 24, 14:07:09]" or
+// "[05 Mär 24, 2:07:09 PM]". Month names follow the configured locale, or the
+// system's when none is set; languages without a table here use English. The
+// time is local unless a timezone override is configured.
+
+// Abbreviated month names by language subtag
+const MONTH_NAMES: &[(&str, [&str; 12])] = &[
+    ("en", ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"]),
+    ("da", ["jan", "feb", "mar", "apr", "maj", "jun", "jul", "aug", "sep", "okt", "nov", "dec"]),
+    ("de", ["Jan", "Feb", "Mär", "Apr", "Mai", "Jun", "Jul", "Aug", "Sep", "Okt", "Nov", "Dez"]),
+    ("es", ["ene", "feb", "mar", "abr", "may", "jun", "jul", "ago", "sept", "oct", "nov", "dic"]),
+    ("fi", ["tammi", "helmi", "maalis", "huhti", "touko", "kesä", "heinä", "elo", "syys", "loka", "marras", "joulu"]),
+    ("fr", ["janv.", "févr.", "mars", "avr.", "mai", "juin", "juil.", "août", "sept.", "oct.", "nov.", "déc."]),
+    ("it", ["gen", "feb", "mar", "apr", "mag", "giu", "lug", "ago", "set", "ott", "nov", "dic"]),
+    ("nb", ["jan", "feb", "mar", "apr", "mai", "jun", "jul", "aug", "sep", "okt", "nov", "des"]),
+    ("nl", ["jan", "feb", "mrt", "apr", "mei", "jun", "jul", "aug", "sep", "okt", "nov", "dec"]),
+    ("no", ["jan", "feb", "mar", "apr", "mai", "jun", "jul", "aug", "sep", "okt", "nov", "des"]),
+    ("pl", ["sty", "lut", "mar", "kwi", "maj", "cze", "lip", "sie", "wrz", "paź", "lis", "gru"]),
+    ("pt", ["jan", "fev", "mar", "abr", "mai", "jun", "jul", "ago", "set", "out", "nov", "dez"]),
+    ("ro", ["ian", "feb", "mar", "apr", "mai", "iun", "iul", "aug", "sept", "oct", "nov", "dec"]),
+    ("ru", ["янв", "фев", "мар", "апр", "май", "июн", "июл", "авг", "сен", "окт", "ноя", "дек"]),
+    ("sv", ["jan", "feb", "mars", "apr", "maj", "juni", "juli", "aug", "sep", "okt", "nov", "dec"]),
+    ("tr", ["Oca", "Şub", "Mar", "Nis", "May", "Haz", "Tem", "Ağu", "Eyl", "Eki", "Kas", "Ara"]),
+    ("uk", ["січ", "лют", "бер", "кві", "тра", "чер", "лип", "сер", "вер", "жов", "лис", "гру"]),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimestampOptions {
+    // "2:07:09 PM" instead of "14:07:09"
+    pub hour12: bool,
+    // A locale like "de", "pt-BR" or "fr_CA.UTF-8"
+    pub locale: String,
+    // Shown in this zone instead of the system's
+    pub timezone: Option<Tz>,
+}
+
+impl Default for TimestampOptions {
+    fn default() -> Self {
+        TimestampOptions {
+            hour12: false,
+            locale: "en".to_string(),
+            timezone: None,
+        }
+    }
+}
+
+impl TimestampOptions {
+    pub fn from_config(config: &AppConfig) -> Self {
+        TimestampOptions {
+            hour12: config.timestamp_12_hour,
+            locale: config.timestamp_locale.clone().unwrap_or_else(system_locale),
+            // Checked when the config is saved
+            timezone: config.timestamp_timezone.as_deref().and_then(|name| name.parse().ok()),
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref SYSTEM_LOCALE: String = read_system_locale().unwrap_or_else(|| "en".to_string());
+}
+
+// The user's locale as the OS reports it, read once
+pub fn system_locale() -> String {
+    SYSTEM_LOCALE.clone()
+}
+
+#[cfg(windows)]
+fn read_system_locale() -> Option<String> {
+    use windows_sys::Win32::Globalization::GetUserDefaultLocaleName;
+    // LOCALE_NAME_MAX_LENGTH
+    let mut buffer = [0u16; 85];
+    let len = unsafe { GetUserDefaultLocaleName(buffer.as_mut_ptr(), buffer.len() as i32) };
+    // The length includes the terminating null
+    (len > 1).then(|| String::from_utf16_lossy(&buffer[..len as usize - 1]))
+}
+
+#[cfg(not(windows))]
+fn read_system_locale() -> Option<String> {
+    ["LC_ALL", "LC_TIME", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty() && value != "C" && value != "POSIX")
+}
+
+// A timezone setting as stored: the IANA name in its canonical form, or None
+// for local time when blank
+pub fn validate_timezone(name: Option<String>) -> Result<Option<String>, AppError> {
+    let Some(name) = name.map(|name| name.trim().to_string()).filter(|name| !name.is_empty()) else {
+        return Ok(None);
+    };
+    let zone = name
+        .parse::<Tz>()
+        .ok()
+        .or_else(|| chrono_tz::TZ_VARIANTS.iter().copied().find(|zone| zone.name().eq_ignore_ascii_case(&name)))
+        .ok_or_else(|| AppError::ValidationError(format!("\"{}\" isn't a known timezone, e.g. \"Europe/Bucharest\"", name)))?;
+    Ok(Some(zone.name().to_string()))
+}
+
+// Every IANA zone name, for the settings window
+pub fn timezone_names() -> Vec<&'static str> {
+    chrono_tz::TZ_VARIANTS.iter().map(|zone| zone.name()).collect()
+}
+
+// "pt-BR", "pt_BR.UTF-8" and "PT" all give "pt"
+fn language(locale: &str) -> String {
+    locale
+        .split(['-', '_', '.', '@'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+pub fn month_names(locale: &str) -> &'static [&'static str; 12] {
+    let language = language(locale);
+    MONTH_NAMES
+        .iter()
+        .find(|(code, _)| *code == language)
+        .map(|(_, names)| names)
+        .unwrap_or(&MONTH_NAMES[0].1)
+}
+
+// "05 Mar 24" in the options' locale
+pub fn format_day(day: NaiveDate, options: &TimestampOptions) -> String {
+    format!(
+        "{:02} {} {:02}",
+        day.day(),
+        month_names(&options.locale)[day.month0() as usize],
+        day.year() % 100
+    )
+}
+
+// The wall-clock time a timestamp shows, in the override timezone if there is one
+fn wall_clock(time: &DateTime<Local>, options: &TimestampOptions) -> NaiveDateTime {
+    match options.timezone {
+        Some(zone) => time.with_timezone(&zone).naive_local(),
+        None => time.naive_local(),
+    }
+}
+
+// The calendar day a note's timestamp falls on
+pub fn day_of(time: &DateTime<Local>, options: &TimestampOptions) -> NaiveDate {
+    wall_clock(time, options).date()
+}
+
+// Note timestamp in the format [DD MMM YY, HH:MM:SS], or with a 12-hour clock
+// [DD MMM YY, H:MM:SS AM]; midnight is 12 AM and noon 12 PM
+pub fn format_timestamp(time: &DateTime<Local>, options: &TimestampOptions) -> String {
+    let time = wall_clock(time, options);
+    let clock = if options.hour12 {
+        let (pm, hour) = time.hour12();
+        format!("{}:{:02}:{:02} {}", hour, time.minute(), time.second(), if pm { "PM" } else { "AM" })
+    } else {
+        format!("{:02}:{:02}:{:02}", time.hour(), time.minute(), time.second())
+    };
+    format!("[{}, {}]", format_day(time.date(), options), clock)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn local(year: i32, month: u32, day: u32, hour: u32, minute: u32, second: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(year, month, day, hour, minute, second).unwrap()
+    }
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, 0).unwrap().with_timezone(&Local)
+    }
+
+    fn options(hour12: bool, locale: &str, timezone: Option<&str>) -> TimestampOptions {
+        TimestampOptions {
+            hour12,
+            locale: locale.to_string(),
+            timezone: timezone.map(|name| name.parse().unwrap()),
+        }
+    }
+
+    #[test]
+    fn the_24_hour_clock_pads_every_field() {
+        let options = TimestampOptions::default();
+        assert_eq!(format_timestamp(&local(2024, 3, 5, 14, 7, 9), &options), "[05 Mar 24, 14:07:09]");
+        assert_eq!(format_timestamp(&local(2024, 3, 5, 0, 0, 0), &options), "[05 Mar 24, 00:00:00]");
+        assert_eq!(format_timestamp(&local(2009, 12, 31, 23, 59, 59), &options), "[31 Dec 09, 23:59:59]");
+    }
+
+    #[test]
+    fn the_12_hour_clock_calls_midnight_12_am_and_noon_12_pm() {
+        let options = options(true, "en", None);
+        assert_eq!(format_timestamp(&local(2024, 3, 5, 0, 0, 0), &options), "[05 Mar 24, 12:00:00 AM]");
+        assert_eq!(format_timestamp(&local(2024, 3, 5, 0, 30, 5), &options), "[05 Mar 24, 12:30:05 AM]");
+        assert_eq!(format_timestamp(&local(2024, 3, 5, 9, 7, 9), &options), "[05 Mar 24, 9:07:09 AM]");
+        assert_eq!(format_timestamp(&local(2024, 3, 5, 12, 0, 0), &options), "[05 Mar 24, 12:00:00 PM]");
+        assert_eq!(format_timestamp(&local(2024, 3, 5, 14, 7, 9), &options), "[05 Mar 24, 2:07:09 PM]");
+        assert_eq!(format_timestamp(&local(2024, 3, 5, 23, 59, 59), &options), "[05 Mar 24, 11:59:59 PM]");
+    }
+
+    #[test]
+    fn month_names_follow_the_locale() {
+        let time = local(2024, 3, 5, 14, 7, 9);
+        assert_eq!(format_timestamp(&time, &options(false, "de-DE", None)), "[05 Mär 24, 14:07:09]");
+        assert_eq!(format_timestamp(&time, &options(true, "de_AT.UTF-8", None)), "[05 Mär 24, 2:07:09 PM]");
+        assert_eq!(format_timestamp(&local(2024, 2, 1, 8, 0, 0), &options(false, "fr", None)), "[01 févr. 24, 08:00:00]");
+        assert_eq!(format_timestamp(&local(2024, 5, 1, 8, 0, 0), &options(false, "ru_RU", None)), "[01 май 24, 08:00:00]");
+        assert_eq!(month_names("PT-br")[1], "fev");
+        // Languages without a table fall back to English
+        assert_eq!(format_timestamp(&time, &options(false, "ja-JP", None)), "[05 Mar 24, 14:07:09]");
+        assert_eq!(month_names(""), month_names("en"));
+    }
+
+    #[test]
+    fn a_timezone_override_sets_the_clock_and_the_day() {
+        // 23:30 UTC is already the next day in Bucharest and still the same day in New York
+        let time = utc(2024, 3, 5, 23, 30);
+        let bucharest = options(false, "en", Some("Europe/Bucharest"));
+        let new_york = options(true, "en", Some("America/New_York"));
+        assert_eq!(format_timestamp(&time, &bucharest), "[06 Mar 24, 01:30:00]");
+        assert_eq!(format_timestamp(&time, &new_york), "[05 Mar 24, 6:30:00 PM]");
+        assert_eq!(day_of(&time, &bucharest), NaiveDate::from_ymd_opt(2024, 3, 6).unwrap());
+        assert_eq!(day_of(&time, &new_york), NaiveDate::from_ymd_opt(2024, 3, 5).unwrap());
+
+        // Without an override the day is the local one, either side of midnight
+        let options = TimestampOptions::default();
+        assert_eq!(day_of(&local(2024, 3, 5, 23, 59, 59), &options), NaiveDate::from_ymd_opt(2024, 3, 5).unwrap());
+        assert_eq!(day_of(&local(2024, 3, 6, 0, 0, 0), &options), NaiveDate::from_ymd_opt(2024, 3, 6).unwrap());
+    }
+
+    #[test]
+    fn timezones_are_stored_by_their_canonical_name() {
+        assert_eq!(validate_timezone(Some(" europe/bucharest ".into())).unwrap(), Some("Europe/Bucharest".into()));
+        assert_eq!(validate_timezone(Some("UTC".into())).unwrap(), Some("UTC".into()));
+        assert_eq!(validate_timezone(Some("  ".into())).unwrap(), None);
+        assert_eq!(validate_timezone(None).unwrap(), None);
+        assert!(validate_timezone(Some("Mars/Olympus".into())).is_err());
+    }
+}
@@ -0,0 +1,206 @@
+use serde::{Deserialize, Serialize};
+
+// Plain-text cleanup for notes pasted from word processors. Each pass can be
+// turned off on its own; code fences and URLs are always left untouched.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct TypographyOptions {
+    // “curly” and ‘curly’ quotes become straight ones
+    pub straighten_quotes: bool,
+    // En dashes, minus signs and figure dashes become "-", em dashes "--"
+    pub normalize_dashes: bool,
+    pub strip_trailing_whitespace: bool,
+    // Runs of spaces inside a line become one; indentation is kept
+    pub collapse_spaces: bool,
+}
+
+impl Default for TypographyOptions {
+    fn default() -> Self {
+        TypographyOptions {
+            straighten_quotes: true,
+            normalize_dashes: true,
+            strip_trailing_whitespace: true,
+            collapse_spaces: true,
+        }
+    }
+}
+
+// Apply the enabled passes line by line, skipping fenced code blocks
+pub fn normalize_typography(text: &str, options: &TypographyOptions) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    let mut in_fence = false;
+    for line in text.split_inclusive('\n') {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            normalized.push_str(line);
+        } else if in_fence {
+            normalized.push_str(line);
+        } else {
+            let content = line.trim_end_matches(['\r', '\n']);
+            normalize_line(content, options, &mut normalized);
+            normalized.push_str(&line[content.len()..]);
+        }
+    }
+    normalized
+}
+
+fn normalize_line(line: &str, options: &TypographyOptions, out: &mut String) {
+    let start = out.len();
+    let indent_len = line.len() - line.trim_start().len();
+    out.push_str(&line[..indent_len]);
+
+    let mut rest = &line[indent_len..];
+    while !rest.is_empty() {
+        let token_len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        normalize_token(&rest[..token_len], options, out);
+        rest = &rest[token_len..];
+
+        let gap_len = rest.find(|c: char| !c.is_whitespace()).unwrap_or(rest.len());
+        let gap = &rest[..gap_len];
+        if options.collapse_spaces && !gap.is_empty() && gap.chars().all(|c| c == ' ') {
+            out.push(' ');
+        } else {
+            out.push_str(gap);
+        }
+        rest = &rest[gap_len..];
+    }
+
+    if options.strip_trailing_whitespace {
+        let kept = out[start..].trim_end().len();
+        out.truncate(start + kept);
+    }
+}
+
+fn is_url(token: &str) -> bool {
+    token.contains("://") || token.to_lowercase().starts_with("www.")
+}
+
+// Letters outside the Latin script, where quote characters can be part of the
+// orthography or the local quoting convention
+fn is_non_latin_letter(c: char) -> bool {
+    c.is_alphabetic()
+        && !c.is_ascii_alphabetic()
+        && !matches!(c, '\u{00C0}'..='\u{024F}' | '\u{1E00}'..='\u{1EFF}')
+}
+
+fn normalize_token(token: &str, options: &TypographyOptions, out: &mut String) {
+    if is_url(token) {
+        out.push_str(token);
+        return;
+    }
+
+    let chars: Vec<char> = token.chars().collect();
+    for (i, &c) in chars.iter().enumerate() {
+        match c {
+            '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{201F}' | '\u{2033}'
+                if options.straighten_quotes && !beside_non_latin(&chars, i) =>
+            {
+                out.push('"')
+            }
+            // Also covers apostrophes inside words: "don’t" becomes "don't"
+            '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}' | '\u{2032}'
+                if options.straighten_quotes && !beside_non_latin(&chars, i) =>
+            {
+                out.push('\'')
+            }
+            '\u{2012}' | '\u{2013}' | '\u{2212}' if options.normalize_dashes => out.push('-'),
+            '\u{2014}' | '\u{2015}' if options.normalize_dashes => out.push_str("--"),
+            _ => out.push(c),
+        }
+    }
+}
+
+// Whether the nearest letter on either side of `chars[i]` is non-Latin
+fn beside_non_latin(chars: &[char], i: usize) -> bool {
+    let before = chars[..i].iter().rev().find(|c| c.is_alphabetic());
+    let after = chars[i + 1..].iter().find(|c| c.is_alphabetic());
+    before.into_iter().chain(after).any(|&c| is_non_latin_letter(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn only(pass: &str) -> TypographyOptions {
+        TypographyOptions {
+            straighten_quotes: pass == "quotes",
+            normalize_dashes: pass == "dashes",
+            strip_trailing_whitespace: pass == "trailing",
+            collapse_spaces: pass == "spaces",
+        }
+    }
+
+    fn assert_table(pass: &str, cases: &[(&str, &str)]) {
+        for (input, expected) in cases {
+            assert_eq!(normalize_typography(input, &only(pass)), *expected, "{}: {:?}", pass, input);
+        }
+    }
+
+    #[test]
+    fn quotes_are_straightened() {
+        assert_table("quotes", &[
+            ("“quoted”", "\"quoted\""),
+            ("‘single’", "'single'"),
+            ("don’t, it’s", "don't, it's"),
+            ("„Anführung“", "\"Anführung\""),
+            ("a 5′ 3″ shelf", "a 5' 3\" shelf"),
+            // Quotes next to non-Latin letters are part of the text there
+            ("«Привет» and „Привет“", "«Привет» and „Привет“"),
+            ("Ukrainian м’ята", "Ukrainian м’ята"),
+            // Other passes stay off
+            ("“a” — b  ", "\"a\" — b  "),
+        ]);
+    }
+
+    #[test]
+    fn dashes_are_normalized() {
+        assert_table("dashes", &[
+            ("pages 3–5", "pages 3-5"),
+            ("wait—what", "wait--what"),
+            ("−4 degrees", "-4 degrees"),
+            ("call ‒ 555", "call - 555"),
+            ("a ― b", "a -- b"),
+            ("plain - hyphen", "plain - hyphen"),
+        ]);
+    }
+
+    #[test]
+    fn trailing_whitespace_is_stripped_per_line() {
+        assert_table("trailing", &[
+            ("line one   \nline two\t\n", "line one\nline two\n"),
+            ("windows  \r\nlines \r\n", "windows\r\nlines\r\n"),
+            ("   ", ""),
+            ("  indented", "  indented"),
+        ]);
+    }
+
+    #[test]
+    fn runs_of_spaces_collapse_but_indentation_stays() {
+        assert_table("spaces", &[
+            ("too    many   spaces", "too many spaces"),
+            ("    - indented  item", "    - indented item"),
+            ("keep\ttabs", "keep\ttabs"),
+            ("end   ", "end "),
+        ]);
+    }
+
+    #[test]
+    fn all_passes_together() {
+        let pasted = "“Draft” — v2   \n  don’t  ship   before 3–5 pm  \n";
+        assert_eq!(normalize_typography(pasted, &TypographyOptions::default()), "\"Draft\" -- v2\n  don't ship before 3-5 pm\n");
+    }
+
+    #[test]
+    fn code_fences_and_urls_are_left_alone() {
+        let options = TypographyOptions::default();
+        let note = "before  “x”\n```\nlet s = “x”;   \n  a  —  b  \n```\nafter  “x”";
+        assert_eq!(
+            normalize_typography(note, &options),
+            "before \"x\"\n```\nlet s = “x”;   \n  a  —  b  \n```\nafter \"x\""
+        );
+        assert_eq!(
+            normalize_typography("see https://example.com/a–b’s and www.example.com/“x”", &options),
+            "see https://example.com/a–b’s and www.example.com/“x”"
+        );
+    }
+}
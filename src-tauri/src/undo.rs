@@ -0,0 +1,227 @@
+use chrono::{DateTime, Utc};
+use tauri::{AppHandle, Manager, State};
+
+use crate::blocks::{self, NoteStyle};
+use crate::capture::api_token_for;
+use crate::config::AppState;
+use crate::error::AppError;
+use crate::events::{self, AppEvent};
+use crate::notion::{AppendResult, NotionApiClient};
+use crate::receipts;
+
+// Taking back the note just sent from the note window. Undo deletes its
+// blocks, which moves them to the page's trash in Notion; edit rewrites the
+// text of its first text block and keeps the timestamp. Only the newest note
+// can be changed, and only within the configured window. It is kept in
+// memory, so a restart ends the chance to undo or edit.
+
+#[derive(Debug, Clone)]
+pub struct LastAppend {
+    pub capture_id: String,
+    pub page_id: String,
+    // The profile it was sent with; changing it needs that profile's token
+    pub profile: Option<String>,
+    pub block_ids: Vec<String>,
+    pub appended_at: DateTime<Utc>,
+    // The timestamp text as sent, kept when the note is edited
+    pub timestamp: String,
+    // The note became a database row, which has no blocks to edit
+    pub row: bool,
+}
+
+// Remember a delivered note as the one to undo; held-back notes have nothing to delete
+pub fn remember(app: &AppHandle, result: &AppendResult, profile: Option<&str>, timestamp: &str, row: bool) {
+    if result.queued || result.block_ids.is_empty() {
+        return;
+    }
+    *app.state::<AppState>().last_append.lock().unwrap() = Some(LastAppend {
+        capture_id: result.capture_id.clone(),
+        page_id: result.page_id.clone(),
+        profile: profile.map(str::to_string),
+        block_ids: result.block_ids.clone(),
+        appended_at: DateTime::from_timestamp_millis(result.appended_at).unwrap_or_else(Utc::now),
+        timestamp: timestamp.to_string(),
+        row,
+    });
+}
+
+// The last note with a client for its token, if it is still recent enough to change
+fn recent_note(state: &AppState, action: &str) -> Result<(LastAppend, NotionApiClient, NoteStyle), String> {
+    let last = state.last_append.lock().unwrap().clone();
+    let last = last.ok_or_else(|| format!("There's no note to {}", action))?;
+    let (window_minutes, api_token, style) = {
+        let config = state.config.lock().unwrap();
+        let style = NoteStyle {
+            bold: config.note_bold,
+            color: blocks::note_color(&config.note_color),
+            tag_color: config.highlight_hashtags.then(|| blocks::note_color(&config.hashtag_color)),
+            ..Default::default()
+        };
+        (config.undo_window_minutes, api_token_for(&config, last.profile.as_deref())?, style)
+    };
+    if Utc::now() - last.appended_at > chrono::Duration::minutes(window_minutes.into()) {
+        return Err(format!(
+            "The last note was sent more than {} minutes ago and can't be {} anymore",
+            window_minutes,
+            if action == "undo" { "undone" } else { "edited" }
+        ));
+    }
+    Ok((last, NotionApiClient::new(api_token)?, style))
+}
+
+// Clear the note to undo, unless a newer one was sent meanwhile
+fn forget(state: &AppState, capture_id: &str) {
+    let mut last_append = state.last_append.lock().unwrap();
+    if last_append.as_ref().is_some_and(|last| last.capture_id == capture_id) {
+        *last_append = None;
+    }
+}
+
+// Delete the blocks of the last note sent, returning its capture id
+#[tauri::command]
+pub async fn undo_last_note(app: AppHandle, state: State<'_, AppState>) -> Result<String, String> {
+    let (last, client, _) = recent_note(&state, "undo")?;
+    for (index, block_id) in last.block_ids.iter().enumerate() {
+        let status = client.delete_block_status(block_id).await?;
+        if status.is_success() {
+            continue;
+        }
+        let problem = match status {
+            reqwest::StatusCode::NOT_FOUND => "the note is no longer in Notion; it may have been deleted already",
+            reqwest::StatusCode::CONFLICT => "the page was changed elsewhere since the note was sent",
+            _ => "Notion refused to delete it",
+        };
+        let removed = if index > 0 {
+            format!(" Part of it ({} of {} blocks) was removed.", index, last.block_ids.len())
+        } else {
+            String::new()
+        };
+        // Nothing to retry once part of the note is gone or it was removed elsewhere
+        if index > 0 || status == reqwest::StatusCode::NOT_FOUND {
+            forget(&state, &last.capture_id);
+        }
+        return Err(format!("The note couldn't be undone: {} (HTTP {}).{}", problem, status.as_u16(), removed));
+    }
+
+    forget(&state, &last.capture_id);
+    println!("Undid note {}", last.capture_id);
+    events::emit_app_event(&app, AppEvent::NoteUndone(last.capture_id.clone()));
+    Ok(last.capture_id)
+}
+
+// Replace the text of the last note sent, keeping its timestamp. A note that
+// became several blocks (one per line, or a list) only has its first text
+// block rewritten; the others stay as they are. Toggles and database rows
+// can't be edited here.
+#[tauri::command]
+pub async fn edit_last_note(new_text: String, state: State<'_, AppState>) -> Result<(), String> {
+    if new_text.trim().is_empty() {
+        return Err(AppError::ValidationError("The note can't be empty; undo it instead".into()).to_string());
+    }
+    let (last, client, style) = recent_note(&state, "edit")?;
+    if last.row {
+        return Err("Notes saved as database rows can't be edited here".into());
+    }
+
+    let deleted = || "The note was deleted in Notion and can't be edited".to_string();
+    for block_id in &last.block_ids {
+        let block = client.fetch_block(block_id).await?.ok_or_else(deleted)?;
+        if block["archived"].as_bool() == Some(true) || block["in_trash"].as_bool() == Some(true) {
+            return Err(deleted());
+        }
+        let block_type = block["type"].as_str().unwrap_or_default();
+        if block_type == "toggle" {
+            return Err("Toggle notes can't be edited here; change it in Notion".into());
+        }
+        let Some(runs) = block[block_type]["rich_text"].as_array() else {
+            continue;
+        };
+        // A timestamp on a paragraph of its own isn't the note
+        if runs.iter().all(|run| blocks::is_timestamp_run(run, &last.timestamp)) {
+            continue;
+        }
+        let rich_text = blocks::edited_runs(runs, &last.timestamp, &new_text, style);
+        if !client.update_block_text(block_id, block_type, rich_text).await? {
+            return Err(deleted());
+        }
+        if receipts::keeps_note_text() {
+            if let Err(e) = receipts::update_receipt(&last.capture_id, |receipt| receipt.note_text = Some(new_text.clone())) {
+                eprintln!("Couldn't update the history for an edited note: {}", e);
+            }
+        }
+        println!("Edited note {}", last.capture_id);
+        return Ok(());
+    }
+    Err("The last note has no text to edit".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AppConfig, CaptureProfile};
+    use crate::mock_notion::{MockNotion, MockReply};
+    use crate::tasks::TaskRegistry;
+    use std::sync::{Arc, Mutex};
+
+    fn state_with(config: AppConfig, last: LastAppend) -> AppState {
+        AppState {
+            config: Arc::new(Mutex::new(config)),
+            tasks: Arc::new(TaskRegistry::new()),
+            last_verification: Arc::new(Mutex::new(None)),
+            focus: Arc::new(Mutex::new(Default::default())),
+            pending_setup: Arc::new(Mutex::new(Vec::new())),
+            last_append: Arc::new(Mutex::new(Some(last))),
+        }
+    }
+
+    fn last_append(profile: Option<&str>) -> LastAppend {
+        LastAppend {
+            capture_id: "capture-1".into(),
+            page_id: "page-1".into(),
+            profile: profile.map(str::to_string),
+            block_ids: vec!["block-1".into()],
+            appended_at: Utc::now(),
+            timestamp: "[09:00]".into(),
+            row: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn a_note_is_changed_with_the_token_it_was_sent_with() {
+        let deleted = || MockReply::json(200, serde_json::json!({ "object": "block", "id": "block-1", "archived": true }));
+        let main = MockNotion::start(move |_| deleted()).await;
+        let work = MockNotion::start(move |_| deleted()).await;
+        let mut config = AppConfig::default();
+        config.capture_profiles.push(CaptureProfile { name: "work".into(), page_id: "page-1".into(), ..Default::default() });
+        config.secrets.set_main_token(main.token.clone());
+        config.secrets.set_profile_token("work", work.token.clone());
+
+        let state = state_with(config.clone(), last_append(Some("work")));
+        let (_, client, _) = recent_note(&state, "undo").unwrap();
+        client.delete_block_status("block-1").await.unwrap();
+        assert_eq!((work.count("DELETE", "/v1/blocks/"), main.count("DELETE", "/v1/blocks/")), (1, 0));
+
+        // Without a profile, or once the profile's token is gone, it's the main token
+        for profile in [None, Some("removed")] {
+            let state = state_with(config.clone(), last_append(profile));
+            let (_, client, _) = recent_note(&state, "undo").unwrap();
+            client.delete_block_status("block-1").await.unwrap();
+        }
+        assert_eq!((work.count("DELETE", "/v1/blocks/"), main.count("DELETE", "/v1/blocks/")), (1, 2));
+    }
+
+    #[test]
+    fn only_a_recent_note_can_be_changed() {
+        let mut config = AppConfig::default();
+        config.secrets.set_main_token("secret_main".into());
+        let mut old = last_append(None);
+        old.appended_at = Utc::now() - chrono::Duration::minutes(config.undo_window_minutes as i64 + 1);
+
+        let error = recent_note(&state_with(config.clone(), old), "edit").err().unwrap();
+        assert!(error.contains("can't be edited anymore"), "{}", error);
+
+        let state = state_with(config, last_append(None));
+        *state.last_append.lock().unwrap() = None;
+        assert_eq!(recent_note(&state, "undo").err().unwrap(), "There's no note to undo");
+    }
+}
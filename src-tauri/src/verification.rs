@@ -0,0 +1,301 @@
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, GlobalShortcutManager, Manager, State};
+
+use crate::config::{AppState, TargetKind};
+use crate::database_rows;
+use crate::notion::{NotionApiClient, PageAccess};
+use crate::rate_limit::{Priority, RATE_LIMITER};
+
+// Settings checks, run on demand from the settings window and periodically in
+// the background so a revoked token or archived page shows up before capture time
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Ok,
+    Warning,
+    Failed,
+    // Couldn't be checked right now (offline, rate limited, nothing configured)
+    Skipped,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CheckResult {
+    pub name: String,
+    pub status: CheckStatus,
+    pub message: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ValidationReport {
+    pub checks: Vec<CheckResult>,
+    // RFC3339 time the checks finished
+    pub checked_at: String,
+}
+
+impl ValidationReport {
+    pub fn failed_checks(&self) -> BTreeSet<String> {
+        self.checks
+            .iter()
+            .filter(|check| check.status == CheckStatus::Failed)
+            .map(|check| check.name.clone())
+            .collect()
+    }
+}
+
+lazy_static::lazy_static! {
+    // Checks the user has already been notified about; cleared per check once it recovers
+    static ref NOTIFIED_FAILURES: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+}
+
+// Which failures to notify about now, and the set to remember afterwards: a
+// check notifies once when it starts failing, and again only after it recovered
+pub fn failures_to_notify(notified: &BTreeSet<String>, failed: &BTreeSet<String>) -> (Vec<String>, BTreeSet<String>) {
+    let new_failures = failed.difference(notified).cloned().collect();
+    (new_failures, failed.clone())
+}
+
+fn check(name: &str, status: CheckStatus, message: impl Into<String>) -> CheckResult {
+    CheckResult {
+        name: name.to_string(),
+        status,
+        message: message.into(),
+    }
+}
+
+// Transport failures mean we couldn't ask, not that the setting is wrong
+fn is_offline_error(error: &str) -> bool {
+    error.starts_with("API request failed")
+}
+
+pub async fn validate(app: &AppHandle, priority: Priority) -> ValidationReport {
+    let (api_token, page_id, kind) = {
+        let state = app.state::<AppState>();
+        let config = state.config.lock().unwrap();
+        (config.secrets.main_token().to_string(), config.selected_page_id.clone(), config.selected_target_kind)
+    };
+
+    let mut checks = Vec::new();
+    let hotkey_registered = app.global_shortcut_manager()
+        .is_registered(crate::PRIMARY_HOTKEY)
+        .unwrap_or(false);
+    checks.push(if hotkey_registered {
+        check("hotkey", CheckStatus::Ok, format!("{} is registered", crate::PRIMARY_HOTKEY))
+    } else {
+        check("hotkey", CheckStatus::Failed, format!("{} is no longer registered", crate::PRIMARY_HOTKEY))
+    });
+
+    if api_token.is_empty() {
+        checks.push(check("token", CheckStatus::Skipped, "No API token set"));
+        return finish(checks);
+    }
+    if let Some(wait) = RATE_LIMITER.limited_for(&api_token) {
+        let message = format!("Rate limited for {}s", wait.as_secs().max(1));
+        checks.push(check("token", CheckStatus::Skipped, message.clone()));
+        checks.push(check("page", CheckStatus::Skipped, message));
+        return finish(checks);
+    }
+
+    let client = match NotionApiClient::new(api_token) {
+        Ok(client) => client.with_priority(priority),
+        Err(e) => {
+            checks.push(check("token", CheckStatus::Failed, e));
+            return finish(checks);
+        }
+    };
+
+    match client.verify_token().await {
+        Ok(true) => checks.push(check("token", CheckStatus::Ok, "Notion accepts the token")),
+        Ok(false) => {
+            checks.push(check("token", CheckStatus::Failed, "Notion rejected the token"));
+            return finish(checks);
+        }
+        Err(e) if is_offline_error(&e) => {
+            checks.push(check("token", CheckStatus::Skipped, "Offline"));
+            checks.push(check("page", CheckStatus::Skipped, "Offline"));
+            return finish(checks);
+        }
+        Err(e) => checks.push(check("token", CheckStatus::Failed, e)),
+    }
+
+    if page_id.is_empty() {
+        checks.push(check("page", CheckStatus::Skipped, "No page selected"));
+        return finish(checks);
+    }
+    if kind == TargetKind::Database {
+        let database_check = match database_rows::refresh_schema(&client, &page_id).await {
+            Ok(_) => check("page", CheckStatus::Ok, "The database accepts notes as new rows"),
+            Err(e) if is_offline_error(&e) => check("page", CheckStatus::Skipped, "Offline"),
+            Err(e) => check("page", CheckStatus::Failed, e),
+        };
+        checks.push(database_check);
+        return finish(checks);
+    }
+    let page_check = match client.fetch_page(&page_id).await {
+        Ok(None) => check("page", CheckStatus::Failed, "The page was deleted or is no longer shared"),
+        Ok(Some(page)) if page["archived"].as_bool() == Some(true) => {
+            check("page", CheckStatus::Failed, "The page is archived")
+        }
+        Ok(Some(_)) => match client.probe_page_access(&page_id).await {
+            Ok(PageAccess::ReadOnly) => check("page", CheckStatus::Warning, "The integration can only read this page"),
+            Ok(_) => check("page", CheckStatus::Ok, "The page accepts notes"),
+            Err(e) => check("page", CheckStatus::Skipped, e),
+        },
+        Err(e) if is_offline_error(&e) => check("page", CheckStatus::Skipped, "Offline"),
+        Err(e) => check("page", CheckStatus::Failed, e),
+    };
+    checks.push(page_check);
+
+    finish(checks)
+}
+
+fn finish(checks: Vec<CheckResult>) -> ValidationReport {
+    ValidationReport {
+        checks,
+        checked_at: chrono::Utc::now().to_rfc3339(),
+    }
+}
+
+// Store the report, refresh the tray and notify about checks that just started failing
+fn record(app: &AppHandle, report: &ValidationReport) {
+    *app.state::<AppState>().last_verification.lock().unwrap() = Some(report.clone());
+    crate::refresh_tray(app);
+
+    let new_failures = {
+        let mut notified = NOTIFIED_FAILURES.lock().unwrap();
+        let (new_failures, remembered) = failures_to_notify(&notified, &report.failed_checks());
+        *notified = remembered;
+        new_failures
+    };
+    for name in new_failures {
+        if let Some(result) = report.checks.iter().find(|check| check.name == name) {
+            crate::quiet_hours::notify_background(app, "Notion Quick Notes needs attention", &result.message);
+        }
+    }
+}
+
+// Background task: re-check settings every `verification_interval_hours` (0 disables)
+pub fn start_settings_verification(app: &AppHandle) {
+    let app_handle = app.clone();
+    let registry = app.state::<AppState>().tasks.clone();
+
+    registry.spawn("settings-verification", move |mut token| async move {
+        loop {
+            let hours = app_handle.state::<AppState>().config.lock().unwrap().verification_interval_hours;
+            // Re-read the setting hourly while disabled
+            let interval = Duration::from_secs(3600 * hours.max(1));
+
+            tokio::select! {
+                _ = token.cancelled() => break,
+                _ = tokio::time::sleep(interval) => {}
+            }
+
+            if !crate::quiet_hours::wait_until_loud(&mut token).await {
+                break;
+            }
+            if hours > 0 {
+                let report = validate(&app_handle, Priority::Background).await;
+                record(&app_handle, &report);
+            }
+        }
+    });
+}
+
+// Re-check in the background, e.g. once the network is back after a resume
+pub async fn recheck(app: &AppHandle) {
+    let report = validate(app, Priority::Background).await;
+    record(app, &report);
+}
+
+// Run every check now
+#[tauri::command]
+pub async fn validate_settings(app: AppHandle) -> Result<ValidationReport, String> {
+    let report = validate(&app, Priority::Interactive).await;
+    record(&app, &report);
+    // Setup is finished: send what was captured while it wasn't
+    if report.failed_checks().is_empty() && crate::check_settings_configured(&app) {
+        crate::setup_captures::resume(&app);
+    }
+    Ok(report)
+}
+
+// The most recent report, for "All good — checked 2h ago"
+#[tauri::command]
+pub fn get_last_verification(state: State<'_, AppState>) -> Option<ValidationReport> {
+    state.last_verification.lock().unwrap().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failed(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    // Runs reports with these failures through the notification logic; the names notified per run
+    fn notifications(runs: &[&[&str]]) -> Vec<Vec<String>> {
+        let mut notified = BTreeSet::new();
+        runs.iter()
+            .map(|run| {
+                let (new_failures, remembered) = failures_to_notify(&notified, &failed(run));
+                notified = remembered;
+                new_failures
+            })
+            .collect()
+    }
+
+    #[test]
+    fn a_failing_check_notifies_once_until_it_recovers() {
+        let none: Vec<String> = Vec::new();
+        assert_eq!(
+            notifications(&[&["token"], &["token"], &["token"], &[], &["token"]]),
+            vec![vec!["token".to_string()], none.clone(), none.clone(), none.clone(), vec!["token".to_string()]]
+        );
+    }
+
+    #[test]
+    fn each_check_is_tracked_on_its_own() {
+        let runs = notifications(&[&["page"], &["page", "token"], &["token"], &["page", "token"]]);
+        assert_eq!(runs[0], vec!["page"]);
+        assert_eq!(runs[1], vec!["token"]);
+        assert!(runs[2].is_empty());
+        assert_eq!(runs[3], vec!["page"]);
+    }
+
+    #[test]
+    fn only_failed_checks_count_as_failures() {
+        let report = finish(vec![
+            check("hotkey", CheckStatus::Ok, "Alt+Q is registered"),
+            check("token", CheckStatus::Skipped, "Offline"),
+            check("page", CheckStatus::Warning, "The integration can only read this page"),
+        ]);
+        assert!(report.failed_checks().is_empty());
+
+        let report = finish(vec![check("hotkey", CheckStatus::Failed, "Alt+Q is no longer registered")]);
+        assert_eq!(report.failed_checks(), failed(&["hotkey"]));
+    }
+
+    #[test]
+    fn reports_are_stamped_when_the_checks_finish() {
+        let before = chrono::Utc::now();
+        let first = finish(Vec::new());
+        std::thread::sleep(Duration::from_millis(5));
+        let second = finish(Vec::new());
+
+        let first_at = chrono::DateTime::parse_from_rfc3339(&first.checked_at).unwrap();
+        let second_at = chrono::DateTime::parse_from_rfc3339(&second.checked_at).unwrap();
+        assert!(first_at >= before && first_at <= chrono::Utc::now());
+        // A later run replaces the "checked … ago" time
+        assert!(second_at > first_at);
+    }
+
+    #[test]
+    fn transport_failures_are_skipped_as_offline() {
+        assert!(is_offline_error("API request failed: error sending request"));
+        assert!(!is_offline_error("Notion API error: 404 object_not_found"));
+    }
+}
@@ -0,0 +1,247 @@
+use reqwest::Client;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::config::AppState;
+use crate::error::AppError;
+use crate::receipts::DeliverySource;
+use crate::retry;
+
+// Optional mirror of delivered captures to a webhook (e.g. a team service).
+// Runs after Notion confirmed the delivery, in the background: whatever the
+// webhook does, the capture's result has already been decided.
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Serialized payloads above this are dropped rather than sent
+pub const MAX_PAYLOAD_BYTES: usize = 64 * 1024;
+
+// Header carrying the configured shared secret
+pub const SECRET_HEADER: &str = "X-Mirror-Secret";
+
+#[derive(Serialize, Debug, Clone)]
+pub struct MirrorPayload {
+    pub text: String,
+    pub captured_at: String,
+    pub page_id: String,
+    pub page_title: String,
+    pub source: DeliverySource,
+    pub block_ids: Vec<String>,
+}
+
+// Mirror outcomes for diagnostics
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct MirrorStats {
+    pub delivered: u64,
+    pub failed: u64,
+    pub last_error: Option<String>,
+}
+
+static DELIVERED: AtomicU64 = AtomicU64::new(0);
+static FAILED: AtomicU64 = AtomicU64::new(0);
+
+lazy_static::lazy_static! {
+    static ref LAST_ERROR: Mutex<Option<String>> = Mutex::new(None);
+    static ref WEBHOOK_CLIENT: Client = Client::builder()
+        .timeout(WEBHOOK_TIMEOUT)
+        .build()
+        .unwrap_or_default();
+}
+
+pub fn stats() -> MirrorStats {
+    MirrorStats {
+        delivered: DELIVERED.load(Ordering::Relaxed),
+        failed: FAILED.load(Ordering::Relaxed),
+        last_error: LAST_ERROR.lock().unwrap().clone(),
+    }
+}
+
+// HTTPS only, unless plain HTTP was explicitly allowed (e.g. for a service on localhost)
+pub fn validate_webhook_url(url: &str, allow_insecure: bool) -> Result<(), AppError> {
+    let parsed = reqwest::Url::parse(url)
+        .map_err(|e| AppError::ValidationError(format!("Invalid webhook URL: {}", e)))?;
+    match parsed.scheme() {
+        "https" => Ok(()),
+        "http" if allow_insecure => Ok(()),
+        "http" => Err(AppError::ValidationError(
+            "The webhook must use https unless allow_insecure_webhook is set".into(),
+        )),
+        scheme => Err(AppError::ValidationError(format!("Unsupported webhook scheme '{}'", scheme))),
+    }
+}
+
+async fn post(url: &str, secret: Option<&str>, body: &str) -> Result<(), String> {
+    let mut request = WEBHOOK_CLIENT
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body.to_string());
+    if let Some(secret) = secret {
+        request = request.header(SECRET_HEADER, secret);
+    }
+    let res = request.send().await.map_err(|e| format!("Webhook request failed: {}", e))?;
+    if res.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("Webhook returned {}", res.status()))
+    }
+}
+
+fn record_failure(error: String) {
+    eprintln!("Mirror webhook failed: {}", error);
+    FAILED.fetch_add(1, Ordering::Relaxed);
+    *LAST_ERROR.lock().unwrap() = Some(error);
+}
+
+// Send with retries (see retry::WEBHOOK_RETRY); 4xx answers other than 429 aren't retried
+pub async fn deliver(url: &str, secret: Option<&str>, payload: &MirrorPayload) -> Result<(), String> {
+    let body = serde_json::to_string(payload).map_err(|e| format!("Failed to serialize mirror payload: {}", e))?;
+    if body.len() > MAX_PAYLOAD_BYTES {
+        return Err(format!("Mirror payload is {} bytes, over the {} byte limit", body.len(), MAX_PAYLOAD_BYTES));
+    }
+
+    retry::retry_async(
+        &retry::WEBHOOK_RETRY,
+        None,
+        |retry| eprintln!("Mirror webhook attempt {} failed, retrying in {:?}: {}", retry.attempt, retry.delay, retry.error),
+        |_| post(url, secret, &body),
+    )
+    .await
+    .result
+}
+
+// Post-delivery hook: mirror the capture if a webhook is configured. Never
+// blocks or fails the caller; outcomes only show up in diagnostics.
+pub fn mirror_delivery(app: &AppHandle, payload: MirrorPayload) {
+    if !crate::safe_mode::enabled(crate::safe_mode::Subsystem::MirrorWebhook) {
+        return;
+    }
+    let (url, secret, allow_insecure) = {
+        let state = app.state::<AppState>();
+        let config = state.config.lock().unwrap();
+        (
+            config.mirror_webhook_url.clone(),
+            config.secrets.webhook_secret().map(str::to_string),
+            config.allow_insecure_webhook,
+        )
+    };
+    let Some(url) = url.filter(|url| !url.is_empty()) else {
+        return;
+    };
+    if let Err(e) = validate_webhook_url(&url, allow_insecure) {
+        record_failure(e.to_string());
+        return;
+    }
+
+    tauri::async_runtime::spawn(async move {
+        mirror(&url, secret.as_deref().filter(|secret| !secret.is_empty()), &payload).await;
+    });
+}
+
+// Deliver and count the outcome; failures end here
+async fn mirror(url: &str, secret: Option<&str>, payload: &MirrorPayload) {
+    match deliver(url, secret, payload).await {
+        Ok(()) => {
+            DELIVERED.fetch_add(1, Ordering::Relaxed);
+        }
+        Err(e) => record_failure(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_notion::{MockNotion, MockReply};
+    use serde_json::json;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+
+    fn payload(text: &str) -> MirrorPayload {
+        MirrorPayload {
+            text: text.to_string(),
+            captured_at: "2026-05-04T09:30:00Z".to_string(),
+            page_id: "page-1".to_string(),
+            page_title: "Inbox".to_string(),
+            source: DeliverySource::Raw,
+            block_ids: vec!["block-1".to_string()],
+        }
+    }
+
+    #[test]
+    fn plain_http_needs_allow_insecure() {
+        assert!(validate_webhook_url("https://hooks.example.com/notes", false).is_ok());
+        assert!(validate_webhook_url("http://localhost:8080/notes", true).is_ok());
+        let error = validate_webhook_url("http://localhost:8080/notes", false).unwrap_err().to_string();
+        assert!(error.contains("allow_insecure_webhook"), "{}", error);
+        assert!(validate_webhook_url("ftp://example.com/notes", true).is_err());
+        assert!(validate_webhook_url("not a url", true).is_err());
+    }
+
+    #[tokio::test]
+    async fn the_payload_and_secret_are_posted() {
+        let mock = MockNotion::start(|_| MockReply::json(200, json!({}))).await;
+        deliver(&mock.url("/mirror"), Some("shared"), &payload("Buy milk")).await.unwrap();
+
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!((requests[0].method.as_str(), requests[0].path.as_str()), ("POST", "/mirror"));
+        assert_eq!(requests[0].headers.get("x-mirror-secret").map(String::as_str), Some("shared"));
+        assert_eq!(requests[0].headers.get("content-type").map(String::as_str), Some("application/json"));
+        assert_eq!(
+            requests[0].body,
+            Some(json!({
+                "text": "Buy milk",
+                "captured_at": "2026-05-04T09:30:00Z",
+                "page_id": "page-1",
+                "page_title": "Inbox",
+                "source": "raw",
+                "block_ids": ["block-1"],
+            }))
+        );
+
+        deliver(&mock.url("/mirror"), None, &payload("No secret")).await.unwrap();
+        assert!(!mock.requests()[1].headers.contains_key("x-mirror-secret"));
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_retried_but_client_errors_are_not() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let seen = calls.clone();
+        let mock = MockNotion::start(move |_| match seen.fetch_add(1, Ordering::SeqCst) {
+            0 => MockReply::json(503, json!({})),
+            _ => MockReply::json(200, json!({})),
+        })
+        .await;
+        deliver(&mock.url("/mirror"), None, &payload("Buy milk")).await.unwrap();
+        assert_eq!(mock.count("POST", "/mirror"), 2);
+
+        let mock = MockNotion::start(|_| MockReply::json(404, json!({}))).await;
+        let error = deliver(&mock.url("/mirror"), None, &payload("Buy milk")).await.unwrap_err();
+        assert!(error.contains("404"), "{}", error);
+        assert_eq!(mock.count("POST", "/mirror"), 1);
+    }
+
+    #[tokio::test]
+    async fn oversized_payloads_are_not_sent() {
+        let mock = MockNotion::start(|_| MockReply::json(200, json!({}))).await;
+        let error = deliver(&mock.url("/mirror"), None, &payload(&"x".repeat(MAX_PAYLOAD_BYTES))).await.unwrap_err();
+        assert!(error.contains("over the"), "{}", error);
+        assert!(mock.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn a_failing_webhook_is_only_counted() {
+        let before = stats();
+        let mock = MockNotion::start(|_| MockReply::json(200, json!({}))).await;
+        mirror(&mock.url("/mirror"), None, &payload("Buy milk")).await;
+        let mock = MockNotion::start(|_| MockReply::json(401, json!({}))).await;
+        mirror(&mock.url("/mirror"), None, &payload("Buy milk")).await;
+
+        let after = stats();
+        assert_eq!(after.delivered - before.delivered, 1);
+        assert_eq!(after.failed - before.failed, 1);
+        assert_eq!(after.last_error.as_deref(), Some("Webhook returned 401 Unauthorized"));
+    }
+}
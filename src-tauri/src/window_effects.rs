@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::Window;
+
+// Backdrop effect for the note window
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WindowEffect {
+    #[default]
+    None,
+    // Acrylic blur on Windows 11; translucent background elsewhere
+    Acrylic,
+    // Mica on Windows 11, vibrancy on macOS; translucent background on Linux
+    Vibrancy,
+}
+
+// What was asked for versus what the note window actually got, for diagnostics
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct EffectStatus {
+    pub requested: WindowEffect,
+    pub applied: WindowEffect,
+    pub fallback_reason: Option<String>,
+}
+
+lazy_static::lazy_static! {
+    static ref EFFECT_STATUS: Mutex<EffectStatus> = Mutex::new(EffectStatus::default());
+}
+
+// Which effects this platform can attempt at all
+fn platform_supports(effect: WindowEffect) -> bool {
+    match effect {
+        WindowEffect::None => true,
+        _ => cfg!(any(windows, target_os = "linux")),
+    }
+}
+
+// Requested vs supported -> what to apply; unsupported effects fall back to none
+pub fn resolve_effect(requested: WindowEffect, supported: impl Fn(WindowEffect) -> bool) -> WindowEffect {
+    if supported(requested) {
+        requested
+    } else {
+        WindowEffect::None
+    }
+}
+
+// The effect a newly created note window should get. The window must be
+// transparent for any effect to show, so builders check this first.
+pub fn effect_for_new_window(requested: WindowEffect) -> WindowEffect {
+    resolve_effect(requested, platform_supports)
+}
+
+#[cfg(windows)]
+fn apply_platform_effect(window: &Window, effect: WindowEffect) -> Result<(), String> {
+    use windows_sys::Win32::Graphics::Dwm::{
+        DwmSetWindowAttribute, DWMSBT_MAINWINDOW, DWMSBT_TRANSIENTWINDOW, DWMWA_SYSTEMBACKDROP_TYPE,
+    };
+
+    let backdrop = match effect {
+        WindowEffect::None => return Ok(()),
+        WindowEffect::Acrylic => DWMSBT_TRANSIENTWINDOW,
+        WindowEffect::Vibrancy => DWMSBT_MAINWINDOW,
+    };
+    let hwnd = window.hwnd().map_err(|e| e.to_string())?;
+
+    // Fails on Windows 10 and early Windows 11 builds, which lack system backdrops
+    let result = unsafe {
+        DwmSetWindowAttribute(
+            hwnd.0 as _,
+            DWMWA_SYSTEMBACKDROP_TYPE as u32,
+            &backdrop as *const _ as *const std::ffi::c_void,
+            std::mem::size_of_val(&backdrop) as u32,
+        )
+    };
+    if result < 0 {
+        return Err(format!("System backdrops unavailable (HRESULT 0x{:08x})", result));
+    }
+    Ok(())
+}
+
+// Linux compositors have no common blur API; the window is simply transparent
+// and the frontend draws a semi-transparent background
+#[cfg(target_os = "linux")]
+fn apply_platform_effect(_window: &Window, _effect: WindowEffect) -> Result<(), String> {
+    Ok(())
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+fn apply_platform_effect(_window: &Window, effect: WindowEffect) -> Result<(), String> {
+    match effect {
+        WindowEffect::None => Ok(()),
+        _ => Err("Window effects aren't supported on this platform yet".into()),
+    }
+}
+
+// Apply the effect to a freshly built window and record the outcome
+pub fn apply(window: &Window, requested: WindowEffect) -> WindowEffect {
+    let resolved = effect_for_new_window(requested);
+    let (applied, fallback_reason) = match apply_platform_effect(window, resolved) {
+        Ok(()) if resolved == requested => (resolved, None),
+        Ok(()) => (resolved, Some("Not supported on this platform".to_string())),
+        Err(e) => {
+            eprintln!("Falling back to no window effect: {}", e);
+            (WindowEffect::None, Some(e))
+        }
+    };
+
+    *EFFECT_STATUS.lock().unwrap() = EffectStatus {
+        requested,
+        applied,
+        fallback_reason,
+    };
+    applied
+}
+
+pub fn effect_status() -> EffectStatus {
+    EFFECT_STATUS.lock().unwrap().clone()
+}
+
+// The effect the note window is showing, so the frontend can pick a matching background
+#[tauri::command]
+pub fn get_window_effect() -> WindowEffect {
+    effect_status().applied
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [WindowEffect; 3] = [WindowEffect::None, WindowEffect::Acrylic, WindowEffect::Vibrancy];
+
+    #[test]
+    fn supported_effects_are_applied_as_requested() {
+        for requested in ALL {
+            assert_eq!(resolve_effect(requested, |_| true), requested);
+        }
+    }
+
+    #[test]
+    fn unsupported_effects_fall_back_to_none() {
+        let only_acrylic = |effect| effect != WindowEffect::Vibrancy;
+        assert_eq!(resolve_effect(WindowEffect::Vibrancy, only_acrylic), WindowEffect::None);
+        assert_eq!(resolve_effect(WindowEffect::Acrylic, only_acrylic), WindowEffect::Acrylic);
+        for requested in ALL {
+            assert_eq!(resolve_effect(requested, |_| false), WindowEffect::None);
+        }
+    }
+
+    #[test]
+    fn new_windows_get_what_this_platform_supports() {
+        assert_eq!(effect_for_new_window(WindowEffect::None), WindowEffect::None);
+        let expected = |effect| if cfg!(any(windows, target_os = "linux")) { effect } else { WindowEffect::None };
+        assert_eq!(effect_for_new_window(WindowEffect::Acrylic), expected(WindowEffect::Acrylic));
+        assert_eq!(effect_for_new_window(WindowEffect::Vibrancy), expected(WindowEffect::Vibrancy));
+    }
+
+    #[test]
+    fn the_setting_is_stored_in_lowercase() {
+        assert_eq!(serde_json::to_value(WindowEffect::Acrylic).unwrap(), "acrylic");
+        assert_eq!(serde_json::from_value::<WindowEffect>("vibrancy".into()).unwrap(), WindowEffect::Vibrancy);
+        assert!(serde_json::from_value::<WindowEffect>("mica".into()).is_err());
+    }
+}
@@ -1,10 +1,38 @@
 import { useState, useEffect, useMemo, useCallback, memo, lazy, Suspense } from "react";
+import { invoke } from "@tauri-apps/api/tauri";
+import { listen } from "@tauri-apps/api/event";
 import "./App.css";
 // Lazy load components instead of importing them directly
 const NoteInput = lazy(() => import("./components/NoteInput"));
 const Settings = lazy(() => import("./components/Settings"));
 const About = lazy(() => import("./components/About"));
 
+interface AccessibilityPrefs {
+  reduced_motion: boolean;
+  high_contrast: boolean;
+  screen_reader_mode: boolean;
+}
+
+// Mirror the OS accessibility settings onto the root element so CSS can react
+const applyAccessibilityPrefs = (prefs: AccessibilityPrefs) => {
+  document.documentElement.setAttribute('data-reduced-motion', String(prefs.reduced_motion));
+  document.documentElement.setAttribute('data-high-contrast', String(prefs.high_contrast));
+};
+
+// Backend events the frontend listens for; checked against the backend's catalog in dev builds
+const LISTENED_EVENTS = ['accessibility-changed', 'prefill-note', 'file-dropped', 'note-progress', 'system-resumed', 'page-display-updated'];
+
+// Warn about listeners for events the backend never emits, e.g. after a rename
+const checkEventListeners = () => {
+  invoke<{ name: string }[]>('get_event_catalog')
+    .then(catalog => {
+      const known = new Set(catalog.map(event => event.name));
+      LISTENED_EVENTS.filter(name => !known.has(name))
+        .forEach(name => console.warn(`Listening for unknown backend event "${name}"`));
+    })
+    .catch(err => console.error('Failed to get event catalog:', err));
+};
+
 // Loading fallback component
 const LoadingFallback = () => (
   <div className="loading-container">
@@ -58,11 +86,23 @@ const App: React.FC = () => {
     // Initial view check
     checkWindow();
     
+    if (import.meta.env.DEV) {
+      checkEventListeners();
+    }
+    
+    invoke<AccessibilityPrefs>('get_accessibility_prefs')
+      .then(applyAccessibilityPrefs)
+      .catch(err => console.error('Failed to get accessibility prefs:', err));
+    const unlistenAccessibility = listen<AccessibilityPrefs>('accessibility-changed', (event) => {
+      applyAccessibilityPrefs(event.payload);
+    });
+    
     // Also check window params when URL changes
     window.addEventListener('popstate', checkWindow);
     
     return () => {
       window.removeEventListener('popstate', checkWindow);
+      unlistenAccessibility.then(fn => fn());
     };
   }, [checkWindow]);
 
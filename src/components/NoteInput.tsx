@@ -1,19 +1,134 @@
 import { useState, useEffect, useRef, useCallback, memo } from 'react';
 import { invoke } from '@tauri-apps/api/tauri';
+import { listen } from '@tauri-apps/api/event';
 import useApiWorker from '../worker/useApiWorker';
 import './NoteInput.css';
 
+type CaptureBanner =
+  | { kind: 'none' }
+  | { kind: 'offline'; message: string }
+  | { kind: 'rate_limited'; retry_in_secs: number }
+  | { kind: 'sync_paused' };
+
+// Placeholder text telling the user whether their note will be delayed
+const placeholderFor = (banner: CaptureBanner): string => {
+  switch (banner.kind) {
+    case 'offline':
+      return `Offline - your note ${banner.message}...`;
+    case 'rate_limited':
+      return `Rate limited - your note will be sent in about ${banner.retry_in_secs}s...`;
+    case 'sync_paused':
+      return 'Sync paused - your note will be sent once the Notion token is updated in Settings...';
+    default:
+      return 'Type your note here...';
+  }
+};
+
+type DroppedFile =
+  | { kind: 'text'; path: string; text: string; lossy: boolean }
+  | { kind: 'import_offer'; path: string; size_bytes: number };
+
+interface DeliveryEstimate {
+  id: string;
+  position: number;
+  estimated_delivery_at: string | null;
+  estimate: 'estimated' | 'unknown';
+}
+
+interface AppendRetry {
+  id: string;
+  attempt: number;
+  max_attempts: number;
+  retry_in_ms: number;
+  reason: 'conflict' | 'server_error' | 'connection_lost' | 'unreachable';
+}
+
+type NoteProgress = ({ stage: 'queued' } & DeliveryEstimate) | ({ stage: 'retrying' } & AppendRetry);
+
+// Status text while a note is being sent, naming the retry if Notion pushed back
+const sendingLabel = (retry: AppendRetry | null): string => {
+  if (!retry) {
+    return 'Sending...';
+  }
+  const seconds = Math.max(1, Math.round(retry.retry_in_ms / 1000));
+  const cause = retry.reason === 'conflict' ? 'Notion is busy' : 'Notion didn\'t answer';
+  return `${cause} - retrying in ${seconds}s (${retry.attempt + 1}/${retry.max_attempts})...`;
+};
+
+// Status text for a queued note: its place in line and when it should go out
+const queuedLabel = (estimate: DeliveryEstimate | null): string => {
+  if (!estimate) {
+    return 'Queued - will send when online.';
+  }
+  if (estimate.estimate === 'unknown' || !estimate.estimated_delivery_at) {
+    return `Queued (#${estimate.position}) - delivery time unknown.`;
+  }
+  const seconds = Math.max(0, Math.round((Date.parse(estimate.estimated_delivery_at) - Date.now()) / 1000));
+  return `Queued (#${estimate.position}) - sending in about ${seconds}s.`;
+};
+
+// The error the backend reports with code PAGE_FULL, for the page it was meant for
+interface PageFull {
+  code: 'PAGE_FULL';
+  message: string;
+  details: string | null;
+  page_id: string;
+  page_title: string;
+}
+
+interface PageDisplay {
+  page_id: string;
+  title: string;
+  icon: string | null;
+  resolved: boolean;
+}
+
+interface CaptureTarget {
+  profile: string | null;
+  page_title: string;
+  spellcheck: { enabled: boolean; languages: string[] };
+}
+
+interface ResolvedDestination {
+  profile: string | null;
+  page_id: string;
+  page_title: string;
+  rule: 'sandbox' | 'override' | 'route_prefix' | 'pinned' | 'default';
+  scratch: boolean;
+}
+
+// Titlebar subtitle saying where the note will go
+const destinationLabel = (destination: ResolvedDestination | null): string => {
+  if (!destination) {
+    return 'Quick Note';
+  }
+  if (destination.scratch) {
+    return 'Quick Note → scratch buffer';
+  }
+  return destination.rule === 'default' ? 'Quick Note' : `Quick Note → ${destination.page_title}`;
+};
+
 const NoteInput: React.FC = () => {
   const [note, setNote] = useState('');
+  const [target, setTarget] = useState<CaptureTarget | null>(null);
+  const [destination, setDestination] = useState<ResolvedDestination | null>(null);
+  const [banner, setBanner] = useState<CaptureBanner>({ kind: 'none' });
   const [error, setError] = useState<string | null>(null);
+  const [dropNotice, setDropNotice] = useState<string | null>(null);
+  // Scratch captures are buffered locally and sent later as one block group
+  const [scratchMode, setScratchMode] = useState(false);
   const [isOffline, setIsOffline] = useState(!navigator.onLine);
-  const [sendStatus, setSendStatus] = useState<null | 'sending' | 'sent'>(null);
+  const [sendStatus, setSendStatus] = useState<null | 'sending' | 'sent' | 'queued' | 'removed'>(null);
+  const [queuedEstimate, setQueuedEstimate] = useState<DeliveryEstimate | null>(null);
+  const [sendRetry, setSendRetry] = useState<AppendRetry | null>(null);
   const [darkMode, setDarkMode] = useState(() => {
     // Check if user previously had dark mode enabled
     const savedTheme = localStorage.getItem('theme');
     return savedTheme === 'dark';
   });
   const textareaRef = useRef<HTMLTextAreaElement>(null);
+  // Set by the page-full event that precedes the failed append_note call
+  const pageFullRef = useRef<PageFull | null>(null);
   
   // Get the API worker
   const { sendMessage } = useApiWorker();
@@ -29,36 +144,184 @@ const NoteInput: React.FC = () => {
     }
   }, [darkMode]);
   
+  // Let the backend know about unsent text, for its window-shown events and so
+  // the draft survives if this webview has to be rebuilt
+  const hasDraft = note.trim() !== '';
+  useEffect(() => {
+    const timer = setTimeout(() => {
+      invoke('report_note_draft', { hasDraft, text: hasDraft ? note : null }).catch(() => {});
+    }, hasDraft ? 500 : 0);
+    return () => clearTimeout(timer);
+  }, [hasDraft, note]);
+  
+  // Resolve the destination as the user types, so route prefixes show where the note will land
+  useEffect(() => {
+    const timer = setTimeout(() => {
+      invoke<ResolvedDestination>('resolve_destination', {
+        noteText: note,
+        context: { pinned_profile: target?.profile ?? null, scratch: scratchMode }
+      })
+        .then(setDestination)
+        .catch(() => setDestination(null));
+    }, 150);
+    return () => clearTimeout(timer);
+  }, [note, target, scratchMode]);
+  
+  // Large clipboard captures arrive pre-filled so they can be reviewed before sending
+  useEffect(() => {
+    const unlisten = listen<string>('prefill-note', (event) => {
+      setNote(event.payload);
+      textareaRef.current?.focus();
+    });
+    
+    return () => {
+      unlisten.then(fn => fn());
+    };
+  }, []);
+  
+  // Small text files dropped on the window replace the note; large ones are only offered for import
+  useEffect(() => {
+    const unlisten = listen<DroppedFile>('file-dropped', (event) => {
+      const dropped = event.payload;
+      if (dropped.kind === 'text') {
+        setNote(dropped.text);
+        setDropNotice(dropped.lossy ? 'Some characters in the file couldn\'t be read and were replaced.' : null);
+        textareaRef.current?.focus();
+      } else {
+        const kb = Math.round(dropped.size_bytes / 1024);
+        setDropNotice(`That file is ${kb} KB - too large to paste as one note. Import it instead.`);
+      }
+    });
+    
+    return () => {
+      unlisten.then(fn => fn());
+    };
+  }, []);
+  
+  // Estimates move when Notion rate limits us; follow the note we just queued
+  useEffect(() => {
+    const unlisten = listen<NoteProgress>('note-progress', (event) => {
+      const progress = event.payload;
+      if (progress.stage === 'retrying') {
+        setSendRetry(progress);
+        return;
+      }
+      setQueuedEstimate(current => (current && current.id === progress.id ? progress : current));
+    });
+    
+    return () => {
+      unlisten.then(fn => fn());
+    };
+  }, []);
+  
+  // A page shown by its shortened id got its title; relabel the destination
+  useEffect(() => {
+    const unlisten = listen<PageDisplay>('page-display-updated', (event) => {
+      const page = event.payload;
+      const label = page.icon ? `${page.icon} ${page.title}` : page.title;
+      setDestination(current => (current && current.page_id === page.page_id ? { ...current, page_title: label } : current));
+    });
+    
+    return () => {
+      unlisten.then(fn => fn());
+    };
+  }, []);
+  
+  // After sleep the browser's online flag and the banner can both be stale
+  useEffect(() => {
+    const unlisten = listen<number>('system-resumed', () => {
+      setIsOffline(!navigator.onLine);
+      invoke<CaptureBanner>('get_capture_banner', { online: navigator.onLine })
+        .then(setBanner)
+        .catch(() => setBanner({ kind: 'none' }));
+    });
+    
+    return () => {
+      unlisten.then(fn => fn());
+    };
+  }, []);
+  
+  // Rate limits and token problems change while the window is open
+  useEffect(() => {
+    const unlisten = listen<CaptureBanner>('capture-banner-changed', (event) => setBanner(event.payload));
+    
+    return () => {
+      unlisten.then(fn => fn());
+    };
+  }, []);
+  
+  // A full page is reported with a structured code instead of a message to match on
+  useEffect(() => {
+    const unlisten = listen<PageFull>('page-full', (event) => {
+      pageFullRef.current = event.payload;
+    });
+    
+    return () => {
+      unlisten.then(fn => fn());
+    };
+  }, []);
+  
+  // Undo from the tray (or Ctrl+Z below) removed the note again
+  useEffect(() => {
+    const unlisten = listen<string>('note-undone', () => setSendStatus('removed'));
+    
+    return () => {
+      unlisten.then(fn => fn());
+    };
+  }, []);
+  
+  // While "Sent." shows, Ctrl+Z takes the note back out of Notion
+  useEffect(() => {
+    if (sendStatus !== 'sent') {
+      return;
+    }
+    const handleUndoKey = (e: KeyboardEvent) => {
+      if (e.key === 'z' && e.ctrlKey) {
+        e.preventDefault();
+        invoke('undo_last_note').catch(err => setError(String(err)));
+      }
+    };
+    window.addEventListener('keydown', handleUndoKey);
+    return () => window.removeEventListener('keydown', handleUndoKey);
+  }, [sendStatus]);
+  
   // Memoize handlers to prevent recreation on every render
   const handleSave = useCallback(async () => {
     if (!note.trim()) {
       return;
     }
     
-    if (isOffline) {
-      setError('You appear to be offline. Can\'t save note at the moment.');
-      return;
-    }
-    
     setSendStatus('sending');
+    setSendRetry(null);
     setError(null);
+    pageFullRef.current = null;
     
     try {
-      // Use the worker instead of direct invoke
-      await sendMessage('appendNote', { noteText: note });
+      // Offline notes are queued and delivered in the background once we're back online
+      if (isOffline && !scratchMode) {
+        await sendMessage('queueNote', { noteText: note, profile: target?.profile ?? null });
+        invoke<{ estimate: DeliveryEstimate }[]>('get_pending_notes')
+          .then(pending => setQueuedEstimate(pending.length > 0 ? pending[pending.length - 1].estimate : null))
+          .catch(() => setQueuedEstimate(null));
+      } else {
+        await sendMessage('appendNote', { noteText: note, profile: target?.profile ?? null, scratch: scratchMode });
+      }
       
       setNote('');
-      setSendStatus('sent');
+      setSendStatus(isOffline && !scratchMode ? 'queued' : 'sent');
       
       // Clear the sent status after 2 seconds and close window
       setTimeout(() => {
         setSendStatus(null);
-        handleCancel(); // Close the window after showing sent message
+        invoke('close_note_input', { reason: 'capture-complete' }); // Close the window after showing sent message
       }, 2000);
     } catch (err) {
       const errorMsg = err instanceof Error ? err.message : String(err);
+      const pageFull = pageFullRef.current;
       
-      if (errorMsg.includes('network')) {
+      if (pageFull) {
+        setError(`"${pageFull.page_title}" is full. ${pageFull.details ?? ''}`.trim());
+      } else if (errorMsg.includes('network')) {
         setError('Network error: Please check your internet connection and try again.');
       } else if (errorMsg.includes('token')) {
         setError('API token error: Please go to Settings and verify your Notion API token.');
@@ -69,7 +332,7 @@ const NoteInput: React.FC = () => {
       }
       setSendStatus(null);
     }
-  }, [note, isOffline, sendMessage]);
+  }, [note, target, scratchMode, isOffline, sendMessage]);
   
   const handleCancel = useCallback(() => {
     invoke('close_note_input');
@@ -111,16 +374,45 @@ const NoteInput: React.FC = () => {
       }
     };
     
-    // Add online/offline event listeners
-    const handleOnline = () => setIsOffline(false);
-    const handleOffline = () => setIsOffline(true);
+    // The window is preloaded hidden and reused, so refocus the input and
+    // report readiness every time it is shown rather than only on mount
+    const handleFocus = () => {
+      textareaRef.current?.focus();
+      invoke('window_ready').catch(() => {});
+      // A profile hotkey pins the destination for this capture only
+      invoke<CaptureTarget>('get_capture_target')
+        .then(setTarget)
+        .catch(() => setTarget(null));
+      invoke<string>('get_window_effect')
+        .then(effect => document.documentElement.setAttribute('data-effect', effect))
+        .catch(() => document.documentElement.setAttribute('data-effect', 'none'));
+      invoke<CaptureBanner>('get_capture_banner', { online: navigator.onLine })
+        .then(setBanner)
+        .catch(() => setBanner({ kind: 'none' }));
+    };
+    
+    // Add online/offline event listeners; the backend keeps the banner in step with them
+    const refreshBanner = (online: boolean) =>
+      invoke<CaptureBanner>('get_capture_banner', { online })
+        .then(setBanner)
+        .catch(() => setBanner({ kind: 'none' }));
+    const handleOnline = () => {
+      setIsOffline(false);
+      refreshBanner(true);
+    };
+    const handleOffline = () => {
+      setIsOffline(true);
+      refreshBanner(false);
+    };
     
     window.addEventListener('keydown', handleKeyDown);
+    window.addEventListener('focus', handleFocus);
     window.addEventListener('online', handleOnline);
     window.addEventListener('offline', handleOffline);
     
     return () => {
       window.removeEventListener('keydown', handleKeyDown);
+      window.removeEventListener('focus', handleFocus);
       window.removeEventListener('online', handleOnline);
       window.removeEventListener('offline', handleOffline);
     };
@@ -131,7 +423,9 @@ const NoteInput: React.FC = () => {
     <textarea
       ref={textareaRef}
       className="note-textarea"
-      placeholder="Type your note here..."
+      placeholder={placeholderFor(banner)}
+      spellCheck={target?.spellcheck.enabled ?? true}
+      lang={target?.spellcheck.languages[0]}
       value={note}
       onChange={(e) => setNote(e.target.value)}
     />
@@ -141,11 +435,20 @@ const NoteInput: React.FC = () => {
   return (
     <div className="note-input-container">
       <div className="titlebar">
-        <div className="titlebar-text">Quick Note</div>
+        <div className="titlebar-text">
+          {destinationLabel(destination)}
+        </div>
         <div className="titlebar-controls">
           <button className="titlebar-button" onClick={openSettings} title="Settings">
             ⚙️
           </button>
+          <button
+            className={`titlebar-button ${scratchMode ? '' : 'inactive'}`}
+            onClick={() => setScratchMode(prev => !prev)}
+            title={scratchMode ? 'Scratch mode on: note will be batched' : 'Scratch mode off'}
+          >
+            🗒️
+          </button>
           <button className="titlebar-button" onClick={toggleDarkMode} title="Toggle Dark Mode">
             {darkMode ? '☀️' : '🌙'}
           </button>
@@ -155,6 +458,12 @@ const NoteInput: React.FC = () => {
         </div>
       </div>
       
+      {destination?.rule === 'sandbox' && (
+        <div className="sandbox-banner">
+          Sandbox mode - notes go to "{destination.page_title}", not your usual pages.
+        </div>
+      )}
+      
       {isOffline && (
         <div className="offline-banner">
           You're currently offline. Notes will be saved when you're back online.
@@ -163,6 +472,12 @@ const NoteInput: React.FC = () => {
       
       <NoteTextArea />
       
+      {dropNotice && (
+        <div className="offline-banner">
+          {dropNotice}
+        </div>
+      )}
+      
       {error && (
         <div className="error-message">
           {error}
@@ -177,7 +492,13 @@ const NoteInput: React.FC = () => {
       {sendStatus && (
         <div className={`status-bar ${sendStatus}`}>
           <span className="status-text">
-            {sendStatus === 'sending' ? 'Sending...' : 'Sent.'}
+            {sendStatus === 'sending'
+              ? sendingLabel(sendRetry)
+              : sendStatus === 'queued'
+                ? queuedLabel(queuedEstimate)
+                : sendStatus === 'removed'
+                  ? 'Note removed.'
+                  : 'Sent. Ctrl+Z to undo.'}
           </span>
         </div>
       )}
@@ -1,5 +1,6 @@
 import React, { useState, useEffect, useCallback, memo } from 'react';
 import { invoke } from '@tauri-apps/api/tauri';
+import { listen } from '@tauri-apps/api/event';
 import useApiWorker from '../worker/useApiWorker';
 import './Settings.css';
 
@@ -8,8 +9,145 @@ interface NotionPage {
   title: string;
   icon?: string;
   url: string;
+  parent_title?: string | null;
+  last_edited_time?: string | null;
+  archived?: boolean;
 }
 
+// "edited 2 days ago"
+const formatEdited = (iso: string): string => {
+  const minutes = Math.max(0, Math.round((Date.now() - new Date(iso).getTime()) / 60000));
+  if (minutes < 60) {
+    return minutes <= 1 ? 'edited just now' : `edited ${minutes} minutes ago`;
+  }
+  const hours = Math.round(minutes / 60);
+  if (hours < 24) {
+    return hours === 1 ? 'edited an hour ago' : `edited ${hours} hours ago`;
+  }
+  const days = Math.round(hours / 24);
+  return days === 1 ? 'edited yesterday' : `edited ${days} days ago`;
+};
+
+// "Notes — Projects · edited 2 days ago", so same-named pages can be told apart
+const pageLabel = (page: NotionPage) =>
+  `${page.icon ? `${page.icon} ` : ''}${page.title}${page.parent_title ? ` — ${page.parent_title}` : ''}` +
+  `${page.last_edited_time ? ` · ${formatEdited(page.last_edited_time)}` : ''}${page.archived ? ' (archived)' : ''}`;
+
+// Pages found by a search; `truncated` means more matched than were listed
+interface PageSearch {
+  pages: NotionPage[];
+  truncated: boolean;
+}
+
+type PageAccess = 'writable' | 'read_only' | 'not_found' | 'unknown';
+
+// The selected page as Notion reports it now
+interface PageInfo {
+  id: string;
+  title: string;
+  icon: string | null;
+  url: string;
+  found: boolean;
+}
+
+type AccessIssue = 'not_shared' | 'forbidden' | 'deleted' | 'unknown';
+
+interface PageAccessReport {
+  accessible: boolean;
+  can_append: boolean;
+  title: string | null;
+  reason: AccessIssue | null;
+}
+
+const ACCESS_ISSUE_MESSAGES: Record<AccessIssue, string> = {
+  not_shared: 'Notion can\'t find this page. Share it with your integration from the page\'s ••• menu (Connections).',
+  forbidden: 'Your integration doesn\'t have permission to add content. Enable "Insert content" in its capabilities.',
+  deleted: 'This page is in the Notion trash. Restore it or pick another page.',
+  unknown: 'Couldn\'t confirm that notes can be added to this page.'
+};
+
+type NoteBlockType = 'paragraph' | 'callout' | 'toggle';
+
+interface NoteEmphasis {
+  bold: boolean;
+  color: string;
+  highlight_hashtags: boolean;
+  hashtag_color: string;
+}
+
+interface TargetModeSettings {
+  mode: 'fixed' | 'daily';
+  daily_parent_page_id: string;
+  daily_title_format: string;
+}
+
+const NOTE_COLORS = ['default', 'gray', 'brown', 'orange', 'yellow', 'green', 'blue', 'purple', 'pink', 'red'];
+const TAG_COLORS = [...NOTE_COLORS.slice(1), ...NOTE_COLORS.slice(1).map(color => `${color}_background`)];
+
+interface PageStats {
+  local: { last_note_at: string | null; notes_today: number };
+  remote: { newest_block_at: string | null } | null;
+  remote_skipped: string | null;
+}
+
+interface ValidationReport {
+  checks: { name: string; status: 'ok' | 'warning' | 'failed' | 'skipped'; message: string }[];
+  checked_at: string;
+}
+
+interface RateLimitInfo {
+  state: 'unknown' | 'ok' | 'limited';
+  limited: boolean;
+  retry_in_secs: number | null;
+  source: 'headers' | 'backoff' | null;
+  limit: number | null;
+  remaining: number | null;
+  allowed_rps: number;
+  current_rps: number;
+  recent_requests_last_minute: number;
+}
+
+interface ResetReport {
+  removed: { scope: string; item: string; detail: string }[];
+  failed: { scope: string; item: string; detail: string }[];
+  pending_notes: number;
+  refused: boolean;
+}
+
+interface PendingSetupCapture {
+  id: string;
+  note_text: string;
+  profile: string | null;
+  captured_at: string;
+  origin: 'hotkey' | 'clipboard' | 'uri' | 'cli';
+}
+
+// A top-level block of the selected page that notes can be inserted after
+interface AnchorCandidate {
+  id: string;
+  block_type: string;
+  preview: string;
+}
+
+// "just now", "15m ago", "2h ago", "3d ago"
+const formatAgo = (iso: string): string => {
+  const minutes = Math.floor((Date.now() - new Date(iso).getTime()) / 60000);
+  if (minutes < 1) return 'just now';
+  if (minutes < 60) return `${minutes}m ago`;
+  if (minutes < 24 * 60) return `${Math.floor(minutes / 60)}h ago`;
+  return `${Math.floor(minutes / (24 * 60))}d ago`;
+};
+
+// "14:32 today" or "12 Oct, 09:10"
+const formatWhen = (iso: string): string => {
+  const date = new Date(iso);
+  const time = date.toLocaleTimeString([], { hour: '2-digit', minute: '2-digit' });
+  if (date.toDateString() === new Date().toDateString()) {
+    return `${time} today`;
+  }
+  return `${date.toLocaleDateString([], { day: 'numeric', month: 'short' })}, ${time}`;
+};
+
 // Create a cache for pages to prevent unnecessary API calls
 let pagesCache: NotionPage[] = [];
 
@@ -19,17 +157,64 @@ const Settings: React.FC = () => {
   const [isTokenValid, setIsTokenValid] = useState(false);
   const [isFetchingPages, setIsFetchingPages] = useState(false);
   const [notionPages, setNotionPages] = useState<NotionPage[]>([]);
+  // Narrows the page search on Notion's side; empty lists every page
+  const [pageQuery, setPageQuery] = useState('');
+  const [searchTruncated, setSearchTruncated] = useState(false);
+  const [includeArchived, setIncludeArchived] = useState(false);
+  const [newPageTitle, setNewPageTitle] = useState('');
+  const [newPageParent, setNewPageParent] = useState('');
+  // Databases get a new row per note instead of appended blocks
+  const [notionDatabases, setNotionDatabases] = useState<NotionPage[]>([]);
   const [selectedPageId, setSelectedPageId] = useState('');
   const [selectedPageTitle, setSelectedPageTitle] = useState('');
   const [errorMessage, setErrorMessage] = useState('');
   const [successMessage, setSuccessMessage] = useState('');
   const [isFirstTime, setIsFirstTime] = useState(false);
+  const [pageAccess, setPageAccess] = useState<PageAccess | null>(null);
+  const [allowReadOnly, setAllowReadOnly] = useState(false);
+  const [pageStats, setPageStats] = useState<PageStats | null>(null);
+  const [verification, setVerification] = useState<ValidationReport | null>(null);
+  const [isVerifying, setIsVerifying] = useState(false);
+  const [rateLimit, setRateLimit] = useState<RateLimitInfo | null>(null);
+  const [usageHistory, setUsageHistory] = useState<number[]>([]);
   const [darkMode, setDarkMode] = useState(() => {
     // Check if user previously had dark mode enabled
     const savedTheme = localStorage.getItem('theme');
     return savedTheme === 'dark';
   });
   const [isLoaded, setIsLoaded] = useState(false);
+  // Set when the backend opened settings because Notion revoked the token
+  const [tokenRevoked] = useState(() =>
+    new URLSearchParams(window.location.search).get('reason') === 'token_invalid'
+  );
+  
+  const [encryptLocalData, setEncryptLocalData] = useState(false);
+  const [noteHistory, setNoteHistory] = useState(true);
+  const [anchorBlockId, setAnchorBlockId] = useState<string | null>(null);
+  const [anchorBlocks, setAnchorBlocks] = useState<AnchorCandidate[] | null>(null);
+  const [noteBlockType, setNoteBlockType] = useState<NoteBlockType>('paragraph');
+  const [noteEmphasis, setNoteEmphasis] = useState<NoteEmphasis>({
+    bold: false,
+    color: 'default',
+    highlight_hashtags: true,
+    hashtag_color: 'yellow_background',
+  });
+  const [targetMode, setTargetMode] = useState<TargetModeSettings>({
+    mode: 'fixed',
+    daily_parent_page_id: '',
+    daily_title_format: '%Y-%m-%d',
+  });
+  const [localDataLocked, setLocalDataLocked] = useState(false);
+  const [dryRun, setDryRun] = useState<{ source: string } | null>(null);
+  const [reliability, setReliability] = useState<{
+    days: number;
+    success_rate: number | null;
+    median_latency_ms: number | null;
+  } | null>(null);
+  const [quietHours, setQuietHours] = useState<{ active: boolean; until_local: string | null } | null>(null);
+  const [safeMode, setSafeMode] = useState<{ reason: string; skipped: string[] } | null>(null);
+  // Notes captured before setup was finished, sent once it validates
+  const [pendingCaptures, setPendingCaptures] = useState<PendingSetupCapture[]>([]);
   
   // Get API worker
   const { sendMessage } = useApiWorker();
@@ -66,6 +251,18 @@ const Settings: React.FC = () => {
       }
       
       if (token && pageId) {
+        // Picks up renames made in Notion; a missing page asks for a new target
+        try {
+          const info = await invoke<PageInfo>('get_page_info', { pageId });
+          if (info.found) {
+            setSelectedPageTitle(info.title);
+          } else {
+            setErrorMessage('The selected page was deleted or is no longer shared with your integration. Please pick a new page.');
+          }
+        } catch (err) {
+          console.error('Failed to refresh the selected page:', err);
+        }
+        
         // Only fetch pages if cache is empty
         if (pagesCache.length === 0) {
           await fetchNotionPages();
@@ -130,16 +327,29 @@ const Settings: React.FC = () => {
     
     try {
       // Use the worker for API call
-      const response = await sendMessage('searchPages') as { data: NotionPage[] };
-      const pages = response.data;
+      const query = pageQuery.trim();
+      const response = await sendMessage('searchPages', { query: query || null, includeArchived }) as { data: PageSearch };
+      const pages = response.data.pages;
+      setSearchTruncated(response.data.truncated);
       
-      // Update cache and state
-      pagesCache = pages;
+      // Update cache and state; only the full list is cached for the next load
+      if (!query && !includeArchived) {
+        pagesCache = pages;
+      }
       setNotionPages(pages);
       
+      // A failed database search still leaves the pages usable
+      let databases: NotionPage[] = [];
+      try {
+        databases = (await sendMessage('searchDatabases') as { data: NotionPage[] }).data;
+        setNotionDatabases(databases);
+      } catch (error) {
+        console.error('Failed to fetch databases:', error);
+      }
+      
       // If there's a selected page, find its title
       if (selectedPageId) {
-        const selectedPage = pages.find(p => p.id === selectedPageId);
+        const selectedPage = [...pages, ...databases].find(p => p.id === selectedPageId);
         if (selectedPage) {
           setSelectedPageTitle(selectedPage.title);
         }
@@ -149,7 +359,308 @@ const Settings: React.FC = () => {
     } finally {
       setIsFetchingPages(false);
     }
-  }, [selectedPageId, clearMessages, sendMessage]);
+  }, [selectedPageId, pageQuery, includeArchived, clearMessages, sendMessage]);
+  
+  // Probe the picked page only (not every search result) to keep requests low
+  const handlePageSelection = useCallback(async (pageId: string) => {
+    setSelectedPageId(pageId);
+    setPageAccess(null);
+    setAllowReadOnly(false);
+    
+    // Databases are checked when saved
+    if (!pageId || notionDatabases.some(d => d.id === pageId)) {
+      return;
+    }
+    
+    try {
+      const access = await invoke<PageAccess>('probe_page_access', { pageId });
+      setPageAccess(access);
+    } catch (error) {
+      console.error('Failed to probe page access:', error);
+      setPageAccess('unknown');
+    }
+  }, [notionDatabases]);
+  
+  // Local delivery stats for the selected page; `verify` also asks Notion for its newest block
+  const loadPageStats = useCallback(async (verify: boolean) => {
+    if (!selectedPageId) {
+      setPageStats(null);
+      return;
+    }
+    
+    try {
+      const stats = await invoke<PageStats>('get_target_page_stats', {
+        pageId: selectedPageId,
+        verify,
+        online: navigator.onLine
+      });
+      setPageStats(stats);
+    } catch (error) {
+      console.error('Failed to load page stats:', error);
+    }
+  }, [selectedPageId]);
+  
+  useEffect(() => {
+    loadPageStats(false);
+  }, [loadPageStats]);
+  
+  useEffect(() => {
+    invoke<ValidationReport | null>('get_last_verification')
+      .then(setVerification)
+      .catch(error => console.error('Failed to load last verification:', error));
+  }, []);
+  
+  // Poll API usage while the settings window is open
+  useEffect(() => {
+    const load = () => {
+      invoke<RateLimitInfo>('get_rate_limit_info')
+        .then(setRateLimit)
+        .catch(() => setRateLimit(null));
+      invoke<number[]>('get_rate_limit_history', { minutes: 15 })
+        .then(setUsageHistory)
+        .catch(() => setUsageHistory([]));
+    };
+    load();
+    const timer = setInterval(load, 2000);
+    return () => clearInterval(timer);
+  }, []);
+  
+  useEffect(() => {
+    invoke<{
+      local_data_encrypted: boolean;
+      local_data_locked: boolean;
+      dry_run: { source: string } | null;
+      safe_mode: { reason: string; skipped: string[] } | null;
+    }>('get_app_info')
+      .then(info => {
+        setEncryptLocalData(info.local_data_encrypted);
+        setLocalDataLocked(info.local_data_locked);
+        setDryRun(info.dry_run);
+        setSafeMode(info.safe_mode);
+      })
+      .catch(() => {});
+    invoke<typeof reliability>('get_reliability_summary', { days: 30 })
+      .then(setReliability)
+      .catch(() => {});
+    invoke<typeof quietHours>('get_quiet_hours_state')
+      .then(setQuietHours)
+      .catch(() => {});
+    invoke<NoteBlockType>('get_note_block_type')
+      .then(setNoteBlockType)
+      .catch(() => {});
+    invoke<NoteEmphasis>('get_note_emphasis')
+      .then(setNoteEmphasis)
+      .catch(() => {});
+    invoke<TargetModeSettings>('get_target_mode')
+      .then(setTargetMode)
+      .catch(() => {});
+  }, []);
+  
+  const changeTargetMode = useCallback(async (settings: TargetModeSettings) => {
+    setTargetMode(settings);
+    // Daily mode can't be saved until a parent page is chosen
+    if (settings.mode === 'daily' && !settings.daily_parent_page_id) {
+      return;
+    }
+    try {
+      await invoke('set_target_mode', {
+        mode: settings.mode,
+        dailyParentPageId: settings.daily_parent_page_id,
+        dailyTitleFormat: settings.daily_title_format,
+      });
+    } catch (error) {
+      setErrorMessage(String(error));
+    }
+  }, []);
+  
+  const changeNoteEmphasis = useCallback(async (emphasis: NoteEmphasis) => {
+    try {
+      await invoke('set_note_emphasis', {
+        bold: emphasis.bold,
+        color: emphasis.color,
+        highlightHashtags: emphasis.highlight_hashtags,
+        hashtagColor: emphasis.hashtag_color,
+      });
+      setNoteEmphasis(emphasis);
+    } catch (error) {
+      setErrorMessage(String(error));
+    }
+  }, []);
+  
+  const changeNoteBlockType = useCallback(async (blockType: NoteBlockType) => {
+    try {
+      await invoke('set_note_block_type', { blockType });
+      setNoteBlockType(blockType);
+    } catch (error) {
+      setErrorMessage(String(error));
+    }
+  }, []);
+  
+  useEffect(() => {
+    invoke<{ path: string; value: unknown }[]>('list_settings_schema')
+      .then(entries => {
+        const entry = entries.find(setting => setting.path === 'note_history');
+        if (entry) {
+          setNoteHistory(entry.value === true);
+        }
+        const anchor = entries.find(setting => setting.path === 'anchor_block_id');
+        if (anchor) {
+          setAnchorBlockId(typeof anchor.value === 'string' ? anchor.value : null);
+        }
+      })
+      .catch(() => {});
+  }, []);
+  
+  const toggleNoteHistory = useCallback(async (enabled: boolean) => {
+    try {
+      await invoke('set_setting_by_path', { path: 'note_history', valueJson: JSON.stringify(enabled) });
+      setNoteHistory(enabled);
+    } catch (error) {
+      setErrorMessage(String(error));
+    }
+  }, []);
+  
+  const loadAnchorBlocks = useCallback(async () => {
+    try {
+      setAnchorBlocks(await invoke<AnchorCandidate[]>('list_anchor_blocks'));
+    } catch (error) {
+      setErrorMessage(String(error));
+    }
+  }, []);
+  
+  const changeAnchorBlock = useCallback(async (blockId: string | null) => {
+    try {
+      await invoke('set_setting_by_path', { path: 'anchor_block_id', valueJson: JSON.stringify(blockId) });
+      setAnchorBlockId(blockId);
+    } catch (error) {
+      setErrorMessage(String(error));
+    }
+  }, []);
+  
+  // A note went to the end of the page because its anchor block is gone
+  useEffect(() => {
+    const unlisten = listen<string>('anchor-missing', () => {
+      setErrorMessage('The block notes are inserted after no longer exists; notes go to the end of the page until you choose another.');
+    });
+    
+    return () => {
+      unlisten.then(fn => fn());
+    };
+  }, []);
+  
+  const clearNoteHistory = useCallback(async () => {
+    if (!window.confirm('Forget every sent note kept on this computer? Notes in Notion are not affected.')) {
+      return;
+    }
+    try {
+      const count = await invoke<number>('clear_note_history');
+      setSuccessMessage(`Removed ${count} notes from the local history`);
+    } catch (error) {
+      setErrorMessage(String(error));
+    }
+  }, []);
+  
+  const toggleEncryption = useCallback(async (enabled: boolean) => {
+    try {
+      await invoke('set_encrypt_local_data', { enabled });
+      setEncryptLocalData(enabled);
+      setSuccessMessage(enabled ? 'Local notes are now encrypted' : 'Local notes are no longer encrypted');
+    } catch (error) {
+      setErrorMessage(String(error));
+    }
+  }, []);
+  
+  const loadPendingCaptures = useCallback(() => {
+    invoke<PendingSetupCapture[]>('get_pending_setup_captures')
+      .then(setPendingCaptures)
+      .catch(() => setPendingCaptures([]));
+  }, []);
+  
+  useEffect(() => {
+    loadPendingCaptures();
+  }, [loadPendingCaptures]);
+  
+  const discardPendingCapture = useCallback(async (id: string) => {
+    try {
+      await invoke('discard_pending_setup_capture', { id });
+    } catch (error) {
+      setErrorMessage(`Couldn't discard the note: ${error}`);
+    }
+    loadPendingCaptures();
+  }, [loadPendingCaptures]);
+  
+  const runVerification = useCallback(async () => {
+    setIsVerifying(true);
+    try {
+      setVerification(await invoke<ValidationReport>('validate_settings'));
+    } catch (error) {
+      console.error('Failed to verify settings:', error);
+    } finally {
+      setIsVerifying(false);
+      // A passing check sends the notes that were waiting
+      loadPendingCaptures();
+    }
+  }, [loadPendingCaptures]);
+  
+  // Make a capture page under an existing one and select it
+  const createCapturePage = useCallback(async () => {
+    clearMessages();
+    try {
+      const page = await invoke<NotionPage>('create_notion_page', {
+        title: newPageTitle,
+        parentPageId: newPageParent,
+        select: true
+      });
+      setNotionPages(current => [page, ...current]);
+      setSelectedPageId(page.id);
+      setSelectedPageTitle(page.title);
+      setNewPageTitle('');
+      setSuccessMessage(`Created "${page.title}" and selected it for your notes`);
+    } catch (error) {
+      setErrorMessage(`Couldn't create the page: ${error}`);
+    }
+  }, [newPageTitle, newPageParent, clearMessages]);
+  
+  const createSandbox = useCallback(async () => {
+    try {
+      const page = await invoke<{ id: string; title: string }>('create_sandbox_page');
+      setSuccessMessage(`Created "${page.title}" - turn on sandbox mode from the tray to use it`);
+    } catch (error) {
+      setErrorMessage(`Couldn't create the sandbox page: ${error}`);
+    }
+  }, []);
+  
+  // Full reset behind explicit confirmations; undelivered notes need a second one
+  const resetAppData = useCallback(async () => {
+    if (!window.confirm('Reset Notion Quick Notes? This removes your token, settings, queue, history and logs.')) {
+      return;
+    }
+    try {
+      const run = async (force: boolean) => {
+        const confirmation = await invoke<string>('request_reset_confirmation');
+        return invoke<ResetReport>('reset_app_data', { scopes: ['all'], confirmation, force });
+      };
+      let report = await run(false);
+      if (report.refused) {
+        if (!window.confirm(`${report.pending_notes} notes haven't been delivered yet and will be lost. Reset anyway?`)) {
+          return;
+        }
+        report = await run(true);
+      }
+      if (report.failed.length > 0) {
+        setErrorMessage(`Some data couldn't be removed: ${report.failed.map(item => `${item.item} (${item.detail})`).join(', ')}`);
+      } else {
+        setSuccessMessage('App data was reset');
+      }
+      setApiToken('');
+      setSavedToken('');
+      setIsTokenValid(false);
+      setSelectedPageId('');
+      setSelectedPageTitle('');
+    } catch (error) {
+      setErrorMessage(`Reset failed: ${error}`);
+    }
+  }, []);
   
   const saveSelectedPage = useCallback(async () => {
     if (!selectedPageId) {
@@ -160,6 +671,17 @@ const Settings: React.FC = () => {
     clearMessages();
     
     try {
+      const selectedDatabase = notionDatabases.find(d => d.id === selectedPageId);
+      if (selectedDatabase) {
+        await sendMessage('saveSelectedDatabase', {
+          databaseId: selectedPageId,
+          databaseTitle: selectedDatabase.title
+        });
+        setSelectedPageTitle(selectedDatabase.title);
+        setSuccessMessage('Selected database saved; each note will be added as a new row.');
+        return;
+      }
+      
       // Get the title of the selected page
       const selectedPage = notionPages.find(p => p.id === selectedPageId);
       if (!selectedPage) {
@@ -167,18 +689,25 @@ const Settings: React.FC = () => {
         return;
       }
       
-      // Use the worker for API call
-      await sendMessage('saveSelectedPage', {
+      // Use the worker for API call; the page is saved even when the check warns
+      const response = await sendMessage('saveSelectedPage', {
         pageId: selectedPageId,
-        pageTitle: selectedPage.title
-      });
+        pageTitle: selectedPage.title,
+        allowReadOnly,
+        verify: true
+      }) as { report: PageAccessReport | null };
       
       setSelectedPageTitle(selectedPage.title);
-      setSuccessMessage('Selected page saved successfully!');
+      const report = response.report;
+      if (report && !report.can_append) {
+        setErrorMessage(`Saved, but notes may fail: ${ACCESS_ISSUE_MESSAGES[report.reason ?? 'unknown']}`);
+      } else {
+        setSuccessMessage('Selected page saved successfully!');
+      }
     } catch (error) {
       setErrorMessage(`Failed to save selected page: ${error instanceof Error ? error.message : String(error)}`);
     }
-  }, [selectedPageId, notionPages, clearMessages, sendMessage]);
+  }, [selectedPageId, notionPages, notionDatabases, allowReadOnly, clearMessages, sendMessage]);
   
   const handleBackNavigation = useCallback(async () => {
     try {
@@ -233,6 +762,45 @@ const Settings: React.FC = () => {
         </div>
       )}
       
+      {dryRun && (
+        <div className="warning-message">
+          DRY RUN - nothing is sent to Notion. Requests are answered locally
+          ({dryRun.source === 'env' ? 'NQN_DRY_RUN is set' : 'dry_run is on in the config'}).
+        </div>
+      )}
+      
+      {safeMode && (
+        <div className="warning-message">
+          SAFE MODE - {safeMode.reason === 'flag' ? 'started with --safe-mode' : 'recent launches crashed during startup'}.
+          Notes go straight to the selected page; skipped: {safeMode.skipped.join(', ').replace(/_/g, ' ')}.
+          Use "Exit safe mode" in the tray menu to restart normally.
+        </div>
+      )}
+      
+      {tokenRevoked && (
+        <div className="warning-message">
+          Notion no longer accepts your API token. The integration may have been removed from your workspace.
+          Paste a new token below; notes captured in the meantime will be sent once it verifies.
+        </div>
+      )}
+      
+      {pendingCaptures.length > 0 && (
+        <div className="warning-message">
+          {pendingCaptures.length === 1 ? '1 note waiting' : `${pendingCaptures.length} notes waiting`} - finish setup to send {pendingCaptures.length === 1 ? 'it' : 'them'}.
+          <ul className="pending-captures">
+            {pendingCaptures.map(capture => (
+              <li key={capture.id}>
+                <span className="pending-capture-text">{capture.note_text}</span>
+                <span className="pending-capture-time">{formatAgo(capture.captured_at)}</span>
+                <button className="pending-capture-discard" onClick={() => discardPendingCapture(capture.id)}>
+                  Discard
+                </button>
+              </li>
+            ))}
+          </ul>
+        </div>
+      )}
+      
       <div className="settings-section">
         <h2>Notion API Integration</h2>
         <p className="settings-description">
@@ -273,6 +841,31 @@ const Settings: React.FC = () => {
               Select the page where your quick notes will be appended.
             </p>
             
+            <div className="select-container">
+              <label htmlFor="pageQuery">Search:</label>
+              <input
+                id="pageQuery"
+                type="text"
+                placeholder="Page title (leave empty for all pages)"
+                value={pageQuery}
+                onChange={(e) => setPageQuery(e.target.value)}
+                onKeyDown={(e) => {
+                  if (e.key === 'Enter') {
+                    fetchNotionPages();
+                  }
+                }}
+              />
+            </div>
+            
+            <label>
+              <input
+                type="checkbox"
+                checked={includeArchived}
+                onChange={(e) => setIncludeArchived(e.target.checked)}
+              />
+              Include archived pages
+            </label>
+            
             <button 
               className="fetch-button"
               onClick={fetchNotionPages}
@@ -281,6 +874,12 @@ const Settings: React.FC = () => {
               {isFetchingPages ? 'Fetching...' : 'Fetch Pages'}
             </button>
             
+            {searchTruncated && (
+              <div className="warning-message">
+                Not every page is listed. Type part of the page's title above to search for it.
+              </div>
+            )}
+            
             {notionPages.length > 0 && (
               <>
                 <div className="select-container">
@@ -289,30 +888,345 @@ const Settings: React.FC = () => {
                     id="notionPage"
                     className="page-select"
                     value={selectedPageId}
-                    onChange={(e) => setSelectedPageId(e.target.value)}
+                    onChange={(e) => handlePageSelection(e.target.value)}
                   >
                     <option value="">-- Select a page --</option>
                     {notionPages.map(page => (
                       <option key={page.id} value={page.id}>
-                        {page.icon ? `${page.icon} ` : ''}{page.title}
+                        {pageLabel(page)}
                       </option>
                     ))}
+                    {notionDatabases.length > 0 && (
+                      <optgroup label="Databases (a new row per note)">
+                        {notionDatabases.map(database => (
+                          <option key={database.id} value={database.id}>
+                            {database.icon ? `${database.icon} ` : ''}{database.title}
+                          </option>
+                        ))}
+                      </optgroup>
+                    )}
                   </select>
                 </div>
                 
+                <div className="select-container">
+                  <label htmlFor="targetMode">Send notes to:</label>
+                  <select
+                    id="targetMode"
+                    className="page-select"
+                    value={targetMode.mode}
+                    onChange={(e) => changeTargetMode({ ...targetMode, mode: e.target.value as TargetModeSettings['mode'] })}
+                  >
+                    <option value="fixed">The selected page</option>
+                    <option value="daily">A new page each day</option>
+                  </select>
+                </div>
+                
+                {targetMode.mode === 'daily' && (
+                  <>
+                    <div className="select-container">
+                      <label htmlFor="dailyParent">Create daily pages under:</label>
+                      <select
+                        id="dailyParent"
+                        className="page-select"
+                        value={targetMode.daily_parent_page_id}
+                        onChange={(e) => changeTargetMode({ ...targetMode, daily_parent_page_id: e.target.value })}
+                      >
+                        <option value="">-- Select a page --</option>
+                        {notionPages.map(page => (
+                          <option key={page.id} value={page.id}>
+                            {pageLabel(page)}
+                          </option>
+                        ))}
+                      </select>
+                    </div>
+                    <div className="select-container">
+                      <label htmlFor="dailyTitleFormat">Daily page title:</label>
+                      <input
+                        id="dailyTitleFormat"
+                        type="text"
+                        value={targetMode.daily_title_format}
+                        onChange={(e) => setTargetMode({ ...targetMode, daily_title_format: e.target.value })}
+                        onBlur={() => changeTargetMode(targetMode)}
+                      />
+                    </div>
+                  </>
+                )}
+                
+                {pageAccess === 'read_only' && (
+                  <div className="warning-message">
+                    <p>Your integration appears to only have read access to this page, so notes will likely fail to send.</p>
+                    <label>
+                      <input
+                        type="checkbox"
+                        checked={allowReadOnly}
+                        onChange={(e) => setAllowReadOnly(e.target.checked)}
+                      />
+                      Use this page anyway
+                    </label>
+                  </div>
+                )}
+                
+                {pageAccess === 'not_found' && (
+                  <div className="warning-message">
+                    This page could not be found. Make sure it is shared with your integration.
+                  </div>
+                )}
+                
                 <button 
                   className="save-button"
                   onClick={saveSelectedPage}
-                  disabled={!selectedPageId}
+                  disabled={!selectedPageId || (pageAccess === 'read_only' && !allowReadOnly)}
                 >
                   Save Selected Page
                 </button>
+                
+                <div className="select-container">
+                  <label htmlFor="newPageTitle">Or create a new page:</label>
+                  <input
+                    id="newPageTitle"
+                    type="text"
+                    placeholder="Quick Notes"
+                    value={newPageTitle}
+                    onChange={(e) => setNewPageTitle(e.target.value)}
+                  />
+                  <select
+                    className="page-select"
+                    value={newPageParent}
+                    onChange={(e) => setNewPageParent(e.target.value)}
+                  >
+                    <option value="">-- Inside which page? --</option>
+                    {notionPages.map(page => (
+                      <option key={page.id} value={page.id}>
+                        {pageLabel(page)}
+                      </option>
+                    ))}
+                  </select>
+                  <button
+                    className="save-button"
+                    onClick={createCapturePage}
+                    disabled={!newPageTitle.trim() || !newPageParent}
+                  >
+                    Create and Use
+                  </button>
+                </div>
               </>
             )}
             
             {selectedPageTitle && (
               <div className="selected-page">
                 <p>Current Selected Page: <strong>{selectedPageTitle}</strong></p>
+                {pageStats && (
+                  <div className="page-stats">
+                    <p>
+                      {pageStats.local.last_note_at
+                        ? `Last note sent to this page: ${formatWhen(pageStats.local.last_note_at)} — ${pageStats.local.notes_today} notes today`
+                        : 'No notes sent to this page yet'}
+                    </p>
+                    {pageStats.remote && (
+                      <p>
+                        {pageStats.remote.newest_block_at
+                          ? `Newest block in Notion: ${formatWhen(pageStats.remote.newest_block_at)}`
+                          : 'Notion reports no blocks on this page'}
+                      </p>
+                    )}
+                    {pageStats.remote_skipped && (
+                      <p>Couldn't verify with Notion: {pageStats.remote_skipped}</p>
+                    )}
+                    <button className="verify-button" onClick={() => loadPageStats(true)}>
+                      Verify
+                    </button>
+                  </div>
+                )}
+              </div>
+            )}
+          </div>
+        )}
+        
+        {quietHours?.active && (
+          <p className="quiet-hours-status">
+            Quiet hours until {quietHours.until_local}: background sync and notifications are paused
+          </p>
+        )}
+        
+        {reliability?.success_rate != null && (
+          <p className="reliability-summary">
+            Reliability: {(reliability.success_rate * 100).toFixed(1)}% over the last {reliability.days} days
+            {reliability.median_latency_ms != null &&
+              `, median delivery ${(reliability.median_latency_ms / 1000).toFixed(1)}s`}
+          </p>
+        )}
+        
+        <div className="verification-status">
+          {verification ? (
+            <>
+              <p>
+                {verification.checks.every(check => check.status === 'ok' || check.status === 'skipped')
+                  ? 'All good'
+                  : 'Some settings need attention'} — checked {formatAgo(verification.checked_at)}
+              </p>
+              <ul>
+                {verification.checks.map(check => (
+                  <li key={check.name} className={`check-${check.status}`}>
+                    {check.name}: {check.message}
+                  </li>
+                ))}
+              </ul>
+            </>
+          ) : (
+            <p>Settings haven't been checked yet</p>
+          )}
+          <button className="verify-button" onClick={runVerification} disabled={isVerifying}>
+            {isVerifying ? 'Checking...' : 'Check now'}
+          </button>
+        </div>
+        
+        <div className="select-container">
+          <label htmlFor="noteBlockType">Append notes as:</label>
+          <select
+            id="noteBlockType"
+            className="page-select"
+            value={noteBlockType}
+            onChange={(e) => changeNoteBlockType(e.target.value as NoteBlockType)}
+          >
+            <option value="paragraph">Plain blocks</option>
+            <option value="callout">A callout (a leading emoji becomes its icon)</option>
+            <option value="toggle">A toggle titled with the timestamp</option>
+          </select>
+        </div>
+        
+        <div className="select-container">
+          <label htmlFor="noteColor">Note color:</label>
+          <select
+            id="noteColor"
+            className="page-select"
+            value={noteEmphasis.color}
+            onChange={(e) => changeNoteEmphasis({ ...noteEmphasis, color: e.target.value })}
+          >
+            {NOTE_COLORS.map(color => (
+              <option key={color} value={color}>{color}</option>
+            ))}
+          </select>
+        </div>
+        <label className="encryption-toggle">
+          <input
+            type="checkbox"
+            checked={noteEmphasis.bold}
+            onChange={(e) => changeNoteEmphasis({ ...noteEmphasis, bold: e.target.checked })}
+          />
+          Make note text bold
+        </label>
+        <label className="encryption-toggle">
+          <input
+            type="checkbox"
+            checked={noteEmphasis.highlight_hashtags}
+            onChange={(e) => changeNoteEmphasis({ ...noteEmphasis, highlight_hashtags: e.target.checked })}
+          />
+          Highlight #tags
+        </label>
+        {noteEmphasis.highlight_hashtags && (
+          <div className="select-container">
+            <label htmlFor="hashtagColor">Tag color:</label>
+            <select
+              id="hashtagColor"
+              className="page-select"
+              value={noteEmphasis.hashtag_color}
+              onChange={(e) => changeNoteEmphasis({ ...noteEmphasis, hashtag_color: e.target.value })}
+            >
+              {TAG_COLORS.map(color => (
+                <option key={color} value={color}>{color.replace('_', ' ')}</option>
+              ))}
+            </select>
+          </div>
+        )}
+        
+        <div className="select-container">
+          <label htmlFor="anchorBlock">Insert notes after:</label>
+          {anchorBlocks ? (
+            <select
+              id="anchorBlock"
+              className="page-select"
+              value={anchorBlockId ?? ''}
+              onChange={(e) => changeAnchorBlock(e.target.value || null)}
+            >
+              <option value="">The end of the page</option>
+              {anchorBlockId && !anchorBlocks.some(block => block.id === anchorBlockId) && (
+                <option value={anchorBlockId}>The current block (not among the first 100)</option>
+              )}
+              {anchorBlocks.map(block => (
+                <option key={block.id} value={block.id}>
+                  {block.preview || `(${block.block_type.replace(/_/g, ' ')})`}
+                </option>
+              ))}
+            </select>
+          ) : (
+            <button className="verify-button" onClick={loadAnchorBlocks} disabled={!selectedPageId}>
+              {anchorBlockId ? 'Change the anchor block' : 'Choose a block...'}
+            </button>
+          )}
+        </div>
+        
+        <label className="encryption-toggle">
+          <input
+            type="checkbox"
+            checked={encryptLocalData}
+            onChange={(e) => toggleEncryption(e.target.checked)}
+          />
+          Encrypt notes stored on this computer (queue, scratch notes)
+        </label>
+        {localDataLocked && (
+          <div className="warning-message">
+            Encrypted notes are locked until the system keyring is available. Nothing has been lost.
+          </div>
+        )}
+        
+        <label className="encryption-toggle">
+          <input
+            type="checkbox"
+            checked={noteHistory}
+            onChange={(e) => toggleNoteHistory(e.target.checked)}
+          />
+          Keep a history of sent notes on this computer
+        </label>
+        
+        <div className="reset-section">
+          <button className="verify-button" onClick={clearNoteHistory}>
+            Clear note history
+          </button>
+          <button className="verify-button" onClick={createSandbox} disabled={!selectedPageId}>
+            Create sandbox page
+          </button>
+          <button className="verify-button" onClick={resetAppData}>
+            Reset app data...
+          </button>
+        </div>
+        
+        {rateLimit && (
+          <div className="api-usage">
+            <h3>API usage</h3>
+            <div className="usage-bar" title={`${rateLimit.current_rps.toFixed(1)} of ${rateLimit.allowed_rps.toFixed(1)} requests/s`}>
+              <div
+                className={`usage-bar-fill ${rateLimit.limited ? 'limited' : ''}`}
+                style={{ width: `${Math.min(100, (rateLimit.current_rps / rateLimit.allowed_rps) * 100)}%` }}
+              />
+            </div>
+            <p>
+              {rateLimit.state === 'limited'
+                ? `Rate limited - retrying in ${rateLimit.retry_in_secs ?? 0}s${rateLimit.source === 'backoff' ? ' (estimated)' : ''}`
+                : rateLimit.state === 'unknown'
+                  ? 'No requests sent yet'
+                  : `${rateLimit.recent_requests_last_minute} requests in the last minute`}
+              {rateLimit.limit !== null && rateLimit.remaining !== null
+                && ` - ${rateLimit.remaining} of ${rateLimit.limit} remaining`}
+            </p>
+            {usageHistory.length > 0 && (
+              <div className="usage-history" title="Requests per minute, last 15 minutes">
+                {usageHistory.map((count, index) => (
+                  <div
+                    key={index}
+                    className="usage-history-bar"
+                    style={{ height: `${Math.min(100, (count / (rateLimit.allowed_rps * 60)) * 100)}%` }}
+                  />
+                ))}
               </div>
             )}
           </div>
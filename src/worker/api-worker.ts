@@ -15,12 +15,28 @@ self.onmessage = async (event: MessageEvent<WorkerMessage>) => {
     
     switch (type) {
       case 'appendNote':
-        result = await invoke('append_note', { noteText: payload.noteText });
-        self.postMessage({ type: 'appendNoteResult', success: true });
+        result = await invoke('append_note', {
+          noteText: payload.noteText,
+          profile: payload.profile ?? null,
+          scratch: payload.scratch ?? null
+        });
+        self.postMessage({ type: 'appendNoteResult', success: true, data: result });
+        break;
+        
+      case 'queueNote':
+        await invoke('queue_note', {
+          noteText: payload.noteText,
+          profile: payload.profile ?? null
+        });
+        self.postMessage({ type: 'queueNoteResult', success: true });
         break;
         
       case 'searchPages':
-        result = await invoke('search_notion_pages');
+        result = await invoke('search_notion_pages', {
+          query: payload?.query ?? null,
+          limit: payload?.limit ?? null,
+          includeArchived: payload?.includeArchived ?? null
+        });
         self.postMessage({ 
           type: 'searchPagesResult', 
           success: true, 
@@ -28,6 +44,15 @@ self.onmessage = async (event: MessageEvent<WorkerMessage>) => {
         });
         break;
         
+      case 'searchDatabases':
+        result = await invoke('search_notion_databases');
+        self.postMessage({ 
+          type: 'searchDatabasesResult', 
+          success: true, 
+          data: result 
+        });
+        break;
+        
       case 'verifyToken':
         result = await invoke('set_notion_api_token', { apiToken: payload.apiToken });
         self.postMessage({ 
@@ -38,12 +63,26 @@ self.onmessage = async (event: MessageEvent<WorkerMessage>) => {
         break;
         
       case 'saveSelectedPage':
-        await invoke('set_selected_page_id', { 
+        result = await invoke('set_selected_page_id', { 
           pageId: payload.pageId,
-          pageTitle: payload.pageTitle
+          pageTitle: payload.pageTitle,
+          allowReadOnly: payload.allowReadOnly ?? false,
+          verify: payload.verify ?? false
         });
         self.postMessage({ 
           type: 'saveSelectedPageResult', 
+          success: true,
+          report: result
+        });
+        break;
+        
+      case 'saveSelectedDatabase':
+        await invoke('set_selected_database', { 
+          databaseId: payload.databaseId,
+          databaseTitle: payload.databaseTitle
+        });
+        self.postMessage({ 
+          type: 'saveSelectedDatabaseResult', 
           success: true 
         });
         break;